@@ -1,11 +1,13 @@
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("cargo:rerun-if-changed=../format_descriptions/");
+use hexbait_lang::ir::ImportPaths;
 
-    let mut out = String::new();
+#[path = "src/load.rs"]
+mod load;
 
-    out.push_str("const BUILT_IN_DEFINITIONS_RAW: [(&str, &str); NUM_BUILTIN_DEFINITIONS] = [\n");
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=../format_descriptions/");
+    println!("cargo:rerun-if-changed=src/load.rs");
 
-    let mut count = 0;
+    let mut definitions = Vec::new();
     for entry in std::fs::read_dir("../format_descriptions")? {
         let entry = entry?;
         let content = std::fs::read_to_string(entry.path())?;
@@ -14,14 +16,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .into_string()
             .expect("built in format description with non utf8 name");
 
-        out.push_str(&format!("    ({name:?}, {content:?}),\n"));
-        count += 1;
+        definitions.push((&*name.leak(), &*content.leak()));
     }
 
+    // invalid format descriptions are reported without failing the build, so that the others
+    // remain usable
+    let import_paths = ImportPaths {
+        search_dirs: Vec::new(),
+        builtins: definitions.clone().leak(),
+    };
+    for &(name, content) in &definitions {
+        if let Err(err) = load::load_builtin(name, content, &import_paths) {
+            for line in err.to_string().lines() {
+                println!("cargo:warning={line}");
+            }
+        }
+    }
+
+    let mut out = String::new();
+
+    out.push_str("const BUILT_IN_DEFINITIONS_RAW: [(&str, &str); NUM_BUILTIN_DEFINITIONS] = [\n");
+    for (name, content) in &definitions {
+        out.push_str(&format!("    ({name:?}, {content:?}),\n"));
+    }
     out.push_str("];\n");
     out.push('\n');
     out.push_str(&format!(
-        "const NUM_BUILTIN_DEFINITIONS: usize = {count};\n"
+        "const NUM_BUILTIN_DEFINITIONS: usize = {};\n",
+        definitions.len()
     ));
 
     let out_dir = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
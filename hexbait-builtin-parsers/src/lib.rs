@@ -1,30 +1,43 @@
 //! Provides the built-in format descriptions.
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::PathBuf};
 
-use hexbait_lang::{
-    check_ir,
-    ir::{File, lower_file},
-    parse,
-};
+use hexbait_lang::ir::{File, ImportPaths};
+
+mod load;
+
+pub use load::BuiltinError;
 
 include!(concat!(env!("OUT_DIR"), "/built_in.gen.rs"));
 
 /// Returns the built-in format definitions.
-pub fn built_in_format_descriptions() -> BTreeMap<&'static str, File> {
+///
+/// Files that only declare named `struct`s for other files to import are not included. Invalid
+/// format descriptions are already reported while building, but are returned as errors here
+/// instead of keeping the valid ones from being used.
+pub fn built_in_format_descriptions() -> BTreeMap<&'static str, Result<File, BuiltinError>> {
+    let import_paths = import_paths(Vec::new());
+
     BUILT_IN_DEFINITIONS_RAW
         .iter()
-        .map(|&(name, content)| {
-            let name = name.strip_suffix(".hbl").unwrap_or(name);
+        .filter_map(|&(file_name, content)| {
+            let name = file_name.strip_suffix(".hbl").unwrap_or(file_name);
 
-            let parse = parse(content);
-            // TODO: handle errors better here
-            assert!(parse.errors.is_empty());
-            let ir = lower_file(parse.ast);
-            // TODO: use these
-            let _resolved_names = check_ir(&ir).unwrap();
-
-            (name, ir)
+            match load::load_builtin(file_name, content, &import_paths) {
+                Ok(ir) => (!ir.content.is_empty()).then_some((name, Ok(ir))),
+                Err(err) => Some((name, Err(err))),
+            }
         })
         .collect()
 }
+
+/// Returns the paths where imports are looked up.
+///
+/// Imports are looked up next to the importing file first, then in the given directories and
+/// finally among the built-in format descriptions.
+pub fn import_paths(search_dirs: Vec<PathBuf>) -> ImportPaths {
+    ImportPaths {
+        search_dirs,
+        builtins: &BUILT_IN_DEFINITIONS_RAW,
+    }
+}
@@ -0,0 +1,82 @@
+//! Implements loading a built-in format description.
+//!
+//! This module is also included by the build script, which reports invalid built-in format
+//! descriptions while building.
+
+use std::fmt;
+
+use hexbait_lang::{
+    Diagnostic, check_ir,
+    ir::{File, ImportError, ImportPaths, SourceLocation, lower_file, resolve_imports},
+    parse,
+};
+
+/// A problem that keeps a built-in format description from being used.
+#[derive(Debug)]
+pub enum BuiltinError {
+    /// The format description contains syntax errors or invalid constructs.
+    Invalid {
+        /// The file name of the format description.
+        file_name: &'static str,
+        /// The source of the format description.
+        source: &'static str,
+        /// The problems that were found in the source.
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The imports of the format description could not be resolved.
+    Import {
+        /// The file name of the format description.
+        file_name: &'static str,
+        /// The error that occurred while resolving the imports.
+        err: ImportError,
+    },
+}
+
+impl fmt::Display for BuiltinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuiltinError::Invalid {
+                file_name,
+                source,
+                diagnostics,
+            } => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", diagnostic.render(file_name, source))?;
+                }
+
+                Ok(())
+            }
+            BuiltinError::Import { file_name, err } => write!(f, "error: {err}\n --> {file_name}"),
+        }
+    }
+}
+
+impl std::error::Error for BuiltinError {}
+
+/// Parses, lowers and checks the built-in format description `file_name` with the given source.
+pub fn load_builtin(
+    file_name: &'static str,
+    source: &'static str,
+    import_paths: &ImportPaths,
+) -> Result<File, BuiltinError> {
+    let invalid = |diagnostics| BuiltinError::Invalid {
+        file_name,
+        source,
+        diagnostics,
+    };
+
+    let parse = parse(source);
+    if !parse.errors.is_empty() {
+        return Err(invalid(parse.errors.iter().map(Diagnostic::from).collect()));
+    }
+
+    let mut ir = lower_file(parse.ast);
+    resolve_imports(&mut ir, &SourceLocation::Builtin(file_name), import_paths)
+        .map_err(|err| BuiltinError::Import { file_name, err })?;
+    check_ir(&ir).map_err(|err| invalid(err.problems.iter().map(Diagnostic::from).collect()))?;
+
+    Ok(ir)
+}
@@ -2,14 +2,36 @@
 
 /// Determines the byte-order of multi-byte structures.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Endianness {
     /// The most significant byte is stored at the highest address.
     Little,
     /// The most significant byte is stored at the lowest address.
     Big,
+    /// The PDP-11 middle-endian byte order.
+    ///
+    /// The value is split into little endian 16-bit words, which are stored with the most
+    /// significant word at the lowest address.
+    Pdp,
+    /// Big endian 16-bit words, stored with the least significant word at the lowest address.
+    BigWordSwapped16,
+    /// Little endian 32-bit words, stored with the most significant word at the lowest address.
+    LittleWordSwapped32,
+    /// Big endian 32-bit words, stored with the least significant word at the lowest address.
+    BigWordSwapped32,
 }
 
 impl Endianness {
+    /// All supported endiannesses.
+    pub const ALL: [Endianness; 6] = [
+        Endianness::Little,
+        Endianness::Big,
+        Endianness::Pdp,
+        Endianness::BigWordSwapped16,
+        Endianness::LittleWordSwapped32,
+        Endianness::BigWordSwapped32,
+    ];
+
     /// The native endianness.
     pub fn native() -> Endianness {
         if cfg!(target_endian = "little") {
@@ -18,18 +40,75 @@ impl Endianness {
             Endianness::Big
         }
     }
+
+    /// Returns a human readable name of the endianness.
+    pub fn name(self) -> &'static str {
+        match self {
+            Endianness::Little => "Little Endian",
+            Endianness::Big => "Big Endian",
+            Endianness::Pdp => "PDP-11 Middle Endian",
+            Endianness::BigWordSwapped16 => "Big Endian (16-bit words swapped)",
+            Endianness::LittleWordSwapped32 => "Little Endian (32-bit words swapped)",
+            Endianness::BigWordSwapped32 => "Big Endian (32-bit words swapped)",
+        }
+    }
+
+    /// Returns the size of the words whose order is swapped, if any, and the endianness within
+    /// these words.
+    fn swapped_words(self) -> Option<(usize, Endianness)> {
+        match self {
+            Endianness::Little | Endianness::Big => None,
+            Endianness::Pdp => Some((2, Endianness::Little)),
+            Endianness::BigWordSwapped16 => Some((2, Endianness::Big)),
+            Endianness::LittleWordSwapped32 => Some((4, Endianness::Little)),
+            Endianness::BigWordSwapped32 => Some((4, Endianness::Big)),
+        }
+    }
+
+    /// Reorders the bytes of a value stored in this endianness, such that they are in little
+    /// endian order.
+    ///
+    /// Word-swapped endiannesses only swap words if the length of `bytes` is a multiple of the
+    /// word size and behave like the endianness within the words otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::Endianness;
+    /// let mut bytes = [0x0b, 0x0a, 0x0d, 0x0c];
+    /// Endianness::Pdp.reorder_to_little_endian(&mut bytes);
+    /// assert_eq!(u32::from_le_bytes(bytes), 0x0a0b0c0d);
+    /// ```
+    pub fn reorder_to_little_endian(self, bytes: &mut [u8]) {
+        match self.swapped_words() {
+            None if self == Endianness::Big => bytes.reverse(),
+            None => (),
+            Some((word_size, within_words)) => {
+                if !bytes.len().is_multiple_of(word_size) {
+                    within_words.reorder_to_little_endian(bytes);
+                    return;
+                }
+
+                if within_words == Endianness::Little {
+                    // reverses the order of the words, but also the bytes within them
+                    bytes.reverse();
+                }
+                for word in bytes.chunks_exact_mut(word_size) {
+                    word.reverse();
+                }
+            }
+        }
+    }
 }
 
 macro_rules! endianness_from_bytes {
     ($(($name:ident: $num:ident),)*) => {
         impl Endianness {
             $(
-                #[doc = concat!("Returns the function used to parse a `", stringify!($num), "` from bytes of this endianness.")]
-                pub fn $name(self) -> fn([u8; std::mem::size_of::<$num>()]) -> $num {
-                    match self {
-                        Endianness::Little => $num::from_le_bytes,
-                        Endianness::Big => $num::from_be_bytes,
-                    }
+                #[doc = concat!("Parses a `", stringify!($num), "` from bytes of this endianness.")]
+                pub fn $name(self, mut bytes: [u8; std::mem::size_of::<$num>()]) -> $num {
+                    self.reorder_to_little_endian(&mut bytes);
+                    $num::from_le_bytes(bytes)
                 }
             )*
         }
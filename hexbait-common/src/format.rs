@@ -0,0 +1,123 @@
+//! Implements helpers for displaying offsets and lengths.
+
+use std::fmt;
+
+use crate::Len;
+
+/// Displays a number in hexadecimal with a `0x` prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hex {
+    /// The value to display.
+    value: u64,
+    /// The minimum number of hex digits to display.
+    width: usize,
+}
+
+impl Hex {
+    /// Creates a new hex display of the given value.
+    pub(crate) fn new(value: u64) -> Hex {
+        Hex { value, width: 0 }
+    }
+
+    /// Pads the displayed value with zeros to at least `width` hex digits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::AbsoluteOffset;
+    /// assert_eq!(AbsoluteOffset::from(0x1f).hex().to_string(), "0x1f");
+    /// assert_eq!(AbsoluteOffset::from(0x1f).hex().padded(8).to_string(), "0x0000001f");
+    /// ```
+    pub fn padded(self, width: usize) -> Hex {
+        Hex { width, ..self }
+    }
+}
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:0width$x}", self.value, width = self.width)
+    }
+}
+
+/// Displays a size in a human readable form using binary prefixes.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::Len;
+/// assert_eq!(Len::from(12).human_size().to_string(), "12 B");
+/// assert_eq!(Len::from(1536).human_size().to_string(), "1.5 KiB");
+/// assert_eq!(Len::from(3355443).human_size().to_string(), "3.2 MiB");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanSize(pub(crate) Len);
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+        let bytes = self.0.as_u64();
+        if bytes < 1024 {
+            return write!(f, "{bytes} B");
+        }
+
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        // values that would be rounded up to 1024.0 are displayed with the next unit instead
+        while value >= 1023.95 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+
+        write!(f, "{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Displays a size in units of a block size, such as sectors or clusters.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::Len;
+/// let sector = Len::from(512);
+/// assert_eq!(Len::from(2048).in_blocks(sector, "sectors").to_string(), "4 sectors");
+/// assert_eq!(Len::from(2060).in_blocks(sector, "sectors").to_string(), "4 sectors + 12 B");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockUnits {
+    /// The size to display.
+    len: Len,
+    /// The size of a single block.
+    block_size: Len,
+    /// The name of the unit in plural form.
+    unit: &'static str,
+}
+
+impl BlockUnits {
+    /// Creates a new display of the given size in blocks.
+    pub(crate) fn new(len: Len, block_size: Len, unit: &'static str) -> BlockUnits {
+        BlockUnits {
+            len,
+            block_size,
+            unit,
+        }
+    }
+}
+
+impl fmt::Display for BlockUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.block_size == Len::ZERO {
+            return write!(f, "{}", HumanSize(self.len));
+        }
+
+        let blocks = self.len / self.block_size;
+        let rest = self.len % self.block_size.as_u64();
+
+        write!(f, "{blocks} {}", self.unit)?;
+        if rest != Len::ZERO {
+            write!(f, " + {}", HumanSize(rest))?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     ops::Deref,
     path::Path,
     sync::Arc,
+    time::SystemTime,
 };
 
 use memmap2::Mmap;
@@ -27,8 +28,8 @@ enum InputType {
     },
     /// The input is the given memory map.
     Memmap(Mmap),
-    /// The input was read from stdin.
-    Stdin(Box<[u8]>),
+    /// The input is held in memory, for example after reading it from stdin.
+    Memory(Box<[u8]>),
 }
 
 impl Input {
@@ -63,7 +64,7 @@ impl Input {
         if false && let Ok(mmap) = mmap_from_path(path) {
             Ok(Input(Arc::new(InputType::Memmap(mmap))))
         } else {
-            let mut file = File::open(path).unwrap();
+            let mut file = File::open(path)?;
             let len = file.seek(io::SeekFrom::End(0))?;
 
             Ok(Input(Arc::new(InputType::File { file, len })))
@@ -77,7 +78,12 @@ impl Input {
         let mut buf = Vec::new();
         io::Read::read_to_end(&mut io::stdin(), &mut buf)?;
 
-        Ok(Input(Arc::new(InputType::Stdin(buf.into()))))
+        Ok(Input::from_bytes(buf))
+    }
+
+    /// Creates an input from bytes in memory.
+    pub fn from_bytes(bytes: impl Into<Box<[u8]>>) -> Input {
+        Input(Arc::new(InputType::Memory(bytes.into())))
     }
 
     /// The length of the data.
@@ -88,8 +94,8 @@ impl Input {
                 u64::try_from(mmap.len())
                     .expect("non `u64`-fitting length would not fit into memory"),
             ),
-            InputType::Stdin(stdin) => Len::from(
-                u64::try_from(stdin.len())
+            InputType::Memory(memory) => Len::from(
+                u64::try_from(memory.len())
                     .expect("non `u64`-fitting length would not fit into memory"),
             ),
         }
@@ -100,6 +106,26 @@ impl Input {
         self.len().is_zero()
     }
 
+    /// The time at which the data of the input was last modified, if it is known.
+    ///
+    /// Only the data of inputs that are read from a file can change after they were opened.
+    pub fn modified(&self) -> Option<SystemTime> {
+        match &*self.0 {
+            InputType::File { file, .. } => file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            InputType::Memmap(_) | InputType::Memory(_) => None,
+        }
+    }
+
+    /// Determines if both handles refer to the same opened input.
+    ///
+    /// Opening the same file again results in a different input.
+    pub fn ptr_eq(this: &Input, other: &Input) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
     /// Signals a planned read so that the data at this offset can already be prefetched.
     ///
     /// This method is merely a hint and may also do nothing on some operating systems.
@@ -115,9 +141,11 @@ impl Input {
                         nix::fcntl::PosixFadviseAdvice::POSIX_FADV_WILLNEED,
                     );
                 }
+                #[cfg(not(unix))]
+                let _ = (file, offset, len);
             }
             InputType::Memmap(_) => (),
-            InputType::Stdin(_) => (),
+            InputType::Memory(_) => (),
         }
     }
 
@@ -194,17 +222,17 @@ impl Input {
                     buf: &mmap[offset_usize..offset_usize + output_size],
                 }))
             }
-            InputType::Stdin(stdin) => {
+            InputType::Memory(memory) => {
                 let offset_usize: usize = offset
                     .as_u64()
                     .try_into()
                     .expect("offset does not fit into `usize`");
 
-                if offset_usize > stdin.len() {
+                if offset_usize > memory.len() {
                     return Err(io::Error::other("offset is beyond input"));
                 }
 
-                let len_left = stdin.len() - offset_usize;
+                let len_left = memory.len() - offset_usize;
                 let output_size = std::cmp::min(
                     len_left,
                     len.as_u64()
@@ -213,7 +241,7 @@ impl Input {
                 );
 
                 Ok(ReadBytes(ReadBytesInner::ByRef {
-                    buf: &stdin[offset_usize..offset_usize + output_size],
+                    buf: &memory[offset_usize..offset_usize + output_size],
                 }))
             }
         }
@@ -301,6 +329,11 @@ fn read_exact(file: &File, mut pos: u64, mut buf: &mut [u8]) -> io::Result<()> {
     // However since `Input` is private internally, this is not a problem, since `read_seek` itself is atomic.
     #[cfg(windows)]
     let read_at = std::os::window::fs::FileExt::seek_read;
+    // there are no files on platforms like the web, so reading them is never possible
+    #[cfg(not(any(unix, windows)))]
+    let read_at = |_: &File, _: &mut [u8], _: u64| -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    };
 
     while !buf.is_empty() {
         match read_at(file, buf, pos) {
@@ -1,15 +1,20 @@
 //! Defines common types and functions used by all hexbait `crate`s.
 
 pub use endianness::Endianness;
+pub use format::{BlockUnits, Hex, HumanSize};
 pub use input::{Input, ReadBytes};
 pub use quantities::{AbsoluteOffset, Len, RelativeOffset};
+pub use range::AbsoluteRange;
 
 mod endianness;
+mod format;
 mod input;
 mod quantities;
+mod range;
 
 /// Indicates whether something changed or remained the same between frames.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StateChangeFlag {
     /// The state in question remained unchanged.
     Unchanged,
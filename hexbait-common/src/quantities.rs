@@ -2,11 +2,18 @@
 
 use std::{
     fmt,
-    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Rem, Sub, SubAssign},
 };
 
+use crate::format::{BlockUnits, Hex, HumanSize};
+
 /// Defines an absolute offset into a file.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct AbsoluteOffset(u64);
 
 impl AbsoluteOffset {
@@ -62,6 +69,55 @@ impl AbsoluteOffset {
     pub const fn to_relative(self) -> RelativeOffset {
         RelativeOffset(self.0)
     }
+
+    /// Adds the given length to this offset, returning `None` on overflow.
+    pub const fn checked_add(self, len: Len) -> Option<AbsoluteOffset> {
+        match self.0.checked_add(len.0) {
+            Some(offset) => Some(AbsoluteOffset(offset)),
+            None => None,
+        }
+    }
+
+    /// Subtracts the given length from this offset, returning `None` on underflow.
+    pub const fn checked_sub(self, len: Len) -> Option<AbsoluteOffset> {
+        match self.0.checked_sub(len.0) {
+            Some(offset) => Some(AbsoluteOffset(offset)),
+            None => None,
+        }
+    }
+
+    /// Adds the given length to this offset, saturating at the maximum offset.
+    pub const fn saturating_add(self, len: Len) -> AbsoluteOffset {
+        AbsoluteOffset(self.0.saturating_add(len.0))
+    }
+
+    /// Subtracts the given length from this offset, saturating at the start of the file.
+    pub const fn saturating_sub(self, len: Len) -> AbsoluteOffset {
+        AbsoluteOffset(self.0.saturating_sub(len.0))
+    }
+
+    /// Returns the length between `other` and this offset, returning `None` if `other` is larger.
+    pub const fn checked_len_since(self, other: AbsoluteOffset) -> Option<Len> {
+        match self.0.checked_sub(other.0) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Returns the length between `other` and this offset, or `0` if `other` is larger.
+    pub const fn saturating_len_since(self, other: AbsoluteOffset) -> Len {
+        Len(self.0.saturating_sub(other.0))
+    }
+
+    /// Returns the length from the start of the file to this offset.
+    pub const fn len_from_start(self) -> Len {
+        Len(self.0)
+    }
+
+    /// Displays the offset in hexadecimal with a `0x` prefix.
+    pub fn hex(self) -> Hex {
+        Hex::new(self.0)
+    }
 }
 
 impl fmt::Debug for AbsoluteOffset {
@@ -136,8 +192,29 @@ impl SubAssign<Len> for AbsoluteOffset {
     }
 }
 
+impl Sub<RelativeOffset> for AbsoluteOffset {
+    type Output = AbsoluteOffset;
+
+    #[track_caller]
+    fn sub(self, rhs: RelativeOffset) -> Self::Output {
+        AbsoluteOffset(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign<RelativeOffset> for AbsoluteOffset {
+    #[track_caller]
+    fn sub_assign(&mut self, rhs: RelativeOffset) {
+        self.0 -= rhs.0;
+    }
+}
+
 /// An offset that is relative to some other offset.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct RelativeOffset(u64);
 
 impl RelativeOffset {
@@ -180,6 +257,55 @@ impl RelativeOffset {
     pub const fn to_absolute(self) -> AbsoluteOffset {
         AbsoluteOffset(self.0)
     }
+
+    /// Adds the given length to this offset, returning `None` on overflow.
+    pub const fn checked_add(self, len: Len) -> Option<RelativeOffset> {
+        match self.0.checked_add(len.0) {
+            Some(offset) => Some(RelativeOffset(offset)),
+            None => None,
+        }
+    }
+
+    /// Subtracts the given length from this offset, returning `None` on underflow.
+    pub const fn checked_sub(self, len: Len) -> Option<RelativeOffset> {
+        match self.0.checked_sub(len.0) {
+            Some(offset) => Some(RelativeOffset(offset)),
+            None => None,
+        }
+    }
+
+    /// Adds the given length to this offset, saturating at the maximum offset.
+    pub const fn saturating_add(self, len: Len) -> RelativeOffset {
+        RelativeOffset(self.0.saturating_add(len.0))
+    }
+
+    /// Subtracts the given length from this offset, saturating at the base offset.
+    pub const fn saturating_sub(self, len: Len) -> RelativeOffset {
+        RelativeOffset(self.0.saturating_sub(len.0))
+    }
+
+    /// Returns the length between `other` and this offset, returning `None` if `other` is larger.
+    pub const fn checked_len_since(self, other: RelativeOffset) -> Option<Len> {
+        match self.0.checked_sub(other.0) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Returns the length between `other` and this offset, or `0` if `other` is larger.
+    pub const fn saturating_len_since(self, other: RelativeOffset) -> Len {
+        Len(self.0.saturating_sub(other.0))
+    }
+
+    /// Returns the length from the base offset to this offset.
+    pub const fn len_from_base(self) -> Len {
+        Len(self.0)
+    }
+
+    /// Displays the offset in hexadecimal with a `0x` prefix.
+    pub fn hex(self) -> Hex {
+        Hex::new(self.0)
+    }
 }
 
 impl fmt::Debug for RelativeOffset {
@@ -231,8 +357,20 @@ impl AddAssign<Len> for RelativeOffset {
     }
 }
 
+impl SubAssign<Len> for RelativeOffset {
+    #[track_caller]
+    fn sub_assign(&mut self, rhs: Len) {
+        self.0 -= rhs.0;
+    }
+}
+
 /// A length of a section of data.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Len(u64);
 
 impl Len {
@@ -278,6 +416,75 @@ impl Len {
     pub const fn align_down(self, align: u64) -> Self {
         Self(align_down(self.0, align))
     }
+
+    /// Returns the offset that is this length after the base offset.
+    pub const fn to_relative(self) -> RelativeOffset {
+        RelativeOffset(self.0)
+    }
+
+    /// Adds the given length to this length, returning `None` on overflow.
+    pub const fn checked_add(self, rhs: Len) -> Option<Len> {
+        match self.0.checked_add(rhs.0) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Subtracts the given length from this length, returning `None` on underflow.
+    pub const fn checked_sub(self, rhs: Len) -> Option<Len> {
+        match self.0.checked_sub(rhs.0) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Multiplies this length by the given factor, returning `None` on overflow.
+    pub const fn checked_mul(self, rhs: u64) -> Option<Len> {
+        match self.0.checked_mul(rhs) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Divides this length by the given divisor, returning `None` if the divisor is `0`.
+    pub const fn checked_div(self, rhs: u64) -> Option<Len> {
+        match self.0.checked_div(rhs) {
+            Some(len) => Some(Len(len)),
+            None => None,
+        }
+    }
+
+    /// Adds the given length to this length, saturating at the maximum length.
+    pub const fn saturating_add(self, rhs: Len) -> Len {
+        Len(self.0.saturating_add(rhs.0))
+    }
+
+    /// Subtracts the given length from this length, saturating at `0`.
+    pub const fn saturating_sub(self, rhs: Len) -> Len {
+        Len(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Multiplies this length by the given factor, saturating at the maximum length.
+    pub const fn saturating_mul(self, rhs: u64) -> Len {
+        Len(self.0.saturating_mul(rhs))
+    }
+
+    /// Displays the length in hexadecimal with a `0x` prefix.
+    pub fn hex(self) -> Hex {
+        Hex::new(self.0)
+    }
+
+    /// Displays the length in a human readable form, such as `3.2 MiB`.
+    pub fn human_size(self) -> HumanSize {
+        HumanSize(self)
+    }
+
+    /// Displays the length in units of `block_size`, such as sectors or clusters.
+    ///
+    /// `unit` is the plural name of the unit.
+    pub fn in_blocks(self, block_size: Len, unit: &'static str) -> BlockUnits {
+        BlockUnits::new(self, block_size, unit)
+    }
 }
 
 impl Add<Len> for Len {
@@ -337,6 +544,24 @@ impl DivAssign<u64> for Len {
     }
 }
 
+impl Div<Len> for Len {
+    type Output = u64;
+
+    #[track_caller]
+    fn div(self, rhs: Len) -> Self::Output {
+        self.0 / rhs.0
+    }
+}
+
+impl Rem<u64> for Len {
+    type Output = Len;
+
+    #[track_caller]
+    fn rem(self, rhs: u64) -> Self::Output {
+        Len(self.0 % rhs)
+    }
+}
+
 impl Sub<Len> for Len {
     type Output = Len;
 
@@ -0,0 +1,273 @@
+//! Models half-open ranges of absolute offsets.
+
+use std::{
+    fmt,
+    ops::{Range, RangeInclusive},
+};
+
+use crate::{AbsoluteOffset, Len};
+
+/// Represents a half-open region `start..end` of the input.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbsoluteRange {
+    // The field order of `start` first is required by several parts of the code in the `Ord` impl.
+    // Don't change it!
+    /// The index of the first byte in the region.
+    start: AbsoluteOffset,
+    /// The index one past the last byte in the region.
+    end: AbsoluteOffset,
+}
+
+impl AbsoluteRange {
+    /// The `ZERO` range that is empty at the start of the file.
+    pub const ZERO: AbsoluteRange = AbsoluteRange {
+        start: AbsoluteOffset::from(0),
+        end: AbsoluteOffset::from(0),
+    };
+
+    /// Creates a new range.
+    ///
+    /// If `end` is before `start`, the two are swapped.
+    pub fn new(start: AbsoluteOffset, end: AbsoluteOffset) -> AbsoluteRange {
+        if start < end {
+            AbsoluteRange { start, end }
+        } else {
+            AbsoluteRange {
+                start: end,
+                end: start,
+            }
+        }
+    }
+
+    /// Creates a range from a start offset and a length.
+    pub fn from_start_len(start: AbsoluteOffset, len: Len) -> AbsoluteRange {
+        AbsoluteRange {
+            start,
+            end: start + len,
+        }
+    }
+
+    /// Creates an empty range at the given offset.
+    pub fn empty_from_start(start: AbsoluteOffset) -> AbsoluteRange {
+        AbsoluteRange { start, end: start }
+    }
+
+    /// Creates the joined range between `self` and `other` if they are adjacent.
+    pub fn joined(self, other: AbsoluteRange) -> Option<AbsoluteRange> {
+        if self.end() == other.start() {
+            Some(AbsoluteRange {
+                start: self.start(),
+                end: other.end(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The start of the range.
+    pub fn start(self) -> AbsoluteOffset {
+        self.start
+    }
+
+    /// The end of the range.
+    pub fn end(self) -> AbsoluteOffset {
+        self.end
+    }
+
+    /// The size of the range in bytes.
+    pub fn size(self) -> Len {
+        self.end() - self.start()
+    }
+
+    /// Determines if the range is empty.
+    pub fn is_empty(self) -> bool {
+        self.start() == self.end()
+    }
+
+    /// Determines if the range contains the given offset.
+    pub fn contains(self, offset: AbsoluteOffset) -> bool {
+        self.start() <= offset && offset < self.end()
+    }
+
+    /// Determines if the range contains the given other range.
+    pub fn contains_window(self, other: AbsoluteRange) -> bool {
+        self.start() <= other.start() && other.end() <= self.end()
+    }
+
+    /// Determines if the range overlaps with the other range.
+    pub fn overlaps(self, other: AbsoluteRange) -> bool {
+        self.start() < other.end() && other.start() < self.end()
+    }
+
+    /// Returns the intersection of both ranges, if they overlap.
+    pub fn intersection(self, other: AbsoluteRange) -> Option<AbsoluteRange> {
+        if self.overlaps(other) {
+            Some(AbsoluteRange {
+                start: self.start().max(other.start()),
+                end: self.end().min(other.end()),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the union of both ranges, if they overlap or are adjacent.
+    pub fn union(self, other: AbsoluteRange) -> Option<AbsoluteRange> {
+        if self.start() <= other.end() && other.start() <= self.end() {
+            Some(AbsoluteRange {
+                start: self.start().min(other.start()),
+                end: self.end().max(other.end()),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Splits the range into the part before `offset` and the part starting at `offset`.
+    ///
+    /// If `offset` is outside of the range, one of the returned ranges is empty.
+    pub fn split_at(self, offset: AbsoluteOffset) -> (AbsoluteRange, AbsoluteRange) {
+        let offset = offset.clamp(self.start(), self.end());
+
+        (
+            AbsoluteRange {
+                start: self.start(),
+                end: offset,
+            },
+            AbsoluteRange {
+                start: offset,
+                end: self.end(),
+            },
+        )
+    }
+
+    /// Returns the range as a [`Range`] instead.
+    pub fn range(self) -> Range<AbsoluteOffset> {
+        self.start()..self.end()
+    }
+
+    /// Returns the range as a [`RangeInclusive`] instead, if it is non-empty.
+    pub fn range_inclusive(self) -> Option<RangeInclusive<AbsoluteOffset>> {
+        if self.start() < self.end() {
+            Some(self.start()..=(self.end() - Len::from(1)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over smaller ranges of the given size.
+    ///
+    /// `self.size()` must be a multiple of `size`.
+    ///
+    /// # Panics
+    /// This function MAY panic if `self.size()` is not a multiple of `size`.
+    pub fn subwindows_of_size(self, size: Len) -> impl Iterator<Item = AbsoluteRange> {
+        debug_assert!(self.size().as_u64().is_multiple_of(size.as_u64()));
+
+        (0..self.size() / size)
+            .map(move |i| AbsoluteRange::from_start_len(self.start() + i * size, size))
+    }
+
+    /// Expands this range such that both the start and end are aligned to `align`.
+    ///
+    /// `align` must be a power of two.
+    pub fn expand_to_align(self, align: u64) -> AbsoluteRange {
+        let start = self.start().align_down(align);
+        let end = self.end().align_up(align);
+
+        AbsoluteRange { start, end }
+    }
+
+    /// Returns three subranges `(before, aligned, after)`.
+    ///
+    /// The second returned range has alignment `align` for both its start and end and will be
+    /// fully contained in the original range if it exists.
+    /// It will also be the maximum size range that fulfills these conditions.
+    /// Any of the ranges may be empty.
+    /// All ranges joined together will span the whole original range.
+    ///
+    /// `None` is returned if no aligned subrange exists within `self`.
+    ///
+    /// `align` must be a power of two.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::{AbsoluteOffset, AbsoluteRange};
+    /// # let range = |start: u64, end: u64| {
+    /// #     AbsoluteRange::new(AbsoluteOffset::from(start), AbsoluteOffset::from(end))
+    /// # };
+    /// assert_eq!(
+    ///     range(3, 25).align(8),
+    ///     Some((
+    ///         range(3, 8),
+    ///         range(8, 24),
+    ///         range(24, 25),
+    ///     ))
+    /// );
+    /// assert_eq!(
+    ///     range(3, 8).align(8),
+    ///     Some((
+    ///         range(3, 8),
+    ///         range(8, 8),
+    ///         range(8, 8),
+    ///     ))
+    /// );
+    /// assert_eq!(
+    ///     range(7, 11).align(8),
+    ///     Some((
+    ///         range(7, 8),
+    ///         range(8, 8),
+    ///         range(8, 11),
+    ///     ))
+    /// );
+    /// assert_eq!(range(3, 25).align(32), None);
+    /// ```
+    pub fn align(self, align: u64) -> Option<(AbsoluteRange, AbsoluteRange, AbsoluteRange)> {
+        let start = self.start().align_up(align);
+        let end = self.end().align_down(align);
+
+        if start <= end {
+            Some((
+                AbsoluteRange {
+                    start: self.start(),
+                    end: start,
+                },
+                AbsoluteRange { start, end },
+                AbsoluteRange {
+                    start: end,
+                    end: self.end(),
+                },
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Range<AbsoluteOffset>> for AbsoluteRange {
+    fn from(value: Range<AbsoluteOffset>) -> Self {
+        AbsoluteRange {
+            start: value.start,
+            end: value.end.max(value.start),
+        }
+    }
+}
+
+impl From<RangeInclusive<AbsoluteOffset>> for AbsoluteRange {
+    fn from(value: RangeInclusive<AbsoluteOffset>) -> Self {
+        AbsoluteRange::new(*value.start(), *value.end() + Len::from(1))
+    }
+}
+
+impl fmt::Debug for AbsoluteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AbsoluteRange(at: {}, size: {})",
+            self.start().hex(),
+            self.size().human_size(),
+        )
+    }
+}
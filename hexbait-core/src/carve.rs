@@ -0,0 +1,583 @@
+//! Implements carving of embedded files out of the input.
+//!
+//! The input is scanned for known file signatures.
+//! Compressed streams are decompressed and their content is carved recursively, which finds
+//! files nested in archives or compressed firmware blobs.
+//! Signatures for which a built-in format description exists are additionally validated by
+//! parsing them.
+//!
+//! Optionally, all carved files are extracted into an output directory, where the decompressed
+//! content of a file `<name>` and the files carved from it are stored in `<name>.extracted`.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use hexbait_lang::ir::File;
+use miniz_oxide::{
+    DataFormat, MZError, MZFlush, MZStatus,
+    inflate::stream::{InflateState, inflate},
+};
+
+use crate::{
+    magic::{FileKind, MAGIC_LOOKAHEAD},
+    parse::parse_input,
+    window::Window,
+    worker::{self, Worker, WorkerStatus},
+};
+
+/// The size of the chunks in which the input is scanned for signatures.
+const SCAN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The size of the chunks in which compressed streams are read.
+const INFLATE_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The size of the buffer that decompressed data is written to.
+const INFLATE_OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The maximum size of a compressed stream that is decompressed.
+const MAX_COMPRESSED_LEN: u64 = 256 * 1024 * 1024;
+
+/// The maximum size that a decompressed stream may have.
+const MAX_DECOMPRESSED_LEN: u64 = 256 * 1024 * 1024;
+
+/// The file extensions whose signatures are too short to be meaningful when carving.
+///
+/// These signatures match in random data all the time, so they are ignored while carving.
+const WEAK_SIGNATURES: &[&str] = &[
+    "aac", "bc", "bmp", "cpio", "der", "html", "ico", "jxl", "mp3", "mpg", "obj", "ps", "sh",
+    "ttf", "Z",
+];
+
+impl FileKind {
+    /// A zlib compressed stream.
+    const ZLIB: FileKind = FileKind {
+        mime_type: "application/zlib",
+        extension: "zlib",
+    };
+    /// Determines if the file of this kind starting with the given bytes is worth carving.
+    fn is_plausible(self, bytes: &[u8]) -> bool {
+        match self.extension {
+            // the `MZ` signature is only meaningful if it is followed by a PE header
+            "exe" | "dll" => bytes
+                .get(0x3c..0x40)
+                .map(|offset| u32::from_le_bytes(offset.try_into().unwrap()) as usize)
+                .and_then(|offset| bytes.get(offset..offset.checked_add(4)?))
+                .is_some_and(|signature| signature == b"PE\0\0"),
+            extension => !WEAK_SIGNATURES.contains(&extension),
+        }
+    }
+
+    /// The compression format of files of this kind, if they are compressed streams.
+    fn compression(self) -> Option<Compression> {
+        match self.extension {
+            "gz" => Some(Compression::Gzip),
+            "zlib" => Some(Compression::Zlib),
+            _ => None,
+        }
+    }
+}
+
+/// The options for carving.
+pub struct CarveOptions {
+    /// How many levels of compressed streams are decompressed and carved.
+    pub max_depth: usize,
+    /// The directory that carved files are extracted into, if any.
+    pub output_dir: Option<PathBuf>,
+    /// The format descriptions available to validate signatures.
+    pub parsers: BTreeMap<&'static str, File>,
+}
+
+/// The result of validating a signature with a format description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserCheck {
+    /// The name of the format description.
+    pub name: &'static str,
+    /// The number of errors that occurred while parsing.
+    pub errors: usize,
+}
+
+/// A file found while carving.
+#[derive(Debug, Clone)]
+pub struct CarvedFile {
+    /// The bytes of the file within the content it was carved from.
+    ///
+    /// Unless the file is a compressed stream, its exact size is unknown, so it is assumed to
+    /// extend up to the next carved file.
+    pub window: Window,
+    /// The type of the file.
+    pub kind: FileKind,
+    /// The result of validating the file with a format description, if one is available.
+    pub parser_check: Option<ParserCheck>,
+    /// The size of the decompressed content, if the file is a compressed stream.
+    pub decompressed_len: Option<Len>,
+    /// The files carved from the decompressed content.
+    pub children: Vec<CarvedFile>,
+    /// The path that the file was extracted to.
+    pub extracted_to: Option<PathBuf>,
+}
+
+impl CarvedFile {
+    /// Returns the total number of files carved, including this file and all nested files.
+    pub fn count(&self) -> usize {
+        1 + self.children.iter().map(CarvedFile::count).sum::<usize>()
+    }
+}
+
+/// A compression format whose streams are decompressed while carving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    /// A gzip member.
+    Gzip,
+    /// A zlib stream.
+    Zlib,
+}
+
+/// Carves embedded files out of an input.
+pub struct Carver {
+    /// The input that is carved.
+    input: Input,
+    /// The options for carving.
+    options: Arc<CarveOptions>,
+    /// How many compressed streams the input is nested in.
+    depth: usize,
+    /// The directory that files are extracted into.
+    output_dir: Option<PathBuf>,
+    /// The offset up to which signatures are ignored, because they are part of a carved stream.
+    skip_until: AbsoluteOffset,
+    /// The start of the next chunk that is scanned.
+    chunk_start: AbsoluteOffset,
+    /// The files carved so far.
+    files: Vec<CarvedFile>,
+    /// Whether the size of the last carved file is still unknown.
+    last_is_open: bool,
+    /// The buffer that chunks are read into.
+    buf: Vec<u8>,
+}
+
+impl Carver {
+    /// Creates a carver for the given input.
+    pub fn new(input: Input, options: Arc<CarveOptions>) -> Carver {
+        let output_dir = options.output_dir.clone();
+
+        Carver::nested(input, options, 0, output_dir)
+    }
+
+    /// Creates a carver for content nested in `depth` compressed streams.
+    fn nested(
+        input: Input,
+        options: Arc<CarveOptions>,
+        depth: usize,
+        output_dir: Option<PathBuf>,
+    ) -> Carver {
+        Carver {
+            input,
+            options,
+            depth,
+            output_dir,
+            skip_until: AbsoluteOffset::ZERO,
+            chunk_start: AbsoluteOffset::ZERO,
+            files: Vec::new(),
+            last_is_open: false,
+            buf: Vec::new(),
+        }
+    }
+
+    /// The fraction of the input that was carved so far.
+    pub fn progress(&self) -> f32 {
+        if self.input.len().is_zero() {
+            return 1.0;
+        }
+
+        (self.chunk_start.as_u64() as f64 / self.input.len().as_u64() as f64).min(1.0) as f32
+    }
+
+    /// The files carved so far.
+    pub fn files(&self) -> &[CarvedFile] {
+        &self.files
+    }
+
+    /// Carves the next chunk of the input.
+    ///
+    /// Returns `true` once the whole input was carved.
+    pub fn step(&mut self) -> io::Result<bool> {
+        let end = AbsoluteOffset::ZERO + self.input.len();
+        if self.chunk_start >= end {
+            self.close_last_file(end)?;
+            return Ok(true);
+        }
+
+        // carving needs mutable access, while the chunk borrows the input and the buffer
+        let input = self.input.clone();
+        let mut buf = std::mem::take(&mut self.buf);
+        let chunk = input.read_at(
+            self.chunk_start,
+            Len::from(SCAN_CHUNK_SIZE + MAGIC_LOOKAHEAD),
+            Some(&mut buf),
+        )?;
+        let checked_len = chunk.len().min(SCAN_CHUNK_SIZE as usize);
+        for i in 0..checked_len {
+            let offset = self.chunk_start + Len::from(i as u64);
+            if offset < self.skip_until {
+                continue;
+            }
+
+            let bytes = &chunk[i..];
+            let kind = FileKind::identify(bytes)
+                .filter(|kind| kind.is_plausible(bytes))
+                .or_else(|| {
+                    is_zlib_header(bytes)
+                        .then_some(FileKind::ZLIB)
+                        .filter(|_| self.depth < self.options.max_depth)
+                });
+            if let Some(kind) = kind {
+                self.carve_at(offset, kind)?;
+            }
+        }
+        drop(chunk);
+        self.buf = buf;
+
+        self.chunk_start += Len::from(SCAN_CHUNK_SIZE);
+
+        Ok(false)
+    }
+
+    /// Carves the whole remaining input and returns all carved files.
+    pub fn finish(mut self) -> io::Result<Vec<CarvedFile>> {
+        while !self.step()? {}
+
+        Ok(self.files)
+    }
+
+    /// Carves the file of the given kind at the given offset.
+    fn carve_at(&mut self, offset: AbsoluteOffset, kind: FileKind) -> io::Result<()> {
+        let mut file = CarvedFile {
+            window: Window::empty_from_start(offset),
+            kind,
+            parser_check: None,
+            decompressed_len: None,
+            children: Vec::new(),
+            extracted_to: None,
+        };
+
+        if let Some(compression) = kind.compression() {
+            let Some((len, content)) = decompress(&self.input, offset, compression)? else {
+                // not a valid stream, so the signature was a false positive
+                return Ok(());
+            };
+            self.close_last_file(offset)?;
+
+            file.window = Window::from_start_len(offset, len);
+            file.decompressed_len = Some(Len::from(content.len() as u64));
+            self.extract(&mut file)?;
+
+            if self.depth < self.options.max_depth {
+                let output_dir = file.extracted_to.as_ref().map(|path| {
+                    let mut dir = path.clone().into_os_string();
+                    dir.push(".extracted");
+                    PathBuf::from(dir)
+                });
+                if let Some(dir) = &output_dir {
+                    fs::create_dir_all(dir)?;
+                    fs::write(dir.join("decompressed"), &content)?;
+                }
+                file.children = Carver::nested(
+                    Input::from_bytes(content),
+                    Arc::clone(&self.options),
+                    self.depth + 1,
+                    output_dir,
+                )
+                .finish()?;
+            }
+
+            self.skip_until = file.window.end();
+            self.files.push(file);
+            self.last_is_open = false;
+        } else {
+            self.close_last_file(offset)?;
+
+            file.parser_check = self.check_with_parser(offset, kind);
+            self.files.push(file);
+            self.last_is_open = true;
+        }
+
+        Ok(())
+    }
+
+    /// Validates the file of the given kind at the given offset with a format description.
+    fn check_with_parser(&self, offset: AbsoluteOffset, kind: FileKind) -> Option<ParserCheck> {
+        let (name, parser) = self.options.parsers.get_key_value(kind.builtin_parser()?)?;
+        let result = parse_input(parser, &self.input, offset);
+
+        Some(ParserCheck {
+            name,
+            errors: result.errors.len(),
+        })
+    }
+
+    /// Ends the last carved file at the given offset, if its size is still unknown.
+    fn close_last_file(&mut self, end: AbsoluteOffset) -> io::Result<()> {
+        if !std::mem::take(&mut self.last_is_open) {
+            return Ok(());
+        }
+
+        let mut file = self.files.pop().expect("the last file is open");
+        file.window = Window::new(file.window.start(), end);
+        self.extract(&mut file)?;
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    /// Extracts the given file into the output directory, if there is one.
+    fn extract(&self, file: &mut CarvedFile) -> io::Result<()> {
+        let Some(dir) = &self.output_dir else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "{}.{}",
+            file.window.start().hex().padded(8),
+            file.kind.extension
+        ));
+        copy_window(&self.input, file.window, &path)?;
+        file.extracted_to = Some(path);
+
+        Ok(())
+    }
+}
+
+/// Carves embedded files out of the whole input.
+pub fn carve(input: Input, options: Arc<CarveOptions>) -> io::Result<Vec<CarvedFile>> {
+    Carver::new(input, options).finish()
+}
+
+/// Writes the given window of the input to the file at `path`.
+fn copy_window(input: &Input, window: Window, path: &Path) -> io::Result<()> {
+    use io::Write as _;
+
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    let mut buf = Vec::new();
+    let mut offset = window.start();
+    while offset < window.end() {
+        let len = (window.end() - offset).min(Len::from(SCAN_CHUNK_SIZE));
+        out.write_all(&input.read_at(offset, len, Some(&mut buf))?)?;
+        offset += len;
+    }
+
+    out.flush()
+}
+
+/// Determines if the given bytes start with a plausible zlib header.
+fn is_zlib_header(bytes: &[u8]) -> bool {
+    let &[cmf, flg, ..] = bytes else {
+        return false;
+    };
+
+    // deflate with a window size of at most 32 KiB, no preset dictionary and a valid checksum
+    cmf == 0x78 && flg & 0x20 == 0 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+/// Decompresses the stream at the given offset.
+///
+/// Returns the size of the compressed stream and the decompressed content or `None` if the
+/// stream is not valid.
+fn decompress(
+    input: &Input,
+    offset: AbsoluteOffset,
+    compression: Compression,
+) -> io::Result<Option<(Len, Vec<u8>)>> {
+    match compression {
+        Compression::Zlib => inflate_stream(input, offset, DataFormat::Zlib),
+        Compression::Gzip => {
+            let header = input.read_at(offset, Len::from(MAGIC_LOOKAHEAD), None)?;
+            let Some(header_len) = gzip_header_len(&header) else {
+                return Ok(None);
+            };
+            let data_start = offset + Len::from(header_len as u64);
+            let Some((data_len, content)) = inflate_stream(input, data_start, DataFormat::Raw)?
+            else {
+                return Ok(None);
+            };
+
+            // the stream is followed by a CRC-32 and the size of the decompressed content
+            let trailer = input.read_at(data_start + data_len, Len::from(8), None)?;
+            let Ok(trailer) = <[u8; 8]>::try_from(&*trailer) else {
+                return Ok(None);
+            };
+            let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+            if size != content.len() as u32 {
+                return Ok(None);
+            }
+
+            Ok(Some((
+                Len::from(header_len as u64) + data_len + Len::from(8),
+                content,
+            )))
+        }
+    }
+}
+
+/// Returns the size of the gzip member header at the start of `bytes`.
+fn gzip_header_len(bytes: &[u8]) -> Option<usize> {
+    /// The flag indicating that an extra field is present.
+    const FEXTRA: u8 = 0x04;
+    /// The flag indicating that a file name is present.
+    const FNAME: u8 = 0x08;
+    /// The flag indicating that a comment is present.
+    const FCOMMENT: u8 = 0x10;
+    /// The flag indicating that a header CRC is present.
+    const FHCRC: u8 = 0x02;
+
+    let &[0x1f, 0x8b, 8, flags, ..] = bytes else {
+        return None;
+    };
+    let mut len = 10;
+    if flags & FEXTRA != 0 {
+        let extra_len = u16::from_le_bytes([*bytes.get(len)?, *bytes.get(len + 1)?]);
+        len += 2 + extra_len as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            len += bytes.get(len..)?.iter().position(|&byte| byte == 0)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+
+    (len <= bytes.len()).then_some(len)
+}
+
+/// Inflates the deflate stream in the given format at the given offset.
+///
+/// Returns the size of the compressed stream and the decompressed content or `None` if the
+/// stream is not valid or too large.
+fn inflate_stream(
+    input: &Input,
+    offset: AbsoluteOffset,
+    format: DataFormat,
+) -> io::Result<Option<(Len, Vec<u8>)>> {
+    let end = AbsoluteOffset::ZERO + input.len();
+    let mut state = InflateState::new_boxed(format);
+    let mut content = Vec::new();
+    let mut out = vec![0; INFLATE_OUTPUT_BUFFER_SIZE];
+    let mut buf = Vec::new();
+
+    let mut chunk_start = offset;
+    while chunk_start < end && chunk_start - offset < Len::from(MAX_COMPRESSED_LEN) {
+        let chunk = input.read_at(chunk_start, Len::from(INFLATE_CHUNK_SIZE), Some(&mut buf))?;
+
+        let mut pos = 0;
+        loop {
+            let result = inflate(&mut state, &chunk[pos..], &mut out, MZFlush::None);
+            pos += result.bytes_consumed;
+            content.extend_from_slice(&out[..result.bytes_written]);
+            if content.len() as u64 > MAX_DECOMPRESSED_LEN {
+                return Ok(None);
+            }
+
+            match result.status {
+                Ok(MZStatus::StreamEnd) if !content.is_empty() => {
+                    let len = chunk_start - offset + Len::from(pos as u64);
+                    return Ok(Some((len, content)));
+                }
+                Ok(MZStatus::Ok) | Err(MZError::Buf) => (),
+                _ => return Ok(None),
+            }
+
+            // stop once all input is consumed and all pending output is written
+            if pos == chunk.len() && result.bytes_written < out.len() {
+                break;
+            }
+        }
+
+        chunk_start += Len::from(chunk.len() as u64);
+    }
+
+    Ok(None)
+}
+
+/// The progress of carving in the background.
+struct CarvingProgress {
+    /// The fraction of the input that was carved so far.
+    progress: f32,
+    /// The result of carving once it is finished.
+    result: Option<io::Result<Vec<CarvedFile>>>,
+}
+
+/// A handle to carving that happens in the background.
+///
+/// Dropping the handle cancels carving.
+pub struct CarvingHandle {
+    /// The progress shared with the background worker.
+    shared: Arc<Mutex<CarvingProgress>>,
+}
+
+impl CarvingHandle {
+    /// Starts carving the given input in the background.
+    pub fn start(input: Input, options: Arc<CarveOptions>) -> CarvingHandle {
+        let shared = Arc::new(Mutex::new(CarvingProgress {
+            progress: 0.0,
+            result: None,
+        }));
+
+        worker::spawn(CarvingWorker {
+            carver: Some(Carver::new(input, options)),
+            shared: Arc::clone(&shared),
+        });
+
+        CarvingHandle { shared }
+    }
+
+    /// The fraction of the input that was carved so far.
+    pub fn progress(&self) -> f32 {
+        self.shared.lock().unwrap().progress
+    }
+
+    /// Takes the result once carving is finished.
+    pub fn take_result(&self) -> Option<io::Result<Vec<CarvedFile>>> {
+        self.shared.lock().unwrap().result.take()
+    }
+}
+
+/// Carves an input in the background.
+struct CarvingWorker {
+    /// The carver that does the work, until it is finished.
+    carver: Option<Carver>,
+    /// The progress shared with the handle.
+    shared: Arc<Mutex<CarvingProgress>>,
+}
+
+impl Worker for CarvingWorker {
+    fn step(&mut self, _: bool) -> WorkerStatus {
+        // the handle was dropped, so nobody is interested in the result anymore
+        if Arc::strong_count(&self.shared) == 1 {
+            return WorkerStatus::Terminated;
+        }
+        let Some(carver) = &mut self.carver else {
+            return WorkerStatus::Terminated;
+        };
+
+        let result = match carver.step() {
+            Ok(false) => {
+                self.shared.lock().unwrap().progress = carver.progress();
+                return WorkerStatus::Busy;
+            }
+            Ok(true) => Ok(std::mem::take(&mut carver.files)),
+            Err(err) => Err(err),
+        };
+        self.carver = None;
+
+        let mut shared = self.shared.lock().unwrap();
+        shared.progress = 1.0;
+        shared.result = Some(result);
+
+        WorkerStatus::Terminated
+    }
+}
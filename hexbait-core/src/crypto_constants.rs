@@ -0,0 +1,523 @@
+//! Implements detection of well-known cryptographic constants.
+//!
+//! Cryptographic code usually contains tables and initial values that are identical in every
+//! implementation, such as the AES S-box or the initial hash values of SHA-2.
+//! Finding them is a quick way to locate cryptographic code in packed binaries and firmware.
+
+use std::{
+    io,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use aho_corasick::AhoCorasick;
+use hexbait_common::{AbsoluteOffset, Input, Len};
+
+use crate::{
+    window::Window,
+    worker::{self, Worker, WorkerStatus},
+};
+
+/// The size of the chunks in which the input is scanned.
+const SCAN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The number of bytes at the start of each constant that are searched for.
+///
+/// The rest of a longer constant is compared after its start was found.
+const KEY_LEN: usize = 64;
+
+/// The initial hash values of SHA-224.
+const SHA224_IV: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+/// The initial hash values of SHA-256.
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The round constants of SHA-224 and SHA-256.
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial hash values of SHA-384.
+const SHA384_IV: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+/// The initial hash values of SHA-512.
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The alphabet of standard Base64 encoding.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The alphabet of URL and filename safe Base64 encoding.
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A well-known cryptographic constant.
+#[derive(Debug)]
+pub struct Constant {
+    /// A human readable name of the constant.
+    pub name: String,
+    /// The bytes of the constant as they are stored in memory.
+    pub bytes: Vec<u8>,
+}
+
+/// The known constants and a searcher for their starts.
+struct KnownConstants {
+    /// The known constants.
+    constants: Vec<Constant>,
+    /// Searches for the first [`KEY_LEN`] bytes of all constants.
+    searcher: AhoCorasick,
+}
+
+/// Returns all known cryptographic constants.
+pub fn known_constants() -> &'static [Constant] {
+    &known().constants
+}
+
+/// Returns the known constants, building them on first use.
+fn known() -> &'static KnownConstants {
+    static KNOWN: OnceLock<KnownConstants> = OnceLock::new();
+
+    KNOWN.get_or_init(|| {
+        let mut constants = Vec::new();
+
+        let sbox = aes_sbox();
+        let mut inverse_sbox = [0; 256];
+        for (x, &s) in sbox.iter().enumerate() {
+            inverse_sbox[s as usize] = x as u8;
+        }
+        let te0 = sbox.map(|s| u32::from_be_bytes([gf_mul(s, 2), s, s, gf_mul(s, 3)]));
+        let td0 = inverse_sbox.map(|s| {
+            u32::from_be_bytes([gf_mul(s, 14), gf_mul(s, 9), gf_mul(s, 13), gf_mul(s, 11)])
+        });
+
+        constants.push(Constant {
+            name: String::from("AES S-box"),
+            bytes: sbox.to_vec(),
+        });
+        constants.push(Constant {
+            name: String::from("AES inverse S-box"),
+            bytes: inverse_sbox.to_vec(),
+        });
+        push_words(&mut constants, "AES T-table Te0", &te0);
+        push_words(&mut constants, "AES T-table Td0", &td0);
+
+        push_words(&mut constants, "SHA-224 IV", &SHA224_IV);
+        push_words(&mut constants, "SHA-256 IV", &SHA256_IV);
+        push_words(&mut constants, "SHA-256 round constants", &SHA256_K);
+        push_words(&mut constants, "SHA-384 IV", &SHA384_IV);
+        push_words(&mut constants, "SHA-512 IV", &SHA512_IV);
+
+        for (name, polynomial) in [
+            ("CRC-32 polynomial", 0x04c11db7u32),
+            ("CRC-32 polynomial (reflected)", 0xedb88320),
+            ("CRC-32C polynomial (reflected)", 0x82f63b78),
+        ] {
+            push_words(&mut constants, name, &[polynomial]);
+        }
+        for (name, table) in [
+            ("CRC-32 table", crc32_table(0x04c11db7)),
+            (
+                "CRC-32 table (reflected)",
+                crc32_table_reflected(0xedb88320),
+            ),
+            (
+                "CRC-32C table (reflected)",
+                crc32_table_reflected(0x82f63b78),
+            ),
+        ] {
+            push_words(&mut constants, name, &table);
+        }
+        for (name, table) in [
+            ("CRC-16/CCITT table", crc16_table(0x1021)),
+            (
+                "CRC-16/ARC table (reflected)",
+                crc16_table_reflected(0xa001),
+            ),
+        ] {
+            push_words(&mut constants, name, &table);
+        }
+
+        constants.push(Constant {
+            name: String::from("Base64 alphabet"),
+            bytes: BASE64_ALPHABET.to_vec(),
+        });
+        constants.push(Constant {
+            name: String::from("Base64 URL-safe alphabet"),
+            bytes: BASE64_URL_ALPHABET.to_vec(),
+        });
+
+        let searcher = AhoCorasick::new(
+            constants
+                .iter()
+                .map(|constant| &constant.bytes[..constant.bytes.len().min(KEY_LEN)]),
+        )
+        .unwrap();
+
+        KnownConstants {
+            constants,
+            searcher,
+        }
+    })
+}
+
+/// An integer that constants consist of.
+trait Word: Copy {
+    /// Appends the little endian bytes of the word to `out`.
+    fn push_le_bytes(self, out: &mut Vec<u8>);
+
+    /// Appends the big endian bytes of the word to `out`.
+    fn push_be_bytes(self, out: &mut Vec<u8>);
+}
+
+/// Implements [`Word`] for the given integer types.
+macro_rules! impl_word {
+    ($($ty:ty),*) => {
+        $(
+            impl Word for $ty {
+                fn push_le_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn push_be_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(u16, u32, u64);
+
+/// Adds the given words as constants in little and big endian byte order.
+fn push_words(constants: &mut Vec<Constant>, name: &str, words: &[impl Word]) {
+    let mut little_endian = Vec::new();
+    let mut big_endian = Vec::new();
+    for &word in words {
+        word.push_le_bytes(&mut little_endian);
+        word.push_be_bytes(&mut big_endian);
+    }
+
+    constants.push(Constant {
+        name: format!("{name} (little endian)"),
+        bytes: little_endian,
+    });
+    constants.push(Constant {
+        name: format!("{name} (big endian)"),
+        bytes: big_endian,
+    });
+}
+
+/// Multiplies two elements of the finite field used by AES.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+
+    product
+}
+
+/// Computes the AES S-box.
+fn aes_sbox() -> [u8; 256] {
+    std::array::from_fn(|x| {
+        // the multiplicative inverse is x^254, which maps 0 to 0 as required
+        let mut inverse = 1;
+        for _ in 0..254 {
+            inverse = gf_mul(inverse, x as u8);
+        }
+
+        inverse
+            ^ inverse.rotate_left(1)
+            ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3)
+            ^ inverse.rotate_left(4)
+            ^ 0x63
+    })
+}
+
+/// Computes the lookup table of a most significant bit first CRC-32.
+fn crc32_table(polynomial: u32) -> [u32; 256] {
+    std::array::from_fn(|byte| {
+        let mut crc = (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = (crc << 1)
+                ^ if crc & 0x8000_0000 != 0 {
+                    polynomial
+                } else {
+                    0
+                };
+        }
+        crc
+    })
+}
+
+/// Computes the lookup table of a least significant bit first CRC-32.
+pub(crate) fn crc32_table_reflected(polynomial: u32) -> [u32; 256] {
+    std::array::from_fn(|byte| {
+        let mut crc = byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { polynomial } else { 0 };
+        }
+        crc
+    })
+}
+
+/// Computes the lookup table of a most significant bit first CRC-16.
+fn crc16_table(polynomial: u16) -> [u16; 256] {
+    std::array::from_fn(|byte| {
+        let mut crc = (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = (crc << 1) ^ if crc & 0x8000 != 0 { polynomial } else { 0 };
+        }
+        crc
+    })
+}
+
+/// Computes the lookup table of a least significant bit first CRC-16.
+fn crc16_table_reflected(polynomial: u16) -> [u16; 256] {
+    std::array::from_fn(|byte| {
+        let mut crc = byte as u16;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { polynomial } else { 0 };
+        }
+        crc
+    })
+}
+
+/// A cryptographic constant found in the input.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantMatch {
+    /// The bytes that matched the constant.
+    pub window: Window,
+    /// The constant that was found.
+    pub constant: &'static Constant,
+}
+
+impl ConstantMatch {
+    /// Determines if the whole constant was found and not just its start.
+    pub fn is_complete(&self) -> bool {
+        self.window.size().as_u64() == self.constant.bytes.len() as u64
+    }
+}
+
+/// Scans the input for cryptographic constants.
+pub struct ConstantScanner {
+    /// The input that is scanned.
+    input: Input,
+    /// The start of the next chunk that is scanned.
+    chunk_start: AbsoluteOffset,
+    /// The buffer that chunks are read into.
+    buf: Vec<u8>,
+    /// The buffer that the rest of long constants are read into.
+    rest_buf: Vec<u8>,
+}
+
+impl ConstantScanner {
+    /// Creates a scanner for the given input.
+    pub fn new(input: Input) -> ConstantScanner {
+        ConstantScanner {
+            input,
+            chunk_start: AbsoluteOffset::ZERO,
+            buf: Vec::new(),
+            rest_buf: Vec::new(),
+        }
+    }
+
+    /// Scans the next chunk of the input and returns all constants that start in it.
+    ///
+    /// Returns `None` once the whole input was scanned.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Vec<ConstantMatch>>> {
+        let end = AbsoluteOffset::ZERO + self.input.len();
+        if self.chunk_start >= end {
+            return Ok(None);
+        }
+
+        let known = known();
+        let chunk = self.input.read_at(
+            self.chunk_start,
+            Len::from(SCAN_CHUNK_SIZE + KEY_LEN as u64 - 1),
+            Some(&mut self.buf),
+        )?;
+
+        let mut matches = Vec::new();
+        for found in known.searcher.find_overlapping_iter(&*chunk) {
+            if found.start() as u64 >= SCAN_CHUNK_SIZE {
+                continue;
+            }
+
+            let constant = &known.constants[found.pattern().as_usize()];
+            let start = self.chunk_start + Len::from(found.start() as u64);
+            let mut len = found.len();
+
+            // compare the rest of constants that are longer than what is searched for
+            if constant.bytes.len() > len {
+                let rest = self.input.read_at(
+                    start + Len::from(len as u64),
+                    Len::from((constant.bytes.len() - len) as u64),
+                    Some(&mut self.rest_buf),
+                )?;
+                len += rest
+                    .iter()
+                    .zip(&constant.bytes[len..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            }
+
+            matches.push(ConstantMatch {
+                window: Window::from_start_len(start, Len::from(len as u64)),
+                constant,
+            });
+        }
+
+        self.chunk_start += Len::from(SCAN_CHUNK_SIZE);
+
+        Ok(Some(matches))
+    }
+
+    /// The fraction of the input that was scanned so far.
+    pub fn progress(&self) -> f32 {
+        if self.input.len().is_zero() {
+            return 1.0;
+        }
+
+        (self.chunk_start.as_u64() as f64 / self.input.len().as_u64() as f64).min(1.0) as f32
+    }
+}
+
+/// Finds all cryptographic constants in the input.
+pub fn find_constants(input: Input) -> io::Result<Vec<ConstantMatch>> {
+    let mut scanner = ConstantScanner::new(input);
+    let mut matches = Vec::new();
+    while let Some(chunk_matches) = scanner.next_chunk()? {
+        matches.extend(chunk_matches);
+    }
+
+    Ok(matches)
+}
+
+/// The progress of a scan that happens in the background.
+struct ScanProgress {
+    /// The fraction of the input that was scanned so far.
+    progress: f32,
+    /// The constants found, but not yet taken.
+    matches: Vec<ConstantMatch>,
+    /// Whether the scan is finished.
+    finished: bool,
+    /// The error that stopped the scan, if any.
+    error: Option<io::Error>,
+}
+
+/// A handle to a scan for cryptographic constants that happens in the background.
+///
+/// Dropping the handle cancels the scan.
+pub struct ConstantScanHandle {
+    /// The progress shared with the background worker.
+    shared: Arc<Mutex<ScanProgress>>,
+}
+
+impl ConstantScanHandle {
+    /// Starts scanning the given input in the background.
+    pub fn start(input: Input) -> ConstantScanHandle {
+        let shared = Arc::new(Mutex::new(ScanProgress {
+            progress: 0.0,
+            matches: Vec::new(),
+            finished: false,
+            error: None,
+        }));
+
+        worker::spawn(ConstantScanWorker {
+            scanner: ConstantScanner::new(input),
+            shared: Arc::clone(&shared),
+        });
+
+        ConstantScanHandle { shared }
+    }
+
+    /// The fraction of the input that was scanned so far.
+    pub fn progress(&self) -> f32 {
+        self.shared.lock().unwrap().progress
+    }
+
+    /// Determines if the scan is finished.
+    pub fn is_finished(&self) -> bool {
+        self.shared.lock().unwrap().finished
+    }
+
+    /// Takes the constants found since the last call.
+    pub fn take_matches(&self) -> Vec<ConstantMatch> {
+        std::mem::take(&mut self.shared.lock().unwrap().matches)
+    }
+
+    /// Takes the error that stopped the scan, if any.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.shared.lock().unwrap().error.take()
+    }
+}
+
+/// Scans an input for cryptographic constants in the background.
+struct ConstantScanWorker {
+    /// The scanner that does the work.
+    scanner: ConstantScanner,
+    /// The progress shared with the handle.
+    shared: Arc<Mutex<ScanProgress>>,
+}
+
+impl Worker for ConstantScanWorker {
+    fn step(&mut self, _: bool) -> WorkerStatus {
+        // the handle was dropped, so nobody is interested in the result anymore
+        if Arc::strong_count(&self.shared) == 1 {
+            return WorkerStatus::Terminated;
+        }
+
+        let result = self.scanner.next_chunk();
+        let mut shared = self.shared.lock().unwrap();
+        shared.progress = self.scanner.progress();
+        match result {
+            Ok(Some(matches)) => {
+                shared.matches.extend(matches);
+                WorkerStatus::Busy
+            }
+            Ok(None) => {
+                shared.finished = true;
+                WorkerStatus::Terminated
+            }
+            Err(err) => {
+                shared.error = Some(err);
+                shared.finished = true;
+                WorkerStatus::Terminated
+            }
+        }
+    }
+}
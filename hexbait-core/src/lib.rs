@@ -0,0 +1,18 @@
+//! Implements the analysis engine of hexbait without any GUI dependencies.
+//!
+//! This includes searching, statistics and classification, identifying file types, carving
+//! embedded files, finding cryptographic constants and creating patches, as well as parsing the
+//! input with format descriptions.
+
+#![forbid(unsafe_code)]
+
+pub mod cache;
+pub mod carve;
+pub mod crypto_constants;
+pub mod magic;
+pub mod parse;
+pub mod patch;
+pub mod search;
+pub mod statistics;
+pub mod window;
+pub mod worker;
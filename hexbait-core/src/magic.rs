@@ -0,0 +1,304 @@
+//! Implements identification of file types by their magic numbers.
+//!
+//! The signatures known to [`infer`] are extended by rules for formats that are common in
+//! firmware and disk images, such as file systems, partition tables and boot images.
+
+use std::{io, sync::OnceLock};
+
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use infer::Infer;
+
+/// The number of bytes that are made available to the file type detection at each offset.
+pub const MAGIC_LOOKAHEAD: u64 = 8 * 1024;
+
+/// The size of the chunks in which the input is scanned for signatures.
+const SCAN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Determines if a buffer starts with a file of a specific format.
+type Matcher = fn(&[u8]) -> bool;
+
+/// The matchers that extend the signatures known to [`infer`].
+///
+/// Each matcher is given together with the MIME type and the file extension of its format.
+const EXTENDED_MATCHERS: &[(&str, &str, Matcher)] = &[
+    ("application/x-squashfs", "sqsh", is_squashfs),
+    ("application/x-uboot-image", "uimage", is_uboot_image),
+    ("application/x-devicetree", "dtb", is_devicetree),
+    ("application/x-ubi-image", "ubi", is_ubi_image),
+    (
+        "application/x-android-boot-image",
+        "img",
+        is_android_boot_image,
+    ),
+    ("application/x-luks", "luks", is_luks),
+    ("application/x-qemu-disk", "qcow2", is_qcow),
+    ("application/x-vmdk", "vmdk", is_vmdk),
+    ("application/x-vhdx", "vhdx", is_vhdx),
+    ("application/x-gpt", "gpt", is_gpt),
+    ("application/x-ntfs", "ntfs", is_ntfs),
+    ("application/x-ext4", "ext4", is_ext4),
+    ("application/x-fat32", "fat32", is_fat32),
+    ("application/x-exfat", "exfat", is_exfat),
+    ("application/x-bitlocker", "bitlocker", is_bitlocker),
+    ("application/x-ms-shortcut", "lnk", is_lnk),
+    ("application/vnd.tcpdump.pcap", "pcap", is_pcap),
+    ("application/x-pcapng", "pcapng", is_pcapng),
+];
+
+/// The built-in format descriptions that describe files with the given extension.
+const BUILTIN_PARSERS: &[(&str, &str)] = &[
+    ("elf", "elf"),
+    ("mach", "macho"),
+    ("jpg", "jpeg"),
+    ("png", "png"),
+    ("sqlite", "sqlite"),
+    ("lnk", "lnk"),
+    ("pcap", "pcap"),
+    ("pcapng", "pcapng"),
+    ("exe", "pe"),
+    ("dll", "pe"),
+    ("vmdk", "vmdk_header"),
+    ("gpt", "efi_partition_header"),
+    ("ntfs", "ntfs_header"),
+    ("ext4", "ext4"),
+    ("fat32", "fat32"),
+    ("exfat", "exfat"),
+    ("bitlocker", "bitlocker_header"),
+];
+
+/// Returns the identification engine, building it on first use.
+fn infer() -> &'static Infer {
+    static INFER: OnceLock<Infer> = OnceLock::new();
+
+    INFER.get_or_init(|| {
+        let mut infer = Infer::new();
+        for &(mime_type, extension, matcher) in EXTENDED_MATCHERS {
+            infer.add(mime_type, extension, matcher);
+        }
+
+        infer
+    })
+}
+
+/// Determines if `bytes` contain `magic` at the given offset.
+fn has_magic_at(bytes: &[u8], offset: usize, magic: &[u8]) -> bool {
+    bytes.get(offset..offset + magic.len()) == Some(magic)
+}
+
+/// Determines if the bytes start a SquashFS file system in either byte order.
+fn is_squashfs(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"hsqs") || has_magic_at(bytes, 0, b"sqsh")
+}
+
+/// Determines if the bytes start a legacy U-Boot image.
+fn is_uboot_image(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, &[0x27, 0x05, 0x19, 0x56])
+}
+
+/// Determines if the bytes start a flattened device tree.
+fn is_devicetree(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, &[0xd0, 0x0d, 0xfe, 0xed])
+}
+
+/// Determines if the bytes start an UBI image.
+fn is_ubi_image(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"UBI#")
+}
+
+/// Determines if the bytes start an Android boot image.
+fn is_android_boot_image(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"ANDROID!")
+}
+
+/// Determines if the bytes start a LUKS encrypted volume.
+fn is_luks(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"LUKS\xba\xbe")
+}
+
+/// Determines if the bytes start a QEMU copy on write disk image.
+fn is_qcow(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"QFI\xfb")
+}
+
+/// Determines if the bytes start a sparse VMDK extent.
+fn is_vmdk(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"KDMV")
+}
+
+/// Determines if the bytes start a VHDX disk image.
+fn is_vhdx(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"vhdxfile")
+}
+
+/// Determines if the bytes start a GUID partition table header.
+fn is_gpt(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, b"EFI PART")
+}
+
+/// Determines if the bytes start an NTFS boot sector.
+fn is_ntfs(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 3, b"NTFS    ")
+}
+
+/// Determines if the bytes start an ext2, ext3 or ext4 file system.
+fn is_ext4(bytes: &[u8]) -> bool {
+    // the two byte magic number is short, so the block size is checked to be at most 64 KiB too
+    has_magic_at(bytes, 0x438, &[0x53, 0xef])
+        && bytes.get(0x418..0x41c).is_some_and(|log_block_size| {
+            u32::from_le_bytes(log_block_size.try_into().unwrap()) <= 6
+        })
+}
+
+/// Determines if the bytes start a FAT32 boot sector.
+fn is_fat32(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 82, b"FAT32   ") && has_magic_at(bytes, 510, &[0x55, 0xaa])
+}
+
+/// Determines if the bytes start an exFAT boot sector.
+fn is_exfat(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 3, b"EXFAT   ")
+}
+
+/// Determines if the bytes start a BitLocker encrypted volume.
+fn is_bitlocker(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 3, b"-FVE-FS-")
+}
+
+/// Determines if the bytes start a Windows shortcut.
+fn is_lnk(bytes: &[u8]) -> bool {
+    // the size of the header followed by the class identifier of shell links
+    has_magic_at(
+        bytes,
+        0,
+        b"\x4c\0\0\0\x01\x14\x02\0\0\0\0\0\xc0\0\0\0\0\0\0\x46",
+    )
+}
+
+/// Determines if the bytes start a pcap capture file in either byte order.
+fn is_pcap(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, &[0xd4, 0xc3, 0xb2, 0xa1])
+        || has_magic_at(bytes, 0, &[0xa1, 0xb2, 0xc3, 0xd4])
+}
+
+/// Determines if the bytes start a pcapng capture file in either byte order.
+fn is_pcapng(bytes: &[u8]) -> bool {
+    has_magic_at(bytes, 0, &[0x0a, 0x0d, 0x0d, 0x0a])
+        && (has_magic_at(bytes, 8, &[0x4d, 0x3c, 0x2b, 0x1a])
+            || has_magic_at(bytes, 8, &[0x1a, 0x2b, 0x3c, 0x4d]))
+}
+
+/// The type of a file identified by its signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileKind {
+    /// The MIME type of the file.
+    pub mime_type: &'static str,
+    /// The usual file extension of the file, without the leading dot.
+    pub extension: &'static str,
+}
+
+impl FileKind {
+    /// Identifies the file that starts with the given bytes, if it has a known signature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_core::magic::FileKind;
+    /// let kind = FileKind::identify(b"hsqs\x04\x00\x00\x00").unwrap();
+    /// assert_eq!(kind.mime_type, "application/x-squashfs");
+    /// assert_eq!(kind.extension, "sqsh");
+    /// ```
+    pub fn identify(bytes: &[u8]) -> Option<FileKind> {
+        infer().get(bytes).map(|ty| FileKind {
+            mime_type: ty.mime_type(),
+            extension: ty.extension(),
+        })
+    }
+
+    /// Identifies the file that starts at the given offset in the input.
+    pub fn identify_at(input: &Input, offset: AbsoluteOffset) -> io::Result<Option<FileKind>> {
+        let bytes = input.read_at(offset, Len::from(MAGIC_LOOKAHEAD), None)?;
+
+        Ok(FileKind::identify(&bytes))
+    }
+
+    /// The name of the built-in format description for files of this kind, if there is one.
+    pub fn builtin_parser(self) -> Option<&'static str> {
+        BUILTIN_PARSERS
+            .iter()
+            .find(|(extension, _)| *extension == self.extension)
+            .map(|&(_, parser)| parser)
+    }
+}
+
+/// A signature of a known file type found in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    /// The offset at which the signature was found.
+    pub offset: AbsoluteOffset,
+    /// The type of file that the signature belongs to.
+    pub kind: FileKind,
+}
+
+/// Scans the input for known file signatures.
+pub struct SignatureScanner<'input> {
+    /// The input that is scanned.
+    input: &'input Input,
+    /// Only offsets that are a multiple of this alignment are checked.
+    align: u64,
+    /// The start of the next chunk that is scanned.
+    chunk_start: AbsoluteOffset,
+    /// The buffer that chunks are read into.
+    buf: Vec<u8>,
+}
+
+impl<'input> SignatureScanner<'input> {
+    /// Creates a scanner that checks every offset that is a multiple of `align`.
+    pub fn new(input: &'input Input, align: u64) -> SignatureScanner<'input> {
+        SignatureScanner {
+            input,
+            align: align.max(1),
+            chunk_start: AbsoluteOffset::ZERO,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Scans the next chunk of the input and returns all signatures found in it.
+    ///
+    /// Returns `None` once the whole input was scanned.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Vec<Signature>>> {
+        let end = AbsoluteOffset::ZERO + self.input.len();
+        if self.chunk_start >= end {
+            return Ok(None);
+        }
+
+        let chunk = self.input.read_at(
+            self.chunk_start,
+            Len::from(SCAN_CHUNK_SIZE + MAGIC_LOOKAHEAD),
+            Some(&mut self.buf),
+        )?;
+        let checked_len = chunk.len().min(SCAN_CHUNK_SIZE as usize);
+        let first = (self.align - self.chunk_start.as_u64() % self.align) % self.align;
+        let signatures = (first as usize..checked_len)
+            .step_by(self.align as usize)
+            .filter_map(|i| {
+                Some(Signature {
+                    offset: self.chunk_start + Len::from(i as u64),
+                    kind: FileKind::identify(&chunk[i..])?,
+                })
+            })
+            .collect();
+
+        self.chunk_start += Len::from(SCAN_CHUNK_SIZE);
+
+        Ok(Some(signatures))
+    }
+
+    /// The fraction of the input that was scanned so far.
+    pub fn progress(&self) -> f32 {
+        if self.input.len().is_zero() {
+            return 1.0;
+        }
+
+        (self.chunk_start.as_u64() as f64 / self.input.len().as_u64() as f64).min(1.0) as f32
+    }
+}
@@ -0,0 +1,222 @@
+//! Implements parsing of the input using format descriptions.
+
+use std::{
+    borrow::Cow,
+    fmt, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use hexbait_common::{AbsoluteOffset, Input, RelativeOffset};
+use hexbait_lang::{
+    CancellationToken, EvalOptions, ParseResult, View, check_ir, eval_ir_with_options,
+    ir::{AnalysisError, File, ImportError, ImportResolver, SourceLocation, resolve_imports},
+};
+
+use crate::worker::{self, Worker, WorkerStatus};
+
+/// The type of parser to use.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseType {
+    /// Use no parser.
+    None,
+    /// Use a built-in parser.
+    Builtin(&'static str),
+    /// Use a custom parser.
+    Custom(PathBuf),
+}
+
+impl ParseType {
+    /// Returns a string representation of the parse type.
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self {
+            ParseType::None => Cow::Borrowed("none"),
+            ParseType::Builtin(name) => Cow::Borrowed(name),
+            ParseType::Custom(path_buf) => {
+                if let Some(file_name) = path_buf.file_name() {
+                    match file_name.to_string_lossy() {
+                        Cow::Borrowed(name) => Cow::Borrowed(
+                            name.strip_suffix(".hbl")
+                                .or_else(|| name.strip_suffix(".hexpat"))
+                                .unwrap_or(name),
+                        ),
+                        Cow::Owned(name) => {
+                            if let Some(name) = name
+                                .strip_suffix(".hbl")
+                                .or_else(|| name.strip_suffix(".hexpat"))
+                            {
+                                Cow::Owned(String::from(name))
+                            } else {
+                                Cow::Owned(name)
+                            }
+                        }
+                    }
+                } else {
+                    path_buf.to_string_lossy()
+                }
+            }
+        }
+    }
+}
+
+/// An error that occurred while loading a format description.
+#[derive(Debug)]
+pub enum LoadParserError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file contains syntax errors.
+    Syntax,
+    /// The files imported by the format description could not be loaded.
+    Import(ImportError),
+    /// The format description is not well formed.
+    Analysis(AnalysisError),
+}
+
+impl fmt::Display for LoadParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadParserError::Io(err) => write!(f, "{err}"),
+            LoadParserError::Syntax => write!(f, "the format description contains syntax errors"),
+            LoadParserError::Import(err) => write!(f, "{err}"),
+            LoadParserError::Analysis(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadParserError {}
+
+/// Loads the format description at the given path and lowers it to IR.
+///
+/// The files it imports are found using the given resolver.
+pub fn load_parser(
+    path: impl AsRef<std::path::Path>,
+    resolver: &impl ImportResolver,
+) -> Result<File, LoadParserError> {
+    let path = path.as_ref();
+    let mut content = std::fs::read_to_string(path).map_err(LoadParserError::Io)?;
+    if is_hexpat(path) {
+        let translation = hexbait_lang::translate_hexpat(&content);
+        for problem in &translation.unsupported {
+            tracing::debug!(path = %path.display(), "cannot translate ImHex pattern: {}", problem.inner);
+        }
+        content = translation.hbl;
+    }
+    let parse = hexbait_lang::parse(&content);
+    if !parse.errors.is_empty() {
+        return Err(LoadParserError::Syntax);
+    }
+
+    let mut file = hexbait_lang::ir::lower_file(parse.ast);
+    let location = SourceLocation::Path(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+    resolve_imports(&mut file, &location, resolver).map_err(LoadParserError::Import)?;
+    check_ir(&file).map_err(LoadParserError::Analysis)?;
+
+    Ok(file)
+}
+
+/// Determines if the file at the path is an ImHex pattern instead of a format description.
+///
+/// ImHex patterns are translated to format descriptions when they are loaded.
+pub fn is_hexpat(path: &std::path::Path) -> bool {
+    path.extension() == Some("hexpat".as_ref())
+}
+
+/// Parses the input at the given offset with the given format description.
+pub fn parse_input(parser: &File, input: &Input, offset: AbsoluteOffset) -> ParseResult {
+    hexbait_lang::eval_ir(parser, view_at(input, offset), RelativeOffset::ZERO)
+}
+
+/// Returns a view of the input starting at the given offset.
+fn view_at(input: &Input, offset: AbsoluteOffset) -> View {
+    let view = View::from_input(input.clone());
+
+    view.subview(offset.to_relative()..view.len().to_relative())
+}
+
+/// A handle to parsing that happens in the background.
+///
+/// Dropping the handle cancels parsing.
+pub struct ParseHandle {
+    /// The result shared with the background worker, once parsing is finished.
+    result: Arc<Mutex<Option<ParseResult>>>,
+    /// Cancels the parsing.
+    cancellation: CancellationToken,
+}
+
+impl ParseHandle {
+    /// Starts parsing the input at the given offset with the given format description in the
+    /// background.
+    ///
+    /// The parsing can be cancelled using the handle as well as the cancellation token of the
+    /// options.
+    pub fn start(
+        parser: Arc<File>,
+        input: &Input,
+        offset: AbsoluteOffset,
+        options: EvalOptions,
+    ) -> ParseHandle {
+        let result = Arc::new(Mutex::new(None));
+        let cancellation = options.cancellation.clone();
+
+        worker::spawn(ParseWorker {
+            parser,
+            view: view_at(input, offset),
+            options,
+            result: Arc::clone(&result),
+        });
+
+        ParseHandle {
+            result,
+            cancellation,
+        }
+    }
+
+    /// Cancels parsing.
+    ///
+    /// The values that were parsed until then are still delivered as the result.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Takes the result once parsing is finished.
+    pub fn take_result(&self) -> Option<ParseResult> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+impl Drop for ParseHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Parses an input in the background.
+struct ParseWorker {
+    /// The format description to parse with.
+    parser: Arc<File>,
+    /// The view of the input to parse.
+    view: View,
+    /// The options of the parsing.
+    options: EvalOptions,
+    /// The result shared with the handle.
+    result: Arc<Mutex<Option<ParseResult>>>,
+}
+
+impl Worker for ParseWorker {
+    fn step(&mut self, _: bool) -> WorkerStatus {
+        // the handle was dropped, so nobody is interested in the result anymore
+        if Arc::strong_count(&self.result) == 1 {
+            return WorkerStatus::Terminated;
+        }
+
+        let result = eval_ir_with_options(
+            &self.parser,
+            self.view.clone(),
+            RelativeOffset::ZERO,
+            self.options.clone(),
+        );
+        *self.result.lock().unwrap() = Some(result);
+
+        WorkerStatus::Terminated
+    }
+}
@@ -0,0 +1,584 @@
+//! Implements creating and applying patches that describe the modifications of a file.
+//!
+//! Patches can be stored in the IPS and BPS formats understood by many patching tools, or in a
+//! simple JSON format that lists the offset, the original bytes and the new bytes of every
+//! modification, which is easy to review.
+
+use std::{borrow::Cow, fmt, path::Path, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_constants::crc32_table_reflected;
+
+/// The magic bytes at the start of IPS patches.
+const IPS_HEADER: &[u8] = b"PATCH";
+
+/// The marker at the end of IPS patches.
+const IPS_FOOTER: &[u8] = b"EOF";
+
+/// The largest offset that can be represented in IPS patches.
+const IPS_MAX_OFFSET: u64 = 0xff_ffff;
+
+/// The largest number of bytes in a single IPS record.
+const IPS_MAX_RECORD_LEN: usize = 0xffff;
+
+/// The magic bytes at the start of BPS patches.
+const BPS_HEADER: &[u8] = b"BPS1";
+
+/// The length of the checksums at the end of BPS patches.
+const BPS_FOOTER_LEN: usize = 12;
+
+/// The version of the JSON patch format.
+const JSON_VERSION: u32 = 1;
+
+/// A format in which patches can be stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// The International Patching System format.
+    ///
+    /// IPS patches can only describe modifications in the first 16 MiB of a file.
+    Ips,
+    /// The beat patching format.
+    Bps,
+    /// A JSON file listing the original and the new bytes of every modification.
+    Json,
+}
+
+impl PatchFormat {
+    /// All supported patch formats.
+    pub const ALL: [PatchFormat; 3] = [PatchFormat::Ips, PatchFormat::Bps, PatchFormat::Json];
+
+    /// The name of the format, which is also the usual file extension of patches in it.
+    pub fn name(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Bps => "bps",
+            PatchFormat::Json => "json",
+        }
+    }
+
+    /// Returns the format with the given name.
+    pub fn from_name(name: &str) -> Option<PatchFormat> {
+        PatchFormat::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the format of the patch file at the given path, based on its extension.
+    pub fn from_path(path: &Path) -> Option<PatchFormat> {
+        PatchFormat::from_name(path.extension()?.to_str()?)
+    }
+}
+
+/// An error that occurred while creating or applying a patch.
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch is malformed.
+    Invalid(Cow<'static, str>),
+    /// The patch does not belong to the file it is applied to.
+    SourceMismatch(Cow<'static, str>),
+    /// The modifications cannot be represented in the requested format.
+    Unrepresentable(Cow<'static, str>),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Invalid(msg) => write!(f, "invalid patch: {msg}"),
+            PatchError::SourceMismatch(msg) => {
+                write!(f, "the patch does not match the file: {msg}")
+            }
+            PatchError::Unrepresentable(msg) => write!(f, "cannot create the patch: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl From<serde_json::Error> for PatchError {
+    fn from(err: serde_json::Error) -> Self {
+        PatchError::Invalid(Cow::Owned(err.to_string()))
+    }
+}
+
+/// A single modification of consecutive bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    /// The offset of the first modified byte.
+    pub offset: u64,
+    /// The bytes before the modification.
+    ///
+    /// This is shorter than the new bytes if the modification extends the file.
+    #[serde(with = "hex_bytes")]
+    pub original: Vec<u8>,
+    /// The bytes after the modification.
+    #[serde(with = "hex_bytes")]
+    pub new: Vec<u8>,
+}
+
+/// All modifications that turn a file into a modified version of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Patch {
+    /// The version of the JSON patch format.
+    pub version: u32,
+    /// The length of the original file.
+    pub source_len: u64,
+    /// The length of the modified file.
+    ///
+    /// If this is smaller than the length of the original file, the file is truncated.
+    pub target_len: u64,
+    /// The modifications, ordered by their offset.
+    pub changes: Vec<Change>,
+}
+
+impl Patch {
+    /// Computes the modifications that turn `source` into `target`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_core::patch::Patch;
+    /// let patch = Patch::diff(b"hello world", b"hello there!");
+    /// assert_eq!(patch.changes.len(), 2);
+    /// assert_eq!(patch.changes[0].offset, 6);
+    /// assert_eq!(patch.changes[0].original, b"world");
+    /// assert_eq!(patch.changes[0].new, b"there");
+    /// assert_eq!(patch.changes[1].new, b"!");
+    /// assert_eq!(patch.apply(b"hello world").unwrap(), b"hello there!");
+    /// ```
+    pub fn diff(source: &[u8], target: &[u8]) -> Patch {
+        let mut changes = Vec::new();
+        let common_len = source.len().min(target.len());
+
+        let mut i = 0;
+        while i < common_len {
+            if source[i] == target[i] {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < common_len && source[i] != target[i] {
+                i += 1;
+            }
+            changes.push(Change {
+                offset: start as u64,
+                original: source[start..i].to_vec(),
+                new: target[start..i].to_vec(),
+            });
+        }
+
+        if target.len() > source.len() {
+            changes.push(Change {
+                offset: source.len() as u64,
+                original: Vec::new(),
+                new: target[source.len()..].to_vec(),
+            });
+        }
+
+        Patch {
+            version: JSON_VERSION,
+            source_len: source.len() as u64,
+            target_len: target.len() as u64,
+            changes,
+        }
+    }
+
+    /// Applies the modifications to `source`, returning the modified file.
+    ///
+    /// This fails if `source` is not the file the patch was created for.
+    pub fn apply(&self, source: &[u8]) -> Result<Vec<u8>, PatchError> {
+        if source.len() as u64 != self.source_len {
+            return Err(PatchError::SourceMismatch(Cow::Owned(format!(
+                "expected {} bytes, but the file has {} bytes",
+                self.source_len,
+                source.len()
+            ))));
+        }
+
+        let mut target = source.to_vec();
+        for change in &self.changes {
+            let start = usize::try_from(change.offset)
+                .map_err(|_| PatchError::Invalid(Cow::Borrowed("offset out of range")))?;
+            let original_end = start + change.original.len();
+            if source.get(start..original_end) != Some(&change.original[..]) {
+                return Err(PatchError::SourceMismatch(Cow::Owned(format!(
+                    "the original bytes at offset {start:#x} differ"
+                ))));
+            }
+
+            let end = start + change.new.len();
+            if target.len() < end {
+                target.resize(end, 0);
+            }
+            target[start..end].copy_from_slice(&change.new);
+        }
+        target.truncate(self.target_len as usize);
+        target.resize(self.target_len as usize, 0);
+
+        Ok(target)
+    }
+}
+
+/// Creates a patch in the given format that turns `source` into `target`.
+pub fn create(format: PatchFormat, source: &[u8], target: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match format {
+        PatchFormat::Ips => create_ips(&Patch::diff(source, target), target),
+        PatchFormat::Bps => Ok(create_bps(source, target)),
+        PatchFormat::Json => Ok(serde_json::to_vec_pretty(&Patch::diff(source, target))?),
+    }
+}
+
+/// Applies a patch in the given format to `source`, returning the modified file.
+pub fn apply(format: PatchFormat, source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match format {
+        PatchFormat::Ips => apply_ips(source, patch),
+        PatchFormat::Bps => apply_bps(source, patch),
+        PatchFormat::Json => {
+            let patch: Patch = serde_json::from_slice(patch)?;
+            if patch.version > JSON_VERSION {
+                return Err(PatchError::Invalid(Cow::Owned(format!(
+                    "unsupported version {}",
+                    patch.version
+                ))));
+            }
+
+            patch.apply(source)
+        }
+    }
+}
+
+/// Encodes the modifications as an IPS patch.
+///
+/// `target` is the modified file, which is needed to avoid records at ambiguous offsets.
+fn create_ips(patch: &Patch, target: &[u8]) -> Result<Vec<u8>, PatchError> {
+    // an offset that reads as "EOF" would end the patch early
+    const EOF_OFFSET: u64 = 0x45_4f46;
+
+    let mut out = IPS_HEADER.to_vec();
+    for change in &patch.changes {
+        let mut offset = change.offset;
+        let end = change.offset + change.new.len() as u64;
+        if end - 1 > IPS_MAX_OFFSET {
+            return Err(PatchError::Unrepresentable(Cow::Borrowed(
+                "IPS patches cannot modify bytes after the first 16 MiB",
+            )));
+        }
+
+        while offset < end {
+            if offset == EOF_OFFSET {
+                // start one byte earlier, which is either unchanged or rewritten with the same value
+                offset -= 1;
+            }
+            let len = (end - offset).min(IPS_MAX_RECORD_LEN as u64);
+
+            out.extend_from_slice(&offset.to_be_bytes()[5..]);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+            out.extend_from_slice(&target[offset as usize..(offset + len) as usize]);
+
+            offset += len;
+        }
+    }
+    out.extend_from_slice(IPS_FOOTER);
+
+    if patch.target_len < patch.source_len {
+        if patch.target_len > IPS_MAX_OFFSET {
+            return Err(PatchError::Unrepresentable(Cow::Borrowed(
+                "IPS patches cannot truncate files to more than 16 MiB",
+            )));
+        }
+        out.extend_from_slice(&patch.target_len.to_be_bytes()[5..]);
+    }
+
+    Ok(out)
+}
+
+/// Applies an IPS patch to `source`.
+fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(
+        patch
+            .strip_prefix(IPS_HEADER)
+            .ok_or(PatchError::Invalid(Cow::Borrowed("missing IPS header")))?,
+    );
+
+    let mut target = source.to_vec();
+    // the footer may be followed by the length to truncate the file to
+    while !(reader.rest().starts_with(IPS_FOOTER) && matches!(reader.rest().len(), 3 | 6)) {
+        let offset = reader.be_uint(3)? as usize;
+        let len = reader.be_uint(2)? as usize;
+        if len == 0 {
+            // a record without length is a run of the same byte
+            let end = offset + reader.be_uint(2)? as usize;
+            let value = reader.bytes(1)?[0];
+            if target.len() < end {
+                target.resize(end, 0);
+            }
+            target[offset..end].fill(value);
+        } else {
+            let end = offset + len;
+            let data = reader.bytes(len)?;
+            if target.len() < end {
+                target.resize(end, 0);
+            }
+            target[offset..end].copy_from_slice(data);
+        }
+    }
+
+    reader.bytes(IPS_FOOTER.len())?;
+    if !reader.rest().is_empty() {
+        target.truncate(reader.be_uint(3)? as usize);
+    }
+
+    Ok(target)
+}
+
+/// Encodes a BPS patch that turns `source` into `target`.
+///
+/// Bytes that are unchanged are read from the source and all other bytes are stored in the patch.
+fn create_bps(source: &[u8], target: &[u8]) -> Vec<u8> {
+    /// The action that reads bytes from the source at the same offset.
+    const SOURCE_READ: u64 = 0;
+    /// The action that reads bytes from the patch.
+    const TARGET_READ: u64 = 1;
+
+    let mut out = BPS_HEADER.to_vec();
+    push_bps_number(&mut out, source.len() as u64);
+    push_bps_number(&mut out, target.len() as u64);
+    // no metadata
+    push_bps_number(&mut out, 0);
+
+    let unchanged = |i: usize| source.get(i) == Some(&target[i]);
+    let mut i = 0;
+    while i < target.len() {
+        let start = i;
+        let is_unchanged = unchanged(i);
+        while i < target.len() && unchanged(i) == is_unchanged {
+            i += 1;
+        }
+
+        let action = if is_unchanged {
+            SOURCE_READ
+        } else {
+            TARGET_READ
+        };
+        push_bps_number(&mut out, ((i - start - 1) as u64) << 2 | action);
+        if !is_unchanged {
+            out.extend_from_slice(&target[start..i]);
+        }
+    }
+
+    out.extend_from_slice(&crc32(source).to_le_bytes());
+    out.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc = crc32(&out);
+    out.extend_from_slice(&patch_crc.to_le_bytes());
+
+    out
+}
+
+/// Applies a BPS patch to `source`.
+fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let invalid = |msg| PatchError::Invalid(Cow::Borrowed(msg));
+
+    if patch.len() < BPS_HEADER.len() + BPS_FOOTER_LEN || !patch.starts_with(BPS_HEADER) {
+        return Err(invalid("missing BPS header"));
+    }
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER_LEN);
+    let checksum = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != checksum(8) {
+        return Err(invalid("the checksum of the patch is wrong"));
+    }
+
+    let mut reader = Reader::new(&body[BPS_HEADER.len()..]);
+    let source_len = reader.bps_number()?;
+    let target_len = reader.bps_number()?;
+    let metadata_len = reader.bps_number()?;
+    reader.bytes(usize::try_from(metadata_len).map_err(|_| invalid("metadata too large"))?)?;
+
+    if source.len() as u64 != source_len || crc32(source) != checksum(0) {
+        return Err(PatchError::SourceMismatch(Cow::Borrowed(
+            "the length or checksum of the file differs",
+        )));
+    }
+
+    let target_len = usize::try_from(target_len).map_err(|_| invalid("target too large"))?;
+    let mut target = Vec::with_capacity(target_len);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while !reader.rest().is_empty() {
+        let command = reader.bps_number()?;
+        let len = usize::try_from((command >> 2) + 1).map_err(|_| invalid("length too large"))?;
+        if target.len() + len > target_len {
+            return Err(invalid("the patch writes past the end of the target"));
+        }
+
+        match command & 3 {
+            // source read
+            0 => {
+                let start = target.len();
+                let bytes = source
+                    .get(start..start + len)
+                    .ok_or(invalid("source read out of bounds"))?;
+                target.extend_from_slice(bytes);
+            }
+            // target read
+            1 => target.extend_from_slice(reader.bytes(len)?),
+            // source copy
+            2 => {
+                source_offset = reader.bps_offset(source_offset)?;
+                let bytes = source
+                    .get(source_offset..source_offset + len)
+                    .ok_or(invalid("source copy out of bounds"))?;
+                target.extend_from_slice(bytes);
+                source_offset += len;
+            }
+            // target copy, which may overlap with the bytes it writes
+            _ => {
+                target_offset = reader.bps_offset(target_offset)?;
+                for _ in 0..len {
+                    let byte = *target
+                        .get(target_offset)
+                        .ok_or(invalid("target copy out of bounds"))?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_len || crc32(&target) != checksum(4) {
+        return Err(invalid("the result has the wrong length or checksum"));
+    }
+
+    Ok(target)
+}
+
+/// Appends a number in the variable length encoding of BPS patches.
+fn push_bps_number(out: &mut Vec<u8>, mut number: u64) {
+    loop {
+        let low = (number & 0x7f) as u8;
+        number >>= 7;
+        if number == 0 {
+            out.push(0x80 | low);
+            break;
+        }
+        out.push(low);
+        number -= 1;
+    }
+}
+
+/// Computes the CRC-32 checksum used by BPS patches.
+fn crc32(bytes: &[u8]) -> u32 {
+    static TABLE: OnceLock<[u32; 256]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| crc32_table_reflected(0xedb88320));
+
+    !bytes.iter().fold(!0, |crc, &byte| {
+        table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Reads the fields of a patch.
+struct Reader<'patch> {
+    /// The bytes that were not read yet.
+    rest: &'patch [u8],
+}
+
+impl<'patch> Reader<'patch> {
+    /// Creates a reader for the given bytes.
+    fn new(bytes: &'patch [u8]) -> Reader<'patch> {
+        Reader { rest: bytes }
+    }
+
+    /// The bytes that were not read yet.
+    fn rest(&self) -> &'patch [u8] {
+        self.rest
+    }
+
+    /// Reads the given number of bytes.
+    fn bytes(&mut self, len: usize) -> Result<&'patch [u8], PatchError> {
+        if self.rest.len() < len {
+            return Err(PatchError::Invalid(Cow::Borrowed(
+                "unexpected end of patch",
+            )));
+        }
+        let (bytes, rest) = self.rest.split_at(len);
+        self.rest = rest;
+
+        Ok(bytes)
+    }
+
+    /// Reads a big endian unsigned integer of the given number of bytes.
+    fn be_uint(&mut self, len: usize) -> Result<u64, PatchError> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    /// Reads a number in the variable length encoding of BPS patches.
+    fn bps_number(&mut self) -> Result<u64, PatchError> {
+        let mut number = 0u64;
+        let mut shift = 1u64;
+        loop {
+            let byte = self.bytes(1)?[0];
+            number = (byte as u64 & 0x7f)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or(PatchError::Invalid(Cow::Borrowed("number too large")))?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or(PatchError::Invalid(Cow::Borrowed("number too large")))?;
+            number = number
+                .checked_add(shift)
+                .ok_or(PatchError::Invalid(Cow::Borrowed("number too large")))?;
+        }
+    }
+
+    /// Reads a signed relative offset of BPS patches and applies it to `offset`.
+    fn bps_offset(&mut self, offset: usize) -> Result<usize, PatchError> {
+        let data = self.bps_number()?;
+        let distance = usize::try_from(data >> 1)
+            .map_err(|_| PatchError::Invalid(Cow::Borrowed("offset out of range")))?;
+        let offset = if data & 1 != 0 {
+            offset.checked_sub(distance)
+        } else {
+            offset.checked_add(distance)
+        };
+
+        offset.ok_or(PatchError::Invalid(Cow::Borrowed("offset out of range")))
+    }
+}
+
+/// Serializes bytes as lowercase hex strings.
+mod hex_bytes {
+    use serde::{Deserialize as _, Deserializer, Serializer, de::Error as _};
+
+    /// Serializes the bytes as a hex string.
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(
+            &bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>(),
+        )
+    }
+
+    /// Deserializes bytes from a hex string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("hex string of odd length"));
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(|| D::Error::custom("invalid hex string"))
+            })
+            .collect()
+    }
+}
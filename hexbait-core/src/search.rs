@@ -0,0 +1,293 @@
+//! Implements search through files.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, RwLock, mpsc},
+};
+
+use hexbait_common::{Input, Len};
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::{
+    search::background::{BackgroundSearcher, SearchCommand, SearchKind, SearchRequest},
+    window::Window,
+};
+
+mod background;
+pub mod parsed;
+
+/// The encoding under which a search result was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TextEncoding {
+    /// The search bytes were matched as they are.
+    Bytes,
+    /// The search text was matched as UTF-8.
+    Utf8,
+    /// The search text was matched as Latin-1 (ISO 8859-1).
+    Latin1,
+    /// The search text was matched as little endian UTF-16.
+    Utf16Le,
+    /// The search text was matched as big endian UTF-16.
+    Utf16Be,
+}
+
+impl TextEncoding {
+    /// Returns a human readable name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextEncoding::Bytes => "raw bytes",
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Latin1 => "Latin-1",
+            TextEncoding::Utf16Le => "UTF-16LE",
+            TextEncoding::Utf16Be => "UTF-16BE",
+        }
+    }
+
+    /// Encodes the given text in this encoding.
+    ///
+    /// Returns `None` if the text cannot be represented in this encoding.
+    fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            TextEncoding::Bytes | TextEncoding::Utf8 => Some(text.as_bytes().to_vec()),
+            TextEncoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).ok())
+                .collect(),
+            TextEncoding::Utf16Le => Some(
+                text.encode_utf16()
+                    .flat_map(|code_unit| code_unit.to_le_bytes())
+                    .collect(),
+            ),
+            TextEncoding::Utf16Be => Some(
+                text.encode_utf16()
+                    .flat_map(|code_unit| code_unit.to_be_bytes())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The maximum length of a match of a regular expression.
+///
+/// Matches of regular expressions with longer or unbounded matches may be cut off at an
+/// unspecified length of at least this size.
+const MAX_REGEX_MATCH_LEN: Len = Len::from(4096);
+
+/// What to search for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchQuery {
+    /// Search for the given bytes, which may be text.
+    Text(Vec<u8>),
+    /// Search for a byte pattern, where `None` is a wildcard matching any byte.
+    Pattern(Vec<Option<u8>>),
+    /// Search for matches of a regular expression over bytes.
+    Regex(String),
+}
+
+impl SearchQuery {
+    /// Returns the text searched for, if this is a text search with valid UTF-8.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            SearchQuery::Text(bytes) => std::str::from_utf8(bytes).ok(),
+            SearchQuery::Pattern(_) | SearchQuery::Regex(_) => None,
+        }
+    }
+
+    /// Whether the query can be searched for.
+    ///
+    /// This requires at least one byte that is not a wildcard.
+    pub fn is_searchable(&self) -> bool {
+        match self {
+            SearchQuery::Text(bytes) => !bytes.is_empty(),
+            SearchQuery::Pattern(pattern) => pattern.iter().any(Option::is_some),
+            SearchQuery::Regex(regex) => !regex.is_empty(),
+        }
+    }
+}
+
+/// Builds a regular expression for searching through bytes.
+///
+/// Unicode support is disabled by default, so that `.` matches any byte (except `\n`).
+/// It can be enabled with the `(?u)` flag.
+/// Besides the regular expression, the maximum length of a match is returned, if it exists.
+pub fn build_regex(
+    pattern: &str,
+    ascii_case_insensitive: bool,
+) -> Result<(Regex, Option<usize>), String> {
+    let regex = RegexBuilder::new(pattern)
+        .unicode(false)
+        .case_insensitive(ascii_case_insensitive)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let hir = regex_syntax::ParserBuilder::new()
+        .unicode(false)
+        .utf8(false)
+        .case_insensitive(ascii_case_insensitive)
+        .build()
+        .parse(pattern)
+        .map_err(|err| err.to_string())?;
+
+    Ok((regex, hir.properties().maximum_len()))
+}
+
+/// The searcher types manages searches and reports search results.
+pub struct Searcher {
+    /// The progress of the current search.
+    progress: Arc<RwLock<f32>>,
+    /// The error that ended the current search early, if any.
+    error: Arc<Mutex<Option<String>>>,
+    /// The search results.
+    current_results: Arc<Mutex<BTreeSet<(Window, TextEncoding)>>>,
+    /// The requests for new searches to run.
+    requests: mpsc::Sender<SearchCommand>,
+    /// Whether the current search is paused.
+    paused: bool,
+}
+
+impl Searcher {
+    /// Creates a new searcher.
+    pub fn new(input: &Input) -> Searcher {
+        let background = BackgroundSearcher::start(input);
+
+        Searcher {
+            progress: background.progress,
+            error: background.error,
+            current_results: Arc::new(Mutex::new(BTreeSet::new())),
+            requests: background.requests,
+            paused: false,
+        }
+    }
+
+    /// Starts a new search.
+    ///
+    /// If `all_text_encodings` is set and `query` is valid UTF-8 text, the text is searched as
+    /// UTF-8, Latin-1, UTF-16LE and UTF-16BE at the same time.
+    pub fn start_new_search(
+        &mut self,
+        query: &SearchQuery,
+        ascii_case_insensitive: bool,
+        all_text_encodings: bool,
+        window: Window,
+    ) {
+        let kind = if let SearchQuery::Regex(pattern) = query {
+            let Ok((regex, max_match_len)) = build_regex(pattern, ascii_case_insensitive) else {
+                return;
+            };
+            let max_match_len = max_match_len
+                .and_then(|len| u64::try_from(len).ok())
+                .map(Len::from)
+                .filter(|&len| len <= MAX_REGEX_MATCH_LEN)
+                .unwrap_or(MAX_REGEX_MATCH_LEN);
+
+            SearchKind::Regex {
+                regex,
+                max_match_len,
+            }
+        } else if all_text_encodings && let Some(as_str) = query.as_text() {
+            let mut search_sequences = Vec::new();
+            for encoding in [
+                TextEncoding::Utf8,
+                TextEncoding::Latin1,
+                TextEncoding::Utf16Le,
+                TextEncoding::Utf16Be,
+            ] {
+                // pure ASCII text is the same in UTF-8 and Latin-1, so only search for it once
+                if let Some(sequence) = encoding.encode(as_str) {
+                    let sequence = sequence.into_iter().map(Some).collect();
+                    if !search_sequences
+                        .iter()
+                        .any(|(existing, _)| *existing == sequence)
+                    {
+                        search_sequences.push((sequence, encoding));
+                    }
+                }
+            }
+
+            SearchKind::Patterns {
+                content: search_sequences,
+                ascii_case_insensitive,
+            }
+        } else {
+            let sequence = match query {
+                SearchQuery::Text(bytes) => bytes.iter().copied().map(Some).collect(),
+                SearchQuery::Pattern(pattern) => pattern.clone(),
+                SearchQuery::Regex(_) => unreachable!("regular expressions are handled above"),
+            };
+
+            SearchKind::Patterns {
+                content: vec![(sequence, TextEncoding::Bytes)],
+                ascii_case_insensitive,
+            }
+        };
+
+        self.current_results = Arc::new(Mutex::new(BTreeSet::new()));
+        self.paused = false;
+        *self.error.lock().unwrap() = None;
+        // the search counts as running until the background searcher reports otherwise
+        *self.progress.write().unwrap() = 0.0;
+
+        self.requests
+            .send(SearchCommand::Start(SearchRequest {
+                kind,
+                window,
+                results: Arc::clone(&self.current_results),
+            }))
+            .unwrap();
+    }
+
+    /// Stops a currently ongoing search.
+    pub fn stop_search(&mut self) {
+        self.paused = false;
+        self.requests.send(SearchCommand::Stop).unwrap();
+    }
+
+    /// Pauses a currently ongoing search.
+    ///
+    /// The progress of the search is kept, so it can be resumed later.
+    pub fn pause_search(&mut self) {
+        if self.is_running() {
+            self.paused = true;
+            self.requests.send(SearchCommand::Pause).unwrap();
+        }
+    }
+
+    /// Resumes a paused search.
+    pub fn resume_search(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.requests.send(SearchCommand::Resume).unwrap();
+        }
+    }
+
+    /// Whether a search is currently running or paused.
+    pub fn is_running(&self) -> bool {
+        self.progress() < 1.0
+    }
+
+    /// Whether the current search is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused && self.is_running()
+    }
+
+    /// The progress of the current search.
+    pub fn progress(&self) -> f32 {
+        *self.progress.read().unwrap()
+    }
+
+    /// The error that ended the last search early, if it did not complete.
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// The current search results.
+    pub fn results(&self) -> MutexGuard<'_, BTreeSet<(Window, TextEncoding)>> {
+        self.current_results.lock().unwrap()
+    }
+}
@@ -0,0 +1,547 @@
+//! Does the actual searching on a background thread.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    sync::{
+        Arc, Mutex, RwLock,
+        mpsc::{self, RecvError, TryRecvError},
+    },
+};
+
+use aho_corasick::AhoCorasick;
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use regex::bytes::Regex;
+use web_time::Instant;
+
+use crate::{
+    search::TextEncoding,
+    window::Window,
+    worker::{self, Worker, WorkerStatus},
+};
+
+/// Contains shared state between background and foreground searcher.
+pub(crate) struct BackgroundSearcherStartResult {
+    /// The progress of the current search.
+    pub(crate) progress: Arc<RwLock<f32>>,
+    /// The error that ended the current search early, if any.
+    pub(crate) error: Arc<Mutex<Option<String>>>,
+    /// The requests for new searches to run.
+    pub(crate) requests: mpsc::Sender<SearchCommand>,
+}
+
+/// A command that the background thread receives.
+pub(crate) enum SearchCommand {
+    /// Starts a new search, replacing any previous one.
+    Start(SearchRequest),
+    /// Stops the current search.
+    Stop,
+    /// Pauses the current search, keeping its progress.
+    Pause,
+    /// Resumes a previously paused search.
+    Resume,
+}
+
+/// The search request that the background thread receives.
+pub(crate) struct SearchRequest {
+    /// What to search for.
+    pub(crate) kind: SearchKind,
+    /// The window to search.
+    pub(crate) window: Window,
+    /// The results buffer to use.
+    pub(crate) results: Arc<Mutex<BTreeSet<(Window, TextEncoding)>>>,
+}
+
+/// The different kinds of searches that can be performed.
+pub(crate) enum SearchKind {
+    /// Searches for fixed byte patterns.
+    Patterns {
+        /// The content to search for along with the encoding it represents.
+        ///
+        /// `None` bytes in the content are wildcards that match any byte.
+        content: Vec<(Vec<Option<u8>>, TextEncoding)>,
+        /// Whether to search case insensitively.
+        ascii_case_insensitive: bool,
+    },
+    /// Searches for matches of a regular expression.
+    Regex {
+        /// The regular expression to search for.
+        regex: Regex,
+        /// The maximum length of a match.
+        ///
+        /// Matches of unbounded regular expressions are cut off at this length.
+        max_match_len: Len,
+    },
+}
+
+/// The strategy of a running search.
+enum SearchStrategy {
+    /// Searches for fixed byte patterns.
+    Patterns {
+        /// The searcher looking for the longest run of non-wildcard bytes of each pattern.
+        searcher: AhoCorasick,
+        /// The patterns searched by the searcher.
+        ///
+        /// The indices correspond to the pattern IDs in the searcher.
+        patterns: Vec<SearchPattern>,
+        /// Whether to search case insensitively.
+        ascii_case_insensitive: bool,
+    },
+    /// Searches for matches of a regular expression.
+    Regex {
+        /// The regular expression to search for.
+        regex: Regex,
+    },
+}
+
+/// The search state of the background searcher.
+pub(crate) struct BackgroundSearcher {
+    /// The progress of the current search.
+    progress: Arc<RwLock<f32>>,
+    /// The error that ended the current search early, if any.
+    error: Arc<Mutex<Option<String>>>,
+    /// The search results.
+    results: Arc<Mutex<BTreeSet<(Window, TextEncoding)>>>,
+    /// The current offset at which the search happens.
+    current_offset: AbsoluteOffset,
+    /// The window that is searched.
+    search_window: Window,
+    /// The strategy performing the search itself.
+    strategy: Option<SearchStrategy>,
+    /// Whether the current search is paused.
+    paused: bool,
+    /// The size of the portion of the buffer that needs to overlap between searches.
+    overlap_size: Len,
+    /// The size of the search window.
+    search_window_size: Len,
+    /// The buffer where file contents are loaded.
+    buf: Vec<u8>,
+    /// The requests for new searches to run.
+    requests: mpsc::Receiver<SearchCommand>,
+    /// The input to read from.
+    input: Input,
+    /// The time at which the current search started.
+    search_start: Instant,
+}
+
+/// A single pattern that is searched for.
+struct SearchPattern {
+    /// The bytes of the pattern, where `None` is a wildcard.
+    bytes: Vec<Option<u8>>,
+    /// The offset of the longest run of non-wildcard bytes within the pattern.
+    ///
+    /// This run is what the searcher actually looks for, the rest of the pattern is verified
+    /// afterwards.
+    needle_offset: usize,
+    /// The encoding that the pattern represents.
+    encoding: TextEncoding,
+}
+
+impl SearchPattern {
+    /// Creates a new search pattern, returning it along with its needle.
+    fn new(bytes: Vec<Option<u8>>, encoding: TextEncoding) -> (SearchPattern, Vec<u8>) {
+        let mut best = 0..0;
+        let mut current_start = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            if byte.is_none() {
+                current_start = i + 1;
+            } else if i + 1 - current_start > best.len() {
+                best = current_start..i + 1;
+            }
+        }
+
+        let needle = bytes[best.clone()]
+            .iter()
+            .map(|byte| byte.expect("the needle contains no wildcards"))
+            .collect();
+
+        (
+            SearchPattern {
+                bytes,
+                needle_offset: best.start,
+                encoding,
+            },
+            needle,
+        )
+    }
+
+    /// Whether the pattern matches the start of the given buffer.
+    fn matches(&self, buf: &[u8], ascii_case_insensitive: bool) -> bool {
+        buf.len() >= self.bytes.len()
+            && self
+                .bytes
+                .iter()
+                .zip(buf)
+                .all(|(pattern_byte, byte)| match pattern_byte {
+                    Some(pattern_byte) if ascii_case_insensitive => {
+                        pattern_byte.eq_ignore_ascii_case(byte)
+                    }
+                    Some(pattern_byte) => pattern_byte == byte,
+                    None => true,
+                })
+    }
+}
+
+/// The minimum size of the search window for a single iteration.
+const MIN_SEARCH_WINDOW_SIZE: Len = Len::mib(1);
+
+impl BackgroundSearcher {
+    /// Starts a background searcher thread.
+    pub(crate) fn start(input: &Input) -> BackgroundSearcherStartResult {
+        let progress = Arc::new(RwLock::new(1.0));
+        let error = Arc::new(Mutex::new(None));
+        let results = Arc::new(Mutex::new(BTreeSet::new()));
+        let (sender, receiver) = mpsc::channel();
+
+        let source = input.clone();
+
+        let searcher = BackgroundSearcher {
+            progress: Arc::clone(&progress),
+            error: Arc::clone(&error),
+            results,
+            current_offset: AbsoluteOffset::ZERO,
+            search_window: Window::from_start_len(AbsoluteOffset::ZERO, input.len()),
+            strategy: None,
+            paused: false,
+            overlap_size: Len::ZERO,
+            search_window_size: Len::ZERO,
+            buf: Vec::new(),
+            requests: receiver,
+            input: source,
+            search_start: Instant::now(),
+        };
+
+        worker::spawn(searcher);
+
+        BackgroundSearcherStartResult {
+            progress,
+            error,
+            requests: sender,
+        }
+    }
+
+    /// Processes new search requests.
+    ///
+    /// A new request will always cancel previous requests.
+    /// While a search is paused, its progress is kept until it is resumed or replaced.
+    fn process_new_requests(&mut self, may_block: bool) -> WorkerStatus {
+        let is_working = self.search_is_running() && !self.paused;
+        let command = if is_working || !may_block {
+            match self.requests.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) if is_working => return WorkerStatus::Busy,
+                Err(TryRecvError::Empty) => return WorkerStatus::Idle,
+                Err(TryRecvError::Disconnected) => return WorkerStatus::Terminated,
+            }
+        } else {
+            match self.requests.recv() {
+                Ok(command) => command,
+                Err(RecvError) => return WorkerStatus::Terminated,
+            }
+        };
+
+        let request = match command {
+            SearchCommand::Start(request) => request,
+            SearchCommand::Stop => {
+                self.stop_search();
+                return WorkerStatus::Busy;
+            }
+            SearchCommand::Pause => {
+                self.paused = self.search_is_running();
+                return WorkerStatus::Busy;
+            }
+            SearchCommand::Resume => {
+                self.paused = false;
+                return WorkerStatus::Busy;
+            }
+        };
+
+        let (strategy, largest_content_size) = match request.kind {
+            SearchKind::Patterns {
+                content,
+                ascii_case_insensitive,
+            } => {
+                let largest_content_size = Len::from(
+                    content
+                        .iter()
+                        .map(|(content, _)| content.len())
+                        .max()
+                        .unwrap_or(0) as u64,
+                );
+
+                let (patterns, needles): (Vec<_>, Vec<_>) = content
+                    .into_iter()
+                    .map(|(bytes, encoding)| SearchPattern::new(bytes, encoding))
+                    .unzip();
+
+                if largest_content_size.is_zero() || needles.iter().any(|needle| needle.is_empty())
+                {
+                    self.stop_search();
+                    return WorkerStatus::Busy;
+                }
+
+                let strategy = SearchStrategy::Patterns {
+                    searcher: AhoCorasick::builder()
+                        .ascii_case_insensitive(ascii_case_insensitive)
+                        .build(&needles)
+                        .unwrap(),
+                    patterns,
+                    ascii_case_insensitive,
+                };
+
+                (strategy, largest_content_size)
+            }
+            SearchKind::Regex {
+                regex,
+                max_match_len,
+            } => (
+                SearchStrategy::Regex { regex },
+                max_match_len.max(Len::from(1)),
+            ),
+        };
+
+        tracing::debug!(
+            window = ?request.window,
+            max_match_len = largest_content_size.as_u64(),
+            "starting search"
+        );
+        self.search_start = Instant::now();
+
+        *self.progress.write().unwrap() = 0.0;
+        *self.error.lock().unwrap() = None;
+        self.results = request.results;
+        self.paused = false;
+
+        self.current_offset = request.window.start();
+        self.search_window = request.window;
+        self.strategy = Some(strategy);
+
+        self.overlap_size = largest_content_size - Len::from(1);
+        self.search_window_size = std::cmp::max(largest_content_size * 2, MIN_SEARCH_WINDOW_SIZE);
+
+        WorkerStatus::Busy
+    }
+
+    /// Stops a currently running search.
+    fn stop_search(&mut self) {
+        if self.search_is_running() {
+            tracing::debug!(elapsed = ?self.search_start.elapsed(), "search stopped");
+        }
+        self.strategy = None;
+        self.paused = false;
+        *self.progress.write().unwrap() = 1.0;
+    }
+
+    /// Stops the current search because of an error, reporting it to the foreground.
+    fn fail_search(&mut self, err: io::Error) {
+        tracing::warn!(offset = ?self.current_offset, "search failed: {err}");
+        *self.error.lock().unwrap() = Some(format!(
+            "cannot read the input at {}: {err}",
+            self.current_offset.hex()
+        ));
+        self.stop_search();
+    }
+
+    /// Returns whether a search is currently running.
+    fn search_is_running(&self) -> bool {
+        self.strategy.is_some()
+    }
+
+    /// Runs one iteration of the search.
+    fn run_search(&mut self) {
+        match self.strategy {
+            Some(SearchStrategy::Patterns { .. }) => self.run_pattern_search(),
+            Some(SearchStrategy::Regex { .. }) => self.run_regex_search(),
+            None => (),
+        }
+    }
+
+    /// Runs one iteration of a search for fixed patterns.
+    fn run_pattern_search(&mut self) {
+        let Some(SearchStrategy::Patterns {
+            searcher,
+            patterns,
+            ascii_case_insensitive,
+        }) = &self.strategy
+        else {
+            return;
+        };
+
+        let current_overlap = if self.current_offset == self.search_window.start() {
+            Len::ZERO
+        } else {
+            self.overlap_size
+        };
+        let start = self.current_offset - current_overlap;
+        let end = self.search_window.end();
+
+        // This is a bit wasteful because it reads overlapping bytes multiple times.
+        //
+        // In practice I expect many searches to be for small patterns, so this is less of an
+        // issue. Unfortunately while the new API for reading from `Input` is much nicer for
+        // everything else, here it falls short.
+        // But even then, when using memory mapped reads, this makes it actually more efficient.
+        let buf = match self.input.read_at(
+            start,
+            self.search_window_size.min(end - start),
+            Some(&mut self.buf),
+        ) {
+            Ok(buf) => buf,
+            Err(err) => {
+                self.fail_search(err);
+                return;
+            }
+        };
+        if buf.is_empty() {
+            // we finished the search
+            self.stop_search();
+            return;
+        }
+        let buf_len = Len::from(u64::try_from(buf.len()).expect("buffer length must fit u64"));
+
+        // the needle is only a part of the pattern, so a candidate that is rejected may overlap a
+        // real match, which is why every overlapping occurrence of the needle has to be checked
+        for result in searcher.find_overlapping_iter(&*buf) {
+            let pattern = &patterns[result.pattern().as_usize()];
+
+            // matches that do not fit in the buffer are found in the previous or next iteration
+            // because of the overlap
+            let Some(pattern_start) = result.start().checked_sub(pattern.needle_offset) else {
+                continue;
+            };
+            if !pattern.matches(&buf[pattern_start..], *ascii_case_insensitive) {
+                continue;
+            }
+
+            let offset =
+                start + Len::from(u64::try_from(pattern_start).expect("read buffer must fit u64"));
+            let len =
+                Len::from(u64::try_from(pattern.bytes.len()).expect("search string must fit u64"));
+            let window = Window::from_start_len(offset, len);
+            self.results
+                .lock()
+                .unwrap()
+                .insert((window, pattern.encoding));
+        }
+
+        if start + buf_len == self.search_window.end() {
+            // we finished the search
+            self.stop_search();
+            return;
+        }
+
+        self.current_offset += buf_len - current_overlap;
+        self.update_progress();
+    }
+
+    /// Runs one iteration of a search for a regular expression.
+    ///
+    /// Unlike fixed patterns, regular expression matches can have different lengths, so the
+    /// search cannot simply overlap the read buffers.
+    /// Instead a match is only accepted if the buffer contains at least the maximum match length
+    /// of bytes after its start, otherwise the next iteration starts at the match.
+    /// This ensures that matches spanning the end of a buffer are found in their entirety.
+    fn run_regex_search(&mut self) {
+        let Some(SearchStrategy::Regex { regex }) = &self.strategy else {
+            return;
+        };
+        let max_match_len = usize::try_from(self.overlap_size.as_u64() + 1)
+            .expect("maximum match length must fit usize");
+
+        // include one byte before the search start, so that look-around assertions such as
+        // word boundaries work correctly
+        let context_len = if self.current_offset == self.search_window.start() {
+            Len::ZERO
+        } else {
+            Len::from(1)
+        };
+        let start = self.current_offset - context_len;
+        let end = self.search_window.end();
+
+        let buf = match self.input.read_at(
+            start,
+            self.search_window_size.min(end - start),
+            Some(&mut self.buf),
+        ) {
+            Ok(buf) => buf,
+            Err(err) => {
+                self.fail_search(err);
+                return;
+            }
+        };
+        let buf_len = Len::from(u64::try_from(buf.len()).expect("buffer length must fit u64"));
+        if buf_len <= context_len {
+            // we finished the search
+            self.stop_search();
+            return;
+        }
+        let is_last = start + buf_len == end;
+
+        // matches starting after this position may be incomplete if this is not the last buffer
+        let last_complete_start = if is_last {
+            buf.len()
+        } else {
+            buf.len().saturating_sub(max_match_len)
+        };
+
+        let mut pos = usize::try_from(context_len.as_u64()).expect("context must fit usize");
+        let mut next_start = None;
+        while pos <= buf.len()
+            && let Some(result) = regex.find_at(&buf, pos)
+        {
+            if result.start() > last_complete_start {
+                next_start = Some(result.start());
+                break;
+            }
+
+            if !result.is_empty() {
+                let offset = start
+                    + Len::from(u64::try_from(result.start()).expect("read buffer must fit u64"));
+                let len =
+                    Len::from(u64::try_from(result.len()).expect("match length must fit u64"));
+                self.results
+                    .lock()
+                    .unwrap()
+                    .insert((Window::from_start_len(offset, len), TextEncoding::Bytes));
+            }
+
+            pos = if result.is_empty() {
+                result.end() + 1
+            } else {
+                result.end()
+            };
+        }
+
+        if is_last {
+            // we finished the search
+            self.stop_search();
+            return;
+        }
+
+        let next_start = next_start.unwrap_or(std::cmp::max(pos, last_complete_start + 1));
+        self.current_offset =
+            start + Len::from(u64::try_from(next_start).expect("read buffer must fit u64"));
+        self.update_progress();
+    }
+
+    /// Updates the progress based on the current offset.
+    fn update_progress(&self) {
+        let fraction_completed = ((self.current_offset - self.search_window.start()).as_u64()
+            as f32)
+            / (self.search_window.size().as_u64() as f32);
+
+        *self.progress.write().unwrap() = fraction_completed;
+    }
+}
+
+impl Worker for BackgroundSearcher {
+    fn step(&mut self, may_block: bool) -> WorkerStatus {
+        let status = self.process_new_requests(may_block);
+
+        if status == WorkerStatus::Busy && self.search_is_running() && !self.paused {
+            self.run_search();
+        }
+
+        status
+    }
+}
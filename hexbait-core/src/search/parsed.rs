@@ -0,0 +1,148 @@
+//! Implements searching through parsed values.
+
+use hexbait_lang::{
+    Int, Value, ValueKind,
+    ir::{
+        Symbol,
+        path::{Path, PathComponent},
+        str_lit_content_to_bytes,
+    },
+};
+
+/// The maximum length of bytes values that are compared against a query.
+///
+/// Larger values are skipped to avoid reading huge amounts of data while searching.
+const MAX_SEARCHED_BYTES_LEN: usize = 64 * 1024;
+
+/// A query to search for in parsed values.
+#[derive(Debug, Clone)]
+pub struct ParsedValueQuery {
+    /// The lowercase text to search for in field names.
+    name: String,
+    /// The integer to compare integer values against.
+    int: Option<Int>,
+    /// The boolean to compare boolean values against.
+    boolean: Option<bool>,
+    /// The bytes to search for in bytes values.
+    bytes: Option<Vec<u8>>,
+}
+
+impl ParsedValueQuery {
+    /// Creates a new query from the given user input.
+    ///
+    /// Field names match if they contain the query (ignoring case).
+    /// Values match if they equal the query as an integer (decimal or `0x` prefixed hex) or
+    /// boolean, or if bytes values contain the query interpreted as a string literal.
+    pub fn new(query: &str) -> ParsedValueQuery {
+        let query = query.trim();
+
+        let mut bytes = Vec::new();
+        let bytes = match str_lit_content_to_bytes(query, &mut bytes) {
+            Ok(()) if !bytes.is_empty() => Some(bytes),
+            _ => None,
+        };
+
+        ParsedValueQuery {
+            name: query.to_lowercase(),
+            int: parse_int(query),
+            boolean: query.parse().ok(),
+            bytes,
+        }
+    }
+
+    /// Whether the query is empty and thus matches nothing.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty()
+    }
+
+    /// Whether the given field name matches the query.
+    fn matches_name(&self, name: &Symbol) -> bool {
+        name.as_str().to_lowercase().contains(&self.name)
+    }
+
+    /// Whether the given value matches the query.
+    fn matches_value(&self, value: &Value) -> bool {
+        match &value.kind {
+            ValueKind::Boolean(boolean) => self.boolean == Some(*boolean),
+            ValueKind::Integer(int) => self.int.as_ref() == Some(int),
+            ValueKind::Float(_) => false,
+            ValueKind::Bytes(bytes) => {
+                let Some(needle) = &self.bytes else {
+                    return false;
+                };
+                if bytes.len() < needle.len() || bytes.len() > MAX_SEARCHED_BYTES_LEN {
+                    return false;
+                }
+                let Ok(haystack) = bytes.value() else {
+                    return false;
+                };
+
+                haystack
+                    .windows(needle.len())
+                    .any(|window| window == needle.as_slice())
+            }
+            ValueKind::Struct { .. } | ValueKind::Array { .. } => false,
+        }
+    }
+}
+
+/// Parses the given text as a decimal or hexadecimal integer.
+fn parse_int(text: &str) -> Option<Int> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let int = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Int::parse_bytes(hex.as_bytes(), 16)?
+    } else {
+        Int::parse_bytes(text.as_bytes(), 10)?
+    };
+
+    Some(if negative { -int } else { int })
+}
+
+/// Searches the given value for subvalues matching the query.
+///
+/// The paths of all matching subvalues are returned in the order they appear in the value.
+pub fn search_parsed_value(value: &Value, query: &ParsedValueQuery) -> Vec<Path> {
+    let mut results = Vec::new();
+
+    if !query.is_empty() {
+        search_recursive(value, None, Path::new(), query, &mut results);
+    }
+
+    results
+}
+
+/// Recursively searches the given value and its children.
+fn search_recursive(
+    value: &Value,
+    name: Option<&Symbol>,
+    path: Path,
+    query: &ParsedValueQuery,
+    results: &mut Vec<Path>,
+) {
+    if name.is_some_and(|name| query.matches_name(name)) || query.matches_value(value) {
+        results.push(path.clone());
+    }
+
+    match &value.kind {
+        ValueKind::Struct { fields, .. } => {
+            for (name, value) in fields {
+                let mut path = path.clone();
+                path.push(PathComponent::FieldAccess(name.clone()));
+
+                search_recursive(value, Some(name), path, query, results);
+            }
+        }
+        ValueKind::Array { items, .. } => {
+            for (i, value) in items.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(PathComponent::Indexing(i));
+
+                search_recursive(value, None, path, query, results);
+            }
+        }
+        _ => (),
+    }
+}
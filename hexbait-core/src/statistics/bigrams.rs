@@ -4,7 +4,6 @@ use std::{fmt, io, ops::AddAssign};
 
 use hexbait_common::{Input, Len};
 use range_set_blaze::RangeSetBlaze;
-use size_format::SizeFormatterBinary;
 
 use crate::{
     statistics::{Statistics, downsampled_bigrams::DownsampledBigramStatistics},
@@ -61,6 +60,11 @@ impl BigramStatistics {
         self.contained_regions.is_superset(&window_region)
     }
 
+    /// Computes the statistics of the given window of the input.
+    pub fn compute_for_window(input: &Input, window: Window) -> Result<Self, io::Error> {
+        <BigramStatistics as Statistics>::compute(input, window)
+    }
+
     /// Returns the downsampled statistics.
     pub fn downsampled(&self) -> DownsampledBigramStatistics {
         let mut follow = Box::new([[0; 16]; 16]);
@@ -149,7 +153,7 @@ impl fmt::Debug for BigramStatistics {
         f.debug_struct("Statistics")
             .field(
                 "size",
-                &format!("{}B", SizeFormatterBinary::new(self.num_covered_bytes())),
+                &Len::from(self.num_covered_bytes()).human_size().to_string(),
             )
             .field("contained_regions", &self.contained_regions)
             .finish()
@@ -23,7 +23,6 @@ use std::io;
 use hexbait_common::{AbsoluteOffset, Input, Len};
 
 use crate::{
-    state::State,
     statistics::{BigramStatistics, Statistics as _},
     window::Window,
 };
@@ -107,22 +106,17 @@ impl ClassificationData {
     }
 }
 
-/// Classifies the currently selected window.
-pub fn classify_selected_window(state: &mut State) {
-    let (statistics, quality) = state
-        .statistics_handler
-        .get_bigram_statistics(state.scroll_state.selected_window());
-    if quality == 0.0 {
-        return;
-    }
-
+/// Classifies the given statistics.
+///
+/// The classes are sorted by their score, with the best matching class first.
+/// If the statistics are empty, `None` is returned.
+pub fn classify(statistics: &BigramStatistics) -> Option<Vec<Class>> {
     let mut quantized_statistics = Box::new([[0; 256]; 256]);
-    quantized_hellinger_statistics(&statistics, &mut quantized_statistics);
+    quantized_hellinger_statistics(statistics, &mut quantized_statistics);
 
     let total_count = statistics.num_covered_bytes();
     if total_count == 0 {
-        state.classification_state.classification_results = None;
-        return;
+        return None;
     }
 
     let mut result = Vec::new();
@@ -183,7 +177,7 @@ pub fn classify_selected_window(state: &mut State) {
             .reverse()
     });
 
-    state.classification_state.classification_results = Some(result);
+    Some(result)
 }
 
 /// Computes the quantized statistics ready for a Hellinger comparison.
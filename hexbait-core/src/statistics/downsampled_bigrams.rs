@@ -4,7 +4,6 @@ use std::{fmt, io, ops::AddAssign};
 
 use hexbait_common::{Input, Len};
 use range_set_blaze::RangeSetBlaze;
-use size_format::SizeFormatterBinary;
 
 use crate::{
     statistics::{Statistics, StatisticsMetrics},
@@ -192,7 +191,7 @@ impl fmt::Debug for DownsampledBigramStatistics {
         f.debug_struct("Statistics")
             .field(
                 "size",
-                &format!("{}B", SizeFormatterBinary::new(self.num_covered_bytes())),
+                &Len::from(self.num_covered_bytes()).human_size().to_string(),
             )
             .field("contained_regions", &self.contained_regions)
             .finish()
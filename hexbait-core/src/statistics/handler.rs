@@ -5,11 +5,7 @@ use std::sync::{Arc, atomic::AtomicU32, mpsc};
 use arc_swap::ArcSwap;
 use hexbait_common::{Input, Len};
 
-use crate::{
-    state::{ScrollState, Settings},
-    statistics::StatisticsMetrics,
-    window::Window,
-};
+use crate::{statistics::StatisticsMetrics, window::Window};
 
 use super::BigramStatistics;
 
@@ -219,26 +215,25 @@ impl StatisticsHandler {
         })
     }
 
-    /// Signals to the statistics handler that a frame has ended.
+    /// Requests statistics for the given windows.
     ///
-    /// The `changed` parameter corresponds to the change state of the scrollbars.
-    pub fn end_of_frame(&mut self, settings: &Settings, scroll_state: &ScrollState) {
-        if scroll_state.changed().is_changed() {
-            self.bins_per_window = (scroll_state.effective_height()
-                * if settings.fine_grained_scrollbars() {
-                    16
-                } else {
-                    1
-                }) as u64;
-
-            self.request_channel
-                .send(Request {
-                    windows: scroll_state.windows().collect(),
-                    bins_per_window: self.bins_per_window,
-                    bins_in_innermost_window: scroll_state.gilbert_pixel_budget,
-                })
-                .unwrap();
-        }
+    /// The windows must be monotonically decreasing in size and contained in each other.
+    /// Smaller windows are computed with higher priority.
+    pub fn request_windows(
+        &mut self,
+        windows: Vec<Window>,
+        bins_per_window: u64,
+        bins_in_innermost_window: u64,
+    ) {
+        self.bins_per_window = bins_per_window;
+
+        self.request_channel
+            .send(Request {
+                windows,
+                bins_per_window,
+                bins_in_innermost_window,
+            })
+            .unwrap();
     }
 }
 
@@ -7,12 +7,12 @@ use std::{
         atomic::AtomicU32,
         mpsc::{self, RecvError, TryRecvError},
     },
-    thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use arc_swap::ArcSwap;
 use hexbait_common::{Input, Len};
+use web_time::Instant;
 
 use crate::{
     statistics::{
@@ -25,6 +25,7 @@ use crate::{
         },
     },
     window::Window,
+    worker::{self, Worker, WorkerStatus},
 };
 
 mod statistics_tree;
@@ -51,6 +52,8 @@ pub struct BackgroundStatisticsEngine {
     computation_state: ComputationState,
     /// The work phase the background thread is in.
     work_phase: WorkPhase,
+    /// The time at which work on the current request started.
+    request_start: Instant,
 }
 
 impl BackgroundStatisticsEngine {
@@ -69,15 +72,12 @@ impl BackgroundStatisticsEngine {
         }));
         let frontend_result = Arc::clone(&result);
 
-        thread::spawn(move || {
-            let background_state = BackgroundStatisticsEngine {
-                request_channel: recv,
-                result,
-                computation_state: ComputationState::new(input),
-                work_phase: WorkPhase::Idle,
-            };
-
-            background_state.run();
+        worker::spawn(BackgroundStatisticsEngine {
+            request_channel: recv,
+            result,
+            computation_state: ComputationState::new(input),
+            work_phase: WorkPhase::Idle,
+            request_start: Instant::now(),
         });
 
         BackgroundStatisticsEngineStartResult {
@@ -104,6 +104,12 @@ impl BackgroundStatisticsEngine {
 
     /// Resets the state for a new request.
     fn reset_for_request(&mut self, request: Request) {
+        tracing::debug!(
+            windows = request.windows.len(),
+            bins_per_window = request.bins_per_window,
+            "starting statistics request"
+        );
+        self.request_start = Instant::now();
         self.computation_state.reset_for_request(request);
         self.work_phase = WorkPhase::from_beginning(&mut self.computation_state);
     }
@@ -128,24 +134,19 @@ impl BackgroundStatisticsEngine {
 
     /// Processes new requests.
     ///
-    /// Returns `false` if the thread should terminate.
-    fn process_new_requests(&mut self) -> bool {
-        let mut request = if self.has_more_work() {
+    /// If `may_block` is set, this blocks until a new request arrives when there is no work left.
+    fn process_new_requests(&mut self, may_block: bool) -> WorkerStatus {
+        let mut request = if self.has_more_work() || !may_block {
             match self.request_channel.try_recv() {
                 Ok(request) => request,
-                Err(TryRecvError::Empty) => return true,
-                Err(TryRecvError::Disconnected) => return false,
+                Err(TryRecvError::Empty) if self.has_more_work() => return WorkerStatus::Busy,
+                Err(TryRecvError::Empty) => return WorkerStatus::Idle,
+                Err(TryRecvError::Disconnected) => return WorkerStatus::Terminated,
             }
         } else {
-            // we will possibly block now, so let's ensure that we publish one last time and garbage collect
-            if self.computation_state.latest_request.is_some() {
-                self.publish_work();
-                self.do_garbage_collection();
-            }
-
             match self.request_channel.recv() {
                 Ok(request) => request,
-                Err(RecvError) => return false,
+                Err(RecvError) => return WorkerStatus::Terminated,
             }
         };
 
@@ -156,26 +157,43 @@ impl BackgroundStatisticsEngine {
                 Err(TryRecvError::Empty) => {
                     break;
                 }
-                Err(TryRecvError::Disconnected) => return false,
+                Err(TryRecvError::Disconnected) => return WorkerStatus::Terminated,
             };
         }
 
         self.reset_for_request(request);
 
-        true
+        WorkerStatus::Busy
     }
+}
 
-    /// Runs the background computations.
-    fn run(mut self) {
-        loop {
-            if !self.process_new_requests() {
-                break;
-            }
-            self.publish_work();
-            self.do_garbage_collection();
+impl Worker for BackgroundStatisticsEngine {
+    fn step(&mut self, may_block: bool) -> WorkerStatus {
+        let status = self.process_new_requests(may_block);
+        if status != WorkerStatus::Busy {
+            return status;
+        }
+
+        self.publish_work();
+        self.do_garbage_collection();
 
+        {
+            let _span = tracing::trace_span!("statistics_work_phase").entered();
             self.work_phase.advance(&mut self.computation_state);
         }
+
+        if !self.has_more_work() {
+            tracing::debug!(
+                elapsed = ?self.request_start.elapsed(),
+                "finished statistics request"
+            );
+
+            // we may be waiting for new requests now, so let's ensure that we publish one last time and garbage collect
+            self.publish_work();
+            self.do_garbage_collection();
+        }
+
+        WorkerStatus::Busy
     }
 }
 
@@ -241,17 +241,14 @@ impl<Statistics: crate::statistics::Statistics> StatisticsTree<Statistics> {
             stats.0 += 1;
             stats.1 += node.statistics.approximate_memory_usage();
         }
-        eprint!(
-            "mem: {}B",
-            size_format::SizeFormatterBinary::new(self.memory_usage)
-        );
+        eprint!("mem: {}", Len::from(self.memory_usage).human_size());
         for (tier, stats) in tier_stats {
             eprint!(
-                ", tier {} ({}B): {} nodes ({}B)",
+                ", tier {} ({}): {} nodes ({})",
                 tier.0,
-                size_format::SizeFormatterBinary::new(tier.size().as_u64()),
+                tier.size().human_size(),
                 stats.0,
-                size_format::SizeFormatterBinary::new(stats.1)
+                Len::from(stats.1).human_size()
             );
         }
         eprintln!()
@@ -0,0 +1,4 @@
+//! Models "windows" as regions of the input.
+
+/// Represents a region of the input.
+pub type Window = hexbait_common::AbsoluteRange;
@@ -0,0 +1,89 @@
+//! Runs background workers, either on their own thread or cooperatively where threads are not
+//! available.
+
+use std::time::Duration;
+
+/// The status of a worker after performing a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WorkerStatus {
+    /// The worker has more work to do.
+    Busy,
+    /// The worker is waiting for new work.
+    Idle,
+    /// The worker should not be run anymore.
+    Terminated,
+}
+
+/// A worker that performs its work in small steps.
+pub(crate) trait Worker: Send + 'static {
+    /// Performs one step of work.
+    ///
+    /// If `may_block` is set, the worker may block until new work arrives instead of returning
+    /// [`WorkerStatus::Idle`].
+    fn step(&mut self, may_block: bool) -> WorkerStatus;
+}
+
+/// Starts running the given worker in the background.
+pub(crate) fn spawn(worker: impl Worker) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut worker = worker;
+        std::thread::spawn(move || while worker.step(true) != WorkerStatus::Terminated {});
+    }
+    #[cfg(target_arch = "wasm32")]
+    cooperative::WORKERS.with_borrow_mut(|workers| workers.push(Box::new(worker)));
+}
+
+/// Runs the workers that do not have their own thread for roughly the given duration.
+///
+/// This needs to be called regularly, for example once per frame, on platforms without threads.
+/// On all other platforms the workers run on their own threads and this does nothing.
+///
+/// Returns whether any worker has more work to do.
+pub fn run_cooperative_workers(budget: Duration) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = budget;
+        false
+    }
+    #[cfg(target_arch = "wasm32")]
+    cooperative::run(budget)
+}
+
+/// Implements running workers cooperatively on the current thread.
+#[cfg(target_arch = "wasm32")]
+mod cooperative {
+    use std::{cell::RefCell, time::Duration};
+
+    use web_time::Instant;
+
+    use super::{Worker, WorkerStatus};
+
+    thread_local! {
+        /// The workers that are run cooperatively.
+        pub(super) static WORKERS: RefCell<Vec<Box<dyn Worker>>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Runs all workers for roughly the given duration.
+    pub(super) fn run(budget: Duration) -> bool {
+        let start = Instant::now();
+
+        WORKERS.with_borrow_mut(|workers| {
+            loop {
+                let mut any_busy = false;
+                workers.retain_mut(|worker| match worker.step(false) {
+                    WorkerStatus::Busy => {
+                        any_busy = true;
+                        true
+                    }
+                    WorkerStatus::Idle => true,
+                    WorkerStatus::Terminated => false,
+                });
+
+                if !any_busy || start.elapsed() > budget {
+                    return any_busy;
+                }
+            }
+        })
+    }
+}
@@ -11,8 +11,8 @@ use std::{
 };
 
 use clap::Parser;
-use hexbait::statistics::classification::compute_classification_data;
 use hexbait_common::{Input, Len};
+use hexbait_core::statistics::classification::compute_classification_data;
 
 /// Generates hexbait classification data from a folder of samples
 #[derive(Parser, Debug)]
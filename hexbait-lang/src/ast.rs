@@ -32,6 +32,33 @@ pub trait AstNode: Sized {
     fn text(&self) -> rowan::SyntaxText {
         self.syntax().text()
     }
+
+    /// Returns the text of the documentation comments (`///`) directly in front of the node.
+    ///
+    /// Multiple comments are joined by newlines.
+    fn doc_comment(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut token = self.syntax().first_token()?.prev_token();
+        while let Some(current) = token {
+            let SyntaxKind::Token { kind } = current.kind() else {
+                break;
+            };
+            if kind == TokenKind::DocComment {
+                let line = current.text().trim_start_matches('/').trim_end();
+                lines.push(line.strip_prefix(' ').unwrap_or(line).to_string());
+            } else if !kind.is_trivia() {
+                break;
+            }
+            token = current.prev_token();
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+        lines.reverse();
+
+        Some(lines.join("\n"))
+    }
 }
 
 /// Returns an iterator over all children of the given type from the syntax node.
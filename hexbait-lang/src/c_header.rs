@@ -0,0 +1,1132 @@
+//! Implements generating format descriptions from C struct declarations.
+//!
+//! The `struct`s, `enum`s and `typedef`s of a C header are translated to named `struct`s of a
+//! format description. Members are laid out like a C compiler for a 64-bit platform would lay
+//! them out: every member is aligned to its size, which `#pragma pack` and
+//! `__attribute__((packed))` limit, and the padding in between becomes hidden fields. `long` and
+//! pointers are 8 bytes wide, as on LP64 platforms.
+//!
+//! Consecutive bitfield members of the same type share a storage unit of that type, which becomes
+//! a bitfield named `bitfield0`, `bitfield1` and so on. `union`s and function declarations have no
+//! equivalent and are reported instead.
+
+use std::collections::HashMap;
+
+use crate::{
+    Int, Span,
+    hexpat::{Token, TokenKind, char_value, name, parse_int, tokenize},
+    ir::Spanned,
+};
+
+/// The size of a pointer in bytes.
+const POINTER_SIZE: u64 = 8;
+
+/// C declarations translated to a format description.
+#[derive(Debug, Clone)]
+pub struct CHeaderTranslation {
+    /// The source of the format description.
+    pub hbl: String,
+    /// The declarations that have no equivalent and were left out.
+    ///
+    /// The spans refer to the source of the C header.
+    pub unsupported: Vec<Spanned<String>>,
+}
+
+/// Translates the `struct` declarations of a C header to the source of a format description.
+///
+/// If `root` is given, the format description parses the `struct` of that name, otherwise it only
+/// defines the `struct`s.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_lang::translate_c_header;
+/// let translation = translate_c_header(
+///     r#"
+///     #include <stdint.h>
+///     #define NAME_LEN 6
+///
+///     typedef struct {
+///         uint8_t kind;
+///         uint32_t size;
+///         char name[NAME_LEN];
+///     } entry_t;
+///
+///     struct table {
+///         uint16_t count;
+///         entry_t entries[2];
+///     };
+///     "#,
+///     Some("table"),
+/// );
+///
+/// assert!(translation.unsupported.is_empty());
+/// assert_eq!(
+///     translation.hbl,
+///     r#"struct entry_t {
+///     kind u8;
+///     _padding0 bytes len 3;
+///     size u32;
+///     name bytes len 6;
+///     _padding1 bytes len 2;
+/// }
+///
+/// struct table {
+///     count u16;
+///     _padding0 bytes len 2;
+///     entries [entry_t] len 2;
+/// }
+///
+/// table table;
+/// "#,
+/// );
+/// ```
+pub fn translate_c_header(src: &str, root: Option<&str>) -> CHeaderTranslation {
+    let mut translator = Translator {
+        tokens: tokenize(src),
+        pos: 0,
+        out: String::new(),
+        unsupported: Vec::new(),
+        defines: HashMap::new(),
+        types: HashMap::new(),
+        tags: HashMap::new(),
+        constants: HashMap::new(),
+        pack: Vec::new(),
+    };
+
+    while translator.peek().is_some() {
+        translator.item();
+    }
+
+    if let Some(root) = root {
+        match translator
+            .types
+            .get(root)
+            .or_else(|| translator.tags.get(root))
+        {
+            Some(ty) => translator.out += &format!("{} {};\n", name(root), ty.hbl),
+            None => {
+                let span = Span {
+                    start: src.len(),
+                    end: src.len(),
+                };
+                translator.unsupported(&format!("the unknown `struct` `{root}`"), span);
+            }
+        }
+    }
+
+    let hbl = match crate::format(&translator.out) {
+        Ok(formatted) => formatted,
+        Err(_) => translator.out,
+    };
+
+    CHeaderTranslation {
+        hbl,
+        unsupported: translator.unsupported,
+    }
+}
+
+/// Returns the type of a built-in C type given by its words, such as `unsigned long`.
+fn builtin_type(words: &[&str]) -> Option<TypeInfo> {
+    let signed = !words.contains(&"unsigned");
+    let count = |word| words.iter().filter(|&&w| w == word).count();
+
+    let (size, signed) = match words {
+        ["float"] => return Some(TypeInfo::primitive("f32", 4)),
+        ["double"] => return Some(TypeInfo::primitive("f64", 8)),
+        ["_Bool" | "bool"] => (1, false),
+        [fixed] if fixed_width(fixed).is_some() => return fixed_width(fixed),
+        _ if count("char") == 1 => {
+            let mut ty = TypeInfo::primitive(if count("signed") == 1 { "i8" } else { "u8" }, 1);
+            ty.is_byte = true;
+            return Some(ty);
+        }
+        _ if count("short") == 1 => (2, signed),
+        _ if count("long") == 2 => (8, signed),
+        _ if count("long") == 1 => (8, signed),
+        _ if words
+            .iter()
+            .all(|word| matches!(*word, "int" | "signed" | "unsigned")) =>
+        {
+            (4, signed)
+        }
+        _ => return None,
+    };
+
+    let sign = if signed { 'i' } else { 'u' };
+    Some(TypeInfo::primitive(&format!("{sign}{}", size * 8), size))
+}
+
+/// Returns the type of a fixed width integer type name, such as `uint32_t`, `__le16` or `DWORD`.
+fn fixed_width(name: &str) -> Option<TypeInfo> {
+    let (ty, size, endianness) = match name {
+        "BYTE" | "UCHAR" => ("u8", 1, None),
+        "WORD" | "USHORT" => ("u16", 2, None),
+        "DWORD" | "ULONG" | "UINT" => ("u32", 4, None),
+        "QWORD" | "ULONGLONG" => ("u64", 8, None),
+        "size_t" | "uintptr_t" => ("u64", 8, None),
+        "ssize_t" | "intptr_t" | "ptrdiff_t" => ("i64", 8, None),
+        _ => {
+            let name = name.trim_start_matches('_');
+            let (rest, endianness) = if let Some(rest) = name.strip_prefix("le") {
+                (rest, Some("le"))
+            } else if let Some(rest) = name.strip_prefix("be") {
+                (rest, Some("be"))
+            } else {
+                (name, None)
+            };
+            let rest = rest.strip_suffix("_t").unwrap_or(rest);
+            let (signed, bits) = if let Some(bits) = rest.strip_prefix("uint") {
+                (false, bits)
+            } else if let Some(bits) = rest.strip_prefix("int") {
+                (true, bits)
+            } else if let Some(bits) = rest.strip_prefix('u') {
+                (false, bits)
+            } else if let Some(bits) = rest.strip_prefix('s') {
+                (true, bits)
+            } else if endianness.is_some() {
+                (false, rest)
+            } else {
+                return None;
+            };
+            let bits: u64 = bits
+                .parse()
+                .ok()
+                .filter(|bits| matches!(bits, 8 | 16 | 32 | 64))?;
+            let sign = if signed { 'i' } else { 'u' };
+
+            let mut ty = TypeInfo::primitive(&format!("{sign}{bits}"), bits / 8);
+            ty.endianness = endianness;
+            ty.is_byte = bits == 8;
+            return Some(ty);
+        }
+    };
+
+    let mut ty = TypeInfo::primitive(ty, size);
+    ty.endianness = endianness;
+    ty.is_byte = size == 1;
+    Some(ty)
+}
+
+/// Parses an integer literal of C, where a leading zero marks an octal number.
+fn c_int(text: &str) -> Option<Int> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    if digits.len() > 1 && digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return crate::int_from_str(8, &digits[1..]);
+    }
+
+    parse_int(text)
+}
+
+/// Rounds the offset up to a multiple of the alignment.
+fn align_up(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// What is known about a C type.
+#[derive(Debug, Clone)]
+struct TypeInfo {
+    /// The parse type of the format description.
+    hbl: String,
+    /// The size of the type in bytes, if it is known.
+    size: Option<u64>,
+    /// The alignment of the type in bytes.
+    align: u64,
+    /// Whether the type is a single byte, so that arrays of it become `bytes`.
+    is_byte: bool,
+    /// The endianness that the type is always stored in, if it has a fixed one.
+    endianness: Option<&'static str>,
+}
+
+impl TypeInfo {
+    /// Creates the type of a primitive value whose alignment is its size.
+    fn primitive(hbl: &str, size: u64) -> TypeInfo {
+        TypeInfo {
+            hbl: hbl.to_string(),
+            size: Some(size),
+            align: size,
+            is_byte: false,
+            endianness: None,
+        }
+    }
+
+    /// Returns the type of an array of this type with the given length.
+    fn array(&self, len: u64) -> TypeInfo {
+        let hbl = if self.is_byte && !self.hbl.starts_with('i') {
+            format!("bytes len {len}")
+        } else {
+            format!("[{}] len {len}", self.hbl)
+        };
+
+        TypeInfo {
+            hbl,
+            size: self.size.map(|size| size * len),
+            align: self.align,
+            is_byte: false,
+            endianness: self.endianness,
+        }
+    }
+}
+
+/// A declarator, which names a value and may turn the type into a pointer or an array.
+struct Declarator<'src> {
+    /// The declared name.
+    name: Token<'src>,
+    /// The type of the declared value.
+    ty: Option<TypeInfo>,
+    /// The width of the bitfield member, if it is one.
+    bits: Option<u32>,
+}
+
+/// A bitfield storage unit that is being filled.
+struct BitfieldUnit {
+    /// The type of the storage unit.
+    ty: TypeInfo,
+    /// The fields in the unit with their widths.
+    fields: Vec<(String, u32)>,
+    /// The number of bits that are used.
+    used_bits: u32,
+}
+
+/// The layout of a `struct` that is being translated.
+struct Layout {
+    /// The translated members.
+    out: String,
+    /// The offset after the last member, if it is known.
+    offset: Option<u64>,
+    /// The largest alignment of any member.
+    align: u64,
+    /// The number of hidden padding fields.
+    paddings: usize,
+    /// The number of bitfield storage units.
+    bitfields: usize,
+    /// The bitfield storage unit that is being filled.
+    unit: Option<BitfieldUnit>,
+}
+
+/// Keeps track of the state of a translation.
+struct Translator<'src> {
+    /// The tokens of the header.
+    tokens: Vec<Token<'src>>,
+    /// The index of the next token.
+    pos: usize,
+    /// The translated source.
+    out: String,
+    /// The declarations that could not be translated.
+    unsupported: Vec<Spanned<String>>,
+    /// The values of object-like macros.
+    defines: HashMap<&'src str, &'src str>,
+    /// The types defined with `typedef`, by their names.
+    types: HashMap<&'src str, TypeInfo>,
+    /// The `struct`s and `enum`s, by their tags.
+    tags: HashMap<&'src str, TypeInfo>,
+    /// The values of enumeration constants.
+    constants: HashMap<&'src str, Int>,
+    /// The stack of the maximum alignments set with `#pragma pack`.
+    pack: Vec<Option<u64>>,
+}
+
+impl<'src> Translator<'src> {
+    /// Returns the next token without consuming it.
+    fn peek(&self) -> Option<&Token<'src>> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Returns the text of the token `n` tokens ahead without consuming anything.
+    fn peek_text(&self, n: usize) -> Option<&'src str> {
+        self.tokens.get(self.pos + n).map(|token| token.text)
+    }
+
+    /// Consumes the next token.
+    fn bump(&mut self) -> Option<Token<'src>> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+
+        token
+    }
+
+    /// Consumes the next token if it has the given text.
+    fn eat(&mut self, text: &str) -> bool {
+        if self.peek_text(0) == Some(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The span of the next token, or an empty span at the end of the source.
+    fn span(&self) -> Span {
+        self.peek()
+            .or_else(|| self.tokens.last())
+            .map(|token| token.span)
+            .unwrap_or(Span { start: 0, end: 0 })
+    }
+
+    /// Reports a declaration that has no equivalent in the hexbait language.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        self.unsupported.push(Spanned {
+            inner: format!("{what} cannot be translated"),
+            span,
+        });
+    }
+
+    /// Skips the rest of a declaration, including the blocks it contains.
+    fn skip_declaration(&mut self) {
+        let mut depth = 0usize;
+        let mut is_function_body = false;
+        let mut prev = None;
+        while let Some(token) = self.bump() {
+            match token.text {
+                "{" if depth == 0 => {
+                    is_function_body = prev == Some(")");
+                    depth += 1;
+                }
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" => depth = depth.saturating_sub(1),
+                "}" => {
+                    depth = depth.saturating_sub(1);
+                    // function definitions end without a `;`
+                    if depth == 0 && is_function_body {
+                        return;
+                    }
+                }
+                ";" if depth == 0 => return,
+                _ => (),
+            }
+            prev = Some(token.text);
+        }
+    }
+
+    /// The maximum alignment that is currently set, if there is one.
+    fn max_align(&self) -> Option<u64> {
+        self.pack.last().copied().flatten()
+    }
+
+    /// Translates a declaration at the top level of the header.
+    fn item(&mut self) {
+        let Some(token) = self.peek().cloned() else {
+            return;
+        };
+
+        match (token.kind, token.text) {
+            (TokenKind::Directive, text) => {
+                self.bump();
+                self.directive(text);
+            }
+            (_, ";") => {
+                self.bump();
+            }
+            (TokenKind::Ident, "extern")
+                if self.peek_text(1).is_some_and(|t| t.starts_with('"')) =>
+            {
+                // `extern "C" {` blocks only wrap other declarations
+                self.bump();
+                self.bump();
+                self.eat("{");
+            }
+            (_, "}") => {
+                self.bump();
+            }
+            (TokenKind::Ident, "typedef") => {
+                self.bump();
+                self.typedef();
+            }
+            (TokenKind::Ident, "struct" | "enum" | "union") => {
+                let start = self.pos;
+                match self.specifier() {
+                    Some(_) if self.eat(";") => (),
+                    _ => {
+                        // variables or functions of the type, which only matter for the type
+                        self.pos = start;
+                        self.skip_declaration();
+                    }
+                }
+            }
+            _ => self.skip_declaration(),
+        }
+    }
+
+    /// Handles a preprocessor directive.
+    fn directive(&mut self, text: &'src str) {
+        let text = text.trim_start_matches('#').trim_start();
+        if let Some(define) = text.strip_prefix("define") {
+            let define = define.trim_start();
+            let name_len = define
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(define.len());
+            let (name, value) = define.split_at(name_len);
+            // function-like macros are not supported
+            if !value.starts_with('(') {
+                let value = value.split("//").next().unwrap_or(value).trim();
+                self.defines.insert(name, value);
+            }
+        } else if let Some(pack) = text.strip_prefix("pragma") {
+            let pack: String = pack.chars().filter(|c| !c.is_whitespace()).collect();
+            if let Some(args) = pack
+                .strip_prefix("pack(")
+                .and_then(|args| args.strip_suffix(')'))
+            {
+                let value = args
+                    .rsplit(',')
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok());
+                match args {
+                    "pop" => {
+                        self.pack.pop();
+                    }
+                    "" => self.pack.clear(),
+                    _ if args.starts_with("push") => self.pack.push(value.or(self.max_align())),
+                    _ => {
+                        self.pack.pop();
+                        self.pack.push(value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Translates a `typedef` after the keyword.
+    fn typedef(&mut self) {
+        let start = self.pos;
+        let Some(ty) = self.specifier() else {
+            self.pos = start;
+            self.skip_declaration();
+            return;
+        };
+
+        loop {
+            let Some(declarator) = self.declarator(&ty) else {
+                self.skip_declaration();
+                return;
+            };
+            let struct_name = declarator.name.text;
+            if let Some(mut ty) = declarator.ty {
+                // anonymous `struct`s are named after the first `typedef` of them
+                if ty.hbl.starts_with('{') {
+                    self.out += &format!("struct {} {}\n\n", name(struct_name), ty.hbl);
+                    ty.hbl = name(struct_name);
+                }
+                self.types.insert(struct_name, ty);
+            }
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.eat(";");
+    }
+
+    /// Parses a type specifier, such as `unsigned int` or `struct foo { ... }`.
+    ///
+    /// `struct`s and `enum`s that are defined in it are translated.
+    fn specifier(&mut self) -> Option<TypeInfo> {
+        let mut words = Vec::new();
+        let span = self.span();
+        let doc = self.peek().and_then(|token| token.doc.clone());
+        loop {
+            let token = self.peek()?.clone();
+            match token.text {
+                "const" | "volatile" | "static" | "extern" | "register" | "restrict" => {
+                    self.bump();
+                }
+                "__attribute__" | "__declspec" | "alignas" | "_Alignas" => {
+                    self.bump();
+                    self.attribute();
+                }
+                "struct" | "union" => {
+                    self.bump();
+                    return self.record(token.text == "union", span, doc);
+                }
+                "enum" => {
+                    self.bump();
+                    return self.r#enum();
+                }
+                "signed" | "unsigned" | "short" | "long" | "int" | "char" => {
+                    self.bump();
+                    words.push(token.text);
+                }
+                _ if token.kind == TokenKind::Ident && words.is_empty() => {
+                    self.bump();
+                    words.push(token.text);
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(ty) = builtin_type(&words) {
+            return Some(ty);
+        }
+        if let [ty] = words[..]
+            && let Some(ty) = self.types.get(ty)
+        {
+            return Some(ty.clone());
+        }
+
+        let what = match &words[..] {
+            [] => String::from("this declaration"),
+            words => format!("the type `{}`", words.join(" ")),
+        };
+        self.unsupported(&what, span);
+        None
+    }
+
+    /// Skips the arguments of an attribute and remembers if it packs the type.
+    ///
+    /// Returns `true` if the attribute packs the type.
+    fn attribute(&mut self) -> bool {
+        let mut packed = false;
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token.text {
+                "(" => depth += 1,
+                ")" => depth = depth.saturating_sub(1),
+                "packed" | "__packed__" => packed = true,
+                _ if depth == 0 => break,
+                _ => (),
+            }
+            self.bump();
+            if depth == 0 {
+                break;
+            }
+        }
+
+        packed
+    }
+
+    /// Translates a `struct` or `union` after the keyword.
+    fn record(&mut self, is_union: bool, span: Span, doc: Option<String>) -> Option<TypeInfo> {
+        let mut packed = false;
+        while matches!(self.peek_text(0), Some("__attribute__" | "__declspec")) {
+            self.bump();
+            packed |= self.attribute();
+        }
+
+        let tag = self
+            .peek()
+            .filter(|token| token.kind == TokenKind::Ident)
+            .map(|token| token.text);
+        if tag.is_some() {
+            self.bump();
+        }
+
+        if !self.eat("{") {
+            let Some(tag) = tag else {
+                self.unsupported("this declaration", span);
+                return None;
+            };
+            return Some(self.tags.get(tag).cloned().unwrap_or_else(|| TypeInfo {
+                // a declaration of a `struct` that is defined later
+                hbl: name(tag),
+                size: None,
+                align: 1,
+                is_byte: false,
+                endianness: None,
+            }));
+        }
+
+        if is_union {
+            self.pos -= 1;
+            self.skip_block();
+            self.unsupported("`union`s", span);
+            return None;
+        }
+
+        let max_align = self.max_align();
+        let mut layout = Layout {
+            out: String::new(),
+            offset: Some(0),
+            align: 1,
+            paddings: 0,
+            bitfields: 0,
+            unit: None,
+        };
+        let mut members = Vec::new();
+        while self.peek().is_some() && !self.eat("}") {
+            let doc = self.peek().and_then(|token| token.doc.clone());
+            let start = self.pos;
+            let Some(ty) = self.specifier() else {
+                self.pos = start;
+                self.skip_declaration();
+                continue;
+            };
+            loop {
+                let Some(declarator) = self.declarator(&ty) else {
+                    self.skip_declaration();
+                    break;
+                };
+                members.push((declarator, doc.clone()));
+                if !self.eat(",") {
+                    self.eat(";");
+                    break;
+                }
+            }
+        }
+        while matches!(self.peek_text(0), Some("__attribute__" | "__declspec")) {
+            self.bump();
+            packed |= self.attribute();
+        }
+        let max_align = if packed { Some(1) } else { max_align };
+
+        for (declarator, doc) in members {
+            self.member(&mut layout, declarator, doc, max_align);
+        }
+        self.flush_bitfield(&mut layout);
+
+        let size = layout.offset.map(|offset| {
+            let size = align_up(offset, layout.align);
+            if size > offset {
+                self.padding(&mut layout, size - offset);
+            }
+            size
+        });
+
+        // anonymous `struct`s are translated inline
+        let hbl = match tag {
+            Some(tag) => {
+                for line in doc.iter().flat_map(|doc| doc.lines()) {
+                    self.out += &format!("/// {line}\n");
+                }
+                self.out += &format!("struct {} {{\n{}}}\n\n", name(tag), layout.out);
+                name(tag)
+            }
+            None => format!("{{\n{}}}", layout.out),
+        };
+
+        let ty = TypeInfo {
+            hbl,
+            size,
+            align: layout.align,
+            is_byte: false,
+            endianness: None,
+        };
+        if let Some(tag) = tag {
+            self.tags.insert(tag, ty.clone());
+        }
+
+        Some(ty)
+    }
+
+    /// Skips a block in braces.
+    fn skip_block(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.bump() {
+            match token.text {
+                "{" => depth += 1,
+                "}" => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Translates a member of a `struct` into the layout.
+    fn member(
+        &mut self,
+        layout: &mut Layout,
+        declarator: Declarator<'src>,
+        doc: Option<String>,
+        max_align: Option<u64>,
+    ) {
+        let Some(ty) = declarator.ty else {
+            return;
+        };
+        let align = max_align.map_or(ty.align, |max| ty.align.min(max));
+
+        if let Some(bits) = declarator.bits {
+            // a bitfield of width zero starts a new storage unit
+            if bits == 0 {
+                self.flush_bitfield(layout);
+                return;
+            }
+
+            let fits = layout.unit.as_ref().is_some_and(|unit| {
+                unit.ty.hbl == ty.hbl
+                    && unit
+                        .ty
+                        .size
+                        .is_some_and(|size| u64::from(unit.used_bits + bits) <= size * 8)
+            });
+            if !fits {
+                self.flush_bitfield(layout);
+                self.align(layout, align);
+                layout.unit = Some(BitfieldUnit {
+                    ty,
+                    fields: Vec::new(),
+                    used_bits: 0,
+                });
+            }
+
+            let unit = layout.unit.as_mut().expect("the unit was just created");
+            let field = if declarator.name.text.is_empty() {
+                format!("_unused{}", unit.fields.len())
+            } else {
+                name(declarator.name.text)
+            };
+            unit.fields.push((field, bits));
+            unit.used_bits += bits;
+            return;
+        }
+
+        self.flush_bitfield(layout);
+        self.align(layout, align);
+
+        if let Some(doc) = doc {
+            for line in doc.lines() {
+                layout.out += &format!("/// {line}\n");
+            }
+        }
+        let field = format!("{} {};\n", name(declarator.name.text), ty.hbl);
+        match ty.endianness {
+            Some(endianness) if endianness != "le" => {
+                layout.out += &format!("!endian {endianness};\n{field}!endian le;\n");
+            }
+            _ => layout.out += &field,
+        }
+
+        layout.offset = layout
+            .offset
+            .zip(ty.size)
+            .map(|(offset, size)| offset + size);
+        if layout.offset.is_none() {
+            self.unsupported(
+                &format!("the layout after `{}`", declarator.name.text),
+                declarator.name.span,
+            );
+        }
+    }
+
+    /// Aligns the layout to the given alignment by adding padding.
+    fn align(&mut self, layout: &mut Layout, align: u64) {
+        layout.align = layout.align.max(align);
+        if let Some(offset) = layout.offset {
+            let aligned = align_up(offset, align);
+            if aligned > offset {
+                self.padding(layout, aligned - offset);
+            }
+        }
+    }
+
+    /// Adds a hidden field of the given number of bytes to the layout.
+    fn padding(&mut self, layout: &mut Layout, len: u64) {
+        layout.out += &format!("_padding{} bytes len {len};\n", layout.paddings);
+        layout.paddings += 1;
+        layout.offset = layout.offset.map(|offset| offset + len);
+    }
+
+    /// Adds the bitfield storage unit that is being filled to the layout.
+    fn flush_bitfield(&mut self, layout: &mut Layout) {
+        let Some(unit) = layout.unit.take() else {
+            return;
+        };
+        let Some(size) = unit.ty.size else {
+            return;
+        };
+
+        let mut out = format!("bitfield{} bits u{} {{\n", layout.bitfields, size * 8);
+        layout.bitfields += 1;
+        for (field, bits) in &unit.fields {
+            out += &format!("{field} u{bits};\n");
+        }
+        out += "};\n";
+
+        match unit.ty.endianness {
+            Some(endianness) if endianness != "le" => {
+                layout.out += &format!("!endian {endianness};\n{out}!endian le;\n");
+            }
+            _ => layout.out += &out,
+        }
+        layout.offset = layout.offset.map(|offset| offset + size);
+    }
+
+    /// Parses a declarator of a value of the given base type.
+    ///
+    /// Returns `None` if the declarator cannot be parsed.
+    fn declarator(&mut self, base: &TypeInfo) -> Option<Declarator<'src>> {
+        let mut ty = Some(base.clone());
+        let mut is_pointer = false;
+        while self.eat("*") {
+            is_pointer = true;
+            self.eat("const");
+        }
+        if is_pointer {
+            ty = Some(TypeInfo::primitive("u64", POINTER_SIZE));
+        }
+
+        let declarator_name = match self.peek() {
+            Some(token) if token.kind == TokenKind::Ident => {
+                let token = token.clone();
+                self.bump();
+                token
+            }
+            // an unnamed bitfield member for padding
+            Some(token) if token.text == ":" => Token {
+                text: "",
+                ..token.clone()
+            },
+            _ => {
+                let span = self.span();
+                self.unsupported("this declarator", span);
+                return None;
+            }
+        };
+
+        if self.peek_text(0) == Some("(") {
+            self.unsupported("function declarations", declarator_name.span);
+            return None;
+        }
+
+        let mut dims = Vec::new();
+        while self.eat("[") {
+            let span = self.span();
+            let len = self.const_expr(&["]"]);
+            if !self.eat("]") {
+                return None;
+            }
+            match len.and_then(|len| u64::try_from(len).ok()) {
+                Some(len) => dims.push(len),
+                None => {
+                    self.unsupported("arrays without a constant length", span);
+                    ty = None;
+                }
+            }
+        }
+        for len in dims.into_iter().rev() {
+            ty = ty.map(|ty| ty.array(len));
+        }
+
+        let bits = if self.eat(":") {
+            let span = self.span();
+            let bits = self
+                .const_expr(&[",", ";"])
+                .and_then(|bits| u32::try_from(bits).ok());
+            if bits.is_none() {
+                self.unsupported("this bitfield width", span);
+                ty = None;
+            }
+            bits
+        } else {
+            None
+        };
+
+        while matches!(self.peek_text(0), Some("__attribute__" | "__declspec")) {
+            self.bump();
+            self.attribute();
+        }
+
+        Some(Declarator {
+            name: declarator_name,
+            ty,
+            bits,
+        })
+    }
+
+    /// Translates an `enum` after the keyword into an integer with named values.
+    fn r#enum(&mut self) -> Option<TypeInfo> {
+        let tag = self
+            .peek()
+            .filter(|token| token.kind == TokenKind::Ident)
+            .map(|token| token.text);
+        if tag.is_some() {
+            self.bump();
+        }
+        let underlying = if self.eat(":") {
+            self.specifier()?
+        } else {
+            TypeInfo::primitive("u32", 4)
+        };
+
+        if !self.eat("{") {
+            return match tag.and_then(|tag| self.tags.get(tag)) {
+                Some(ty) => Some(ty.clone()),
+                None => Some(underlying),
+            };
+        }
+
+        let mut names = Vec::new();
+        let mut next = Some(Int::from(0));
+        while let Some(variant) = self.bump() {
+            match variant.text {
+                "}" => break,
+                "," => continue,
+                _ => (),
+            }
+            let value = if self.eat("=") {
+                self.const_expr(&[",", "}"])
+            } else {
+                next.clone()
+            };
+            match value {
+                Some(value) => {
+                    next = Some(&value + 1);
+                    self.constants.insert(variant.text, value.clone());
+                    names.push(format!("{value} => \"{}\",\n", variant.text));
+                }
+                None => {
+                    self.unsupported("this enumeration constant", variant.span);
+                    next = None;
+                }
+            }
+        }
+
+        let ty = TypeInfo {
+            hbl: format!("enum {} {{\n{}}}", underlying.hbl, names.concat()),
+            ..underlying
+        };
+        if let Some(tag) = tag {
+            self.tags.insert(tag, ty.clone());
+        }
+
+        Some(ty)
+    }
+
+    /// Evaluates a constant expression up to one of the given tokens.
+    fn const_expr(&mut self, terminators: &[&str]) -> Option<Int> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token.text {
+                "(" | "[" => depth += 1,
+                ")" | "]" if depth == 0 => break,
+                ")" | "]" => depth -= 1,
+                text if depth == 0 && terminators.contains(&text) => break,
+                ";" | "{" | "}" => break,
+                _ => (),
+            }
+            self.bump();
+        }
+
+        let tokens: Vec<_> = self.tokens[start..self.pos]
+            .iter()
+            .map(|token| token.text)
+            .collect();
+        self.eval(&tokens, 0)
+    }
+
+    /// Evaluates the tokens of a constant expression.
+    ///
+    /// `depth` counts the macros that are expanded, to stop recursive ones.
+    fn eval(&self, tokens: &[&'src str], depth: usize) -> Option<Int> {
+        let mut evaluator = ConstEval {
+            tokens,
+            pos: 0,
+            translator: self,
+            depth,
+        };
+        let value = evaluator.binary(0)?;
+
+        (evaluator.pos == tokens.len()).then_some(value)
+    }
+}
+
+/// Evaluates constant expressions with precedence climbing.
+struct ConstEval<'eval, 'src> {
+    /// The tokens of the expression.
+    tokens: &'eval [&'src str],
+    /// The index of the next token.
+    pos: usize,
+    /// The translator that knows the macros and constants.
+    translator: &'eval Translator<'src>,
+    /// The number of macros that are being expanded.
+    depth: usize,
+}
+
+impl ConstEval<'_, '_> {
+    /// Returns the precedence of a binary operator, with higher ones binding more tightly.
+    fn precedence(op: &str) -> Option<u8> {
+        Some(match op {
+            "|" => 1,
+            "^" => 2,
+            "&" => 3,
+            "<<" | ">>" => 4,
+            "+" | "-" => 5,
+            "*" | "/" | "%" => 6,
+            _ => return None,
+        })
+    }
+
+    /// Evaluates binary operators that bind at least as tightly as `min_precedence`.
+    fn binary(&mut self, min_precedence: u8) -> Option<Int> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.tokens.get(self.pos).copied()
+            && let Some(precedence) = Self::precedence(op)
+            && precedence >= min_precedence
+        {
+            self.pos += 1;
+            let rhs = self.binary(precedence + 1)?;
+            lhs = match op {
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "<<" => lhs << usize::try_from(rhs).ok()?,
+                ">>" => lhs >> usize::try_from(rhs).ok()?,
+                "+" => lhs + rhs,
+                "-" => lhs - rhs,
+                "*" => lhs * rhs,
+                "/" if rhs != Int::from(0) => lhs / rhs,
+                "%" if rhs != Int::from(0) => lhs % rhs,
+                _ => return None,
+            };
+        }
+
+        Some(lhs)
+    }
+
+    /// Evaluates a unary operator or an atom.
+    fn unary(&mut self) -> Option<Int> {
+        let token = *self.tokens.get(self.pos)?;
+        self.pos += 1;
+
+        match token {
+            "-" => Some(-self.unary()?),
+            "+" => self.unary(),
+            "~" => Some(-self.unary()? - 1),
+            "(" => {
+                let value = self.binary(0)?;
+                (self.tokens.get(self.pos) == Some(&")")).then(|| {
+                    self.pos += 1;
+                    value
+                })
+            }
+            "sizeof" => {
+                let end = self.tokens[self.pos..].iter().position(|t| *t == ")")?;
+                let words: Vec<_> = self.tokens[self.pos + 1..self.pos + end]
+                    .iter()
+                    .copied()
+                    .filter(|word| !matches!(*word, "struct" | "enum" | "const"))
+                    .collect();
+                self.pos += end + 1;
+                let size = builtin_type(&words)
+                    .or_else(|| {
+                        let [word] = words[..] else {
+                            return None;
+                        };
+                        (self.translator.types.get(word))
+                            .or_else(|| self.translator.tags.get(word))
+                            .cloned()
+                    })?
+                    .size?;
+                Some(Int::from(size))
+            }
+            _ if token.starts_with('\'') => char_value(token).map(Int::from),
+            _ if token.starts_with(|c: char| c.is_ascii_digit()) => c_int(token),
+            _ => {
+                if let Some(value) = self.translator.constants.get(token) {
+                    return Some(value.clone());
+                }
+                let define = self.translator.defines.get(token)?;
+                if self.depth > 16 {
+                    return None;
+                }
+                let tokens: Vec<_> = tokenize(define).iter().map(|token| token.text).collect();
+                self.translator.eval(&tokens, self.depth + 1)
+            }
+        }
+    }
+}
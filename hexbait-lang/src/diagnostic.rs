@@ -0,0 +1,165 @@
+//! Implements rendering problems in format descriptions with excerpts of their source.
+
+use std::fmt::Write as _;
+
+use crate::{ParseError, Span, ir::Spanned};
+
+/// The number of columns that a tab is shown as.
+const TAB_WIDTH: usize = 4;
+
+/// The maximum number of lines of source shown for a single problem.
+const MAX_SHOWN_LINES: usize = 4;
+
+/// A problem in a format description at a location in its source.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The message describing the problem.
+    pub message: String,
+    /// The location of the problem.
+    pub span: Span,
+    /// An additional note that is shown next to the marked location, if there is one.
+    pub label: Option<String>,
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let label = match &err.expected[..] {
+            [] => None,
+            [expected] => Some(format!("expected {expected}")),
+            [expected @ .., last] => Some(format!("expected {} or {last}", expected.join(", "))),
+        };
+
+        Diagnostic {
+            message: err.message.clone(),
+            span: err.span,
+            label,
+        }
+    }
+}
+
+impl From<&Spanned<String>> for Diagnostic {
+    fn from(problem: &Spanned<String>) -> Self {
+        Diagnostic {
+            message: problem.inner.clone(),
+            span: problem.span,
+            label: None,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders the problem with an excerpt of the source it is found in.
+    ///
+    /// `name` is used to refer to the source, for example its path.
+    ///
+    /// ```
+    /// # use hexbait_lang::{Diagnostic, parse};
+    /// let src = "magic u32;\nsize u16 = (magic + 2;\n";
+    /// let parse = parse(src);
+    /// let diagnostic = Diagnostic::from(&parse.errors[0]);
+    ///
+    /// assert_eq!(
+    ///     diagnostic.render("example.hbl", src),
+    ///     "\
+    /// error: expected something else
+    ///  --> example.hbl:2:22
+    ///   |
+    /// 2 | size u16 = (magic + 2;
+    ///   |                      ^ expected `)`
+    /// ",
+    /// );
+    /// ```
+    pub fn render(&self, name: &str, src: &str) -> String {
+        let start = floor_char_boundary(src, self.span.start());
+        let end = floor_char_boundary(src, self.span.end()).max(start);
+
+        // a span that ends directly after a line break does not mark the following line
+        let last = if end > start && src[..end].ends_with('\n') {
+            end - 1
+        } else {
+            end
+        };
+        let first_line = src[..start].matches('\n').count();
+        let last_line = src[..last].matches('\n').count();
+
+        let lines: Vec<_> = src.split('\n').collect();
+        let gutter = (last_line + 1).to_string().len();
+
+        let mut out = String::new();
+        let line_start = src[..start].rfind('\n').map_or(0, |pos| pos + 1);
+        let column = src[line_start..start].chars().count() + 1;
+        let _ = writeln!(out, "error: {}", self.message);
+        let _ = writeln!(out, "{:gutter$}--> {name}:{}:{column}", "", first_line + 1);
+        let _ = writeln!(out, "{:gutter$} |", "");
+
+        let mut offset: usize = lines[..first_line].iter().map(|line| line.len() + 1).sum();
+        for (i, line) in lines
+            .iter()
+            .enumerate()
+            .take(last_line + 1)
+            .skip(first_line)
+        {
+            let line_offset = offset;
+            offset += line.len() + 1;
+
+            // only the first and the last lines of long spans are shown
+            let shown = i - first_line;
+            if last_line - first_line >= MAX_SHOWN_LINES
+                && shown >= MAX_SHOWN_LINES - 1
+                && i != last_line
+            {
+                if shown == MAX_SHOWN_LINES - 1 {
+                    let _ = writeln!(out, "{:gutter$}...", "");
+                }
+                continue;
+            }
+
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            // the indentation of following lines is not marked
+            let indentation = line.len() - line.trim_start().len();
+            let marked_start = if i == first_line {
+                (start - line_offset).min(line.len())
+            } else {
+                indentation
+            };
+            let marked_end = end
+                .saturating_sub(line_offset)
+                .clamp(marked_start, line.len());
+            let indent = display_width(&line[..marked_start]);
+            let carets = display_width(&line[marked_start..marked_end]).max(1);
+
+            let _ = writeln!(out, "{:>gutter$} | {}", i + 1, expand_tabs(line));
+            let _ = write!(out, "{:gutter$} | {:indent$}{}", "", "", "^".repeat(carets));
+            match &self.label {
+                Some(label) if i == last_line => {
+                    let _ = writeln!(out, " {label}");
+                }
+                _ => out.push('\n'),
+            }
+        }
+
+        out
+    }
+}
+
+/// Returns the largest character boundary in `src` that is not larger than `index`.
+fn floor_char_boundary(src: &str, index: usize) -> usize {
+    let mut index = index.min(src.len());
+    while !src.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+/// Returns the number of columns that the text takes up when shown.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Replaces the tabs in the text with spaces, so that markers below it line up.
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
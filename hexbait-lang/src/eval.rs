@@ -1,10 +1,27 @@
 //! Implements evaluation of the parser.
 
+mod classification;
+mod coverage;
+mod export;
+mod locations;
 pub(crate) mod parse;
 mod provenance;
+mod transform;
 mod value;
 pub(crate) mod view;
 
-pub use parse::{ParseErr, ParseErrId, ParseResult, ParseWarning, eval_ir};
+pub use coverage::Coverage;
+pub(crate) use export::write_yaml;
+pub use export::{ExportFormat, export_value, value_to_json};
+pub use locations::{ValueLocation, value_locations};
+pub use parse::{
+    CancellationToken, DEFAULT_MAX_DEPTH, EvalLimits, EvalOptions, LazyElements, ParseErr,
+    ParseErrId, ParseErrKind, ParseResult, ParseWarning, Pointer, eval_ir, eval_ir_with_options,
+};
+pub use provenance::Provenance;
+pub use transform::{
+    ByteTransform, DecompressionError, MAX_DECOMPRESSED_LEN, TransformError, TransformedBytes,
+    UPDATE_SEQUENCE_STRIDE, UpdateSequence, UpdateSequenceError, decompress,
+};
 pub use value::{BytesValue, Value, ValueKind};
 pub use view::View;
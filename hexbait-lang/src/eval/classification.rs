@@ -0,0 +1,110 @@
+//! Implements displaying values according to their classification.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat};
+
+use crate::{
+    Int,
+    ir::{Classification, TimestampEncoding},
+};
+
+use super::value::ValueKind;
+
+/// The seconds between the start of Windows FILETIMEs (1601-01-01) and the UNIX epoch.
+const FILETIME_UNIX_DIFF_SECS: i64 = 11_644_473_600;
+
+/// The seconds between the start of HFS timestamps (1904-01-01) and the UNIX epoch.
+const HFS_UNIX_DIFF_SECS: i64 = 2_082_844_800;
+
+impl Classification {
+    /// Formats the given value as described by the classification.
+    ///
+    /// Returns `None` if the value cannot be interpreted as described, for example because it is
+    /// out of range.
+    ///
+    /// ```
+    /// # use hexbait_lang::{ValueKind, ir::{Classification, TimestampEncoding}};
+    /// let classification = Classification::Timestamp(TimestampEncoding::Unix);
+    /// let value = ValueKind::Integer(1_700_000_000.into());
+    ///
+    /// assert_eq!(
+    ///     classification.format(&value).as_deref(),
+    ///     Some("2023-11-14T22:13:20Z"),
+    /// );
+    /// ```
+    pub fn format(self, value: &ValueKind) -> Option<String> {
+        match (self, value) {
+            (Classification::Offset, ValueKind::Integer(int)) => Some(format!("{int:#x}")),
+            (Classification::Length, ValueKind::Integer(int)) => {
+                if *int == Int::from(1) {
+                    Some(String::from("1 byte"))
+                } else {
+                    Some(format!("{int} bytes"))
+                }
+            }
+            (Classification::Timestamp(encoding), ValueKind::Integer(int)) => {
+                format_timestamp(encoding, i64::try_from(int).ok()?)
+            }
+            (Classification::Guid, ValueKind::Bytes(bytes)) => {
+                let bytes = <[u8; 16]>::try_from(&*bytes.value().ok()?).ok()?;
+
+                Some(format_guid(bytes))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Formats the given timestamp as an ISO 8601 date.
+fn format_timestamp(encoding: TimestampEncoding, int: i64) -> Option<String> {
+    let (secs, nsecs) = match encoding {
+        TimestampEncoding::Filetime => (
+            int.div_euclid(10_000_000) - FILETIME_UNIX_DIFF_SECS,
+            int.rem_euclid(10_000_000) as u32 * 100,
+        ),
+        TimestampEncoding::Unix => (int, 0),
+        TimestampEncoding::UnixMillis => (
+            int.div_euclid(1000),
+            int.rem_euclid(1000) as u32 * 1_000_000,
+        ),
+        TimestampEncoding::Hfs => (int.checked_sub(HFS_UNIX_DIFF_SECS)?, 0),
+        TimestampEncoding::Dos => {
+            // MS-DOS timestamps are in local time, so they are shown without a time zone
+            return Some(
+                dos_datetime(u32::try_from(int).ok()?)?
+                    .to_string()
+                    .replace(' ', "T"),
+            );
+        }
+    };
+
+    Some(DateTime::from_timestamp(secs, nsecs)?.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// Decodes an MS-DOS date (upper 16 bits) and time (lower 16 bits).
+fn dos_datetime(int: u32) -> Option<NaiveDateTime> {
+    let date = int >> 16;
+    let time = int & 0xffff;
+
+    let year = 1980 + (date >> 9) as i32;
+    let day = NaiveDate::from_ymd_opt(year, (date >> 5) & 0xf, date & 0x1f)?;
+
+    day.and_hms_opt(time >> 11, (time >> 5) & 0x3f, (time & 0x1f) * 2)
+}
+
+/// Formats the given bytes as a GUID in its canonical form.
+///
+/// The first three groups of a GUID are stored in little endian.
+fn format_guid(bytes: [u8; 16]) -> String {
+    let data1 = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let data2 = u16::from_le_bytes([bytes[4], bytes[5]]);
+    let data3 = u16::from_le_bytes([bytes[6], bytes[7]]);
+    let data4: String = bytes[10..]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    format!(
+        "{{{data1:08x}-{data2:04x}-{data3:04x}-{:02x}{:02x}-{data4}}}",
+        bytes[8], bytes[9]
+    )
+}
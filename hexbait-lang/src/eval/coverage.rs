@@ -0,0 +1,108 @@
+//! Implements finding the bytes that were not parsed.
+
+use hexbait_common::{AbsoluteOffset, AbsoluteRange, Len};
+
+use super::value::Value;
+
+/// Which bytes of a range a parsed value originated from and which bytes it did not.
+///
+/// The gaps are the bytes that no field consumed, which is useful for finding the parts of a
+/// format that a format description does not describe yet.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    /// The range that the coverage is computed for.
+    range: AbsoluteRange,
+    /// The disjoint and sorted ranges within the range that were parsed.
+    parsed: Vec<AbsoluteRange>,
+}
+
+impl Coverage {
+    /// Computes which bytes of the range the value originated from.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::{AbsoluteOffset, AbsoluteRange, Input, RelativeOffset};
+    /// # use hexbait_lang::{Coverage, View, eval_ir, ir::lower_file, parse};
+    /// let ir = lower_file(parse("magic u16; !seek to 6; len u16;").ast);
+    /// let view = View::from_input(Input::from_bytes(vec![0; 10]));
+    /// let value = eval_ir(&ir, view, RelativeOffset::ZERO).value;
+    ///
+    /// let range = |start: u64, end: u64| {
+    ///     AbsoluteRange::new(AbsoluteOffset::from(start), AbsoluteOffset::from(end))
+    /// };
+    /// let coverage = Coverage::new(&value, range(0, 10));
+    /// assert_eq!(coverage.gaps().collect::<Vec<_>>(), [range(2, 6), range(8, 10)]);
+    /// assert_eq!(coverage.ratio(), 0.4);
+    /// ```
+    pub fn new(value: &Value, range: AbsoluteRange) -> Coverage {
+        Coverage {
+            range,
+            parsed: value
+                .provenance
+                .byte_ranges_in(range)
+                .filter_map(|parsed| parsed.intersection(range))
+                .filter(|parsed| !parsed.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Computes which bytes between the first and the last byte the value originated from were
+    /// parsed.
+    pub fn of_value(value: &Value) -> Coverage {
+        let mut ranges = value.provenance.byte_ranges();
+        let range = match (ranges.next(), ranges.last()) {
+            (Some(first), Some(last)) => AbsoluteRange::new(first.start(), last.end()),
+            (Some(first), None) => first,
+            (None, _) => AbsoluteRange::empty_from_start(AbsoluteOffset::from(0)),
+        };
+
+        Coverage::new(value, range)
+    }
+
+    /// The range that the coverage is computed for.
+    pub fn range(&self) -> AbsoluteRange {
+        self.range
+    }
+
+    /// Returns the ranges of the bytes that were parsed in ascending order.
+    pub fn parsed(&self) -> impl Iterator<Item = AbsoluteRange> {
+        self.parsed.iter().copied()
+    }
+
+    /// Returns the ranges of the bytes that were not parsed in ascending order.
+    pub fn gaps(&self) -> impl Iterator<Item = AbsoluteRange> {
+        let starts = std::iter::once(self.range.start()).chain(self.parsed.iter().map(|r| r.end()));
+        let ends = self
+            .parsed
+            .iter()
+            .map(|r| r.start())
+            .chain(std::iter::once(self.range.end()));
+
+        starts
+            .zip(ends)
+            .map(|(start, end)| AbsoluteRange::new(start, end))
+            .filter(|gap| !gap.is_empty())
+    }
+
+    /// The number of bytes that were parsed.
+    pub fn parsed_len(&self) -> Len {
+        Len::from(
+            self.parsed
+                .iter()
+                .map(|range| range.size().as_u64())
+                .sum::<u64>(),
+        )
+    }
+
+    /// The fraction of the bytes in the range that were parsed.
+    ///
+    /// An empty range counts as fully parsed.
+    pub fn ratio(&self) -> f64 {
+        if self.range.is_empty() {
+            return 1.0;
+        }
+
+        self.parsed_len().as_u64() as f64 / self.range.size().as_u64() as f64
+    }
+}
@@ -0,0 +1,298 @@
+//! Implements exporting parsed values as text.
+
+use std::{fmt::Write as _, str::FromStr as _};
+
+use serde_json::Number;
+
+use crate::ir::Classification;
+
+use super::{
+    parse::LazyElements,
+    value::{Value, ValueKind},
+};
+
+/// A format in which parsed values can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// JSON, where bytes are hex strings.
+    Json,
+    /// YAML, where bytes are hex strings.
+    Yaml,
+    /// A Rust expression, where `struct`s are named after their fields.
+    RustLiteral,
+}
+
+impl ExportFormat {
+    /// All supported export formats.
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Json,
+        ExportFormat::Yaml,
+        ExportFormat::RustLiteral,
+    ];
+
+    /// Returns the name of the format for displaying.
+    pub fn display_str(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Yaml => "YAML",
+            ExportFormat::RustLiteral => "Rust literal",
+        }
+    }
+}
+
+/// Exports the given parsed value as text in the given format.
+///
+/// If `with_offsets` is set, the location of every value in the input is included.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::{Input, RelativeOffset};
+/// # use hexbait_lang::{ExportFormat, View, eval_ir, export_value, ir::lower_file, parse};
+/// let ir = lower_file(parse("!endian le; magic u8; len u16;").ast);
+/// let view = View::from_input(Input::from_bytes(vec![0x7f, 0x10, 0x00]));
+/// let value = eval_ir(&ir, view, RelativeOffset::ZERO).value;
+///
+/// assert_eq!(
+///     export_value(&value, ExportFormat::Yaml, false),
+///     "magic: 127\nlen: 16",
+/// );
+/// assert_eq!(
+///     export_value(&value, ExportFormat::RustLiteral, false),
+///     "Value {\n    magic: 127,\n    len: 16,\n}",
+/// );
+/// ```
+pub fn export_value(value: &Value, format: ExportFormat, with_offsets: bool) -> String {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&value_to_json(value, with_offsets))
+            .expect("JSON values can always be serialized"),
+        ExportFormat::Yaml => {
+            let mut out = String::new();
+            write_yaml(&mut out, &value_to_json(value, with_offsets), 0);
+            out.trim_start().to_string()
+        }
+        ExportFormat::RustLiteral => {
+            let mut out = String::new();
+            write_rust(&mut out, value, "Value", 0, with_offsets);
+            out
+        }
+    }
+}
+
+/// Converts the given parsed value to JSON.
+///
+/// Bytes are converted to hex strings, timestamps to ISO 8601 dates, GUIDs to their canonical form
+/// and values with a symbolic name to their name. If `with_offsets` is set, every value is wrapped
+/// in an object containing the `offset` of its first byte, its `size` in bytes and the `value`
+/// itself.
+pub fn value_to_json(value: &Value, with_offsets: bool) -> serde_json::Value {
+    let json = match &value.kind {
+        _ if let Some(name) = &value.name => serde_json::Value::String(name.to_string()),
+        _ if let Some(classification @ (Classification::Timestamp(_) | Classification::Guid)) =
+            value.classification
+            && let Some(formatted) = classification.format(&value.kind) =>
+        {
+            serde_json::Value::String(formatted)
+        }
+        ValueKind::Boolean(val) => serde_json::Value::Bool(*val),
+        ValueKind::Integer(val) => {
+            let num = if let Ok(num) = u128::try_from(val) {
+                Number::from_u128(num)
+            } else if let Ok(num) = i128::try_from(val) {
+                Number::from_i128(num)
+            } else {
+                Number::from_str(&val.to_string()).ok()
+            };
+            num.map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null)
+        }
+        ValueKind::Float(val) => Number::from_f64(*val)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueKind::Bytes(val) => match val.value() {
+            Ok(bytes) => serde_json::Value::String(bytes.iter().fold(
+                String::with_capacity(bytes.len() * 2),
+                |mut hex, byte| {
+                    let _ = write!(hex, "{byte:02x}");
+                    hex
+                },
+            )),
+            Err(_) => serde_json::Value::Null,
+        },
+        ValueKind::Struct { fields, .. } => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(name, val)| (name.as_str().to_string(), value_to_json(val, with_offsets)))
+                .collect(),
+        ),
+        ValueKind::Array { items, lazy, .. } => serde_json::Value::Array(
+            items
+                .iter()
+                .chain(&evaluate_lazy(lazy))
+                .map(|item| value_to_json(item, with_offsets))
+                .collect(),
+        ),
+    };
+
+    if !with_offsets {
+        return json;
+    }
+
+    let (offset, size) = location(value);
+    serde_json::Value::Object(
+        [
+            (
+                String::from("offset"),
+                offset.map_or(serde_json::Value::Null, serde_json::Value::from),
+            ),
+            (String::from("size"), serde_json::Value::from(size)),
+            (String::from("value"), json),
+        ]
+        .into_iter()
+        .collect(),
+    )
+}
+
+/// Evaluates all lazy elements of an array, so that they can be exported.
+fn evaluate_lazy(lazy: &Option<LazyElements>) -> Vec<Value> {
+    lazy.as_ref()
+        .map(|lazy| lazy.evaluate(0..lazy.len()).value.kind.expect_array_take())
+        .unwrap_or_default()
+}
+
+/// Returns the offset of the first byte of the value and its size in bytes.
+fn location(value: &Value) -> (Option<u64>, u64) {
+    let offset = value
+        .provenance
+        .byte_ranges()
+        .next()
+        .map(|range| range.start().as_u64());
+    let size = value
+        .provenance
+        .byte_ranges()
+        .map(|range| range.size().as_u64())
+        .sum();
+
+    (offset, size)
+}
+
+/// Writes the value as YAML.
+///
+/// Scalars are written on the current line and collections on the following lines, so the value
+/// can follow a key or an item marker.
+pub(crate) fn write_yaml(out: &mut String, value: &serde_json::Value, indent: usize) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let _ = write!(out, "\n{:indent$}{key}:", "");
+                write_yaml(out, value, indent + 2);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                let _ = write!(out, "\n{:indent$}-", "");
+                write_yaml(out, item, indent + 2);
+            }
+        }
+        // JSON scalars are valid YAML flow scalars
+        scalar => {
+            let _ = write!(out, " {scalar}");
+        }
+    }
+}
+
+/// Writes the value as a Rust expression.
+///
+/// `name` is the name of the `struct` if the value is one.
+fn write_rust(out: &mut String, value: &Value, name: &str, indent: usize, with_offsets: bool) {
+    match &value.kind {
+        ValueKind::Boolean(val) => {
+            let _ = write!(out, "{val}");
+        }
+        ValueKind::Integer(val) => {
+            let _ = write!(out, "{val}");
+            if let Some(name) = &value.name {
+                let _ = write!(out, " /* {name} */");
+            }
+        }
+        ValueKind::Float(val) => {
+            let _ = write!(out, "{val:?}");
+        }
+        ValueKind::Bytes(val) => match val.value() {
+            Ok(bytes) => {
+                let _ = write!(out, "b\"{}\"", bytes.escape_ascii());
+            }
+            Err(err) => {
+                let _ = write!(out, "b\"\" /* unreadable: {err} */");
+            }
+        },
+        ValueKind::Struct { fields, .. } => {
+            let _ = write!(out, "{name} {{");
+            for (field, value) in fields {
+                let _ = write!(
+                    out,
+                    "\n{:width$}{}: ",
+                    "",
+                    field.as_str(),
+                    width = indent + 4
+                );
+                write_rust(
+                    out,
+                    value,
+                    &type_name(field.as_str()),
+                    indent + 4,
+                    with_offsets,
+                );
+                out.push(',');
+                write_rust_location(out, value, with_offsets);
+            }
+            let _ = write!(out, "\n{:indent$}}}", "");
+        }
+        ValueKind::Array { items, lazy, .. } => {
+            out.push('[');
+            let lazy_items = evaluate_lazy(lazy);
+            for item in items.iter().chain(&lazy_items) {
+                let _ = write!(out, "\n{:width$}", "", width = indent + 4);
+                write_rust(out, item, name, indent + 4, with_offsets);
+                out.push(',');
+                write_rust_location(out, item, with_offsets);
+            }
+            if !items.is_empty() || !lazy_items.is_empty() {
+                let _ = write!(out, "\n{:indent$}", "");
+            }
+            out.push(']');
+        }
+    }
+}
+
+/// Writes a comment containing the location of the value, if locations are included.
+fn write_rust_location(out: &mut String, value: &Value, with_offsets: bool) {
+    if !with_offsets {
+        return;
+    }
+
+    if let (Some(offset), size) = location(value) {
+        let _ = write!(out, " // at {offset:#x}, {size} bytes");
+    }
+}
+
+/// Converts the name of a field to the name of a type in `PascalCase`.
+fn type_name(field: &str) -> String {
+    let name: String = field
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    if name.is_empty() {
+        String::from("Value")
+    } else {
+        name
+    }
+}
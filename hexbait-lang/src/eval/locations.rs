@@ -0,0 +1,91 @@
+//! Implements listing where the values within a parsed value are located in the input.
+
+use hexbait_common::AbsoluteRange;
+
+use crate::ir::{
+    Classification,
+    path::{Path, PathComponent},
+};
+
+use super::value::{Value, ValueKind};
+
+/// A byte range of the input that a value within a parsed value originated from.
+#[derive(Debug, Clone)]
+pub struct ValueLocation<'value> {
+    /// The path from the parsed value to the value.
+    pub path: Path,
+    /// The value.
+    pub value: &'value Value,
+    /// The byte range that the value originated from.
+    pub range: AbsoluteRange,
+    /// The classification of the value, if it has one.
+    pub classification: Option<Classification>,
+}
+
+/// Returns the byte ranges that the given value and all values within it originated from.
+///
+/// The values are visited in depth-first order, with every value before the values within it.
+/// A value that originated from several byte ranges appears once for each of them, in ascending
+/// order. Values that did not originate from the input and the lazy elements of arrays are
+/// skipped.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::{Input, RelativeOffset};
+/// # use hexbait_lang::{View, eval_ir, ir::{Classification, lower_file}, parse, value_locations};
+/// let ir = lower_file(parse("magic u8; header { @offset start u16; };").ast);
+/// let view = View::from_input(Input::from_bytes(vec![0x7f, 0x10, 0x00]));
+/// let value = eval_ir(&ir, view, RelativeOffset::ZERO).value;
+///
+/// let locations: Vec<_> = value_locations(&value).collect();
+/// assert_eq!(locations.len(), 4);
+/// assert_eq!(locations[0].range.size().as_u64(), 3);
+///
+/// let start = &locations[3];
+/// assert_eq!(start.value, value.subvalue_at_path(&start.path).unwrap());
+/// assert_eq!(start.range.start().as_u64(), 1);
+/// assert_eq!(start.classification, Some(Classification::Offset));
+/// ```
+pub fn value_locations(value: &Value) -> impl Iterator<Item = ValueLocation<'_>> {
+    let mut stack = vec![(Path::new(), value)];
+
+    std::iter::from_fn(move || {
+        let (path, value) = stack.pop()?;
+
+        match &value.kind {
+            ValueKind::Struct { fields, .. } => {
+                for (name, field) in fields.iter().rev() {
+                    let mut path = path.clone();
+                    path.push(PathComponent::FieldAccess(name.clone()));
+                    stack.push((path, field));
+                }
+            }
+            ValueKind::Array { items, .. } => {
+                for (i, item) in items.iter().enumerate().rev() {
+                    let mut path = path.clone();
+                    path.push(PathComponent::Indexing(i));
+                    stack.push((path, item));
+                }
+            }
+            ValueKind::Boolean(_)
+            | ValueKind::Integer(_)
+            | ValueKind::Float(_)
+            | ValueKind::Bytes(_) => (),
+        }
+
+        let locations: Vec<_> = value
+            .provenance
+            .byte_ranges()
+            .map(|range| ValueLocation {
+                path: path.clone(),
+                value,
+                range,
+                classification: value.classification,
+            })
+            .collect();
+
+        Some(locations)
+    })
+    .flatten()
+}
@@ -1,26 +1,47 @@
 //! Implements the parsing evaluation logic.
 
-use std::{fmt, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use crate::{
     BytesValue, Int, Span,
     eval::parse::diagnostics::ParseErrWithMaybePartialResult,
     ir::{
-        BinOp, ConcatArg, Declaration, ElsePart, Expr, ExprKind, File, IfChain, LetStatement, Lit,
-        ParseType, ParseTypeKind, RepeatKind, ScopeKind, StructContent, StructField, Symbol, UnOp,
+        BinOp, BytePattern, Classification, ConcatArg, Declaration, ElsePart, Expr, ExprKind, File,
+        IfChain, LetStatement, Lit, NamedStruct, ParseType, ParseTypeKind, RecoveryKind,
+        RepeatKind, ScopeKind, StructContent, StructField, Symbol, UnOp,
     },
 };
 
 use super::{
     provenance::Provenance,
+    transform::{DecompressionError, decompress},
     value::{Value, ValueKind},
     view::View,
 };
 
+use bitfield::flags_value;
 pub use diagnostics::{ParseErr, ParseErrId, ParseErrKind, ParseWarning};
-use hexbait_common::{Endianness, Len, ReadBytes, RelativeOffset};
-
+use hexbait_common::{Endianness, Input, Len, ReadBytes, RelativeOffset};
+use lazy::ArrayBuilder;
+pub use lazy::{LazyElements, Pointer};
+use num_traits::{ToPrimitive as _, Zero as _};
+
+mod bitfield;
+mod builtins;
+mod bytecode;
 mod diagnostics;
+mod indexing;
+mod lazy;
+mod recovery;
+mod varint;
 
 /// An offset in bytes to parse from.
 #[derive(Debug, Clone, Copy)]
@@ -47,21 +68,168 @@ pub struct ParseResult {
     pub warnings: Vec<ParseWarning>,
 }
 
+/// A token that allows cancelling an evaluation from another thread.
+///
+/// Clones of the token refer to the same evaluation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    /// Whether the evaluation was cancelled.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the evaluation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Determines if the evaluation was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The maximum number of `struct`s that are parsed within each other by default.
+///
+/// Every nested `struct` uses stack space, so this keeps recursive `struct`s from overflowing even
+/// the 2 MiB stack of a thread in a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Limits on the resources that an evaluation may use.
+///
+/// Exceeding a limit fails the evaluation with an error of kind [`ParseErrKind::LimitExceeded`].
+/// Limits that are `None` are not enforced. By default only the depth is limited, to
+/// [`DEFAULT_MAX_DEPTH`].
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::{Input, RelativeOffset};
+/// # use hexbait_lang::{ParseErrKind, View, eval_ir, ir::lower_file, parse};
+/// let ir = lower_file(parse("struct s { x s; } a s;").ast);
+/// let view = View::from_input(Input::from_bytes(Vec::new()));
+///
+/// let result = eval_ir(&ir, view, RelativeOffset::ZERO);
+/// assert!(matches!(result.errors[0].kind, ParseErrKind::LimitExceeded));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EvalLimits {
+    /// The maximum number of elements of a single array.
+    pub max_repetitions: Option<usize>,
+    /// The maximum number of `struct`s that are parsed within each other.
+    pub max_depth: Option<usize>,
+    /// The maximum total number of bytes that are read from the input.
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for EvalLimits {
+    fn default() -> EvalLimits {
+        EvalLimits {
+            max_repetitions: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_bytes: None,
+        }
+    }
+}
+
+/// Options that control the evaluation of IR.
+#[derive(Debug, Clone, Default)]
+pub struct EvalOptions {
+    /// Allows cancelling the evaluation from another thread.
+    pub cancellation: CancellationToken,
+    /// The limits on the resources that the evaluation may use.
+    pub limits: EvalLimits,
+    /// The number of elements that are kept of arrays with more elements.
+    ///
+    /// The remaining elements are still parsed to find out where the array ends, but they are
+    /// not kept. Instead they can be evaluated again on demand using the [`LazyElements`] of the
+    /// array. If this is `None`, all elements are kept.
+    pub lazy_array_threshold: Option<usize>,
+}
+
 /// Evaluates the given IR on the given input.
+///
+/// # Example
+///
+/// The elements of long arrays are evaluated by compiling their type, which gives the same values
+/// as evaluating each element on its own.
+///
+/// ```rust
+/// # use hexbait_common::{Input, RelativeOffset};
+/// # use hexbait_lang::{View, eval_ir, ir::lower_file, parse};
+/// for endianness in ["le", "be", "pdp", "be_ws16", "le_ws32", "be_ws32"] {
+///     for ty in ["u16", "i32", "u64", "f32", "f64"] {
+///         let src = format!("!endian {endianness}; items [{ty}] len 40;");
+///         let ir = lower_file(parse(&src).ast);
+///         let bytes = [0x01, 0x02, 0x03, 0x84, 0x05, 0x06, 0x07, 0x08].repeat(40);
+///         let view = View::from_input(Input::from_bytes(bytes));
+///         let value = eval_ir(&ir, view, RelativeOffset::ZERO).value;
+///
+///         // every 8 elements start at the same bytes
+///         let items = value.kind.expect_struct()[0].1.kind.expect_array();
+///         for (i, item) in items.iter().enumerate() {
+///             assert!(item.kind == items[i % 8].kind, "{src}");
+///         }
+///     }
+/// }
+/// ```
 pub fn eval_ir(file: &File, view: View, start_offset: RelativeOffset) -> ParseResult {
+    eval_ir_with_options(file, view, start_offset, EvalOptions::default())
+}
+
+/// Evaluates the given IR on the given input with the given options.
+///
+/// If the evaluation is cancelled, the values parsed so far are returned together with an error
+/// of kind [`ParseErrKind::Cancelled`]. Likewise, if it exceeds one of the limits of the options,
+/// the error is of kind [`ParseErrKind::LimitExceeded`].
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_common::{Input, RelativeOffset};
+/// # use hexbait_lang::{EvalOptions, ParseErrKind, View, eval_ir_with_options, ir::lower_file, parse};
+/// let ir = lower_file(parse("!endian le; magic u8; len u16;").ast);
+/// let view = View::from_input(Input::from_bytes(vec![0x7f, 0x10, 0x00]));
+///
+/// let options = EvalOptions::default();
+/// options.cancellation.cancel();
+/// let result = eval_ir_with_options(&ir, view, RelativeOffset::ZERO, options);
+/// assert!(matches!(result.errors[0].kind, ParseErrKind::Cancelled));
+///
+/// let view = View::from_input(Input::from_bytes(vec![0x7f, 0x10, 0x00]));
+/// let mut options = EvalOptions::default();
+/// options.limits.max_bytes = Some(2);
+/// let result = eval_ir_with_options(&ir, view, RelativeOffset::ZERO, options);
+/// assert!(matches!(result.errors[0].kind, ParseErrKind::LimitExceeded));
+/// ```
+#[tracing::instrument(level = "debug", skip_all, fields(start_offset = start_offset.as_u64()))]
+pub fn eval_ir_with_options(
+    file: &File,
+    view: View,
+    start_offset: RelativeOffset,
+    options: EvalOptions,
+) -> ParseResult {
     let mut struct_ctx = StructContext::new();
     let mut scope = Scope::new(view);
     scope.offset = ByteOffset(start_offset);
 
-    let mut parse_ctx = ParseContext {
-        errors: Vec::new(),
-        warnings: Vec::new(),
-    };
+    let mut parse_ctx = ParseContext::new(options, Arc::clone(&file.structs));
 
     scope
         .eval_struct_content(&file.content, &mut struct_ctx, &mut parse_ctx)
         .ok();
 
+    tracing::debug!(
+        errors = parse_ctx.errors.len(),
+        warnings = parse_ctx.warnings.len(),
+        "evaluation finished"
+    );
+
     ParseResult {
         value: struct_ctx.into_value(),
         errors: parse_ctx.errors,
@@ -69,6 +237,39 @@ pub fn eval_ir(file: &File, view: View, start_offset: RelativeOffset) -> ParseRe
     }
 }
 
+/// Evaluates an expression that does not depend on the input, such as the value of a constant.
+///
+/// The expression must only consist of literals, operators and function calls. If the evaluation
+/// fails, the error message is returned.
+pub(crate) fn eval_const_expr(expr: &Expr) -> Result<Lit, String> {
+    let scope = Scope::new(View::from_input(Input::from_bytes(Vec::new())));
+    let struct_ctx = StructContext::new();
+    let mut parse_ctx = ParseContext::new(EvalOptions::default(), Arc::default());
+
+    let value = scope
+        .eval_expr(expr, &struct_ctx, &mut parse_ctx, Default::default())
+        .map_err(|_| {
+            parse_ctx
+                .errors
+                .pop()
+                .map_or_else(|| "evaluation failed".into(), |err| err.message)
+        })?;
+
+    Ok(match value.kind {
+        ValueKind::Integer(int) => Lit::Int(int),
+        ValueKind::Float(float) => Lit::Float(float),
+        ValueKind::Boolean(val) => Lit::Bool(val),
+        ValueKind::Bytes(bytes) => {
+            let mut buf = vec![0; bytes.len()];
+            bytes
+                .fill_buf_at(0, &mut buf)
+                .map_err(|err| err.to_string())?;
+            Lit::Bytes(buf.into())
+        }
+        _ => return Err("the value is not an integer, float, boolean or bytes".into()),
+    })
+}
+
 macro_rules! impossible {
     () => {
         unreachable!("impossible because of static analysis")
@@ -82,13 +283,84 @@ struct ParseContext {
     errors: Vec<ParseErr>,
     /// The warnings that occurred during parsing.
     warnings: Vec<ParseWarning>,
+    /// The options of the evaluation.
+    options: EvalOptions,
+    /// The named `struct`s that can be parsed.
+    structs: Arc<HashMap<Symbol, NamedStruct>>,
+    /// The number of `struct`s that are currently parsed within each other.
+    depth: usize,
+    /// The number of bytes that were read from the input so far.
+    bytes_read: u64,
 }
 
 impl ParseContext {
+    /// Creates a new parsing context with the given options and named `struct`s.
+    fn new(options: EvalOptions, structs: Arc<HashMap<Symbol, NamedStruct>>) -> ParseContext {
+        ParseContext {
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            options,
+            structs,
+            depth: 0,
+            bytes_read: 0,
+        }
+    }
+
     /// Creates a new error in the parsing context.
     fn new_err(&mut self, err: ParseErr) -> ParseErrId {
         ParseErrId::new(err, &mut self.errors)
     }
+
+    /// Returns an error if the parsing was cancelled.
+    fn check_cancelled(&mut self, provenance: Provenance, span: Span) -> Result<(), ParseErrId> {
+        if self.options.cancellation.is_cancelled() {
+            Err(self.new_err(ParseErr {
+                message: "parsing was cancelled".into(),
+                kind: ParseErrKind::Cancelled,
+                provenance,
+                span,
+            }))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns an error if an array that already has the given number of elements must not get
+    /// another one.
+    fn check_repetitions(
+        &mut self,
+        len: usize,
+        provenance: Provenance,
+        span: Span,
+    ) -> Result<(), ParseErrId> {
+        match self.options.limits.max_repetitions {
+            Some(max) if len >= max => Err(self.new_err(ParseErr {
+                message: format!("array has more than the maximum of {max} elements"),
+                kind: ParseErrKind::LimitExceeded,
+                provenance,
+                span,
+            })),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that the given number of bytes are read, unless that exceeds the limit.
+    ///
+    /// Returns whether the bytes may be read.
+    fn try_read(&mut self, count: Len) -> bool {
+        let bytes_read = self.bytes_read.saturating_add(count.as_u64());
+        if self
+            .options
+            .limits
+            .max_bytes
+            .is_some_and(|max| bytes_read > max)
+        {
+            return false;
+        }
+        self.bytes_read = bytes_read;
+
+        true
+    }
 }
 
 /// The different recovery strategies.
@@ -101,13 +373,24 @@ enum RecoveryStrategy {
         /// The offset to skip to.
         offset: ByteOffset,
     },
+    /// Skips to the next occurrence of the given pattern.
+    Scan {
+        /// The pattern to search for.
+        pattern: BytePattern,
+        /// The span of the pattern expression.
+        span: Span,
+    },
 }
 
 /// The parsing context for a `struct`.
 #[derive(Debug)]
 struct StructContext<'parent> {
     /// The already parsed fields.
+    ///
+    /// These start with the arguments of the `struct`.
     parsed_fields: Vec<(Symbol, Value)>,
+    /// The number of arguments at the start of the parsed fields.
+    num_args: usize,
     /// The parent `struct`.
     parent: Option<&'parent StructContext<'parent>>,
     /// The recovery strategy to use if parsing fails.
@@ -123,6 +406,7 @@ impl<'parent> StructContext<'parent> {
     fn new() -> StructContext<'static> {
         StructContext {
             parsed_fields: Vec::new(),
+            num_args: 0,
             parent: None,
             recovery_strategy: RecoveryStrategy::Fallback,
             error: None,
@@ -135,6 +419,7 @@ impl<'parent> StructContext<'parent> {
     fn child<'this>(&'this self) -> StructContext<'this> {
         StructContext {
             parsed_fields: Vec::new(),
+            num_args: 0,
             parent: Some(self),
             recovery_strategy: RecoveryStrategy::Fallback,
             error: None,
@@ -156,13 +441,18 @@ impl<'parent> StructContext<'parent> {
                 error: self.error,
             },
             provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
         }
     }
 
     /// Turns the `struct` context into a fully parsed `struct`.
     fn into_value(self) -> Value {
         let mut provenance = Provenance::empty();
-        for (_, value) in &self.parsed_fields {
+        for (_, value) in &self.parsed_fields[self.num_args..] {
             provenance += &value.provenance;
         }
 
@@ -171,11 +461,17 @@ impl<'parent> StructContext<'parent> {
                 fields: self
                     .parsed_fields
                     .into_iter()
+                    .skip(self.num_args)
                     .filter(|(name, _)| !name.as_str().starts_with('_'))
                     .collect(),
                 error: self.error,
             },
             provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
         }
     }
 }
@@ -219,9 +515,27 @@ impl Scope {
         parse_ctx: &mut ParseContext,
     ) -> Result<(ReadBytes<'_>, Provenance), ParseErrId> {
         let start = self.offset.0;
+        parse_ctx.check_cancelled(
+            self.view.provenance_from_range(start..start + Len::from(1)),
+            span,
+        )?;
+        if !parse_ctx.try_read(count) {
+            return Err(parse_ctx.new_err(ParseErr {
+                message: format!(
+                    "reading more than the maximum of {} bytes",
+                    parse_ctx.options.limits.max_bytes.unwrap_or_default()
+                ),
+                kind: ParseErrKind::LimitExceeded,
+                provenance: self.view.provenance_from_range(start..start + Len::from(1)),
+                span,
+            }));
+        }
 
         let view_len = self.view.len();
-        if RelativeOffset::from(view_len.as_u64()) < start + count {
+        if start
+            .checked_add(count)
+            .is_none_or(|end| end > view_len.to_relative())
+        {
             return Err(parse_ctx.new_err(ParseErr {
                 message: "view is too short".into(),
                 kind: ParseErrKind::InputTooShort,
@@ -253,6 +567,57 @@ impl Scope {
         Ok((buf, provenance))
     }
 
+    /// Evaluates the terminator of a repetition to the bytes it consists of.
+    fn eval_terminator(
+        &mut self,
+        terminator: &Expr,
+        struct_ctx: &StructContext,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Vec<u8>, ParseErrWithMaybePartialResult> {
+        let value = self.eval_expr(terminator, struct_ctx, parse_ctx, Default::default())?;
+
+        match value.kind.expect_bytes().value() {
+            Ok(bytes) => Ok(bytes.to_vec()),
+            Err(err) => Err(parse_ctx
+                .new_err(ParseErr {
+                    message: format!("could not read the terminator: {err}"),
+                    kind: ParseErrKind::Io(err),
+                    provenance: value.provenance,
+                    span: terminator.span,
+                })
+                .into()),
+        }
+    }
+
+    /// Checks whether the upcoming bytes are equal to the given terminator.
+    ///
+    /// If the view ends before the terminator could be read, the terminator is not reached.
+    fn at_terminator(
+        &self,
+        terminator: &[u8],
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<bool, ParseErrId> {
+        let start = self.offset.0;
+        let len = Len::from(terminator.len() as u64);
+        if start
+            .checked_add(len)
+            .is_none_or(|end| end > self.view.len().to_relative())
+        {
+            return Ok(false);
+        }
+
+        match self.view.read_at(start, len) {
+            Ok(buf) => Ok(*buf == *terminator),
+            Err(err) => Err(parse_ctx.new_err(ParseErr {
+                message: format!("io error: {err}"),
+                kind: ParseErrKind::Io(err),
+                provenance: self.view.provenance_from_range(start..start + len),
+                span,
+            })),
+        }
+    }
+
     /// Evaluates the given expression.
     fn eval_expr(
         &self,
@@ -265,10 +630,17 @@ impl Scope {
             ExprKind::Lit(lit) => Ok(Value {
                 kind: match lit {
                     Lit::Int(int) => ValueKind::Integer(int.clone()),
+                    Lit::Float(float) => ValueKind::Float(*float),
                     Lit::Bytes(bytes) => ValueKind::Bytes(BytesValue::Lit(Arc::clone(bytes))),
+                    Lit::BytePattern(_) => impossible!(),
                     Lit::Bool(val) => ValueKind::Boolean(*val),
                 },
                 provenance: Provenance::empty(),
+                name: None,
+                classification: None,
+                color: None,
+                doc: None,
+                pointer: None,
             }),
             ExprKind::VarUse(var) => {
                 for (name, val) in &struct_ctx.parsed_fields {
@@ -281,24 +653,98 @@ impl Scope {
             ExprKind::Offset => Ok(Value {
                 kind: ValueKind::Integer(Int::from(self.offset.0.as_u64())),
                 provenance: Provenance::empty(),
+                name: None,
+                classification: None,
+                color: None,
+                doc: None,
+                pointer: None,
+            }),
+            ExprKind::End => Ok(Value {
+                kind: ValueKind::Integer(Int::from(self.view.len().as_u64())),
+                provenance: Provenance::empty(),
+                name: None,
+                classification: None,
+                color: None,
+                doc: None,
+                pointer: None,
             }),
             ExprKind::Parent => Ok(struct_ctx.parent.static_analysis_expect().as_value()),
             ExprKind::Last => Ok(additional_ctx.last.static_analysis_expect().clone()),
             ExprKind::Len => Ok(additional_ctx.len.static_analysis_expect().clone()),
+            ExprKind::StartOf(field) => {
+                let value = self.eval_expr(field, struct_ctx, parse_ctx, additional_ctx)?;
+
+                let Some(first_range) = value.provenance.byte_ranges().next() else {
+                    return Err(parse_ctx.new_err(ParseErr {
+                        message: "the value was not parsed from the input".into(),
+                        kind: ParseErrKind::NotInInput,
+                        provenance: Provenance::empty(),
+                        span: field.span,
+                    }));
+                };
+
+                Ok(Value {
+                    kind: ValueKind::Integer(Int::from(first_range.start().as_u64())),
+                    provenance: value.provenance,
+                    name: None,
+                    classification: Some(Classification::Offset),
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            ExprKind::SizeOf(field) => {
+                let value = self.eval_expr(field, struct_ctx, parse_ctx, additional_ctx)?;
+                let size: u64 = value
+                    .provenance
+                    .byte_ranges()
+                    .map(|range| range.size().as_u64())
+                    .sum();
+
+                Ok(Value {
+                    kind: ValueKind::Integer(Int::from(size)),
+                    provenance: value.provenance,
+                    name: None,
+                    classification: Some(Classification::Length),
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
             ExprKind::UnOp { op, operand } => {
                 let Value {
                     kind: operand,
                     provenance,
+                    ..
                 } = self.eval_expr(operand, struct_ctx, parse_ctx, additional_ctx)?;
 
                 Ok(match op {
+                    UnOp::Neg if let ValueKind::Float(float) = operand => Value {
+                        kind: ValueKind::Float(-float),
+                        provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
+                    },
                     UnOp::Neg => Value {
                         kind: ValueKind::Integer(-operand.expect_int()),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     UnOp::Plus => Value {
                         kind: operand,
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     UnOp::Not => todo!(),
                 })
@@ -307,6 +753,7 @@ impl Scope {
                 let Value {
                     kind: lhs,
                     mut provenance,
+                    ..
                 } = self.eval_expr(lhs, struct_ctx, parse_ctx, additional_ctx)?;
 
                 match op {
@@ -314,12 +761,22 @@ impl Scope {
                         return Ok(Value {
                             kind: ValueKind::Boolean(false),
                             provenance,
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
                         });
                     }
                     BinOp::LogicalOr if lhs.expect_bool() => {
                         return Ok(Value {
                             kind: ValueKind::Boolean(true),
                             provenance,
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
                         });
                     }
                     _ => (),
@@ -328,12 +785,31 @@ impl Scope {
                 let Value {
                     kind: rhs,
                     provenance: rhs_provenance,
+                    ..
                 } = self.eval_expr(rhs, struct_ctx, parse_ctx, additional_ctx)?;
                 provenance += &rhs_provenance;
 
+                if let Some((lhs, rhs)) = float_operands(&lhs, &rhs)
+                    && let Some(kind) = eval_float_op(op, lhs, rhs)
+                {
+                    return Ok(Value {
+                        kind,
+                        provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
+                    });
+                }
+
+                /// The result of an integer operation that can fail, with the kind and message of
+                /// the error.
+                type FallibleResult = Result<Int, (ParseErrKind, &'static str)>;
+
                 enum OpKind {
                     IntOp(fn(&Int, &Int) -> Int),
-                    FallibleIntOp(fn(&Int, &Int) -> Result<Int, String>),
+                    FallibleIntOp(fn(&Int, &Int) -> FallibleResult),
                     CmpOp(fn(&Int, &Int) -> bool),
                     Eq,
                     Neq,
@@ -344,8 +820,23 @@ impl Scope {
                     BinOp::Add => OpKind::IntOp(|x, y| x + y),
                     BinOp::Sub => OpKind::IntOp(|x, y| x - y),
                     BinOp::Mul => OpKind::IntOp(|x, y| x * y),
-                    BinOp::Div => OpKind::IntOp(|x, y| x / y),
-                    BinOp::Mod => OpKind::IntOp(|x, y| x % y),
+                    BinOp::Div => OpKind::FallibleIntOp(|x, y| {
+                        if y.is_zero() {
+                            Err((ParseErrKind::DivisionByZero, "division by zero"))
+                        } else {
+                            Ok(x / y)
+                        }
+                    }),
+                    BinOp::Mod => OpKind::FallibleIntOp(|x, y| {
+                        if y.is_zero() {
+                            Err((
+                                ParseErrKind::DivisionByZero,
+                                "remainder with a divisor of zero",
+                            ))
+                        } else {
+                            Ok(x % y)
+                        }
+                    }),
                     BinOp::Eq => OpKind::Eq,
                     BinOp::Neq => OpKind::Neq,
                     BinOp::Gt => OpKind::CmpOp(|x, y| x > y),
@@ -357,12 +848,12 @@ impl Scope {
                     BinOp::BitXor => OpKind::IntOp(|x, y| x ^ y),
                     BinOp::ShiftLeft => OpKind::FallibleIntOp(|x, y| {
                         u32::try_from(y)
-                            .map_err(|_| "shift offset too large".to_string())
+                            .map_err(|_| (ParseErrKind::ArithmeticError, "shift offset too large"))
                             .map(|y| x << y)
                     }),
                     BinOp::ShiftRight => OpKind::FallibleIntOp(|x, y| {
                         u32::try_from(y)
-                            .map_err(|_| "shift offset too large".to_string())
+                            .map_err(|_| (ParseErrKind::ArithmeticError, "shift offset too large"))
                             .map(|y| x >> y)
                     }),
                     BinOp::LogicalAnd | BinOp::LogicalOr => OpKind::BoolRhsIdentity,
@@ -372,38 +863,69 @@ impl Scope {
                     OpKind::IntOp(func) => Value {
                         kind: ValueKind::Integer(func(lhs.expect_int(), rhs.expect_int())),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     OpKind::FallibleIntOp(func) => {
-                        let value =
-                            func(lhs.expect_int(), rhs.expect_int()).map_err(|message| {
+                        let value = func(lhs.expect_int(), rhs.expect_int()).map_err(
+                            |(kind, message)| {
                                 parse_ctx.new_err(ParseErr {
-                                    message,
-                                    kind: ParseErrKind::ArithmeticError,
+                                    message: message.to_string(),
+                                    kind,
                                     provenance: provenance.clone(),
                                     span: expr.span,
                                 })
-                            })?;
+                            },
+                        )?;
 
                         Value {
                             kind: ValueKind::Integer(value),
                             provenance,
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
                         }
                     }
                     OpKind::CmpOp(func) => Value {
                         kind: ValueKind::Boolean(func(lhs.expect_int(), rhs.expect_int())),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     OpKind::Eq => Value {
                         kind: ValueKind::Boolean(lhs == rhs),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     OpKind::Neq => Value {
                         kind: ValueKind::Boolean(lhs != rhs),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                     OpKind::BoolRhsIdentity => Value {
                         kind: ValueKind::Boolean(rhs.expect_bool()),
                         provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
                     },
                 })
             }
@@ -471,8 +993,41 @@ impl Scope {
                 Ok(Value {
                     kind: ValueKind::Bytes(BytesValue::Concat { parts }),
                     provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
                 })
             }
+            ExprKind::Call { func, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg, struct_ctx, parse_ctx, additional_ctx))
+                    .collect::<Result<_, _>>()?;
+
+                self.eval_call(*func, args, expr.span, parse_ctx)
+            }
+            ExprKind::Index {
+                expr: indexed,
+                index,
+            } => {
+                let indexed = self.eval_expr(indexed, struct_ctx, parse_ctx, additional_ctx)?;
+                let index = self.eval_expr(index, struct_ctx, parse_ctx, additional_ctx)?;
+
+                self.eval_index(indexed, index, expr.span, parse_ctx)
+            }
+            ExprKind::Slice {
+                expr: sliced,
+                start,
+                end,
+            } => {
+                let sliced = self.eval_expr(sliced, struct_ctx, parse_ctx, additional_ctx)?;
+                let start = self.eval_expr(start, struct_ctx, parse_ctx, additional_ctx)?;
+                let end = self.eval_expr(end, struct_ctx, parse_ctx, additional_ctx)?;
+
+                self.eval_slice(sliced, start, end, expr.span, parse_ctx)
+            }
             ExprKind::Error => impossible!(),
         }
     }
@@ -573,7 +1128,7 @@ impl Scope {
                                     .into());
                             }
                         } else {
-                            RelativeOffset::from(self.view.len().as_u64())
+                            self.view.len().to_relative()
                         };
 
                         self.view.subview(start..end)
@@ -584,6 +1139,28 @@ impl Scope {
 
                         View::from_bytes(bytes_expr.kind.expect_bytes_take())
                     }
+                    ScopeKind::Decompressed { compression, bytes } => {
+                        let bytes_expr =
+                            self.eval_expr(bytes, struct_ctx, parse_ctx, Default::default())?;
+                        let compressed = bytes_expr.kind.expect_bytes_take();
+
+                        match decompress(*compression, &compressed) {
+                            Ok(data) => View::from_decompressed(data, compressed),
+                            Err(err) => {
+                                return Err(parse_ctx
+                                    .new_err(ParseErr {
+                                        message: err.to_string(),
+                                        kind: match err {
+                                            DecompressionError::Io(err) => ParseErrKind::Io(err),
+                                            _ => ParseErrKind::InvalidTransform,
+                                        },
+                                        provenance: bytes_expr.provenance,
+                                        span: bytes.span,
+                                    })
+                                    .into());
+                            }
+                        }
+                    }
                 };
 
                 let mut scope =
@@ -652,7 +1229,46 @@ impl Scope {
                     });
                 }
             }
-            Declaration::Recover { at } => {
+            Declaration::Verify {
+                computed,
+                expected,
+                message,
+            } => {
+                let computed_value =
+                    self.eval_expr(computed, struct_ctx, parse_ctx, Default::default())?;
+                let expected_value =
+                    self.eval_expr(expected, struct_ctx, parse_ctx, Default::default())?;
+                if computed_value != expected_value {
+                    let mut text = format!(
+                        "checksum mismatch: computed {}, expected {}",
+                        checksum_display(&computed_value.kind),
+                        checksum_display(&expected_value.kind),
+                    );
+                    if let Some(message) = message {
+                        let message_val =
+                            self.eval_expr(message, struct_ctx, parse_ctx, Default::default())?;
+                        text.push_str(": ");
+                        text.push_str(match message_val.kind.expect_bytes() {
+                            BytesValue::Lit(lit) => {
+                                std::str::from_utf8(lit).static_analysis_expect()
+                            }
+                            _ => impossible!(),
+                        });
+                    }
+
+                    parse_ctx.warnings.push(ParseWarning {
+                        message: text,
+                        provenance: &computed_value.provenance + &expected_value.provenance,
+                        span: Span {
+                            start: computed.span.start,
+                            end: expected.span.end,
+                        },
+                    });
+                }
+            }
+            Declaration::Recover {
+                kind: RecoveryKind::At { offset: at },
+            } => {
                 let offset = self.eval_expr(at, struct_ctx, parse_ctx, Default::default())?;
                 if let Ok(offset) = u64::try_from(offset.kind.expect_int())
                     && let Some(offset) = offset.checked_add(struct_ctx.start_offset.0.as_u64())
@@ -672,6 +1288,41 @@ impl Scope {
                         .into());
                 }
             }
+            Declaration::Recover {
+                kind: RecoveryKind::Scan { pattern },
+            } => {
+                let byte_pattern = if let ExprKind::Lit(Lit::BytePattern(byte_pattern)) =
+                    &pattern.kind
+                {
+                    byte_pattern.clone()
+                } else {
+                    let value =
+                        self.eval_expr(pattern, struct_ctx, parse_ctx, Default::default())?;
+                    let bytes = match value.kind.expect_bytes().value() {
+                        Ok(bytes) => bytes,
+                        Err(err) => {
+                            return Err(parse_ctx
+                                .new_err(ParseErr {
+                                    message: format!("could not read the recovery pattern: {err}"),
+                                    kind: ParseErrKind::Io(err),
+                                    provenance: value.provenance,
+                                    span: pattern.span,
+                                })
+                                .into());
+                        }
+                    };
+
+                    BytePattern {
+                        mask: vec![0xff; bytes.len()].into(),
+                        bytes: bytes.to_vec().into(),
+                    }
+                };
+
+                struct_ctx.recovery_strategy = RecoveryStrategy::Scan {
+                    pattern: byte_pattern,
+                    span: pattern.span,
+                };
+            }
         }
 
         Ok(())
@@ -746,6 +1397,11 @@ impl Scope {
                 buf,
             }),
             provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
         })
     }
 
@@ -757,8 +1413,26 @@ impl Scope {
         parse_ctx: &mut ParseContext,
     ) -> Result<Value, ParseErrWithMaybePartialResult> {
         let value = match &parse_type.kind {
-            ParseTypeKind::Named { name } => {
-                todo!("trying to parse named `{name:?}` unimplemented")
+            ParseTypeKind::Named { name, args } => {
+                let structs = Arc::clone(&parse_ctx.structs);
+                let named_struct = structs.get(&name.inner).static_analysis_expect();
+
+                let mut params = Vec::with_capacity(args.len());
+                for (param, arg) in named_struct.params.iter().zip(args) {
+                    let value = self.eval_expr(arg, struct_ctx, parse_ctx, Default::default())?;
+                    params.push((param.inner.clone(), value));
+                }
+
+                let mut value = self.eval_struct(
+                    &named_struct.content,
+                    params,
+                    parse_type.span,
+                    struct_ctx,
+                    parse_ctx,
+                )?;
+                value.color = named_struct.color.clone();
+
+                value
             }
             ParseTypeKind::Bytes { repetition_kind } => match repetition_kind {
                 RepeatKind::Len { count: count_expr } => {
@@ -793,6 +1467,11 @@ impl Scope {
                                 len: Some(&Value {
                                     kind: ValueKind::Integer(Int::from(len)),
                                     provenance: Provenance::empty(),
+                                    name: None,
+                                    classification: None,
+                                    color: None,
+                                    doc: None,
+                                    pointer: None,
                                 }),
                             },
                         )?
@@ -805,6 +1484,11 @@ impl Scope {
                         last_byte = Some(Value {
                             kind: ValueKind::Integer(bytes[0].into()),
                             provenance,
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
                         });
                         len += 1;
                     }
@@ -812,6 +1496,19 @@ impl Scope {
 
                     self.read_bytes_value(len, parse_type.span, parse_ctx)?
                 }
+                RepeatKind::Until { terminator } => {
+                    let terminator = self.eval_terminator(terminator, struct_ctx, parse_ctx)?;
+
+                    let start_offset = self.offset;
+                    let mut len = 0;
+                    while !self.at_terminator(&terminator, parse_type.span, parse_ctx)? {
+                        self.read_bytes(Len::from(1), parse_type.span, parse_ctx)?;
+                        len += 1;
+                    }
+                    self.offset = start_offset;
+
+                    self.read_bytes_value(len, parse_type.span, parse_ctx)?
+                }
                 RepeatKind::Error => impossible!(),
             },
             ParseTypeKind::Integer { signed, .. }
@@ -837,7 +1534,7 @@ impl Scope {
 
                 assert!(
                     bit_width % 8 == 0,
-                    "non byte aligned integers currently unimplemented"
+                    "non byte aligned integers are only implemented in bitfields"
                 );
                 let size_in_bytes = (bit_width / 8) as usize;
 
@@ -848,20 +1545,59 @@ impl Scope {
                     parse_ctx,
                 )?;
 
-                let num = match (endianness, signed) {
-                    (Endianness::Little, true) => Int::from_signed_bytes_le(&parsed_bytes),
-                    (Endianness::Big, true) => Int::from_signed_bytes_be(&parsed_bytes),
-                    (Endianness::Little, false) => {
-                        Int::from_bytes_le(num_bigint::Sign::Plus, &parsed_bytes)
-                    }
-                    (Endianness::Big, false) => {
-                        Int::from_bytes_be(num_bigint::Sign::Plus, &parsed_bytes)
-                    }
+                let le_bytes = if endianness == Endianness::Little {
+                    Cow::Borrowed(&*parsed_bytes)
+                } else {
+                    let mut bytes = parsed_bytes.to_vec();
+                    endianness.reorder_to_little_endian(&mut bytes);
+                    Cow::Owned(bytes)
+                };
+
+                let num = if signed {
+                    Int::from_signed_bytes_le(&le_bytes)
+                } else {
+                    Int::from_bytes_le(num_bigint::Sign::Plus, &le_bytes)
                 };
 
                 Value {
                     kind: ValueKind::Integer(num),
                     provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                }
+            }
+            ParseTypeKind::Leb128 { signed } => {
+                self.eval_leb128(*signed, parse_type.span, parse_ctx)?
+            }
+            ParseTypeKind::Float { bit_width } => {
+                let size_in_bytes = (bit_width / 8) as usize;
+
+                let (parsed_bytes, provenance) = self.read_bytes(
+                    Len::from(u64::try_from(size_in_bytes).unwrap()),
+                    parse_type.span,
+                    parse_ctx,
+                )?;
+
+                let mut le_bytes = parsed_bytes.to_vec();
+                self.endianness.reorder_to_little_endian(&mut le_bytes);
+
+                let float = match *bit_width {
+                    32 => f64::from(f32::from_le_bytes(le_bytes.try_into().unwrap())),
+                    64 => f64::from_le_bytes(le_bytes.try_into().unwrap()),
+                    _ => impossible!(),
+                };
+
+                Value {
+                    kind: ValueKind::Float(float),
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
                 }
             }
             ParseTypeKind::Repeating {
@@ -872,35 +1608,7 @@ impl Scope {
                     let count_val =
                         self.eval_expr(count, struct_ctx, parse_ctx, Default::default())?;
 
-                    let mut values = Vec::new();
-                    let mut provenance = Provenance::empty();
-
-                    if let Ok(count) = u64::try_from(count_val.kind.expect_int()) {
-                        for _ in 0..count {
-                            match self.eval_parse_type(parse_type, struct_ctx, parse_ctx) {
-                                Ok(parsed_value) => {
-                                    provenance += &parsed_value.provenance;
-                                    values.push(parsed_value);
-                                }
-                                Err(err) => {
-                                    if let Some(partial_result) = err.partial_result {
-                                        provenance += &partial_result.provenance;
-                                        values.push(partial_result);
-                                    }
-                                    return Err(ParseErrWithMaybePartialResult {
-                                        parse_err: err.parse_err,
-                                        partial_result: Some(Value {
-                                            kind: ValueKind::Array {
-                                                items: values,
-                                                error: Some(err.parse_err),
-                                            },
-                                            provenance,
-                                        }),
-                                    });
-                                }
-                            };
-                        }
-                    } else {
+                    let Ok(count) = u64::try_from(count_val.kind.expect_int()) else {
                         return Err(ParseErrWithMaybePartialResult {
                             parse_err: parse_ctx.new_err(ParseErr {
                                 message: "count too large".into(),
@@ -910,19 +1618,18 @@ impl Scope {
                             }),
                             partial_result: None,
                         });
-                    }
+                    };
 
-                    Value {
-                        kind: ValueKind::Array {
-                            items: values,
-                            error: None,
-                        },
-                        provenance,
+                    let mut array = ArrayBuilder::new(parse_type, struct_ctx, parse_ctx);
+                    for _ in 0..count {
+                        array = self.eval_array_element(array, parse_ctx)?;
                     }
+
+                    array.finish(None)
                 }
                 crate::ir::RepeatKind::While { condition } => {
-                    let mut values = Vec::new();
-                    let mut provenance = Provenance::empty();
+                    let mut array =
+                        ArrayBuilder::new(parse_type, struct_ctx, parse_ctx).keeping_last();
 
                     while self
                         .eval_expr(
@@ -930,64 +1637,40 @@ impl Scope {
                             struct_ctx,
                             parse_ctx,
                             AdditionalExprContext {
-                                last: values.last(),
+                                last: array.last(),
                                 len: Some(&Value {
-                                    kind: ValueKind::Integer(Int::from(values.len())),
+                                    kind: ValueKind::Integer(Int::from(array.len())),
                                     provenance: Provenance::empty(),
+                                    name: None,
+                                    classification: None,
+                                    color: None,
+                                    doc: None,
+                                    pointer: None,
                                 }),
                             },
                         )?
                         .kind
                         .expect_bool()
                     {
-                        match self.eval_parse_type(parse_type, struct_ctx, parse_ctx) {
-                            Ok(parsed_value) => {
-                                provenance += &parsed_value.provenance;
-                                values.push(parsed_value);
-                            }
-                            Err(err) => {
-                                if let Some(partial_result) = err.partial_result {
-                                    provenance += &partial_result.provenance;
-                                    values.push(partial_result);
-                                }
-                                return Err(ParseErrWithMaybePartialResult {
-                                    parse_err: err.parse_err,
-                                    partial_result: Some(Value {
-                                        kind: ValueKind::Array {
-                                            items: values,
-                                            error: Some(err.parse_err),
-                                        },
-                                        provenance,
-                                    }),
-                                });
-                            }
-                        };
+                        array = self.eval_array_element(array, parse_ctx)?;
                     }
 
-                    Value {
-                        kind: ValueKind::Array {
-                            items: values,
-                            error: None,
-                        },
-                        provenance,
+                    array.finish(None)
+                }
+                crate::ir::RepeatKind::Until { terminator } => {
+                    let terminator = self.eval_terminator(terminator, struct_ctx, parse_ctx)?;
+
+                    let mut array = ArrayBuilder::new(parse_type, struct_ctx, parse_ctx);
+                    while !self.at_terminator(&terminator, parse_type.span, parse_ctx)? {
+                        array = self.eval_array_element(array, parse_ctx)?;
                     }
+
+                    array.finish(None)
                 }
                 crate::ir::RepeatKind::Error => impossible!(),
             },
             ParseTypeKind::Struct { content } => {
-                let mut ctx = struct_ctx.child();
-
-                match self.eval_struct_content(content, &mut ctx, parse_ctx) {
-                    Ok(()) => ctx.into_value(),
-                    Err(mut err) => {
-                        // the partial result should have already been added at this point
-                        assert!(err.partial_result.is_none());
-
-                        err.partial_result = Some(ctx.into_value());
-
-                        Err(err)?
-                    }
-                }
+                self.eval_struct(content, Vec::new(), parse_type.span, struct_ctx, parse_ctx)?
             }
             ParseTypeKind::Switch {
                 scrutinee,
@@ -1008,12 +1691,121 @@ impl Scope {
                     self.eval_parse_type(default, struct_ctx, parse_ctx)?
                 }
             }
+            ParseTypeKind::Bitfield { bit_width, fields } => {
+                self.eval_bitfield(*bit_width, fields, parse_type.span, parse_ctx)?
+            }
+            ParseTypeKind::Enum { parse_type, names } => {
+                let mut value = self.eval_parse_type(parse_type, struct_ctx, parse_ctx)?;
+
+                let int = value.kind.expect_int();
+                value.name = names
+                    .iter()
+                    .find(|(named, _)| named == int)
+                    .map(|(_, name)| Arc::clone(name));
+
+                value
+            }
+            ParseTypeKind::Flags { parse_type, flags } => {
+                let value = self.eval_parse_type(parse_type, struct_ctx, parse_ctx)?;
+
+                flags_value(value, flags)
+            }
+            ParseTypeKind::Pointer {
+                parse_type: int_type,
+                target,
+                base,
+            } => {
+                let mut value = self.eval_parse_type(int_type, struct_ctx, parse_ctx)?;
+                let base = match base {
+                    Some(base) => self
+                        .eval_expr(base, struct_ctx, parse_ctx, Default::default())?
+                        .kind
+                        .expect_int()
+                        .clone(),
+                    None => Int::from(0),
+                };
+
+                // pointers to targets outside of the scope, such as null pointers, have no target
+                if let Ok(offset) = u64::try_from(base + value.kind.expect_int())
+                    && Len::from(offset) < self.view.len()
+                {
+                    value.pointer = Some(Pointer::new(
+                        target,
+                        self,
+                        ByteOffset(RelativeOffset::from(offset)),
+                        struct_ctx,
+                        parse_ctx,
+                    ));
+                }
+
+                value
+            }
+            ParseTypeKind::Classified {
+                parse_type,
+                classification,
+            } => {
+                let mut value = self.eval_parse_type(parse_type, struct_ctx, parse_ctx)?;
+                value.classification = Some(*classification);
+
+                value
+            }
             ParseTypeKind::Error => impossible!(),
         };
 
         Ok(value)
     }
 
+    /// Evaluates a `struct` with the given content as a child of the given `struct`.
+    ///
+    /// The given arguments are available like fields in the `struct`, but are not part of the
+    /// result.
+    /// Changes to the endianness within the `struct` are undone once it ends.
+    fn eval_struct(
+        &mut self,
+        content: &[StructContent],
+        args: Vec<(Symbol, Value)>,
+        span: Span,
+        struct_ctx: &StructContext,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrWithMaybePartialResult> {
+        if let Some(max) = parse_ctx.options.limits.max_depth
+            && parse_ctx.depth >= max
+        {
+            let start = self.offset.0;
+            return Err(parse_ctx
+                .new_err(ParseErr {
+                    message: format!("`struct`s are nested deeper than the maximum of {max}"),
+                    kind: ParseErrKind::LimitExceeded,
+                    provenance: self.view.provenance_from_range(start..start + Len::from(1)),
+                    span,
+                })
+                .into());
+        }
+
+        let mut ctx = struct_ctx.child();
+        ctx.num_args = args.len();
+        ctx.parsed_fields = args;
+
+        // declarations such as `!endian` only apply until the end of the `struct`
+        let endianness = self.endianness;
+        parse_ctx.depth += 1;
+        let result = self.eval_struct_content(content, &mut ctx, parse_ctx);
+        parse_ctx.depth -= 1;
+        self.endianness = endianness;
+
+        match result {
+            Ok(()) => Ok(ctx.into_value()),
+            Err(mut err) => {
+                // the partial result should have already been added at this point
+                assert!(err.partial_result.is_none());
+
+                err.partial_result = Some(Box::new(ctx.into_value()));
+
+                Err(err)
+            }
+        }
+    }
+
     /// Evaluates the given `struct` field.
     fn eval_struct_field(
         &mut self,
@@ -1021,9 +1813,35 @@ impl Scope {
         struct_ctx: &mut StructContext,
         parse_ctx: &mut ParseContext,
     ) -> Result<(), ParseErrWithMaybePartialResult> {
-        let value = self.eval_parse_type(&field.ty, struct_ctx, parse_ctx)?;
+        let mut value = self.eval_parse_type(&field.ty, struct_ctx, parse_ctx)?;
+        if field.classification.is_some() {
+            value.classification = field.classification;
+        }
+        if field.color.is_some() {
+            value.color = field.color.clone();
+        }
+        if field.doc.is_some() {
+            value.doc = field.doc.clone();
+        }
 
-        if let Some(expected) = &field.expected {
+        if let Some(expected) = &field.expected
+            && let ExprKind::Lit(pattern @ Lit::BytePattern(byte_pattern)) = &expected.kind
+        {
+            if value.kind != *pattern {
+                return Err(ParseErrWithMaybePartialResult {
+                    parse_err: parse_ctx.new_err(ParseErr {
+                        message: format!(
+                            "field expectation failed: {byte_pattern} does not match {:?}",
+                            value.kind
+                        ),
+                        kind: ParseErrKind::ExpectationFailure,
+                        provenance: value.provenance.clone(),
+                        span: expected.span,
+                    }),
+                    partial_result: Some(Box::new(value)),
+                });
+            }
+        } else if let Some(expected) = &field.expected {
             let span = expected.span;
             let expected = self.eval_expr(expected, struct_ctx, parse_ctx, Default::default())?;
             if expected != value {
@@ -1037,7 +1855,7 @@ impl Scope {
                         provenance: &value.provenance + &expected.provenance,
                         span,
                     }),
-                    partial_result: Some(value),
+                    partial_result: Some(Box::new(value)),
                 });
             }
         }
@@ -1088,7 +1906,7 @@ impl Scope {
                             // TODO: use resolved names here later
                             struct_ctx
                                 .parsed_fields
-                                .push((field.name.inner.clone(), partial_result));
+                                .push((field.name.inner.clone(), *partial_result));
                         }
                         Err(ParseErrWithMaybePartialResult {
                             parse_err: err.parse_err,
@@ -1128,6 +1946,19 @@ impl Scope {
 
                             return Ok(());
                         }
+                        RecoveryStrategy::Scan { pattern, span } => {
+                            // the pattern may start within the field that failed to parse
+                            let start = struct_ctx.start_offset.0 + Len::from(1);
+
+                            return match self.scan_for(pattern, start, *span, parse_ctx) {
+                                Ok(Some(offset)) => {
+                                    self.offset = ByteOffset(offset);
+
+                                    Ok(())
+                                }
+                                Ok(None) | Err(_) => Err(err),
+                            };
+                        }
                     }
                 }
             }
@@ -1137,6 +1968,67 @@ impl Scope {
     }
 }
 
+/// Returns both operands as floats if at least one of them is a float and the other is numeric.
+fn float_operands(lhs: &ValueKind, rhs: &ValueKind) -> Option<(f64, f64)> {
+    let to_float = |value: &ValueKind| match value {
+        ValueKind::Float(float) => Some(*float),
+        ValueKind::Integer(int) => int.to_f64(),
+        _ => None,
+    };
+
+    match (lhs, rhs) {
+        (ValueKind::Float(_), _) | (_, ValueKind::Float(_)) => {
+            Some((to_float(lhs)?, to_float(rhs)?))
+        }
+        _ => None,
+    }
+}
+
+/// Formats a checksum value for a message.
+fn checksum_display(value: &ValueKind) -> String {
+    match value {
+        ValueKind::Integer(int) => format!("{int:#x}"),
+        ValueKind::Bytes(bytes) => {
+            let mut text = String::from("0x");
+            bytes
+                .for_each_chunk(|chunk| {
+                    for byte in chunk {
+                        text.push_str(&format!("{byte:02x}"));
+                    }
+                })
+                .ok();
+            text
+        }
+        value => format!("{value:?}"),
+    }
+}
+
+/// Evaluates a binary operator on floats.
+///
+/// Returns `None` if the operator is not defined for floats.
+fn eval_float_op(op: &BinOp, lhs: f64, rhs: f64) -> Option<ValueKind> {
+    Some(match op {
+        BinOp::Add => ValueKind::Float(lhs + rhs),
+        BinOp::Sub => ValueKind::Float(lhs - rhs),
+        BinOp::Mul => ValueKind::Float(lhs * rhs),
+        BinOp::Div => ValueKind::Float(lhs / rhs),
+        BinOp::Mod => ValueKind::Float(lhs % rhs),
+        BinOp::Eq => ValueKind::Boolean(lhs == rhs),
+        BinOp::Neq => ValueKind::Boolean(lhs != rhs),
+        BinOp::Gt => ValueKind::Boolean(lhs > rhs),
+        BinOp::Geq => ValueKind::Boolean(lhs >= rhs),
+        BinOp::Lt => ValueKind::Boolean(lhs < rhs),
+        BinOp::Leq => ValueKind::Boolean(lhs <= rhs),
+        BinOp::BitAnd
+        | BinOp::BitOr
+        | BinOp::BitXor
+        | BinOp::ShiftLeft
+        | BinOp::ShiftRight
+        | BinOp::LogicalAnd
+        | BinOp::LogicalOr => return None,
+    })
+}
+
 /// Additional context that can be used during expression evaluation.
 #[derive(Debug, Default, Clone, Copy)]
 struct AdditionalExprContext<'parent> {
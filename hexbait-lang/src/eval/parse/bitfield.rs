@@ -0,0 +1,147 @@
+//! Implements parsing integers whose bits are split into fields.
+
+use hexbait_common::{Endianness, Len};
+use num_traits::Zero as _;
+
+use crate::{
+    Int, Span,
+    eval::{
+        provenance::Provenance,
+        value::{Value, ValueKind},
+    },
+    ir::{BitfieldField, Symbol},
+};
+
+use super::{ParseContext, ParseErrWithMaybePartialResult, Scope};
+
+impl Scope {
+    /// Parses an unsigned integer of the given bit width and splits its bits into the fields.
+    ///
+    /// The fields start at the least significant bit for little endian integers and at the most
+    /// significant bit otherwise.
+    pub(super) fn eval_bitfield(
+        &mut self,
+        bit_width: u32,
+        fields: &[BitfieldField],
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrWithMaybePartialResult> {
+        let size = (bit_width / 8) as u8;
+        let start = self.offset.0;
+        let (bytes, provenance) = self.read_bytes(Len::from(u64::from(size)), span, parse_ctx)?;
+
+        let mut le_bytes = bytes.to_vec();
+        self.endianness.reorder_to_little_endian(&mut le_bytes);
+        let value = Int::from_bytes_le(num_bigint::Sign::Plus, &le_bytes);
+
+        // where each byte of the little endian value is stored, to find the bytes of each field
+        let mut stored_positions: Vec<u8> = (0..size).collect();
+        self.endianness
+            .reorder_to_little_endian(&mut stored_positions);
+
+        let lsb_first = matches!(
+            self.endianness,
+            Endianness::Little | Endianness::Pdp | Endianness::LittleWordSwapped32
+        );
+
+        let mut parsed_fields = Vec::new();
+        let mut used_bits = 0;
+        for field in fields {
+            let shift = if lsb_first {
+                used_bits
+            } else {
+                bit_width - used_bits - field.bit_width
+            };
+            used_bits += field.bit_width;
+
+            if field.name.inner.as_str().starts_with('_') {
+                continue;
+            }
+
+            let modulus = Int::from(1) << field.bit_width;
+            let mut num = (&value >> shift) & (&modulus - Int::from(1));
+            if field.signed && num.bit(u64::from(field.bit_width - 1)) {
+                num -= &modulus;
+            }
+
+            let mut field_provenance = Provenance::empty();
+            for le_byte in shift / 8..=(shift + field.bit_width - 1) / 8 {
+                let stored = start + Len::from(u64::from(stored_positions[le_byte as usize]));
+                field_provenance += &self
+                    .view
+                    .provenance_from_range(stored..stored + Len::from(1));
+            }
+
+            parsed_fields.push((
+                field.name.inner.clone(),
+                Value {
+                    kind: ValueKind::Integer(num),
+                    provenance: field_provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                },
+            ));
+        }
+
+        Ok(Value {
+            kind: ValueKind::Struct {
+                fields: parsed_fields,
+                error: None,
+            },
+            provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        })
+    }
+}
+
+/// Converts the given parsed integer into a `struct` showing which of the named bit masks are set.
+///
+/// Set bits that are not part of any mask are collected in an `unknown_bits` field.
+pub(super) fn flags_value(value: Value, flags: &[(Int, Symbol)]) -> Value {
+    let int = value.kind.expect_int();
+    let flag_value = |kind| Value {
+        kind,
+        provenance: value.provenance.clone(),
+        name: None,
+        classification: None,
+        color: None,
+        doc: None,
+        pointer: None,
+    };
+
+    let mut known_bits = Int::ZERO;
+    let mut fields = Vec::new();
+    for (mask, name) in flags {
+        known_bits |= mask;
+
+        if name.as_str().starts_with('_') {
+            continue;
+        }
+
+        let is_set = (int & mask) == *mask;
+        fields.push((name.clone(), flag_value(ValueKind::Boolean(is_set))));
+    }
+
+    let unknown_bits = int & !known_bits;
+    if !unknown_bits.is_zero() {
+        fields.push((
+            Symbol::from_static("unknown_bits"),
+            flag_value(ValueKind::Integer(unknown_bits)),
+        ));
+    }
+
+    Value {
+        kind: ValueKind::Struct {
+            fields,
+            error: None,
+        },
+        ..value
+    }
+}
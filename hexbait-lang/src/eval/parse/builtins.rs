@@ -0,0 +1,411 @@
+//! Implements the functions that are built into the language.
+
+use std::{cmp::Ordering, io, sync::Arc};
+
+use crc::{CRC_16_ARC, CRC_32_ISCSI, CRC_32_ISO_HDLC, Crc};
+use md5::Md5;
+use num_traits::{Signed as _, ToPrimitive as _, Zero as _};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::{
+    BytesValue, Int, Span,
+    eval::{
+        provenance::Provenance,
+        transform::{
+            ByteTransform, TransformError, TransformedBytes, UPDATE_SEQUENCE_STRIDE,
+            UpdateSequence, UpdateSequenceError,
+        },
+        value::{Value, ValueKind},
+    },
+    ir::Builtin,
+};
+
+use super::{
+    ParseContext, ParseErr, ParseErrId, ParseErrKind, ParseWarning, Scope,
+    StaticAnalysisImpossible as _, float_operands,
+    indexing::{array_elements, indexable_len},
+};
+
+impl Scope {
+    /// Evaluates a call of the built-in function with the given evaluated arguments.
+    pub(super) fn eval_call(
+        &self,
+        func: Builtin,
+        args: Vec<Value>,
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrId> {
+        let mut provenance = Provenance::empty();
+        for arg in &args {
+            provenance += &arg.provenance;
+        }
+
+        match func {
+            Builtin::Fixup => {
+                let [record, offset, count] = <[Value; 3]>::try_from(args).static_analysis_expect();
+                let (Ok(offset), Ok(count)) = (
+                    usize::try_from(offset.kind.expect_int()),
+                    usize::try_from(count.kind.expect_int()),
+                ) else {
+                    return Err(parse_ctx.new_err(ParseErr {
+                        message: "update sequence array offset or size too large".into(),
+                        kind: ParseErrKind::OffsetTooLarge,
+                        provenance,
+                        span,
+                    }));
+                };
+                let record = record.kind.expect_bytes_take();
+
+                let (fixed, torn) = UpdateSequence::read(&record, offset, count)
+                    .and_then(|sequence| sequence.apply(&record))
+                    .map_err(|err| {
+                        parse_ctx.new_err(ParseErr {
+                            message: err.to_string(),
+                            kind: match err {
+                                UpdateSequenceError::Io(err) => ParseErrKind::Io(err),
+                                _ => ParseErrKind::InvalidTransform,
+                            },
+                            provenance: provenance.clone(),
+                            span,
+                        })
+                    })?;
+
+                for sector in torn {
+                    let sector_end = (sector + 1) * UPDATE_SEQUENCE_STRIDE;
+                    parse_ctx.warnings.push(ParseWarning {
+                        message: format!(
+                            "sector {sector} of the record does not end in the update sequence number"
+                        ),
+                        provenance: record.provenance_range(
+                            (sector_end as u64 - 2).into()..(sector_end as u64).into(),
+                        ),
+                        span,
+                    });
+                }
+
+                Ok(Value {
+                    kind: ValueKind::Bytes(fixed),
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Min | Builtin::Max => {
+                let [a, b] = <[Value; 2]>::try_from(args).static_analysis_expect();
+                let ordering = compare_numbers(&a.kind, &b.kind);
+                let kind = match (func, ordering) {
+                    (Builtin::Min, Ordering::Greater) | (Builtin::Max, Ordering::Less) => b.kind,
+                    _ => a.kind,
+                };
+
+                Ok(Value {
+                    kind,
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Abs => {
+                let [x] = <[Value; 1]>::try_from(args).static_analysis_expect();
+                let kind = match x.kind {
+                    ValueKind::Float(float) => ValueKind::Float(float.abs()),
+                    kind => ValueKind::Integer(kind.expect_int().abs()),
+                };
+
+                Ok(Value {
+                    kind,
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Clamp => {
+                let [x, low, high] = <[Value; 3]>::try_from(args).static_analysis_expect();
+                if compare_numbers(&low.kind, &high.kind) == Ordering::Greater {
+                    return Err(parse_ctx.new_err(ParseErr {
+                        message: "the lower bound of `clamp` is larger than the upper bound".into(),
+                        kind: ParseErrKind::ArithmeticError,
+                        provenance,
+                        span,
+                    }));
+                }
+
+                let kind = if compare_numbers(&x.kind, &low.kind) == Ordering::Less {
+                    low.kind
+                } else if compare_numbers(&x.kind, &high.kind) == Ordering::Greater {
+                    high.kind
+                } else {
+                    x.kind
+                };
+
+                Ok(Value {
+                    kind,
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::AlignUp | Builtin::AlignDown => {
+                let [x, alignment] = <[Value; 2]>::try_from(args).static_analysis_expect();
+                let x = x.kind.expect_int();
+                let alignment = alignment.kind.expect_int();
+                if !alignment.is_positive() {
+                    return Err(parse_ctx.new_err(ParseErr {
+                        message: format!("alignment must be positive, but is {alignment}"),
+                        kind: ParseErrKind::ArithmeticError,
+                        provenance,
+                        span,
+                    }));
+                }
+
+                // the remainder of the floored division, which is never negative
+                let remainder = (x % alignment + alignment) % alignment;
+                let aligned_down = x - &remainder;
+                let result = if func == Builtin::AlignUp && !remainder.is_zero() {
+                    aligned_down + alignment
+                } else {
+                    aligned_down
+                };
+
+                Ok(Value {
+                    kind: ValueKind::Integer(result),
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Crc16
+            | Builtin::Crc32
+            | Builtin::Crc32c
+            | Builtin::Md5
+            | Builtin::Sha1
+            | Builtin::Sha256 => {
+                let [data] = <[Value; 1]>::try_from(args).static_analysis_expect();
+                let data = data.kind.expect_bytes_take();
+
+                match checksum(func, &data) {
+                    Ok(kind) => Ok(Value {
+                        kind,
+                        provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
+                    }),
+                    Err(err) => Err(parse_ctx.new_err(ParseErr {
+                        message: format!("could not read the checksummed bytes: {err}"),
+                        kind: ParseErrKind::Io(err),
+                        provenance,
+                        span,
+                    })),
+                }
+            }
+            Builtin::Len => {
+                let [data] = <[Value; 1]>::try_from(args).static_analysis_expect();
+
+                Ok(Value {
+                    kind: ValueKind::Integer(Int::from(indexable_len(&data.kind))),
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Sum | Builtin::Contains => {
+                let mut args = args.into_iter();
+                let array = args.next().static_analysis_expect().kind;
+                let elements = array_elements(&array, 0..indexable_len(&array), parse_ctx)?;
+
+                let kind = if func == Builtin::Sum {
+                    sum(&elements)
+                } else {
+                    let needle = args.next().static_analysis_expect().kind;
+                    ValueKind::Boolean(elements.iter().any(|element| {
+                        float_operands(&element.kind, &needle)
+                            .map_or(element.kind == needle, |(element, needle)| {
+                                element == needle
+                            })
+                    }))
+                };
+
+                Ok(Value {
+                    kind,
+                    provenance,
+                    name: None,
+                    classification: None,
+                    color: None,
+                    doc: None,
+                    pointer: None,
+                })
+            }
+            Builtin::Xor | Builtin::ByteSwap | Builtin::Base64Decode => {
+                let mut args = args.into_iter();
+                let data = args
+                    .next()
+                    .static_analysis_expect()
+                    .kind
+                    .expect_bytes_take();
+                let transform = match func {
+                    Builtin::Xor => match args.next().static_analysis_expect().kind {
+                        ValueKind::Integer(key) => match u8::try_from(&key) {
+                            Ok(key) => ByteTransform::Xor {
+                                key: Arc::new([key]),
+                            },
+                            Err(_) => {
+                                return Err(parse_ctx.new_err(ParseErr {
+                                    message: format!("XOR key must be a byte, but is {key}"),
+                                    kind: ParseErrKind::InvalidTransform,
+                                    provenance,
+                                    span,
+                                }));
+                            }
+                        },
+                        key => match key.expect_bytes().value() {
+                            Ok(key) => ByteTransform::Xor {
+                                key: Arc::from(&*key),
+                            },
+                            Err(err) => {
+                                return Err(parse_ctx.new_err(ParseErr {
+                                    message: format!("could not read the XOR key: {err}"),
+                                    kind: ParseErrKind::Io(err),
+                                    provenance,
+                                    span,
+                                }));
+                            }
+                        },
+                    },
+                    Builtin::ByteSwap => {
+                        let width = args.next().static_analysis_expect().kind;
+                        let width = width.expect_int();
+                        match usize::try_from(width) {
+                            Ok(width) => ByteTransform::ByteSwap { width },
+                            Err(_) => {
+                                return Err(parse_ctx.new_err(ParseErr {
+                                    message: format!("invalid byte swap width {width}"),
+                                    kind: ParseErrKind::InvalidTransform,
+                                    provenance,
+                                    span,
+                                }));
+                            }
+                        }
+                    }
+                    _ => ByteTransform::Base64,
+                };
+
+                match TransformedBytes::new(transform, data) {
+                    Ok(transformed) => Ok(Value {
+                        kind: ValueKind::Bytes(BytesValue::Transformed {
+                            range: 0..transformed.data().len(),
+                            transformed: Arc::new(transformed),
+                        }),
+                        provenance,
+                        name: None,
+                        classification: None,
+                        color: None,
+                        doc: None,
+                        pointer: None,
+                    }),
+                    Err(err) => Err(parse_ctx.new_err(ParseErr {
+                        message: err.to_string(),
+                        kind: match err {
+                            TransformError::Io(err) => ParseErrKind::Io(err),
+                            _ => ParseErrKind::InvalidTransform,
+                        },
+                        provenance,
+                        span,
+                    })),
+                }
+            }
+        }
+    }
+}
+
+/// Adds up the given numbers.
+///
+/// The sum is a float if any of the numbers is a float and an integer otherwise.
+fn sum(numbers: &[Value]) -> ValueKind {
+    if numbers
+        .iter()
+        .any(|number| matches!(number.kind, ValueKind::Float(_)))
+    {
+        ValueKind::Float(
+            numbers
+                .iter()
+                .map(|number| match &number.kind {
+                    ValueKind::Float(float) => *float,
+                    kind => kind.expect_int().to_f64().unwrap_or(f64::NAN),
+                })
+                .sum(),
+        )
+    } else {
+        ValueKind::Integer(numbers.iter().map(|number| number.kind.expect_int()).sum())
+    }
+}
+
+/// Computes the checksum or hash of the given bytes with the given built-in function.
+fn checksum(func: Builtin, data: &BytesValue) -> io::Result<ValueKind> {
+    /// Computes a hash of the bytes.
+    fn hash<D: Digest>(data: &BytesValue) -> io::Result<ValueKind> {
+        let mut hasher = D::new();
+        data.for_each_chunk(|chunk| hasher.update(chunk))?;
+
+        Ok(ValueKind::Bytes(BytesValue::Lit(
+            hasher.finalize().as_slice().into(),
+        )))
+    }
+
+    Ok(match func {
+        Builtin::Crc16 => {
+            let crc = Crc::<u16>::new(&CRC_16_ARC);
+            let mut digest = crc.digest();
+            data.for_each_chunk(|chunk| digest.update(chunk))?;
+
+            ValueKind::Integer(Int::from(digest.finalize()))
+        }
+        Builtin::Crc32 | Builtin::Crc32c => {
+            let crc = Crc::<u32>::new(if func == Builtin::Crc32 {
+                &CRC_32_ISO_HDLC
+            } else {
+                &CRC_32_ISCSI
+            });
+            let mut digest = crc.digest();
+            data.for_each_chunk(|chunk| digest.update(chunk))?;
+
+            ValueKind::Integer(Int::from(digest.finalize()))
+        }
+        Builtin::Md5 => hash::<Md5>(data)?,
+        Builtin::Sha1 => hash::<Sha1>(data)?,
+        Builtin::Sha256 => hash::<Sha256>(data)?,
+        _ => unreachable!("not a checksum function"),
+    })
+}
+
+/// Compares two numbers, which may be integers or floats.
+///
+/// Numbers that cannot be compared, such as NaN, are considered equal.
+fn compare_numbers(a: &ValueKind, b: &ValueKind) -> Ordering {
+    if let Some((a, b)) = float_operands(a, b) {
+        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+    } else {
+        a.expect_int().cmp(b.expect_int())
+    }
+}
@@ -0,0 +1,862 @@
+//! Implements compiling the elements of arrays to a compact bytecode and evaluating it.
+//!
+//! Evaluating the IR directly is general, but slow for large arrays, because the IR of the element
+//! type is interpreted again for every element. Element types that only consist of numbers,
+//! `struct`s, `let` statements, `!endian` declarations and `!if` chains over integer arithmetic are
+//! therefore compiled to a flat list of instructions, which a small virtual machine evaluates.
+//! It computes with `i64` instead of arbitrary precision integers and reads the input in blocks.
+//!
+//! The virtual machine never reports errors. Whenever it encounters something it cannot handle,
+//! such as an arithmetic overflow or the end of the view, it gives up on the element, which is
+//! then evaluated from the IR again to get the exact result including the errors.
+
+use std::{collections::HashMap, sync::Arc};
+
+use hexbait_common::{Endianness, Len, RelativeOffset};
+
+use crate::{
+    Int,
+    eval::{
+        provenance::Provenance,
+        value::{Value, ValueKind},
+        view::View,
+    },
+    ir::{
+        BinOp, Classification, Color, Declaration, ElsePart, Expr, ExprKind, IfChain, Lit,
+        NamedStruct, ParseType, ParseTypeKind, StructContent, StructField, Symbol, UnOp,
+    },
+};
+
+use super::{ByteOffset, Scope, StructContext, bitfield::flags_value};
+
+/// The maximum depth of named `struct`s within each other that are compiled.
+///
+/// This also stops the compilation of recursive `struct`s.
+const MAX_NAMED_STRUCT_DEPTH: usize = 8;
+
+/// The number of bytes that are read for the first block of the input.
+const MIN_BLOCK_LEN: u64 = 64;
+
+/// The maximum number of bytes that are read for a block of the input.
+const MAX_BLOCK_LEN: u64 = 64 * 1024;
+
+/// The compiled form of a parse type.
+#[derive(Debug)]
+pub(super) struct Program {
+    /// The instructions that evaluate the parse type.
+    code: Vec<Instr>,
+    /// Whether a `let` statement refers to the parent, so that the provenance of the value is not
+    /// limited to the bytes it was parsed from.
+    let_uses_parent: bool,
+}
+
+/// An instruction of a [`Program`].
+///
+/// Parsed values are kept on a stack of values, while expressions are evaluated on a separate
+/// stack of integers and booleans.
+#[derive(Debug)]
+enum Instr {
+    /// Parses an integer with the given number of bytes and pushes it to the values.
+    Int {
+        /// The number of bytes of the integer.
+        size: usize,
+        /// Whether the integer is signed.
+        signed: bool,
+    },
+    /// Parses a floating point number with the given number of bytes and pushes it to the values.
+    Float {
+        /// The number of bytes of the number.
+        size: usize,
+    },
+    /// Gives the last value the name of its integer in the given names.
+    Enum(Arc<[(Int, Arc<str>)]>),
+    /// Replaces the last value by the flags that are set in it.
+    Flags(Arc<[(Int, Symbol)]>),
+    /// Classifies the last value.
+    Classify(Classification),
+    /// Starts a `struct` whose fields are collected until it ends.
+    BeginStruct,
+    /// Ends the innermost `struct`, pushing it to the values with the given color.
+    EndStruct(Option<Color>),
+    /// Moves the last value into the innermost `struct` as a field.
+    Field(Box<FieldInfo>),
+    /// Checks that the last value equals the integer or boolean on top of the stack.
+    Expect,
+    /// Moves the integer or boolean on top of the stack into the innermost `struct` as a field.
+    Let(Symbol),
+    /// Sets the endianness until the end of the innermost `struct`.
+    Endian(Endianness),
+    /// Continues at the given instruction if the boolean on top of the stack is false.
+    JumpUnless(usize),
+    /// Continues at the given instruction.
+    Jump(usize),
+    /// Pushes an integer.
+    PushInt(i64),
+    /// Pushes a boolean.
+    PushBool(bool),
+    /// Pushes the current offset.
+    Offset,
+    /// Pushes the integer or boolean value of a field.
+    Load {
+        /// Whether the field is looked up in the parent of the innermost `struct`.
+        parent: bool,
+        /// The name of the field.
+        name: Symbol,
+        /// The names of the nested fields that are accessed within the field.
+        path: Vec<Symbol>,
+    },
+    /// Negates the integer on top of the stack.
+    Neg,
+    /// Applies a binary operator to the two operands on top of the stack.
+    Binary(BinOp),
+    /// Continues at the given instruction if the boolean on top of the stack is false, which is
+    /// then the result of a `&&`.
+    AndThen(usize),
+    /// Continues at the given instruction if the boolean on top of the stack is true, which is
+    /// then the result of a `||`.
+    OrElse(usize),
+    /// Combines the operands of a `&&` or `||` that did not short-circuit to the right operand.
+    Combine,
+}
+
+/// What is known about a field besides its type.
+#[derive(Debug)]
+struct FieldInfo {
+    /// The name of the field.
+    name: Symbol,
+    /// The classification of the field, if it is annotated.
+    classification: Option<Classification>,
+    /// The color of the field, if it has one.
+    color: Option<Color>,
+    /// The documentation of the field, if it has one.
+    doc: Option<Arc<str>>,
+}
+
+/// An integer or boolean that an expression evaluates to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    /// An integer.
+    Int(i64),
+    /// A boolean.
+    Bool(bool),
+}
+
+impl Scalar {
+    /// Returns the scalar of an integer or boolean value.
+    fn from_value(value: &ValueKind) -> Option<Scalar> {
+        match value {
+            ValueKind::Integer(int) => i64::try_from(int).ok().map(Scalar::Int),
+            ValueKind::Boolean(val) => Some(Scalar::Bool(*val)),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if the scalar is one.
+    fn int(self) -> Option<i64> {
+        match self {
+            Scalar::Int(int) => Some(int),
+            Scalar::Bool(_) => None,
+        }
+    }
+
+    /// Returns the boolean, if the scalar is one.
+    fn bool(self) -> Option<bool> {
+        match self {
+            Scalar::Int(_) => None,
+            Scalar::Bool(val) => Some(val),
+        }
+    }
+
+    /// Turns the scalar into a value with the given provenance.
+    fn into_value(self, provenance: Provenance) -> Value {
+        Value {
+            kind: match self {
+                Scalar::Int(int) => ValueKind::Integer(Int::from(int)),
+                Scalar::Bool(val) => ValueKind::Boolean(val),
+            },
+            provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        }
+    }
+}
+
+/// A value on the stack of the virtual machine or a field of a `struct` that is being parsed.
+#[derive(Debug)]
+enum Slot {
+    /// A fully evaluated value.
+    Value(Value),
+    /// The integer or boolean of a value that is not kept, if it is one that fits into an `i64`.
+    Scalar(Option<Scalar>),
+    /// A `struct` that is not kept, with the fields that expressions can still refer to.
+    Struct(Vec<(Symbol, Slot)>),
+}
+
+impl Slot {
+    /// Returns the integer or boolean of the slot, if it is one that fits into an `i64`.
+    fn scalar(&self) -> Option<Scalar> {
+        match self {
+            Slot::Value(value) => Scalar::from_value(&value.kind),
+            Slot::Scalar(scalar) => *scalar,
+            Slot::Struct(_) => None,
+        }
+    }
+
+    /// Returns the integer or boolean at the path of field names within the slot along with its
+    /// provenance, if it is known.
+    fn load(&self, path: &[Symbol]) -> Option<(Scalar, Option<&Provenance>)> {
+        match (self, path) {
+            (Slot::Value(value), path) => {
+                let value = value_at(value, path)?;
+                Some((Scalar::from_value(&value.kind)?, Some(&value.provenance)))
+            }
+            (Slot::Scalar(scalar), []) => Some(((*scalar)?, None)),
+            (Slot::Struct(fields), [name, rest @ ..]) => find_field(fields, name)?.load(rest),
+            (Slot::Scalar(_), [_, ..]) | (Slot::Struct(_), []) => None,
+        }
+    }
+}
+
+impl Program {
+    /// Compiles the parse type, if all of its parts are supported by the bytecode.
+    pub(super) fn compile(
+        parse_type: &ParseType,
+        structs: &HashMap<Symbol, NamedStruct>,
+    ) -> Option<Program> {
+        let mut compiler = Compiler {
+            code: Vec::new(),
+            structs,
+            depth: 0,
+            let_uses_parent: false,
+        };
+        compiler.parse_type(parse_type)?;
+
+        let let_uses_parent = compiler.let_uses_parent;
+        Some(Program {
+            code: compiler.code,
+            let_uses_parent,
+        })
+    }
+
+    /// Evaluates the compiled parse type at the current offset of the scope.
+    ///
+    /// On success, the offset of the scope is moved past the parsed value. If the value cannot be
+    /// evaluated by the bytecode, `None` is returned and the scope is left unchanged.
+    pub(super) fn run(
+        &self,
+        scope: &mut Scope,
+        reader: &mut BlockReader,
+        struct_ctx: &StructContext,
+    ) -> Option<Value> {
+        match self.exec(scope, reader, struct_ctx, true)? {
+            Slot::Value(value) => Some(value),
+            Slot::Scalar(_) | Slot::Struct(_) => None,
+        }
+    }
+
+    /// Parses over a value of the compiled parse type at the current offset of the scope.
+    ///
+    /// This works like [`Program::run`], but only returns the provenance of the value, which
+    /// avoids building it.
+    pub(super) fn skip(
+        &self,
+        scope: &mut Scope,
+        reader: &mut BlockReader,
+        struct_ctx: &StructContext,
+    ) -> Option<Provenance> {
+        if self.let_uses_parent {
+            return self
+                .run(scope, reader, struct_ctx)
+                .map(|value| value.provenance);
+        }
+
+        let start = scope.offset.0;
+        self.exec(scope, reader, struct_ctx, false)?;
+
+        // the bytecode reads all bytes from the start to the end of the value
+        Some(scope.view.provenance_from_range(start..scope.offset.0))
+    }
+
+    /// Executes the instructions, building values only if `materialize` is set.
+    fn exec(
+        &self,
+        scope: &mut Scope,
+        reader: &mut BlockReader,
+        struct_ctx: &StructContext,
+        materialize: bool,
+    ) -> Option<Slot> {
+        let mut vm = Vm {
+            offset: scope.offset.0,
+            endianness: scope.endianness,
+            values: Vec::new(),
+            structs: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        let mut pc = 0;
+        while let Some(instr) = self.code.get(pc) {
+            pc += 1;
+            match instr {
+                Instr::Int { size, signed } => {
+                    let start = vm.offset;
+                    let bytes = vm.read(&scope.view, reader, *size)?;
+                    vm.values.push(if materialize {
+                        Slot::Value(Value {
+                            kind: ValueKind::Integer(int_from_bytes(bytes, *size, *signed)),
+                            provenance: scope.view.provenance_from_range(start..vm.offset),
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
+                        })
+                    } else {
+                        Slot::Scalar(i64_from_bytes(bytes, *size, *signed).map(Scalar::Int))
+                    });
+                }
+                Instr::Float { size } => {
+                    let start = vm.offset;
+                    let bytes = vm.read(&scope.view, reader, *size)?;
+                    vm.values.push(if materialize {
+                        let float = match size {
+                            4 => f64::from(f32::from_le_bytes(bytes[..4].try_into().ok()?)),
+                            _ => f64::from_le_bytes(bytes[..8].try_into().ok()?),
+                        };
+                        Slot::Value(Value {
+                            kind: ValueKind::Float(float),
+                            provenance: scope.view.provenance_from_range(start..vm.offset),
+                            name: None,
+                            classification: None,
+                            color: None,
+                            doc: None,
+                            pointer: None,
+                        })
+                    } else {
+                        Slot::Scalar(None)
+                    });
+                }
+                Instr::Enum(names) => {
+                    if let Slot::Value(value) = vm.values.last_mut()? {
+                        let int = value.kind.expect_int();
+                        value.name = names
+                            .iter()
+                            .find(|(named, _)| named == int)
+                            .map(|(_, name)| Arc::clone(name));
+                    }
+                }
+                Instr::Flags(flags) => {
+                    let slot = vm.values.pop()?;
+                    vm.values.push(match slot {
+                        Slot::Value(value) => Slot::Value(flags_value(value, flags)),
+                        Slot::Scalar(_) | Slot::Struct(_) => Slot::Scalar(None),
+                    });
+                }
+                Instr::Classify(classification) => {
+                    if let Slot::Value(value) = vm.values.last_mut()? {
+                        value.classification = Some(*classification);
+                    }
+                }
+                Instr::BeginStruct => vm.structs.push((Vec::new(), vm.endianness)),
+                Instr::EndStruct(color) => {
+                    let (fields, endianness) = vm.structs.pop()?;
+                    vm.endianness = endianness;
+
+                    if !materialize {
+                        vm.values.push(Slot::Struct(
+                            fields
+                                .into_iter()
+                                .filter(|(name, _)| !name.as_str().starts_with('_'))
+                                .collect(),
+                        ));
+                        continue;
+                    }
+
+                    // hidden fields are not part of the result, but still part of its provenance
+                    let mut provenance = Provenance::empty();
+                    let mut values = Vec::new();
+                    for (name, slot) in fields {
+                        let Slot::Value(value) = slot else {
+                            return None;
+                        };
+                        provenance += &value.provenance;
+                        if !name.as_str().starts_with('_') {
+                            values.push((name, value));
+                        }
+                    }
+                    vm.values.push(Slot::Value(Value {
+                        kind: ValueKind::Struct {
+                            fields: values,
+                            error: None,
+                        },
+                        provenance,
+                        name: None,
+                        classification: None,
+                        color: color.clone(),
+                        doc: None,
+                        pointer: None,
+                    }));
+                }
+                Instr::Field(info) => {
+                    let mut slot = vm.values.pop()?;
+                    if let Slot::Value(value) = &mut slot {
+                        if info.classification.is_some() {
+                            value.classification = info.classification;
+                        }
+                        if info.color.is_some() {
+                            value.color = info.color.clone();
+                        }
+                        if info.doc.is_some() {
+                            value.doc = info.doc.clone();
+                        }
+                    }
+                    vm.structs.last_mut()?.0.push((info.name.clone(), slot));
+                }
+                Instr::Expect => {
+                    let (expected, _) = vm.stack.pop()?;
+                    if vm.values.last()?.scalar() != Some(expected) {
+                        return None;
+                    }
+                }
+                Instr::Let(name) => {
+                    let (scalar, provenance) = vm.stack.pop()?;
+                    let slot = if materialize {
+                        Slot::Value(scalar.into_value(provenance))
+                    } else {
+                        Slot::Scalar(Some(scalar))
+                    };
+                    vm.structs.last_mut()?.0.push((name.clone(), slot));
+                }
+                Instr::Endian(endianness) => vm.endianness = *endianness,
+                Instr::JumpUnless(target) => {
+                    let (condition, _) = vm.stack.pop()?;
+                    if !condition.bool()? {
+                        pc = *target;
+                    }
+                }
+                Instr::Jump(target) => pc = *target,
+                Instr::PushInt(int) => vm.stack.push((Scalar::Int(*int), Provenance::empty())),
+                Instr::PushBool(val) => vm.stack.push((Scalar::Bool(*val), Provenance::empty())),
+                Instr::Offset => {
+                    let offset = i64::try_from(vm.offset.as_u64()).ok()?;
+                    vm.stack.push((Scalar::Int(offset), Provenance::empty()));
+                }
+                Instr::Load { parent, name, path } => {
+                    let (scalar, provenance) = if *parent && vm.structs.len() == 1 {
+                        let value = value_at(find_field(&struct_ctx.parsed_fields, name)?, path)?;
+                        (Scalar::from_value(&value.kind)?, Some(&value.provenance))
+                    } else {
+                        let frame = vm.structs.len().checked_sub(1 + usize::from(*parent))?;
+                        find_field(&vm.structs[frame].0, name)?.load(path)?
+                    };
+
+                    // the provenance is only needed for the values that are built
+                    let provenance = match provenance {
+                        Some(provenance) if materialize => provenance.clone(),
+                        _ => Provenance::empty(),
+                    };
+                    vm.stack.push((scalar, provenance));
+                }
+                Instr::Neg => {
+                    let (operand, _) = vm.stack.last_mut()?;
+                    *operand = Scalar::Int(operand.int()?.checked_neg()?);
+                }
+                Instr::Binary(op) => {
+                    let (rhs, rhs_provenance) = vm.stack.pop()?;
+                    let (lhs, provenance) = vm.stack.last_mut()?;
+                    *lhs = binary(op, *lhs, rhs)?;
+                    *provenance += &rhs_provenance;
+                }
+                Instr::AndThen(target) => {
+                    if !vm.stack.last()?.0.bool()? {
+                        pc = *target;
+                    }
+                }
+                Instr::OrElse(target) => {
+                    if vm.stack.last()?.0.bool()? {
+                        pc = *target;
+                    }
+                }
+                Instr::Combine => {
+                    let (rhs, rhs_provenance) = vm.stack.pop()?;
+                    let (lhs, provenance) = vm.stack.last_mut()?;
+                    *lhs = Scalar::Bool(rhs.bool()?);
+                    *provenance += &rhs_provenance;
+                }
+            }
+        }
+
+        let value = vm.values.pop()?;
+        scope.offset = ByteOffset(vm.offset);
+
+        Some(value)
+    }
+}
+
+/// The state of evaluating a [`Program`].
+struct Vm {
+    /// The current offset.
+    offset: RelativeOffset,
+    /// The current endianness.
+    endianness: Endianness,
+    /// The parsed values that are not yet part of a `struct`.
+    values: Vec<Slot>,
+    /// The fields of the `struct`s that are currently parsed and the endianness before each of
+    /// them, starting with the outermost one.
+    structs: Vec<(Vec<(Symbol, Slot)>, Endianness)>,
+    /// The operands of the expressions that are currently evaluated.
+    stack: Vec<(Scalar, Provenance)>,
+}
+
+impl Vm {
+    /// Reads the given number of bytes at the current offset in little endian order.
+    fn read(&mut self, view: &View, reader: &mut BlockReader, size: usize) -> Option<[u8; 16]> {
+        let mut bytes = [0; 16];
+        bytes[..size].copy_from_slice(reader.read(view, self.offset, size)?);
+        self.endianness.reorder_to_little_endian(&mut bytes[..size]);
+        self.offset = self.offset.checked_add(Len::from(size as u64))?;
+
+        Some(bytes)
+    }
+}
+
+/// Reads bytes from a view in blocks, so that reading many small values does not access the input
+/// for each of them.
+#[derive(Debug, Default)]
+pub(super) struct BlockReader {
+    /// The bytes of the current block.
+    block: Vec<u8>,
+    /// The offset of the current block in the view.
+    start: u64,
+    /// The number of bytes to read for the next block.
+    next_block_len: u64,
+}
+
+impl BlockReader {
+    /// Returns the given number of bytes at the offset in the view.
+    ///
+    /// Returns `None` if the bytes cannot be read, such as when the view ends before them.
+    fn read(&mut self, view: &View, offset: RelativeOffset, len: usize) -> Option<&[u8]> {
+        let offset = offset.as_u64();
+        let end = offset.checked_add(len as u64)?;
+        if offset < self.start || end > self.start + self.block.len() as u64 {
+            let available = view.len().as_u64().checked_sub(offset)?;
+            if len as u64 > available {
+                return None;
+            }
+
+            let block_len = self
+                .next_block_len
+                .max(MIN_BLOCK_LEN)
+                .max(len as u64)
+                .min(available);
+            let bytes = view
+                .read_at(RelativeOffset::from(offset), Len::from(block_len))
+                .ok()?;
+            if bytes.len() < len {
+                return None;
+            }
+
+            self.block.clear();
+            self.block.extend_from_slice(&bytes);
+            self.start = offset;
+            self.next_block_len = (block_len * 2).min(MAX_BLOCK_LEN);
+        }
+
+        let start = (offset - self.start) as usize;
+        Some(&self.block[start..start + len])
+    }
+}
+
+/// Compiles parse types to a [`Program`].
+struct Compiler<'ir> {
+    /// The instructions compiled so far.
+    code: Vec<Instr>,
+    /// The named `struct`s that can be parsed.
+    structs: &'ir HashMap<Symbol, NamedStruct>,
+    /// The number of named `struct`s that are currently compiled within each other.
+    depth: usize,
+    /// Whether a `let` statement refers to the parent.
+    let_uses_parent: bool,
+}
+
+impl Compiler<'_> {
+    /// Compiles the parse type, if it is supported.
+    fn parse_type(&mut self, parse_type: &ParseType) -> Option<()> {
+        match &parse_type.kind {
+            ParseTypeKind::Integer { bit_width, signed } => {
+                if *bit_width == 0 || bit_width % 8 != 0 || *bit_width > 128 {
+                    return None;
+                }
+                self.code.push(Instr::Int {
+                    size: (bit_width / 8) as usize,
+                    signed: *signed,
+                });
+            }
+            ParseTypeKind::Float { bit_width } => {
+                if *bit_width != 32 && *bit_width != 64 {
+                    return None;
+                }
+                self.code.push(Instr::Float {
+                    size: (bit_width / 8) as usize,
+                });
+            }
+            ParseTypeKind::Enum { parse_type, names } => {
+                self.parse_type(parse_type)?;
+                self.code.push(Instr::Enum(names.as_slice().into()));
+            }
+            ParseTypeKind::Flags { parse_type, flags } => {
+                self.parse_type(parse_type)?;
+                self.code.push(Instr::Flags(flags.as_slice().into()));
+            }
+            ParseTypeKind::Classified {
+                parse_type,
+                classification,
+            } => {
+                self.parse_type(parse_type)?;
+                self.code.push(Instr::Classify(*classification));
+            }
+            ParseTypeKind::Struct { content } => {
+                self.code.push(Instr::BeginStruct);
+                self.struct_content(content)?;
+                self.code.push(Instr::EndStruct(None));
+            }
+            ParseTypeKind::Named { name, args } => {
+                let named_struct = self.structs.get(&name.inner)?;
+                if !args.is_empty()
+                    || !named_struct.params.is_empty()
+                    || self.depth >= MAX_NAMED_STRUCT_DEPTH
+                {
+                    return None;
+                }
+
+                self.depth += 1;
+                self.code.push(Instr::BeginStruct);
+                self.struct_content(&named_struct.content)?;
+                self.code.push(Instr::EndStruct(named_struct.color.clone()));
+                self.depth -= 1;
+            }
+            ParseTypeKind::Leb128 { .. }
+            | ParseTypeKind::DynamicInteger { .. }
+            | ParseTypeKind::Bytes { .. }
+            | ParseTypeKind::Repeating { .. }
+            | ParseTypeKind::Switch { .. }
+            | ParseTypeKind::Bitfield { .. }
+            | ParseTypeKind::Pointer { .. }
+            | ParseTypeKind::Error => return None,
+        }
+
+        Some(())
+    }
+
+    /// Compiles the content of a `struct`, if it is supported.
+    fn struct_content(&mut self, content: &[StructContent]) -> Option<()> {
+        for content in content {
+            match content {
+                StructContent::Field(field) => self.field(field)?,
+                StructContent::LetStatement(let_statement) => {
+                    // other expressions keep the names and annotations of the values they refer to
+                    if !matches!(
+                        let_statement.expr.kind,
+                        ExprKind::Lit(_)
+                            | ExprKind::Offset
+                            | ExprKind::UnOp { op: UnOp::Neg, .. }
+                            | ExprKind::BinOp { .. }
+                    ) {
+                        return None;
+                    }
+                    let start = self.code.len();
+                    self.expr(&let_statement.expr)?;
+                    self.let_uses_parent |= self.code[start..]
+                        .iter()
+                        .any(|instr| matches!(instr, Instr::Load { parent: true, .. }));
+                    self.code.push(Instr::Let(let_statement.name.inner.clone()));
+                }
+                StructContent::Declaration(Declaration::Endianness(endianness)) => {
+                    self.code.push(Instr::Endian(*endianness));
+                }
+                StructContent::Declaration(Declaration::If(if_chain)) => self.if_chain(if_chain)?,
+                StructContent::Declaration(_) | StructContent::Error => return None,
+            }
+        }
+
+        Some(())
+    }
+
+    /// Compiles a field of a `struct`, if it is supported.
+    fn field(&mut self, field: &StructField) -> Option<()> {
+        self.parse_type(&field.ty)?;
+        if let Some(expected) = &field.expected {
+            self.expr(expected)?;
+            self.code.push(Instr::Expect);
+        }
+        self.code.push(Instr::Field(Box::new(FieldInfo {
+            name: field.name.inner.clone(),
+            classification: field.classification,
+            color: field.color.clone(),
+            doc: field.doc.clone(),
+        })));
+
+        Some(())
+    }
+
+    /// Compiles an `if` chain, if it is supported.
+    fn if_chain(&mut self, if_chain: &IfChain) -> Option<()> {
+        self.expr(&if_chain.condition)?;
+        let jump_to_else = self.code.len();
+        self.code.push(Instr::JumpUnless(usize::MAX));
+        self.struct_content(&if_chain.then_block)?;
+
+        let Some(else_part) = &if_chain.else_part else {
+            self.code[jump_to_else] = Instr::JumpUnless(self.code.len());
+            return Some(());
+        };
+
+        let jump_to_end = self.code.len();
+        self.code.push(Instr::Jump(usize::MAX));
+        self.code[jump_to_else] = Instr::JumpUnless(self.code.len());
+        match else_part {
+            ElsePart::IfChain(if_chain) => self.if_chain(if_chain)?,
+            ElsePart::ElseBlock(else_block) => self.struct_content(else_block)?,
+        }
+        self.code[jump_to_end] = Instr::Jump(self.code.len());
+
+        Some(())
+    }
+
+    /// Compiles an expression that evaluates to an integer or boolean, if it is supported.
+    fn expr(&mut self, expr: &Expr) -> Option<()> {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Int(int)) => {
+                self.code.push(Instr::PushInt(i64::try_from(int).ok()?))
+            }
+            ExprKind::Lit(Lit::Bool(val)) => self.code.push(Instr::PushBool(*val)),
+            ExprKind::Offset => self.code.push(Instr::Offset),
+            ExprKind::VarUse(_) | ExprKind::FieldAccess { .. } => {
+                let mut path = Vec::new();
+                let mut expr = expr;
+                while let ExprKind::FieldAccess { expr: inner, field } = &expr.kind {
+                    path.push(field.inner.clone());
+                    expr = inner;
+                }
+                path.reverse();
+
+                let (parent, name) = match &expr.kind {
+                    ExprKind::VarUse(var) => (false, var.inner.clone()),
+                    ExprKind::Parent if !path.is_empty() => (true, path.remove(0)),
+                    _ => return None,
+                };
+                self.code.push(Instr::Load { parent, name, path });
+            }
+            ExprKind::UnOp {
+                op: UnOp::Neg,
+                operand,
+            } => {
+                self.expr(operand)?;
+                self.code.push(Instr::Neg);
+            }
+            ExprKind::BinOp {
+                op: op @ (BinOp::LogicalAnd | BinOp::LogicalOr),
+                lhs,
+                rhs,
+            } => {
+                self.expr(lhs)?;
+                let short_circuit = self.code.len();
+                self.code.push(Instr::Jump(usize::MAX));
+                self.expr(rhs)?;
+                self.code.push(Instr::Combine);
+                self.code[short_circuit] = match op {
+                    BinOp::LogicalAnd => Instr::AndThen(self.code.len()),
+                    _ => Instr::OrElse(self.code.len()),
+                };
+            }
+            ExprKind::BinOp { op, lhs, rhs } => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                self.code.push(Instr::Binary(op.clone()));
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+/// Returns the first field with the given name.
+fn find_field<'a, T>(fields: &'a [(Symbol, T)], name: &Symbol) -> Option<&'a T> {
+    fields
+        .iter()
+        .find_map(|(field, value)| (field == name).then_some(value))
+}
+
+/// Returns the value at the path of field names within the value.
+fn value_at<'a>(mut value: &'a Value, path: &[Symbol]) -> Option<&'a Value> {
+    for name in path {
+        let ValueKind::Struct { fields, .. } = &value.kind else {
+            return None;
+        };
+        value = find_field(fields, name)?;
+    }
+
+    Some(value)
+}
+
+/// Converts little endian bytes of which the first `size` are used to an integer.
+///
+/// The unused bytes must be zero.
+fn int_from_bytes(bytes: [u8; 16], size: usize, signed: bool) -> Int {
+    let int = u128::from_le_bytes(bytes);
+    if signed {
+        // moves the sign bit of the used bytes to the top to extend it
+        let unused_bits = 128 - 8 * size as u32;
+        Int::from(((int << unused_bits) as i128) >> unused_bits)
+    } else {
+        Int::from(int)
+    }
+}
+
+/// Converts little endian bytes of which the first `size` are used to an `i64`, if it fits.
+fn i64_from_bytes(bytes: [u8; 16], size: usize, signed: bool) -> Option<i64> {
+    let int = u128::from_le_bytes(bytes);
+    if signed {
+        let unused_bits = 128 - 8 * size as u32;
+        i64::try_from(((int << unused_bits) as i128) >> unused_bits).ok()
+    } else {
+        i64::try_from(int).ok()
+    }
+}
+
+/// Applies a binary operator to two operands that are not logical operators.
+///
+/// Returns `None` if the result may differ from the result of the IR evaluation, such as when
+/// the result does not fit into an `i64`.
+fn binary(op: &BinOp, lhs: Scalar, rhs: Scalar) -> Option<Scalar> {
+    let ints = || Some((lhs.int()?, rhs.int()?));
+
+    Some(match op {
+        BinOp::Eq => Scalar::Bool(lhs == rhs),
+        BinOp::Neq => Scalar::Bool(lhs != rhs),
+        BinOp::Gt => ints().map(|(lhs, rhs)| Scalar::Bool(lhs > rhs))?,
+        BinOp::Geq => ints().map(|(lhs, rhs)| Scalar::Bool(lhs >= rhs))?,
+        BinOp::Lt => ints().map(|(lhs, rhs)| Scalar::Bool(lhs < rhs))?,
+        BinOp::Leq => ints().map(|(lhs, rhs)| Scalar::Bool(lhs <= rhs))?,
+        op => {
+            let (lhs, rhs) = ints()?;
+            Scalar::Int(match op {
+                BinOp::Add => lhs.checked_add(rhs)?,
+                BinOp::Sub => lhs.checked_sub(rhs)?,
+                BinOp::Mul => lhs.checked_mul(rhs)?,
+                BinOp::Div => lhs.checked_div(rhs)?,
+                BinOp::Mod => lhs.checked_rem(rhs)?,
+                BinOp::BitAnd => lhs & rhs,
+                BinOp::BitOr => lhs | rhs,
+                BinOp::BitXor => lhs ^ rhs,
+                BinOp::ShiftLeft if (0..63).contains(&rhs) => lhs.checked_mul(1 << rhs)?,
+                BinOp::ShiftRight if (0..64).contains(&rhs) => lhs >> rhs,
+                _ => return None,
+            })
+        }
+    })
+}
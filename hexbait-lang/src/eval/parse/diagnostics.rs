@@ -13,12 +13,26 @@ pub enum ParseErrKind {
     OffsetTooLarge,
     /// An arithmetic error occurred while evaluating an expression.
     ArithmeticError,
+    /// An integer was divided by zero.
+    DivisionByZero,
     /// An assertion failed.
     AssertionFailure,
     /// An assertion failed.
     ExpectationFailure,
+    /// A transformation could not be applied to its input.
+    InvalidTransform,
+    /// A value in the input is not encoded validly.
+    InvalidEncoding,
+    /// An index or a range was outside of the indexed value.
+    IndexOutOfBounds,
+    /// A value that is not parsed from the input was used as if it was.
+    NotInInput,
     /// An I/O error occurred during parsing.
     Io(io::Error),
+    /// Parsing was cancelled before it finished.
+    Cancelled,
+    /// Parsing exceeded one of the limits of the evaluation options.
+    LimitExceeded,
 }
 
 impl From<io::Error> for ParseErrKind {
@@ -67,7 +81,9 @@ pub(crate) struct ParseErrWithMaybePartialResult {
     /// The parse error.
     pub(crate) parse_err: ParseErrId,
     /// A partial result that was parsed despite the error.
-    pub(crate) partial_result: Option<Value>,
+    ///
+    /// It is boxed, because values are large compared to the error itself.
+    pub(crate) partial_result: Option<Box<Value>>,
 }
 
 impl From<ParseErrId> for ParseErrWithMaybePartialResult {
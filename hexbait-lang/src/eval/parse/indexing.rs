@@ -0,0 +1,213 @@
+//! Implements indexing and slicing of values.
+
+use std::ops::Range;
+
+use hexbait_common::RelativeOffset;
+
+use crate::{
+    Int, Span,
+    eval::{
+        provenance::Provenance,
+        value::{Value, ValueKind},
+    },
+};
+
+use super::{ParseContext, ParseErr, ParseErrId, ParseErrKind, Scope};
+
+impl Scope {
+    /// Evaluates the element at the evaluated `index` of the evaluated `indexed` value.
+    pub(super) fn eval_index(
+        &self,
+        indexed: Value,
+        index: Value,
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrId> {
+        let len = indexable_len(&indexed.kind);
+        let Some(i) = usize::try_from(index.kind.expect_int())
+            .ok()
+            .filter(|&i| i < len)
+        else {
+            return Err(parse_ctx.new_err(ParseErr {
+                message: format!(
+                    "index {} is out of bounds for {} of length {len}",
+                    index.kind.expect_int(),
+                    indexable_name(&indexed.kind),
+                ),
+                kind: ParseErrKind::IndexOutOfBounds,
+                provenance: &indexed.provenance + &index.provenance,
+                span,
+            }));
+        };
+
+        if let ValueKind::Array { .. } = indexed.kind {
+            let element = array_elements(&indexed.kind, i..i + 1, parse_ctx)?
+                .pop()
+                .expect("the element exists if its evaluation succeeded");
+
+            return Ok(element);
+        }
+
+        let bytes = indexed.kind.expect_bytes();
+        let mut byte = [0];
+        if let Err(err) = bytes.fill_buf_at(i, &mut byte) {
+            return Err(parse_ctx.new_err(ParseErr {
+                message: format!("could not read the indexed byte: {err}"),
+                kind: ParseErrKind::Io(err),
+                provenance: indexed.provenance,
+                span,
+            }));
+        }
+
+        Ok(Value {
+            kind: ValueKind::Integer(Int::from(byte[0])),
+            provenance: bytes.provenance_range(
+                RelativeOffset::from(i as u64)..RelativeOffset::from(i as u64 + 1),
+            ),
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        })
+    }
+
+    /// Evaluates the elements from the evaluated `start` up to the evaluated `end` of the
+    /// evaluated `sliced` value.
+    pub(super) fn eval_slice(
+        &self,
+        sliced: Value,
+        start: Value,
+        end: Value,
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrId> {
+        let (Ok(start_idx), Ok(end_idx)) = (
+            usize::try_from(start.kind.expect_int()),
+            usize::try_from(end.kind.expect_int()),
+        ) else {
+            return Err(out_of_bounds(&sliced, &start, &end, span, parse_ctx));
+        };
+        if start_idx > end_idx || end_idx > indexable_len(&sliced.kind) {
+            return Err(out_of_bounds(&sliced, &start, &end, span, parse_ctx));
+        }
+
+        if let ValueKind::Array { .. } = sliced.kind {
+            let items = array_elements(&sliced.kind, start_idx..end_idx, parse_ctx)?;
+            let mut provenance = Provenance::empty();
+            for item in &items {
+                provenance += &item.provenance;
+            }
+
+            return Ok(Value {
+                provenance,
+                kind: ValueKind::Array {
+                    items,
+                    error: None,
+                    lazy: None,
+                },
+                name: None,
+                classification: None,
+                color: None,
+                doc: None,
+                pointer: None,
+            });
+        }
+
+        let bytes = sliced.kind.expect_bytes();
+        let slice = bytes.slice(start_idx..end_idx).map_err(|err| {
+            parse_ctx.new_err(ParseErr {
+                message: format!("could not read the sliced bytes: {err}"),
+                kind: ParseErrKind::Io(err),
+                provenance: sliced.provenance.clone(),
+                span,
+            })
+        })?;
+
+        Ok(Value {
+            provenance: bytes.provenance_range(
+                RelativeOffset::from(start_idx as u64)..RelativeOffset::from(end_idx as u64),
+            ),
+            kind: ValueKind::Bytes(slice),
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        })
+    }
+}
+
+/// Returns the number of elements of an array or the number of bytes of a `bytes` value.
+///
+/// Arrays count their lazy elements as well.
+pub(super) fn indexable_len(value: &ValueKind) -> usize {
+    match value {
+        ValueKind::Array { items, lazy, .. } => {
+            items.len() + lazy.as_ref().map_or(0, |lazy| lazy.len())
+        }
+        value => value.expect_bytes().len(),
+    }
+}
+
+/// Returns how the kind of the indexed value is called in messages.
+fn indexable_name(value: &ValueKind) -> &'static str {
+    match value {
+        ValueKind::Array { .. } => "an array",
+        _ => "`bytes`",
+    }
+}
+
+/// Returns the elements in the given range of the given array.
+///
+/// Lazy elements in the range are evaluated, the range must be within the array.
+///
+/// # Panics
+/// This function will panic if the value is not an array.
+pub(super) fn array_elements(
+    array: &ValueKind,
+    range: Range<usize>,
+    parse_ctx: &mut ParseContext,
+) -> Result<Vec<Value>, ParseErrId> {
+    let ValueKind::Array { items, lazy, .. } = array else {
+        unreachable!("expected an array value")
+    };
+    let kept = range.start.min(items.len())..range.end.min(items.len());
+    let mut elements = items[kept].to_vec();
+
+    if range.end > items.len()
+        && let Some(lazy) = lazy
+    {
+        let result =
+            lazy.evaluate(range.start.saturating_sub(items.len())..range.end - items.len());
+        if let Some(err) = result.errors.into_iter().next() {
+            return Err(parse_ctx.new_err(err));
+        }
+
+        elements.extend(result.value.kind.expect_array_take());
+    }
+
+    Ok(elements)
+}
+
+/// Creates the error for a slice range that does not fit into the sliced value.
+fn out_of_bounds(
+    sliced: &Value,
+    start: &Value,
+    end: &Value,
+    span: Span,
+    parse_ctx: &mut ParseContext,
+) -> ParseErrId {
+    parse_ctx.new_err(ParseErr {
+        message: format!(
+            "range {}..{} is out of bounds for {} of length {}",
+            start.kind.expect_int(),
+            end.kind.expect_int(),
+            indexable_name(&sliced.kind),
+            indexable_len(&sliced.kind),
+        ),
+        kind: ParseErrKind::IndexOutOfBounds,
+        provenance: &(&sliced.provenance + &start.provenance) + &end.provenance,
+        span,
+    })
+}
@@ -0,0 +1,510 @@
+//! Implements arrays whose elements are only evaluated on demand.
+
+use std::{collections::HashMap, fmt, ops::Range, sync::Arc};
+
+use hexbait_common::{AbsoluteOffset, Endianness, Len, RelativeOffset};
+
+use crate::{
+    eval::{
+        provenance::Provenance,
+        value::{Value, ValueKind},
+        view::View,
+    },
+    ir::{NamedStruct, ParseType, Symbol},
+};
+
+use super::bytecode::{BlockReader, Program};
+
+use super::{
+    ByteOffset, EvalLimits, EvalOptions, ParseContext, ParseErrId, ParseErrWithMaybePartialResult,
+    ParseResult, RecoveryStrategy, Scope, StructContext,
+};
+
+/// The number of lazy elements between two positions that evaluation can resume from.
+const CHECKPOINT_INTERVAL: usize = 64;
+
+/// The number of elements of an array after which its element type is compiled to bytecode.
+///
+/// The compiled element type is used for the kept elements as well as for the lazy ones, so
+/// arrays are also sped up when all elements are kept, as with [`eval_ir`](crate::eval_ir).
+/// Short arrays are not worth compiling.
+const COMPILE_THRESHOLD: usize = 16;
+
+/// The elements at the end of an array that are only evaluated on demand.
+///
+/// Clones refer to the same elements.
+#[derive(Clone)]
+pub struct LazyElements {
+    /// The shared information needed to evaluate the elements.
+    inner: Arc<LazyElementsInner>,
+}
+
+/// The information needed to evaluate lazy elements.
+struct LazyElementsInner {
+    /// The number of lazy elements.
+    len: usize,
+    /// The type of the elements.
+    parse_type: ParseType,
+    /// The view that the elements are parsed from.
+    view: View,
+    /// The offset and endianness before every [`CHECKPOINT_INTERVAL`]th lazy element.
+    checkpoints: Vec<(ByteOffset, Endianness)>,
+    /// The fields of the `struct`s surrounding the array, starting with the outermost one.
+    ancestors: Vec<Vec<(Symbol, Value)>>,
+    /// The number of elements that are kept of nested arrays.
+    lazy_array_threshold: Option<usize>,
+    /// The limits on the resources that evaluating the elements may use.
+    limits: EvalLimits,
+    /// The named `struct`s that can be parsed.
+    structs: Arc<HashMap<Symbol, NamedStruct>>,
+}
+
+impl LazyElements {
+    /// The number of lazy elements.
+    pub fn len(&self) -> usize {
+        self.inner.len
+    }
+
+    /// Determines if there are no lazy elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.len == 0
+    }
+
+    /// Evaluates the lazy elements in the given range.
+    ///
+    /// The range counts from the first lazy element. The result is an array of the evaluated
+    /// elements and the errors and warnings that occurred while evaluating them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::{Input, RelativeOffset};
+    /// # use hexbait_lang::{EvalOptions, Int, ValueKind, View, eval_ir_with_options, ir::lower_file, parse};
+    /// let ir = lower_file(parse("items [u8] len 200;").ast);
+    /// let view = View::from_input(Input::from_bytes((0..200).collect::<Vec<u8>>()));
+    /// let options = EvalOptions {
+    ///     lazy_array_threshold: Some(10),
+    ///     ..EvalOptions::default()
+    /// };
+    /// let value = eval_ir_with_options(&ir, view, RelativeOffset::ZERO, options).value;
+    ///
+    /// let ValueKind::Array { items, lazy: Some(lazy), .. } = &value.kind.expect_struct()[0].1.kind
+    /// else {
+    ///     panic!("the array should have lazy elements");
+    /// };
+    /// assert_eq!(items.len(), 10);
+    /// assert_eq!(lazy.len(), 190);
+    ///
+    /// let evaluated = lazy.evaluate(100..102).value;
+    /// assert_eq!(evaluated.kind.expect_array()[1].kind.expect_int(), &Int::from(111));
+    /// ```
+    pub fn evaluate(&self, range: Range<usize>) -> ParseResult {
+        let inner = &*self.inner;
+        let end = range.end.min(inner.len);
+        let start = range.start.min(end);
+        let checkpoint = start / CHECKPOINT_INTERVAL;
+        let (offset, endianness) = inner.checkpoints[checkpoint.min(inner.checkpoints.len() - 1)];
+
+        let mut scope = Scope::new(inner.view.clone());
+        scope.offset = offset;
+        scope.endianness = endianness;
+        let mut parse_ctx = ParseContext::new(
+            EvalOptions {
+                limits: inner.limits,
+                lazy_array_threshold: inner.lazy_array_threshold,
+                ..EvalOptions::default()
+            },
+            Arc::clone(&inner.structs),
+        );
+
+        let value = with_struct_contexts(&inner.ancestors, None, |struct_ctx| {
+            let mut items = Vec::new();
+            let mut provenance = Provenance::empty();
+            let mut error = None;
+
+            for i in checkpoint * CHECKPOINT_INTERVAL..end {
+                let (value, err) =
+                    match scope.eval_parse_type(&inner.parse_type, struct_ctx, &mut parse_ctx) {
+                        Ok(value) => (Some(value), None),
+                        Err(err) => (err.partial_result.map(|value| *value), Some(err.parse_err)),
+                    };
+                if i >= start
+                    && let Some(value) = value
+                {
+                    provenance += &value.provenance;
+                    items.push(value);
+                }
+                if err.is_some() {
+                    error = err;
+                    break;
+                }
+            }
+
+            Value {
+                kind: ValueKind::Array {
+                    items,
+                    error,
+                    lazy: None,
+                },
+                provenance,
+                name: None,
+                classification: None,
+                color: None,
+                doc: None,
+                pointer: None,
+            }
+        });
+
+        ParseResult {
+            value,
+            errors: parse_ctx.errors,
+            warnings: parse_ctx.warnings,
+        }
+    }
+}
+
+/// A link from a pointer to the value that it points to.
+///
+/// The target is only evaluated on demand. Clones refer to the same target.
+#[derive(Clone, PartialEq)]
+pub struct Pointer {
+    /// The offset of the first byte of the target in the input, if it comes from the input.
+    target_offset: Option<AbsoluteOffset>,
+    /// The target as the only lazy element.
+    target: LazyElements,
+}
+
+impl Pointer {
+    /// Creates a pointer to a value of the given type at the given offset in the scope.
+    pub(super) fn new(
+        parse_type: &ParseType,
+        scope: &Scope,
+        offset: ByteOffset,
+        struct_ctx: &StructContext,
+        parse_ctx: &ParseContext,
+    ) -> Pointer {
+        Pointer {
+            target_offset: scope
+                .view
+                .provenance_from_range(offset.0..offset.0 + Len::from(1))
+                .byte_ranges()
+                .next()
+                .map(|range| range.start()),
+            target: LazyElements {
+                inner: Arc::new(LazyElementsInner {
+                    len: 1,
+                    parse_type: parse_type.clone(),
+                    view: scope.view.clone(),
+                    checkpoints: vec![(offset, scope.endianness)],
+                    ancestors: ancestors(struct_ctx),
+                    lazy_array_threshold: parse_ctx.options.lazy_array_threshold,
+                    limits: parse_ctx.options.limits,
+                    structs: Arc::clone(&parse_ctx.structs),
+                }),
+            },
+        }
+    }
+
+    /// The offset of the first byte of the target in the input, if it comes from the input.
+    pub fn target_offset(&self) -> Option<AbsoluteOffset> {
+        self.target_offset
+    }
+
+    /// Evaluates the target of the pointer.
+    ///
+    /// The result also contains the errors and warnings that occurred while evaluating it. If
+    /// nothing of the target could be parsed, the value is an empty array containing the error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::{Input, RelativeOffset};
+    /// # use hexbait_lang::{Int, View, eval_ir, ir::lower_file, parse};
+    /// let ir = lower_file(parse("!endian le; target ptr<u16> u8 @ 1;").ast);
+    /// let view = View::from_input(Input::from_bytes(vec![0x01, 0x00, 0x34, 0x12]));
+    /// let value = eval_ir(&ir, view, RelativeOffset::ZERO).value;
+    ///
+    /// let pointer = &value.kind.expect_struct()[0].1;
+    /// assert_eq!(pointer.kind.expect_int(), &Int::from(1));
+    /// let target = pointer.pointer.as_ref().unwrap().evaluate().value;
+    /// assert_eq!(target.kind.expect_int(), &Int::from(0x1234));
+    /// ```
+    pub fn evaluate(&self) -> ParseResult {
+        let mut result = self.target.evaluate(0..1);
+        if let ValueKind::Array { items, .. } = &mut result.value.kind
+            && let Some(target) = items.pop()
+        {
+            result.value = target;
+        }
+
+        result
+    }
+}
+
+impl fmt::Debug for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pointer")
+            .field("target_offset", &self.target_offset)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for LazyElements {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl fmt::Debug for LazyElements {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyElements")
+            .field("len", &self.inner.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the fields of the given `struct` context and its ancestors, starting with the outermost
+/// one.
+fn ancestors(struct_ctx: &StructContext) -> Vec<Vec<(Symbol, Value)>> {
+    let mut ancestors = Vec::new();
+    let mut ctx = Some(struct_ctx);
+    while let Some(current) = ctx {
+        ancestors.push(current.parsed_fields.clone());
+        ctx = current.parent;
+    }
+    ancestors.reverse();
+
+    ancestors
+}
+
+/// Calls `f` with the innermost of the `struct` contexts that contain the given fields.
+fn with_struct_contexts<R>(
+    ancestors: &[Vec<(Symbol, Value)>],
+    parent: Option<&StructContext>,
+    f: impl FnOnce(&StructContext) -> R,
+) -> R {
+    let Some((outermost, rest)) = ancestors.split_first() else {
+        return f(parent.expect("lazy elements are always inside of a `struct`"));
+    };
+
+    let ctx = StructContext {
+        parsed_fields: outermost.clone(),
+        num_args: 0,
+        parent,
+        recovery_strategy: RecoveryStrategy::Fallback,
+        error: None,
+        start_offset: ByteOffset(RelativeOffset::ZERO),
+    };
+
+    with_struct_contexts(rest, Some(&ctx), f)
+}
+
+/// Collects the elements of an array while it is parsed.
+///
+/// Once more than [`EvalOptions::lazy_array_threshold`] elements were parsed, the remaining
+/// elements are dropped and only recorded as [`LazyElements`].
+pub(super) struct ArrayBuilder<'a> {
+    /// The type of the elements.
+    parse_type: &'a ParseType,
+    /// The context of the `struct` containing the array.
+    struct_ctx: &'a StructContext<'a>,
+    /// The number of elements to keep.
+    threshold: Option<usize>,
+    /// The limits on the resources that evaluating lazy elements may use.
+    limits: EvalLimits,
+    /// The named `struct`s that can be parsed.
+    structs: Arc<HashMap<Symbol, NamedStruct>>,
+    /// The kept elements.
+    items: Vec<Value>,
+    /// The provenance of all elements.
+    provenance: Provenance,
+    /// The lazy elements, once there are any.
+    lazy: Option<LazyElementsInner>,
+    /// The last lazy element, if it is kept.
+    last_lazy: Option<Value>,
+    /// Whether the last lazy element is kept, so that it can be referred to.
+    keep_last: bool,
+    /// The compiled element type, once enough elements were parsed and if it can be compiled.
+    program: Option<Program>,
+    /// Reads the input for the compiled element type.
+    reader: BlockReader,
+}
+
+impl<'a> ArrayBuilder<'a> {
+    /// Creates a builder for an array of the given type.
+    pub(super) fn new(
+        parse_type: &'a ParseType,
+        struct_ctx: &'a StructContext<'a>,
+        parse_ctx: &ParseContext,
+    ) -> ArrayBuilder<'a> {
+        ArrayBuilder {
+            parse_type,
+            struct_ctx,
+            threshold: parse_ctx.options.lazy_array_threshold,
+            limits: parse_ctx.options.limits,
+            structs: Arc::clone(&parse_ctx.structs),
+            items: Vec::new(),
+            provenance: Provenance::empty(),
+            lazy: None,
+            last_lazy: None,
+            keep_last: false,
+            program: None,
+            reader: BlockReader::default(),
+        }
+    }
+
+    /// The number of elements parsed so far.
+    pub(super) fn len(&self) -> usize {
+        self.items.len() + self.lazy.as_ref().map_or(0, |lazy| lazy.len)
+    }
+
+    /// Keeps the last element, so that it is available through [`ArrayBuilder::last`].
+    pub(super) fn keeping_last(mut self) -> ArrayBuilder<'a> {
+        self.keep_last = true;
+        self
+    }
+
+    /// Determines if the next element only becomes a lazy element.
+    fn next_is_lazy(&self) -> bool {
+        self.threshold
+            .is_some_and(|threshold| self.items.len() >= threshold)
+    }
+
+    /// The last element parsed so far.
+    ///
+    /// The last lazy element is only available if it is kept.
+    pub(super) fn last(&self) -> Option<&Value> {
+        self.last_lazy.as_ref().or(self.items.last())
+    }
+
+    /// Adds an element that was parsed starting in the given state of the scope.
+    fn push(&mut self, scope: &Scope, start: (ByteOffset, Endianness), value: Value) {
+        if !self.next_is_lazy() {
+            self.provenance += &value.provenance;
+            self.items.push(value);
+            return;
+        }
+
+        self.push_lazy(scope, start, &value.provenance);
+        if self.keep_last {
+            self.last_lazy = Some(value);
+        }
+    }
+
+    /// Adds a lazy element with the given provenance that was parsed starting in the given state
+    /// of the scope.
+    fn push_lazy(
+        &mut self,
+        scope: &Scope,
+        start: (ByteOffset, Endianness),
+        provenance: &Provenance,
+    ) {
+        self.provenance += provenance;
+
+        let lazy = self.lazy.get_or_insert_with(|| LazyElementsInner {
+            len: 0,
+            parse_type: self.parse_type.clone(),
+            view: scope.view.clone(),
+            checkpoints: Vec::new(),
+            ancestors: ancestors(self.struct_ctx),
+            lazy_array_threshold: self.threshold,
+            limits: self.limits,
+            structs: Arc::clone(&self.structs),
+        });
+        if lazy.len.is_multiple_of(CHECKPOINT_INTERVAL) {
+            lazy.checkpoints.push(start);
+        }
+        lazy.len += 1;
+    }
+
+    /// Turns the collected elements into an array value.
+    pub(super) fn finish(self, error: Option<ParseErrId>) -> Value {
+        Value {
+            kind: ValueKind::Array {
+                items: self.items,
+                error,
+                lazy: self.lazy.map(|inner| LazyElements {
+                    inner: Arc::new(inner),
+                }),
+            },
+            provenance: self.provenance,
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        }
+    }
+}
+
+impl Scope {
+    /// Parses the next element of the array.
+    ///
+    /// If parsing fails, the partially parsed array is returned as part of the error.
+    pub(super) fn eval_array_element<'a>(
+        &mut self,
+        mut array: ArrayBuilder<'a>,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<ArrayBuilder<'a>, ParseErrWithMaybePartialResult> {
+        let start = (self.offset, self.endianness);
+        if array.len() == COMPILE_THRESHOLD {
+            array.program = Program::compile(array.parse_type, &array.structs);
+        }
+
+        let checked = parse_ctx
+            .check_cancelled(Provenance::empty(), array.parse_type.span)
+            .and_then(|()| {
+                parse_ctx.check_repetitions(
+                    array.len(),
+                    array.provenance.clone(),
+                    array.parse_type.span,
+                )
+            });
+        let result = match checked {
+            Ok(()) => {
+                if let Some(program) = &array.program {
+                    // lazy elements that are not kept do not need to be built
+                    if array.next_is_lazy() && !array.keep_last {
+                        if let Some(provenance) =
+                            program.skip(self, &mut array.reader, array.struct_ctx)
+                        {
+                            if parse_ctx.try_read(self.offset.0 - start.0.0) {
+                                array.push_lazy(self, start, &provenance);
+                                return Ok(array);
+                            }
+                            self.offset = start.0;
+                        }
+                    } else if let Some(value) =
+                        program.run(self, &mut array.reader, array.struct_ctx)
+                    {
+                        if parse_ctx.try_read(self.offset.0 - start.0.0) {
+                            array.push(self, start, value);
+                            return Ok(array);
+                        }
+                        self.offset = start.0;
+                    }
+                }
+
+                // this also reports the errors that the bytecode gives up on
+                self.eval_parse_type(array.parse_type, array.struct_ctx, parse_ctx)
+            }
+            Err(err) => Err(ParseErrWithMaybePartialResult::from(err)),
+        };
+
+        match result {
+            Ok(value) => {
+                array.push(self, start, value);
+                Ok(array)
+            }
+            Err(err) => {
+                if let Some(partial_result) = err.partial_result {
+                    array.push(self, start, *partial_result);
+                }
+                Err(ParseErrWithMaybePartialResult {
+                    parse_err: err.parse_err,
+                    partial_result: Some(Box::new(array.finish(Some(err.parse_err)))),
+                })
+            }
+        }
+    }
+}
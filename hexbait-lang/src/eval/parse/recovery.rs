@@ -0,0 +1,62 @@
+//! Implements searching for patterns to recover from errors.
+
+use hexbait_common::{Len, RelativeOffset};
+
+use crate::{Span, ir::BytePattern};
+
+use super::{ParseContext, ParseErr, ParseErrId, ParseErrKind, Scope};
+
+/// The number of possible starts of the pattern that are searched with a single read.
+const SCAN_CHUNK_SIZE: u64 = 64 * 1024;
+
+impl Scope {
+    /// Searches for the first occurrence of the pattern that starts at or after `start`.
+    ///
+    /// Returns `None` if the pattern does not occur before the end of the view.
+    pub(super) fn scan_for(
+        &self,
+        pattern: &BytePattern,
+        start: RelativeOffset,
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Option<RelativeOffset>, ParseErrId> {
+        let view_len = self.view.len().as_u64();
+        let pattern_len = pattern.len() as u64;
+
+        if pattern.is_empty() {
+            return Ok(Some(start).filter(|start| start.as_u64() <= view_len));
+        }
+
+        let mut chunk_start = start.as_u64();
+        while chunk_start + pattern_len <= view_len {
+            let chunk_end =
+                std::cmp::min(chunk_start + SCAN_CHUNK_SIZE + pattern_len - 1, view_len);
+            let range = RelativeOffset::from(chunk_start)..RelativeOffset::from(chunk_end);
+
+            parse_ctx.check_cancelled(self.view.provenance_from_range(range.clone()), span)?;
+
+            let buf = self
+                .view
+                .read_at(range.start, Len::from(chunk_end - chunk_start))
+                .map_err(|err| {
+                    parse_ctx.new_err(ParseErr {
+                        message: format!("io error: {err}"),
+                        kind: ParseErrKind::Io(err),
+                        provenance: self.view.provenance_from_range(range),
+                        span,
+                    })
+                })?;
+
+            if let Some(pos) = buf
+                .windows(pattern.len())
+                .position(|window| pattern.matches(window))
+            {
+                return Ok(Some(RelativeOffset::from(chunk_start + pos as u64)));
+            }
+
+            chunk_start += SCAN_CHUNK_SIZE;
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,68 @@
+//! Implements parsing variable length integers.
+
+use hexbait_common::Len;
+
+use crate::{
+    Int, Span,
+    eval::value::{Value, ValueKind},
+};
+
+use super::{ParseContext, ParseErr, ParseErrKind, ParseErrWithMaybePartialResult, Scope};
+
+/// The maximum number of bytes of a LEB128 integer.
+///
+/// This is enough for 128 bit integers and stops runaway reads of garbage data.
+const MAX_LEB128_LEN: u64 = 19;
+
+impl Scope {
+    /// Parses a LEB128 encoded integer.
+    ///
+    /// Each byte contributes its lower seven bits, starting with the least significant ones, and
+    /// its most significant bit is set if another byte follows.
+    pub(super) fn eval_leb128(
+        &mut self,
+        signed: bool,
+        span: Span,
+        parse_ctx: &mut ParseContext,
+    ) -> Result<Value, ParseErrWithMaybePartialResult> {
+        let start = self.offset.0;
+        let mut num = Int::ZERO;
+        let mut shift = 0;
+
+        for len in 0.. {
+            if len == MAX_LEB128_LEN {
+                return Err(parse_ctx
+                    .new_err(ParseErr {
+                        message: format!("LEB128 integer is longer than {MAX_LEB128_LEN} bytes"),
+                        kind: ParseErrKind::InvalidEncoding,
+                        provenance: self.view.provenance_from_range(start..self.offset.0),
+                        span,
+                    })
+                    .into());
+            }
+
+            let (byte, _) = self.read_bytes(Len::from(1), span, parse_ctx)?;
+            let byte = byte[0];
+
+            num |= Int::from(byte & 0x7f) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if signed && byte & 0x40 != 0 {
+                    num -= Int::from(1) << shift;
+                }
+                break;
+            }
+        }
+
+        Ok(Value {
+            kind: ValueKind::Integer(num),
+            provenance: self.view.provenance_from_range(start..self.offset.0),
+            name: None,
+            classification: None,
+            color: None,
+            doc: None,
+            pointer: None,
+        })
+    }
+}
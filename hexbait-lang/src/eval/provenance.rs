@@ -1,33 +1,36 @@
 //! Implements tracking where values originated.
 
-use std::ops::{Add, AddAssign, Range, RangeInclusive};
+use std::{
+    collections::BTreeMap,
+    ops::{Add, AddAssign, Range},
+};
 
-use hexbait_common::AbsoluteOffset;
-use range_set_blaze::RangeSetBlaze;
+use hexbait_common::{AbsoluteOffset, AbsoluteRange};
 
 /// Tracks where parsed values originated.
+///
+/// The byte ranges are kept in a balanced tree, so that adding a range and finding the ranges
+/// overlapping a region take logarithmic time in the number of ranges.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Provenance {
-    /// The byte ranges where the tracked value originated.
-    byte_ranges: RangeSetBlaze<u64>,
+    /// The disjoint and non-adjacent byte ranges as a map from their start to their end.
+    byte_ranges: BTreeMap<u64, u64>,
 }
 
 impl Provenance {
     /// Creates a new empty provenance.
     pub fn empty() -> Provenance {
         Provenance {
-            byte_ranges: RangeSetBlaze::new(),
+            byte_ranges: BTreeMap::new(),
         }
     }
 
-    /// Creates a new provenance from the give window.
-    pub fn from_range(range: Range<AbsoluteOffset>) -> Provenance {
-        let mut byte_ranges = RangeSetBlaze::new();
-        if !range.is_empty() {
-            byte_ranges.ranges_insert(range.start.as_u64()..=range.end.as_u64() - 1);
-        }
+    /// Creates a new provenance from the given range.
+    pub fn from_range(range: AbsoluteRange) -> Provenance {
+        let mut provenance = Provenance::empty();
+        provenance.insert(range.start().as_u64(), range.end().as_u64());
 
-        Provenance { byte_ranges }
+        provenance
     }
 
     /// Returns whether the provenance is empty.
@@ -39,15 +42,94 @@ impl Provenance {
         self.byte_ranges.is_empty()
     }
 
+    /// Determines if the byte at the given offset is part of the provenance.
+    pub fn contains(&self, offset: AbsoluteOffset) -> bool {
+        let offset = offset.as_u64();
+
+        self.byte_ranges
+            .range(..=offset)
+            .next_back()
+            .is_some_and(|(_, &end)| offset < end)
+    }
+
     /// Returns an iterator over the byte ranges that make up this provenance.
-    pub fn byte_ranges(&self) -> impl Iterator<Item = RangeInclusive<u64>> {
-        self.byte_ranges.ranges()
+    pub fn byte_ranges(&self) -> impl Iterator<Item = AbsoluteRange> {
+        self.byte_ranges
+            .iter()
+            .map(|(&start, &end)| to_range(start, end))
+    }
+
+    /// Returns an iterator over the byte ranges of this provenance that overlap the given range.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_common::{AbsoluteOffset, AbsoluteRange};
+    /// # use hexbait_lang::Provenance;
+    /// let range = |start: u64, end: u64| {
+    ///     AbsoluteRange::new(AbsoluteOffset::from(start), AbsoluteOffset::from(end))
+    /// };
+    /// let provenance = &Provenance::from_range(range(0, 4)) + &Provenance::from_range(range(8, 12));
+    ///
+    /// assert!(provenance.contains(AbsoluteOffset::from(9)));
+    /// assert!(!provenance.contains(AbsoluteOffset::from(4)));
+    /// assert_eq!(
+    ///     provenance.byte_ranges_in(range(2, 9)).collect::<Vec<_>>(),
+    ///     [range(0, 4), range(8, 12)],
+    /// );
+    /// ```
+    pub fn byte_ranges_in(&self, range: AbsoluteRange) -> impl Iterator<Item = AbsoluteRange> {
+        let (start, end) = (range.start().as_u64(), range.end().as_u64());
+        let first = self
+            .byte_ranges
+            .range(..=start)
+            .next_back()
+            .filter(|&(_, &range_end)| start < range_end)
+            .map_or(start, |(&range_start, _)| range_start);
+
+        self.byte_ranges
+            .range(first..end.max(first))
+            .map(|(&start, &end)| to_range(start, end))
+    }
+
+    /// Adds the byte range `start..end`, merging it with the ranges it overlaps or touches.
+    fn insert(&mut self, mut start: u64, mut end: u64) {
+        if start >= end {
+            return;
+        }
+
+        if let Some((&previous_start, &previous_end)) = self.byte_ranges.range(..=start).next_back()
+            && previous_end >= start
+        {
+            if previous_end >= end {
+                return;
+            }
+            start = previous_start;
+        }
+
+        while let Some((&next_start, &next_end)) = self.byte_ranges.range(start..=end).next() {
+            self.byte_ranges.remove(&next_start);
+            end = end.max(next_end);
+        }
+
+        self.byte_ranges.insert(start, end);
+    }
+}
+
+/// Converts a range of the provenance to an [`AbsoluteRange`].
+fn to_range(start: u64, end: u64) -> AbsoluteRange {
+    AbsoluteRange::new(AbsoluteOffset::from(start), AbsoluteOffset::from(end))
+}
+
+impl From<AbsoluteRange> for Provenance {
+    fn from(value: AbsoluteRange) -> Self {
+        Provenance::from_range(value)
     }
 }
 
 impl From<Range<AbsoluteOffset>> for Provenance {
     fn from(value: Range<AbsoluteOffset>) -> Self {
-        Provenance::from_range(value)
+        Provenance::from_range(AbsoluteRange::from(value))
     }
 }
 
@@ -62,15 +144,29 @@ impl Add for &Provenance {
 
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn add(self, rhs: &Provenance) -> Self::Output {
-        Provenance {
-            byte_ranges: &self.byte_ranges | &rhs.byte_ranges,
-        }
+        let (larger, smaller) = if self.byte_ranges.len() >= rhs.byte_ranges.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+
+        let mut sum = larger.clone();
+        sum += smaller;
+        sum
     }
 }
 
 impl AddAssign<&Provenance> for Provenance {
-    #[allow(clippy::suspicious_op_assign_impl)]
     fn add_assign(&mut self, rhs: &Self) {
-        self.byte_ranges |= &rhs.byte_ranges;
+        if rhs.byte_ranges.len() > self.byte_ranges.len() {
+            let smaller = std::mem::replace(self, rhs.clone());
+            for (&start, &end) in &smaller.byte_ranges {
+                self.insert(start, end);
+            }
+        } else {
+            for (&start, &end) in &rhs.byte_ranges {
+                self.insert(start, end);
+            }
+        }
     }
 }
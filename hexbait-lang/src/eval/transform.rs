@@ -0,0 +1,404 @@
+//! Implements transformations that turn stored bytes into the bytes they represent.
+
+use std::{
+    fmt,
+    io::{self, Read},
+    ops::Range,
+    sync::Arc,
+};
+
+use base64::{
+    Engine as _,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+
+use crate::ir::Compression;
+
+use super::value::BytesValue;
+
+/// The largest number of bytes that compressed bytes may decompress to.
+///
+/// This protects against small inputs that decompress to huge amounts of data.
+pub const MAX_DECOMPRESSED_LEN: u64 = 256 * 1024 * 1024;
+
+/// The number of bytes of a record protected by each entry of an update sequence array.
+pub const UPDATE_SEQUENCE_STRIDE: usize = 512;
+
+/// The update sequence array of a record, such as an NTFS MFT entry or index record.
+///
+/// Before a record is written, the last two bytes of each of its sectors are moved into the
+/// array and replaced by the update sequence number. A sector that does not end in the update
+/// sequence number was torn by an incomplete write.
+#[derive(Debug, Clone)]
+pub struct UpdateSequence {
+    /// The offset of the array in the record.
+    offset: usize,
+    /// The number of entries in the array, including the update sequence number.
+    count: usize,
+    /// The number that ends every sector as stored.
+    sequence_number: [u8; 2],
+}
+
+impl UpdateSequence {
+    /// Reads the update sequence array with `count` entries at `offset` in the record.
+    ///
+    /// The first entry is the update sequence number, every following entry holds the original
+    /// last two bytes of a sector.
+    pub fn read(
+        record: &BytesValue,
+        offset: usize,
+        count: usize,
+    ) -> Result<UpdateSequence, UpdateSequenceError> {
+        if count == 0 {
+            return Err(UpdateSequenceError::EmptyArray);
+        }
+        if count
+            .checked_mul(2)
+            .and_then(|len| len.checked_add(offset))
+            .is_none_or(|end| end > record.len())
+        {
+            return Err(UpdateSequenceError::ArrayOutOfBounds);
+        }
+
+        let mut sequence_number = [0; 2];
+        record.fill_buf_at(offset, &mut sequence_number)?;
+
+        let sequence = UpdateSequence {
+            offset,
+            count,
+            sequence_number,
+        };
+        if sequence.protected_len() > record.len() {
+            return Err(UpdateSequenceError::RecordTooShort {
+                protected_len: sequence.protected_len(),
+                record_len: record.len(),
+            });
+        }
+
+        Ok(sequence)
+    }
+
+    /// The number of bytes at the start of the record that are protected by the array.
+    pub fn protected_len(&self) -> usize {
+        (self.count - 1) * UPDATE_SEQUENCE_STRIDE
+    }
+
+    /// Applies the fixups to the record without copying it.
+    ///
+    /// Returns the corrected record and the indices of the torn sectors. The corrected bytes
+    /// refer to the array entries they were taken from.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_lang::{BytesValue, UpdateSequence};
+    /// let mut record = vec![0; 1024];
+    /// // the array: the update sequence number followed by the original sector ends
+    /// record[2..8].copy_from_slice(&[0x01, 0x00, 0xaa, 0xbb, 0xcc, 0xdd]);
+    /// record[510..512].copy_from_slice(&[0x01, 0x00]);
+    /// record[1022..1024].copy_from_slice(&[0x02, 0x00]);
+    ///
+    /// let record = BytesValue::Lit(record.into());
+    /// let sequence = UpdateSequence::read(&record, 2, 3).unwrap();
+    /// let (fixed, torn) = sequence.apply(&record).unwrap();
+    ///
+    /// let fixed = fixed.value().unwrap();
+    /// assert_eq!(fixed[510..512], [0xaa, 0xbb]);
+    /// assert_eq!(fixed[1022..1024], [0xcc, 0xdd]);
+    /// assert_eq!(torn, [1]);
+    /// ```
+    pub fn apply(
+        &self,
+        record: &BytesValue,
+    ) -> Result<(BytesValue, Vec<usize>), UpdateSequenceError> {
+        let mut parts = Vec::with_capacity(self.count * 2 - 1);
+        let mut torn = Vec::new();
+        let mut start = 0;
+
+        for sector in 0..self.count - 1 {
+            let sector_end = (sector + 1) * UPDATE_SEQUENCE_STRIDE - 2;
+            let entry = self.offset + (sector + 1) * 2;
+
+            let mut stored = [0; 2];
+            record.fill_buf_at(sector_end, &mut stored)?;
+            if stored != self.sequence_number {
+                torn.push(sector);
+            }
+
+            parts.push(record.slice(start..sector_end)?);
+            parts.push(record.slice(entry..entry + 2)?);
+            start = sector_end + 2;
+        }
+        parts.push(record.slice(start..record.len())?);
+
+        Ok((BytesValue::Concat { parts }, torn))
+    }
+}
+
+/// An error that occurred while applying an update sequence array.
+#[derive(Debug)]
+pub enum UpdateSequenceError {
+    /// The array does not even contain the update sequence number.
+    EmptyArray,
+    /// The array does not fit into the record.
+    ArrayOutOfBounds,
+    /// The record is shorter than the bytes protected by the array.
+    RecordTooShort {
+        /// The number of bytes protected by the array.
+        protected_len: usize,
+        /// The length of the record.
+        record_len: usize,
+    },
+    /// The record could not be read.
+    Io(io::Error),
+}
+
+impl fmt::Display for UpdateSequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateSequenceError::EmptyArray => write!(f, "the update sequence array is empty"),
+            UpdateSequenceError::ArrayOutOfBounds => {
+                write!(f, "the update sequence array exceeds the record")
+            }
+            UpdateSequenceError::RecordTooShort {
+                protected_len,
+                record_len,
+            } => write!(
+                f,
+                "the update sequence array protects {protected_len} bytes, but the record is only {record_len} bytes long"
+            ),
+            UpdateSequenceError::Io(err) => write!(f, "cannot read the record: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for UpdateSequenceError {}
+
+impl From<io::Error> for UpdateSequenceError {
+    fn from(err: io::Error) -> Self {
+        UpdateSequenceError::Io(err)
+    }
+}
+
+/// Decompresses the given bytes, which are compressed in the given format.
+pub fn decompress(
+    compression: Compression,
+    compressed: &BytesValue,
+) -> Result<Vec<u8>, DecompressionError> {
+    let compressed = compressed.value()?;
+    let compressed = &*compressed;
+
+    let decoder: Box<dyn Read + '_> = match compression {
+        Compression::Deflate => Box::new(flate2::read::DeflateDecoder::new(compressed)),
+        Compression::Zlib => Box::new(flate2::read::ZlibDecoder::new(compressed)),
+        Compression::Gzip => Box::new(flate2::read::GzDecoder::new(compressed)),
+        Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(compressed)),
+        Compression::Zstd => Box::new(
+            ruzstd::decoding::StreamingDecoder::new(compressed)
+                .map_err(|err| DecompressionError::Invalid(err.to_string()))?,
+        ),
+    };
+
+    let mut decompressed = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_LEN + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|err| DecompressionError::Invalid(err.to_string()))?;
+
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_LEN {
+        return Err(DecompressionError::TooLarge);
+    }
+
+    Ok(decompressed)
+}
+
+/// An error that occurred while decompressing bytes.
+#[derive(Debug)]
+pub enum DecompressionError {
+    /// The bytes are not validly compressed.
+    Invalid(String),
+    /// The bytes decompress to more than [`MAX_DECOMPRESSED_LEN`] bytes.
+    TooLarge,
+    /// The compressed bytes could not be read.
+    Io(io::Error),
+}
+
+impl fmt::Display for DecompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressionError::Invalid(msg) => write!(f, "invalid compressed data: {msg}"),
+            DecompressionError::TooLarge => write!(
+                f,
+                "the decompressed data is larger than {MAX_DECOMPRESSED_LEN} bytes"
+            ),
+            DecompressionError::Io(err) => write!(f, "cannot read the compressed data: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DecompressionError {}
+
+impl From<io::Error> for DecompressionError {
+    fn from(err: io::Error) -> Self {
+        DecompressionError::Io(err)
+    }
+}
+
+/// A transformation of bytes, after which every byte still comes from specific stored bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ByteTransform {
+    /// Combines every byte with the key, which is repeated as often as necessary, using XOR.
+    Xor {
+        /// The key to combine the bytes with.
+        key: Arc<[u8]>,
+    },
+    /// Reverses the order of the bytes in each group of `width` bytes.
+    ByteSwap {
+        /// The number of bytes in each group.
+        width: usize,
+    },
+    /// Decodes base64 text with the standard alphabet.
+    Base64,
+}
+
+/// Bytes that are the result of transforming other bytes.
+#[derive(Debug)]
+pub struct TransformedBytes {
+    /// The result of the transformation.
+    data: Vec<u8>,
+    /// The bytes that were transformed.
+    source: BytesValue,
+    /// The transformation that was applied.
+    transform: ByteTransform,
+}
+
+impl TransformedBytes {
+    /// Applies the transformation to the source bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait_lang::{BytesValue, ByteTransform, TransformedBytes};
+    /// let source = BytesValue::Lit(b"\x12\x34\x56\x78".as_slice().into());
+    ///
+    /// let swapped = TransformedBytes::new(ByteTransform::ByteSwap { width: 2 }, source).unwrap();
+    /// assert_eq!(swapped.data(), [0x34, 0x12, 0x78, 0x56]);
+    /// ```
+    pub fn new(
+        transform: ByteTransform,
+        source: BytesValue,
+    ) -> Result<TransformedBytes, TransformError> {
+        let mut data = source.value()?.to_vec();
+
+        match &transform {
+            ByteTransform::Xor { key } => {
+                if key.is_empty() {
+                    return Err(TransformError::EmptyKey);
+                }
+                for (byte, key) in data.iter_mut().zip(key.iter().cycle()) {
+                    *byte ^= key;
+                }
+            }
+            ByteTransform::ByteSwap { width } => {
+                if *width == 0 || !data.len().is_multiple_of(*width) {
+                    return Err(TransformError::UnevenLength {
+                        len: data.len(),
+                        width: *width,
+                    });
+                }
+                for group in data.chunks_exact_mut(*width) {
+                    group.reverse();
+                }
+            }
+            ByteTransform::Base64 => {
+                let engine = GeneralPurpose::new(
+                    &base64::alphabet::STANDARD,
+                    GeneralPurposeConfig::new()
+                        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+                );
+                data = engine
+                    .decode(&data)
+                    .map_err(|err| TransformError::InvalidBase64(err.to_string()))?;
+            }
+        }
+
+        Ok(TransformedBytes {
+            data,
+            source,
+            transform,
+        })
+    }
+
+    /// The result of the transformation.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The bytes that were transformed.
+    pub fn source(&self) -> &BytesValue {
+        &self.source
+    }
+
+    /// The transformation that was applied.
+    pub fn transform(&self) -> &ByteTransform {
+        &self.transform
+    }
+
+    /// Returns the range of the source bytes that the given range of the result comes from.
+    pub fn source_range(&self, range: Range<usize>) -> Range<usize> {
+        if range.start >= range.end {
+            return 0..0;
+        }
+
+        let (start, end) = match &self.transform {
+            ByteTransform::Xor { .. } => (range.start, range.end),
+            ByteTransform::ByteSwap { width } => (
+                range.start / width * width,
+                range.end.div_ceil(*width) * width,
+            ),
+            // every group of four characters encodes three bytes
+            ByteTransform::Base64 => (range.start / 3 * 4, range.end.div_ceil(3) * 4),
+        };
+
+        start.min(self.source.len())..end.min(self.source.len())
+    }
+}
+
+/// An error that occurred while transforming bytes.
+#[derive(Debug)]
+pub enum TransformError {
+    /// The key of an XOR transformation is empty.
+    EmptyKey,
+    /// The length of the bytes is not a multiple of the width of the groups to swap.
+    UnevenLength {
+        /// The length of the bytes.
+        len: usize,
+        /// The width of the groups.
+        width: usize,
+    },
+    /// The bytes are not valid base64.
+    InvalidBase64(String),
+    /// The bytes could not be read.
+    Io(io::Error),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::EmptyKey => write!(f, "the XOR key is empty"),
+            TransformError::UnevenLength { len, width } => {
+                write!(f, "cannot swap groups of {width} bytes in {len} bytes")
+            }
+            TransformError::InvalidBase64(msg) => write!(f, "invalid base64: {msg}"),
+            TransformError::Io(err) => write!(f, "cannot read the transformed bytes: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TransformError {}
+
+impl From<io::Error> for TransformError {
+    fn from(err: io::Error) -> Self {
+        TransformError::Io(err)
+    }
+}
@@ -6,14 +6,14 @@ use hexbait_common::{Len, ReadBytes, RelativeOffset};
 
 use crate::{
     Int, View,
-    eval::parse::ParseErrId,
+    eval::parse::{LazyElements, ParseErrId, Pointer},
     ir::{
-        Lit, Symbol,
+        Classification, Color, Lit, Symbol,
         path::{Path, PathComponent},
     },
 };
 
-use super::provenance::Provenance;
+use super::{provenance::Provenance, transform::TransformedBytes};
 
 /// Represents a parsed value.
 #[derive(Clone)]
@@ -22,6 +22,16 @@ pub struct Value {
     pub kind: ValueKind,
     /// The provenance of the value.
     pub provenance: Provenance,
+    /// The symbolic name of the value, if the format description names it.
+    pub name: Option<Arc<str>>,
+    /// How the value is meant to be interpreted, if the format description classifies it.
+    pub classification: Option<Classification>,
+    /// The color of the value, if the format description gives it one.
+    pub color: Option<Color>,
+    /// The documentation of the value, if the format description documents it.
+    pub doc: Option<Arc<str>>,
+    /// The value that this value points to, if it is a pointer.
+    pub pointer: Option<Pointer>,
 }
 
 impl PartialEq for Value {
@@ -68,6 +78,8 @@ pub enum ValueKind {
         items: Vec<Value>,
         /// An error that occurred while parsing the array.
         error: Option<ParseErrId>,
+        /// The elements after `items` that are only evaluated on demand.
+        lazy: Option<LazyElements>,
     },
 }
 
@@ -122,12 +134,17 @@ impl fmt::Debug for ValueKind {
                 }
                 debug_struct.finish()
             }
-            Self::Array { items, error } => {
+            Self::Array { items, error, lazy } => {
                 let mut arr = f.debug_list();
                 arr.entries(items);
 
+                if let Some(lazy) = lazy {
+                    arr.entry(&format!("__lazy: {} more elements", lazy.len()));
+                }
                 if let Some(err) = error {
                     arr.entry(&format!("__error: {err:?}"));
+                }
+                if lazy.is_some() || error.is_some() {
                     arr.finish_non_exhaustive()
                 } else {
                     arr.finish()
@@ -281,6 +298,13 @@ impl PartialEq<Lit> for ValueKind {
                     false
                 }
             }
+            Lit::Float(other) => {
+                if let ValueKind::Float(this) = self {
+                    this == other
+                } else {
+                    false
+                }
+            }
             Lit::Bytes(other) => {
                 if let ValueKind::Bytes(this) = self {
                     *this == BytesValue::Lit(Arc::clone(other))
@@ -288,6 +312,14 @@ impl PartialEq<Lit> for ValueKind {
                     false
                 }
             }
+            Lit::BytePattern(pattern) => {
+                if let ValueKind::Bytes(this) = self {
+                    this.len() == pattern.len()
+                        && this.value().is_ok_and(|bytes| pattern.matches(&bytes))
+                } else {
+                    false
+                }
+            }
             Lit::Bool(other) => {
                 if let ValueKind::Boolean(this) = self {
                     this == other
@@ -323,6 +355,13 @@ pub enum BytesValue {
         /// The parts that are concatenated together.
         parts: Vec<BytesValue>,
     },
+    /// The bytes are a part of the result of transforming other bytes.
+    Transformed {
+        /// The result of the transformation.
+        transformed: Arc<TransformedBytes>,
+        /// The range of the result that the bytes consist of.
+        range: Range<usize>,
+    },
 }
 
 impl BytesValue {
@@ -332,6 +371,9 @@ impl BytesValue {
     /// The number of bytes that can be stored inline.
     pub const INLINE_LEN: usize = Self::PREFIX_SUFFIX_LEN * 2;
 
+    /// The number of bytes that are read at once when comparing or processing large values.
+    const CHUNK_LEN: usize = 64 * 1024;
+
     /// Returns the value of the bytes.
     pub fn value(&self) -> io::Result<ReadBytes<'_>> {
         match self {
@@ -342,7 +384,7 @@ impl BytesValue {
                 len,
                 buf,
             } if let len = len.as_u64() as usize
-                && len < Self::INLINE_LEN =>
+                && len <= Self::INLINE_LEN =>
             {
                 Ok(ReadBytes::from_buf(&buf[..len]))
             }
@@ -360,6 +402,9 @@ impl BytesValue {
 
                 Ok(ReadBytes::from_vec(out))
             }
+            BytesValue::Transformed { transformed, range } => {
+                Ok(ReadBytes::from_buf(&transformed.data()[range.clone()]))
+            }
         }
     }
 
@@ -368,19 +413,29 @@ impl BytesValue {
     /// If they fit in the `buf` `Some(len)` is returned and `buf[..len]` is filled.
     /// If they do not fit in the `buf` the first half of the buffer is filled with a prefix and the second half with a suffix and `None` is returned.
     pub fn preview_slice(&self, buf: &mut [u8; Self::INLINE_LEN]) -> Option<usize> {
-        match self {
-            BytesValue::Lit(lit) => {
-                if lit.len() <= Self::INLINE_LEN {
-                    buf[..lit.len()].copy_from_slice(lit);
+        /// Fills the preview from bytes that are stored in memory.
+        fn preview_from_slice(
+            slice: &[u8],
+            buf: &mut [u8; BytesValue::INLINE_LEN],
+        ) -> Option<usize> {
+            if slice.len() <= BytesValue::INLINE_LEN {
+                buf[..slice.len()].copy_from_slice(slice);
+
+                Some(slice.len())
+            } else {
+                buf[..BytesValue::PREFIX_SUFFIX_LEN]
+                    .copy_from_slice(&slice[..BytesValue::PREFIX_SUFFIX_LEN]);
+                buf[BytesValue::PREFIX_SUFFIX_LEN..]
+                    .copy_from_slice(&slice[slice.len() - BytesValue::PREFIX_SUFFIX_LEN..]);
 
-                    Some(lit.len())
-                } else {
-                    buf[..Self::PREFIX_SUFFIX_LEN].copy_from_slice(&lit[..Self::PREFIX_SUFFIX_LEN]);
-                    buf[Self::PREFIX_SUFFIX_LEN..]
-                        .copy_from_slice(&lit[lit.len() - Self::PREFIX_SUFFIX_LEN..]);
+                None
+            }
+        }
 
-                    None
-                }
+        match self {
+            BytesValue::Lit(lit) => preview_from_slice(lit, buf),
+            BytesValue::Transformed { transformed, range } => {
+                preview_from_slice(&transformed.data()[range.clone()], buf)
             }
             BytesValue::FromView {
                 len, buf: inline, ..
@@ -455,6 +510,7 @@ impl BytesValue {
             BytesValue::Lit(lit) => lit.len(),
             BytesValue::FromView { len, .. } => len.as_u64() as usize,
             BytesValue::Concat { parts } => parts.iter().map(|part| part.len()).sum(),
+            BytesValue::Transformed { range, .. } => range.len(),
         }
     }
 
@@ -463,6 +519,64 @@ impl BytesValue {
         self.len() == 0
     }
 
+    /// Returns the bytes in the given range.
+    ///
+    /// Only literals are copied, other bytes keep referring to where they are stored.
+    pub fn slice(&self, range: Range<usize>) -> io::Result<BytesValue> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes value too short for slice",
+            ));
+        }
+
+        match self {
+            BytesValue::Lit(lit) => Ok(BytesValue::Lit(Arc::from(&lit[range]))),
+            BytesValue::FromView { view, start, .. } => {
+                let len = range.end - range.start;
+                let mut buf = [0; Self::INLINE_LEN];
+                if len <= Self::INLINE_LEN {
+                    self.fill_buf_at(range.start, &mut buf[..len])?;
+                } else {
+                    let (prefix, suffix) = buf.split_at_mut(Self::PREFIX_SUFFIX_LEN);
+                    self.fill_buf_at(range.start, prefix)?;
+                    self.fill_buf_at(range.end - Self::PREFIX_SUFFIX_LEN, suffix)?;
+                }
+
+                Ok(BytesValue::FromView {
+                    view: view.clone(),
+                    start: *start + Len::from(range.start as u64),
+                    len: Len::from(len as u64),
+                    buf,
+                })
+            }
+            BytesValue::Concat { parts } => {
+                let mut sliced = Vec::new();
+                let mut part_start = 0;
+
+                for part in parts {
+                    let part_end = part_start + part.len();
+                    if part_end > range.start && part_start < range.end {
+                        sliced.push(part.slice(
+                            range.start.saturating_sub(part_start)
+                                ..range.end.min(part_end) - part_start,
+                        )?);
+                    }
+                    part_start = part_end;
+                }
+
+                Ok(BytesValue::Concat { parts: sliced })
+            }
+            BytesValue::Transformed {
+                transformed,
+                range: own_range,
+            } => Ok(BytesValue::Transformed {
+                transformed: Arc::clone(transformed),
+                range: own_range.start + range.start..own_range.start + range.end,
+            }),
+        }
+    }
+
     /// Fills the given buffer from bytes at the given offset.
     pub fn fill_buf_at(&self, offset: usize, buf: &mut [u8]) -> io::Result<()> {
         match self {
@@ -476,6 +590,17 @@ impl BytesValue {
                 })?;
                 buf.copy_from_slice(slice);
             }
+            BytesValue::Transformed { transformed, range } => {
+                let data = &transformed.data()[range.clone()];
+                let end = offset.checked_add(buf.len());
+                let slice = end.and_then(|end| data.get(offset..end)).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "transformed bytes value too short",
+                    )
+                })?;
+                buf.copy_from_slice(slice);
+            }
             BytesValue::FromView {
                 view,
                 start,
@@ -530,6 +655,21 @@ impl BytesValue {
         Ok(())
     }
 
+    /// Calls `f` with consecutive chunks of the bytes, so that large values are never read into
+    /// memory as a whole.
+    pub fn for_each_chunk(&self, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+        let len = self.len();
+        let mut buf = vec![0; len.min(Self::CHUNK_LEN)];
+
+        for offset in (0..len).step_by(Self::CHUNK_LEN) {
+            let chunk = &mut buf[..Self::CHUNK_LEN.min(len - offset)];
+            self.fill_buf_at(offset, chunk)?;
+            f(chunk);
+        }
+
+        Ok(())
+    }
+
     /// Returns the provenance of the bytes of the given range.
     pub fn provenance_range(&self, range: Range<RelativeOffset>) -> Provenance {
         match self {
@@ -537,7 +677,7 @@ impl BytesValue {
             BytesValue::FromView {
                 view, start, len, ..
             } => {
-                let clamp = |off: RelativeOffset| std::cmp::min(Len::from(off.as_u64()), *len);
+                let clamp = |off: RelativeOffset| std::cmp::min(off.len_from_base(), *len);
 
                 view.provenance_from_range(*start + clamp(range.start)..*start + clamp(range.end))
             }
@@ -571,6 +711,20 @@ impl BytesValue {
 
                 provenance
             }
+            BytesValue::Transformed {
+                transformed,
+                range: own_range,
+            } => {
+                let clamp = |off: RelativeOffset| {
+                    std::cmp::min(own_range.start + off.as_u64() as usize, own_range.end)
+                };
+                let source_range = transformed.source_range(clamp(range.start)..clamp(range.end));
+
+                transformed.source().provenance_range(
+                    RelativeOffset::from(source_range.start as u64)
+                        ..RelativeOffset::from(source_range.end as u64),
+                )
+            }
         }
     }
 }
@@ -582,13 +736,30 @@ impl PartialEq for BytesValue {
             return false;
         }
 
-        let Ok(self_val) = self.value() else {
-            return false;
-        };
-        let Ok(other_val) = other.value() else {
-            return false;
-        };
+        let len = self.len();
+        if len <= BytesValue::INLINE_LEN {
+            let Ok(self_val) = self.value() else {
+                return false;
+            };
+            let Ok(other_val) = other.value() else {
+                return false;
+            };
+
+            return *self_val == *other_val;
+        }
 
-        *self_val == *other_val
+        // compare large values chunk by chunk, so that they are never read into memory as a whole
+        let chunk_len = len.min(BytesValue::CHUNK_LEN);
+        let mut self_buf = vec![0; chunk_len];
+        let mut other_buf = vec![0; chunk_len];
+        (0..len).step_by(chunk_len).all(|offset| {
+            let chunk_len = chunk_len.min(len - offset);
+            let self_chunk = &mut self_buf[..chunk_len];
+            let other_chunk = &mut other_buf[..chunk_len];
+
+            self.fill_buf_at(offset, self_chunk).is_ok()
+                && other.fill_buf_at(offset, other_chunk).is_ok()
+                && self_chunk == other_chunk
+        })
     }
 }
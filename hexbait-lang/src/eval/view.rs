@@ -28,6 +28,13 @@ enum ViewType {
     },
     /// Parses out of the given bytes.
     Bytes(BytesValue),
+    /// Parses out of data that was decompressed from the given bytes.
+    Decompressed {
+        /// The decompressed data.
+        data: Arc<[u8]>,
+        /// The compressed bytes.
+        compressed: BytesValue,
+    },
 }
 
 impl View {
@@ -41,13 +48,24 @@ impl View {
         View(Arc::new(ViewType::Bytes(bytes)))
     }
 
+    /// Creates a view of the given data that was decompressed from the `compressed` bytes.
+    ///
+    /// Since individual decompressed bytes cannot be traced back to specific compressed bytes,
+    /// every part of the view comes from all of the compressed bytes.
+    pub fn from_decompressed(data: Vec<u8>, compressed: BytesValue) -> View {
+        View(Arc::new(ViewType::Decompressed {
+            data: data.into(),
+            compressed,
+        }))
+    }
+
     /// Creates a subview with the given range in the current view.
     ///
     /// This function does not check any bounds, so the view may be invalid.
     pub fn subview(&self, range: Range<RelativeOffset>) -> View {
         if let ViewType::Subview { view, valid_range } = &*self.0 {
             // avoid long chains of sub-views to improve read performance
-            let offset = Len::from(valid_range.start.as_u64());
+            let offset = valid_range.start.len_from_base();
 
             let start = range.start + offset;
             let end = std::cmp::min(range.end + offset, valid_range.end);
@@ -72,17 +90,11 @@ impl View {
             ViewType::Subview { view, valid_range } => {
                 assert!(valid_range.end >= valid_range.start);
 
-                let len = view.len();
-                if valid_range.start.as_u64() > len.as_u64() {
-                    Len::ZERO
-                } else {
-                    Len::from(
-                        std::cmp::min(len.as_u64(), valid_range.end.as_u64())
-                            - valid_range.start.as_u64(),
-                    )
-                }
+                std::cmp::min(view.len().to_relative(), valid_range.end)
+                    .saturating_len_since(valid_range.start)
             }
             ViewType::Bytes(bytes) => Len::from(bytes.len() as u64),
+            ViewType::Decompressed { data, .. } => Len::from(data.len() as u64),
         }
     }
 
@@ -100,7 +112,7 @@ impl View {
         let out_buf = match &*self.0 {
             ViewType::Input(input) => input.read_at(offset.to_absolute(), len, None)?,
             ViewType::Subview { view, valid_range } => {
-                view.read_at(valid_range.start + Len::from(offset.as_u64()), len)?
+                view.read_at(valid_range.start + offset.len_from_base(), len)?
             }
             ViewType::Bytes(bytes) => {
                 let mut out = vec![0; len.as_u64() as usize];
@@ -109,6 +121,12 @@ impl View {
 
                 ReadBytes::from_vec(out)
             }
+            ViewType::Decompressed { data, .. } => {
+                let start = offset.as_u64() as usize;
+                let end = std::cmp::min(start.saturating_add(len.as_u64() as usize), data.len());
+
+                ReadBytes::from_buf(&data[start..end])
+            }
         };
 
         Ok(out_buf)
@@ -118,13 +136,22 @@ impl View {
     pub(crate) fn provenance_from_range(&self, range: Range<RelativeOffset>) -> Provenance {
         match &*self.0 {
             ViewType::Input(_) => {
-                Provenance::from_range(range.start.to_absolute()..range.end.to_absolute())
+                Provenance::from(range.start.to_absolute()..range.end.to_absolute())
             }
             ViewType::Subview { view, valid_range } => view.provenance_from_range(
-                range.start + Len::from(valid_range.start.as_u64())
-                    ..range.end + Len::from(valid_range.start.as_u64()),
+                range.start + valid_range.start.len_from_base()
+                    ..range.end + valid_range.start.len_from_base(),
             ),
             ViewType::Bytes(bytes) => bytes.provenance_range(range),
+            ViewType::Decompressed { compressed, .. } => {
+                if range.start == range.end {
+                    Provenance::empty()
+                } else {
+                    compressed.provenance_range(
+                        RelativeOffset::ZERO..RelativeOffset::from(compressed.len() as u64),
+                    )
+                }
+            }
         }
     }
 }
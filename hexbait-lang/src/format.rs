@@ -0,0 +1,375 @@
+//! Implements formatting format descriptions in a canonical style.
+//!
+//! The formatter works on the tokens of the lossless syntax tree, so comments are preserved.
+//! Line breaks are kept where they were written, except that every item of a `struct` is put on
+//! its own line, while spaces and indentation are always normalized.
+
+use crate::{
+    NodeKind, ParseError, SyntaxKind, SyntaxNode, SyntaxToken, ast::AstNode as _, lexer::TokenKind,
+    parse,
+};
+
+/// The string used for one level of indentation.
+const INDENT: &str = "    ";
+
+/// The pairs of tokens that form a single operator when they are written without space between
+/// them, such as `==` or `=>`.
+const COMPOUND_TOKENS: [(TokenKind, TokenKind); 10] = [
+    (TokenKind::Equals, TokenKind::Equals),
+    (TokenKind::ExclamationMark, TokenKind::Equals),
+    (TokenKind::LAngle, TokenKind::Equals),
+    (TokenKind::RAngle, TokenKind::Equals),
+    (TokenKind::Ampersand, TokenKind::Ampersand),
+    (TokenKind::VerticalLine, TokenKind::VerticalLine),
+    (TokenKind::LAngle, TokenKind::LAngle),
+    (TokenKind::RAngle, TokenKind::RAngle),
+    (TokenKind::Equals, TokenKind::RAngle),
+    (TokenKind::Dot, TokenKind::Dot),
+];
+
+/// The nodes whose items are each written on their own lines.
+const STRUCT_NODES: &[NodeKind] = &[NodeKind::StructBlock, NodeKind::File];
+
+/// The nodes whose items may be written on their own lines.
+const LIST_NODES: &[NodeKind] = &[
+    NodeKind::StructBlock,
+    NodeKind::File,
+    NodeKind::SwitchParseType,
+    NodeKind::BitfieldParseType,
+    NodeKind::EnumParseType,
+    NodeKind::FlagsParseType,
+];
+
+/// Formats the format description in `src` in the canonical style.
+///
+/// Returns the syntax errors instead if `src` cannot be parsed.
+///
+/// ```
+/// # use hexbait_lang::format;
+/// let src = "!endian le;\nheader{magic bytes=\"MZ\"; // the magic\n  size u32;};\n";
+///
+/// assert_eq!(
+///     format(src).unwrap(),
+///     "\
+/// !endian le;
+/// header {
+///     magic bytes = \"MZ\"; // the magic
+///     size u32;
+/// };
+/// ",
+/// );
+/// ```
+pub fn format(src: &str) -> Result<String, Vec<ParseError>> {
+    let parse = parse(src);
+    if !parse.errors.is_empty() {
+        return Err(parse.errors);
+    }
+
+    let mut formatter = Formatter {
+        out: String::new(),
+        brackets: Vec::new(),
+        indent: 0,
+        pending_break: false,
+        ends_unit: true,
+    };
+    let mut prev: Option<SyntaxToken> = None;
+    let mut newlines = 0;
+    for token in parse
+        .ast
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        let kind = token_kind(&token);
+        if kind == TokenKind::Whitespace {
+            newlines += token.text().matches('\n').count();
+            continue;
+        }
+
+        if let Some(prev) = &prev {
+            formatter.separate(prev, &token, newlines);
+        }
+        formatter.push(&token);
+
+        newlines = usize::from(is_line_comment(kind));
+        prev = Some(token);
+    }
+
+    let mut out = formatter.out;
+    out.truncate(out.trim_end().len());
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+/// The state of formatting a format description.
+struct Formatter {
+    /// The formatted text so far.
+    out: String,
+    /// The indentation levels of the lines of the currently open brackets.
+    brackets: Vec<usize>,
+    /// The indentation level of the current line.
+    indent: usize,
+    /// Whether the next token that is not a comment must start on a new line.
+    pending_break: bool,
+    /// Whether the last token that is not a comment ends something that is written on its own
+    /// lines, so that a line starting after it is not a continuation.
+    ends_unit: bool,
+}
+
+impl Formatter {
+    /// Writes what separates the token `prev` from the following `token`.
+    ///
+    /// `newlines` is the number of line breaks between them in the original source.
+    fn separate(&mut self, prev: &SyntaxToken, token: &SyntaxToken, newlines: usize) {
+        let prev_kind = token_kind(prev);
+        let kind = token_kind(token);
+
+        let mut newlines = if is_line_comment(prev_kind) {
+            newlines.max(1)
+        } else if kind == TokenKind::Semicolon
+            || kind == TokenKind::Comma
+            || is_struct_block_brace(token, TokenKind::LBrace)
+            || (is_keyword(token, "else") && prev_kind == TokenKind::RBrace)
+        {
+            0
+        } else if is_struct_block_brace(token, TokenKind::RBrace) {
+            // empty blocks are written as `{}`
+            if prev_kind == TokenKind::LBrace && prev.parent() == token.parent() {
+                0
+            } else {
+                newlines.max(1)
+            }
+        } else if self.pending_break && !is_comment(kind) {
+            newlines.max(1)
+        } else {
+            newlines
+        };
+
+        // there is at most one empty line, but none at the start or end of a block
+        newlines = newlines.min(2);
+        if is_opening_bracket(prev_kind) || is_closing_bracket(kind) {
+            newlines = newlines.min(1);
+        }
+
+        if newlines == 0 {
+            if needs_space(prev, token) {
+                self.out.push(' ');
+            }
+            return;
+        }
+
+        for _ in 0..newlines {
+            self.out.push('\n');
+        }
+        self.pending_break = false;
+        self.indent = match self.brackets.last() {
+            Some(&indent) if is_closing_bracket(kind) => indent,
+            Some(&indent) => indent + 1,
+            None => 0,
+        };
+        // lines that continue an expression are indented further
+        if !self.ends_unit && !is_closing_bracket(kind) {
+            self.indent += 1;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Writes the token.
+    fn push(&mut self, token: &SyntaxToken) {
+        let kind = token_kind(token);
+        if is_comment(kind) {
+            self.out.push_str(token.text().trim_end());
+        } else {
+            self.out.push_str(token.text());
+        }
+
+        if is_opening_bracket(kind) {
+            self.brackets.push(self.indent);
+        } else if is_closing_bracket(kind) {
+            self.brackets.pop();
+        }
+
+        if is_struct_block_brace(token, TokenKind::LBrace) || ends_item(token, STRUCT_NODES) {
+            self.pending_break = true;
+        }
+        if !is_comment(kind) {
+            self.ends_unit = is_opening_bracket(kind)
+                || kind == TokenKind::Comma
+                || kind == TokenKind::Semicolon
+                || ends_item(token, LIST_NODES);
+        }
+    }
+}
+
+/// Returns whether a space is written between `prev` and `token` on the same line.
+fn needs_space(prev: &SyntaxToken, token: &SyntaxToken) -> bool {
+    let prev_kind = token_kind(prev);
+    let kind = token_kind(token);
+    let prev_parent = parent_kind(prev);
+    let parent = parent_kind(token);
+    let adjacent = prev.text_range().end() == token.text_range().start();
+
+    if is_comment(prev_kind) || is_comment(kind) {
+        return true;
+    }
+    if adjacent && COMPOUND_TOKENS.contains(&(prev_kind, kind)) {
+        return false;
+    }
+    if prev_kind == TokenKind::Comma || prev_kind == TokenKind::Colon {
+        return true;
+    }
+    if parent == Some(NodeKind::ByteConcat) && prev_parent == Some(NodeKind::ByteConcat) {
+        // wildcards such as `0?` are written next to their digit
+        return prev_kind != TokenKind::LAngle && kind != TokenKind::RAngle && !adjacent;
+    }
+
+    match kind {
+        TokenKind::Semicolon
+        | TokenKind::Comma
+        | TokenKind::Colon
+        | TokenKind::Dot
+        | TokenKind::RParen
+        | TokenKind::RBracket => return false,
+        TokenKind::RBrace if prev_kind == TokenKind::LBrace => return false,
+        TokenKind::LParen => {
+            return !matches!(
+                parent,
+                Some(
+                    NodeKind::CallExpr
+                        | NodeKind::MethodCallExpr
+                        | NodeKind::NamedParseType
+                        | NodeKind::Metavar
+                        | NodeKind::StructParams
+                        | NodeKind::Attribute
+                        | NodeKind::DynamicSizeIntParseType
+                        | NodeKind::DynamicSizeUIntParseType
+                        | NodeKind::PeekExpr
+                        | NodeKind::ConcatExpr
+                )
+            ) && !is_opening_bracket(prev_kind)
+                && !is_prefix(prev);
+        }
+        TokenKind::LBracket
+            if matches!(parent, Some(NodeKind::IndexExpr | NodeKind::SliceExpr)) =>
+        {
+            return false;
+        }
+        TokenKind::LAngle | TokenKind::RAngle if parent == Some(NodeKind::PointerParseType) => {
+            return false;
+        }
+        _ => (),
+    }
+
+    match prev_kind {
+        TokenKind::LParen | TokenKind::LBracket | TokenKind::Dot | TokenKind::Dollar => false,
+        TokenKind::At => prev_parent != Some(NodeKind::Attribute),
+        TokenKind::LAngle => prev_parent != Some(NodeKind::PointerParseType),
+        _ => !is_prefix(prev),
+    }
+}
+
+/// Returns whether the token is written directly in front of what follows it, like the `!` of
+/// declarations and prefix operators.
+fn is_prefix(token: &SyntaxToken) -> bool {
+    match parent_kind(token) {
+        Some(NodeKind::Op) => token
+            .parent()
+            .and_then(|op| op.parent())
+            .is_some_and(|expr| node_kind(&expr) == Some(NodeKind::PrefixExpr)),
+        _ => token_kind(token) == TokenKind::ExclamationMark,
+    }
+}
+
+/// Returns whether the token is the last token of an item in a node of one of the given kinds,
+/// such as a field of a `struct`.
+fn ends_item(token: &SyntaxToken, kinds: &[NodeKind]) -> bool {
+    for node in token.parent_ancestors() {
+        if last_significant_token(&node).as_ref() != Some(token) {
+            return false;
+        }
+        if node
+            .parent()
+            .and_then(|parent| node_kind(&parent))
+            .is_some_and(|kind| kinds.contains(&kind))
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Returns the last token of the node that is not trivia.
+fn last_significant_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    let mut token = node.last_token()?;
+    while token_kind(&token).is_trivia() {
+        token = token.prev_token()?;
+        if !node.text_range().contains_range(token.text_range()) {
+            return None;
+        }
+    }
+
+    Some(token)
+}
+
+/// Returns whether the token is the given brace of a `struct` block.
+fn is_struct_block_brace(token: &SyntaxToken, brace: TokenKind) -> bool {
+    token_kind(token) == brace && parent_kind(token) == Some(NodeKind::StructBlock)
+}
+
+/// Returns whether the token is the given contextual keyword.
+fn is_keyword(token: &SyntaxToken, keyword: &str) -> bool {
+    token_kind(token) == TokenKind::Identifier && token.text() == keyword
+}
+
+/// Returns whether the token kind is a comment.
+fn is_comment(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::DocComment | TokenKind::LineComment | TokenKind::BlockComment
+    )
+}
+
+/// Returns whether the token kind is a comment that extends to the end of its line.
+fn is_line_comment(kind: TokenKind) -> bool {
+    matches!(kind, TokenKind::DocComment | TokenKind::LineComment)
+}
+
+/// Returns whether the token kind opens a bracket that its content is indented in.
+fn is_opening_bracket(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace
+    )
+}
+
+/// Returns whether the token kind closes a bracket that its content is indented in.
+fn is_closing_bracket(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace
+    )
+}
+
+/// Returns the kind of the token.
+fn token_kind(token: &SyntaxToken) -> TokenKind {
+    token.kind().expect_token()
+}
+
+/// Returns the kind of the node.
+fn node_kind(node: &SyntaxNode) -> Option<NodeKind> {
+    match node.kind() {
+        SyntaxKind::Node { kind } => Some(kind),
+        SyntaxKind::Token { .. } => None,
+    }
+}
+
+/// Returns the kind of the node that contains the token.
+fn parent_kind(token: &SyntaxToken) -> Option<NodeKind> {
+    token.parent().and_then(|parent| node_kind(&parent))
+}
@@ -0,0 +1,1203 @@
+//! Implements translating ImHex patterns to format descriptions.
+//!
+//! Patterns of the ImHex pattern language (`.hexpat` files) are translated to the source of an
+//! equivalent format description, which can then be parsed and lowered like any other one. The
+//! declarative part of the pattern language translates well: `struct`s, `bitfield`s, `enum`s,
+//! arrays, pointers, conditions and placements. Functions, loops, `union`s, `match` statements
+//! and most of the standard library have no equivalent and are reported instead.
+//!
+//! Bitfields are translated with their fields starting at the least significant bit for little
+//! endian and at the most significant bit for big endian integers, which is the default order of
+//! both languages for little endian integers.
+
+use std::collections::HashMap;
+
+use crate::{Int, Span, ir::Spanned};
+
+/// The keywords of the hexbait language that cannot be used as names.
+const KEYWORDS: [&str; 9] = [
+    "bytes", "struct", "let", "const", "peek", "concat", "switch", "true", "false",
+];
+
+/// An ImHex pattern translated to a format description.
+#[derive(Debug, Clone)]
+pub struct HexpatTranslation {
+    /// The source of the format description.
+    pub hbl: String,
+    /// The constructs of the pattern that have no equivalent and were left out.
+    ///
+    /// The spans refer to the source of the pattern.
+    pub unsupported: Vec<Spanned<String>>,
+}
+
+/// Translates an ImHex pattern to the source of a format description.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_lang::translate_hexpat;
+/// let translation = translate_hexpat(
+///     r#"
+///     enum Kind : u8 { File = 1, Directory };
+///     bitfield Flags { hidden : 1; system : 1; padding : 6; };
+///     struct Entry {
+///         Kind kind;
+///         Flags flags;
+///         u16 name_len;
+///         char name[name_len];
+///         u32 *next : u32;
+///     };
+///     Entry entry @ 0x10;
+///     "#,
+/// );
+///
+/// assert!(translation.unsupported.is_empty());
+/// assert_eq!(
+///     translation.hbl,
+///     r#"struct Entry {
+///     kind enum u8 {
+///         1 => "File",
+///         2 => "Directory",
+///     };
+///     flags bits u8 {
+///         hidden u1;
+///         system u1;
+///         _padding u6;
+///     };
+///     name_len u16;
+///     name bytes len name_len;
+///     next ptr<u32> u32;
+/// }
+///
+/// !seek to 0x10;
+/// entry Entry;
+/// "#,
+/// );
+/// ```
+pub fn translate_hexpat(src: &str) -> HexpatTranslation {
+    let mut translator = Translator {
+        tokens: tokenize(src),
+        pos: 0,
+        out: String::new(),
+        unsupported: Vec::new(),
+        aliases: HashMap::new(),
+        enums: HashMap::new(),
+        bitfields: HashMap::new(),
+        endianness: "le",
+        hidden_fields: 0,
+    };
+
+    while translator.peek().is_some() {
+        translator.item();
+    }
+
+    let hbl = match crate::format(&translator.out) {
+        Ok(formatted) => formatted,
+        Err(_) => translator.out,
+    };
+
+    HexpatTranslation {
+        hbl,
+        unsupported: translator.unsupported,
+    }
+}
+
+/// The kinds of tokens in the pattern language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// A name, possibly qualified with `::`.
+    Ident,
+    /// An integer or floating point number.
+    Number,
+    /// A string literal.
+    Str,
+    /// A character literal.
+    Char,
+    /// A preprocessor directive, such as `#pragma endian big`.
+    Directive,
+    /// An operator or a delimiter.
+    Punct,
+}
+
+/// A token in the pattern language.
+#[derive(Debug, Clone)]
+pub(crate) struct Token<'src> {
+    /// The kind of the token.
+    pub(crate) kind: TokenKind,
+    /// The text of the token.
+    pub(crate) text: &'src str,
+    /// The location of the token in the source.
+    pub(crate) span: Span,
+    /// The documentation comment in front of the token, if there is one.
+    pub(crate) doc: Option<String>,
+}
+
+/// The operators that consist of two characters.
+const TWO_CHAR_PUNCTS: [&str; 10] = ["==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "::", "^^"];
+
+/// Splits the source of a pattern into tokens, dropping whitespace and comments.
+///
+/// C sources share the same tokens, so this is also used to read C headers.
+pub(crate) fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut doc: Option<String> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let rest = &src[i..];
+        let kind = match bytes[i] {
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' if rest.starts_with("///") || rest.starts_with("/**") => {
+                let (text, len) = if rest.starts_with("///") {
+                    let len = rest.find('\n').unwrap_or(rest.len());
+                    (&rest[3..len], len)
+                } else {
+                    let len = rest.find("*/").map(|end| end + 2).unwrap_or(rest.len());
+                    (rest[3..len].trim_end_matches("*/"), len)
+                };
+                let doc = doc.get_or_insert_with(String::new);
+                for line in text.lines() {
+                    let line = line.trim().trim_start_matches('*').trim();
+                    if !line.is_empty() {
+                        if !doc.is_empty() {
+                            doc.push('\n');
+                        }
+                        doc.push_str(line);
+                    }
+                }
+                i += len;
+                continue;
+            }
+            b'/' if rest.starts_with("//") => {
+                i += rest.find('\n').unwrap_or(rest.len());
+                continue;
+            }
+            b'/' if rest.starts_with("/*") => {
+                i += rest.find("*/").map(|end| end + 2).unwrap_or(rest.len());
+                continue;
+            }
+            b'#' => {
+                i += rest.find('\n').unwrap_or(rest.len());
+                TokenKind::Directive
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while i < bytes.len() {
+                    if bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' {
+                        i += 1;
+                    } else if src[i..].starts_with("::")
+                        && src[i + 2..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    {
+                        i += 2;
+                    } else {
+                        break;
+                    }
+                }
+                TokenKind::Ident
+            }
+            c if c.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                if quote == b'"' {
+                    TokenKind::Str
+                } else {
+                    TokenKind::Char
+                }
+            }
+            _ => {
+                i += if TWO_CHAR_PUNCTS.iter().any(|punct| rest.starts_with(punct)) {
+                    2
+                } else {
+                    rest.chars().next().map(char::len_utf8).unwrap_or(1)
+                };
+                TokenKind::Punct
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &src[start..i],
+            span: Span { start, end: i },
+            doc: doc.take(),
+        });
+    }
+
+    tokens
+}
+
+/// Converts a name of the pattern language to a name that can be used in a format description.
+pub(crate) fn name(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the type of the format description for a built-in type of the pattern language.
+fn builtin_type(name: &str) -> Option<String> {
+    let sized = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|bits| bits.parse::<u32>().ok())
+            .filter(|bits| bits % 8 == 0 && (8..=128).contains(bits))
+    };
+
+    Some(match name {
+        "float" => String::from("f32"),
+        "double" => String::from("f64"),
+        "char" | "bool" => String::from("u8"),
+        "char16" => String::from("u16"),
+        _ => {
+            if let Some(bits) = sized("u") {
+                format!("u{bits}")
+            } else if let Some(bits) = sized("s") {
+                format!("i{bits}")
+            } else {
+                return None;
+            }
+        }
+    })
+}
+
+/// Parses an integer literal of the pattern language or of C, ignoring type suffixes.
+pub(crate) fn parse_int(text: &str) -> Option<Int> {
+    let text = text
+        .trim_end_matches(['u', 'U', 'l', 'L'])
+        .replace('\'', "");
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        crate::int_from_str(16, hex)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        crate::int_from_str(2, bin)
+    } else if let Some(oct) = lower.strip_prefix("0o") {
+        crate::int_from_str(8, oct)
+    } else {
+        crate::int_from_str(10, &lower)
+    }
+}
+
+/// Returns the value of a character literal, such as `'A'`.
+pub(crate) fn char_value(text: &str) -> Option<u32> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let c = match inner {
+        "\\0" => '\0',
+        "\\n" => '\n',
+        "\\r" => '\r',
+        "\\t" => '\t',
+        "\\\\" => '\\',
+        "\\'" => '\'',
+        _ => {
+            let mut chars = inner.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            c
+        }
+    };
+
+    Some(u32::from(c))
+}
+
+/// A `bitfield` of the pattern.
+#[derive(Clone)]
+struct Bitfield {
+    /// The fields with their bit width and whether they are signed.
+    fields: Vec<(String, u32, bool)>,
+}
+
+/// An `enum` of the pattern.
+#[derive(Clone)]
+struct Enum {
+    /// The type of the format description that the values are parsed as.
+    ty: String,
+    /// The named values.
+    values: Vec<(Int, String)>,
+}
+
+/// The attributes of a field that are translated.
+#[derive(Default)]
+struct Attributes {
+    /// The color of the field as `#rrggbb`.
+    color: Option<String>,
+    /// The documentation of the field.
+    doc: Option<String>,
+}
+
+/// Keeps track of the state of a translation.
+struct Translator<'src> {
+    /// The tokens of the pattern.
+    tokens: Vec<Token<'src>>,
+    /// The index of the next token.
+    pos: usize,
+    /// The translated source.
+    out: String,
+    /// The constructs that could not be translated.
+    unsupported: Vec<Spanned<String>>,
+    /// The translated types of the `using` aliases by their names.
+    aliases: HashMap<&'src str, String>,
+    /// The `enum`s by their names.
+    enums: HashMap<&'src str, Enum>,
+    /// The `bitfield`s by their names.
+    bitfields: HashMap<&'src str, Bitfield>,
+    /// The endianness set with `#pragma endian`.
+    endianness: &'static str,
+    /// The number of hidden fields that were generated for padding.
+    hidden_fields: usize,
+}
+
+impl<'src> Translator<'src> {
+    /// Returns the next token without consuming it.
+    fn peek(&self) -> Option<&Token<'src>> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Returns the text of the token `n` tokens ahead without consuming anything.
+    fn peek_text(&self, n: usize) -> Option<&'src str> {
+        self.tokens.get(self.pos + n).map(|token| token.text)
+    }
+
+    /// Consumes the next token.
+    fn bump(&mut self) -> Option<Token<'src>> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+
+        token
+    }
+
+    /// Consumes the next token if it has the given text.
+    fn eat(&mut self, text: &str) -> bool {
+        if self.peek_text(0) == Some(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The span of the next token, or an empty span at the end of the source.
+    fn span(&self) -> Span {
+        self.peek()
+            .or_else(|| self.tokens.last())
+            .map(|token| token.span)
+            .unwrap_or(Span { start: 0, end: 0 })
+    }
+
+    /// Reports a construct that has no equivalent in the hexbait language.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        self.unsupported.push(Spanned {
+            inner: format!("{what} cannot be translated"),
+            span,
+        });
+    }
+
+    /// Skips the rest of a statement, including a block it contains.
+    fn skip_statement(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.bump() {
+            match token.text {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" => depth = depth.saturating_sub(1),
+                "}" => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        self.eat(";");
+                        return;
+                    }
+                }
+                ";" if depth == 0 => return,
+                _ => (),
+            }
+        }
+    }
+
+    /// Reports the construct at the current token and skips its statement.
+    fn skip_unsupported(&mut self, what: &str) {
+        let span = self.span();
+        self.unsupported(what, span);
+        self.skip_statement();
+    }
+
+    /// Translates an item at the top level of the pattern.
+    fn item(&mut self) {
+        let Some(token) = self.peek().cloned() else {
+            return;
+        };
+
+        match (token.kind, token.text) {
+            (TokenKind::Directive, text) => {
+                self.bump();
+                let words: Vec<_> = text.split_whitespace().collect();
+                if let ["#pragma", "endian", endianness] = words[..] {
+                    self.endianness = match endianness {
+                        "big" => "be",
+                        _ => "le",
+                    };
+                    self.out += &format!("!endian {};\n", self.endianness);
+                }
+            }
+            (_, ";") => {
+                self.bump();
+            }
+            (TokenKind::Ident, "import") => self.skip_statement(),
+            (TokenKind::Ident, "using") => self.using(),
+            (TokenKind::Ident, "struct") => self.r#struct(),
+            (TokenKind::Ident, "bitfield") => self.bitfield(),
+            (TokenKind::Ident, "enum") => self.r#enum(),
+            (TokenKind::Ident, "namespace") => {
+                self.bump();
+                self.bump();
+                if self.eat("{") {
+                    while self.peek().is_some() && !self.eat("}") {
+                        self.item();
+                    }
+                }
+            }
+            (TokenKind::Ident, "fn") => self.skip_unsupported("functions"),
+            (TokenKind::Ident, "union") => self.skip_unsupported("`union`s"),
+            _ => {
+                let placed = self.tokens[self.pos..]
+                    .iter()
+                    .take_while(|token| token.text != ";")
+                    .any(|token| token.text == "@");
+                if placed {
+                    self.member(true);
+                } else {
+                    self.skip_unsupported("variables without a placement");
+                }
+            }
+        }
+    }
+
+    /// Translates a `using` alias.
+    fn using(&mut self) {
+        self.bump();
+        let Some(alias) = self.bump() else {
+            return;
+        };
+        if !self.eat("=") {
+            self.skip_statement();
+            return;
+        }
+
+        let span = self.span();
+        if let Some(ty) = self.bump().and_then(|ty| self.type_name(ty.text))
+            && self.eat(";")
+        {
+            self.aliases.insert(alias.text, ty);
+        } else {
+            self.unsupported("this alias", span);
+            self.skip_statement();
+        }
+    }
+
+    /// Translates a `struct` definition.
+    fn r#struct(&mut self) {
+        self.bump();
+        let Some(struct_name) = self.bump() else {
+            return;
+        };
+        if self.peek_text(0) == Some("<") {
+            self.pos -= 2;
+            self.skip_unsupported("templates");
+            return;
+        }
+        if self.eat(":") {
+            let span = self.span();
+            self.unsupported("inheritance", span);
+            while self.peek().is_some_and(|token| token.text != "{") {
+                self.bump();
+            }
+        }
+        if !self.eat("{") {
+            self.skip_statement();
+            return;
+        }
+
+        self.out += &format!("struct {} {{\n", name(struct_name.text));
+        self.block_content();
+        self.out += "}\n";
+
+        let attributes = self.attributes();
+        if attributes.color.is_some() {
+            let span = struct_name.span;
+            self.unsupported("colors of `struct` definitions", span);
+        }
+        self.eat(";");
+        self.out += "\n";
+    }
+
+    /// Records a `bitfield` definition, which is translated where it is used.
+    fn bitfield(&mut self) {
+        self.bump();
+        let Some(bitfield_name) = self.bump() else {
+            return;
+        };
+        if !self.eat("{") {
+            self.skip_statement();
+            return;
+        }
+
+        let mut fields = Vec::new();
+        while self.peek().is_some() && !self.eat("}") {
+            let start = self.pos;
+            let mut signed = false;
+            let mut field_name = None;
+            while let Some(token) = self.peek().filter(|token| token.text != ":") {
+                if matches!(token.text, ";" | "{" | "}" | "(") {
+                    break;
+                }
+                if token.text == "signed" {
+                    signed = true;
+                }
+                field_name = Some(token.text);
+                self.bump();
+            }
+
+            let bits = match (field_name, self.eat(":")) {
+                (Some(field_name), true) => self
+                    .bump()
+                    .and_then(|bits| parse_int(bits.text))
+                    .and_then(|bits| u32::try_from(bits).ok())
+                    .map(|bits| (field_name, bits)),
+                _ => None,
+            };
+            match bits {
+                Some((field_name, bits)) if self.eat(";") => {
+                    let field_name = if field_name == "padding" {
+                        String::from("_padding")
+                    } else {
+                        name(field_name)
+                    };
+                    fields.push((field_name, bits, signed));
+                }
+                _ => {
+                    self.pos = start;
+                    self.skip_unsupported("this bitfield member");
+                }
+            }
+        }
+        self.attributes();
+        self.eat(";");
+
+        self.bitfields
+            .insert(bitfield_name.text, Bitfield { fields });
+    }
+
+    /// Records an `enum` definition, which is translated where it is used.
+    fn r#enum(&mut self) {
+        self.bump();
+        let Some(enum_name) = self.bump() else {
+            return;
+        };
+        let span = self.span();
+        let ty = match (self.eat(":"), self.bump()) {
+            (true, Some(ty)) => self.type_name(ty.text),
+            _ => None,
+        };
+        let Some(ty) = ty.filter(|_| self.eat("{")) else {
+            self.unsupported("this `enum`", span);
+            self.skip_statement();
+            return;
+        };
+
+        let mut values = Vec::new();
+        let mut next = Some(Int::from(0));
+        while let Some(variant) = self.bump() {
+            match variant.text {
+                "}" => break,
+                "," => continue,
+                _ => (),
+            }
+
+            let value = if self.eat("=") {
+                let value = self.bump().and_then(|value| parse_int(value.text));
+                if self.peek_text(0) == Some("...") {
+                    self.unsupported("ranges of `enum` values", variant.span);
+                    self.bump();
+                    self.bump();
+                }
+                value
+            } else {
+                next.clone()
+            };
+
+            match value {
+                Some(value) => {
+                    next = Some(&value + 1);
+                    values.push((value, variant.text.to_string()));
+                }
+                None => {
+                    self.unsupported("computed `enum` values", variant.span);
+                    next = None;
+                }
+            }
+        }
+        self.eat(";");
+
+        self.enums.insert(enum_name.text, Enum { ty, values });
+    }
+
+    /// Translates the content of a block up to and including its closing `}`.
+    fn block_content(&mut self) {
+        while self.peek().is_some() && !self.eat("}") {
+            self.statement();
+        }
+    }
+
+    /// Translates a single statement within a `struct`.
+    fn statement(&mut self) {
+        let Some(token) = self.peek().cloned() else {
+            return;
+        };
+
+        match token.text {
+            ";" => {
+                self.bump();
+            }
+            "if" => self.if_chain(false),
+            "match" => self.skip_unsupported("`match` statements"),
+            "for" | "while" => self.skip_unsupported("loops"),
+            "try" => self.skip_unsupported("`try` blocks"),
+            "return" | "break" | "continue" => self.skip_unsupported("control flow statements"),
+            "union" => self.skip_unsupported("`union`s"),
+            _ if self.peek_text(1) == Some("(") => self.skip_unsupported("function calls"),
+            _ if self.peek_text(1) == Some("=") => self.skip_unsupported("assignments"),
+            _ => self.member(false),
+        }
+    }
+
+    /// Translates an `if` chain.
+    ///
+    /// `nested` is whether the chain follows an `else`.
+    fn if_chain(&mut self, nested: bool) {
+        self.bump();
+        let Some(condition) = self.parenthesized_expr() else {
+            self.skip_statement();
+            return;
+        };
+
+        let keyword = if nested { "if" } else { "!if" };
+        self.out += &format!("{keyword} {condition} {{\n");
+        self.branch();
+        if self.eat("else") {
+            if self.peek_text(0) == Some("if") {
+                self.out += "} else ";
+                self.if_chain(true);
+                return;
+            }
+            self.out += "} else {\n";
+            self.branch();
+        }
+        self.out += "}\n";
+    }
+
+    /// Translates a branch of an `if` chain, which is a block or a single statement.
+    fn branch(&mut self) {
+        if self.eat("{") {
+            self.block_content();
+        } else {
+            self.statement();
+        }
+    }
+
+    /// Translates a field or a local variable.
+    ///
+    /// Fields at the top level need a placement, which becomes a seek.
+    fn member(&mut self, top_level: bool) {
+        let start = self.pos;
+        let doc = self.peek().and_then(|token| token.doc.clone());
+
+        let endianness = match self.peek_text(0) {
+            Some(endianness @ ("be" | "le")) => {
+                self.bump();
+                Some(endianness)
+            }
+            _ => None,
+        };
+        self.eat("const");
+
+        let Some(ty) = self.bump() else {
+            return;
+        };
+        if ty.kind != TokenKind::Ident || self.peek_text(0) == Some("<") {
+            self.pos = start;
+            self.skip_unsupported("this statement");
+            return;
+        }
+        if ty.text == "padding" && self.eat("[") {
+            self.padding();
+            return;
+        }
+
+        let is_pointer = self.eat("*");
+        let Some(field) = self.bump().filter(|field| field.kind == TokenKind::Ident) else {
+            self.pos = start;
+            self.skip_unsupported("this statement");
+            return;
+        };
+
+        if self.eat("=") {
+            self.local_variable(field.text);
+            return;
+        }
+
+        let repetition = if self.peek_text(0) == Some("[") && self.peek_text(1) != Some("[") {
+            self.bump();
+            match self.repetition(ty.text) {
+                Some(repetition) => Some(repetition),
+                None => {
+                    self.pos = start;
+                    self.skip_statement();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let pointer = if is_pointer {
+            let ptr_type = self.eat(":").then(|| self.bump()).flatten();
+            match ptr_type.and_then(|ptr_type| self.type_name(ptr_type.text)) {
+                Some(ptr_type) => Some(ptr_type),
+                None => {
+                    self.pos = start;
+                    self.skip_unsupported("this pointer");
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let placement = if self.eat("@") {
+            match self.expr(&[";"]) {
+                Some(placement) => Some(placement),
+                None => {
+                    self.pos = start;
+                    self.skip_statement();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut attributes = self.attributes();
+        if !self.eat(";") {
+            self.pos = start;
+            self.skip_unsupported("this statement");
+            return;
+        }
+        attributes.doc = attributes.doc.or(doc);
+
+        let Some(mut parse_type) = self.parse_type(ty.text, ty.span) else {
+            return;
+        };
+        if let Some(ptr_type) = pointer {
+            parse_type = format!("ptr<{parse_type}> {ptr_type}");
+        }
+        let parse_type = match repetition {
+            Some(Repetition::Bytes(repetition)) if !is_pointer => {
+                format!("bytes {repetition}")
+            }
+            Some(Repetition::Bytes(repetition) | Repetition::Elements(repetition)) => {
+                format!("[{parse_type}] {repetition}")
+            }
+            None => parse_type,
+        };
+
+        let field_line = {
+            let mut line = String::new();
+            if let Some(doc) = &attributes.doc {
+                for doc_line in doc.lines() {
+                    line += &format!("/// {doc_line}\n");
+                }
+            }
+            if let Some(color) = &attributes.color {
+                line += &format!("@color(\"{color}\") ");
+            }
+            line += &format!("{} {parse_type};\n", name(field.text));
+            line
+        };
+
+        if let Some(endianness) = endianness {
+            self.out += &format!("!endian {endianness};\n");
+        }
+        match placement {
+            Some(placement) if top_level => {
+                self.out += &format!("!seek to {placement};\n{field_line}");
+            }
+            Some(placement) => {
+                self.out += &format!("!scope at {placement} {{\n{field_line}}}\n");
+            }
+            None => self.out += &field_line,
+        }
+        if endianness.is_some() {
+            self.out += &format!("!endian {};\n", self.endianness);
+        }
+    }
+
+    /// Translates a `padding[n]` statement after the `[`.
+    fn padding(&mut self) {
+        let span = self.span();
+        let Some(len) = self.expr(&["]"]) else {
+            self.skip_statement();
+            return;
+        };
+        if !(self.eat("]") && self.eat(";")) {
+            self.unsupported("this padding", span);
+            self.skip_statement();
+            return;
+        }
+
+        self.out += &format!("_padding{} bytes len {len};\n", self.hidden_fields);
+        self.hidden_fields += 1;
+    }
+
+    /// Translates a local variable after the `=`.
+    fn local_variable(&mut self, variable: &str) {
+        match self.expr(&[";"]) {
+            Some(value) if self.eat(";") => {
+                self.out += &format!("let {} = {value};\n", name(variable));
+            }
+            _ => self.skip_statement(),
+        }
+    }
+
+    /// Translates the size of an array after the `[` up to and including the `]`.
+    fn repetition(&mut self, element_type: &str) -> Option<Repetition> {
+        let is_byte = matches!(element_type, "u8" | "char");
+        let span = self.span();
+
+        let repetition = if self.eat("]") {
+            if !is_byte {
+                self.unsupported("arrays without a size", span);
+                return None;
+            }
+            return Some(Repetition::Bytes(String::from("until == <00>")));
+        } else if self.peek_text(0) == Some("while") {
+            self.bump();
+            let condition = self.parenthesized_expr()?;
+            format!("while {condition}")
+        } else {
+            format!("len {}", self.expr(&["]"])?)
+        };
+
+        if !self.eat("]") {
+            self.unsupported("this array size", span);
+            return None;
+        }
+
+        Some(if is_byte {
+            Repetition::Bytes(repetition)
+        } else {
+            Repetition::Elements(repetition)
+        })
+    }
+
+    /// Returns the parse type of the format description for a type of the pattern.
+    ///
+    /// `bitfield`s and `enum`s are written inline.
+    fn parse_type(&mut self, ty: &str, span: Span) -> Option<String> {
+        if let Some(bitfield) = self.bitfields.get(ty) {
+            let used_bits: u32 = bitfield.fields.iter().map(|(_, bits, _)| bits).sum();
+            let bit_width = used_bits.div_ceil(8).max(1) * 8;
+
+            let mut out = format!("bits u{bit_width} {{\n");
+            for (field, bits, signed) in &bitfield.fields {
+                let sign = if *signed { 'i' } else { 'u' };
+                out += &format!("{field} {sign}{bits};\n");
+            }
+            if used_bits < bit_width {
+                out += &format!("_padding u{};\n", bit_width - used_bits);
+            }
+            out += "}";
+
+            return Some(out);
+        }
+
+        if let Some(r#enum) = self.enums.get(ty) {
+            let mut out = format!("enum {} {{\n", r#enum.ty);
+            for (value, variant) in &r#enum.values {
+                out += &format!("{value} => \"{variant}\",\n");
+            }
+            out += "}";
+
+            return Some(out);
+        }
+
+        let translated = self.type_name(ty);
+        if translated.is_none() {
+            self.unsupported(&format!("the type `{ty}`"), span);
+        }
+
+        translated
+    }
+
+    /// Returns the name of a type of the format description for a type name of the pattern.
+    fn type_name(&self, ty: &str) -> Option<String> {
+        if let Some(alias) = self.aliases.get(ty) {
+            return Some(alias.clone());
+        }
+
+        if let Some(builtin) = builtin_type(ty) {
+            return Some(builtin);
+        }
+
+        (!ty.contains("::")
+            && !matches!(ty, "str" | "auto" | "padding")
+            && !self.bitfields.contains_key(ty)
+            && !self.enums.contains_key(ty))
+        .then(|| name(ty))
+    }
+
+    /// Parses the attributes of a field, if there are any.
+    fn attributes(&mut self) -> Attributes {
+        let mut attributes = Attributes::default();
+        while self.peek_text(0) == Some("[") && self.peek_text(1) == Some("[") {
+            self.bump();
+            self.bump();
+
+            while let Some(attribute) = self.bump() {
+                if attribute.text == "]" {
+                    self.eat("]");
+                    break;
+                }
+                if attribute.text == "," {
+                    continue;
+                }
+
+                let arg = (self.peek_text(0) == Some("(")).then(|| {
+                    self.bump();
+                    let arg = self
+                        .peek()
+                        .filter(|token| token.kind == TokenKind::Str)
+                        .cloned();
+                    let mut depth = 1;
+                    while let Some(token) = self.bump() {
+                        match token.text {
+                            "(" => depth += 1,
+                            ")" if depth == 1 => break,
+                            ")" => depth -= 1,
+                            _ => (),
+                        }
+                    }
+                    arg
+                });
+                let arg = arg
+                    .flatten()
+                    .map(|arg| arg.text.trim_matches('"').to_string());
+
+                match (attribute.text, arg) {
+                    ("color", Some(color)) if color.len() == 6 => {
+                        attributes.color = Some(format!("#{}", color.to_ascii_lowercase()));
+                    }
+                    ("comment", Some(comment)) => attributes.doc = Some(comment),
+                    _ => (),
+                }
+            }
+        }
+
+        attributes
+    }
+
+    /// Translates an expression in parentheses, consuming the parentheses.
+    fn parenthesized_expr(&mut self) -> Option<String> {
+        let span = self.span();
+        if !self.eat("(") {
+            self.unsupported("this condition", span);
+            return None;
+        }
+        let expr = self.expr(&[")"])?;
+        self.eat(")").then_some(expr)
+    }
+
+    /// Translates the expression up to one of the given tokens at the outermost nesting level.
+    fn expr(&mut self, terminators: &[&str]) -> Option<String> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            if depth == 0 && terminators.contains(&token.text) {
+                break;
+            }
+            // attributes follow the expression of a placement
+            if token.text == "[" && self.peek_text(1) == Some("[") {
+                break;
+            }
+            match token.text {
+                "(" | "[" => depth += 1,
+                ")" | "]" if depth == 0 => break,
+                ")" | "]" => depth -= 1,
+                ";" | "{" | "}" => break,
+                _ => (),
+            }
+            self.bump();
+        }
+
+        let tokens = self.tokens[start..self.pos].to_vec();
+        if tokens.is_empty() {
+            let span = self.span();
+            self.unsupported("an empty expression", span);
+            return None;
+        }
+
+        self.expr_tokens(&tokens)
+    }
+
+    /// Translates the expression made up of the given tokens.
+    fn expr_tokens(&mut self, tokens: &[Token<'src>]) -> Option<String> {
+        let mut out = String::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            let call_args = (tokens.get(i + 1).map(|token| token.text) == Some("("))
+                .then(|| split_call_args(&tokens[i + 1..]))
+                .flatten();
+
+            let text = match (token.kind, token.text) {
+                (TokenKind::Ident, func) if call_args.is_some() => {
+                    let (args, len) = call_args.expect("the arguments were just checked");
+                    i += len;
+                    self.call(func, &args, token.span)?
+                }
+                (TokenKind::Punct, "$") => String::from("$offset"),
+                (TokenKind::Ident, "parent") => String::from("$parent"),
+                (TokenKind::Ident, "this") => {
+                    self.unsupported("`this`", token.span);
+                    return None;
+                }
+                (TokenKind::Ident, ident) if ident.contains("::") => {
+                    let (enum_name, variant) =
+                        ident.rsplit_once("::").expect("the name is qualified");
+                    let value = self.enums.get(enum_name).and_then(|r#enum| {
+                        r#enum
+                            .values
+                            .iter()
+                            .find(|(_, name)| name == variant)
+                            .map(|(value, _)| value.to_string())
+                    });
+                    match value {
+                        Some(value) => value,
+                        None => {
+                            self.unsupported(&format!("`{ident}`"), token.span);
+                            return None;
+                        }
+                    }
+                }
+                (TokenKind::Ident, "true" | "false") => token.text.to_string(),
+                (TokenKind::Ident, ident) => name(ident),
+                (TokenKind::Number, number) => match parse_int(number) {
+                    Some(_) if number.starts_with("0x") || number.starts_with("0b") => {
+                        number.trim_end_matches(['u', 'U']).to_string()
+                    }
+                    Some(int) => int.to_string(),
+                    None => number.to_string(),
+                },
+                (TokenKind::Char, text) => match char_value(text) {
+                    Some(value) => value.to_string(),
+                    None => {
+                        self.unsupported("this character literal", token.span);
+                        return None;
+                    }
+                },
+                (TokenKind::Str, text) => text.to_string(),
+                (TokenKind::Punct, "?" | "~" | "^^" | "=") => {
+                    self.unsupported(&format!("the operator `{}`", token.text), token.span);
+                    return None;
+                }
+                (_, text) => text.to_string(),
+            };
+
+            let glued = matches!(text.as_str(), "." | "," | ")" | "]" | "[")
+                || out.ends_with(['.', '(', '['])
+                || (text == "(" && out.ends_with(|c: char| c.is_alphanumeric() || c == '_'));
+            if !out.is_empty() && !glued {
+                out.push(' ');
+            }
+            out += &text;
+            i += 1;
+        }
+
+        Some(out)
+    }
+
+    /// Translates a call of a function with the given arguments.
+    fn call(&mut self, func: &str, args: &[Vec<Token<'src>>], span: Span) -> Option<String> {
+        match (func, args) {
+            ("sizeof", [arg]) => Some(format!("$sizeof({})", self.expr_tokens(arg)?)),
+            ("addressof", [arg]) => Some(format!("$startof({})", self.expr_tokens(arg)?)),
+            ("std::mem::read_unsigned" | "std::mem::read_signed", [address, size]) => {
+                let bits = match &size[..] {
+                    [size] => parse_int(size.text).map(|size| size * 8),
+                    _ => None,
+                };
+                let Some(bits) = bits else {
+                    self.unsupported("reads of a computed size", span);
+                    return None;
+                };
+                let sign = if func.ends_with("unsigned") { 'u' } else { 'i' };
+                Some(format!(
+                    "peek({sign}{bits} at {})",
+                    self.expr_tokens(address)?
+                ))
+            }
+            _ => {
+                self.unsupported(&format!("calls of `{func}`"), span);
+                None
+            }
+        }
+    }
+}
+
+/// The size of an array.
+enum Repetition {
+    /// The repetition of an array of bytes, which becomes `bytes`.
+    Bytes(String),
+    /// The repetition of an array of other elements.
+    Elements(String),
+}
+
+/// Splits the arguments of a call, starting at its `(`.
+///
+/// Returns the tokens of each argument and the number of tokens up to and including the `)`.
+fn split_call_args<'src>(tokens: &[Token<'src>]) -> Option<(Vec<Vec<Token<'src>>>, usize)> {
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        match token.text {
+            "(" | "[" => depth += 1,
+            ")" if depth == 0 => {
+                if !current.is_empty() {
+                    args.push(current);
+                }
+                return Some((args, i + 1));
+            }
+            ")" | "]" => depth -= 1,
+            "," if depth == 0 => {
+                args.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => (),
+        }
+        current.push(token.clone());
+    }
+
+    None
+}
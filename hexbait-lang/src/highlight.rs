@@ -0,0 +1,183 @@
+//! Implements classifying the tokens of format descriptions for syntax highlighting.
+
+use crate::{
+    NodeKind, SyntaxKind, SyntaxToken,
+    ast::{self, AstNode as _},
+    lexer::TokenKind,
+    span::Span,
+};
+
+/// What a token in a format description is, which determines how it is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// A line or block comment.
+    Comment,
+    /// A documentation comment.
+    DocComment,
+    /// A keyword, including contextual keywords such as `len` and the `!` of declarations.
+    Keyword,
+    /// An operator such as `+` or `==`.
+    Operator,
+    /// Punctuation such as `;` or brackets.
+    Punctuation,
+    /// An integer or floating point literal.
+    Number,
+    /// A string literal.
+    String,
+    /// A part of a byte literal such as `<4d 5a>`.
+    Bytes,
+    /// `true` or `false`.
+    Boolean,
+    /// The name of a parse type, such as `u32` or a named `struct`.
+    Type,
+    /// The name of a field where it is declared or accessed.
+    Field,
+    /// A name that is used in an expression.
+    Variable,
+    /// A parameter of a named `struct`.
+    Parameter,
+    /// The name of a constant where it is declared or a fixed name such as an endianness.
+    Constant,
+    /// The name of a built-in function.
+    Function,
+    /// A metavariable such as `$offset`.
+    Metavariable,
+    /// An attribute such as `@offset`.
+    Attribute,
+    /// A token that could not be recognized.
+    Error,
+}
+
+/// A token in a format description along with what it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    /// The location of the token.
+    pub span: Span,
+    /// What the token is.
+    pub kind: HighlightKind,
+}
+
+/// Classifies all tokens of the file except for whitespace, in the order of their appearance.
+///
+/// ```
+/// # use hexbait_lang::{HighlightKind, highlight, parse};
+/// let src = "size u32 = $offset;";
+/// let parse = parse(src);
+///
+/// let highlights: Vec<_> = highlight(&parse.ast)
+///     .into_iter()
+///     .map(|highlight| (&src[highlight.span.start()..highlight.span.end()], highlight.kind))
+///     .collect();
+/// assert_eq!(
+///     highlights,
+///     [
+///         ("size", HighlightKind::Field),
+///         ("u32", HighlightKind::Type),
+///         ("=", HighlightKind::Operator),
+///         ("$", HighlightKind::Metavariable),
+///         ("offset", HighlightKind::Metavariable),
+///         (";", HighlightKind::Punctuation),
+///     ],
+/// );
+/// ```
+pub fn highlight(file: &ast::File) -> Vec<Highlight> {
+    file.syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() != SyntaxKind::from(TokenKind::Whitespace))
+        .map(|token| Highlight {
+            span: Span::from(token.text_range()),
+            kind: classify(&token),
+        })
+        .collect()
+}
+
+/// Determines what the token is.
+fn classify(token: &SyntaxToken) -> HighlightKind {
+    let kind = token.kind().expect_token();
+    let parent = token.parent().map(|parent| parent.kind().expect_node());
+
+    if parent == Some(NodeKind::ByteConcat) && kind != TokenKind::StringLiteral {
+        return HighlightKind::Bytes;
+    }
+
+    match kind {
+        TokenKind::DocComment => HighlightKind::DocComment,
+        TokenKind::LineComment | TokenKind::BlockComment => HighlightKind::Comment,
+        TokenKind::BinaryIntegerLiteral
+        | TokenKind::OctalIntegerLiteral
+        | TokenKind::HexadecimalIntegerLiteral
+        | TokenKind::DecimalIntegerLiteral
+        | TokenKind::FloatLiteral => HighlightKind::Number,
+        TokenKind::ByteLiteral => HighlightKind::Bytes,
+        TokenKind::StringLiteral => HighlightKind::String,
+        TokenKind::TrueKw | TokenKind::FalseKw => HighlightKind::Boolean,
+        TokenKind::BytesKw
+        | TokenKind::StructKw
+        | TokenKind::LetKw
+        | TokenKind::ConstKw
+        | TokenKind::PeekKw
+        | TokenKind::ConcatKw
+        | TokenKind::SwitchKw => HighlightKind::Keyword,
+        TokenKind::Identifier => classify_identifier(token, parent),
+        TokenKind::Dollar => HighlightKind::Metavariable,
+        TokenKind::At if parent == Some(NodeKind::Attribute) => HighlightKind::Attribute,
+        TokenKind::ExclamationMark if parent != Some(NodeKind::Op) => HighlightKind::Keyword,
+        TokenKind::Error => HighlightKind::Error,
+        _ if parent == Some(NodeKind::Op) => HighlightKind::Operator,
+        TokenKind::Equals | TokenKind::At => HighlightKind::Operator,
+        TokenKind::Dot
+            if matches!(
+                parent,
+                Some(NodeKind::SliceExpr | NodeKind::ConcatArgExpanding)
+            ) =>
+        {
+            HighlightKind::Operator
+        }
+        _ => HighlightKind::Punctuation,
+    }
+}
+
+/// Determines what the identifier token is from the node that contains it.
+fn classify_identifier(token: &SyntaxToken, parent: Option<NodeKind>) -> HighlightKind {
+    let is_named = |name: Option<SyntaxToken>| name.as_ref() == Some(token);
+
+    match parent {
+        Some(
+            NodeKind::StructField
+            | NodeKind::LetStatement
+            | NodeKind::BitfieldField
+            | NodeKind::FlagsArm
+            | NodeKind::FieldAccess,
+        ) => HighlightKind::Field,
+        Some(
+            NodeKind::Struct
+            | NodeKind::NamedParseType
+            | NodeKind::DynamicSizeIntParseType
+            | NodeKind::DynamicSizeUIntParseType,
+        ) => HighlightKind::Type,
+        Some(NodeKind::StructParams) => HighlightKind::Parameter,
+        Some(NodeKind::ConstDeclaration) => HighlightKind::Constant,
+        Some(NodeKind::Attribute) => HighlightKind::Attribute,
+        Some(NodeKind::Atom) => HighlightKind::Variable,
+        Some(NodeKind::Metavar) => HighlightKind::Metavariable,
+        Some(NodeKind::CallExpr | NodeKind::MethodCallExpr) => HighlightKind::Function,
+        Some(NodeKind::EndiannessDeclaration)
+            if token
+                .parent()
+                .and_then(ast::EndiannessDeclaration::cast)
+                .is_some_and(|decl| is_named(decl.kind())) =>
+        {
+            HighlightKind::Constant
+        }
+        Some(NodeKind::ScopeDecompressedDeclaration)
+            if token
+                .parent()
+                .and_then(ast::ScopeDecompressedDeclaration::cast)
+                .is_some_and(|decl| is_named(decl.compression())) =>
+        {
+            HighlightKind::Constant
+        }
+        _ => HighlightKind::Keyword,
+    }
+}
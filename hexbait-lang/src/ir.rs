@@ -1,20 +1,26 @@
 //! Implements an intermediate representation the hexbait language.
 
-use std::fmt;
+use std::{collections::HashMap, fmt, sync::Arc};
 
 use hexbait_common::Endianness;
 use smol_str::SmolStr;
 
-use crate::{SyntaxToken, span::Span};
+use crate::{Int, SyntaxToken, span::Span};
 
-pub use analysis::check_ir;
+pub use analysis::{AnalysisError, ResolvedNames, check_ir};
 pub use expr::*;
+pub use import::{ImportError, ImportPaths, ImportResolver, SourceLocation, resolve_imports};
+pub use kaitai::{KsyExport, to_ksy};
 pub use lowering::lower_file;
-pub use str::str_lit_content_to_bytes;
+pub use magic::{LeadingMagic, leading_magic};
+pub use str::{byte_pattern_to_bytes, str_lit_content_to_bytes};
 
 mod analysis;
 mod expr;
+mod import;
+mod kaitai;
 mod lowering;
+mod magic;
 pub mod path;
 mod str;
 
@@ -35,6 +41,11 @@ impl From<SyntaxToken> for Symbol {
 }
 
 impl Symbol {
+    /// Creates a symbol that is not written in the source, such as a generated field name.
+    pub(crate) fn from_static(text: &'static str) -> Symbol {
+        Symbol(SmolStr::new_static(text))
+    }
+
     /// Returns the text of this symbol as a string.
     pub fn as_str(&self) -> &str {
         &self.0
@@ -42,6 +53,7 @@ impl Symbol {
 }
 
 /// A symbol in the language along with a span.
+#[derive(Clone)]
 pub struct Spanned<T> {
     /// The text of the symbol.
     pub inner: T,
@@ -66,14 +78,37 @@ impl<T: From<SyntaxToken>> From<SyntaxToken> for Spanned<T> {
 }
 
 /// A single file in the hexbait language.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct File {
     /// The content that makes up the file.
     pub content: Vec<StructContent>,
+    /// The named `struct`s that can be used in the file by their name.
+    ///
+    /// After the imports are resolved, this includes the imported `struct`s.
+    pub structs: Arc<HashMap<Symbol, NamedStruct>>,
+    /// The paths of the files imported by this file.
+    pub imports: Vec<Spanned<Arc<str>>>,
+    /// The errors that occurred while lowering the file.
+    ///
+    /// They are reported by [`check_ir`].
+    pub errors: Vec<Spanned<String>>,
+}
+
+/// A `struct` declared with a name, which can be used as a parse type.
+#[derive(Debug, Clone)]
+pub struct NamedStruct {
+    /// The name of the `struct`.
+    pub name: Spanned<Symbol>,
+    /// The parameters of the `struct`, which are given as arguments where it is parsed.
+    pub params: Vec<Spanned<Symbol>>,
+    /// The content of the `struct`.
+    pub content: Vec<StructContent>,
+    /// The color of the `struct` wherever it is parsed, if it has one.
+    pub color: Option<Color>,
 }
 
 /// The possible content of a `struct` in the hexbait language.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StructContent {
     /// A field of the `struct`.
     Field(StructField),
@@ -86,7 +121,7 @@ pub enum StructContent {
 }
 
 /// A field of a `struct`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StructField {
     /// The name of the `struct` field.
     pub name: Spanned<Symbol>,
@@ -94,10 +129,134 @@ pub struct StructField {
     pub ty: ParseType,
     /// The expected value for this field, if one exists.
     pub expected: Option<Expr>,
+    /// How the value of this field is meant to be interpreted, if it is annotated.
+    pub classification: Option<Classification>,
+    /// The color of the value of this field, if it has one.
+    pub color: Option<Color>,
+    /// The documentation of this field, if it has doc comments.
+    pub doc: Option<Arc<str>>,
+}
+
+/// Describes how a parsed value is meant to be interpreted.
+///
+/// The classification does not change the value itself, only how it is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Classification {
+    /// An integer that is an offset in the input.
+    Offset,
+    /// An integer that is a length in bytes.
+    Length,
+    /// An integer that is a point in time.
+    Timestamp(TimestampEncoding),
+    /// A GUID stored in 16 bytes.
+    Guid,
+}
+
+/// A color that a format description assigns to parsed values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// A color given by its red, green and blue components.
+    Rgb([u8; 3]),
+    /// A named category of values, which always has the same color.
+    Category(Symbol),
+}
+
+impl Color {
+    /// The colors that categories are assigned from.
+    const PALETTE: [[u8; 3]; 8] = [
+        [0x4e, 0x79, 0xa7],
+        [0xf2, 0x8e, 0x2b],
+        [0xe1, 0x57, 0x59],
+        [0x76, 0xb7, 0xb2],
+        [0x59, 0xa1, 0x4f],
+        [0xed, 0xc9, 0x48],
+        [0xb0, 0x7a, 0xa1],
+        [0x9c, 0x75, 0x5f],
+    ];
+
+    /// Returns the red, green and blue components of the color.
+    ///
+    /// Categories are assigned a color from a fixed palette based on their name, so the same
+    /// category has the same color everywhere.
+    ///
+    /// ```
+    /// # use hexbait_lang::ir::Color;
+    /// assert_eq!(Color::Rgb([1, 2, 3]).rgb(), [1, 2, 3]);
+    /// ```
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            Color::Rgb(rgb) => *rgb,
+            Color::Category(name) => {
+                // FNV-1a is used, because its result does not differ between builds
+                let hash = name
+                    .as_str()
+                    .bytes()
+                    .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+                    });
+
+                Color::PALETTE[(hash % Color::PALETTE.len() as u64) as usize]
+            }
+        }
+    }
+
+    /// Parses a color of the form `#rrggbb`.
+    pub fn from_hex(s: &str) -> Option<Color> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))?;
+        let component = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+        Some(Color::Rgb([component(0)?, component(2)?, component(4)?]))
+    }
+}
+
+/// A way in which points in time are encoded as integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimestampEncoding {
+    /// 100 nanosecond intervals since 1601-01-01 (Windows FILETIME).
+    Filetime,
+    /// Seconds since 1970-01-01.
+    Unix,
+    /// Milliseconds since 1970-01-01.
+    UnixMillis,
+    /// An MS-DOS date in the upper 16 bits and an MS-DOS time in the lower 16 bits.
+    Dos,
+    /// Seconds since 1904-01-01, as used by Mac HFS.
+    Hfs,
+}
+
+impl TimestampEncoding {
+    /// All supported timestamp encodings.
+    pub const ALL: [TimestampEncoding; 5] = [
+        TimestampEncoding::Filetime,
+        TimestampEncoding::Unix,
+        TimestampEncoding::UnixMillis,
+        TimestampEncoding::Dos,
+        TimestampEncoding::Hfs,
+    ];
+
+    /// Returns the timestamp encoding with the given name.
+    pub fn from_name(name: &str) -> Option<TimestampEncoding> {
+        TimestampEncoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name() == name)
+    }
+
+    /// The name of the timestamp encoding in the language.
+    pub fn name(self) -> &'static str {
+        match self {
+            TimestampEncoding::Filetime => "filetime",
+            TimestampEncoding::Unix => "unix",
+            TimestampEncoding::UnixMillis => "unix_ms",
+            TimestampEncoding::Dos => "dos",
+            TimestampEncoding::Hfs => "hfs",
+        }
+    }
 }
 
 /// A `let` statement.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LetStatement {
     /// The name of the computed value.
     pub name: Spanned<Symbol>,
@@ -106,7 +265,7 @@ pub struct LetStatement {
 }
 
 /// A `scope` kind.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ScopeKind {
     /// Defines a scope by a start and an optional end offset in the current scope.
     At {
@@ -120,10 +279,76 @@ pub enum ScopeKind {
         /// The bytes used for parsing.
         bytes: Expr,
     },
+    /// Defines a scope by compressed bytes, which decompress to the content of the scope.
+    Decompressed {
+        /// The format that the bytes are compressed in.
+        compression: Compression,
+        /// The compressed bytes.
+        bytes: Expr,
+    },
+}
+
+/// A way to recover from errors.
+#[derive(Debug, Clone)]
+pub enum RecoveryKind {
+    /// Recovers at an offset relative to the start of the current `struct`.
+    At {
+        /// The offset at which to recover.
+        offset: Expr,
+    },
+    /// Recovers at the next occurrence of a pattern.
+    Scan {
+        /// The bytes or byte pattern to search for.
+        pattern: Expr,
+    },
+}
+
+/// A format that bytes can be compressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Raw DEFLATE data without a header.
+    Deflate,
+    /// DEFLATE data with a zlib header and checksum.
+    Zlib,
+    /// DEFLATE data in the gzip format.
+    Gzip,
+    /// Data in the LZ4 frame format.
+    Lz4,
+    /// Data in the Zstandard format.
+    Zstd,
+}
+
+impl Compression {
+    /// All supported compression formats.
+    pub const ALL: [Compression; 5] = [
+        Compression::Deflate,
+        Compression::Zlib,
+        Compression::Gzip,
+        Compression::Lz4,
+        Compression::Zstd,
+    ];
+
+    /// Returns the compression format with the given name.
+    pub fn from_name(name: &str) -> Option<Compression> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| compression.name() == name)
+    }
+
+    /// The name of the compression format in the language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Deflate => "deflate",
+            Compression::Zlib => "zlib",
+            Compression::Gzip => "gzip",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        }
+    }
 }
 
 /// A declaration found in a `struct`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Declaration {
     /// Declares the endianness.
     Endianness(Endianness),
@@ -155,15 +380,24 @@ pub enum Declaration {
         /// The message to display if the condition is true.
         message: Option<Expr>,
     },
-    /// Specifies an offset to recover at in case of errors.
+    /// Warns if a computed checksum does not match the expected value.
+    Verify {
+        /// The computed checksum.
+        computed: Expr,
+        /// The checksum that is expected.
+        expected: Expr,
+        /// The message to display if the checksums differ.
+        message: Option<Expr>,
+    },
+    /// Specifies where to recover in case of errors.
     Recover {
-        /// The offset at which to recover.
-        at: Expr,
+        /// The kind of recovery.
+        kind: RecoveryKind,
     },
 }
 
 /// A chain of `if` statements.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IfChain {
     /// The condition that decides which branch to take.
     pub condition: Expr,
@@ -174,7 +408,7 @@ pub struct IfChain {
 }
 
 /// The `else` part of an if chain.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ElsePart {
     /// An else block that is the end of the chain.
     ElseBlock(Vec<StructContent>),
@@ -183,7 +417,7 @@ pub enum ElsePart {
 }
 
 /// A description of a parsing type.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParseType {
     /// The kind of parsing type.
     pub kind: ParseTypeKind,
@@ -192,12 +426,14 @@ pub struct ParseType {
 }
 
 /// The different types that can be parsed.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ParseTypeKind {
     /// Parses a type of the given name.
     Named {
         /// The name of the type to parse.
         name: Spanned<Symbol>,
+        /// The arguments for the parameters of the named `struct`.
+        args: Vec<Expr>,
     },
     /// Parses an integer with a given bit width from the input.
     Integer {
@@ -206,6 +442,16 @@ pub enum ParseTypeKind {
         /// Whether the integer is signed.
         signed: bool,
     },
+    /// Parses a LEB128 encoded integer of variable length.
+    Leb128 {
+        /// Whether the integer is signed.
+        signed: bool,
+    },
+    /// Parses an IEEE 754 floating point number with a given bit width from the input.
+    Float {
+        /// The bit width to use, either 32 or 64.
+        bit_width: u32,
+    },
     /// Parses an integer of dynamic size.
     DynamicInteger {
         /// The bit width to use.
@@ -239,12 +485,60 @@ pub enum ParseTypeKind {
         /// The default branch if no other branch matches.
         default: Box<ParseType>,
     },
+    /// Parses an unsigned integer and splits its bits into fields.
+    Bitfield {
+        /// The bit width of the integer containing the fields.
+        bit_width: u32,
+        /// The fields in the order of their bits.
+        fields: Vec<BitfieldField>,
+    },
+    /// Parses an integer and gives some of its values symbolic names.
+    Enum {
+        /// The integer type to parse.
+        parse_type: Box<ParseType>,
+        /// The named values.
+        names: Vec<(Int, Arc<str>)>,
+    },
+    /// Parses an integer and shows which of the named bit masks are set in it.
+    Flags {
+        /// The integer type to parse.
+        parse_type: Box<ParseType>,
+        /// The named bit masks.
+        flags: Vec<(Int, Symbol)>,
+    },
+    /// Parses an integer that points to a value of another parse type.
+    Pointer {
+        /// The integer type to parse.
+        parse_type: Box<ParseType>,
+        /// The type of the value that is pointed to.
+        target: Box<ParseType>,
+        /// The offset that the integer is relative to.
+        base: Option<Expr>,
+    },
+    /// Parses another parse type and classifies the result, such as built-in timestamps.
+    Classified {
+        /// The parse type to parse.
+        parse_type: Box<ParseType>,
+        /// The classification of the parsed value.
+        classification: Classification,
+    },
     /// A parse type that contained an error during parsing.
     Error,
 }
 
+/// A field of a bitfield.
+#[derive(Debug, Clone)]
+pub struct BitfieldField {
+    /// The name of the field.
+    pub name: Spanned<Symbol>,
+    /// The number of bits of the field.
+    pub bit_width: u32,
+    /// Whether the field is a signed integer.
+    pub signed: bool,
+}
+
 /// The type of repetition of a repeating parse type.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RepeatKind {
     /// Repeats a fixed number of times.
     Len {
@@ -256,6 +550,11 @@ pub enum RepeatKind {
         /// The condition that determines whether another instance is parsed.
         condition: Expr,
     },
+    /// Repeats until the upcoming bytes are equal to the terminator.
+    Until {
+        /// The bytes that end the repetition.
+        terminator: Expr,
+    },
     /// A repeat kind that contained an error during parsing.
     Error,
 }
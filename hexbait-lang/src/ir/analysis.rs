@@ -1,6 +1,13 @@
 //! Performs static analysis on the IR to ensure that the input is well formed.
 
-use super::File;
+use std::{collections::HashMap, fmt};
+
+use super::{
+    ConcatArg, Declaration, ElsePart, Expr, ExprKind, File, IfChain, Lit, NamedStruct, ParseType,
+    ParseTypeKind, RecoveryKind, RepeatKind, ScopeKind, Spanned, StructContent, Symbol,
+};
+
+mod types;
 
 /// The names resolved for each spanned symbol.
 // TODO: implement this with fields
@@ -8,25 +15,282 @@ pub struct ResolvedNames {}
 
 /// The error returned upon a failed analysis.
 #[derive(Debug)]
-pub struct AnalysisError {}
+pub struct AnalysisError {
+    /// The problems that were found, with the spans where they were found.
+    pub problems: Vec<Spanned<String>>,
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", problem.inner)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for AnalysisError {}
 
 /// Checks if the file is well formed.
-// TODO: add an error type here
-pub fn check_ir(_file: &File) -> Result<ResolvedNames, AnalysisError> {
-    // TODO: check types
-    // TODO: resolve names
+///
+/// The imports of the file must be resolved before it is checked.
+pub fn check_ir(file: &File) -> Result<ResolvedNames, AnalysisError> {
     // TODO: ensure that endianness is properly specified before parsing fields
-    // TODO: ensure no errors are contained
     // TODO: ensure alignment is a power of two
     // TODO: ensure that alignment fits into u64
     // TODO: ensure that integers are non-zero length
     // TODO: ensure that non-byte-aligned integers are only allowed in bitfields
-    // TODO: ensure that all field accesses are valid (both field access and in current struct)
-    // TODO: ensure comparison operations are well types (== and != for all, but others only for ints)
     // TODO: ensure assertion and warning messages are utf8
     // TODO: ensure that $last is only used if $len > 0
     // TODO: ensure that $parent, $last and $len are only used in correct contexts
     // TODO: ensure sensible behavior about struct nested in scopes and if declarations
-    // TODO: ensure u(_) and i(_) parse types contain int expressions
-    Ok(ResolvedNames {})
+    let mut resolver = TypeNameResolver {
+        structs: &file.structs,
+        problems: file.errors.clone(),
+    };
+
+    resolver.content(&file.content);
+    for named_struct in file.structs.values() {
+        resolver.content(&named_struct.content);
+    }
+    resolver.problems.extend(types::check_types(file));
+
+    if resolver.problems.is_empty() {
+        Ok(ResolvedNames {})
+    } else {
+        Err(AnalysisError {
+            problems: resolver.problems,
+        })
+    }
+}
+
+/// Finds the named parse types that do not refer to a named `struct` or have the wrong number of
+/// arguments.
+struct TypeNameResolver<'file> {
+    /// The named `struct`s that are available.
+    structs: &'file HashMap<Symbol, NamedStruct>,
+    /// The problems that were found.
+    problems: Vec<Spanned<String>>,
+}
+
+impl TypeNameResolver<'_> {
+    /// Resolves the names in the given `struct` content.
+    fn content(&mut self, content: &[StructContent]) {
+        for item in content {
+            match item {
+                StructContent::Field(field) => {
+                    self.parse_type(&field.ty);
+                    // byte patterns are allowed on their own as expected values
+                    if let Some(expected) = &field.expected
+                        && !matches!(expected.kind, ExprKind::Lit(Lit::BytePattern(_)))
+                    {
+                        self.expr(expected);
+                    }
+                }
+                StructContent::Declaration(declaration) => self.declaration(declaration),
+                StructContent::LetStatement(let_statement) => self.expr(&let_statement.expr),
+                StructContent::Error => (),
+            }
+        }
+    }
+
+    /// Resolves the names in the given declaration.
+    fn declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Endianness(_) => (),
+            Declaration::Align(expr)
+            | Declaration::SeekBy(expr)
+            | Declaration::SeekTo(expr)
+            | Declaration::Recover {
+                kind: RecoveryKind::At { offset: expr },
+            } => self.expr(expr),
+            Declaration::Recover {
+                kind: RecoveryKind::Scan { pattern },
+            } => {
+                if !matches!(pattern.kind, ExprKind::Lit(Lit::BytePattern(_))) {
+                    self.expr(pattern);
+                }
+            }
+            Declaration::Scope { kind, content } => {
+                match kind {
+                    ScopeKind::At { start, end } => {
+                        self.expr(start);
+                        if let Some(end) = end {
+                            self.expr(end);
+                        }
+                    }
+                    ScopeKind::In { bytes } | ScopeKind::Decompressed { bytes, .. } => {
+                        self.expr(bytes)
+                    }
+                }
+                self.content(content);
+            }
+            Declaration::If(if_chain) => self.if_chain(if_chain),
+            Declaration::Assert { condition, message }
+            | Declaration::WarnIf { condition, message } => {
+                self.expr(condition);
+                if let Some(message) = message {
+                    self.expr(message);
+                }
+            }
+            Declaration::Verify {
+                computed,
+                expected,
+                message,
+            } => {
+                self.expr(computed);
+                self.expr(expected);
+                if let Some(message) = message {
+                    self.expr(message);
+                }
+            }
+        }
+    }
+
+    /// Resolves the names in the given `if` chain.
+    fn if_chain(&mut self, if_chain: &IfChain) {
+        self.expr(&if_chain.condition);
+        self.content(&if_chain.then_block);
+        match &if_chain.else_part {
+            Some(ElsePart::ElseBlock(content)) => self.content(content),
+            Some(ElsePart::IfChain(if_chain)) => self.if_chain(if_chain),
+            None => (),
+        }
+    }
+
+    /// Resolves the names in the given parse type.
+    fn parse_type(&mut self, parse_type: &ParseType) {
+        match &parse_type.kind {
+            ParseTypeKind::Named { name, args } => {
+                match self.structs.get(&name.inner) {
+                    Some(named_struct) if named_struct.params.len() != args.len() => {
+                        self.problems.push(Spanned {
+                            inner: format!(
+                                "`{}` takes {} arguments, but {} were given",
+                                name.inner.as_str(),
+                                named_struct.params.len(),
+                                args.len()
+                            ),
+                            span: parse_type.span,
+                        });
+                    }
+                    Some(_) => (),
+                    None => self.problems.push(Spanned {
+                        inner: format!("unknown parse type `{}`", name.inner.as_str()),
+                        span: name.span,
+                    }),
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            ParseTypeKind::Integer { .. }
+            | ParseTypeKind::Leb128 { .. }
+            | ParseTypeKind::Float { .. }
+            | ParseTypeKind::Bitfield { .. }
+            | ParseTypeKind::Error => (),
+            ParseTypeKind::DynamicInteger { bit_width, .. } => self.expr(bit_width),
+            ParseTypeKind::Bytes { repetition_kind } => self.repetition(repetition_kind),
+            ParseTypeKind::Repeating {
+                parse_type,
+                repetition_kind,
+            } => {
+                self.parse_type(parse_type);
+                self.repetition(repetition_kind);
+            }
+            ParseTypeKind::Struct { content } => self.content(content),
+            ParseTypeKind::Switch {
+                scrutinee,
+                branches,
+                default,
+            } => {
+                self.expr(scrutinee);
+                for (_, parse_type) in branches {
+                    self.parse_type(parse_type);
+                }
+                self.parse_type(default);
+            }
+            ParseTypeKind::Enum { parse_type, .. }
+            | ParseTypeKind::Flags { parse_type, .. }
+            | ParseTypeKind::Classified { parse_type, .. } => self.parse_type(parse_type),
+            ParseTypeKind::Pointer {
+                parse_type,
+                target,
+                base,
+            } => {
+                self.parse_type(parse_type);
+                self.parse_type(target);
+                if let Some(base) = base {
+                    self.expr(base);
+                }
+            }
+        }
+    }
+
+    /// Resolves the names in the given repetition.
+    fn repetition(&mut self, repetition_kind: &RepeatKind) {
+        match repetition_kind {
+            RepeatKind::Len { count: expr }
+            | RepeatKind::While { condition: expr }
+            | RepeatKind::Until { terminator: expr } => self.expr(expr),
+            RepeatKind::Error => (),
+        }
+    }
+
+    /// Resolves the names in the given expression.
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(Lit::BytePattern(_)) => self.problems.push(Spanned {
+                inner: String::from(
+                    "byte patterns can only be used in `switch` arms, as expected field values and in `!recover scan`",
+                ),
+                span: expr.span,
+            }),
+            ExprKind::Lit(_)
+            | ExprKind::VarUse(_)
+            | ExprKind::Offset
+            | ExprKind::End
+            | ExprKind::Parent
+            | ExprKind::Last
+            | ExprKind::Len
+            | ExprKind::Error => (),
+            ExprKind::FieldAccess { expr, .. }
+            | ExprKind::UnOp { operand: expr, .. }
+            | ExprKind::StartOf(expr)
+            | ExprKind::SizeOf(expr) => self.expr(expr),
+            ExprKind::BinOp { lhs, rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Peek { ty, offset } => {
+                self.parse_type(ty);
+                if let Some(offset) = offset {
+                    self.expr(offset);
+                }
+            }
+            ExprKind::Concat { args } => {
+                for ConcatArg::Direct(arg) | ConcatArg::Expanding(arg) in args {
+                    self.expr(arg);
+                }
+            }
+            ExprKind::Call { args, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            ExprKind::Index { expr, index } => {
+                self.expr(expr);
+                self.expr(index);
+            }
+            ExprKind::Slice { expr, start, end } => {
+                self.expr(expr);
+                self.expr(start);
+                self.expr(end);
+            }
+        }
+    }
 }
@@ -0,0 +1,746 @@
+//! Implements checking that the expressions in the IR are well typed.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::Span;
+
+use super::super::{
+    BinOp, Builtin, ConcatArg, Declaration, ElsePart, Expr, ExprKind, File, IfChain, Lit,
+    NamedStruct, ParseType, ParseTypeKind, RecoveryKind, RepeatKind, ScopeKind, Spanned,
+    StructContent, Symbol, UnOp,
+};
+
+/// The type of a value, as far as it is known before parsing.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    /// An integer.
+    Int,
+    /// A floating point number.
+    Float,
+    /// A boolean.
+    Bool,
+    /// Bytes.
+    Bytes,
+    /// A `struct` with the given fields.
+    Struct(Arc<[(Symbol, Type)]>),
+    /// An array of elements of the given type.
+    Array(Box<Type>),
+    /// A value whose type cannot be known before parsing.
+    Unknown,
+}
+
+impl Type {
+    /// Describes the type for use in error messages.
+    fn description(&self) -> &'static str {
+        match self {
+            Type::Int => "an integer",
+            Type::Float => "a float",
+            Type::Bool => "a boolean",
+            Type::Bytes => "bytes",
+            Type::Struct(_) => "a struct",
+            Type::Array(_) => "an array",
+            Type::Unknown => "a value",
+        }
+    }
+
+    /// Returns `true` if the type may be a number.
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float | Type::Unknown)
+    }
+
+    /// Returns `true` if the type may be the given type.
+    fn may_be(&self, ty: &Type) -> bool {
+        *self == Type::Unknown || self == ty
+    }
+
+    /// Returns `true` if values of the two types can be compared for equality.
+    fn is_comparable_with(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Int | Type::Float, Type::Int | Type::Float) => true,
+            (Type::Struct(_), Type::Struct(_)) | (Type::Array(_), Type::Array(_)) => true,
+            _ => self == other,
+        }
+    }
+
+    /// Returns the type of a value that is of either of the two types.
+    fn join(self, other: Type) -> Type {
+        if self == other { self } else { Type::Unknown }
+    }
+
+    /// Returns the type of the result of an arithmetic operation on the two types.
+    fn arithmetic(&self, other: &Type) -> Type {
+        match (self, other) {
+            (Type::Int, Type::Int) => Type::Int,
+            (Type::Float, Type::Int | Type::Float) | (Type::Int, Type::Float) => Type::Float,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+/// Checks that the expressions in the file are well typed.
+///
+/// Returns the problems that were found.
+pub(super) fn check_types(file: &File) -> Vec<Spanned<String>> {
+    let mut checker = TypeChecker {
+        structs: &file.structs,
+        named_types: HashMap::new(),
+        frames: Vec::new(),
+        last: None,
+        problems: Vec::new(),
+    };
+
+    checker.struct_type(&file.content, Vec::new());
+    for name in file.structs.keys() {
+        checker.named_struct(name);
+    }
+
+    checker.problems
+}
+
+/// Infers the types of values and reports the places where they are used wrongly.
+struct TypeChecker<'file> {
+    /// The named `struct`s that are available.
+    structs: &'file HashMap<Symbol, NamedStruct>,
+    /// The types of the named `struct`s that were already checked.
+    ///
+    /// `None` means that the `struct` is currently being checked.
+    named_types: HashMap<Symbol, Option<Type>>,
+    /// The fields known so far in each `struct` that is currently being checked.
+    ///
+    /// The last entry is the innermost `struct`.
+    frames: Vec<Vec<(Symbol, Type)>>,
+    /// The type of `$last` in the current repetition condition.
+    last: Option<Type>,
+    /// The problems that were found.
+    problems: Vec<Spanned<String>>,
+}
+
+impl TypeChecker<'_> {
+    /// Reports a problem at the given span.
+    fn problem(&mut self, inner: String, span: Span) {
+        self.problems.push(Spanned { inner, span });
+    }
+
+    /// Reports that a value of the given type was found where another was expected.
+    fn mismatch(&mut self, expected: &str, purpose: &str, found: &Type, span: Span) {
+        self.problem(
+            format!(
+                "expected {expected} as {purpose}, but found {}",
+                found.description()
+            ),
+            span,
+        );
+    }
+
+    /// Checks that the expression is of the expected type and returns its type.
+    fn expect(&mut self, expr: &Expr, expected: Type, purpose: &str) -> Type {
+        let ty = self.expr(expr);
+        if !ty.may_be(&expected) {
+            self.mismatch(expected.description(), purpose, &ty, expr.span);
+        }
+
+        ty
+    }
+
+    /// Checks that the expression is a number and returns its type.
+    fn expect_number(&mut self, expr: &Expr, purpose: &str) -> Type {
+        let ty = self.expr(expr);
+        if !ty.is_numeric() {
+            self.mismatch("a number", purpose, &ty, expr.span);
+        }
+
+        ty
+    }
+
+    /// Checks that values of the two types can be compared for equality.
+    fn expect_comparable(&mut self, lhs: &Type, rhs: &Type, span: Span) {
+        if !lhs.is_comparable_with(rhs) {
+            self.problem(
+                format!(
+                    "cannot compare {} with {}",
+                    lhs.description(),
+                    rhs.description()
+                ),
+                span,
+            );
+        }
+    }
+
+    /// Returns the fields of the innermost `struct` that is currently being checked.
+    fn frame(&mut self) -> &mut Vec<(Symbol, Type)> {
+        self.frames
+            .last_mut()
+            .expect("struct content is only checked within a struct")
+    }
+
+    /// Returns the type of the named `struct`, checking it if that did not happen yet.
+    fn named_struct(&mut self, name: &Symbol) -> Type {
+        match self.named_types.get(name) {
+            Some(Some(ty)) => return ty.clone(),
+            // the type of recursive `struct`s is not inferred
+            Some(None) => return Type::Unknown,
+            None => (),
+        }
+        let structs = self.structs;
+        let Some(named_struct) = structs.get(name) else {
+            return Type::Unknown;
+        };
+
+        self.named_types.insert(name.clone(), None);
+
+        // a named `struct` does not know the `struct` it is parsed in
+        let frames = std::mem::take(&mut self.frames);
+        let params = named_struct
+            .params
+            .iter()
+            .map(|param| (param.inner.clone(), Type::Unknown))
+            .collect();
+        let ty = self.struct_type(&named_struct.content, params);
+        self.frames = frames;
+
+        self.named_types.insert(name.clone(), Some(ty.clone()));
+
+        ty
+    }
+
+    /// Checks the content of a `struct` with the given arguments and returns its type.
+    fn struct_type(&mut self, content: &[StructContent], args: Vec<(Symbol, Type)>) -> Type {
+        let num_args = args.len();
+        let last = self.last.take();
+        self.frames.push(args);
+        self.content(content);
+        let fields = self.frames.pop().expect("the frame was pushed above");
+        self.last = last;
+
+        // the result of a `struct` contains neither its arguments nor hidden fields
+        Type::Struct(
+            fields
+                .into_iter()
+                .skip(num_args)
+                .filter(|(name, _)| !name.as_str().starts_with('_'))
+                .collect(),
+        )
+    }
+
+    /// Checks the given `struct` content.
+    fn content(&mut self, content: &[StructContent]) {
+        for item in content {
+            match item {
+                StructContent::Field(field) => {
+                    let ty = self.parse_type(&field.ty);
+                    if let Some(expected) = &field.expected {
+                        let expected_ty = if let ExprKind::Lit(Lit::BytePattern(_)) = &expected.kind
+                        {
+                            Type::Bytes
+                        } else {
+                            self.expr(expected)
+                        };
+                        self.expect_comparable(&ty, &expected_ty, expected.span);
+                    }
+                    self.frame().push((field.name.inner.clone(), ty));
+                }
+                StructContent::Declaration(declaration) => self.declaration(declaration),
+                StructContent::LetStatement(let_statement) => {
+                    let ty = self.expr(&let_statement.expr);
+                    self.frame().push((let_statement.name.inner.clone(), ty));
+                }
+                StructContent::Error => (),
+            }
+        }
+    }
+
+    /// Checks the given declaration.
+    fn declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Endianness(_) => (),
+            Declaration::Align(expr) => {
+                self.expect(expr, Type::Int, "the alignment");
+            }
+            Declaration::SeekBy(expr) => {
+                self.expect(expr, Type::Int, "the seek distance");
+            }
+            Declaration::SeekTo(expr) => {
+                self.expect(expr, Type::Int, "the seek offset");
+            }
+            Declaration::Recover {
+                kind: RecoveryKind::At { offset },
+            } => {
+                self.expect(offset, Type::Int, "the recovery offset");
+            }
+            Declaration::Recover {
+                kind: RecoveryKind::Scan { pattern },
+            } => {
+                if !matches!(pattern.kind, ExprKind::Lit(Lit::BytePattern(_))) {
+                    self.expect(pattern, Type::Bytes, "the recovery pattern");
+                }
+            }
+            Declaration::Scope { kind, content } => {
+                match kind {
+                    ScopeKind::At { start, end } => {
+                        self.expect(start, Type::Int, "the scope start");
+                        if let Some(end) = end {
+                            self.expect(end, Type::Int, "the scope end");
+                        }
+                    }
+                    ScopeKind::In { bytes } | ScopeKind::Decompressed { bytes, .. } => {
+                        self.expect(bytes, Type::Bytes, "the scope content");
+                    }
+                }
+                // fields in a scope are part of the surrounding `struct`
+                self.content(content);
+            }
+            Declaration::If(if_chain) => {
+                let fields = self.if_chain(if_chain);
+                self.frame().extend(fields);
+            }
+            Declaration::Assert { condition, message } => {
+                self.expect(condition, Type::Bool, "the assertion");
+                self.message(message.as_ref());
+            }
+            Declaration::WarnIf { condition, message } => {
+                self.expect(condition, Type::Bool, "the warning condition");
+                self.message(message.as_ref());
+            }
+            Declaration::Verify {
+                computed,
+                expected,
+                message,
+            } => {
+                let computed_ty = self.expr(computed);
+                let expected_ty = self.expr(expected);
+                self.expect_comparable(&computed_ty, &expected_ty, expected.span);
+                self.message(message.as_ref());
+            }
+        }
+    }
+
+    /// Checks the message of an assertion or warning.
+    fn message(&mut self, message: Option<&Expr>) {
+        if let Some(message) = message {
+            self.expect(message, Type::Bytes, "the message");
+        }
+    }
+
+    /// Checks the given `if` chain and returns the fields that it may define.
+    fn if_chain(&mut self, if_chain: &IfChain) -> Vec<(Symbol, Type)> {
+        self.expect(&if_chain.condition, Type::Bool, "the `if` condition");
+
+        let mut fields = self.branch(&if_chain.then_block);
+        let else_fields = match &if_chain.else_part {
+            Some(ElsePart::ElseBlock(content)) => self.branch(content),
+            Some(ElsePart::IfChain(if_chain)) => self.if_chain(if_chain),
+            None => Vec::new(),
+        };
+
+        // only one branch is taken, so a field defined in several branches may have either type
+        for (name, ty) in else_fields {
+            if let Some((_, existing)) = fields.iter_mut().find(|(field, _)| *field == name) {
+                *existing = std::mem::replace(existing, Type::Unknown).join(ty);
+            } else {
+                fields.push((name, ty));
+            }
+        }
+
+        fields
+    }
+
+    /// Checks a branch of an `if` chain and returns the fields that it defines.
+    fn branch(&mut self, content: &[StructContent]) -> Vec<(Symbol, Type)> {
+        let len = self.frame().len();
+        self.content(content);
+
+        self.frame().split_off(len)
+    }
+
+    /// Checks the given parse type and returns the type of the values it parses.
+    fn parse_type(&mut self, parse_type: &ParseType) -> Type {
+        match &parse_type.kind {
+            ParseTypeKind::Named { name, args } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+
+                self.named_struct(&name.inner)
+            }
+            ParseTypeKind::Integer { .. } | ParseTypeKind::Leb128 { .. } => Type::Int,
+            ParseTypeKind::Float { .. } => Type::Float,
+            ParseTypeKind::DynamicInteger { bit_width, .. } => {
+                self.expect(bit_width, Type::Int, "the bit width");
+
+                Type::Int
+            }
+            ParseTypeKind::Bytes { repetition_kind } => {
+                // `$last` is the last parsed byte
+                self.repetition(repetition_kind, Type::Int);
+
+                Type::Bytes
+            }
+            ParseTypeKind::Repeating {
+                parse_type,
+                repetition_kind,
+            } => {
+                let element = self.parse_type(parse_type);
+                self.repetition(repetition_kind, element.clone());
+
+                Type::Array(Box::new(element))
+            }
+            ParseTypeKind::Struct { content } => self.struct_type(content, Vec::new()),
+            ParseTypeKind::Switch {
+                scrutinee,
+                branches,
+                default,
+            } => {
+                let scrutinee_ty = self.expr(scrutinee);
+
+                let mut ty = self.parse_type(default);
+                for (lit, parse_type) in branches {
+                    let lit_ty = match lit {
+                        Lit::Int(_) => Type::Int,
+                        Lit::Float(_) => Type::Float,
+                        Lit::Bytes(_) | Lit::BytePattern(_) => Type::Bytes,
+                        Lit::Bool(_) => Type::Bool,
+                    };
+                    if !scrutinee_ty.is_comparable_with(&lit_ty) {
+                        self.problem(
+                            format!(
+                                "cannot match {} against {}",
+                                scrutinee_ty.description(),
+                                lit_ty.description()
+                            ),
+                            parse_type.span,
+                        );
+                    }
+
+                    let branch_ty = self.parse_type(parse_type);
+                    ty = ty.join(branch_ty);
+                }
+
+                ty
+            }
+            ParseTypeKind::Bitfield { fields, .. } => Type::Struct(
+                fields
+                    .iter()
+                    .map(|field| (field.name.inner.clone(), Type::Int))
+                    .collect(),
+            ),
+            ParseTypeKind::Enum { parse_type, .. }
+            | ParseTypeKind::Classified { parse_type, .. } => self.parse_type(parse_type),
+            ParseTypeKind::Flags { parse_type, flags } => {
+                self.parse_type(parse_type);
+
+                Type::Struct(
+                    flags
+                        .iter()
+                        .map(|(_, name)| (name.clone(), Type::Bool))
+                        .chain([(Symbol::from_static("unknown_bits"), Type::Int)])
+                        .collect(),
+                )
+            }
+            ParseTypeKind::Pointer {
+                parse_type,
+                target,
+                base,
+            } => {
+                let ty = self.parse_type(parse_type);
+                self.parse_type(target);
+                if let Some(base) = base {
+                    self.expect(base, Type::Int, "the pointer base");
+                }
+
+                ty
+            }
+            ParseTypeKind::Error => Type::Unknown,
+        }
+    }
+
+    /// Checks the given repetition, in which `$last` is of the given type.
+    fn repetition(&mut self, repetition_kind: &RepeatKind, last: Type) {
+        match repetition_kind {
+            RepeatKind::Len { count } => {
+                self.expect(count, Type::Int, "the repetition count");
+            }
+            RepeatKind::While { condition } => {
+                let outer_last = self.last.replace(last);
+                self.expect(condition, Type::Bool, "the repetition condition");
+                self.last = outer_last;
+            }
+            RepeatKind::Until { terminator } => {
+                self.expect(terminator, Type::Bytes, "the terminator");
+            }
+            RepeatKind::Error => (),
+        }
+    }
+
+    /// Checks the given expression and returns its type.
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Int(_)) => Type::Int,
+            ExprKind::Lit(Lit::Float(_)) => Type::Float,
+            ExprKind::Lit(Lit::Bytes(_)) => Type::Bytes,
+            ExprKind::Lit(Lit::Bool(_)) => Type::Bool,
+            // byte patterns on their own are reported by the name resolution
+            ExprKind::Lit(Lit::BytePattern(_)) | ExprKind::Error => Type::Unknown,
+            ExprKind::VarUse(var) => {
+                let ty = self.frames.last().and_then(|fields| {
+                    fields
+                        .iter()
+                        .find_map(|(name, ty)| (*name == var.inner).then(|| ty.clone()))
+                });
+
+                ty.unwrap_or_else(|| {
+                    self.problem(
+                        format!("cannot find `{}` in this struct", var.inner.as_str()),
+                        var.span,
+                    );
+
+                    Type::Unknown
+                })
+            }
+            ExprKind::Offset | ExprKind::End | ExprKind::Len => Type::Int,
+            ExprKind::Parent => match self.frames.len().checked_sub(2) {
+                Some(parent) => Type::Struct(self.frames[parent].iter().cloned().collect()),
+                None => Type::Unknown,
+            },
+            ExprKind::Last => self.last.clone().unwrap_or(Type::Unknown),
+            ExprKind::StartOf(expr) | ExprKind::SizeOf(expr) => {
+                self.expr(expr);
+
+                Type::Int
+            }
+            ExprKind::FieldAccess { expr, field } => match self.expr(expr) {
+                Type::Struct(fields) => fields
+                    .iter()
+                    .find_map(|(name, ty)| (*name == field.inner).then(|| ty.clone()))
+                    .unwrap_or_else(|| {
+                        self.problem(
+                            format!("the struct has no field `{}`", field.inner.as_str()),
+                            field.span,
+                        );
+
+                        Type::Unknown
+                    }),
+                Type::Unknown => Type::Unknown,
+                ty => {
+                    self.problem(
+                        format!(
+                            "cannot access field `{}` of {}",
+                            field.inner.as_str(),
+                            ty.description()
+                        ),
+                        field.span,
+                    );
+
+                    Type::Unknown
+                }
+            },
+            ExprKind::UnOp { op, operand } => match op {
+                UnOp::Neg => self.expect_number(operand, "the operand of `-`"),
+                UnOp::Plus | UnOp::Not => self.expr(operand),
+            },
+            ExprKind::BinOp { op, lhs, rhs } => self.bin_op(op, lhs, rhs, expr.span),
+            ExprKind::Peek { ty, offset } => {
+                if let Some(offset) = offset {
+                    self.expect(offset, Type::Int, "the peek offset");
+                }
+
+                self.parse_type(ty)
+            }
+            ExprKind::Concat { args } => {
+                for arg in args {
+                    match arg {
+                        ConcatArg::Direct(arg) => {
+                            self.expect(arg, Type::Bytes, "a `concat` argument");
+                        }
+                        ConcatArg::Expanding(arg) => match self.expr(arg) {
+                            Type::Array(element) if element.may_be(&Type::Bytes) => (),
+                            Type::Unknown => (),
+                            ty => self.mismatch(
+                                "an array of bytes",
+                                "an expanded `concat` argument",
+                                &ty,
+                                arg.span,
+                            ),
+                        },
+                    }
+                }
+
+                Type::Bytes
+            }
+            ExprKind::Call { func, args } => self.call(*func, args),
+            ExprKind::Index { expr, index } => {
+                let ty = self.expr(expr);
+                self.expect(index, Type::Int, "the index");
+
+                match ty {
+                    Type::Bytes => Type::Int,
+                    Type::Array(element) => *element,
+                    Type::Unknown => Type::Unknown,
+                    ty => {
+                        self.mismatch("bytes or an array", "the indexed value", &ty, expr.span);
+
+                        Type::Unknown
+                    }
+                }
+            }
+            ExprKind::Slice { expr, start, end } => {
+                let ty = self.expr(expr);
+                self.expect(start, Type::Int, "the start of the slice");
+                self.expect(end, Type::Int, "the end of the slice");
+
+                match ty {
+                    Type::Bytes | Type::Array(_) | Type::Unknown => ty,
+                    ty => {
+                        self.mismatch("bytes or an array", "the sliced value", &ty, expr.span);
+
+                        Type::Unknown
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the given binary operation and returns the type of its result.
+    fn bin_op(&mut self, op: &BinOp, lhs: &Expr, rhs: &Expr, span: Span) -> Type {
+        let lhs_ty = self.expr(lhs);
+        let rhs_ty = self.expr(rhs);
+
+        let (valid, ty) = match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => (
+                lhs_ty.is_numeric() && rhs_ty.is_numeric(),
+                lhs_ty.arithmetic(&rhs_ty),
+            ),
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::ShiftLeft | BinOp::ShiftRight => {
+                (
+                    lhs_ty.may_be(&Type::Int) && rhs_ty.may_be(&Type::Int),
+                    Type::Int,
+                )
+            }
+            BinOp::Gt | BinOp::Geq | BinOp::Lt | BinOp::Leq => {
+                (lhs_ty.is_numeric() && rhs_ty.is_numeric(), Type::Bool)
+            }
+            BinOp::Eq | BinOp::Neq => {
+                self.expect_comparable(&lhs_ty, &rhs_ty, span);
+
+                return Type::Bool;
+            }
+            BinOp::LogicalAnd | BinOp::LogicalOr => (
+                lhs_ty.may_be(&Type::Bool) && rhs_ty.may_be(&Type::Bool),
+                Type::Bool,
+            ),
+        };
+
+        if !valid {
+            self.problem(
+                format!(
+                    "`{}` cannot be applied to {} and {}",
+                    op.symbol(),
+                    lhs_ty.description(),
+                    rhs_ty.description()
+                ),
+                span,
+            );
+        }
+
+        if matches!(op, BinOp::Div | BinOp::Mod)
+            && let ExprKind::Lit(Lit::Int(divisor)) = &rhs.kind
+            && divisor.sign() == num_bigint::Sign::NoSign
+        {
+            self.problem(
+                format!("`{}` with a divisor of zero always fails", op.symbol()),
+                rhs.span,
+            );
+        }
+
+        ty
+    }
+
+    /// Checks the given call of a built-in function and returns the type of its result.
+    fn call(&mut self, func: Builtin, args: &[Expr]) -> Type {
+        let purpose = |i: usize| format!("argument {} of `{}`", i + 1, func.name());
+
+        let mut tys = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let ty = match (func, i) {
+                (Builtin::Min | Builtin::Max | Builtin::Clamp | Builtin::Abs, _) => {
+                    self.expect_number(arg, &purpose(i))
+                }
+                (Builtin::Fixup | Builtin::ByteSwap, 1..)
+                | (Builtin::AlignUp | Builtin::AlignDown, _) => {
+                    self.expect(arg, Type::Int, &purpose(i))
+                }
+                (
+                    Builtin::Fixup
+                    | Builtin::Crc16
+                    | Builtin::Crc32
+                    | Builtin::Crc32c
+                    | Builtin::Md5
+                    | Builtin::Sha1
+                    | Builtin::Sha256
+                    | Builtin::Xor
+                    | Builtin::ByteSwap
+                    | Builtin::Base64Decode,
+                    0,
+                ) => self.expect(arg, Type::Bytes, &purpose(i)),
+                (Builtin::Xor, _) => {
+                    let ty = self.expr(arg);
+                    if !ty.may_be(&Type::Int) && !ty.may_be(&Type::Bytes) {
+                        self.mismatch("a byte or bytes", &purpose(i), &ty, arg.span);
+                    }
+                    ty
+                }
+                (Builtin::Len, _) => {
+                    let ty = self.expr(arg);
+                    if !matches!(ty, Type::Bytes | Type::Array(_) | Type::Unknown) {
+                        self.mismatch("bytes or an array", &purpose(i), &ty, arg.span);
+                    }
+                    ty
+                }
+                (Builtin::Sum | Builtin::Contains, 0) => {
+                    let ty = self.expr(arg);
+                    if !matches!(ty, Type::Array(_) | Type::Unknown) {
+                        self.mismatch("an array", &purpose(i), &ty, arg.span);
+                    }
+                    ty
+                }
+                _ => self.expr(arg),
+            };
+            tys.push(ty);
+        }
+
+        match func {
+            Builtin::Fixup
+            | Builtin::Md5
+            | Builtin::Sha1
+            | Builtin::Sha256
+            | Builtin::Xor
+            | Builtin::ByteSwap
+            | Builtin::Base64Decode => Type::Bytes,
+            Builtin::AlignUp
+            | Builtin::AlignDown
+            | Builtin::Crc16
+            | Builtin::Crc32
+            | Builtin::Crc32c
+            | Builtin::Len => Type::Int,
+            // the result is one of the arguments
+            Builtin::Min | Builtin::Max | Builtin::Abs | Builtin::Clamp => {
+                tys.into_iter().reduce(Type::join).unwrap_or(Type::Unknown)
+            }
+            Builtin::Sum => match tys.first() {
+                Some(Type::Array(element)) if matches!(**element, Type::Int | Type::Float) => {
+                    (**element).clone()
+                }
+                _ => Type::Unknown,
+            },
+            Builtin::Contains => {
+                if let [Type::Array(element), needle] = &tys[..]
+                    && let Some(needle_expr) = args.get(1)
+                {
+                    self.expect_comparable(element, needle, needle_expr.span);
+                }
+
+                Type::Bool
+            }
+        }
+    }
+}
@@ -1,24 +1,89 @@
 //! Implements expressions in the IR.
 
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use crate::{Int, span::Span};
 
 use super::{ParseType, Spanned, Symbol};
 
 /// A literal expression.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Lit {
     /// An integer literal.
     Int(Int),
+    /// A floating point literal.
+    Float(f64),
     /// A bytes literal.
     Bytes(Arc<[u8]>),
+    /// A bytes literal with wildcards, that can only be compared against.
+    BytePattern(BytePattern),
     /// A boolean literal.
     Bool(bool),
 }
 
+/// A pattern of bytes in which some nibbles are left unspecified, such as `<4d 5a ?? 0?>`.
+#[derive(Debug, Clone)]
+pub struct BytePattern {
+    /// The bytes of the pattern, with all unspecified bits set to zero.
+    pub bytes: Arc<[u8]>,
+    /// The mask of the specified bits for each byte of the pattern.
+    pub mask: Arc<[u8]>,
+}
+
+impl BytePattern {
+    /// Returns the number of bytes that the pattern matches.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns `true` if the pattern matches no bytes at all.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns `true` if the given bytes match the pattern.
+    ///
+    /// ```
+    /// # use hexbait_lang::ir::BytePattern;
+    /// let pattern = BytePattern {
+    ///     bytes: [0x4d, 0x5a, 0x00, 0x30].into(),
+    ///     mask: [0xff, 0xff, 0x00, 0xf0].into(),
+    /// };
+    ///
+    /// assert!(pattern.matches(&[0x4d, 0x5a, 0x90, 0x3f]));
+    /// assert!(!pattern.matches(&[0x4d, 0x5a, 0x90, 0x4f]));
+    /// assert!(!pattern.matches(&[0x4d, 0x5a, 0x90]));
+    /// ```
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() == self.len()
+            && bytes
+                .iter()
+                .zip(self.bytes.iter().zip(self.mask.iter()))
+                .all(|(byte, (expected, mask))| byte & mask == *expected)
+    }
+}
+
+impl fmt::Display for BytePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<")?;
+        for (i, (byte, mask)) in self.bytes.iter().zip(self.mask.iter()).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            for shift in [4, 0] {
+                if (mask >> shift) & 0xf == 0 {
+                    write!(f, "?")?;
+                } else {
+                    write!(f, "{:x}", (byte >> shift) & 0xf)?;
+                }
+            }
+        }
+        write!(f, ">")
+    }
+}
+
 /// A unary operator.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UnOp {
     /// The negation operator: `-`.
     Neg,
@@ -31,7 +96,7 @@ pub enum UnOp {
 }
 
 /// A binary operator.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BinOp {
     /// The addition operator: `+`.
     Add,
@@ -71,8 +136,34 @@ pub enum BinOp {
     ShiftRight,
 }
 
+impl BinOp {
+    /// The symbol of the operator in the language.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Eq => "==",
+            BinOp::Neq => "!=",
+            BinOp::Gt => ">",
+            BinOp::Geq => ">=",
+            BinOp::Lt => "<",
+            BinOp::Leq => "<=",
+            BinOp::LogicalAnd => "&&",
+            BinOp::LogicalOr => "||",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::ShiftLeft => "<<",
+            BinOp::ShiftRight => ">>",
+        }
+    }
+}
+
 /// An expression.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Expr {
     /// The kind of the expression.
     pub kind: ExprKind,
@@ -81,7 +172,7 @@ pub struct Expr {
 }
 
 /// The different kinds of expressions.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ExprKind {
     /// A literal expression.
     Lit(Lit),
@@ -89,12 +180,18 @@ pub enum ExprKind {
     VarUse(Spanned<Symbol>),
     /// The current parsing offset.
     Offset,
+    /// The offset of the end of the current scope.
+    End,
     /// Accesses the partially parsed parent node.
     Parent,
     /// The last parsed element in a repeating expression.
     Last,
     /// The current length of the element in a repeating expression.
     Len,
+    /// The absolute offset of the first byte that the value of the expression was parsed from.
+    StartOf(Box<Expr>),
+    /// The number of bytes that the value of the expression was parsed from.
+    SizeOf(Box<Expr>),
     /// A field access expression.
     FieldAccess {
         /// The expression of which the field will be accessed.
@@ -130,15 +227,160 @@ pub enum ExprKind {
         /// The arguments that should be concatenated.
         args: Vec<ConcatArg>,
     },
+    /// A call of a built-in function.
+    Call {
+        /// The function that is called.
+        func: Builtin,
+        /// The arguments of the call.
+        args: Vec<Expr>,
+    },
+    /// Accesses a single element of a value.
+    Index {
+        /// The expression that is indexed.
+        expr: Box<Expr>,
+        /// The index of the element.
+        index: Box<Expr>,
+    },
+    /// Accesses a range of elements of a value.
+    Slice {
+        /// The expression that is sliced.
+        expr: Box<Expr>,
+        /// The index of the first element in the slice.
+        start: Box<Expr>,
+        /// The index after the last element in the slice.
+        end: Box<Expr>,
+    },
     /// An expression that contained an error during parsing.
     Error,
 }
 
 /// An argument to a `concat` expression.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ConcatArg {
     /// The concatenation works directly on the given `bytes` expression.
     Direct(Expr),
     /// The concatenation works on an array of `bytes` expressions.
     Expanding(Expr),
 }
+
+/// A function that is built into the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// `fixup(record, offset, count)` applies the update sequence array at `offset` in `record`.
+    Fixup,
+    /// `min(a, b)` returns the smaller number.
+    Min,
+    /// `max(a, b)` returns the larger number.
+    Max,
+    /// `abs(x)` returns the absolute value of a number.
+    Abs,
+    /// `clamp(x, low, high)` limits a number to the range from `low` to `high`.
+    Clamp,
+    /// `align_up(x, alignment)` rounds an integer up to a multiple of `alignment`.
+    AlignUp,
+    /// `align_down(x, alignment)` rounds an integer down to a multiple of `alignment`.
+    AlignDown,
+    /// `crc16(data)` computes the CRC-16/ARC checksum of `bytes`.
+    Crc16,
+    /// `crc32(data)` computes the CRC-32 checksum of `bytes`, as used by zlib.
+    Crc32,
+    /// `crc32c(data)` computes the CRC-32C (Castagnoli) checksum of `bytes`.
+    Crc32c,
+    /// `md5(data)` computes the MD5 hash of `bytes`.
+    Md5,
+    /// `sha1(data)` computes the SHA-1 hash of `bytes`.
+    Sha1,
+    /// `sha256(data)` computes the SHA-256 hash of `bytes`.
+    Sha256,
+    /// `xor(data, key)` combines `bytes` with a repeated key byte or key `bytes` using XOR.
+    Xor,
+    /// `byteswap(data, width)` reverses the byte order of each group of `width` bytes.
+    ByteSwap,
+    /// `base64_decode(data)` decodes base64 text.
+    Base64Decode,
+    /// `len(data)` returns the number of bytes or array elements.
+    Len,
+    /// `sum(array)` adds up the numbers in an array.
+    Sum,
+    /// `contains(array, value)` checks if an array contains a value.
+    Contains,
+}
+
+impl Builtin {
+    /// All built-in functions.
+    pub const ALL: [Builtin; 19] = [
+        Builtin::Fixup,
+        Builtin::Min,
+        Builtin::Max,
+        Builtin::Abs,
+        Builtin::Clamp,
+        Builtin::AlignUp,
+        Builtin::AlignDown,
+        Builtin::Crc16,
+        Builtin::Crc32,
+        Builtin::Crc32c,
+        Builtin::Md5,
+        Builtin::Sha1,
+        Builtin::Sha256,
+        Builtin::Xor,
+        Builtin::ByteSwap,
+        Builtin::Base64Decode,
+        Builtin::Len,
+        Builtin::Sum,
+        Builtin::Contains,
+    ];
+
+    /// Returns the built-in function with the given name.
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        Builtin::ALL.into_iter().find(|func| func.name() == name)
+    }
+
+    /// The name under which the function is called.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Fixup => "fixup",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Abs => "abs",
+            Builtin::Clamp => "clamp",
+            Builtin::AlignUp => "align_up",
+            Builtin::AlignDown => "align_down",
+            Builtin::Crc16 => "crc16",
+            Builtin::Crc32 => "crc32",
+            Builtin::Crc32c => "crc32c",
+            Builtin::Md5 => "md5",
+            Builtin::Sha1 => "sha1",
+            Builtin::Sha256 => "sha256",
+            Builtin::Xor => "xor",
+            Builtin::ByteSwap => "byteswap",
+            Builtin::Base64Decode => "base64_decode",
+            Builtin::Len => "len",
+            Builtin::Sum => "sum",
+            Builtin::Contains => "contains",
+        }
+    }
+
+    /// The number of arguments that the function takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Builtin::Fixup | Builtin::Clamp => 3,
+            Builtin::Min
+            | Builtin::Max
+            | Builtin::AlignUp
+            | Builtin::AlignDown
+            | Builtin::Xor
+            | Builtin::ByteSwap
+            | Builtin::Contains => 2,
+            Builtin::Abs
+            | Builtin::Crc16
+            | Builtin::Crc32
+            | Builtin::Crc32c
+            | Builtin::Md5
+            | Builtin::Sha1
+            | Builtin::Sha256
+            | Builtin::Base64Decode
+            | Builtin::Len
+            | Builtin::Sum => 1,
+        }
+    }
+}
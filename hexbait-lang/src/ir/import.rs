@@ -0,0 +1,234 @@
+//! Implements resolving the imports of files.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::parse;
+
+use super::{File, NamedStruct, Symbol, lower_file};
+
+/// Where the source of a file is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SourceLocation {
+    /// A built-in format description with the given file name.
+    Builtin(&'static str),
+    /// A file on disk.
+    Path(PathBuf),
+    /// A source that is not stored anywhere, such as one that was typed in.
+    Unknown,
+}
+
+/// Finds the source of files that are imported by other files.
+pub trait ImportResolver {
+    /// Finds the file imported as `path` by the file at `importer`.
+    ///
+    /// Returns the location and the source of the imported file or `None` if it does not exist.
+    fn resolve(
+        &self,
+        path: &str,
+        importer: &SourceLocation,
+    ) -> io::Result<Option<(SourceLocation, Cow<'static, str>)>>;
+}
+
+/// Resolves imports relative to the importing file, in a list of directories and among the
+/// built-in format descriptions, in that order.
+#[derive(Debug, Clone, Default)]
+pub struct ImportPaths {
+    /// The directories that are searched if the imported file is not next to the importing file.
+    pub search_dirs: Vec<PathBuf>,
+    /// The built-in format descriptions by their file name, which are searched last.
+    pub builtins: &'static [(&'static str, &'static str)],
+}
+
+impl ImportResolver for ImportPaths {
+    fn resolve(
+        &self,
+        path: &str,
+        importer: &SourceLocation,
+    ) -> io::Result<Option<(SourceLocation, Cow<'static, str>)>> {
+        let sibling_dir = match importer {
+            SourceLocation::Path(importer) => importer.parent().map(|dir| dir.to_path_buf()),
+            SourceLocation::Builtin(_) | SourceLocation::Unknown => None,
+        };
+
+        for dir in sibling_dir.iter().chain(&self.search_dirs) {
+            let candidate = dir.join(path);
+            match std::fs::read_to_string(&candidate) {
+                Ok(source) => {
+                    let location = candidate.canonicalize().unwrap_or(candidate);
+                    return Ok(Some((SourceLocation::Path(location), Cow::Owned(source))));
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(self
+            .builtins
+            .iter()
+            .find(|(name, _)| *name == path)
+            .map(|&(name, source)| (SourceLocation::Builtin(name), Cow::Borrowed(source))))
+    }
+}
+
+/// Loads the files imported by the file at `location` and makes their named `struct`s available
+/// in it.
+///
+/// Imports of imported files are loaded as well. Only the named `struct`s of imported files are
+/// used, their fields are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_lang::{ir::{ImportPaths, SourceLocation, lower_file, resolve_imports}, parse};
+/// let mut file = lower_file(parse(r#"!import "common.hbl"; header header;"#).ast);
+/// let resolver = ImportPaths {
+///     search_dirs: Vec::new(),
+///     builtins: &[("common.hbl", "struct header { magic u32; }")],
+/// };
+///
+/// resolve_imports(&mut file, &SourceLocation::Unknown, &resolver).unwrap();
+/// assert_eq!(file.structs.len(), 1);
+/// ```
+pub fn resolve_imports(
+    file: &mut File,
+    location: &SourceLocation,
+    resolver: &impl ImportResolver,
+) -> Result<(), ImportError> {
+    let mut loader = ImportLoader {
+        resolver,
+        stack: vec![location.clone()],
+        loaded: HashSet::new(),
+        structs: HashMap::new(),
+    };
+    loader.load_imports(file, location)?;
+
+    if !loader.structs.is_empty() {
+        let structs = Arc::make_mut(&mut file.structs);
+        for (name, named_struct) in loader.structs {
+            if structs.contains_key(&name) {
+                return Err(ImportError::DuplicateStruct { name });
+            }
+            structs.insert(name, named_struct);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps track of the files loaded while resolving imports.
+struct ImportLoader<'resolver, R> {
+    /// Finds the imported files.
+    resolver: &'resolver R,
+    /// The files that are currently being loaded, to detect cyclic imports.
+    stack: Vec<SourceLocation>,
+    /// The files that were already loaded.
+    loaded: HashSet<SourceLocation>,
+    /// The named `struct`s of all loaded files.
+    structs: HashMap<Symbol, NamedStruct>,
+}
+
+impl<R: ImportResolver> ImportLoader<'_, R> {
+    /// Loads the files imported by the file at the given location.
+    fn load_imports(&mut self, file: &File, location: &SourceLocation) -> Result<(), ImportError> {
+        for import in &file.imports {
+            let path = &import.inner;
+            let (imported_location, source) = self
+                .resolver
+                .resolve(path, location)
+                .map_err(|err| ImportError::Io {
+                    path: Arc::clone(path),
+                    err,
+                })?
+                .ok_or_else(|| ImportError::NotFound {
+                    path: Arc::clone(path),
+                })?;
+
+            if self.stack.contains(&imported_location) {
+                return Err(ImportError::Cycle {
+                    path: Arc::clone(path),
+                });
+            }
+            if !self.loaded.insert(imported_location.clone()) {
+                continue;
+            }
+
+            let parse = parse(&source);
+            let has_syntax_errors = !parse.errors.is_empty();
+            let imported = lower_file(parse.ast);
+            if has_syntax_errors || !imported.errors.is_empty() {
+                return Err(ImportError::Syntax {
+                    path: Arc::clone(path),
+                });
+            }
+
+            self.stack.push(imported_location.clone());
+            self.load_imports(&imported, &imported_location)?;
+            self.stack.pop();
+
+            for (name, named_struct) in imported.structs.iter() {
+                if self.structs.contains_key(name) {
+                    return Err(ImportError::DuplicateStruct { name: name.clone() });
+                }
+                self.structs.insert(name.clone(), named_struct.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An error that occurred while resolving imports.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The imported file could not be found.
+    NotFound {
+        /// The path of the import.
+        path: Arc<str>,
+    },
+    /// The imported file could not be read.
+    Io {
+        /// The path of the import.
+        path: Arc<str>,
+        /// The error that occurred.
+        err: io::Error,
+    },
+    /// The imported file contains syntax errors or invalid constructs.
+    Syntax {
+        /// The path of the import.
+        path: Arc<str>,
+    },
+    /// The imported file imports itself, either directly or through other files.
+    Cycle {
+        /// The path of the import.
+        path: Arc<str>,
+    },
+    /// Multiple `struct`s with the same name are imported.
+    DuplicateStruct {
+        /// The name of the `struct`.
+        name: Symbol,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::NotFound { path } => write!(f, "cannot find imported file `{path}`"),
+            ImportError::Io { path, err } => write!(f, "cannot read imported file `{path}`: {err}"),
+            ImportError::Syntax { path } => {
+                write!(f, "the imported file `{path}` contains syntax errors")
+            }
+            ImportError::Cycle { path } => write!(f, "the import of `{path}` is cyclic"),
+            ImportError::DuplicateStruct { name } => {
+                write!(f, "`struct` `{}` is declared more than once", name.as_str())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
@@ -0,0 +1,761 @@
+//! Implements exporting format descriptions as Kaitai Struct definitions.
+//!
+//! Kaitai Struct cannot express everything that the hexbait language can, so the export is only
+//! a best effort. Every construct without an equivalent is reported, so that it can be fixed up
+//! by hand in the exported definition.
+
+use std::collections::HashSet;
+
+use hexbait_common::Endianness;
+use serde_json::{Map, Value as Json, json};
+
+use crate::span::Span;
+
+use super::{
+    BinOp, Builtin, Declaration, ElsePart, Expr, ExprKind, File, IfChain, Lit, NamedStruct,
+    ParseType, ParseTypeKind, RepeatKind, Spanned, StructContent, StructField, UnOp,
+};
+
+/// A format description exported as a Kaitai Struct definition.
+#[derive(Debug, Clone)]
+pub struct KsyExport {
+    /// The Kaitai Struct definition in YAML.
+    pub ksy: String,
+    /// The constructs that have no equivalent in Kaitai Struct and were left out.
+    pub unsupported: Vec<Spanned<String>>,
+}
+
+/// Exports the format description as a Kaitai Struct definition with the given id.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_lang::{ir::{lower_file, to_ksy}, parse};
+/// let ir = lower_file(parse("!endian be; magic bytes = <7f \"ELF\">; len u16; rest [u8] len len;").ast);
+/// let export = to_ksy(&ir, "example");
+///
+/// assert!(export.unsupported.is_empty());
+/// assert_eq!(
+///     export.ksy,
+///     r#"meta:
+///   id: "example"
+///   endian: "be"
+/// seq:
+///   -
+///     id: "magic"
+///     contents:
+///       - 127
+///       - 69
+///       - 76
+///       - 70
+///   -
+///     id: "len"
+///     type: "u2"
+///   -
+///     id: "rest"
+///     type: "u1"
+///     repeat: "expr"
+///     repeat-expr: "len"
+/// "#,
+/// );
+/// ```
+pub fn to_ksy(file: &File, id: &str) -> KsyExport {
+    let mut named_types: Vec<_> = file.structs.values().collect();
+    named_types.sort_by(|a, b| a.name.inner.as_str().cmp(b.name.inner.as_str()));
+
+    let mut exporter = Exporter {
+        endianness: root_endianness(&file.content),
+        reserved_types: named_types
+            .iter()
+            .map(|named| identifier(named.name.inner.as_str()))
+            .collect(),
+        in_instance: false,
+        unsupported: Vec::new(),
+    };
+
+    let mut root = TypeSpec::new(None);
+    root.skip_leading_endianness = true;
+    exporter.content(&mut root, &file.content, None);
+    for named in named_types {
+        let ty = exporter.named_struct(named);
+        root.types
+            .insert(identifier(named.name.inner.as_str()), Json::Object(ty));
+    }
+
+    let mut meta = Map::new();
+    meta.insert(String::from("id"), json!(identifier(id)));
+    meta.insert(
+        String::from("endian"),
+        json!(endianness_suffix(exporter.endianness).unwrap_or("le")),
+    );
+
+    let mut ksy = Map::new();
+    ksy.insert(String::from("meta"), Json::Object(meta));
+    ksy.extend(root.finish());
+
+    let mut out = String::new();
+    crate::eval::write_yaml(&mut out, &Json::Object(ksy), 0);
+    // the YAML writer starts every key on a new line
+    let mut ksy = out.trim_start_matches('\n').to_string();
+    ksy.push('\n');
+
+    KsyExport {
+        ksy,
+        unsupported: exporter.unsupported,
+    }
+}
+
+/// Returns the endianness declared at the start of the file, before any fields.
+///
+/// This becomes the default endianness of the exported definition.
+fn root_endianness(content: &[StructContent]) -> Endianness {
+    content
+        .iter()
+        .map_while(|item| match item {
+            StructContent::Declaration(Declaration::Endianness(endianness)) => {
+                Some(Some(*endianness))
+            }
+            StructContent::Declaration(_) => Some(None),
+            _ => None,
+        })
+        .flatten()
+        .last()
+        .unwrap_or(Endianness::Little)
+}
+
+/// Returns the suffix of Kaitai Struct types for the given endianness, if it has one.
+fn endianness_suffix(endianness: Endianness) -> Option<&'static str> {
+    match endianness {
+        Endianness::Little => Some("le"),
+        Endianness::Big => Some("be"),
+        Endianness::Pdp
+        | Endianness::BigWordSwapped16
+        | Endianness::LittleWordSwapped32
+        | Endianness::BigWordSwapped32 => None,
+    }
+}
+
+/// Converts a name to a valid Kaitai Struct identifier, which is lower snake case.
+fn identifier(name: &str) -> String {
+    let mut ident = String::with_capacity(name.len());
+    let mut prev_lower = false;
+    for c in name.trim_start_matches('_').chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            ident.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        if c.is_ascii_alphanumeric() {
+            ident.push(c.to_ascii_lowercase());
+        } else if !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+
+    if !ident.starts_with(|c: char| c.is_ascii_lowercase()) {
+        ident.insert_str(0, "v_");
+    }
+
+    ident
+}
+
+/// The parts of a Kaitai Struct type that are being built.
+struct TypeSpec {
+    /// The endianness within the type, if it differs from the one it inherits.
+    endianness: Option<Endianness>,
+    /// Whether endianness declarations before the first field are skipped, because they are
+    /// already the default endianness.
+    skip_leading_endianness: bool,
+    /// The fields that are parsed in sequence.
+    seq: Vec<Json>,
+    /// The values that are computed or parsed elsewhere.
+    instances: Map<String, Json>,
+    /// The types that are defined within the type.
+    types: Map<String, Json>,
+    /// The enums that are defined within the type.
+    enums: Map<String, Json>,
+}
+
+impl TypeSpec {
+    /// Creates an empty type with the given endianness.
+    fn new(endianness: Option<Endianness>) -> TypeSpec {
+        TypeSpec {
+            endianness,
+            skip_leading_endianness: false,
+            seq: Vec::new(),
+            instances: Map::new(),
+            types: Map::new(),
+            enums: Map::new(),
+        }
+    }
+
+    /// Returns a name based on `name` that is not used by any of the types.
+    fn unique_type_name(&self, name: &str, reserved: &HashSet<String>) -> String {
+        let is_free = |name: &str| !self.types.contains_key(name) && !reserved.contains(name);
+        if is_free(name) {
+            return name.to_string();
+        }
+
+        (2..)
+            .map(|i| format!("{name}_{i}"))
+            .find(|name| is_free(name))
+            .expect("there are infinitely many names")
+    }
+
+    /// Converts the type to its Kaitai Struct definition, leaving out empty parts.
+    fn finish(self) -> Map<String, Json> {
+        let mut ty = Map::new();
+        if !self.seq.is_empty() {
+            ty.insert(String::from("seq"), Json::Array(self.seq));
+        }
+        for (key, map) in [
+            ("instances", self.instances),
+            ("types", self.types),
+            ("enums", self.enums),
+        ] {
+            if !map.is_empty() {
+                ty.insert(String::from(key), Json::Object(map));
+            }
+        }
+
+        ty
+    }
+}
+
+/// Keeps track of the state of an export.
+struct Exporter {
+    /// The default endianness of the exported definition.
+    endianness: Endianness,
+    /// The names of the types defined at the top level.
+    reserved_types: HashSet<String>,
+    /// Whether the expressions being translated are evaluated lazily, after parsing.
+    in_instance: bool,
+    /// The constructs that could not be exported.
+    unsupported: Vec<Spanned<String>>,
+}
+
+impl Exporter {
+    /// Reports a construct that has no equivalent in Kaitai Struct.
+    fn unsupported(&mut self, what: &str, span: Span) {
+        self.unsupported.push(Spanned {
+            inner: format!("{what} cannot be expressed in Kaitai Struct"),
+            span,
+        });
+    }
+
+    /// Exports a named `struct` as a type.
+    fn named_struct(&mut self, named: &NamedStruct) -> Map<String, Json> {
+        let mut spec = TypeSpec::new(None);
+        self.content(&mut spec, &named.content, None);
+
+        let mut ty = Map::new();
+        if !named.params.is_empty() {
+            let params = named
+                .params
+                .iter()
+                .map(|param| json!({ "id": identifier(param.inner.as_str()) }))
+                .collect();
+            ty.insert(String::from("params"), Json::Array(params));
+        }
+        ty.extend(spec.finish());
+
+        ty
+    }
+
+    /// Exports the content of a `struct` into the type.
+    ///
+    /// `condition` is the condition under which the content is parsed, if there is one.
+    fn content(&mut self, spec: &mut TypeSpec, content: &[StructContent], condition: Option<&str>) {
+        for item in content {
+            match item {
+                StructContent::Field(field) => {
+                    spec.skip_leading_endianness = false;
+                    let field = self.field(spec, field, condition);
+                    spec.seq.push(Json::Object(field));
+                }
+                StructContent::Declaration(declaration) => {
+                    self.declaration(spec, declaration, condition)
+                }
+                StructContent::LetStatement(let_statement) => {
+                    let mut instance = Map::new();
+                    if let Some(condition) = condition {
+                        instance.insert(String::from("if"), json!(condition));
+                    }
+                    self.in_instance = true;
+                    if let Some(value) = self.expr(&let_statement.expr) {
+                        instance.insert(String::from("value"), json!(value));
+                    }
+                    self.in_instance = false;
+                    spec.instances.insert(
+                        identifier(let_statement.name.inner.as_str()),
+                        Json::Object(instance),
+                    );
+                }
+                StructContent::Error => (),
+            }
+        }
+    }
+
+    /// Exports a declaration into the type.
+    fn declaration(
+        &mut self,
+        spec: &mut TypeSpec,
+        declaration: &Declaration,
+        condition: Option<&str>,
+    ) {
+        match declaration {
+            Declaration::Endianness(endianness)
+                if spec.skip_leading_endianness && endianness_suffix(*endianness).is_some() => {}
+            Declaration::Endianness(endianness) => spec.endianness = Some(*endianness),
+            Declaration::If(chain) => self.if_chain(spec, chain, condition),
+            Declaration::Align(expr) => self.unsupported("`!align`", expr.span),
+            Declaration::SeekBy(expr) => self.unsupported("`!seek by`", expr.span),
+            Declaration::SeekTo(expr) => self.unsupported("`!seek to`", expr.span),
+            Declaration::Scope { kind, .. } => {
+                let expr = match kind {
+                    super::ScopeKind::At { start, .. } => start,
+                    super::ScopeKind::In { bytes } => bytes,
+                    super::ScopeKind::Decompressed { bytes, .. } => bytes,
+                };
+                self.unsupported("`!scope`", expr.span);
+            }
+            Declaration::Assert { condition, .. } => self.unsupported("`!assert`", condition.span),
+            Declaration::WarnIf { condition, .. } => self.unsupported("`!warn if`", condition.span),
+            Declaration::Verify { computed, .. } => self.unsupported("`!verify`", computed.span),
+            Declaration::Recover { kind } => {
+                let expr = match kind {
+                    super::RecoveryKind::At { offset } => offset,
+                    super::RecoveryKind::Scan { pattern } => pattern,
+                };
+                self.unsupported("`!recover`", expr.span);
+            }
+        }
+    }
+
+    /// Exports an `if` chain by attaching its conditions to the fields within it.
+    fn if_chain(&mut self, spec: &mut TypeSpec, chain: &IfChain, condition: Option<&str>) {
+        let Some(own) = self.expr(&chain.condition) else {
+            return;
+        };
+        let combine = |own: &str| match condition {
+            Some(condition) => format!("({condition}) and ({own})"),
+            None => own.to_string(),
+        };
+
+        self.content(spec, &chain.then_block, Some(&combine(&own)));
+
+        let otherwise = combine(&format!("not ({own})"));
+        match &chain.else_part {
+            Some(ElsePart::ElseBlock(content)) => self.content(spec, content, Some(&otherwise)),
+            Some(ElsePart::IfChain(chain)) => self.if_chain(spec, chain, Some(&otherwise)),
+            None => (),
+        }
+    }
+
+    /// Exports a field as an entry of a `seq`.
+    fn field(
+        &mut self,
+        spec: &mut TypeSpec,
+        field: &StructField,
+        condition: Option<&str>,
+    ) -> Map<String, Json> {
+        let name = field.name.inner.as_str();
+        // hidden fields may share their names, so they stay anonymous
+        let id = (!name.starts_with('_')).then(|| identifier(name));
+
+        let mut entry = Map::new();
+        if let Some(id) = &id {
+            entry.insert(String::from("id"), json!(id));
+        }
+
+        match field.expected.as_ref().map(|expected| &expected.kind) {
+            Some(ExprKind::Lit(Lit::Bytes(bytes)))
+                if matches!(field.ty.kind, ParseTypeKind::Bytes { .. }) =>
+            {
+                entry.insert(String::from("contents"), json!(&bytes[..]));
+            }
+            Some(_) => {
+                self.parse_type(spec, name, &field.ty, &mut entry);
+                let expected = field
+                    .expected
+                    .as_ref()
+                    .expect("the field has an expected value");
+                if let Some(expected) = self.expr(expected) {
+                    entry.insert(String::from("valid"), json!(expected));
+                }
+            }
+            None => self.parse_type(spec, name, &field.ty, &mut entry),
+        }
+
+        if let Some(condition) = condition {
+            entry.insert(String::from("if"), json!(condition));
+        }
+        if let Some(doc) = &field.doc {
+            entry.insert(String::from("doc"), json!(&doc[..]));
+        }
+
+        if let Some(id) = id {
+            self.derived_instances(spec, &id, &field.ty, condition);
+        }
+
+        entry
+    }
+
+    /// Adds the instances for values that Kaitai Struct cannot attach to the field itself, such
+    /// as the targets of pointers and the set flags.
+    fn derived_instances(
+        &mut self,
+        spec: &mut TypeSpec,
+        id: &str,
+        ty: &ParseType,
+        condition: Option<&str>,
+    ) {
+        match &ty.kind {
+            ParseTypeKind::Pointer { target, base, .. } => {
+                let name = format!("{id}_target");
+                let mut instance = Map::new();
+                let pos = match base.as_ref().map(|base| self.expr(base)) {
+                    Some(Some(base)) => format!("{base} + {id}"),
+                    Some(None) => return,
+                    None => id.to_string(),
+                };
+                instance.insert(String::from("pos"), json!(pos));
+                self.parse_type(spec, &name, target, &mut instance);
+                if let Some(condition) = condition {
+                    instance.insert(String::from("if"), json!(condition));
+                }
+                spec.instances.insert(name, Json::Object(instance));
+            }
+            ParseTypeKind::Flags { flags, .. } => {
+                for (mask, flag) in flags {
+                    spec.instances.insert(
+                        format!("{id}_{}", identifier(flag.as_str())),
+                        json!({ "value": format!("({id} & {mask}) != 0") }),
+                    );
+                }
+            }
+            ParseTypeKind::Classified { parse_type, .. } => {
+                self.derived_instances(spec, id, parse_type, condition)
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds the attributes that describe how to parse the parse type to the entry.
+    ///
+    /// `name` is the name of the field that is parsed, which is used to name the types and enums
+    /// that are defined for it.
+    fn parse_type(
+        &mut self,
+        spec: &mut TypeSpec,
+        name: &str,
+        ty: &ParseType,
+        entry: &mut Map<String, Json>,
+    ) {
+        match &ty.kind {
+            ParseTypeKind::Named { name, args } => {
+                let mut type_name = identifier(name.inner.as_str());
+                if !args.is_empty() {
+                    let args: Option<Vec<_>> = args.iter().map(|arg| self.expr(arg)).collect();
+                    let Some(args) = args else {
+                        return;
+                    };
+                    type_name = format!("{type_name}({})", args.join(", "));
+                }
+                entry.insert(String::from("type"), json!(type_name));
+            }
+            ParseTypeKind::Integer { bit_width, signed } => {
+                if !matches!(bit_width, 8 | 16 | 32 | 64) {
+                    self.unsupported(&format!("integers with {bit_width} bits"), ty.span);
+                    return;
+                }
+                let sign = if *signed { 's' } else { 'u' };
+                let Some(suffix) = self.suffix(spec, *bit_width, ty.span) else {
+                    return;
+                };
+                entry.insert(
+                    String::from("type"),
+                    json!(format!("{sign}{}{suffix}", bit_width / 8)),
+                );
+            }
+            ParseTypeKind::Float { bit_width } => {
+                let Some(suffix) = self.suffix(spec, *bit_width, ty.span) else {
+                    return;
+                };
+                entry.insert(
+                    String::from("type"),
+                    json!(format!("f{}{suffix}", bit_width / 8)),
+                );
+            }
+            ParseTypeKind::Leb128 { .. } => self.unsupported("LEB128 integers", ty.span),
+            ParseTypeKind::DynamicInteger { .. } => {
+                self.unsupported("integers of dynamic size", ty.span)
+            }
+            ParseTypeKind::Bytes { repetition_kind } => match repetition_kind {
+                RepeatKind::Len { count } => {
+                    if let Some(count) = self.expr(count) {
+                        entry.insert(String::from("size"), json!(count));
+                    }
+                }
+                RepeatKind::Until { terminator } => {
+                    let byte = match &terminator.kind {
+                        ExprKind::Lit(Lit::Bytes(bytes)) if bytes.len() == 1 => Some(bytes[0]),
+                        ExprKind::Lit(Lit::Int(int)) => u8::try_from(int).ok(),
+                        _ => None,
+                    };
+                    let Some(byte) = byte else {
+                        self.unsupported("terminators other than single bytes", terminator.span);
+                        return;
+                    };
+                    entry.insert(String::from("terminator"), json!(byte));
+                    entry.insert(String::from("consume"), json!(false));
+                    entry.insert(String::from("include"), json!(false));
+                }
+                RepeatKind::While { condition } => {
+                    self.unsupported("`while` repetitions", condition.span)
+                }
+                RepeatKind::Error => (),
+            },
+            ParseTypeKind::Repeating {
+                parse_type,
+                repetition_kind,
+            } => {
+                self.parse_type(spec, name, parse_type, entry);
+                match repetition_kind {
+                    RepeatKind::Len { count } => {
+                        if let Some(count) = self.expr(count) {
+                            entry.insert(String::from("repeat"), json!("expr"));
+                            entry.insert(String::from("repeat-expr"), json!(count));
+                        }
+                    }
+                    RepeatKind::While { condition } => {
+                        self.unsupported("`while` repetitions", condition.span)
+                    }
+                    RepeatKind::Until { terminator } => {
+                        self.unsupported("repetitions until a terminator", terminator.span)
+                    }
+                    RepeatKind::Error => (),
+                }
+            }
+            ParseTypeKind::Struct { content } => {
+                let mut inner = TypeSpec::new(spec.endianness);
+                self.content(&mut inner, content, None);
+
+                let type_name = spec.unique_type_name(&identifier(name), &self.reserved_types);
+                spec.types
+                    .insert(type_name.clone(), Json::Object(inner.finish()));
+                entry.insert(String::from("type"), json!(type_name));
+            }
+            ParseTypeKind::Switch {
+                scrutinee,
+                branches,
+                default,
+            } => {
+                let Some(switch_on) = self.expr(scrutinee) else {
+                    return;
+                };
+
+                let mut cases = Map::new();
+                for (i, (lit, branch)) in branches.iter().enumerate() {
+                    let key = match lit {
+                        Lit::Int(int) => int.to_string(),
+                        Lit::Bool(val) => val.to_string(),
+                        _ => {
+                            self.unsupported(
+                                "`switch` branches that are not integers",
+                                branch.span,
+                            );
+                            continue;
+                        }
+                    };
+                    if let Some(case) = self.case(spec, &format!("{name}_{i}"), branch) {
+                        cases.insert(key, case);
+                    }
+                }
+                if let Some(case) = self.case(spec, &format!("{name}_default"), default) {
+                    cases.insert(String::from("_"), case);
+                }
+
+                entry.insert(
+                    String::from("type"),
+                    json!({ "switch-on": switch_on, "cases": cases }),
+                );
+            }
+            ParseTypeKind::Bitfield { bit_width, fields } => {
+                let endianness = spec.endianness.unwrap_or(self.endianness);
+                let bit_endian = match endianness {
+                    Endianness::Little => "le",
+                    Endianness::Big => "be",
+                    _ => {
+                        self.unsupported("bitfields in this endianness", ty.span);
+                        return;
+                    }
+                };
+
+                let mut seq = Vec::new();
+                let mut used_bits = 0;
+                for field in fields {
+                    if field.signed {
+                        self.unsupported("signed bitfield fields", field.name.span);
+                    }
+                    used_bits += field.bit_width;
+
+                    let name = field.name.inner.as_str();
+                    let mut entry = Map::new();
+                    if !name.starts_with('_') {
+                        entry.insert(String::from("id"), json!(identifier(name)));
+                    }
+                    entry.insert(String::from("type"), json!(format!("b{}", field.bit_width)));
+                    seq.push(Json::Object(entry));
+                }
+                if used_bits < *bit_width {
+                    seq.push(json!({ "type": format!("b{}", bit_width - used_bits) }));
+                }
+
+                let type_name = spec.unique_type_name(&identifier(name), &self.reserved_types);
+                spec.types.insert(
+                    type_name.clone(),
+                    json!({ "meta": { "bit-endian": bit_endian }, "seq": seq }),
+                );
+                entry.insert(String::from("type"), json!(type_name));
+            }
+            ParseTypeKind::Enum { parse_type, names } => {
+                self.parse_type(spec, name, parse_type, entry);
+
+                let enum_name = identifier(name);
+                let values: Map<_, _> = names
+                    .iter()
+                    .map(|(val, name)| (val.to_string(), json!(identifier(name))))
+                    .collect();
+                spec.enums.insert(enum_name.clone(), Json::Object(values));
+                entry.insert(String::from("enum"), json!(enum_name));
+            }
+            ParseTypeKind::Flags { parse_type, .. }
+            | ParseTypeKind::Pointer { parse_type, .. }
+            | ParseTypeKind::Classified { parse_type, .. } => {
+                self.parse_type(spec, name, parse_type, entry)
+            }
+            ParseTypeKind::Error => (),
+        }
+    }
+
+    /// Exports a branch of a `switch` parse type, which can only consist of a type.
+    fn case(&mut self, spec: &mut TypeSpec, name: &str, ty: &ParseType) -> Option<Json> {
+        let mut entry = Map::new();
+        self.parse_type(spec, name, ty, &mut entry);
+
+        match entry.remove("type") {
+            Some(case) if entry.is_empty() => Some(case),
+            _ => {
+                self.unsupported("`switch` branches that are not a single type", ty.span);
+                None
+            }
+        }
+    }
+
+    /// Returns the endianness suffix of a type with the given bit width in the type.
+    ///
+    /// Single bytes and types that use the default endianness need no suffix.
+    fn suffix(&mut self, spec: &TypeSpec, bit_width: u32, span: Span) -> Option<&'static str> {
+        let Some(endianness) = spec.endianness.filter(|_| bit_width > 8) else {
+            return Some("");
+        };
+
+        let suffix = endianness_suffix(endianness);
+        if suffix.is_none() {
+            self.unsupported("byte orders other than little and big endian", span);
+        }
+
+        suffix
+    }
+
+    /// Translates an expression to the Kaitai Struct expression language.
+    ///
+    /// Returns `None` if the expression cannot be translated.
+    fn expr(&mut self, expr: &Expr) -> Option<String> {
+        Some(match &expr.kind {
+            ExprKind::Lit(lit) => match lit {
+                Lit::Int(int) => int.to_string(),
+                Lit::Float(float) => format!("{float:?}"),
+                Lit::Bytes(bytes) => {
+                    let bytes: Vec<_> = bytes.iter().map(|byte| format!("{byte:#04x}")).collect();
+                    format!("[{}]", bytes.join(", "))
+                }
+                Lit::BytePattern(_) => {
+                    self.unsupported("byte patterns", expr.span);
+                    return None;
+                }
+                Lit::Bool(val) => val.to_string(),
+            },
+            ExprKind::VarUse(name) => identifier(name.inner.as_str()),
+            // instances are evaluated lazily, so the position of the stream is not meaningful
+            ExprKind::Offset if self.in_instance => {
+                self.unsupported("`$offset` in `let` statements", expr.span);
+                return None;
+            }
+            ExprKind::Offset => String::from("_io.pos"),
+            ExprKind::End => String::from("_io.size"),
+            ExprKind::Parent => String::from("_parent"),
+            ExprKind::Len => String::from("_index"),
+            ExprKind::FieldAccess { expr, field } => {
+                format!("{}.{}", self.expr(expr)?, identifier(field.inner.as_str()))
+            }
+            ExprKind::UnOp { op, operand } => {
+                let operand = self.expr(operand)?;
+                match op {
+                    UnOp::Neg => format!("-{operand}"),
+                    UnOp::Plus => operand,
+                    UnOp::Not => format!("not {operand}"),
+                }
+            }
+            ExprKind::BinOp { op, lhs, rhs } => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                let op = match op {
+                    BinOp::LogicalAnd => "and",
+                    BinOp::LogicalOr => "or",
+                    op => op.symbol(),
+                };
+                format!("({lhs} {op} {rhs})")
+            }
+            ExprKind::Call {
+                func: Builtin::Len,
+                args,
+            } if args.len() == 1 => format!("{}.size", self.expr(&args[0])?),
+            ExprKind::Call { func, .. } => {
+                self.unsupported(
+                    &format!("the built-in function `{}`", func.name()),
+                    expr.span,
+                );
+                return None;
+            }
+            ExprKind::Index { expr, index } => {
+                format!("{}[{}]", self.expr(expr)?, self.expr(index)?)
+            }
+            ExprKind::Last => {
+                self.unsupported("`$last`", expr.span);
+                return None;
+            }
+            ExprKind::StartOf(_) => {
+                self.unsupported("`$startof`", expr.span);
+                return None;
+            }
+            ExprKind::SizeOf(_) => {
+                self.unsupported("`$sizeof`", expr.span);
+                return None;
+            }
+            ExprKind::Peek { .. } => {
+                self.unsupported("`peek` expressions", expr.span);
+                return None;
+            }
+            ExprKind::Concat { .. } => {
+                self.unsupported("`concat` expressions", expr.span);
+                return None;
+            }
+            ExprKind::Slice { .. } => {
+                self.unsupported("slices", expr.span);
+                return None;
+            }
+            ExprKind::Error => return None,
+        })
+    }
+}
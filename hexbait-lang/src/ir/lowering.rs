@@ -1,10 +1,16 @@
 //! Implements lowering the AST to the IR.
 
+use std::{collections::HashMap, sync::Arc};
+
 use crate::{
-    Int,
+    Int, SyntaxToken,
     ast::{self, AstNode as _},
+    eval::parse::eval_const_expr,
     int_from_str,
-    ir::{ConcatArg, ElsePart, IfChain, ParseTypeKind, ScopeKind},
+    ir::{
+        BitfieldField, Classification, Color, Compression, ConcatArg, ElsePart, IfChain,
+        NamedStruct, ParseTypeKind, RecoveryKind, ScopeKind, TimestampEncoding,
+    },
     lexer::TokenKind,
     span::Span,
 };
@@ -12,7 +18,7 @@ use crate::{
 use super::{
     Declaration, Endianness, File, LetStatement, ParseType, RepeatKind, Spanned, StructContent,
     StructField, Symbol,
-    expr::{BinOp, Expr, ExprKind, Lit, UnOp},
+    expr::{BinOp, Builtin, BytePattern, Expr, ExprKind, Lit, UnOp},
     str::str_lit_content_to_bytes,
 };
 
@@ -22,20 +28,55 @@ macro_rules! parser_unreachable {
     };
 }
 
+/// The attributes of a `struct` field or a named `struct`.
+#[derive(Default)]
+struct Attributes {
+    /// How the value is meant to be interpreted.
+    classification: Option<Classification>,
+    /// The color of the value.
+    color: Option<Color>,
+}
+
+/// A single attribute.
+enum Attribute {
+    /// An attribute that classifies the value.
+    Classification(Classification),
+    /// An attribute that colors the value.
+    Color(Color),
+}
+
 /// Lowers the given file AST to IR.
 pub fn lower_file(file: ast::File) -> File {
     let mut ctx = LoweringCtx::new();
-    let mut out = Vec::new();
-
     for content in file.struct_content() {
-        out.push(ctx.lower_struct_content(content));
+        if let ast::StructContent::ConstDeclaration(const_declaration) = content {
+            ctx.lower_const_declaration(const_declaration);
+        }
+    }
+    let content = ctx.lower_struct_block(
+        file.struct_content()
+            .filter(|content| !matches!(content, ast::StructContent::ConstDeclaration(_))),
+    );
+
+    File {
+        content,
+        structs: Arc::new(ctx.structs),
+        imports: ctx.imports,
+        errors: ctx.errors,
     }
-
-    File { content: out }
 }
 
 /// The context in which lowering is performed.
-struct LoweringCtx {}
+struct LoweringCtx {
+    /// The named `struct`s declared so far.
+    structs: HashMap<Symbol, NamedStruct>,
+    /// The paths of the files imported so far.
+    imports: Vec<Spanned<Arc<str>>>,
+    /// The values of the constants declared so far.
+    consts: HashMap<Symbol, Lit>,
+    /// The errors that occurred so far.
+    errors: Vec<Spanned<String>>,
+}
 
 /// Accesses a required field in the given value.
 ///
@@ -55,32 +96,194 @@ macro_rules! required_field {
 impl LoweringCtx {
     /// Creates a new lowering context.
     fn new() -> LoweringCtx {
-        LoweringCtx {}
+        LoweringCtx {
+            structs: HashMap::new(),
+            imports: Vec::new(),
+            consts: HashMap::new(),
+            errors: Vec::new(),
+        }
     }
 
-    /// Shows the given error message for the given span.
+    /// Records the given error message for the given span.
     fn error(&mut self, message: impl Into<String>, span: Span) {
-        eprintln!(
-            "TODO: improve error handling: {} at {span:?}",
-            message.into()
-        );
+        self.errors.push(Spanned {
+            inner: message.into(),
+            span,
+        });
+    }
+
+    /// Lowers the given AST `struct` contents to IR.
+    fn lower_struct_block(
+        &mut self,
+        contents: impl Iterator<Item = ast::StructContent>,
+    ) -> Vec<StructContent> {
+        contents
+            .filter_map(|content| self.lower_struct_content(content))
+            .collect()
     }
 
     /// Lowers the given `struct` content AST to IR.
-    fn lower_struct_content(&mut self, struct_content: ast::StructContent) -> StructContent {
-        match struct_content {
+    ///
+    /// Named `struct`s, imports and constants are recorded in the context, so there is no IR for
+    /// them.
+    fn lower_struct_content(
+        &mut self,
+        struct_content: ast::StructContent,
+    ) -> Option<StructContent> {
+        let content = match struct_content {
+            ast::StructContent::Declaration(ast::Declaration::ImportDeclaration(import)) => {
+                let path = self.lower_import(import)?;
+                self.imports.push(path);
+                return None;
+            }
             ast::StructContent::Declaration(declaration) => self
                 .lower_declaration(declaration)
                 .map(StructContent::Declaration),
             ast::StructContent::StructField(struct_field) => self
                 .lower_struct_field(struct_field)
                 .map(StructContent::Field),
-            ast::StructContent::Struct(_) => todo!(),
+            ast::StructContent::Struct(named_struct) => {
+                let named_struct = self.lower_named_struct(named_struct)?;
+                if self.structs.contains_key(&named_struct.name.inner) {
+                    self.error(
+                        format!(
+                            "`struct` {:?} is declared more than once",
+                            named_struct.name.inner
+                        ),
+                        named_struct.name.span,
+                    );
+                } else {
+                    self.structs
+                        .insert(named_struct.name.inner.clone(), named_struct);
+                }
+                return None;
+            }
             ast::StructContent::LetStatement(let_statement) => self
                 .lower_let_statement(let_statement)
                 .map(StructContent::LetStatement),
+            ast::StructContent::ConstDeclaration(const_declaration) => {
+                self.error(
+                    "constants can only be declared at the top level of a file",
+                    const_declaration.span(),
+                );
+                return None;
+            }
+        };
+
+        Some(content.unwrap_or(StructContent::Error))
+    }
+
+    /// Lowers the given AST named `struct` to IR.
+    fn lower_named_struct(&mut self, named_struct: ast::Struct) -> Option<NamedStruct> {
+        let name = Spanned::<Symbol>::from(
+            required_field!(named_struct => name ? self: "expected name for `struct`" => None),
+        );
+        let block =
+            required_field!(named_struct => struct_block ? self: "expected struct block" => None);
+        let params = named_struct
+            .struct_params()
+            .map(|params| params.params().map(Spanned::<Symbol>::from).collect())
+            .unwrap_or_default();
+        let content = self.lower_struct_block(block.struct_content());
+
+        let attributes_span = named_struct
+            .attribute()
+            .map(|attribute| attribute.span())
+            .reduce(|first, last| Span {
+                start: first.start,
+                end: last.end,
+            });
+        let Attributes {
+            classification,
+            color,
+        } = self.lower_attributes(named_struct.attribute());
+        if let (Some(_), Some(span)) = (classification, attributes_span) {
+            self.error(
+                "only the `@color` attribute can be used on a `struct`",
+                span,
+            );
         }
-        .unwrap_or(StructContent::Error)
+
+        Some(NamedStruct {
+            name,
+            params,
+            content,
+            color,
+        })
+    }
+
+    /// Lowers the given AST constant declaration and records its value in the context.
+    fn lower_const_declaration(&mut self, const_declaration: ast::ConstDeclaration) -> Option<()> {
+        let name = Spanned::<Symbol>::from(
+            required_field!(const_declaration => name ? self: "expected name for constant" => None),
+        );
+        let expr = self.lower_expr(
+            required_field!(const_declaration => expr ? self: "expected expression" => None),
+        );
+
+        let value = match &expr.kind {
+            // byte patterns cannot be evaluated, but they can still be compared against
+            ExprKind::Lit(pattern @ Lit::BytePattern(_)) => pattern.clone(),
+            _ if !is_constant(&expr) => {
+                self.error(
+                    "constants can only contain literals, operators, function calls and constants declared before them",
+                    expr.span,
+                );
+                return None;
+            }
+            _ => match eval_const_expr(&expr) {
+                Ok(value) => value,
+                Err(message) => {
+                    self.error(message, expr.span);
+                    return None;
+                }
+            },
+        };
+
+        if self.consts.contains_key(&name.inner) {
+            self.error(
+                format!("constant {:?} is declared more than once", name.inner),
+                name.span,
+            );
+            return None;
+        }
+        self.consts.insert(name.inner, value);
+
+        Some(())
+    }
+
+    /// Reports an error if a field is declared with the name of a constant.
+    fn check_not_constant(&mut self, name: &Spanned<Symbol>) {
+        if self.consts.contains_key(&name.inner) {
+            self.error(
+                format!("{:?} is already declared as a constant", name.inner),
+                name.span,
+            );
+        }
+    }
+
+    /// Lowers the given AST import to the imported path.
+    fn lower_import(&mut self, import: ast::ImportDeclaration) -> Option<Spanned<Arc<str>>> {
+        let path = required_field!(import => path ? self: "expected path to import" => None);
+        let span = Span::from(path.text_range());
+        let text = path.text();
+        // strip the leading and trailing `"` characters
+        let content = &text[1..text.len() - 1];
+        let mut bytes = Vec::new();
+
+        if let Err((msg, _)) = str_lit_content_to_bytes(content, &mut bytes) {
+            self.error(msg, span);
+            return None;
+        }
+        let Ok(path) = String::from_utf8(bytes) else {
+            self.error("import paths must be valid UTF-8", span);
+            return None;
+        };
+
+        Some(Spanned {
+            inner: Arc::from(path),
+            span,
+        })
     }
 
     /// Lowers the given AST `struct` field to IR.
@@ -89,18 +292,142 @@ impl LoweringCtx {
             .expected()
             .map(|expected| self.lower_expr(expected));
 
+        let name = Spanned::<Symbol>::from(
+            required_field!(struct_field => name ? self: "expected name for `struct` field" => None),
+        );
+        self.check_not_constant(&name);
+
+        let Attributes {
+            classification,
+            color,
+        } = self.lower_attributes(struct_field.attribute());
+
         Some(StructField {
-            name: Spanned::<Symbol>::from(
-                required_field!(struct_field => name ? self: "expected name for `struct` field" => None),
-            ),
+            name,
             ty: self.lower_parse_type(
                 required_field!(struct_field => parse_type ? self: "expected parse type for `struct` field" => None),
                 &expected,
             ),
             expected,
+            classification,
+            color,
+            doc: struct_field.doc_comment().map(Arc::from),
         })
     }
 
+    /// Lowers the given AST attributes.
+    fn lower_attributes(&mut self, attributes: impl Iterator<Item = ast::Attribute>) -> Attributes {
+        let mut lowered = Attributes::default();
+
+        for attribute in attributes {
+            let span = attribute.span();
+            match self.lower_attribute(attribute) {
+                Some(Attribute::Classification(classification)) => {
+                    if lowered.classification.is_some() {
+                        self.error("a field can only have one classification", span);
+                    }
+                    lowered.classification = Some(classification);
+                }
+                Some(Attribute::Color(color)) => {
+                    if lowered.color.is_some() {
+                        self.error("only one color can be given", span);
+                    }
+                    lowered.color = Some(color);
+                }
+                None => (),
+            }
+        }
+
+        lowered
+    }
+
+    /// Lowers the given AST attribute to IR.
+    fn lower_attribute(&mut self, attribute: ast::Attribute) -> Option<Attribute> {
+        let name = required_field!(attribute => name ? self: "expected attribute name" => None);
+        let args: Vec<_> = attribute.args().collect();
+
+        let classification = match name.text() {
+            "offset" => Classification::Offset,
+            "length" => Classification::Length,
+            "guid" => Classification::Guid,
+            "color" => {
+                let [arg] = &args[..] else {
+                    self.error("expected the color as the only argument", attribute.span());
+                    return None;
+                };
+                let color = match arg {
+                    ast::Expr::Atom(atom) => match (atom.child_kind(), atom.child()) {
+                        (Some(TokenKind::Identifier), Some(token)) => {
+                            Some(Color::Category(Symbol::from(token)))
+                        }
+                        (Some(TokenKind::StringLiteral), Some(token)) => {
+                            Color::from_hex(token.text().trim_matches('"'))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let Some(color) = color else {
+                    self.error(
+                        "expected a color of the form `\"#rrggbb\"` or a category name",
+                        arg.span(),
+                    );
+                    return None;
+                };
+
+                return Some(Attribute::Color(color));
+            }
+            "timestamp" => {
+                let [encoding] = &args[..] else {
+                    self.error(
+                        "expected the timestamp encoding as the only argument",
+                        attribute.span(),
+                    );
+                    return None;
+                };
+                let name = match encoding {
+                    ast::Expr::Atom(atom) if atom.child_kind() == Some(TokenKind::Identifier) => {
+                        atom.child()
+                    }
+                    _ => None,
+                };
+                let Some(encoding) =
+                    name.and_then(|name| TimestampEncoding::from_name(name.text()))
+                else {
+                    let supported: Vec<_> = TimestampEncoding::ALL
+                        .iter()
+                        .map(|encoding| format!("`{}`", encoding.name()))
+                        .collect();
+                    self.error(
+                        format!(
+                            "unknown timestamp encoding, expected one of {}",
+                            supported.join(", ")
+                        ),
+                        encoding.span(),
+                    );
+                    return None;
+                };
+
+                return Some(Attribute::Classification(Classification::Timestamp(
+                    encoding,
+                )));
+            }
+            name => {
+                self.error(format!("unknown attribute `{name}`"), attribute.span());
+                return None;
+            }
+        };
+
+        if !args.is_empty() {
+            self.error(
+                format!("attribute `{}` does not take arguments", name.text()),
+                attribute.span(),
+            );
+        }
+
+        Some(Attribute::Classification(classification))
+    }
+
     /// Lowers the given AST parse type to IR.
     fn lower_parse_type(
         &mut self,
@@ -124,6 +451,23 @@ impl LoweringCtx {
                 let name_token = required_field!(named_parse_type => name ? self: "expected parse type" => ParseTypeKind::Error);
 
                 let name = name_token.text();
+                let args: Vec<_> = named_parse_type
+                    .args()
+                    .map(|arg| self.lower_expr(arg))
+                    .collect();
+                let is_builtin = ((name.starts_with("i") || name.starts_with("u"))
+                    && name[1..].parse::<u32>().is_ok())
+                    || matches!(name, "uleb128" | "sleb128" | "f32" | "f64")
+                    || matches!(name, "guid")
+                    || timestamp_type(name).is_some();
+                if is_builtin && !args.is_empty() {
+                    self.error(
+                        format!("`{name}` does not take arguments"),
+                        named_parse_type.span(),
+                    );
+                    return ParseTypeKind::Error;
+                }
+
                 if (name.starts_with("i") || name.starts_with("u"))
                     && let Ok(num_bits) = name[1..].parse::<u32>()
                 {
@@ -131,9 +475,41 @@ impl LoweringCtx {
                         bit_width: num_bits,
                         signed: name.starts_with("i"),
                     }
+                } else if name == "uleb128" || name == "sleb128" {
+                    ParseTypeKind::Leb128 {
+                        signed: name == "sleb128",
+                    }
+                } else if name == "f32" || name == "f64" {
+                    ParseTypeKind::Float {
+                        bit_width: if name == "f32" { 32 } else { 64 },
+                    }
+                } else if let Some((bit_width, signed, encoding)) = timestamp_type(name) {
+                    ParseTypeKind::Classified {
+                        parse_type: Box::new(ParseType {
+                            kind: ParseTypeKind::Integer { bit_width, signed },
+                            span: named_parse_type.span(),
+                        }),
+                        classification: Classification::Timestamp(encoding),
+                    }
+                } else if name == "guid" {
+                    ParseTypeKind::Classified {
+                        parse_type: Box::new(ParseType {
+                            kind: ParseTypeKind::Bytes {
+                                repetition_kind: RepeatKind::Len {
+                                    count: Expr {
+                                        kind: ExprKind::Lit(Lit::Int(Int::from(16))),
+                                        span: named_parse_type.span(),
+                                    },
+                                },
+                            },
+                            span: named_parse_type.span(),
+                        }),
+                        classification: Classification::Guid,
+                    }
                 } else {
                     ParseTypeKind::Named {
                         name: Spanned::<Symbol>::from(name_token),
+                        args,
                     }
                 }
             }
@@ -154,13 +530,24 @@ impl LoweringCtx {
                     self.lower_repetition(repeat_decl)
                 } else {
                     let expected = expected.as_ref().parser_expect();
-                    let ExprKind::Lit(Lit::Bytes(bytes)) = &expected.kind else {
-                        todo!()
+                    let len = match &expected.kind {
+                        ExprKind::Lit(Lit::Bytes(bytes)) => Some(bytes.len()),
+                        ExprKind::Lit(Lit::BytePattern(pattern)) => Some(pattern.len()),
+                        _ => None,
                     };
-                    RepeatKind::Len {
-                        count: Expr {
-                            kind: ExprKind::Lit(Lit::Int(Int::from(bytes.len()))),
-                            span: expected.span
+                    match len {
+                        Some(len) => RepeatKind::Len {
+                            count: Expr {
+                                kind: ExprKind::Lit(Lit::Int(Int::from(len))),
+                                span: expected.span
+                            }
+                        },
+                        None => {
+                            self.error(
+                                "`bytes` without a length must be expected to be a bytes literal",
+                                expected.span,
+                            );
+                            RepeatKind::Error
                         }
                     }
                 };
@@ -180,8 +567,10 @@ impl LoweringCtx {
             }
             ast::ParseType::AnonymousStructParseType(struct_parse_type) => {
                 ParseTypeKind::Struct {
-                    content: required_field!(struct_parse_type => struct_block ? self: "expected struct block" => ParseTypeKind::Error)
-                        .struct_content().map(|content| self.lower_struct_content(content)).collect(),
+                    content: self.lower_struct_block(
+                        required_field!(struct_parse_type => struct_block ? self: "expected struct block" => ParseTypeKind::Error)
+                            .struct_content(),
+                    ),
                 }
             }
             ast::ParseType::SwitchParseType(switch_parse_type) => {
@@ -214,6 +603,186 @@ impl LoweringCtx {
 
                 ParseTypeKind::Switch { scrutinee, branches, default }
             }
+            ast::ParseType::BitfieldParseType(bitfield_parse_type) => {
+                self.lower_bitfield(bitfield_parse_type)
+            }
+            ast::ParseType::EnumParseType(enum_parse_type) => self.lower_enum(enum_parse_type),
+            ast::ParseType::FlagsParseType(flags_parse_type) => self.lower_flags(flags_parse_type),
+            ast::ParseType::PointerParseType(pointer_parse_type) => {
+                self.lower_pointer(pointer_parse_type)
+            }
+        }
+    }
+
+    /// Lowers the given AST bitfield parse type into an IR parse type kind.
+    fn lower_bitfield(&mut self, bitfield: ast::BitfieldParseType) -> ParseTypeKind {
+        let ty =
+            required_field!(bitfield => ty ? self: "expected integer type" => ParseTypeKind::Error);
+        let ty_span = ty.span();
+        let bit_width = match self.lower_parse_type_kind(ty, &None) {
+            ParseTypeKind::Integer {
+                bit_width,
+                signed: false,
+            } if bit_width % 8 == 0 && (8..=128).contains(&bit_width) => bit_width,
+            _ => {
+                self.error(
+                    "bitfields must be stored in unsigned integers of 8 to 128 bits that are a whole number of bytes",
+                    ty_span,
+                );
+                return ParseTypeKind::Error;
+            }
+        };
+
+        let mut fields = Vec::new();
+        let mut used_bits = 0;
+        for field in bitfield.bitfield_field() {
+            let name = Spanned::<Symbol>::from(
+                required_field!(field => name ? self: "expected field name" => ParseTypeKind::Error),
+            );
+            let ty = required_field!(field => ty ? self: "expected integer type" => ParseTypeKind::Error);
+            let ty_span = ty.span();
+            let ParseTypeKind::Integer {
+                bit_width: field_bit_width,
+                signed,
+            } = self.lower_parse_type_kind(ty, &None)
+            else {
+                self.error("bitfield fields must be integers such as `u3`", ty_span);
+                return ParseTypeKind::Error;
+            };
+
+            if field_bit_width == 0 {
+                self.error("bitfield fields must be at least one bit wide", ty_span);
+                return ParseTypeKind::Error;
+            }
+            used_bits += field_bit_width;
+            if used_bits > bit_width {
+                self.error(
+                    format!("the fields of the bitfield do not fit into {bit_width} bits"),
+                    ty_span,
+                );
+                return ParseTypeKind::Error;
+            }
+
+            fields.push(BitfieldField {
+                name,
+                bit_width: field_bit_width,
+                signed,
+            });
+        }
+
+        ParseTypeKind::Bitfield { bit_width, fields }
+    }
+
+    /// Lowers the given AST enum parse type into an IR parse type kind.
+    fn lower_enum(&mut self, enum_parse_type: ast::EnumParseType) -> ParseTypeKind {
+        let parse_type = self.lower_parse_type(
+            required_field!(enum_parse_type => ty ? self: "expected integer type" => ParseTypeKind::Error),
+            &None,
+        );
+        if !matches!(
+            parse_type.kind,
+            ParseTypeKind::Integer { .. }
+                | ParseTypeKind::DynamicInteger { .. }
+                | ParseTypeKind::Leb128 { .. }
+        ) {
+            self.error("enums must be integers such as `u16`", parse_type.span);
+            return ParseTypeKind::Error;
+        }
+
+        let mut names = Vec::new();
+        for arm in enum_parse_type.enum_arm() {
+            let value = self.lower_expr(
+                required_field!(arm => val ? self: "expected arm value" => ParseTypeKind::Error),
+            );
+            let name = self.lower_expr(
+                required_field!(arm => name ? self: "expected arm name" => ParseTypeKind::Error),
+            );
+
+            let ExprKind::Lit(Lit::Int(value)) = value.kind else {
+                self.error("expected integer literal", value.span);
+                continue;
+            };
+            let ExprKind::Lit(Lit::Bytes(bytes)) = &name.kind else {
+                self.error("expected string literal", name.span);
+                continue;
+            };
+            let Ok(name) = std::str::from_utf8(bytes) else {
+                self.error("names of enum values must be valid UTF-8", name.span);
+                continue;
+            };
+
+            names.push((value, Arc::from(name)));
+        }
+
+        ParseTypeKind::Enum {
+            parse_type: Box::new(parse_type),
+            names,
+        }
+    }
+
+    /// Lowers the given AST flags parse type into an IR parse type kind.
+    fn lower_flags(&mut self, flags_parse_type: ast::FlagsParseType) -> ParseTypeKind {
+        let parse_type = self.lower_parse_type(
+            required_field!(flags_parse_type => ty ? self: "expected integer type" => ParseTypeKind::Error),
+            &None,
+        );
+        if !is_unsigned_integer(&parse_type.kind) {
+            self.error(
+                "flags must be unsigned integers such as `u16`",
+                parse_type.span,
+            );
+            return ParseTypeKind::Error;
+        }
+
+        let mut flags = Vec::new();
+        for arm in flags_parse_type.flags_arm() {
+            let mask = self.lower_expr(
+                required_field!(arm => mask ? self: "expected bit mask" => ParseTypeKind::Error),
+            );
+            let name = Symbol::from(
+                required_field!(arm => name ? self: "expected flag name" => ParseTypeKind::Error),
+            );
+
+            let ExprKind::Lit(Lit::Int(mask_value)) = mask.kind else {
+                self.error("expected integer literal", mask.span);
+                continue;
+            };
+            if mask_value.sign() != num_bigint::Sign::Plus {
+                self.error("bit masks of flags must be positive", mask.span);
+                continue;
+            }
+
+            flags.push((mask_value, name));
+        }
+
+        ParseTypeKind::Flags {
+            parse_type: Box::new(parse_type),
+            flags,
+        }
+    }
+
+    /// Lowers the given AST pointer parse type into an IR parse type kind.
+    fn lower_pointer(&mut self, pointer_parse_type: ast::PointerParseType) -> ParseTypeKind {
+        let target = self.lower_parse_type(
+            required_field!(pointer_parse_type => target ? self: "expected target type" => ParseTypeKind::Error),
+            &None,
+        );
+        let parse_type = self.lower_parse_type(
+            required_field!(pointer_parse_type => ty ? self: "expected integer type" => ParseTypeKind::Error),
+            &None,
+        );
+        if !is_unsigned_integer(&parse_type.kind) {
+            self.error(
+                "pointers must be unsigned integers such as `u32`",
+                parse_type.span,
+            );
+            return ParseTypeKind::Error;
+        }
+
+        ParseTypeKind::Pointer {
+            parse_type: Box::new(parse_type),
+            target: Box::new(target),
+            base: pointer_parse_type.base().map(|base| self.lower_expr(base)),
         }
     }
 
@@ -234,6 +803,13 @@ impl LoweringCtx {
                     ),
                 }
             }
+            ast::RepeatDecl::RepeatUntilDecl(repeat_until_decl) => {
+                RepeatKind::Until {
+                    terminator: self.lower_expr(
+                        required_field!(repeat_until_decl => terminator ? self: "expected terminator expression" => RepeatKind::Error)
+                    ),
+                }
+            }
         }
     }
 
@@ -251,11 +827,34 @@ impl LoweringCtx {
             ast::Expr::Atom(atom) => self.lower_atom(atom),
             ast::Expr::Metavar(metavar) => {
                 let name = required_field!(metavar => name ? self: "expected variable name" => ExprKind::Error);
+                if let Some(arg) = metavar.arg() {
+                    let arg = Box::new(self.lower_expr(arg));
+
+                    return match name.text() {
+                        "startof" => ExprKind::StartOf(arg),
+                        "sizeof" => ExprKind::SizeOf(arg),
+                        var => {
+                            self.error(
+                                format!("metavariable `{var}` does not take an argument"),
+                                metavar.span(),
+                            );
+                            ExprKind::Error
+                        }
+                    };
+                }
                 match name.text() {
                     "offset" => ExprKind::Offset,
+                    "end" => ExprKind::End,
                     "parent" => ExprKind::Parent,
                     "last" => ExprKind::Last,
                     "len" => ExprKind::Len,
+                    var @ ("startof" | "sizeof") => {
+                        self.error(
+                            format!("metavariable `{var}` expects a field as its argument"),
+                            metavar.span(),
+                        );
+                        ExprKind::Error
+                    }
                     var => {
                         self.error(format!("unknown metavariable: {var}"), metavar.span());
                         ExprKind::Error
@@ -264,9 +863,28 @@ impl LoweringCtx {
             }
             ast::Expr::ByteConcat(byte_concat) => {
                 let mut out = Vec::new();
+                let mut mask = Vec::new();
+                // a wildcard splits a byte into multiple tokens, so the digits are collected first
+                let mut digits = String::new();
 
                 for part in byte_concat.tokens() {
-                    match part.kind().expect_token() {
+                    let kind = part.kind().expect_token();
+                    let is_digits = matches!(
+                        kind,
+                        TokenKind::ByteLiteral
+                            | TokenKind::DecimalIntegerLiteral
+                            | TokenKind::Identifier
+                            | TokenKind::QuestionMark
+                    );
+                    if !is_digits && !digits.is_empty() {
+                        self.error(
+                            "expected hex byte literal to be of length two",
+                            Span::from(part.text_range()),
+                        );
+                        return ExprKind::Error;
+                    }
+
+                    match kind {
                         // Ignore surrounding tokens
                         TokenKind::LAngle | TokenKind::RAngle => (),
                         token if token.is_trivia() => (),
@@ -280,12 +898,13 @@ impl LoweringCtx {
                                 self.error(msg, Span::from(part.text_range()));
                                 return ExprKind::Error;
                             }
+                            mask.resize(out.len(), 0xff);
                         }
-                        TokenKind::ByteLiteral
-                        | TokenKind::DecimalIntegerLiteral
-                        | TokenKind::Identifier => {
-                            let text = part.text();
-                            if text.len() != 2 {
+                        _ if is_digits => {
+                            digits.push_str(part.text());
+                            if digits.len() < 2 {
+                                continue;
+                            } else if digits.len() > 2 {
                                 self.error(
                                     "expected hex byte literal to be of length two",
                                     Span::from(part.text_range()),
@@ -293,26 +912,44 @@ impl LoweringCtx {
                                 return ExprKind::Error;
                             }
 
+                            // returns the value of the nibble and the mask of its specified bits
                             let to_val = |c: char| {
-                                c.to_digit(16)
+                                if c == '?' {
+                                    return (0, 0);
+                                }
+
+                                let val = c
+                                    .to_digit(16)
                                     .map(|val| {
                                         u8::try_from(val)
                                             .expect("a single hex digit cannot exceed a u8")
                                     })
-                                    .parser_expect()
+                                    .parser_expect();
+                                (val, 0xf)
                             };
 
-                            let mut iter = text.chars();
-                            let most_significant_nibble = to_val(iter.next().parser_expect());
-                            let least_significant_nibble = to_val(iter.next().parser_expect());
+                            let mut iter = digits.chars();
+                            let (most_significant_nibble, most_significant_mask) =
+                                to_val(iter.next().parser_expect());
+                            let (least_significant_nibble, least_significant_mask) =
+                                to_val(iter.next().parser_expect());
 
                             out.push(most_significant_nibble << 4 | least_significant_nibble);
+                            mask.push(most_significant_mask << 4 | least_significant_mask);
+                            digits.clear();
                         }
                         _ => parser_unreachable!(),
                     }
                 }
 
-                ExprKind::Lit(Lit::Bytes(out.into()))
+                if mask.iter().all(|&mask| mask == 0xff) {
+                    ExprKind::Lit(Lit::Bytes(out.into()))
+                } else {
+                    ExprKind::Lit(Lit::BytePattern(BytePattern {
+                        bytes: out.into(),
+                        mask: mask.into(),
+                    }))
+                }
             }
             ast::Expr::ParenExpr(paren_expr) => paren_expr
                 .expr()
@@ -323,6 +960,12 @@ impl LoweringCtx {
             ast::Expr::FieldAccess(field_access) => self.lower_field_access(field_access),
             ast::Expr::PeekExpr(peek_expr) => self.lower_peek_expr(peek_expr),
             ast::Expr::ConcatExpr(concat_expr) => self.lower_concat_expr(concat_expr),
+            ast::Expr::CallExpr(call_expr) => self.lower_call_expr(call_expr),
+            ast::Expr::MethodCallExpr(method_call_expr) => {
+                self.lower_method_call_expr(method_call_expr)
+            }
+            ast::Expr::IndexExpr(index_expr) => self.lower_index_expr(index_expr),
+            ast::Expr::SliceExpr(slice_expr) => self.lower_slice_expr(slice_expr),
         }
     }
 
@@ -351,6 +994,10 @@ impl LoweringCtx {
                 let int = int_from_str(16, text).parser_expect();
                 ExprKind::Lit(Lit::Int(int))
             }
+            TokenKind::FloatLiteral => {
+                let float = token.text().parse().ok().parser_expect();
+                ExprKind::Lit(Lit::Float(float))
+            }
             TokenKind::StringLiteral => {
                 let text = token.text();
                 // strip the leading and trailing `"` characters
@@ -366,7 +1013,13 @@ impl LoweringCtx {
             }
             TokenKind::TrueKw => ExprKind::Lit(Lit::Bool(true)),
             TokenKind::FalseKw => ExprKind::Lit(Lit::Bool(false)),
-            TokenKind::Identifier => ExprKind::VarUse(Spanned::<Symbol>::from(token)),
+            TokenKind::Identifier => {
+                let name = Spanned::<Symbol>::from(token);
+                match self.consts.get(&name.inner) {
+                    Some(value) => ExprKind::Lit(value.clone()),
+                    None => ExprKind::VarUse(name),
+                }
+            }
             _ => parser_unreachable!(),
         }
     }
@@ -474,6 +1127,80 @@ impl LoweringCtx {
         ExprKind::Concat { args }
     }
 
+    /// Lowers the given AST function call expression to IR.
+    fn lower_call_expr(&mut self, call_expr: ast::CallExpr) -> ExprKind {
+        let name =
+            required_field!(call_expr => name ? self: "expected function name" => ExprKind::Error);
+        let args = call_expr.args().map(|arg| self.lower_expr(arg)).collect();
+
+        self.lower_call(name, args, call_expr.span())
+    }
+
+    /// Lowers the given AST method call expression to IR.
+    fn lower_method_call_expr(&mut self, method_call_expr: ast::MethodCallExpr) -> ExprKind {
+        let name = required_field!(method_call_expr => name ? self: "expected function name" => ExprKind::Error);
+        // the receiver is the first argument of the call
+        let args = method_call_expr
+            .args()
+            .map(|arg| self.lower_expr(arg))
+            .collect();
+
+        self.lower_call(name, args, method_call_expr.span())
+    }
+
+    /// Lowers a call of the function with the given name to IR.
+    fn lower_call(&mut self, name: SyntaxToken, args: Vec<Expr>, span: Span) -> ExprKind {
+        let Some(func) = Builtin::from_name(name.text()) else {
+            self.error(
+                format!("unknown function: {}", name.text()),
+                Span::from(name.text_range()),
+            );
+            return ExprKind::Error;
+        };
+
+        if args.len() != func.arity() {
+            self.error(
+                format!(
+                    "`{}` takes {} arguments, but {} were given",
+                    func.name(),
+                    func.arity(),
+                    args.len()
+                ),
+                span,
+            );
+            return ExprKind::Error;
+        }
+
+        ExprKind::Call { func, args }
+    }
+
+    /// Lowers the given AST indexing expression to IR.
+    fn lower_index_expr(&mut self, index_expr: ast::IndexExpr) -> ExprKind {
+        let expr = index_expr.base().parser_expect();
+        let index =
+            required_field!(index_expr => index ? self: "expected index" => ExprKind::Error);
+
+        ExprKind::Index {
+            expr: Box::new(self.lower_expr(expr)),
+            index: Box::new(self.lower_expr(index)),
+        }
+    }
+
+    /// Lowers the given AST slicing expression to IR.
+    fn lower_slice_expr(&mut self, slice_expr: ast::SliceExpr) -> ExprKind {
+        let expr = slice_expr.base().parser_expect();
+        let start =
+            required_field!(slice_expr => start ? self: "expected slice start" => ExprKind::Error);
+        let end =
+            required_field!(slice_expr => end ? self: "expected slice end" => ExprKind::Error);
+
+        ExprKind::Slice {
+            expr: Box::new(self.lower_expr(expr)),
+            start: Box::new(self.lower_expr(start)),
+            end: Box::new(self.lower_expr(end)),
+        }
+    }
+
     /// Lowers the given AST declaration to IR.
     fn lower_declaration(&mut self, declaration: ast::Declaration) -> Option<Declaration> {
         match declaration {
@@ -491,12 +1218,22 @@ impl LoweringCtx {
             ast::Declaration::ScopeInDeclaration(scope_in) => {
                 self.lower_scope_in_declaration(scope_in)
             }
+            ast::Declaration::ScopeDecompressedDeclaration(scope_decompressed) => {
+                self.lower_scope_decompressed_declaration(scope_decompressed)
+            }
             ast::Declaration::IfDeclaration(if_decl) => self.lower_if_declaration(if_decl),
             ast::Declaration::AssertDeclaration(assert) => self.lower_assert_declaration(assert),
             ast::Declaration::WarnIfDeclaration(warn_if) => self.lower_warn_if_declaration(warn_if),
+            ast::Declaration::VerifyDeclaration(verify) => self.lower_verify_declaration(verify),
             ast::Declaration::RecoveryDeclaration(recovery) => {
                 self.lower_recovery_declaration(recovery)
             }
+            ast::Declaration::RecoveryScanDeclaration(recovery) => {
+                self.lower_recovery_scan_declaration(recovery)
+            }
+            ast::Declaration::ImportDeclaration(_) => {
+                unreachable!("imports are recorded when lowering the `struct` content")
+            }
         }
     }
 
@@ -505,14 +1242,20 @@ impl LoweringCtx {
         &mut self,
         endianness_declaration: ast::EndiannessDeclaration,
     ) -> Option<Declaration> {
-        let token =
-            required_field!(endianness_declaration => kind ? self: "expected `be` or `le`" => None);
+        let token = required_field!(endianness_declaration => kind ? self: "expected `le`, `be`, `pdp`, `be_ws16`, `le_ws32` or `be_ws32`" => None);
 
         let endianness = match token.text() {
             "le" => Endianness::Little,
             "be" => Endianness::Big,
+            "pdp" => Endianness::Pdp,
+            "be_ws16" => Endianness::BigWordSwapped16,
+            "le_ws32" => Endianness::LittleWordSwapped32,
+            "be_ws32" => Endianness::BigWordSwapped32,
             _ => {
-                self.error("expected `be` or `le`", endianness_declaration.span());
+                self.error(
+                    "expected `le`, `be`, `pdp`, `be_ws16`, `le_ws32` or `be_ws32`",
+                    endianness_declaration.span(),
+                );
                 return None;
             }
         };
@@ -559,15 +1302,9 @@ impl LoweringCtx {
             required_field!(scope_at => start ? self: "expected scope start offset" => None),
         );
         let end = scope_at.end().map(|expr| self.lower_expr(expr));
-        let mut content = Vec::new();
-
-        for single_content in scope_at
-            .struct_block()
-            .iter()
-            .flat_map(|block| block.struct_content())
-        {
-            content.push(self.lower_struct_content(single_content));
-        }
+        let block = scope_at.struct_block();
+        let content =
+            self.lower_struct_block(block.iter().flat_map(|block| block.struct_content()));
 
         Some(Declaration::Scope {
             kind: ScopeKind::At { start, end },
@@ -583,15 +1320,9 @@ impl LoweringCtx {
         let bytes = self.lower_expr(
             required_field!(scope_in => bytes ? self: "expected scope bytes expression" => None),
         );
-        let mut content = Vec::new();
-
-        for single_content in scope_in
-            .struct_block()
-            .iter()
-            .flat_map(|block| block.struct_content())
-        {
-            content.push(self.lower_struct_content(single_content));
-        }
+        let block = scope_in.struct_block();
+        let content =
+            self.lower_struct_block(block.iter().flat_map(|block| block.struct_content()));
 
         Some(Declaration::Scope {
             kind: ScopeKind::In { bytes },
@@ -599,6 +1330,40 @@ impl LoweringCtx {
         })
     }
 
+    /// Lowers the given AST `scope decompressed` declaration to IR.
+    fn lower_scope_decompressed_declaration(
+        &mut self,
+        scope_decompressed: ast::ScopeDecompressedDeclaration,
+    ) -> Option<Declaration> {
+        let compression_token = required_field!(scope_decompressed => compression ? self: "expected compression format" => None);
+        let Some(compression) = Compression::from_name(compression_token.text()) else {
+            let supported: Vec<_> = Compression::ALL
+                .iter()
+                .map(|compression| format!("`{}`", compression.name()))
+                .collect();
+            self.error(
+                format!(
+                    "unknown compression format `{}`, expected one of {}",
+                    compression_token.text(),
+                    supported.join(", ")
+                ),
+                Span::from(compression_token.text_range()),
+            );
+            return None;
+        };
+        let bytes = self.lower_expr(
+            required_field!(scope_decompressed => bytes ? self: "expected scope bytes expression" => None),
+        );
+        let block = scope_decompressed.struct_block();
+        let content =
+            self.lower_struct_block(block.iter().flat_map(|block| block.struct_content()));
+
+        Some(Declaration::Scope {
+            kind: ScopeKind::Decompressed { compression, bytes },
+            content,
+        })
+    }
+
     /// Lowers the given AST `if` declaration to IR.
     fn lower_if_declaration(&mut self, if_decl: ast::IfDeclaration) -> Option<Declaration> {
         Some(Declaration::If(self.lower_if_chain(
@@ -611,22 +1376,22 @@ impl LoweringCtx {
         let condition = self.lower_expr(
             required_field!(if_chain => condition ? self: "expected if condition" => None),
         );
-        let then_block = required_field!(if_chain => then_block ? self: "expected block" => None)
-            .struct_content()
-            .map(|content| self.lower_struct_content(content))
-            .collect();
+        let then_block = self.lower_struct_block(
+            required_field!(if_chain => then_block ? self: "expected block" => None)
+                .struct_content(),
+        );
 
         let else_part = if_chain.else_part().and_then(|else_part| {
             Some(match else_part {
                 ast::ElsePart::IfChain(if_chain) => {
                     ElsePart::IfChain(Box::new(self.lower_if_chain(if_chain)?))
                 }
-                ast::ElsePart::ElseBlock(else_block) => ElsePart::ElseBlock(
-                    required_field!(else_block => struct_block ? self: "expected block" => None)
-                        .struct_content()
-                        .map(|content| self.lower_struct_content(content))
-                        .collect(),
-                ),
+                ast::ElsePart::ElseBlock(else_block) => {
+                    ElsePart::ElseBlock(self.lower_struct_block(
+                        required_field!(else_block => struct_block ? self: "expected block" => None)
+                            .struct_content(),
+                    ))
+                }
             })
         });
 
@@ -658,24 +1423,68 @@ impl LoweringCtx {
         })
     }
 
+    /// Lowers the given AST `verify` declaration to IR.
+    fn lower_verify_declaration(&mut self, verify: ast::VerifyDeclaration) -> Option<Declaration> {
+        let comparison = self.lower_expr(
+            required_field!(verify => expr ? self: "expected checksum comparison" => None),
+        );
+        let ExprKind::BinOp {
+            op: BinOp::Eq,
+            lhs,
+            rhs,
+        } = comparison.kind
+        else {
+            self.error(
+                "expected a comparison of a computed and an expected checksum with `==`",
+                comparison.span,
+            );
+            return None;
+        };
+
+        Some(Declaration::Verify {
+            computed: *lhs,
+            expected: *rhs,
+            message: verify.message().map(|expr| self.lower_expr(expr)),
+        })
+    }
+
     /// Lowers the given AST `recover` declaration to IR.
     fn lower_recovery_declaration(
         &mut self,
         recovery: ast::RecoveryDeclaration,
     ) -> Option<Declaration> {
         Some(Declaration::Recover {
-            at: self.lower_expr(
-                required_field!(recovery => expr ? self: "expected expression" => None),
-            ),
+            kind: RecoveryKind::At {
+                offset: self.lower_expr(
+                    required_field!(recovery => expr ? self: "expected expression" => None),
+                ),
+            },
+        })
+    }
+
+    /// Lowers the given AST `recover scan` declaration to IR.
+    fn lower_recovery_scan_declaration(
+        &mut self,
+        recovery: ast::RecoveryScanDeclaration,
+    ) -> Option<Declaration> {
+        Some(Declaration::Recover {
+            kind: RecoveryKind::Scan {
+                pattern: self.lower_expr(
+                    required_field!(recovery => pattern ? self: "expected pattern" => None),
+                ),
+            },
         })
     }
 
     /// Lowers the given AST `let` statement to IR.
     fn lower_let_statement(&mut self, let_statement: ast::LetStatement) -> Option<LetStatement> {
+        let name = Spanned::<Symbol>::from(
+            required_field!(let_statement => name ? self: "expected name" => None),
+        );
+        self.check_not_constant(&name);
+
         Some(LetStatement {
-            name: Spanned::<Symbol>::from(
-                required_field!(let_statement => name ? self: "expected name" => None),
-            ),
+            name,
             expr: self.lower_expr(
                 required_field!(let_statement => expr ? self: "expected expression" => None),
             ),
@@ -683,6 +1492,59 @@ impl LoweringCtx {
     }
 }
 
+/// Determines if the given expression can be evaluated without any input.
+///
+/// Byte patterns cannot be evaluated, so they are not constant.
+fn is_constant(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Lit(Lit::BytePattern(_)) => false,
+        ExprKind::Lit(_) => true,
+        ExprKind::UnOp { operand, .. } => is_constant(operand),
+        ExprKind::BinOp { lhs, rhs, .. } => is_constant(lhs) && is_constant(rhs),
+        ExprKind::Concat { args } => args.iter().all(|arg| match arg {
+            ConcatArg::Direct(arg) | ConcatArg::Expanding(arg) => is_constant(arg),
+        }),
+        ExprKind::Call { args, .. } => args.iter().all(is_constant),
+        ExprKind::Index { expr, index } => is_constant(expr) && is_constant(index),
+        ExprKind::Slice { expr, start, end } => {
+            is_constant(expr) && is_constant(start) && is_constant(end)
+        }
+        ExprKind::VarUse(_)
+        | ExprKind::Offset
+        | ExprKind::End
+        | ExprKind::Parent
+        | ExprKind::Last
+        | ExprKind::Len
+        | ExprKind::StartOf(_)
+        | ExprKind::SizeOf(_)
+        | ExprKind::FieldAccess { .. }
+        | ExprKind::Peek { .. }
+        | ExprKind::Error => false,
+    }
+}
+
+/// Determines if the parse type kind parses an unsigned integer.
+fn is_unsigned_integer(kind: &ParseTypeKind) -> bool {
+    matches!(
+        kind,
+        ParseTypeKind::Integer { signed: false, .. }
+            | ParseTypeKind::DynamicInteger { signed: false, .. }
+            | ParseTypeKind::Leb128 { signed: false }
+    )
+}
+
+/// Returns the bit width, signedness and encoding of the built-in timestamp type with the given name.
+fn timestamp_type(name: &str) -> Option<(u32, bool, TimestampEncoding)> {
+    Some(match name {
+        "filetime" => (64, false, TimestampEncoding::Filetime),
+        "unixtime32" => (32, true, TimestampEncoding::Unix),
+        "unixtime64" => (64, true, TimestampEncoding::Unix),
+        "dostime" => (32, false, TimestampEncoding::Dos),
+        "hfs_time" => (32, false, TimestampEncoding::Hfs),
+        _ => return None,
+    })
+}
+
 /// An extension trait to unwrap with a message that a situation should be impossible because of
 /// the parser.
 trait ParserImpossible {
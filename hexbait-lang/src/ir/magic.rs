@@ -0,0 +1,262 @@
+//! Implements finding the magic bytes that a format description expects at a fixed offset.
+//!
+//! The magic bytes make it possible to search for instances of a format in a larger input
+//! without attempting to parse at every offset.
+
+use std::{collections::HashMap, ops::ControlFlow};
+
+use hexbait_common::Endianness;
+use num_bigint::Sign;
+
+use super::{
+    BinOp, BytePattern, Declaration, Expr, ExprKind, File, Lit, NamedStruct, ParseType,
+    ParseTypeKind, RepeatKind, StructContent, Symbol,
+};
+
+/// The maximum nesting depth of named `struct`s that is followed, to handle recursive `struct`s.
+const MAX_DEPTH: usize = 16;
+
+/// Bytes that every input matching a format description has at a fixed offset.
+#[derive(Debug, Clone)]
+pub struct LeadingMagic {
+    /// The offset of the magic bytes from the start of the format.
+    pub offset: u64,
+    /// The pattern that the magic bytes match.
+    pub pattern: BytePattern,
+}
+
+/// Returns the first magic bytes that the format description expects at a fixed offset.
+///
+/// The magic bytes are taken from the expected value of a field or from an `!assert` that
+/// compares a field with a literal. Only fields whose offset is known without parsing the input
+/// are considered, so the search stops at the first field of a dynamic size or at an `!if`.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait_lang::{ir::{leading_magic, lower_file}, parse};
+/// let ir = lower_file(parse("!endian be; len u16; version u16; !assert version == 3;").ast);
+/// let magic = leading_magic(&ir).unwrap();
+///
+/// assert_eq!(magic.offset, 2);
+/// assert!(magic.pattern.matches(&[0x00, 0x03]));
+/// ```
+pub fn leading_magic(file: &File) -> Option<LeadingMagic> {
+    let mut finder = MagicFinder {
+        structs: &file.structs,
+        depth: 0,
+    };
+
+    match finder.content(&file.content, &mut 0, Endianness::Little) {
+        ControlFlow::Break(magic) => magic,
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+/// Walks the fields at fixed offsets at the start of a format description.
+///
+/// Walking breaks with the magic bytes once they are found and with `None` once the offset of
+/// the following fields depends on the input.
+struct MagicFinder<'file> {
+    /// The named `struct`s that can be parsed.
+    structs: &'file HashMap<Symbol, NamedStruct>,
+    /// The number of named `struct`s that are currently walked.
+    depth: usize,
+}
+
+impl MagicFinder<'_> {
+    /// Walks the content of a `struct` that starts at `offset`, advancing it past the content.
+    fn content(
+        &mut self,
+        content: &[StructContent],
+        offset: &mut u64,
+        mut endianness: Endianness,
+    ) -> ControlFlow<Option<LeadingMagic>> {
+        let mut fields = HashMap::new();
+
+        for content in content {
+            match content {
+                StructContent::Field(field) => {
+                    if let Some(expected) = &field.expected
+                        && let Some(pattern) = expected_pattern(&field.ty, expected, endianness)
+                    {
+                        return ControlFlow::Break(Some(LeadingMagic {
+                            offset: *offset,
+                            pattern,
+                        }));
+                    }
+
+                    fields.insert(&field.name.inner, (*offset, &field.ty, endianness));
+                    self.parse_type(&field.ty, offset, endianness)?;
+                }
+                StructContent::Declaration(Declaration::Endianness(new)) => endianness = *new,
+                StructContent::Declaration(Declaration::Assert { condition, .. }) => {
+                    let ExprKind::BinOp {
+                        op: BinOp::Eq,
+                        lhs,
+                        rhs,
+                    } = &condition.kind
+                    else {
+                        continue;
+                    };
+                    let (name, expected) = match (&lhs.kind, &rhs.kind) {
+                        (ExprKind::VarUse(name), _) => (name, rhs),
+                        (_, ExprKind::VarUse(name)) => (name, lhs),
+                        _ => continue,
+                    };
+
+                    if let Some(&(field_offset, ty, endianness)) = fields.get(&name.inner)
+                        && let Some(pattern) = expected_pattern(ty, expected, endianness)
+                    {
+                        return ControlFlow::Break(Some(LeadingMagic {
+                            offset: field_offset,
+                            pattern,
+                        }));
+                    }
+                }
+                StructContent::Declaration(Declaration::Align(alignment)) => {
+                    let Some(alignment) = lit_u64(alignment).filter(|&alignment| alignment != 0)
+                    else {
+                        return ControlFlow::Break(None);
+                    };
+                    *offset = offset.next_multiple_of(alignment);
+                }
+                StructContent::Declaration(Declaration::SeekBy(by)) => match lit_u64(by) {
+                    Some(by) => *offset += by,
+                    None => return ControlFlow::Break(None),
+                },
+                StructContent::Declaration(Declaration::SeekTo(to)) => match lit_u64(to) {
+                    Some(to) => *offset = to,
+                    None => return ControlFlow::Break(None),
+                },
+                StructContent::Declaration(
+                    Declaration::Scope { .. }
+                    | Declaration::WarnIf { .. }
+                    | Declaration::Verify { .. }
+                    | Declaration::Recover { .. },
+                )
+                | StructContent::LetStatement(_) => (),
+                StructContent::Declaration(Declaration::If(_)) | StructContent::Error => {
+                    return ControlFlow::Break(None);
+                }
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Walks a value of the parse type that starts at `offset`, advancing it past the value.
+    fn parse_type(
+        &mut self,
+        ty: &ParseType,
+        offset: &mut u64,
+        endianness: Endianness,
+    ) -> ControlFlow<Option<LeadingMagic>> {
+        match &ty.kind {
+            ParseTypeKind::Integer { bit_width, .. }
+            | ParseTypeKind::Float { bit_width }
+            | ParseTypeKind::Bitfield { bit_width, .. }
+                if bit_width % 8 == 0 =>
+            {
+                *offset += u64::from(bit_width / 8);
+            }
+            ParseTypeKind::Bytes {
+                repetition_kind: RepeatKind::Len { count },
+            } => match lit_u64(count) {
+                Some(count) => *offset += count,
+                None => return ControlFlow::Break(None),
+            },
+            ParseTypeKind::Repeating {
+                parse_type,
+                repetition_kind: RepeatKind::Len { count },
+            } => {
+                let Some(count) = lit_u64(count) else {
+                    return ControlFlow::Break(None);
+                };
+                if count > 0 {
+                    let start = *offset;
+                    self.parse_type(parse_type, offset, endianness)?;
+                    *offset += (*offset - start) * (count - 1);
+                }
+            }
+            ParseTypeKind::Struct { content } => self.content(content, offset, endianness)?,
+            ParseTypeKind::Named { name, .. } => {
+                let Some(named) = self.structs.get(&name.inner) else {
+                    return ControlFlow::Break(None);
+                };
+                if self.depth >= MAX_DEPTH {
+                    return ControlFlow::Break(None);
+                }
+
+                self.depth += 1;
+                let result = self.content(&named.content, offset, endianness);
+                self.depth -= 1;
+                result?;
+            }
+            ParseTypeKind::Enum { parse_type, .. }
+            | ParseTypeKind::Flags { parse_type, .. }
+            | ParseTypeKind::Pointer { parse_type, .. }
+            | ParseTypeKind::Classified { parse_type, .. } => {
+                self.parse_type(parse_type, offset, endianness)?
+            }
+            _ => return ControlFlow::Break(None),
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Returns the pattern of the bytes that a value of the parse type must consist of to be equal to
+/// the literal `expected`.
+fn expected_pattern(
+    ty: &ParseType,
+    expected: &Expr,
+    endianness: Endianness,
+) -> Option<BytePattern> {
+    let ExprKind::Lit(lit) = &expected.kind else {
+        return None;
+    };
+
+    match (&ty.kind, lit) {
+        (ParseTypeKind::Bytes { .. }, Lit::BytePattern(pattern)) if !pattern.is_empty() => {
+            Some(pattern.clone())
+        }
+        (ParseTypeKind::Bytes { .. }, Lit::Bytes(bytes)) if !bytes.is_empty() => {
+            Some(BytePattern {
+                bytes: bytes.clone(),
+                mask: vec![0xff; bytes.len()].into(),
+            })
+        }
+        (ParseTypeKind::Integer { bit_width, .. }, Lit::Int(value)) if bit_width % 8 == 0 => {
+            let size = (bit_width / 8) as usize;
+            let mut bytes = match value.sign() {
+                Sign::Minus => value.to_signed_bytes_le(),
+                Sign::NoSign | Sign::Plus => value.to_bytes_le().1,
+            };
+            if bytes.len() > size {
+                return None;
+            }
+            bytes.resize(size, if value.sign() == Sign::Minus { 0xff } else { 0 });
+            // reordering bytes from little endian is the same as reordering them to little endian
+            endianness.reorder_to_little_endian(&mut bytes);
+
+            Some(BytePattern {
+                mask: vec![0xff; size].into(),
+                bytes: bytes.into(),
+            })
+        }
+        (
+            ParseTypeKind::Enum { parse_type, .. } | ParseTypeKind::Classified { parse_type, .. },
+            _,
+        ) => expected_pattern(parse_type, expected, endianness),
+        _ => None,
+    }
+}
+
+/// Returns the value of the expression if it is a non-negative integer literal.
+fn lit_u64(expr: &Expr) -> Option<u64> {
+    match &expr.kind {
+        ExprKind::Lit(Lit::Int(value)) => u64::try_from(value).ok(),
+        _ => None,
+    }
+}
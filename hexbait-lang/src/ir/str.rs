@@ -89,3 +89,85 @@ pub fn str_lit_content_to_bytes(
         ))
     }
 }
+
+/// Converts the given byte pattern to bytes, where `None` represents a wildcard byte.
+///
+/// A byte pattern is a whitespace separated sequence of
+/// - quoted string literals (e.g. `"MZ"`), which may contain escape sequences,
+/// - hex bytes (e.g. `90 00` or `9000`),
+/// - wildcard bytes `??` that match any byte,
+/// - escape sequences outside of quotes (e.g. `\x90\x00`).
+///
+/// For example `"MZ" 90 00 ?? "PE"` is a valid byte pattern.
+pub fn byte_pattern_to_bytes(
+    pattern: &str,
+    out: &mut Vec<Option<u8>>,
+) -> Result<(), (Cow<'static, str>, usize)> {
+    let mut buf = Vec::new();
+    let mut rest = pattern;
+
+    loop {
+        rest = rest.trim_start();
+        let offset = pattern.len() - rest.len();
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(string) = rest.strip_prefix('"') {
+            let mut escaped = false;
+            let Some(end) = string.find(|c| {
+                let is_end = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                is_end
+            }) else {
+                return Err((Cow::Borrowed("unterminated string literal"), offset));
+            };
+
+            buf.clear();
+            str_lit_content_to_bytes(&string[..end], &mut buf)
+                .map_err(|(msg, i)| (msg, offset + 1 + i))?;
+            out.extend(buf.iter().copied().map(Some));
+
+            rest = &string[end + 1..];
+            continue;
+        }
+
+        let word_len = rest
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(rest.len());
+        let (word, remaining) = rest.split_at(word_len);
+        rest = remaining;
+
+        if word.starts_with('\\') {
+            buf.clear();
+            str_lit_content_to_bytes(word, &mut buf).map_err(|(msg, i)| (msg, offset + i))?;
+            out.extend(buf.iter().copied().map(Some));
+            continue;
+        }
+
+        if !word.is_ascii() || word.len() % 2 != 0 {
+            return Err((
+                Cow::Borrowed("expected a string literal, hex bytes or `??`"),
+                offset,
+            ));
+        }
+
+        for (i, unit) in word.as_bytes().chunks(2).enumerate() {
+            if unit == b"??" {
+                out.push(None);
+                continue;
+            }
+
+            let digits = std::str::from_utf8(unit).expect("the word is ASCII");
+            if !unit.iter().all(u8::is_ascii_hexdigit) {
+                return Err((
+                    Cow::Owned(format!("invalid hex byte: {digits}")),
+                    offset + i * 2,
+                ));
+            }
+            out.push(Some(
+                u8::from_str_radix(digits, 16).expect("two hex digits always fit a u8"),
+            ));
+        }
+    }
+}
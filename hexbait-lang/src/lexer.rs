@@ -20,6 +20,9 @@ use crate::span::Span;
 #[repr(u16)]
 pub enum TokenKind {
     // Trivia
+    /// A documentation comment on a single line, which documents what follows it.
+    #[regex("///[^\n]*\n?", allow_greedy = true)]
+    DocComment,
     /// A comment on a single line.
     #[regex("//[^\n]*\n?", allow_greedy = true)]
     LineComment,
@@ -45,6 +48,9 @@ pub enum TokenKind {
     /// A decimal integer literal.
     #[regex("[0-9][0-9]*", priority = 10)]
     DecimalIntegerLiteral,
+    /// A floating point literal.
+    #[regex("[0-9][0-9]*\\.[0-9][0-9]*([eE][+-]?[0-9][0-9]*)?")]
+    FloatLiteral,
     /// A byte literal.
     // This has a low priority so that valid decimal integer literals are parsed as such.
     // A consequence of this is that decimal integer literals may be valid byte literals.
@@ -110,6 +116,12 @@ pub enum TokenKind {
     /// The hash symbol: `#`.
     #[token("#")]
     Hash,
+    /// The at symbol: `@`.
+    #[token("@")]
+    At,
+    /// The question mark symbol: `?`.
+    #[token("?")]
+    QuestionMark,
     /// The left angle symbol: `<`.
     #[token("<")]
     LAngle,
@@ -147,6 +159,9 @@ pub enum TokenKind {
     /// The `let` keyword.
     #[token("let")]
     LetKw,
+    /// The `const` keyword.
+    #[token("const")]
+    ConstKw,
     /// The `peek` keyword.
     #[token("peek")]
     PeekKw,
@@ -174,6 +189,7 @@ impl TokenKind {
     /// The human-readable name of this token kind.
     pub fn name(&self) -> &'static str {
         match self {
+            TokenKind::DocComment => "documentation comment",
             TokenKind::LineComment => "line comment",
             TokenKind::BlockComment => "block comment",
             TokenKind::Whitespace => "whitespace",
@@ -181,6 +197,7 @@ impl TokenKind {
             TokenKind::OctalIntegerLiteral => "octal integer",
             TokenKind::HexadecimalIntegerLiteral => "hexadecimal integer",
             TokenKind::DecimalIntegerLiteral => "decimal integer",
+            TokenKind::FloatLiteral => "floating point number",
             TokenKind::ByteLiteral => "byte literal",
             TokenKind::StringLiteral => "string literal",
             TokenKind::Identifier => "identifier",
@@ -201,6 +218,8 @@ impl TokenKind {
             TokenKind::Dot => "`.`",
             TokenKind::Dollar => "`$`",
             TokenKind::Hash => "`#`",
+            TokenKind::At => "`@`",
+            TokenKind::QuestionMark => "`?`",
             TokenKind::LAngle => "`<`",
             TokenKind::RAngle => "`>`",
             TokenKind::LParen => "`(`",
@@ -212,6 +231,7 @@ impl TokenKind {
             TokenKind::BytesKw => "`bytes`",
             TokenKind::StructKw => "`struct`",
             TokenKind::LetKw => "`let`",
+            TokenKind::ConstKw => "`const`",
             TokenKind::PeekKw => "`peek`",
             TokenKind::ConcatKw => "`concat`",
             TokenKind::SwitchKw => "`switch`",
@@ -228,11 +248,15 @@ impl TokenKind {
     /// other tokens.
     pub fn is_trivia(&self) -> bool {
         match self {
-            TokenKind::LineComment | TokenKind::BlockComment | TokenKind::Whitespace => true,
+            TokenKind::DocComment
+            | TokenKind::LineComment
+            | TokenKind::BlockComment
+            | TokenKind::Whitespace => true,
             TokenKind::BinaryIntegerLiteral
             | TokenKind::OctalIntegerLiteral
             | TokenKind::HexadecimalIntegerLiteral
             | TokenKind::DecimalIntegerLiteral
+            | TokenKind::FloatLiteral
             | TokenKind::ByteLiteral
             | TokenKind::StringLiteral
             | TokenKind::Identifier
@@ -253,6 +277,8 @@ impl TokenKind {
             | TokenKind::Dot
             | TokenKind::Dollar
             | TokenKind::Hash
+            | TokenKind::At
+            | TokenKind::QuestionMark
             | TokenKind::LAngle
             | TokenKind::RAngle
             | TokenKind::LParen
@@ -264,6 +290,7 @@ impl TokenKind {
             | TokenKind::BytesKw
             | TokenKind::StructKw
             | TokenKind::LetKw
+            | TokenKind::ConstKw
             | TokenKind::PeekKw
             | TokenKind::ConcatKw
             | TokenKind::SwitchKw
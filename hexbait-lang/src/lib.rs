@@ -3,7 +3,12 @@
 #![forbid(unsafe_code)]
 
 pub mod ast;
+mod c_header;
+mod diagnostic;
 mod eval;
+mod format;
+mod hexpat;
+mod highlight;
 pub mod ir;
 mod lexer;
 mod parser;
@@ -11,9 +16,15 @@ mod span;
 mod syntax;
 
 pub use {
+    c_header::{CHeaderTranslation, translate_c_header},
+    diagnostic::Diagnostic,
     eval::*,
+    format::format,
+    hexpat::{HexpatTranslation, translate_hexpat},
+    highlight::{Highlight, HighlightKind, highlight},
     ir::check_ir,
-    parser::parse,
+    lexer::TokenKind,
+    parser::{Edit, Parse, ParseError, parse},
     span::Span,
     syntax::{Language, NodeKind, SyntaxKind, SyntaxNode, SyntaxToken},
 };
@@ -26,11 +37,5 @@ fn int_from_str(base: u32, s: &str) -> Option<Int> {
     <Int as num_traits::Num>::from_str_radix(s, base).ok()
 }
 
-// TODO: add optional field to reflect max counts for count parsing -> or implement max function
-// TODO: implement display options (enum that name certain values)
-// TODO: implement bitwise fields
-// TODO: implement custom data streams
-// TODO: implement classification of parsed values (offset, integer?, string?)
+// TODO: add optional field to reflect max counts for count parsing
 // TODO: improve display of the parsed values in the GUI
-// TODO: figure out a way to cleverly incorporate colors
-// TODO: implement a new concept of "scopes" in the file to reset endianness (and others) at the end of `!scope` and `struct`s
@@ -1,13 +1,16 @@
 //! Implements the parser for the hexbait language.
 
+use std::ops::Range;
+
 use crate::{
+    NodeKind, SyntaxNode,
     ast::{AstNode as _, File},
-    lexer::lex,
+    lexer::{Token, TokenKind, lex},
     span::Span,
     syntax::SyntaxKind,
 };
 use infrastructure::{Event, Parser};
-use rowan::GreenNodeBuilder;
+use rowan::{GreenNode, GreenNodeBuilder, NodeOrToken, TextRange, TextSize};
 
 mod implementation;
 mod infrastructure;
@@ -32,15 +35,187 @@ pub struct ParseError {
     pub expected: Vec<&'static str>,
 }
 
+/// A change of a text that replaces a range of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The byte range of the old text that is replaced.
+    pub delete: Range<usize>,
+    /// The text that replaces the deleted range.
+    pub insert: String,
+}
+
+impl Edit {
+    /// Applies the edit to the given text.
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.delete.clone(), &self.insert);
+    }
+}
+
+impl Parse {
+    /// Parses the text that results from applying the edit to the parsed text.
+    ///
+    /// If possible, only the innermost `struct` block containing the edit is lexed and parsed
+    /// again, while all other parts of the syntax tree are reused.
+    /// The result is the same as parsing the whole edited text.
+    ///
+    /// ```
+    /// # use hexbait_lang::{Edit, ast::AstNode as _, parse};
+    /// let src = "header {\n    magic u32;\n};\n";
+    /// let edit = Edit {
+    ///     delete: 19..22,
+    ///     insert: String::from("u16;\n    size u32"),
+    /// };
+    ///
+    /// let reparsed = parse(src).reparse(&edit);
+    /// assert_eq!(
+    ///     reparsed.ast.syntax().to_string(),
+    ///     "header {\n    magic u16;\n    size u32;\n};\n",
+    /// );
+    /// assert!(reparsed.errors.is_empty());
+    /// ```
+    pub fn reparse(&self, edit: &Edit) -> Parse {
+        self.reparse_block(edit).unwrap_or_else(|| {
+            let mut text = self.ast.syntax().to_string();
+            edit.apply(&mut text);
+            parse(&text)
+        })
+    }
+
+    /// Parses only the innermost `struct` block containing the edit again.
+    ///
+    /// Returns `None` if this could give a different result than parsing the whole text.
+    fn reparse_block(&self, edit: &Edit) -> Option<Parse> {
+        let root = self.ast.syntax();
+        let delete = TextRange::new(
+            TextSize::try_from(edit.delete.start).ok()?,
+            TextSize::try_from(edit.delete.end).ok()?,
+        );
+        if !root.text_range().contains_range(delete) {
+            return None;
+        }
+
+        let covering = match root.covering_element(delete) {
+            NodeOrToken::Node(node) => node,
+            NodeOrToken::Token(token) => token.parent()?,
+        };
+        for block in covering
+            .ancestors()
+            .filter(|node| node.kind() == SyntaxKind::from(NodeKind::StructBlock))
+        {
+            // the braces of the block need to stay in place
+            let range = block.text_range();
+            if delete.start() <= range.start() || delete.end() >= range.end() {
+                continue;
+            }
+
+            let offset = usize::from(range.start());
+            let mut text = block.to_string();
+            text.replace_range(
+                edit.delete.start - offset..edit.delete.end - offset,
+                &edit.insert,
+            );
+            let Some((green, block_errors)) = parse_lone_block(&text) else {
+                continue;
+            };
+
+            let old_end = usize::from(range.end());
+            let new_end = offset + text.len();
+            let errors = self
+                .errors
+                .iter()
+                .filter(|err| err.span.end <= offset)
+                .cloned()
+                .chain(block_errors.into_iter().map(|err| err.shifted(0, offset)))
+                .chain(
+                    self.errors
+                        .iter()
+                        .filter(|err| err.span.start >= old_end)
+                        .map(|err| err.clone().shifted(old_end, new_end)),
+                )
+                .collect();
+
+            let syntax_node = SyntaxNode::new_root(block.replace_with(green));
+            return Some(Parse {
+                ast: File::cast(syntax_node).expect("root node is always `File`"),
+                errors,
+            });
+        }
+
+        None
+    }
+}
+
+impl ParseError {
+    /// Moves the error from a text position `from` to the text position `to`.
+    fn shifted(mut self, from: usize, to: usize) -> ParseError {
+        self.span = Span {
+            start: self.span.start - from + to,
+            end: self.span.end - from + to,
+        };
+
+        self
+    }
+}
+
 /// Parses the given text.
+///
+/// Parsing always produces a syntax tree, even if the text contains errors.
+///
+/// ```
+/// # use hexbait_lang::parse;
+/// let enum_parse = parse("x enum u8 { 1 => \"a\"; };");
+/// assert!(!enum_parse.errors.is_empty());
+///
+/// let flags_parse = parse("x flags u8 { 1 => A; };");
+/// assert!(!flags_parse.errors.is_empty());
+/// ```
 pub fn parse(src: &str) -> Parse {
     let tokens = lex(src);
     let mut p = Parser::new(src, &tokens);
     implementation::root(&mut p);
 
+    let (green, errors) = build_tree(src, &tokens, p.events());
+    let syntax_node = rowan::SyntaxNode::<crate::syntax::Language>::new_root(green);
+    Parse {
+        ast: File::cast(syntax_node).expect("root node is always `File`"),
+        errors,
+    }
+}
+
+/// Parses the text of a `struct` block on its own.
+///
+/// Returns `None` if the result could be different when the block is parsed as part of a file,
+/// which is the case if the block does not end exactly at the end of the text.
+fn parse_lone_block(src: &str) -> Option<(GreenNode, Vec<ParseError>)> {
+    let tokens = lex(src);
+    // unrecognized tokens may be part of a longer token when the surrounding text is lexed as well
+    if tokens.first()?.kind != TokenKind::LBrace
+        || tokens.iter().any(|token| token.kind == TokenKind::Error)
+    {
+        return None;
+    }
+
+    let mut p = Parser::new(src, &tokens);
+    implementation::lone_struct_block(&mut p);
+    if p.cur().is_some() {
+        return None;
+    }
+
+    let (green, errors) = build_tree(src, &tokens, p.events());
+    // an error at the end means that the block was not closed by its last token
+    if errors.iter().any(|err| err.span.start >= src.len()) {
+        return None;
+    }
+
+    Some((green, errors))
+}
+
+/// Builds the syntax tree from the events of the parser, returning it along with the errors that
+/// occurred.
+fn build_tree(src: &str, tokens: &[Token], events: &[Event]) -> (GreenNode, Vec<ParseError>) {
     let mut builder = GreenNodeBuilder::new();
     let mut tok_idx = 0;
-    for ev in p.events() {
+    for ev in events {
         match ev {
             Event::Start {
                 kind,
@@ -59,7 +234,7 @@ pub fn parse(src: &str) -> Parse {
                         kind,
                         forward_parent: new_forward_parent,
                         is_forward_parent: true,
-                    } = &p.events()[*parent_idx]
+                    } = &events[*parent_idx]
                 {
                     parents.push(kind);
                     forward_parent = new_forward_parent;
@@ -93,8 +268,7 @@ pub fn parse(src: &str) -> Parse {
         }
     }
     let green = builder.finish();
-    let errors = p
-        .events()
+    let errors = events
         .iter()
         .filter_map(|e| match e {
             Event::Error(e) => Some(e.clone()),
@@ -102,9 +276,5 @@ pub fn parse(src: &str) -> Parse {
         })
         .collect();
 
-    let syntax_node = rowan::SyntaxNode::<crate::syntax::Language>::new_root(green);
-    Parse {
-        ast: File::cast(syntax_node).expect("root node is always `File`"),
-        errors,
-    }
+    (green, errors)
 }
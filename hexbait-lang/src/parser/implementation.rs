@@ -1,10 +1,10 @@
 //! Contains the actual syntax descriptions.
 
-use expressions::expr;
+use expressions::{arguments, expr};
 
 use crate::{NodeKind, lexer::TokenKind};
 
-use super::infrastructure::{Completed, Parser};
+use super::infrastructure::{Completed, Marker, Parser};
 
 mod expressions;
 
@@ -22,6 +22,12 @@ pub(crate) fn root(p: &mut Parser) {
     p.complete(m, NodeKind::File);
 }
 
+/// Parses a struct block on its own, as done when only a part of a file is parsed again.
+pub(crate) fn lone_struct_block(p: &mut Parser) {
+    // trivia after the block belongs to its parent, so it is not bumped
+    struct_block(p).handle_trivia_manually();
+}
+
 /// Parses the content of a struct.
 fn struct_content<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     let Some(kind) = p.cur() else {
@@ -29,10 +35,22 @@ fn struct_content<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     };
 
     match kind {
-        TokenKind::StructKw => r#struct(p),
         TokenKind::LetKw => r#let(p),
+        TokenKind::ConstKw => r#const(p),
         TokenKind::ExclamationMark => decl(p),
-        _ => struct_field(p),
+        _ => {
+            // both `struct`s and fields can have attributes in front of them
+            let m = p.start();
+            while p.at(TokenKind::At) {
+                attribute(p);
+            }
+
+            if p.at(TokenKind::StructKw) {
+                r#struct(p, m)
+            } else {
+                struct_field(p, m)
+            }
+        }
     }
 }
 
@@ -48,13 +66,17 @@ fn struct_block<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     p.complete_after(m, NodeKind::StructBlock, TokenKind::RBrace)
 }
 
-/// Parses a `struct`.
-fn r#struct<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
-    let m = p.start();
-
+/// Parses a `struct` after its attributes.
+fn r#struct<'p, 'src>(p: &'p mut Parser<'src>, m: Marker) -> Completed<'p, 'src> {
     p.expect(TokenKind::StructKw);
     p.expect(TokenKind::Identifier);
 
+    if p.at(TokenKind::LParen) {
+        let m = p.start();
+        arguments(p, |p| p.expect(TokenKind::Identifier));
+        p.complete_after(m, NodeKind::StructParams, TokenKind::RParen);
+    }
+
     struct_block(p).and_complete(m, NodeKind::Struct)
 }
 
@@ -71,6 +93,19 @@ fn r#let<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     p.complete_after(m, NodeKind::LetStatement, TokenKind::Semicolon)
 }
 
+/// Parses a `const` declaration.
+fn r#const<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
+    let m = p.start();
+
+    p.expect(TokenKind::ConstKw);
+    p.expect(TokenKind::Identifier);
+    p.expect(TokenKind::Equals);
+
+    expr(p);
+
+    p.complete_after(m, NodeKind::ConstDeclaration, TokenKind::Semicolon)
+}
+
 /// Parses an `if` chain.
 fn if_chain<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     let m = p.start();
@@ -97,15 +132,14 @@ fn if_chain<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
 
         p.bump();
 
-        let m_else_part = p.start();
-
+        // the else part is not wrapped in its own node, because the AST accesses it as a direct
+        // child of the `if` chain
         if p.at_contextual_kw("if") {
             if_chain(p)
         } else {
             let m_else_block = p.start();
             struct_block(p).and_complete(m_else_block, NodeKind::ElseBlock)
         }
-        .and_complete(m_else_part, NodeKind::ElsePart)
         .and_complete(m, NodeKind::IfChain)
     } else {
         // complete the chain without bumping trivia
@@ -125,7 +159,7 @@ fn decl<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
         Some("endian") => {
             p.bump();
             match p.expect_and_bump_contextual_kw() {
-                Some("le") | Some("be") => (),
+                Some("le" | "be" | "pdp" | "be_ws16" | "le_ws32" | "be_ws32") => (),
                 _ => todo!("error"),
             }
 
@@ -147,6 +181,10 @@ fn decl<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
             let kind = match p.expect_and_bump_contextual_kw() {
                 Some("at") => NodeKind::ScopeAtDeclaration,
                 Some("in") => NodeKind::ScopeInDeclaration,
+                Some("decompressed") => {
+                    p.expect(TokenKind::Identifier);
+                    NodeKind::ScopeDecompressedDeclaration
+                }
                 _ => todo!("error"),
             };
 
@@ -192,26 +230,45 @@ fn decl<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
 
             p.complete_after(m, NodeKind::WarnIfDeclaration, TokenKind::Semicolon)
         }
+        Some("verify") => {
+            p.bump();
+            expr(p);
+            if p.at(TokenKind::Colon) {
+                p.expect(TokenKind::Colon);
+                expr(p);
+            }
+
+            p.complete_after(m, NodeKind::VerifyDeclaration, TokenKind::Semicolon)
+        }
         Some("recover") => {
             p.bump();
-            if p.at_contextual_kw("at") {
-                p.bump();
+            let kind = if p.at_contextual_kw("at") {
+                NodeKind::RecoveryDeclaration
+            } else if p.at_contextual_kw("scan") {
+                NodeKind::RecoveryScanDeclaration
             } else {
-                todo!("recover currently requires at");
-            }
+                // the error skips to the end of the declaration
+                p.expect_error(vec!["`at`", "`scan`"]);
+                return p.complete_after(m, NodeKind::RecoveryDeclaration, TokenKind::Semicolon);
+            };
+            p.bump();
 
             expr(p);
 
-            p.complete_after(m, NodeKind::RecoveryDeclaration, TokenKind::Semicolon)
+            p.complete_after(m, kind, TokenKind::Semicolon)
+        }
+        Some("import") => {
+            p.bump();
+            p.expect(TokenKind::StringLiteral);
+
+            p.complete_after(m, NodeKind::ImportDeclaration, TokenKind::Semicolon)
         }
         _ => todo!("error"),
     }
 }
 
-/// Parses a struct field.
-fn struct_field<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
-    let m = p.start();
-
+/// Parses a struct field after its attributes.
+fn struct_field<'p, 'src>(p: &'p mut Parser<'src>, m: Marker) -> Completed<'p, 'src> {
     p.expect(TokenKind::Identifier);
     top_level_parse_type(p);
     if p.cur() == Some(TokenKind::Equals) {
@@ -222,6 +279,25 @@ fn struct_field<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     p.complete_after(m, NodeKind::StructField, TokenKind::Semicolon)
 }
 
+/// Parses an attribute of a struct field or a `struct`.
+fn attribute<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
+    let m = p.start();
+
+    p.expect(TokenKind::At);
+    if p.cur() == Some(TokenKind::Identifier)
+        && matches!(p.peek().nth(1), Some((_, TokenKind::LParen)))
+    {
+        p.expect(TokenKind::Identifier);
+        arguments(p, |p| {
+            expr(p);
+        });
+
+        p.complete_after(m, NodeKind::Attribute, TokenKind::RParen)
+    } else {
+        p.complete_after(m, NodeKind::Attribute, TokenKind::Identifier)
+    }
+}
+
 /// Parses a top-level parse type.
 fn top_level_parse_type<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     parse_type_raw(p, false)
@@ -260,9 +336,85 @@ fn parse_type_raw<'p, 'src>(p: &'p mut Parser<'src>, nested: bool) -> Completed<
 
             p.complete_after(m, kind, TokenKind::RParen)
         }
+        Some(TokenKind::Identifier)
+            if p.cur_text() == Some("bits")
+                && matches!(p.peek().nth(1), Some((_, TokenKind::Identifier))) =>
+        {
+            p.bump();
+            nested_parse_type(p);
+            p.expect(TokenKind::LBrace);
+
+            while p.cur().is_some_and(|t| t != TokenKind::RBrace) {
+                let m = p.start();
+
+                p.expect(TokenKind::Identifier);
+                nested_parse_type(p);
+
+                p.complete_after(m, NodeKind::BitfieldField, TokenKind::Semicolon);
+            }
+
+            p.complete_after(m, NodeKind::BitfieldParseType, TokenKind::RBrace)
+        }
+        Some(TokenKind::Identifier)
+            if p.cur_text() == Some("enum")
+                && matches!(p.peek().nth(1), Some((_, TokenKind::Identifier))) =>
+        {
+            p.bump();
+            nested_parse_type(p);
+            p.expect(TokenKind::LBrace);
+
+            arms(p, NodeKind::EnumArm, |p| {
+                expr(p);
+            });
+
+            p.complete_after(m, NodeKind::EnumParseType, TokenKind::RBrace)
+        }
+        Some(TokenKind::Identifier)
+            if p.cur_text() == Some("flags")
+                && matches!(p.peek().nth(1), Some((_, TokenKind::Identifier))) =>
+        {
+            p.bump();
+            nested_parse_type(p);
+            p.expect(TokenKind::LBrace);
+
+            arms(p, NodeKind::FlagsArm, |p| p.expect(TokenKind::Identifier));
+
+            p.complete_after(m, NodeKind::FlagsParseType, TokenKind::RBrace)
+        }
+        Some(TokenKind::Identifier)
+            if p.cur_text() == Some("ptr")
+                && matches!(p.peek().nth(1), Some((_, TokenKind::LAngle))) =>
+        {
+            p.bump();
+            p.expect(TokenKind::LAngle);
+            nested_parse_type(p);
+            p.expect(TokenKind::RAngle);
+
+            // the base is optional, so trivia is handled manually like in `if` chains
+            nested_parse_type(p).handle_trivia_manually();
+
+            if matches!(p.peek().next(), Some((_, TokenKind::At))) {
+                p.trivia_bumper().bump();
+                p.expect(TokenKind::At);
+
+                expr(p).and_complete(m, NodeKind::PointerParseType)
+            } else {
+                let completed = p.complete(m, NodeKind::PointerParseType);
+
+                p.completed_from_marker(completed)
+            }
+        }
         Some(TokenKind::LBrace) => {
             struct_block(p).and_complete(m, NodeKind::AnonymousStructParseType)
         }
+        Some(TokenKind::Identifier) if matches!(p.peek().nth(1), Some((_, TokenKind::LParen))) => {
+            p.expect(TokenKind::Identifier);
+            arguments(p, |p| {
+                expr(p);
+            });
+
+            p.complete_after(m, NodeKind::NamedParseType, TokenKind::RParen)
+        }
         Some(TokenKind::Identifier) => {
             p.complete_after(m, NodeKind::NamedParseType, TokenKind::Identifier)
         }
@@ -308,12 +460,45 @@ fn parse_type_raw<'p, 'src>(p: &'p mut Parser<'src>, nested: bool) -> Completed<
 }
 
 /// Parses a repeating declaration.
+/// Parses the comma separated arms of an `enum` or `flags` type up to the closing `}`.
+///
+/// Each arm maps an expression to what `target` parses.
+fn arms(p: &mut Parser, kind: NodeKind, target: impl Fn(&mut Parser)) {
+    while p.cur().is_some_and(|t| t != TokenKind::RBrace) {
+        let pos = p.pos();
+        let m = p.start();
+
+        expr(p);
+        // error recovery stops in front of a `;`, which cannot start an arm, so it is skipped to
+        // make progress
+        if p.pos() == pos {
+            p.bump();
+            p.complete(m, kind);
+            continue;
+        }
+        p.expect(TokenKind::Equals);
+        p.expect(TokenKind::RAngle);
+        target(p);
+
+        if p.at(TokenKind::RBrace) {
+            p.complete(m, kind);
+        } else {
+            p.complete_after(m, kind, TokenKind::Comma);
+        }
+    }
+}
+
 fn repeat_decl<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     let m = p.start();
 
     match p.expect_and_bump_contextual_kw() {
         Some("len") => expr(p).and_complete(m, NodeKind::RepeatLenDecl),
         Some("while") => expr(p).and_complete(m, NodeKind::RepeatWhileDecl),
+        Some("until") => {
+            p.expect(TokenKind::Equals);
+            p.expect(TokenKind::Equals);
+            expr(p).and_complete(m, NodeKind::RepeatUntilDecl)
+        }
         _ => todo!("error"),
     }
 }
@@ -13,19 +13,37 @@ fn atom<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     let m = p.start();
 
     let (node_kind, next) = match p.cur() {
+        Some(TokenKind::Identifier) if matches!(p.peek().nth(1), Some((_, TokenKind::LParen))) => {
+            p.expect(TokenKind::Identifier);
+
+            arguments(p, |p| {
+                expr(p);
+            });
+
+            (NodeKind::CallExpr, TokenKind::RParen)
+        }
         Some(
             kind @ (TokenKind::Identifier
             | TokenKind::BinaryIntegerLiteral
             | TokenKind::OctalIntegerLiteral
             | TokenKind::DecimalIntegerLiteral
             | TokenKind::HexadecimalIntegerLiteral
+            | TokenKind::FloatLiteral
             | TokenKind::TrueKw
             | TokenKind::FalseKw
             | TokenKind::StringLiteral),
         ) => (NodeKind::Atom, kind),
         Some(TokenKind::Dollar) => {
             p.expect(TokenKind::Dollar);
-            (NodeKind::Metavar, TokenKind::Identifier)
+            if p.peek().nth(1).map(|(_, kind)| kind) == Some(TokenKind::LParen) {
+                p.expect(TokenKind::Identifier);
+                p.expect(TokenKind::LParen);
+                expr(p);
+
+                (NodeKind::Metavar, TokenKind::RParen)
+            } else {
+                (NodeKind::Metavar, TokenKind::Identifier)
+            }
         }
         Some(TokenKind::PeekKw) => {
             p.expect(TokenKind::PeekKw);
@@ -42,42 +60,22 @@ fn atom<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
         }
         Some(TokenKind::ConcatKw) => {
             p.expect(TokenKind::ConcatKw);
-            p.expect(TokenKind::LParen);
-
-            let mut needs_comma = false;
-            loop {
-                if needs_comma {
-                    match p.cur() {
-                        Some(TokenKind::Comma) => {
-                            p.expect(TokenKind::Comma);
-                        }
-                        Some(TokenKind::RParen) => break,
-                        _ => {
-                            p.expect_error(vec!["`,`", "`)`"]);
-                            break;
-                        }
-                    }
-                }
-
-                match p.cur() {
-                    Some(TokenKind::RParen) => break,
-                    Some(TokenKind::Dot) => {
-                        let arg_ty = p.start();
 
-                        p.expect(TokenKind::Dot);
-                        p.expect(TokenKind::Dot);
+            arguments(p, |p| match p.cur() {
+                Some(TokenKind::Dot) => {
+                    let arg_ty = p.start();
 
-                        expr(p).and_complete(arg_ty, NodeKind::ConcatArgExpanding);
-                    }
-                    _ => {
-                        let arg_ty = p.start();
+                    p.expect(TokenKind::Dot);
+                    p.expect(TokenKind::Dot);
 
-                        expr(p).and_complete(arg_ty, NodeKind::ConcatArgDirect);
-                    }
+                    expr(p).and_complete(arg_ty, NodeKind::ConcatArgExpanding);
                 }
+                _ => {
+                    let arg_ty = p.start();
 
-                needs_comma = true;
-            }
+                    expr(p).and_complete(arg_ty, NodeKind::ConcatArgDirect);
+                }
+            });
 
             (NodeKind::ConcatExpr, TokenKind::RParen)
         }
@@ -90,7 +88,8 @@ fn atom<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
                         lit @ (TokenKind::StringLiteral
                         | TokenKind::ByteLiteral // for things like 1a
                         | TokenKind::DecimalIntegerLiteral // for things like 10
-                        | TokenKind::Identifier), // for things like a1
+                        | TokenKind::Identifier // for things like a1
+                        | TokenKind::QuestionMark), // for wildcards like ?? or 4?
                     ) => p.expect(lit),
                     _ => {
                         p.dbg();
@@ -123,6 +122,37 @@ fn atom<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     p.complete_after(m, node_kind, next)
 }
 
+/// Parses a parenthesized, comma separated list of arguments up to the closing parenthesis.
+///
+/// Each argument is parsed using `arg`.
+pub(super) fn arguments<'src>(p: &mut Parser<'src>, mut arg: impl FnMut(&mut Parser<'src>)) {
+    p.expect(TokenKind::LParen);
+
+    let mut needs_comma = false;
+    loop {
+        if needs_comma {
+            match p.cur() {
+                Some(TokenKind::Comma) => {
+                    p.expect(TokenKind::Comma);
+                }
+                Some(TokenKind::RParen) => break,
+                _ => {
+                    p.expect_error(vec!["`,`", "`)`"]);
+                    break;
+                }
+            }
+        }
+
+        if p.cur() == Some(TokenKind::RParen) {
+            break;
+        }
+
+        arg(p);
+
+        needs_comma = true;
+    }
+}
+
 /// Parses an expression.
 pub(crate) fn expr<'p, 'src>(p: &'p mut Parser<'src>) -> Completed<'p, 'src> {
     let completed_marker = expr_bp(p, 0);
@@ -151,13 +181,43 @@ fn expr_bp<'p, 'src>(p: &'p mut Parser<'src>, min_bp: u8) -> CompletedMarker {
     loop {
         let next_token = p.peek().map(|(_, kind)| kind).next();
         match next_token {
-            Some(TokenKind::Dot) => {
+            // `..` is not a field access, but the range in a slice expression
+            Some(TokenKind::Dot) if !matches!(p.peek().nth(1), Some((_, TokenKind::Dot))) => {
                 let m = lhs.precede(p);
 
                 p.expect(TokenKind::Dot);
 
+                lhs = if matches!(p.peek().nth(1), Some((_, TokenKind::LParen))) {
+                    p.expect(TokenKind::Identifier);
+
+                    arguments(p, |p| {
+                        expr(p);
+                    });
+
+                    p.complete_after(m, NodeKind::MethodCallExpr, TokenKind::RParen)
+                } else {
+                    p.complete_after(m, NodeKind::FieldAccess, TokenKind::Identifier)
+                }
+                .handle_trivia_manually();
+            }
+            Some(TokenKind::LBracket) => {
+                let m = lhs.precede(p);
+
+                p.expect(TokenKind::LBracket);
+                expr(p);
+
+                let kind = if p.cur() == Some(TokenKind::Dot) {
+                    p.expect(TokenKind::Dot);
+                    p.expect(TokenKind::Dot);
+                    expr(p);
+
+                    NodeKind::SliceExpr
+                } else {
+                    NodeKind::IndexExpr
+                };
+
                 lhs = p
-                    .complete_after(m, NodeKind::FieldAccess, TokenKind::Identifier)
+                    .complete_after(m, kind, TokenKind::RBracket)
                     .handle_trivia_manually();
             }
             _ => break,
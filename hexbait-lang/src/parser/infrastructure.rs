@@ -160,6 +160,13 @@ impl<'src> Parser<'src> {
             .map(|(i, t)| (self.pos + i, t.kind))
     }
 
+    /// Returns the current offset into the token stream.
+    ///
+    /// Comparing positions shows whether parsing made progress.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
     /// Returns the current token.
     pub(crate) fn cur(&self) -> Option<TokenKind> {
         self.tokens.get(self.pos).map(|t| t.kind)
@@ -13,6 +13,18 @@ pub struct Span {
     pub(crate) end: usize,
 }
 
+impl Span {
+    /// The start offset of the location, inclusive.
+    pub fn start(self) -> usize {
+        self.start
+    }
+
+    /// The end offset of the location, exclusive.
+    pub fn end(self) -> usize {
+        self.end
+    }
+}
+
 impl fmt::Debug for Span {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}..{}", self.start, self.end)
@@ -87,13 +87,19 @@ pub enum NodeKind {
     Struct,
     /// A field of a struct.
     StructField,
+    /// An attribute of a struct field.
+    Attribute,
     /// Defines a new computed value.
     LetStatement,
+    /// Defines a constant.
+    ConstDeclaration,
     /// A block of struct contents.
     StructBlock,
+    /// The parameters of a named struct.
+    StructParams,
 
     // Parse types
-    /// A parse type that is a single identifier.
+    /// A parse type that refers to another type by name, possibly with arguments.
     NamedParseType,
     /// A parse type that parses a dynamically sized signed integer.
     DynamicSizeIntParseType,
@@ -109,12 +115,28 @@ pub enum NodeKind {
     SwitchParseType,
     /// A single arm of a switch parse type.
     SwitchParseTypeArm,
+    /// A parse type that splits the bits of an integer into fields.
+    BitfieldParseType,
+    /// A single field of a bitfield parse type.
+    BitfieldField,
+    /// A parse type that names some values of an integer.
+    EnumParseType,
+    /// A single named value of an enum parse type.
+    EnumArm,
+    /// A parse type that names the bits of an integer.
+    FlagsParseType,
+    /// A single named bit mask of a flags parse type.
+    FlagsArm,
+    /// A parse type for an integer that points to another value.
+    PointerParseType,
 
     // Repeating types
     /// A repetition of a fixed number of elements.
     RepeatLenDecl,
     /// A repetition until a condition is met.
     RepeatWhileDecl,
+    /// A repetition until a terminator is reached.
+    RepeatUntilDecl,
 
     // Conditional parsing
     /// A chain of one or more if statements.
@@ -137,14 +159,22 @@ pub enum NodeKind {
     ScopeAtDeclaration,
     /// A declaration that parsing should continue in another scope that is defined by parsed bytes.
     ScopeInDeclaration,
+    /// A declaration that parsing should continue in the decompressed content of parsed bytes.
+    ScopeDecompressedDeclaration,
     /// A declaration to parse contained fields only if a condition is true.
     IfDeclaration,
     /// A declaration asserting that an expression is true like `!assert size > 4`.
     AssertDeclaration,
     /// A declaration warns if the expression is true like `!warn if size > 4: "expected small size"`.
     WarnIfDeclaration,
+    /// A declaration verifying a checksum like `!verify crc32(data) == checksum`.
+    VerifyDeclaration,
     /// A declaration to specify recovery behavior in case of errors like `!recover at 8`.
     RecoveryDeclaration,
+    /// A declaration to recover at the next occurrence of a pattern like `!recover scan "FILE"`.
+    RecoveryScanDeclaration,
+    /// A declaration that imports the named `struct`s of another file like `!import "common.hbl"`.
+    ImportDeclaration,
 
     // Expressions
     /// An atomic expression.
@@ -167,6 +197,14 @@ pub enum NodeKind {
     PeekExpr,
     /// A `concat(val1, val2, ..val3)` expression.
     ConcatExpr,
+    /// A call of a built-in function: `func(arg1, arg2)`.
+    CallExpr,
+    /// A call of a built-in function on a value: `val.func(arg)`.
+    MethodCallExpr,
+    /// An indexing expression: `val[index]`.
+    IndexExpr,
+    /// A slicing expression: `val[start..end]`.
+    SliceExpr,
 
     // Concatenation helpers
     /// An argument to a `concat` expression.
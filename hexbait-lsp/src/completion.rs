@@ -0,0 +1,85 @@
+//! Implements suggesting names while typing format descriptions.
+
+use hexbait_lang::{SyntaxNode, ast::AstNode as _, ir::Builtin};
+use lsp_types::{CompletionItem, CompletionItemKind};
+
+use crate::navigation::{self, Item, METAVARIABLES};
+
+/// Returns the names that can be typed at the given offset.
+pub(crate) fn completions(root: &SyntaxNode, text: &str, offset: usize) -> Vec<CompletionItem> {
+    let offset = offset.min(text.len());
+    // the part of the name that was already typed is not relevant for the suggestions
+    let before = text[..offset].trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+
+    let scope = root
+        .token_at_offset((before.len() as u32).into())
+        .left_biased()
+        .and_then(|token| token.parent())
+        .and_then(|node| navigation::enclosing_scope(&node));
+    let Some(scope) = scope else {
+        return Vec::new();
+    };
+
+    if before.ends_with('$') {
+        return METAVARIABLES
+            .iter()
+            .map(|(name, description)| CompletionItem {
+                label: name.to_string(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(description.to_string()),
+                ..Default::default()
+            })
+            .collect();
+    }
+    if let Some(before) = before.strip_suffix('.') {
+        if !before.trim_end().ends_with("$parent") {
+            return Vec::new();
+        }
+
+        return navigation::parent_scope(&scope)
+            .map(|parent| items(&parent))
+            .unwrap_or_default();
+    }
+
+    let mut completions = items(&scope);
+    completions.extend(
+        root.children()
+            .filter_map(hexbait_lang::ast::Struct::cast)
+            .filter_map(|named_struct| {
+                Some(CompletionItem {
+                    label: named_struct.name()?.text().to_string(),
+                    kind: Some(CompletionItemKind::STRUCT),
+                    documentation: named_struct
+                        .doc_comment()
+                        .map(lsp_types::Documentation::String),
+                    ..Default::default()
+                })
+            }),
+    );
+    completions.extend(Builtin::ALL.into_iter().map(|func| CompletionItem {
+        label: func.name().to_string(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        ..Default::default()
+    }));
+
+    completions
+}
+
+/// Returns the completions for the items of the `struct` defined by the scope.
+fn items(scope: &SyntaxNode) -> Vec<CompletionItem> {
+    navigation::scope_items(scope)
+        .into_iter()
+        .filter_map(|item| {
+            let kind = match item {
+                Item::Field(_) => CompletionItemKind::FIELD,
+                Item::Let(_) | Item::Param(_) => CompletionItemKind::VARIABLE,
+            };
+
+            Some(CompletionItem {
+                label: item.name()?.text().to_string(),
+                kind: Some(kind),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,77 @@
+//! Implements finding the problems in format descriptions.
+
+use hexbait_builtin_parsers::import_paths;
+use hexbait_lang::{
+    Parse, check_ir,
+    ir::{File, ImportError, SourceLocation, lower_file, resolve_imports},
+};
+use lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+
+use crate::line_index::LineIndex;
+
+/// Finds the problems in the format description with the given text and syntax tree.
+pub(crate) fn diagnostics(text: &str, parse: &Parse, location: &SourceLocation) -> Vec<Diagnostic> {
+    let line_index = LineIndex::new(text);
+
+    if !parse.errors.is_empty() {
+        return parse
+            .errors
+            .iter()
+            .map(|err| to_lsp(&line_index, &hexbait_lang::Diagnostic::from(err)))
+            .collect();
+    }
+
+    let mut file = lower_file(parse.ast.clone());
+    if let Err(err) = resolve_imports(&mut file, location, &import_paths(Vec::new())) {
+        let range =
+            import_span(&file, &err).map_or_else(Range::default, |span| line_index.range(span));
+
+        return vec![error(range, err.to_string())];
+    }
+
+    match check_ir(&file) {
+        Ok(_) => Vec::new(),
+        Err(err) => err
+            .problems
+            .iter()
+            .map(|problem| to_lsp(&line_index, &hexbait_lang::Diagnostic::from(problem)))
+            .collect(),
+    }
+}
+
+/// Creates an error diagnostic with the given message.
+pub(crate) fn error(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(String::from("hexbait")),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Converts a problem found in a format description to an LSP diagnostic.
+fn to_lsp(line_index: &LineIndex, diagnostic: &hexbait_lang::Diagnostic) -> Diagnostic {
+    let message = match &diagnostic.label {
+        Some(label) => format!("{}\n{label}", diagnostic.message),
+        None => diagnostic.message.clone(),
+    };
+
+    error(line_index.range(diagnostic.span), message)
+}
+
+/// Returns the span of the import that caused the error, if it is known.
+fn import_span(file: &File, err: &ImportError) -> Option<hexbait_lang::Span> {
+    let path = match err {
+        ImportError::NotFound { path }
+        | ImportError::Io { path, .. }
+        | ImportError::Syntax { path }
+        | ImportError::Cycle { path } => path,
+        ImportError::DuplicateStruct { .. } => return None,
+    };
+
+    file.imports
+        .iter()
+        .find(|import| import.inner == *path)
+        .map(|import| import.span)
+}
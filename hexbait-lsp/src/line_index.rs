@@ -0,0 +1,64 @@
+//! Implements converting between byte offsets and LSP positions.
+
+use hexbait_lang::Span;
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets in a text and the positions used by the LSP.
+///
+/// Columns in LSP positions count UTF-16 code units.
+pub(crate) struct LineIndex<'text> {
+    /// The text that positions refer to.
+    text: &'text str,
+    /// The byte offsets at which the lines of the text start.
+    line_starts: Vec<usize>,
+}
+
+impl<'text> LineIndex<'text> {
+    /// Creates an index of the lines of the text.
+    pub(crate) fn new(text: &'text str) -> LineIndex<'text> {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(pos, _)| pos + 1))
+            .collect();
+
+        LineIndex { text, line_starts }
+    }
+
+    /// Returns the position of the given byte offset.
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let column = self
+            .text
+            .get(line_start..offset)
+            .map_or(0, |prefix| prefix.encode_utf16().count());
+
+        Position::new(line as u32, column as u32)
+    }
+
+    /// Returns the byte offset of the given position.
+    ///
+    /// Positions after the end of a line refer to the end of that line.
+    pub(crate) fn offset(&self, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let line = &self.text[line_start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+
+        let mut column = 0;
+        for (pos, c) in line.char_indices() {
+            if column >= position.character as usize {
+                return line_start + pos;
+            }
+            column += c.len_utf16();
+        }
+
+        line_start + line.len()
+    }
+
+    /// Returns the range of the given span.
+    pub(crate) fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start()), self.position(span.end()))
+    }
+}
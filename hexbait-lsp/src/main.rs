@@ -0,0 +1,393 @@
+//! A language server for the hexbait format description language.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+    str::FromStr as _,
+};
+
+use hexbait_lang::{Edit, Parse, ast::AstNode as _, ir::SourceLocation, parse};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, InitializeParams, Location, MarkupContent, MarkupKind, OneOf,
+    Position, PublishDiagnosticsParams, Range, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensResult, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as _, SemanticTokensFullRequest},
+};
+
+use crate::{
+    line_index::LineIndex,
+    navigation::{METAVARIABLES, Resolved},
+};
+
+mod completion;
+mod diagnostics;
+mod line_index;
+mod navigation;
+mod semantic_tokens;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![String::from("$"), String::from(".")]),
+            ..Default::default()
+        }),
+        semantic_tokens_provider: Some(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            }
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let _: InitializeParams = serde_json::from_value(params)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.run()?;
+
+    drop(server);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// A format description that is opened in the editor.
+struct Document {
+    /// The current text of the document, which may not be saved yet.
+    text: String,
+    /// The syntax tree of the current text, which is `None` if parsing it failed.
+    parse: Option<Parse>,
+    /// The location of the document, which imports are relative to.
+    location: SourceLocation,
+}
+
+/// The state of the language server.
+struct Server {
+    /// The connection to the editor.
+    connection: Connection,
+    /// The documents that are currently opened.
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    /// Handles the messages from the editor until it shuts down the server.
+    fn run(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(req);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answers a request of the editor.
+    fn handle_request(&self, req: Request) -> Response {
+        match &*req.method {
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(req, |server, params| {
+                let position = params.text_document_position_params;
+                server.definition(&position.text_document.uri, position.position)
+            }),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(req, |server, params| {
+                let position = params.text_document_position_params;
+                server.hover(&position.text_document.uri, position.position)
+            }),
+            Completion::METHOD => self.respond::<Completion>(req, |server, params| {
+                let position = params.text_document_position;
+                server.completion(&position.text_document.uri, position.position)
+            }),
+            SemanticTokensFullRequest::METHOD => self
+                .respond::<SemanticTokensFullRequest>(req, |server, params| {
+                    server.semantic_tokens(&params.text_document.uri)
+                }),
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", req.method),
+            ),
+        }
+    }
+
+    /// Answers a request of type `R` using the given handler.
+    ///
+    /// A panic in the handler is reported as an error instead of stopping the server.
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        req: Request,
+        handler: impl FnOnce(&Server, R::Params) -> R::Result,
+    ) -> Response {
+        let params = match serde_json::from_value(req.params) {
+            Ok(params) => params,
+            Err(err) => {
+                return Response::new_err(req.id, ErrorCode::InvalidParams as i32, err.to_string());
+            }
+        };
+
+        match catch_unwind(AssertUnwindSafe(|| handler(self, params))) {
+            Ok(result) => Response::new_ok(req.id, result),
+            Err(_) => Response::new_err(
+                req.id,
+                ErrorCode::InternalError as i32,
+                String::from("the request could not be handled"),
+            ),
+        }
+    }
+
+    /// Handles a notification of the editor.
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match &*notification.method {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.update(params.text_document.uri, params.text_document.text, None)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let Some(document) = self.documents.remove(&params.text_document.uri) else {
+                    return Ok(());
+                };
+
+                // only the parts of the syntax tree that are affected by the changes are parsed again
+                let mut text = document.text;
+                let mut parse = document.parse;
+                for change in params.content_changes {
+                    let Some(range) = change.range else {
+                        text = change.text;
+                        parse = None;
+                        continue;
+                    };
+
+                    let line_index = LineIndex::new(&text);
+                    let start = line_index.offset(range.start);
+                    let end = line_index.offset(range.end).max(start);
+                    let edit = Edit {
+                        delete: start..end,
+                        insert: change.text,
+                    };
+                    parse = parse.and_then(|parse| {
+                        catch_unwind(AssertUnwindSafe(|| parse.reparse(&edit))).ok()
+                    });
+                    edit.apply(&mut text);
+                }
+                self.update(params.text_document.uri, text, parse)?;
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish_diagnostics(params.text_document.uri, Vec::new())?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Stores the new text of a document and reports the problems in it.
+    ///
+    /// The text is parsed if its syntax tree is not given.
+    fn update(
+        &mut self,
+        uri: Uri,
+        text: String,
+        parse: Option<Parse>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let location = uri_to_path(&uri).map_or(SourceLocation::Unknown, SourceLocation::Path);
+        let parse = parse.or_else(|| catch_unwind(|| hexbait_lang::parse(&text)).ok());
+        let diagnostics = parse
+            .as_ref()
+            .and_then(|parse| {
+                catch_unwind(AssertUnwindSafe(|| {
+                    diagnostics::diagnostics(&text, parse, &location)
+                }))
+                .ok()
+            })
+            .unwrap_or_else(|| {
+                vec![diagnostics::error(
+                    Range::default(),
+                    String::from("internal error while checking the format description"),
+                )]
+            });
+        self.documents.insert(
+            uri.clone(),
+            Document {
+                text,
+                parse,
+                location,
+            },
+        );
+
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Sends the problems found in a document to the editor.
+    fn publish_diagnostics(
+        &self,
+        uri: Uri,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            )))?;
+
+        Ok(())
+    }
+
+    /// Finds the definition of the name at the given position.
+    fn definition(&self, uri: &Uri, position: Position) -> Option<GotoDefinitionResponse> {
+        let document = self.documents.get(uri)?;
+        let line_index = LineIndex::new(&document.text);
+        let root = document.parse.as_ref()?.ast.syntax().clone();
+        let identifier = navigation::identifier_at(&root, line_index.offset(position))?;
+
+        let location = match navigation::resolve(&identifier)? {
+            Resolved::Item(item) => Location::new(
+                uri.clone(),
+                line_index.range(item.name()?.text_range().into()),
+            ),
+            Resolved::NamedStruct(name) => match navigation::find_named_struct(&root, &name) {
+                Some(named_struct) => Location::new(
+                    uri.clone(),
+                    line_index.range(named_struct.name()?.text_range().into()),
+                ),
+                None => {
+                    let (path, text, span) =
+                        navigation::find_imported_struct(&root, &document.location, &name)?;
+                    Location::new(path_to_uri(&path)?, LineIndex::new(&text).range(span))
+                }
+            },
+            Resolved::Metavariable(_) => return None,
+        };
+
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    /// Describes the name at the given position.
+    fn hover(&self, uri: &Uri, position: Position) -> Option<Hover> {
+        let document = self.documents.get(uri)?;
+        let line_index = LineIndex::new(&document.text);
+        let root = document.parse.as_ref()?.ast.syntax().clone();
+        let identifier = navigation::identifier_at(&root, line_index.offset(position))?;
+
+        let description = match navigation::resolve(&identifier)? {
+            Resolved::Item(item) => item.describe(),
+            Resolved::NamedStruct(name) => {
+                let named_struct = match navigation::find_named_struct(&root, &name) {
+                    Some(named_struct) => named_struct,
+                    None => {
+                        let (_, text, _) =
+                            navigation::find_imported_struct(&root, &document.location, &name)?;
+                        navigation::find_named_struct(parse(&text).ast.syntax(), &name)?
+                    }
+                };
+                let params = named_struct
+                    .struct_params()
+                    .map(|params| params.text().to_string())
+                    .unwrap_or_default();
+
+                navigation::with_doc(
+                    format!("```hbl\nstruct {name}{params}\n```"),
+                    named_struct.doc_comment(),
+                )
+            }
+            Resolved::Metavariable(name) => {
+                let (_, description) =
+                    METAVARIABLES.iter().find(|(metavar, _)| *metavar == name)?;
+                format!("```hbl\n${name}\n```\n\n{description}")
+            }
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: description,
+            }),
+            range: Some(line_index.range(identifier.text_range().into())),
+        })
+    }
+
+    /// Suggests names to type at the given position.
+    fn completion(&self, uri: &Uri, position: Position) -> Option<CompletionResponse> {
+        let document = self.documents.get(uri)?;
+        let offset = LineIndex::new(&document.text).offset(position);
+        let root = document.parse.as_ref()?.ast.syntax().clone();
+
+        Some(CompletionResponse::Array(completion::completions(
+            &root,
+            &document.text,
+            offset,
+        )))
+    }
+
+    /// Classifies the tokens of a document for highlighting.
+    fn semantic_tokens(&self, uri: &Uri) -> Option<SemanticTokensResult> {
+        let document = self.documents.get(uri)?;
+
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::semantic_tokens(&document.text, &document.parse.as_ref()?.ast),
+        }))
+    }
+}
+
+/// Returns the path of a `file:` URI.
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme()?.as_str() != "file" {
+        return None;
+    }
+
+    let path = uri.path().as_estr().decode().into_string().ok()?;
+    Some(PathBuf::from(path.into_owned()))
+}
+
+/// Returns the `file:` URI of a path.
+fn path_to_uri(path: &Path) -> Option<Uri> {
+    let mut uri = String::from("file://");
+    for byte in path.to_str()?.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    Uri::from_str(&uri).ok()
+}
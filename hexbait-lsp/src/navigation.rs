@@ -0,0 +1,293 @@
+//! Implements finding the definitions of names in format descriptions.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use hexbait_builtin_parsers::import_paths;
+use hexbait_lang::{
+    NodeKind, Span, SyntaxKind, SyntaxNode, SyntaxToken, TokenKind,
+    ast::{self, AstNode as _},
+    ir::{ImportResolver as _, SourceLocation, str_lit_content_to_bytes},
+    parse,
+};
+
+/// The metavariables with a description of their value.
+pub(crate) const METAVARIABLES: [(&str, &str); 7] = [
+    ("offset", "The current parsing offset."),
+    ("end", "The offset of the end of the current scope."),
+    ("parent", "The partially parsed parent `struct`."),
+    ("last", "The last parsed element in the current repetition."),
+    (
+        "len",
+        "The number of elements parsed so far in the current repetition.",
+    ),
+    (
+        "startof",
+        "`$startof(field)` is the absolute offset of the first byte that `field` was parsed from.",
+    ),
+    (
+        "sizeof",
+        "`$sizeof(field)` is the number of bytes that `field` was parsed from.",
+    ),
+];
+
+/// Something that can be referred to by its name within a `struct`.
+pub(crate) enum Item {
+    /// A field of the `struct`.
+    Field(ast::StructField),
+    /// A value computed by a `let` statement.
+    Let(ast::LetStatement),
+    /// A parameter of a named `struct`.
+    Param(SyntaxToken),
+}
+
+impl Item {
+    /// Returns the token of the name of the item.
+    pub(crate) fn name(&self) -> Option<SyntaxToken> {
+        match self {
+            Item::Field(field) => field.name(),
+            Item::Let(let_statement) => let_statement.name(),
+            Item::Param(param) => Some(param.clone()),
+        }
+    }
+
+    /// Describes the item in markdown.
+    pub(crate) fn describe(&self) -> String {
+        let name = self
+            .name()
+            .map(|name| name.text().to_string())
+            .unwrap_or_default();
+
+        match self {
+            Item::Field(field) => {
+                let ty = field
+                    .parse_type()
+                    .map(|ty| abbreviate(&ty.text().to_string()))
+                    .unwrap_or_default();
+
+                with_doc(format!("```hbl\n{name} {ty}\n```"), field.doc_comment())
+            }
+            Item::Let(let_statement) => {
+                let expr = let_statement
+                    .expr()
+                    .map(|expr| abbreviate(&expr.text().to_string()))
+                    .unwrap_or_default();
+
+                format!("```hbl\nlet {name} = {expr}\n```")
+            }
+            Item::Param(_) => format!("parameter `{name}`"),
+        }
+    }
+}
+
+/// Returns the identifier or metavariable name at the given offset.
+pub(crate) fn identifier_at(root: &SyntaxNode, offset: usize) -> Option<SyntaxToken> {
+    root.token_at_offset((offset as u32).into())
+        .find(|token| token.kind() == SyntaxKind::from(TokenKind::Identifier))
+}
+
+/// Returns `true` if the node is of the given kind.
+fn is(node: &SyntaxNode, kind: NodeKind) -> bool {
+    node.kind() == SyntaxKind::from(kind)
+}
+
+/// Returns the innermost node that defines the fields of a `struct` and contains the node.
+pub(crate) fn enclosing_scope(node: &SyntaxNode) -> Option<SyntaxNode> {
+    node.ancestors().find(|node| {
+        is(node, NodeKind::File)
+            || is(node, NodeKind::Struct)
+            || is(node, NodeKind::AnonymousStructParseType)
+    })
+}
+
+/// Returns the items defined in the `struct` of the given scope.
+pub(crate) fn scope_items(scope: &SyntaxNode) -> Vec<Item> {
+    let mut items = Vec::new();
+    if let Some(named_struct) = ast::Struct::cast(scope.clone())
+        && let Some(params) = named_struct.struct_params()
+    {
+        items.extend(params.params().map(Item::Param));
+    }
+    collect_items(scope, &mut items);
+
+    items
+}
+
+/// Collects the items defined in the node, including those in declarations such as `!if`.
+fn collect_items(node: &SyntaxNode, items: &mut Vec<Item>) {
+    for child in node.children() {
+        if let Some(field) = ast::StructField::cast(child.clone()) {
+            items.push(Item::Field(field));
+        } else if let Some(let_statement) = ast::LetStatement::cast(child.clone()) {
+            items.push(Item::Let(let_statement));
+        } else if [
+            NodeKind::StructBlock,
+            NodeKind::IfDeclaration,
+            NodeKind::IfChain,
+            NodeKind::ElsePart,
+            NodeKind::ElseBlock,
+            NodeKind::ScopeAtDeclaration,
+            NodeKind::ScopeInDeclaration,
+            NodeKind::ScopeDecompressedDeclaration,
+        ]
+        .into_iter()
+        .any(|kind| is(&child, kind))
+        {
+            collect_items(&child, items);
+        }
+    }
+}
+
+/// Returns the scope that `$parent` refers to in the given scope.
+pub(crate) fn parent_scope(scope: &SyntaxNode) -> Option<SyntaxNode> {
+    // named `struct`s can be parsed anywhere, so their parent is not known
+    if is(scope, NodeKind::AnonymousStructParseType) {
+        enclosing_scope(&scope.parent()?)
+    } else {
+        None
+    }
+}
+
+/// Returns the item with the given name in the scope.
+fn find_item(scope: &SyntaxNode, name: &str) -> Option<Item> {
+    scope_items(scope)
+        .into_iter()
+        .find(|item| item.name().is_some_and(|token| token.text() == name))
+}
+
+/// The meaning of an identifier in a format description.
+pub(crate) enum Resolved {
+    /// A field, `let` statement or parameter.
+    Item(Item),
+    /// The named `struct` with the given name.
+    NamedStruct(String),
+    /// The metavariable with the given name.
+    Metavariable(&'static str),
+}
+
+/// Finds out what the given identifier refers to.
+pub(crate) fn resolve(identifier: &SyntaxToken) -> Option<Resolved> {
+    let parent = identifier.parent()?;
+    let name = identifier.text();
+
+    if let Some(named) = ast::NamedParseType::cast(parent.clone()) {
+        return named
+            .name()
+            .filter(|token| token == identifier)
+            .map(|_| Resolved::NamedStruct(name.to_string()));
+    }
+    if ast::Metavar::cast(parent.clone()).is_some() {
+        return METAVARIABLES
+            .iter()
+            .find(|(metavar, _)| *metavar == name)
+            .map(|(metavar, _)| Resolved::Metavariable(metavar));
+    }
+    if is(&parent, NodeKind::Atom) {
+        return find_item(&enclosing_scope(&parent)?, name).map(Resolved::Item);
+    }
+    if let Some(field_access) = ast::FieldAccess::cast(parent.clone()) {
+        // only fields of `$parent` can be found without knowing the values of expressions
+        let ast::Expr::Metavar(metavar) = field_access.expr()? else {
+            return None;
+        };
+        if metavar.name()?.text() != "parent" {
+            return None;
+        }
+        let scope = parent_scope(&enclosing_scope(&parent)?)?;
+
+        return find_item(&scope, name).map(Resolved::Item);
+    }
+    if let Some(field) = ast::StructField::cast(parent.clone())
+        && field.name().as_ref() == Some(identifier)
+    {
+        return Some(Resolved::Item(Item::Field(field)));
+    }
+    if let Some(let_statement) = ast::LetStatement::cast(parent)
+        && let_statement.name().as_ref() == Some(identifier)
+    {
+        return Some(Resolved::Item(Item::Let(let_statement)));
+    }
+
+    None
+}
+
+/// Returns the named `struct` with the given name that is declared in the file.
+pub(crate) fn find_named_struct(root: &SyntaxNode, name: &str) -> Option<ast::Struct> {
+    root.children()
+        .filter_map(ast::Struct::cast)
+        .find(|named_struct| {
+            named_struct
+                .name()
+                .is_some_and(|token| token.text() == name)
+        })
+}
+
+/// Finds the file that declares the named `struct` among the files imported by the file at
+/// `location`, directly or indirectly.
+///
+/// Returns the path and the source of the file, along with the span of the name of the `struct`.
+pub(crate) fn find_imported_struct(
+    root: &SyntaxNode,
+    location: &SourceLocation,
+    name: &str,
+) -> Option<(PathBuf, String, Span)> {
+    let resolver = import_paths(Vec::new());
+    let mut visited = HashSet::new();
+    let mut queue = vec![(location.clone(), root.clone())];
+
+    while let Some((location, root)) = queue.pop() {
+        for path in imports(&root) {
+            let Ok(Some((imported_location, source))) = resolver.resolve(&path, &location) else {
+                continue;
+            };
+            if !visited.insert(imported_location.clone()) {
+                continue;
+            }
+
+            let imported_root = parse(&source).ast.syntax().clone();
+            if let SourceLocation::Path(path) = &imported_location
+                && let Some(name) =
+                    find_named_struct(&imported_root, name).and_then(|found| found.name())
+            {
+                return Some((
+                    path.clone(),
+                    source.into_owned(),
+                    Span::from(name.text_range()),
+                ));
+            }
+            queue.push((imported_location, imported_root));
+        }
+    }
+
+    None
+}
+
+/// Returns the paths imported by the file.
+fn imports(root: &SyntaxNode) -> impl Iterator<Item = String> {
+    root.children()
+        .filter_map(ast::ImportDeclaration::cast)
+        .filter_map(|import| {
+            let path = import.path()?;
+            let text = path.text();
+            let mut bytes = Vec::new();
+            // the leading and trailing `"` characters are not part of the content
+            str_lit_content_to_bytes(text.get(1..text.len().checked_sub(1)?)?, &mut bytes).ok()?;
+
+            String::from_utf8(bytes).ok()
+        })
+}
+
+/// Shortens text that spans multiple lines to its first line.
+fn abbreviate(text: &str) -> String {
+    match text.split_once('\n') {
+        Some((first, _)) => format!("{} ...", first.trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// Appends the documentation to the markdown description, if there is any.
+pub(crate) fn with_doc(description: String, doc: Option<String>) -> String {
+    match doc {
+        Some(doc) => format!("{description}\n\n{doc}"),
+        None => description,
+    }
+}
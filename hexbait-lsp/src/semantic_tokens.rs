@@ -0,0 +1,114 @@
+//! Implements classifying the tokens of format descriptions for highlighting in the editor.
+
+use hexbait_lang::{HighlightKind, ast, highlight};
+use lsp_types::{SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend};
+
+use crate::line_index::LineIndex;
+
+/// The token types that are reported, in the order of their indices.
+const TOKEN_TYPES: [SemanticTokenType; 11] = [
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::DECORATOR,
+];
+
+/// The token modifiers that are reported, in the order of their bits.
+const TOKEN_MODIFIERS: [SemanticTokenModifier; 3] = [
+    SemanticTokenModifier::DOCUMENTATION,
+    SemanticTokenModifier::READONLY,
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+];
+
+/// The bit of the [`SemanticTokenModifier::DOCUMENTATION`] modifier.
+const DOCUMENTATION: u32 = 1 << 0;
+/// The bit of the [`SemanticTokenModifier::READONLY`] modifier.
+const READONLY: u32 = 1 << 1;
+/// The bit of the [`SemanticTokenModifier::DEFAULT_LIBRARY`] modifier.
+const DEFAULT_LIBRARY: u32 = 1 << 2;
+
+/// Returns the token types and modifiers that are reported.
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Classifies the tokens in the format description with the given text and syntax tree.
+pub(crate) fn semantic_tokens(text: &str, file: &ast::File) -> Vec<SemanticToken> {
+    let line_index = LineIndex::new(text);
+
+    let mut tokens = Vec::new();
+    let mut prev = lsp_types::Position::new(0, 0);
+    for highlight in highlight(file) {
+        let Some((token_type, token_modifiers_bitset)) = token_type(highlight.kind) else {
+            continue;
+        };
+        let token_type = TOKEN_TYPES
+            .iter()
+            .position(|ty| *ty == token_type)
+            .expect("all reported token types are part of the legend")
+            as u32;
+
+        // tokens may not span multiple lines, so block comments are split into one token per line
+        let mut offset = highlight.span.start();
+        for line in text[highlight.span.start()..highlight.span.end()].split_inclusive('\n') {
+            let content = line.trim_end_matches(['\n', '\r']);
+            let start = line_index.position(offset);
+            offset += line.len();
+            if content.is_empty() {
+                continue;
+            }
+
+            let delta_line = start.line - prev.line;
+            let delta_start = if delta_line == 0 {
+                start.character - prev.character
+            } else {
+                start.character
+            };
+            tokens.push(SemanticToken {
+                delta_line,
+                delta_start,
+                length: content.encode_utf16().count() as u32,
+                token_type,
+                token_modifiers_bitset,
+            });
+            prev = start;
+        }
+    }
+
+    tokens
+}
+
+/// Returns the token type and the bits of the modifiers that a highlighted token is reported as.
+///
+/// Punctuation and unrecognized tokens are not reported.
+fn token_type(kind: HighlightKind) -> Option<(SemanticTokenType, u32)> {
+    let token_type = match kind {
+        HighlightKind::Comment => (SemanticTokenType::COMMENT, 0),
+        HighlightKind::DocComment => (SemanticTokenType::COMMENT, DOCUMENTATION),
+        HighlightKind::Keyword | HighlightKind::Boolean => (SemanticTokenType::KEYWORD, 0),
+        HighlightKind::Operator => (SemanticTokenType::OPERATOR, 0),
+        HighlightKind::Number | HighlightKind::Bytes => (SemanticTokenType::NUMBER, 0),
+        HighlightKind::String => (SemanticTokenType::STRING, 0),
+        HighlightKind::Type => (SemanticTokenType::TYPE, 0),
+        HighlightKind::Field => (SemanticTokenType::PROPERTY, 0),
+        HighlightKind::Variable => (SemanticTokenType::VARIABLE, 0),
+        HighlightKind::Parameter => (SemanticTokenType::PARAMETER, 0),
+        HighlightKind::Constant => (SemanticTokenType::VARIABLE, READONLY),
+        HighlightKind::Function => (SemanticTokenType::FUNCTION, DEFAULT_LIBRARY),
+        HighlightKind::Metavariable => (SemanticTokenType::VARIABLE, DEFAULT_LIBRARY),
+        HighlightKind::Attribute => (SemanticTokenType::DECORATOR, 0),
+        HighlightKind::Punctuation | HighlightKind::Error => return None,
+    };
+
+    Some(token_type)
+}
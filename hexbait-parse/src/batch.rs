@@ -0,0 +1,159 @@
+//! Implements parsing many files at once, printing one JSON object per file.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use hexbait_common::{Input, RelativeOffset};
+use hexbait_lang::{
+    Coverage, EvalLimits, EvalOptions, Provenance, eval_ir_with_options, ir::File, value_to_json,
+};
+use serde_json::json;
+
+use crate::{parsed_range, select::Query, view_of};
+
+/// The options that determine what is printed for each file.
+pub struct BatchOptions<'a> {
+    /// Whether every value is wrapped in an object with its offset and size.
+    pub with_offsets: bool,
+    /// The query selecting the printed values, if only some are printed.
+    pub select: Option<&'a Query>,
+    /// Whether the percentage of the file that was parsed is printed.
+    pub coverage: bool,
+    /// The offset in each file at which parsing starts.
+    pub offset: u64,
+    /// The number of bytes after the offset that are parsed, if not all of them are parsed.
+    pub length: Option<u64>,
+    /// The limits on the resources that parsing each file may use.
+    pub limits: EvalLimits,
+}
+
+/// Returns the files at the given paths, looking for files matching `glob` in directories.
+///
+/// Directories are searched recursively and their files are returned in sorted order.
+pub fn collect_files(paths: &[PathBuf], glob: Option<&str>) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_dir(path, glob, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Adds the files in `dir` whose names match `glob` to `files`.
+fn collect_dir(dir: &Path, glob: Option<&str>, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_dir(&path, glob, files)?;
+        } else if glob.is_none_or(|glob| {
+            path.file_name()
+                .is_some_and(|name| glob_matches(glob.as_bytes(), name.as_encoded_bytes()))
+        }) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Determines if `name` matches `glob`, where `*` matches any number of bytes and `?` a single one.
+fn glob_matches(glob: &[u8], name: &[u8]) -> bool {
+    match (glob.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some((b'*', rest)), _) => {
+            glob_matches(rest, name) || (!name.is_empty() && glob_matches(glob, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => glob_matches(rest, name_rest),
+        (Some((expected, rest)), Some((byte, name_rest))) => {
+            expected == byte && glob_matches(rest, name_rest)
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// Parses the file at `path` and returns the result as a JSON object.
+///
+/// The object contains the `file`, the `status` of the parse, which is `ok`, `error` if parsing
+/// failed somewhere or `unreadable` if the file cannot be read, the `errors` and `warnings` with
+/// their messages and offsets and the parsed `value`.
+pub fn parse_file(parser: &File, path: &Path, options: &BatchOptions) -> serde_json::Value {
+    let failure = |status: &str, message: String| {
+        json!({
+            "file": path.display().to_string(),
+            "status": status,
+            "errors": [{ "message": message, "offset": null }],
+            "warnings": [],
+            "value": null,
+        })
+    };
+
+    let input = match Input::from_path(path) {
+        Ok(input) => input,
+        Err(err) => return failure("unreadable", err.to_string()),
+    };
+    let input_range = match parsed_range(input.len(), options.offset, options.length) {
+        Ok(range) => range,
+        Err(err) => return failure("error", err),
+    };
+
+    let eval_options = EvalOptions {
+        limits: options.limits,
+        ..EvalOptions::default()
+    };
+    let result = eval_ir_with_options(
+        parser,
+        view_of(input, input_range),
+        RelativeOffset::ZERO,
+        eval_options,
+    );
+
+    let value = match options.select {
+        Some(query) => {
+            let mut selected = Vec::new();
+            query.select(&result.value, &mut |value| {
+                selected.push(value_to_json(value, options.with_offsets));
+            });
+            serde_json::Value::Array(selected)
+        }
+        None => value_to_json(&result.value, options.with_offsets),
+    };
+    let mut out = json!({
+        "file": path.display().to_string(),
+        "status": if result.errors.is_empty() { "ok" } else { "error" },
+        "errors": result
+            .errors
+            .iter()
+            .map(|err| problem_to_json(&err.message, &err.provenance))
+            .collect::<Vec<_>>(),
+        "warnings": result
+            .warnings
+            .iter()
+            .map(|warning| problem_to_json(&warning.message, &warning.provenance))
+            .collect::<Vec<_>>(),
+        "value": value,
+    });
+    if options.coverage {
+        out["coverage"] = json!(Coverage::new(&result.value, input_range).ratio());
+    }
+
+    out
+}
+
+/// Returns an error or warning as a JSON object with its `message` and the `offset` of its first
+/// byte.
+pub fn problem_to_json(message: &str, provenance: &Provenance) -> serde_json::Value {
+    json!({
+        "message": message,
+        "offset": provenance.byte_ranges().next().map(|range| range.start().as_u64()),
+    })
+}
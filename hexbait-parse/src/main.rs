@@ -2,35 +2,149 @@
 //!
 //! This also serves as a testing ground for an eventual integration into hexbait itself.
 
-use std::{char, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::IsTerminal as _,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
-use hexbait_builtin_parsers::built_in_format_descriptions;
-use hexbait_common::{Input, RelativeOffset};
-use hexbait_lang::{Value, View, eval_ir, ir::lower_file, parse};
-use serde_json::Number;
+use hexbait_builtin_parsers::{BuiltinError, built_in_format_descriptions, import_paths};
+use hexbait_common::{AbsoluteOffset, AbsoluteRange, Input, Len, RelativeOffset};
+use hexbait_lang::{
+    Coverage, DEFAULT_MAX_DEPTH, Diagnostic, EvalLimits, EvalOptions, Value, ValueKind, View,
+    check_ir, eval_ir_with_options, format,
+    ir::{File, SourceLocation, leading_magic, lower_file, resolve_imports, to_ksy},
+    parse, translate_c_header, translate_hexpat, value_to_json,
+};
+use serde_json::json;
+
+mod batch;
+mod scan;
+mod select;
+mod tree;
+mod watch;
 
 /// hexbait-parser - parses bytes to json according to .hbl-definitions
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Config {
-    /// The file to parse, stdin if omitted
-    file: Option<PathBuf>,
+    /// The files to parse, stdin if omitted
+    ///
+    /// If multiple files or a directory are given, a JSON object with the file name, the parse
+    /// status, the errors and the parsed value is printed per line for each file.
+    files: Vec<PathBuf>,
+    /// Only parses the files in the given directories whose names match this pattern, where `*`
+    /// matches any characters and `?` a single one
+    #[arg(long, value_name = "PATTERN")]
+    glob: Option<String>,
     /// Lists possible definitions
     #[arg(short, long)]
     list: bool,
     /// What to parse in the input
     #[arg(short, long)]
     parse_as: Option<String>,
-    /// A custom parser to use
+    /// A custom parser to use, either a format description or an ImHex pattern (`.hexpat`)
     #[arg(short, long)]
     custom: Option<PathBuf>,
+    /// A directory to look up files imported by the custom parser in
+    #[arg(short = 'I', long)]
+    import_dir: Vec<PathBuf>,
+    /// Prints the documentation of the parsed fields to stderr
+    #[arg(short, long)]
+    verbose: bool,
+    /// The offset in the input at which parsing starts, in decimal or hexadecimal with a `0x` prefix
+    #[arg(long, value_name = "OFFSET", value_parser = parse_number, default_value = "0")]
+    offset: u64,
+    /// The number of bytes after the offset that are parsed, up to the end of the input if omitted
+    #[arg(long, value_name = "LENGTH", value_parser = parse_number)]
+    length: Option<u64>,
+    /// Prints the parsed value as JSON instead of as a tree, with the warnings on stderr
+    #[arg(long)]
+    json: bool,
+    /// Wraps every value in the JSON output in an object with the absolute `offset` of its first
+    /// byte and its `size`, which implies `--json`
+    #[arg(long)]
+    with_offsets: bool,
+    /// Prints only the values selected by a query like `header.num_of_sections` or
+    /// `sections[*].name`, one per line, where strings are printed without quotes
+    #[arg(short, long, value_name = "QUERY")]
+    select: Option<select::Query>,
+    /// Searches the input for the magic bytes of the format and prints every instance of it that
+    /// is parsed without errors at them, together with its offset; formats without magic bytes at
+    /// a fixed offset cannot be scanned and exit with an error
+    #[arg(long, conflicts_with_all = ["select", "coverage", "watch"])]
+    scan: bool,
+    /// Prints the percentage of the input that was parsed to stderr
+    #[arg(long)]
+    coverage: bool,
+    /// The maximum number of `struct`s that are parsed within each other before parsing fails
+    #[arg(long, value_name = "DEPTH", default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    /// The maximum number of elements of a single array before parsing fails, unlimited if omitted
+    #[arg(long, value_name = "COUNT")]
+    max_repetitions: Option<usize>,
+    /// The maximum number of bytes that are read from the input before parsing fails, unlimited if
+    /// omitted
+    #[arg(long, value_name = "LENGTH", value_parser = parse_number)]
+    max_bytes: Option<u64>,
+    /// Checks the format description at the given path for problems without parsing any input,
+    /// exiting with a nonzero exit code if there are any
+    #[arg(long, value_name = "PATH")]
+    check: Option<PathBuf>,
+    /// Parses and prints the input file again whenever it or the custom parser changes
+    #[arg(short, long, conflicts_with_all = ["check", "fmt", "ksy", "from_c_header", "list"])]
+    watch: bool,
+    /// Formats the format description at the given path in place instead of parsing
+    #[arg(long, value_name = "PATH")]
+    fmt: Option<PathBuf>,
+    /// Prints the format description as a Kaitai Struct definition instead of parsing
+    #[arg(long)]
+    ksy: bool,
+    /// Prints a format description for the C `struct`s in the header at the given path instead of
+    /// parsing
+    #[arg(long, value_name = "PATH")]
+    from_c_header: Option<PathBuf>,
+    /// The `struct` of the C header that the printed format description parses
+    #[arg(long, value_name = "STRUCT", requires = "from_c_header")]
+    root: Option<String>,
 }
 
 /// The entry point for the application.
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::parse();
 
+    if let Some(path) = &config.fmt {
+        let content = std::fs::read_to_string(path)?;
+        match format(&content) {
+            Ok(formatted) => {
+                if formatted != content {
+                    std::fs::write(path, formatted)?;
+                }
+                std::process::exit(0);
+            }
+            Err(errors) => {
+                exit_with_diagnostics(path, &content, errors.iter().map(Diagnostic::from))
+            }
+        }
+    }
+
+    if let Some(path) = &config.check {
+        load_custom(path, &config.import_dir)?;
+        std::process::exit(0);
+    }
+
+    if let Some(path) = &config.from_c_header {
+        let content = std::fs::read_to_string(path)?;
+        let translation = translate_c_header(&content, config.root.as_deref());
+        print!("{}", translation.hbl);
+        for problem in &translation.unsupported {
+            let line = content[..problem.span.start()].matches('\n').count() + 1;
+            eprintln!("warning: {} ({}:{line})", problem.inner, path.display());
+        }
+        std::process::exit(0);
+    }
+
     let mut builtin = built_in_format_descriptions();
 
     if config.list {
@@ -44,84 +158,387 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
-    let parser = match (config.custom, config.parse_as) {
-        (Some(path), _) => {
-            let content = std::fs::read_to_string(path)?;
-
-            let parse = parse(&content);
-            // TODO: handle errors better here
-            assert!(parse.errors.is_empty());
-
-            lower_file(parse.ast)
-        }
-        (None, Some(name)) => {
-            if let Some(parser) = builtin.remove(&*name) {
-                parser
-            } else {
-                eprintln!("unknown definition name: {name}, exiting...");
+    if config.watch {
+        let path = match &*config.files {
+            [path] if !path.is_dir() => path,
+            _ => {
+                eprintln!("--watch requires exactly one input file, exiting...");
                 std::process::exit(1);
             }
+        };
+        let parser = match &config.custom {
+            Some(_) => None,
+            None => Some(builtin_parser(&mut builtin, config.parse_as.as_deref())),
+        };
+        return watch::watch(&config, path, parser);
+    }
+
+    let ksy_id = config
+        .custom
+        .as_ref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .or_else(|| config.parse_as.clone());
+
+    let parser = match &config.custom {
+        Some(path) => load_custom(path, &config.import_dir)?,
+        None => builtin_parser(&mut builtin, config.parse_as.as_deref()),
+    };
+
+    if config.ksy {
+        let export = to_ksy(&parser, ksy_id.as_deref().unwrap_or("format"));
+        print!("{}", export.ksy);
+        for problem in &export.unsupported {
+            eprintln!("warning: {}", problem.inner);
         }
-        (None, None) => {
-            eprintln!("no definition to parse as specified, exiting...");
+        std::process::exit(0);
+    }
+
+    if config.files.len() > 1 || config.files.iter().any(|path| path.is_dir()) {
+        if config.scan {
+            eprintln!("--scan requires a single input, exiting...");
             std::process::exit(1);
         }
-    };
 
-    let input = match config.file {
+        let options = batch::BatchOptions {
+            with_offsets: config.with_offsets,
+            select: config.select.as_ref(),
+            coverage: config.coverage,
+            offset: config.offset,
+            length: config.length,
+            limits: limits(&config),
+        };
+        for path in batch::collect_files(&config.files, config.glob.as_deref())? {
+            println!("{}", batch::parse_file(&parser, &path, &options));
+        }
+
+        return Ok(());
+    }
+
+    let input = match config.files.first() {
         Some(path) => Input::from_path(path)?,
         None => Input::from_stdin()?,
     };
-    let view = View::from_input(input);
+    if config.scan {
+        return print_scan(&parser, input, &config);
+    }
+    if let Err(err) = print_parse(&parser, input, &config) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Parses the input with `parser` and prints the result as specified in the config.
+///
+/// Returns an error message if the parsed range is not within the input or if no value matches
+/// the `--select` query.
+fn print_parse(parser: &File, input: Input, config: &Config) -> Result<(), String> {
+    let input_range = parsed_range(input.len(), config.offset, config.length)?;
+    let view = view_of(input, input_range);
+
+    let options = EvalOptions {
+        limits: limits(config),
+        ..EvalOptions::default()
+    };
+    let parse_result = eval_ir_with_options(parser, view, RelativeOffset::ZERO, options);
+    let result = &parse_result.value;
+
+    // the tree shows the warnings itself, but the other outputs only contain values
+    if config.select.is_some() || config.json || config.with_offsets {
+        for warning in &parse_result.warnings {
+            match warning.provenance.byte_ranges().next() {
+                Some(range) => eprintln!(
+                    "warning: {} (at {:#x})",
+                    warning.message,
+                    range.start().as_u64()
+                ),
+                None => eprintln!("warning: {}", warning.message),
+            }
+        }
+    }
 
-    let result = eval_ir(&parser, view, RelativeOffset::ZERO).value;
-    let as_json = value_to_json(&result);
+    if let Some(query) = &config.select {
+        let mut found = false;
+        query.select(result, &mut |value| {
+            found = true;
+            let json = value_to_json(value, config.with_offsets);
+            match json.as_str() {
+                Some(string) => println!("{string}"),
+                None => println!("{json}"),
+            }
+        });
+        if !found {
+            return Err("no value matches the query".to_string());
+        }
+    } else if config.json || config.with_offsets {
+        println!("{}", value_to_json(result, config.with_offsets));
+    } else {
+        let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        print!("{}", tree::render_tree(&parse_result, color));
+    }
 
-    println!("{}", as_json);
+    if config.verbose {
+        print_docs(&mut String::new(), result, &mut HashSet::new());
+    }
+
+    if config.coverage {
+        let coverage = Coverage::new(result, input_range);
+        eprintln!(
+            "coverage: {:.2}% ({} of {} bytes parsed)",
+            coverage.ratio() * 100.0,
+            coverage.parsed_len().as_u64(),
+            input_range.size().as_u64(),
+        );
+    }
 
     Ok(())
 }
 
-/// Converts the given parsed value to JSON.
-fn value_to_json(value: &Value) -> serde_json::Value {
-    match &value.kind {
-        hexbait_lang::ValueKind::Boolean(val) => serde_json::Value::Bool(*val),
-        hexbait_lang::ValueKind::Integer(val) => {
-            let num = if let Ok(num) = u128::try_from(val) {
-                Number::from_u128(num)
-            } else if let Ok(num) = i128::try_from(val) {
-                Number::from_i128(num)
-            } else {
-                Number::from_str(&val.to_string()).ok()
-            };
-            num.map(serde_json::Value::Number)
-                .unwrap_or(serde_json::Value::Null)
-        }
-        hexbait_lang::ValueKind::Float(val) => serde_json::Number::from_f64(*val)
-            .map(serde_json::Value::Number)
-            .unwrap_or(serde_json::Value::Null),
-        hexbait_lang::ValueKind::Bytes(val) => {
-            let mut as_str = String::new();
-            for byte in &*val.value().unwrap() {
-                for bit in (0..8).step_by(4).rev() {
-                    let nibble = (byte >> bit) & 0xf;
-                    let c = char::from_digit(nibble as u32, 16).unwrap();
-                    as_str.push(c);
-                }
+/// Searches the input for instances of the format and prints each of them with its offset.
+///
+/// The program exits with a nonzero exit code if no instance is found.
+fn print_scan(
+    parser: &File,
+    input: Input,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(magic) = leading_magic(parser) else {
+        eprintln!(
+            "the format description expects no magic bytes at a fixed offset to scan for, exiting..."
+        );
+        std::process::exit(1);
+    };
+    let input_range = match parsed_range(input.len(), config.offset, config.length) {
+        Ok(range) => range,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let limits = limits(config);
+    let mut found = 0;
+    scan::scan(parser, &magic, &input, input_range, limits, |instance| {
+        if config.json || config.with_offsets {
+            println!(
+                "{}",
+                json!({
+                    "offset": instance.range.start().as_u64(),
+                    "size": instance.range.size().as_u64(),
+                    "value": value_to_json(&instance.result.value, config.with_offsets),
+                    "warnings": instance
+                        .result
+                        .warnings
+                        .iter()
+                        .map(|warning| {
+                            batch::problem_to_json(&warning.message, &warning.provenance)
+                        })
+                        .collect::<Vec<_>>(),
+                })
+            );
+        } else {
+            if found > 0 {
+                println!();
             }
-            serde_json::Value::String(as_str)
+            println!(
+                "{:#x}..{:#x}:",
+                instance.range.start().as_u64(),
+                instance.range.end().as_u64(),
+            );
+            print!("{}", tree::render_tree(&instance.result, color));
+        }
+        found += 1;
+    })?;
+
+    if found == 0 {
+        eprintln!("no instance of the format was found");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Returns the built-in format description with the given name.
+///
+/// If there is no such format description, an error is printed to stderr, after which the program
+/// exits.
+fn builtin_parser(
+    builtin: &mut BTreeMap<&'static str, Result<File, BuiltinError>>,
+    name: Option<&str>,
+) -> File {
+    let Some(name) = name else {
+        eprintln!("no definition to parse as specified, exiting...");
+        std::process::exit(1);
+    };
+
+    match builtin.remove(name) {
+        Some(Ok(parser)) => parser,
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("unknown definition name: {name}, exiting...");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Loads the custom parser at `path`, which is either a format description or an ImHex pattern.
+///
+/// Problems in the format description are printed to stderr, after which the program exits.
+fn load_custom(path: &Path, import_dirs: &[PathBuf]) -> Result<File, Box<dyn std::error::Error>> {
+    match try_load_custom(path, import_dirs)? {
+        Some(ir) => Ok(ir),
+        None => std::process::exit(1),
+    }
+}
+
+/// Loads the custom parser at `path`, which is either a format description or an ImHex pattern.
+///
+/// Problems in the format description are printed to stderr, in which case `None` is returned.
+fn try_load_custom(
+    path: &Path,
+    import_dirs: &[PathBuf],
+) -> Result<Option<File>, Box<dyn std::error::Error>> {
+    let mut content = std::fs::read_to_string(path)?;
+    if path.extension() == Some("hexpat".as_ref()) {
+        let translation = translate_hexpat(&content);
+        for problem in &translation.unsupported {
+            let line = content[..problem.span.start()].matches('\n').count() + 1;
+            eprintln!("warning: {} ({}:{line})", problem.inner, path.display());
         }
-        hexbait_lang::ValueKind::Struct { fields, .. } => {
-            let mut object = serde_json::Map::new();
+        content = translation.hbl;
+    }
+
+    let parse = parse(&content);
+    if !parse.errors.is_empty() {
+        print_diagnostics(path, &content, parse.errors.iter().map(Diagnostic::from));
+        return Ok(None);
+    }
+
+    let mut ir = lower_file(parse.ast);
+    let location = SourceLocation::Path(path.canonicalize()?);
+    resolve_imports(&mut ir, &location, &import_paths(import_dirs.to_vec()))?;
+    if let Err(err) = check_ir(&ir) {
+        print_diagnostics(path, &content, err.problems.iter().map(Diagnostic::from));
+        return Ok(None);
+    }
+
+    Ok(Some(ir))
+}
+
+/// Returns the limits on the resources that parsing may use, as specified in the config.
+fn limits(config: &Config) -> EvalLimits {
+    EvalLimits {
+        max_repetitions: config.max_repetitions,
+        max_depth: Some(config.max_depth),
+        max_bytes: config.max_bytes,
+    }
+}
+
+/// Parses a number given in decimal or in hexadecimal with a `0x` prefix.
+fn parse_number(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|err| err.to_string())
+}
+
+/// Returns the range of an input of length `input_len` that is parsed.
+///
+/// The range starts at `offset` and is `length` bytes long, or extends to the end of the input if
+/// no length is given.
+fn parsed_range(input_len: Len, offset: u64, length: Option<u64>) -> Result<AbsoluteRange, String> {
+    let input_len = input_len.as_u64();
+    if offset > input_len {
+        return Err(format!(
+            "the offset {offset:#x} is beyond the end of the input ({input_len:#x} bytes)"
+        ));
+    }
+
+    let length = length.unwrap_or(input_len - offset);
+    if length > input_len - offset {
+        return Err(format!(
+            "the range {offset:#x}..{:#x} extends beyond the end of the input ({input_len:#x} bytes)",
+            offset.saturating_add(length),
+        ));
+    }
+
+    Ok(AbsoluteRange::from_start_len(
+        AbsoluteOffset::from(offset),
+        Len::from(length),
+    ))
+}
+
+/// Returns a view of the given range of the input, in which offsets start at the range.
+fn view_of(input: Input, range: AbsoluteRange) -> View {
+    View::from_input(input).subview(range.start().to_relative()..range.end().to_relative())
+}
+
+/// Prints the given problems in the format description at `path` to stderr and exits.
+fn exit_with_diagnostics(
+    path: &Path,
+    src: &str,
+    diagnostics: impl Iterator<Item = Diagnostic>,
+) -> ! {
+    print_diagnostics(path, src, diagnostics);
+
+    std::process::exit(1);
+}
+
+/// Prints the given problems in the format description at `path` to stderr.
+fn print_diagnostics(path: &Path, src: &str, diagnostics: impl Iterator<Item = Diagnostic>) {
+    let name = path.display().to_string();
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(&name, src));
+    }
+}
 
-            for (name, val) in fields {
-                object.insert(name.as_str().to_string(), value_to_json(val));
+/// Prints the documentation of all documented values within the given value to stderr.
+///
+/// Array indices are omitted from the printed paths, so that the documentation of a field is only
+/// printed once, no matter how many array elements contain it.
+fn print_docs(path: &mut String, value: &Value, printed: &mut HashSet<String>) {
+    if let Some(doc) = &value.doc
+        && printed.insert(path.clone())
+    {
+        eprintln!("{path}:");
+        for line in doc.lines() {
+            if line.is_empty() {
+                eprintln!();
+            } else {
+                eprintln!("    {line}");
             }
+        }
+    }
 
-            serde_json::Value::Object(object)
+    let len = path.len();
+    match &value.kind {
+        ValueKind::Struct { fields, .. } => {
+            for (name, value) in fields {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(name.as_str());
+                print_docs(path, value, printed);
+                path.truncate(len);
+            }
         }
-        hexbait_lang::ValueKind::Array { items, .. } => {
-            serde_json::Value::Array(items.iter().map(value_to_json).collect())
+        ValueKind::Array { items, .. } => {
+            path.push_str("[]");
+            for item in items {
+                print_docs(path, item, printed);
+            }
+            path.truncate(len);
         }
+        ValueKind::Boolean(_)
+        | ValueKind::Integer(_)
+        | ValueKind::Float(_)
+        | ValueKind::Bytes(_) => {}
     }
 }
@@ -0,0 +1,99 @@
+//! Implements searching the input for instances of a format at the offsets of its magic bytes.
+
+use std::io;
+
+use hexbait_common::{AbsoluteOffset, AbsoluteRange, Input, Len, RelativeOffset};
+use hexbait_lang::{
+    Coverage, EvalLimits, EvalOptions, ParseResult, eval_ir_with_options,
+    ir::{File, LeadingMagic},
+};
+
+use crate::view_of;
+
+/// The size of the chunks in which the input is searched for the magic bytes.
+const SCAN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// An instance of the format that was parsed successfully.
+pub struct Instance {
+    /// The bytes that the instance was parsed from, from its start to its last parsed byte.
+    pub range: AbsoluteRange,
+    /// The result of parsing the instance.
+    pub result: ParseResult,
+}
+
+/// Searches `range` of the input for the magic bytes of the format and parses an instance of it
+/// at every match.
+///
+/// `found` is called with every instance that is parsed without errors, in the order of their
+/// offsets. Each instance is parsed with the given limits as if the input started at it and ended
+/// at the end of `range`.
+pub fn scan(
+    parser: &File,
+    magic: &LeadingMagic,
+    input: &Input,
+    range: AbsoluteRange,
+    limits: EvalLimits,
+    mut found: impl FnMut(Instance),
+) -> io::Result<()> {
+    let start = range.start().as_u64();
+    let end = range.end().as_u64();
+    let magic_len = magic.pattern.len() as u64;
+    let mut buf = Vec::new();
+
+    let mut chunk_start = start;
+    while chunk_start < end {
+        // the chunk overlaps the next one by the length of the magic bytes, so that magic bytes
+        // crossing the boundary are found
+        let chunk_len = (SCAN_CHUNK_SIZE + magic_len).min(end - chunk_start);
+        let chunk = input.read_at(
+            AbsoluteOffset::from(chunk_start),
+            Len::from(chunk_len),
+            Some(&mut buf),
+        )?;
+
+        let checked_len = (chunk.len() as u64).min(SCAN_CHUNK_SIZE) as usize;
+        for i in 0..checked_len {
+            let Some(bytes) = chunk.get(i..i + magic.pattern.len()) else {
+                break;
+            };
+            let Some(instance_start) = (chunk_start + i as u64)
+                .checked_sub(magic.offset)
+                .filter(|&instance_start| instance_start >= start)
+            else {
+                continue;
+            };
+            if !magic.pattern.matches(bytes) {
+                continue;
+            }
+
+            let instance_range = AbsoluteRange::new(
+                AbsoluteOffset::from(instance_start),
+                AbsoluteOffset::from(end),
+            );
+            let options = EvalOptions {
+                limits,
+                ..EvalOptions::default()
+            };
+            let result = eval_ir_with_options(
+                parser,
+                view_of(input.clone(), instance_range),
+                RelativeOffset::ZERO,
+                options,
+            );
+            if result.errors.is_empty() {
+                let parsed_end = Coverage::of_value(&result.value)
+                    .range()
+                    .end()
+                    .max(instance_range.start());
+                found(Instance {
+                    range: AbsoluteRange::new(instance_range.start(), parsed_end),
+                    result,
+                });
+            }
+        }
+
+        chunk_start += SCAN_CHUNK_SIZE;
+    }
+
+    Ok(())
+}
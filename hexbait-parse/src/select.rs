@@ -0,0 +1,137 @@
+//! Implements selecting parts of a parsed value with queries like `sections[*].name`.
+
+use std::{fmt, str::FromStr};
+
+use hexbait_lang::{Value, ValueKind};
+
+/// A query selecting the parts of a parsed value that are printed.
+///
+/// A query consists of field names separated by dots, each optionally followed by array indices in
+/// brackets. A `*` in place of a field name or an index selects all fields or elements.
+#[derive(Debug, Clone)]
+pub struct Query {
+    /// The steps that lead from the parsed value to the selected values.
+    steps: Vec<Step>,
+}
+
+/// A single step of a query.
+#[derive(Debug, Clone)]
+enum Step {
+    /// Selects the field with the given name of a `struct`.
+    Field(String),
+    /// Selects all fields of a `struct`.
+    AnyField,
+    /// Selects the element at the given index of an array.
+    Index(usize),
+    /// Selects all elements of an array.
+    AnyIndex,
+}
+
+/// A query that could not be parsed.
+#[derive(Debug, Clone)]
+pub struct InvalidQuery(String);
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+impl FromStr for Query {
+    type Err = InvalidQuery;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+
+        // a leading dot is allowed, as in `jq`
+        let query = query.strip_prefix('.').unwrap_or(query);
+        for (i, segment) in query.split('.').enumerate() {
+            let (name, mut indices) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+
+            match name {
+                // only the first segment may start with an index directly
+                "" if i == 0 && !indices.is_empty() => {}
+                "" => return Err(InvalidQuery(format!("empty field name in `{query}`"))),
+                "*" => steps.push(Step::AnyField),
+                name => steps.push(Step::Field(name.to_string())),
+            }
+
+            while !indices.is_empty() {
+                let Some((index, rest)) = indices
+                    .strip_prefix('[')
+                    .and_then(|indices| indices.split_once(']'))
+                else {
+                    return Err(InvalidQuery(format!("malformed index in `{segment}`")));
+                };
+
+                if index == "*" {
+                    steps.push(Step::AnyIndex);
+                } else {
+                    let index = index
+                        .parse()
+                        .map_err(|_| InvalidQuery(format!("invalid index `{index}`")))?;
+                    steps.push(Step::Index(index));
+                }
+                indices = rest;
+            }
+        }
+
+        Ok(Query { steps })
+    }
+}
+
+impl Query {
+    /// Calls `f` with each value within `value` that the query selects.
+    pub fn select(&self, value: &Value, f: &mut impl FnMut(&Value)) {
+        select_steps(&self.steps, value, f);
+    }
+}
+
+/// Calls `f` with each value within `value` that the given steps select.
+fn select_steps(steps: &[Step], value: &Value, f: &mut impl FnMut(&Value)) {
+    let Some((step, rest)) = steps.split_first() else {
+        f(value);
+        return;
+    };
+
+    match (step, &value.kind) {
+        (Step::Field(name), ValueKind::Struct { fields, .. }) => {
+            for (_, value) in fields.iter().filter(|(field, _)| field.as_str() == name) {
+                select_steps(rest, value, f);
+            }
+        }
+        (Step::AnyField, ValueKind::Struct { fields, .. }) => {
+            for (_, value) in fields {
+                select_steps(rest, value, f);
+            }
+        }
+        (Step::Index(index), ValueKind::Array { items, lazy, .. }) => {
+            if let Some(item) = items.get(*index) {
+                select_steps(rest, item, f);
+            } else if let Some(lazy) = lazy
+                && index - items.len() < lazy.len()
+            {
+                // elements of long arrays are only parsed when they are needed
+                let index = index - items.len();
+                let elements = lazy.evaluate(index..index + 1).value;
+                for item in elements.kind.expect_array() {
+                    select_steps(rest, item, f);
+                }
+            }
+        }
+        (Step::AnyIndex, ValueKind::Array { items, lazy, .. }) => {
+            for item in items {
+                select_steps(rest, item, f);
+            }
+            if let Some(lazy) = lazy {
+                let elements = lazy.evaluate(0..lazy.len()).value;
+                for item in elements.kind.expect_array() {
+                    select_steps(rest, item, f);
+                }
+            }
+        }
+        _ => {}
+    }
+}
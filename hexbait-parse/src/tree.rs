@@ -0,0 +1,259 @@
+//! Implements printing parsed values as an indented tree that is meant to be read in a terminal.
+
+use std::fmt::{self, Write as _};
+
+use hexbait_lang::{BytesValue, ParseErr, ParseErrId, ParseResult, Value, ValueKind};
+
+/// The longest bytes that are checked for whether they are text.
+const MAX_TEXT_LEN: usize = 256;
+
+/// The ANSI escape sequence for the names of fields without a color.
+const NAME_STYLE: &str = "1";
+/// The ANSI escape sequence for numbers and booleans.
+const NUMBER_STYLE: &str = "36";
+/// The ANSI escape sequence for bytes.
+const BYTES_STYLE: &str = "32";
+/// The ANSI escape sequence for values that are displayed by name or by their classification.
+const CLASSIFIED_STYLE: &str = "33";
+/// The ANSI escape sequence for the offsets of values.
+const OFFSET_STYLE: &str = "2";
+/// The ANSI escape sequence for parse errors.
+const ERROR_STYLE: &str = "31";
+/// The ANSI escape sequence for parse warnings.
+const WARNING_STYLE: &str = "1;33";
+
+/// Returns the parse result as an indented tree with one value per line.
+///
+/// Each line contains the name of a field, its value displayed according to its classification
+/// and the offset of its first byte. The warnings of the parse result, such as failed checksum
+/// verifications, follow the tree. If `color` is set, the tree is colored with ANSI escape
+/// sequences, where fields are shown in the color that the format description gives them.
+pub fn render_tree(result: &ParseResult, color: bool) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        color,
+    };
+
+    // the fields of the top level `struct` are not nested in anything
+    match &result.value.kind {
+        ValueKind::Struct { fields, error } => {
+            for (name, value) in fields {
+                printer.write_value(name.as_str(), value, &result.errors, 0);
+            }
+            printer.write_error(
+                *error,
+                fields.last().map(|(_, last)| last),
+                &result.errors,
+                0,
+            );
+        }
+        _ => printer.write_value("value", &result.value, &result.errors, 0),
+    }
+
+    for warning in &result.warnings {
+        printer.paint(WARNING_STYLE, format_args!("warning: {}", warning.message));
+        if let Some(range) = warning.provenance.byte_ranges().next() {
+            printer.out.push_str("  ");
+            printer.paint(OFFSET_STYLE, format_args!("@{:#x}", range.start().as_u64()));
+        }
+        printer.out.push('\n');
+    }
+
+    printer.out
+}
+
+/// Writes parsed values as a tree.
+struct Printer {
+    /// The tree written so far.
+    out: String,
+    /// Whether the tree is colored with ANSI escape sequences.
+    color: bool,
+}
+
+impl Printer {
+    /// Writes `text` in the style given by the ANSI escape sequence `style`.
+    fn paint(&mut self, style: &str, text: impl fmt::Display) {
+        if self.color {
+            let _ = write!(self.out, "\x1b[{style}m{text}\x1b[0m");
+        } else {
+            let _ = write!(self.out, "{text}");
+        }
+    }
+
+    /// Writes the value of the field `name` and everything within it, indented by `indent` levels.
+    ///
+    /// `errors` are the errors that the error IDs in the value refer to.
+    fn write_value(&mut self, name: &str, value: &Value, errors: &[ParseErr], indent: usize) {
+        let _ = write!(self.out, "{:width$}", "", width = indent * 2);
+        match &value.color {
+            Some(color) => {
+                let [r, g, b] = color.rgb();
+                self.paint(&format!("1;38;2;{r};{g};{b}"), name);
+            }
+            None => self.paint(NAME_STYLE, name),
+        }
+        self.out.push(':');
+
+        let classified = value
+            .classification
+            .and_then(|classification| classification.format(&value.kind));
+
+        match (&value.kind, classified) {
+            (_, Some(classified)) => {
+                self.out.push(' ');
+                self.paint(CLASSIFIED_STYLE, classified);
+            }
+            (ValueKind::Integer(int), None) => {
+                self.out.push(' ');
+                match &value.name {
+                    Some(symbol) => {
+                        self.paint(CLASSIFIED_STYLE, symbol);
+                        self.out.push_str(" (");
+                        self.paint(NUMBER_STYLE, int);
+                        self.out.push(')');
+                    }
+                    None => self.paint(NUMBER_STYLE, int),
+                }
+            }
+            (ValueKind::Boolean(val), None) => {
+                self.out.push(' ');
+                self.paint(NUMBER_STYLE, val);
+            }
+            (ValueKind::Float(val), None) => {
+                self.out.push(' ');
+                self.paint(NUMBER_STYLE, val);
+            }
+            (ValueKind::Bytes(bytes), None) => {
+                self.out.push(' ');
+                self.write_bytes(bytes);
+            }
+            (ValueKind::Struct { .. }, None) => {}
+            (ValueKind::Array { items, lazy, .. }, None) => {
+                let len = items.len() + lazy.as_ref().map_or(0, |lazy| lazy.len());
+                self.out.push(' ');
+                self.paint(NUMBER_STYLE, format_args!("[{len}]"));
+            }
+        }
+        self.write_offset(value);
+        self.out.push('\n');
+
+        match &value.kind {
+            ValueKind::Struct { fields, error } => {
+                for (name, value) in fields {
+                    self.write_value(name.as_str(), value, errors, indent + 1);
+                }
+                let last = fields.last().map(|(_, last)| last);
+                self.write_error(*error, last, errors, indent + 1);
+            }
+            ValueKind::Array { items, error, lazy } => {
+                for (i, item) in items.iter().enumerate() {
+                    self.write_value(&format!("[{i}]"), item, errors, indent + 1);
+                }
+                // the lazy elements come with their own errors
+                if let Some(lazy) = lazy {
+                    let evaluated = lazy.evaluate(0..lazy.len());
+                    for (i, item) in evaluated.value.kind.expect_array().iter().enumerate() {
+                        let name = format!("[{}]", items.len() + i);
+                        self.write_value(&name, item, &evaluated.errors, indent + 1);
+                    }
+                }
+                let last = items.last().filter(|_| lazy.is_none());
+                self.write_error(*error, last, errors, indent + 1);
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes the given bytes as text if they are printable and in hex otherwise.
+    ///
+    /// Trailing null bytes are not shown in text, since they usually pad fixed size strings.
+    fn write_bytes(&mut self, bytes: &BytesValue) {
+        if bytes.len() <= MAX_TEXT_LEN
+            && let Ok(read) = bytes.value()
+            && let text = without_trailing_nuls(&read)
+            && !text.is_empty()
+            && text
+                .iter()
+                .all(|&byte| byte.is_ascii_graphic() || byte == b' ')
+        {
+            self.paint(BYTES_STYLE, format_args!("\"{}\"", text.escape_ascii()));
+            return;
+        }
+
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut preview = [0; BytesValue::INLINE_LEN];
+        match bytes.preview_slice(&mut preview) {
+            Some(len) => self.paint(BYTES_STYLE, format_args!("<{}>", hex(&preview[..len]))),
+            None => {
+                let (prefix, suffix) = preview.split_at(BytesValue::PREFIX_SUFFIX_LEN);
+                self.paint(
+                    BYTES_STYLE,
+                    format_args!("<{} .. {}>", hex(prefix), hex(suffix)),
+                );
+                let _ = write!(self.out, " ({} bytes)", bytes.len());
+            }
+        }
+    }
+
+    /// Writes the offset of the first byte of the value, if it has one.
+    fn write_offset(&mut self, value: &Value) {
+        if let Some(range) = value.provenance.byte_ranges().next() {
+            // the offset is set apart from the value, but follows the name of a `struct` directly
+            self.out
+                .push_str(if self.out.ends_with(':') { " " } else { "  " });
+            self.paint(OFFSET_STYLE, format_args!("@{:#x}", range.start().as_u64()));
+        }
+    }
+
+    /// Writes the given parse error, if there is one.
+    ///
+    /// Errors are passed on to the enclosing `struct`s and arrays, so the error is only written if
+    /// the `last` value within the `struct` or array did not already write it.
+    fn write_error(
+        &mut self,
+        error: Option<ParseErrId>,
+        last: Option<&Value>,
+        errors: &[ParseErr],
+        indent: usize,
+    ) {
+        let Some(error) = error else { return };
+        if let Some(
+            ValueKind::Struct {
+                error: Some(nested),
+                ..
+            }
+            | ValueKind::Array {
+                error: Some(nested),
+                ..
+            },
+        ) = last.map(|last| &last.kind)
+            && *nested == error
+        {
+            return;
+        }
+
+        let _ = write!(self.out, "{:width$}", "", width = indent * 2);
+        self.paint(
+            ERROR_STYLE,
+            format_args!("error: {}", errors[error.raw_idx()].message),
+        );
+        self.out.push('\n');
+    }
+}
+
+/// Returns the bytes without any null bytes at their end.
+fn without_trailing_nuls(bytes: &[u8]) -> &[u8] {
+    let len = bytes
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |i| i + 1);
+
+    &bytes[..len]
+}
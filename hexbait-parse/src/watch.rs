@@ -0,0 +1,86 @@
+//! Implements parsing the input again whenever it or the custom parser changes.
+
+use std::{
+    io::{IsTerminal as _, Write as _},
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use hexbait_common::Input;
+use hexbait_lang::ir::File;
+
+use crate::{Config, print_parse, try_load_custom};
+
+/// The interval in which the watched files are checked for modifications.
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Parses the input file at `path` and prints the result every time it or the custom parser is
+/// modified.
+///
+/// If `parser` is `None`, the custom parser from the config is loaded again for every parse.
+/// Problems are printed to stderr and watching continues until the program is interrupted.
+pub fn watch(
+    config: &Config,
+    path: &Path,
+    parser: Option<File>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clear_screen = std::io::stdout().is_terminal();
+    let mut last_modified = None;
+
+    loop {
+        let modified = [Some(path), config.custom.as_deref()].map(|path| path.and_then(modified));
+        if last_modified != Some(modified) {
+            last_modified = Some(modified);
+
+            if clear_screen {
+                print!("\x1b[2J\x1b[H");
+            }
+            parse_once(config, path, parser.as_ref());
+            if !clear_screen {
+                println!();
+            }
+            std::io::stdout().flush()?;
+        }
+
+        thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+/// Parses the input file at `path` once, printing any problems to stderr.
+fn parse_once(config: &Config, path: &Path, parser: Option<&File>) {
+    let custom;
+    let parser = match (parser, &config.custom) {
+        (Some(parser), _) => parser,
+        (None, Some(custom_path)) => match try_load_custom(custom_path, &config.import_dir) {
+            Ok(Some(parser)) => {
+                custom = parser;
+                &custom
+            }
+            Ok(None) => return,
+            Err(err) => {
+                eprintln!("{err}");
+                return;
+            }
+        },
+        (None, None) => unreachable!("either a built-in or a custom parser is used"),
+    };
+
+    let input = match Input::from_path(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("cannot read {}: {err}", path.display());
+            return;
+        }
+    };
+    if let Err(err) = print_parse(parser, input, config) {
+        eprintln!("{err}");
+    }
+}
+
+/// Returns the time at which the file at `path` was last modified, if it can be determined.
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
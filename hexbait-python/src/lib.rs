@@ -0,0 +1,246 @@
+//! Implements Python bindings for the hexbait format description language.
+//!
+//! The module exposes [`parse`](hexbait_lang::parse), [`lower_file`](hexbait_lang::ir::lower_file)
+//! and [`eval_ir`](hexbait_lang::eval_ir).
+//! Parsed values are returned as dictionaries that contain the offsets they were parsed from.
+
+#![forbid(unsafe_code)]
+
+use std::path::PathBuf;
+
+use hexbait_builtin_parsers::import_paths;
+use hexbait_common::{AbsoluteOffset, Input, RelativeOffset};
+use hexbait_lang::{Provenance, Span, Value, ValueKind, View, ast, ir};
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyInt, PyList},
+};
+
+/// A format description that was parsed from source code.
+#[pyclass(unsendable, module = "pyhexbait")]
+struct ParsedFile {
+    /// The abstract syntax tree of the format description.
+    ast: ast::File,
+    /// The errors that occurred while parsing.
+    errors: Vec<hexbait_lang::ParseError>,
+}
+
+#[pymethods]
+impl ParsedFile {
+    /// The errors that occurred while parsing as dictionaries.
+    #[getter]
+    fn errors<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(
+            py,
+            self.errors
+                .iter()
+                .map(|err| {
+                    let dict = span_dict(py, &err.message, err.span)?;
+                    dict.set_item("expected", &err.expected)?;
+                    Ok(dict)
+                })
+                .collect::<PyResult<Vec<_>>>()?,
+        )
+    }
+}
+
+/// A format description lowered to its intermediate representation, ready for evaluation.
+#[pyclass(unsendable, module = "pyhexbait")]
+struct Ir {
+    /// The lowered format description.
+    file: ir::File,
+}
+
+/// The data to parse: either the bytes themselves or the path to a file.
+#[derive(FromPyObject)]
+enum Data {
+    /// The bytes to parse.
+    Bytes(Vec<u8>),
+    /// The path of the file to parse.
+    Path(PathBuf),
+}
+
+/// Parses the given format description source code.
+#[pyfunction]
+fn parse(source: &str) -> ParsedFile {
+    let parse = hexbait_lang::parse(source);
+
+    ParsedFile {
+        ast: parse.ast,
+        errors: parse.errors,
+    }
+}
+
+/// Lowers the given parsed format description to its intermediate representation.
+///
+/// Imported files are looked up in `import_dirs` and among the built-in format descriptions.
+/// Raises a `ValueError` if the format description contains syntax errors or its imports cannot
+/// be resolved.
+#[pyfunction]
+#[pyo3(signature = (parsed, import_dirs = Vec::new()))]
+fn lower_file(parsed: &ParsedFile, import_dirs: Vec<PathBuf>) -> PyResult<Ir> {
+    if let Some(err) = parsed.errors.first() {
+        return Err(PyValueError::new_err(format!(
+            "format description contains syntax errors: {}",
+            err.message
+        )));
+    }
+
+    let mut file = ir::lower_file(parsed.ast.clone());
+    ir::resolve_imports(
+        &mut file,
+        &ir::SourceLocation::Unknown,
+        &import_paths(import_dirs),
+    )
+    .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    hexbait_lang::check_ir(&file).map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    Ok(Ir { file })
+}
+
+/// Evaluates the format description on the given data, starting at the given offset.
+///
+/// `data` is either a `bytes` object or a path to a file.
+/// The result is a dictionary containing the parsed `value`, as well as `errors` and `warnings`.
+#[pyfunction]
+#[pyo3(signature = (ir, data, offset = 0))]
+fn eval_ir<'py>(py: Python<'py>, ir: &Ir, data: Data, offset: u64) -> PyResult<Bound<'py, PyDict>> {
+    let input = match data {
+        Data::Bytes(bytes) => Input::from_bytes(bytes),
+        Data::Path(path) => {
+            Input::from_path(path).map_err(|err| PyIOError::new_err(err.to_string()))?
+        }
+    };
+    let offset = AbsoluteOffset::from(offset);
+    if offset > AbsoluteOffset::ZERO + input.len() {
+        return Err(PyValueError::new_err(
+            "offset is beyond the end of the data",
+        ));
+    }
+
+    let view = View::from_input(input);
+    let view = view.subview(offset.to_relative()..view.len().to_relative());
+    let result = hexbait_lang::eval_ir(&ir.file, view, RelativeOffset::ZERO);
+
+    let errors = result
+        .errors
+        .iter()
+        .map(|err| diagnostic_dict(py, &err.message, &err.provenance, err.span))
+        .collect::<PyResult<Vec<_>>>()?;
+    let warnings = result
+        .warnings
+        .iter()
+        .map(|warning| diagnostic_dict(py, &warning.message, &warning.provenance, warning.span))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("value", value_dict(py, &result.value)?)?;
+    dict.set_item("errors", errors)?;
+    dict.set_item("warnings", warnings)?;
+
+    Ok(dict)
+}
+
+/// Creates a dictionary for a message at a location in the format description.
+///
+/// The location is stored as a `(start, end)` tuple of source code offsets in `span`.
+fn span_dict<'py>(py: Python<'py>, message: &str, span: Span) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("message", message)?;
+    dict.set_item("span", (span.start(), span.end()))?;
+
+    Ok(dict)
+}
+
+/// Creates a dictionary for an error or warning that occurred during evaluation.
+fn diagnostic_dict<'py>(
+    py: Python<'py>,
+    message: &str,
+    provenance: &Provenance,
+    span: Span,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = span_dict(py, message, span)?;
+    add_offsets(&dict, provenance)?;
+
+    Ok(dict)
+}
+
+/// Adds the offsets of the given provenance to the dictionary.
+///
+/// `offset` is the start of the first byte range and `end` is the end of the last byte range.
+/// All byte ranges are stored as `(start, end)` tuples in `ranges`.
+fn add_offsets(dict: &Bound<'_, PyDict>, provenance: &Provenance) -> PyResult<()> {
+    let ranges: Vec<_> = provenance
+        .byte_ranges()
+        .map(|range| (range.start().as_u64(), range.end().as_u64()))
+        .collect();
+
+    dict.set_item("offset", ranges.first().map(|range| range.0))?;
+    dict.set_item("end", ranges.last().map(|range| range.1))?;
+    dict.set_item("ranges", ranges)?;
+
+    Ok(())
+}
+
+/// Converts the given parsed value to a dictionary containing the value and its offsets.
+fn value_dict<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    add_offsets(&dict, &value.provenance)?;
+
+    match &value.kind {
+        ValueKind::Boolean(boolean) => {
+            dict.set_item("type", "bool")?;
+            dict.set_item("value", boolean)?;
+        }
+        ValueKind::Integer(int) => {
+            dict.set_item("type", "int")?;
+            let int = match i128::try_from(int) {
+                Ok(int) => int.into_pyobject(py)?.into_any(),
+                Err(_) => py.get_type::<PyInt>().call1((int.to_string(),))?,
+            };
+            dict.set_item("value", int)?;
+        }
+        ValueKind::Float(float) => {
+            dict.set_item("type", "float")?;
+            dict.set_item("value", float)?;
+        }
+        ValueKind::Bytes(bytes) => {
+            dict.set_item("type", "bytes")?;
+            let bytes = bytes
+                .value()
+                .map_err(|err| PyIOError::new_err(err.to_string()))?;
+            dict.set_item("value", PyBytes::new(py, &bytes))?;
+        }
+        ValueKind::Struct { fields, .. } => {
+            dict.set_item("type", "struct")?;
+            let fields_dict = PyDict::new(py);
+            for (name, value) in fields {
+                fields_dict.set_item(name.as_str(), value_dict(py, value)?)?;
+            }
+            dict.set_item("value", fields_dict)?;
+        }
+        ValueKind::Array { items, .. } => {
+            dict.set_item("type", "array")?;
+            let items = items
+                .iter()
+                .map(|item| value_dict(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            dict.set_item("value", items)?;
+        }
+    }
+
+    Ok(dict)
+}
+
+/// The Python module for the hexbait format description language.
+#[pymodule]
+fn pyhexbait(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ParsedFile>()?;
+    m.add_class::<Ir>()?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(lower_file, m)?)?;
+    m.add_function(wrap_pyfunction!(eval_ir, m)?)?;
+
+    Ok(())
+}
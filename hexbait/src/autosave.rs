@@ -0,0 +1,229 @@
+//! Implements crash-safe autosaving of the session.
+//!
+//! The session is periodically captured as a [`Project`] and written to a recovery file next to
+//! the other recovery files of hexbait. The recovery file is removed when hexbait exits normally,
+//! so a recovery file that exists when a file is opened means that the previous session analyzing
+//! that file ended abnormally.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use egui_dock::DockState;
+use hexbait_common::Input;
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    gui::modules::TabType,
+    project::{FileIdentity, Project, ProjectError},
+    state::State,
+};
+
+/// Returns the directory in which recovery files are stored.
+///
+/// This is `hexbait/recovery` in the state directory of the platform.
+pub fn recovery_dir() -> Option<PathBuf> {
+    let state_dir = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                Some(
+                    PathBuf::from(std::env::var_os("HOME")?)
+                        .join(".local")
+                        .join("state"),
+                )
+            })
+    }?;
+
+    Some(state_dir.join("hexbait").join("recovery"))
+}
+
+/// Returns the recovery file for the session analyzing the file at the given path.
+pub fn recovery_path(file_path: &Path) -> Option<PathBuf> {
+    let file_path = std::path::absolute(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+    let hash = Sha256::digest(file_path.as_os_str().as_encoded_bytes());
+    let name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    Some(recovery_dir()?.join(format!(
+        "{name}-{}.hbproj",
+        hash[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    )))
+}
+
+/// A session that ended abnormally and can be restored.
+pub struct Recovery {
+    /// The autosaved state of the session.
+    pub project: Project,
+    /// When the session was last autosaved, if known.
+    pub saved_at: Option<std::time::SystemTime>,
+}
+
+/// Periodically saves the session analyzing a file to its recovery file.
+pub struct Autosaver {
+    /// The recovery file.
+    path: PathBuf,
+    /// The analyzed file.
+    file_path: PathBuf,
+    /// The identity of the analyzed file, once it is known.
+    identity: Option<FileIdentity>,
+    /// Receives the identity of the analyzed file, which is computed in the background.
+    identity_receiver: Option<mpsc::Receiver<io::Result<FileIdentity>>>,
+    /// The session found in the recovery file that was not restored or discarded yet.
+    ///
+    /// No autosaves happen while there is one, so that it is not overwritten.
+    recovery: Option<Recovery>,
+    /// When the session was last checked for changes.
+    last_check: Instant,
+    /// The content of the recovery file that was last written.
+    last_content: String,
+}
+
+impl Autosaver {
+    /// Creates an autosaver for the session analyzing the file at the given path.
+    ///
+    /// Returns `None` if there is no location to store recovery files.
+    pub fn new(input: &Input, file_path: PathBuf) -> Option<Autosaver> {
+        let path = recovery_path(&file_path)?;
+
+        let recovery = match Project::load(&path) {
+            Ok(project) => Some(Recovery {
+                project,
+                saved_at: std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok(),
+            }),
+            Err(ProjectError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), "ignoring unreadable recovery file: {err}");
+                None
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let input = input.clone();
+        let identity_path = file_path.clone();
+        std::thread::spawn(move || {
+            // the autosaver may have been dropped in the meantime, which is fine
+            let _ = sender.send(FileIdentity::of_input(&input, Some(identity_path)));
+        });
+
+        Some(Autosaver {
+            path,
+            file_path,
+            identity: None,
+            identity_receiver: Some(receiver),
+            recovery,
+            last_check: Instant::now(),
+            last_content: String::new(),
+        })
+    }
+
+    /// The session that can be restored, if the previous session ended abnormally.
+    pub fn recovery(&self) -> Option<&Recovery> {
+        self.recovery.as_ref()
+    }
+
+    /// Determines if the recovered session was saved for different file content.
+    ///
+    /// Returns `None` while this is not known yet.
+    pub fn recovery_content_differs(&self) -> Option<bool> {
+        let recovery = self.recovery.as_ref()?;
+
+        Some(!self.identity.as_ref()?.same_content(&recovery.project.file))
+    }
+
+    /// Takes the session that can be restored, resuming autosaves.
+    pub fn take_recovery(&mut self) -> Option<Recovery> {
+        self.recovery.take()
+    }
+
+    /// Saves the session if the interval elapsed and the session changed since the last save.
+    pub fn tick(&mut self, state: &State, layout: &DockState<TabType>, interval: Duration) {
+        if let Some(receiver) = &self.identity_receiver
+            && let Ok(identity) = receiver.try_recv()
+        {
+            self.identity_receiver = None;
+            match identity {
+                Ok(identity) => self.identity = Some(identity),
+                Err(err) => tracing::warn!(
+                    file = %self.file_path.display(),
+                    "cannot autosave the session, because the file cannot be read: {err}"
+                ),
+            }
+        }
+
+        if self.recovery.is_some() || self.last_check.elapsed() < interval {
+            return;
+        }
+        self.last_check = Instant::now();
+        let Some(identity) = &self.identity else {
+            return;
+        };
+
+        let project = Project::capture_for_file(state, identity.clone(), layout);
+        let content = match ron::ser::to_string_pretty(&project, ron::ser::PrettyConfig::default())
+        {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::warn!("cannot autosave the session: {err}");
+                return;
+            }
+        };
+        if content == self.last_content {
+            return;
+        }
+
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| write_atomically(&self.path, &content));
+        match result {
+            Ok(()) => {
+                tracing::debug!(path = %self.path.display(), "autosaved the session");
+                self.last_content = content;
+            }
+            Err(err) => {
+                tracing::warn!(path = %self.path.display(), "cannot autosave the session: {err}")
+            }
+        }
+    }
+
+    /// Removes the recovery file, because the session ended normally.
+    ///
+    /// A session that can still be restored is kept.
+    pub fn finish(&mut self) {
+        if self.recovery.is_some() {
+            return;
+        }
+
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => tracing::debug!(path = %self.path.display(), "removed the recovery file"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => {
+                tracing::warn!(path = %self.path.display(), "cannot remove the recovery file: {err}")
+            }
+        }
+        self.last_content.clear();
+    }
+}
+
+/// Writes the content to the file, so that the file is never left partially written.
+fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let temp_path = path.with_extension("hbproj.tmp");
+    std::fs::write(&temp_path, content)?;
+
+    std::fs::rename(temp_path, path)
+}
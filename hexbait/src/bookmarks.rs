@@ -0,0 +1,305 @@
+//! Implements importing and exporting user marks as bookmarks of other hex editors.
+//!
+//! Supported are the bookmark files of [ImHex](https://imhex.werwolv.net) and the CSV export of
+//! the bookmark list of 010 Editor.
+
+use std::{borrow::Cow, fmt, path::Path};
+
+use hexbait_common::{AbsoluteOffset, Len};
+use serde::{Deserialize, Serialize};
+
+use crate::{project::ProjectMark, window::Window};
+
+/// The color of exported ImHex bookmarks, as `0xAABBGGRR`.
+const IMHEX_BOOKMARK_COLOR: u32 = 0x8000_00ff;
+
+/// The color of exported 010 Editor bookmarks.
+const EDITOR_010_BOOKMARK_COLOR: &str = "Red";
+
+/// The columns of the bookmark list of 010 Editor.
+const EDITOR_010_COLUMNS: [&str; 6] = ["Name", "Value", "Start", "Size", "Color", "Comment"];
+
+/// A format of bookmark files of other hex editors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkFormat {
+    /// The JSON bookmark files of ImHex.
+    ImHex,
+    /// The CSV export of the bookmark list of 010 Editor.
+    Editor010,
+}
+
+impl BookmarkFormat {
+    /// All supported bookmark formats.
+    pub const ALL: [BookmarkFormat; 2] = [BookmarkFormat::ImHex, BookmarkFormat::Editor010];
+
+    /// Returns the name of the format for displaying.
+    pub fn display_str(self) -> &'static str {
+        match self {
+            BookmarkFormat::ImHex => "ImHex (.hexbm)",
+            BookmarkFormat::Editor010 => "010 Editor (.csv)",
+        }
+    }
+
+    /// The usual file extension of bookmark files in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            BookmarkFormat::ImHex => "hexbm",
+            BookmarkFormat::Editor010 => "csv",
+        }
+    }
+
+    /// Returns the format of the bookmark file at the given path, based on its extension.
+    pub fn from_path(path: &Path) -> Option<BookmarkFormat> {
+        let extension = path.extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("json") {
+            return Some(BookmarkFormat::ImHex);
+        }
+
+        BookmarkFormat::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+/// An error that occurred while importing bookmarks.
+#[derive(Debug)]
+pub enum BookmarkError {
+    /// The bookmark file is not valid JSON of the expected structure.
+    Json(serde_json::Error),
+    /// A line of a CSV bookmark file is not valid.
+    Csv {
+        /// The number of the line, starting at one.
+        line: usize,
+        /// A description of the problem.
+        message: Cow<'static, str>,
+    },
+}
+
+impl fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookmarkError::Json(err) => write!(f, "invalid bookmark file: {err}"),
+            BookmarkError::Csv { line, message } => {
+                write!(f, "invalid bookmark file in line {line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookmarkError {}
+
+impl From<serde_json::Error> for BookmarkError {
+    fn from(err: serde_json::Error) -> Self {
+        BookmarkError::Json(err)
+    }
+}
+
+/// A bookmark file of ImHex.
+#[derive(Debug, Serialize, Deserialize)]
+struct ImHexBookmarks {
+    /// The bookmarks in the file.
+    bookmarks: Vec<ImHexBookmark>,
+}
+
+/// A single bookmark of ImHex.
+#[derive(Debug, Serialize, Deserialize)]
+struct ImHexBookmark {
+    /// The bookmarked bytes.
+    region: ImHexRegion,
+    /// The name of the bookmark.
+    name: String,
+    /// A comment about the bookmark.
+    #[serde(default)]
+    comment: String,
+    /// The color of the bookmark as `0xAABBGGRR`.
+    #[serde(default)]
+    color: u32,
+    /// Whether the bookmark can be edited.
+    #[serde(default)]
+    locked: bool,
+}
+
+/// A region of bytes in ImHex.
+#[derive(Debug, Serialize, Deserialize)]
+struct ImHexRegion {
+    /// The offset of the first byte.
+    address: u64,
+    /// The number of bytes.
+    size: u64,
+}
+
+/// Exports the marks as a bookmark file in the given format.
+pub fn export(format: BookmarkFormat, marks: &[ProjectMark]) -> String {
+    match format {
+        BookmarkFormat::ImHex => {
+            let bookmarks = ImHexBookmarks {
+                bookmarks: marks
+                    .iter()
+                    .map(|mark| ImHexBookmark {
+                        region: ImHexRegion {
+                            address: mark.window.start().as_u64(),
+                            size: mark.window.size().as_u64(),
+                        },
+                        name: mark.name.clone(),
+                        comment: String::new(),
+                        color: IMHEX_BOOKMARK_COLOR,
+                        locked: false,
+                    })
+                    .collect(),
+            };
+
+            serde_json::to_string_pretty(&bookmarks).expect("bookmarks can always be serialized")
+        }
+        BookmarkFormat::Editor010 => {
+            let mut out = EDITOR_010_COLUMNS.join(",");
+            out.push('\n');
+            for mark in marks {
+                out.push_str(&format!(
+                    "{},,{:X}h,{:X}h,{EDITOR_010_BOOKMARK_COLOR},\n",
+                    csv_field(&mark.name),
+                    mark.window.start().as_u64(),
+                    mark.window.size().as_u64(),
+                ));
+            }
+
+            out
+        }
+    }
+}
+
+/// Imports the marks from a bookmark file in the given format.
+///
+/// Empty bookmarks are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait::bookmarks::{BookmarkFormat, import};
+/// let csv = "Name,Value,Start,Size,Color,Comment\n\"header, v2\",,100h,10h,Red,\n";
+/// let marks = import(BookmarkFormat::Editor010, csv).unwrap();
+/// assert_eq!(marks[0].name, "header, v2");
+/// assert_eq!(marks[0].window.start().as_u64(), 0x100);
+/// assert_eq!(marks[0].window.size().as_u64(), 0x10);
+/// ```
+pub fn import(format: BookmarkFormat, content: &str) -> Result<Vec<ProjectMark>, BookmarkError> {
+    let marks = match format {
+        BookmarkFormat::ImHex => {
+            let bookmarks: ImHexBookmarks = serde_json::from_str(content)?;
+
+            bookmarks
+                .bookmarks
+                .into_iter()
+                .map(|bookmark| ProjectMark {
+                    window: Window::from_start_len(
+                        AbsoluteOffset::from(bookmark.region.address),
+                        Len::from(bookmark.region.size),
+                    ),
+                    name: bookmark.name,
+                })
+                .collect()
+        }
+        BookmarkFormat::Editor010 => import_010_csv(content)?,
+    };
+
+    Ok(marks
+        .into_iter()
+        .filter(|mark: &ProjectMark| !mark.window.is_empty())
+        .collect())
+}
+
+/// Imports the marks from the CSV export of the bookmark list of 010 Editor.
+///
+/// The columns are identified by the header line, so their order does not matter.
+fn import_010_csv(content: &str) -> Result<Vec<ProjectMark>, BookmarkError> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+
+    let header = split_csv_line(header);
+    let column = |name: &'static str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+            .ok_or(BookmarkError::Csv {
+                line: 1,
+                message: Cow::Owned(format!("missing column `{name}`")),
+            })
+    };
+    let name_column = column("Name")?;
+    let start_column = column("Start")?;
+    let size_column = column("Size")?;
+
+    lines
+        .map(|(i, line)| {
+            let fields = split_csv_line(line);
+            let field = |column: usize| fields.get(column).map(|field| field.trim()).unwrap_or("");
+            let number = |column: usize| {
+                parse_010_number(field(column)).ok_or_else(|| BookmarkError::Csv {
+                    line: i + 1,
+                    message: Cow::Owned(format!("invalid number `{}`", field(column))),
+                })
+            };
+
+            Ok(ProjectMark {
+                window: Window::from_start_len(
+                    AbsoluteOffset::from(number(start_column)?),
+                    Len::from(number(size_column)?),
+                ),
+                name: field(name_column).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parses a number as shown by 010 Editor.
+///
+/// Hexadecimal numbers end in `h`, but numbers with a `0x` prefix and decimal numbers are also
+/// accepted.
+fn parse_010_number(text: &str) -> Option<u64> {
+    let text = text.replace([',', ' '], "");
+    if let Some(hex) = text.strip_suffix(['h', 'H']) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Splits a line of a CSV file into its fields, removing the quotes of quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+
+    fields
+}
+
+/// Quotes a CSV field if necessary.
+///
+/// Line breaks are replaced by spaces, so that every bookmark is on its own line.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!(
+            "\"{}\"",
+            field.replace(['\n', '\r'], " ").replace('"', "\"\"")
+        ))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
@@ -0,0 +1,379 @@
+//! Implements the headless subcommands of hexbait.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use clap::{Args, Subcommand};
+use hexbait::{state::SearchState, window::Window};
+use hexbait_builtin_parsers::built_in_format_descriptions;
+use hexbait_common::{AbsoluteOffset, Input};
+use hexbait_core::{
+    carve::{CarveOptions, CarvedFile, carve},
+    crypto_constants::find_constants,
+    magic::{FileKind, SignatureScanner},
+    patch::{self, PatchFormat},
+    statistics::{BigramStatistics, classification::classify},
+};
+
+/// The interval in which the progress of a search is polled.
+const SEARCH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A subcommand that runs without the GUI.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print statistics and the classification of a file
+    Stats {
+        /// The file to analyze
+        file: PathBuf,
+    },
+    /// Search a file and print the offsets of all matches
+    Search(SearchArgs),
+    /// Scan a file for embedded files and print their offsets and types
+    ScanMagic {
+        /// The file to scan
+        file: PathBuf,
+        /// Only check offsets that are a multiple of this alignment
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        align: u64,
+    },
+    /// Recursively carve embedded files out of a file and print them as a tree
+    ///
+    /// Compressed streams are decompressed and their content is carved as well.
+    Carve {
+        /// The file to carve
+        file: PathBuf,
+        /// Extract the carved files into this directory
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// How many levels of compressed streams are decompressed
+        #[arg(short, long, default_value_t = 4)]
+        depth: usize,
+    },
+    /// Find well-known cryptographic constants in a file and print their offsets
+    ///
+    /// This finds AES S-boxes and T-tables, SHA-2 initial values and round constants, CRC
+    /// polynomials and tables as well as Base64 alphabets.
+    CryptoConstants {
+        /// The file to scan
+        file: PathBuf,
+    },
+    /// Create a patch describing the modifications between an original and a modified file
+    ///
+    /// The supported formats are `ips`, `bps` and `json`, which lists the original and the new
+    /// bytes of every modification.
+    CreatePatch {
+        /// The original file
+        original: PathBuf,
+        /// The modified file
+        modified: PathBuf,
+        /// The patch file to write
+        patch: PathBuf,
+        /// The format of the patch, which defaults to the extension of the patch file
+        #[arg(short, long, value_parser = patch_format)]
+        format: Option<PatchFormat>,
+    },
+    /// Apply a patch to a file and write the modified file
+    ApplyPatch {
+        /// The file to patch
+        file: PathBuf,
+        /// The patch to apply
+        patch: PathBuf,
+        /// The file to write the modified file to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// The format of the patch, which defaults to the extension of the patch file
+        #[arg(short, long, value_parser = patch_format)]
+        format: Option<PatchFormat>,
+    },
+    /// Send a command to a running instance that was started with `--remote`
+    ///
+    /// Supported commands are `open <path>`, `goto <offset>`, `select <start> <end>` and
+    /// `parser <name>`, where the end of a selection is exclusive and offsets are decimal or
+    /// hexadecimal with a `0x` prefix.
+    Send {
+        /// The name of the socket the instance listens on
+        #[arg(short, long, default_value = hexbait::remote::DEFAULT_SOCKET_NAME)]
+        socket: String,
+        /// The command to send
+        #[arg(required = true, trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+}
+
+/// The arguments of the `search` subcommand.
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// The text, byte pattern or regular expression to search for
+    pattern: String,
+    /// The file to search
+    file: PathBuf,
+    /// Interpret the pattern as a regular expression
+    #[arg(short, long)]
+    regex: bool,
+    /// Match ASCII letters case insensitively
+    #[arg(short, long)]
+    ignore_case: bool,
+    /// Also search for the text in UTF-16 and Latin-1
+    #[arg(short, long)]
+    all_encodings: bool,
+}
+
+impl Command {
+    /// Runs the subcommand, printing the results to stdout.
+    pub fn run(self) -> io::Result<()> {
+        match self {
+            Command::Stats { file } => stats(&Input::from_path(file)?),
+            Command::Search(args) => search(&Input::from_path(&args.file)?, &args),
+            Command::ScanMagic { file, align } => scan_magic(&Input::from_path(file)?, align),
+            Command::Carve {
+                file,
+                output,
+                depth,
+            } => carve_files(Input::from_path(file)?, output, depth),
+            Command::CryptoConstants { file } => crypto_constants(Input::from_path(file)?),
+            Command::CreatePatch {
+                original,
+                modified,
+                patch,
+                format,
+            } => create_patch(&original, &modified, &patch, format),
+            Command::ApplyPatch {
+                file,
+                patch,
+                output,
+                format,
+            } => apply_patch(&file, &patch, &output, format),
+            Command::Send { socket, command } => send(&socket, &command.join(" ")),
+        }
+    }
+}
+
+/// Parses the name of a patch format.
+fn patch_format(name: &str) -> Result<PatchFormat, String> {
+    PatchFormat::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = PatchFormat::ALL
+            .iter()
+            .map(|format| format.name())
+            .collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+/// Returns the given patch format or the one matching the extension of the patch file.
+fn resolve_patch_format(format: Option<PatchFormat>, patch: &Path) -> io::Result<PatchFormat> {
+    format
+        .or_else(|| PatchFormat::from_path(patch))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot determine the patch format from the file extension, use `--format`",
+            )
+        })
+}
+
+/// Writes a patch describing the modifications between the original and the modified file.
+fn create_patch(
+    original: &Path,
+    modified: &Path,
+    patch: &Path,
+    format: Option<PatchFormat>,
+) -> io::Result<()> {
+    let format = resolve_patch_format(format, patch)?;
+    let content = patch::create(format, &std::fs::read(original)?, &std::fs::read(modified)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    std::fs::write(patch, content)
+}
+
+/// Applies a patch to the file and writes the modified file.
+fn apply_patch(
+    file: &Path,
+    patch: &Path,
+    output: &Path,
+    format: Option<PatchFormat>,
+) -> io::Result<()> {
+    let format = resolve_patch_format(format, patch)?;
+    let modified = patch::apply(format, &std::fs::read(file)?, &std::fs::read(patch)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    std::fs::write(output, modified)
+}
+
+/// Prints the statistics and classification of the whole input.
+fn stats(input: &Input) -> io::Result<()> {
+    let window = Window::from_start_len(AbsoluteOffset::ZERO, input.len());
+    let statistics = BigramStatistics::compute_for_window(input, window)?;
+    let metrics = statistics.downsampled().metrics();
+
+    let percent = |value: u8| value as f64 / 255.0 * 100.0;
+
+    println!(
+        "size: {} ({} bytes)",
+        input.len().human_size(),
+        input.len().as_u64()
+    );
+    if let Some(kind) = FileKind::identify_at(input, AbsoluteOffset::ZERO)? {
+        println!("type: {} ({})", kind.mime_type, kind.extension);
+    }
+    println!("entropy: {:.1}%", percent(metrics.entropy));
+    println!("printable ASCII: {:.1}%", percent(metrics.printable_ascii));
+    println!("byte delta: {:.1}%", percent(metrics.byte_delta));
+
+    if let Some(classes) = classify(&statistics) {
+        println!("classification:");
+        for class in classes {
+            let matches = if class.score < class.min_score {
+                " "
+            } else {
+                "*"
+            };
+            println!(
+                "  {matches} {:.03} (min {:.03}) {}",
+                class.score, class.min_score, class.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Searches the whole input and prints all matches.
+fn search(input: &Input, args: &SearchArgs) -> io::Result<()> {
+    let mut state = SearchState::new(input);
+    state.search_text = args.pattern.clone();
+    state.search_regex = args.regex;
+    state.search_ascii_case_insensitive = args.ignore_case;
+    state.search_all_text_encodings = args.all_encodings;
+
+    if let Err(msg) = state.search_query() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    if !state.start_search(Window::from_start_len(AbsoluteOffset::ZERO, input.len())) {
+        return Ok(());
+    }
+
+    while state.searcher.is_running() {
+        thread::sleep(SEARCH_POLL_INTERVAL);
+    }
+    if let Some(error) = state.searcher.error() {
+        return Err(io::Error::other(error));
+    }
+
+    for (window, encoding) in state.searcher.results().iter() {
+        println!(
+            "{} {} {}",
+            window.start().hex(),
+            window.size().as_u64(),
+            encoding.name()
+        );
+    }
+
+    Ok(())
+}
+
+/// Scans the input for known file signatures and prints the offsets where they are found.
+fn scan_magic(input: &Input, align: u64) -> io::Result<()> {
+    let mut scanner = SignatureScanner::new(input, align);
+    while let Some(signatures) = scanner.next_chunk()? {
+        for signature in signatures {
+            println!(
+                "{} {} {}",
+                signature.offset.hex(),
+                signature.kind.mime_type,
+                signature.kind.extension
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Carves embedded files out of the input and prints them as a tree.
+fn carve_files(input: Input, output_dir: Option<PathBuf>, max_depth: usize) -> io::Result<()> {
+    let options = CarveOptions {
+        max_depth,
+        output_dir,
+        parsers: built_in_format_descriptions()
+            .into_iter()
+            .filter_map(|(name, ir)| match ir {
+                Ok(ir) => Some((name, ir)),
+                Err(err) => {
+                    eprintln!("warning: skipping invalid built-in format description `{name}`");
+                    eprintln!("{err}");
+                    None
+                }
+            })
+            .collect(),
+    };
+
+    for file in carve(input, Arc::new(options))? {
+        print_carved_file(&file, 0);
+    }
+
+    Ok(())
+}
+
+/// Prints a carved file and the files nested in it, indented by their depth.
+fn print_carved_file(file: &CarvedFile, depth: usize) {
+    let mut line = format!(
+        "{:indent$}{} {} {}",
+        "",
+        file.window.start().hex(),
+        file.window.size().as_u64(),
+        file.kind.mime_type,
+        indent = depth * 2
+    );
+    if let Some(len) = file.decompressed_len {
+        line.push_str(&format!(" (decompressed {} bytes)", len.as_u64()));
+    }
+    if let Some(check) = &file.parser_check {
+        match check.errors {
+            0 => line.push_str(&format!(" [{}: ok]", check.name)),
+            errors => line.push_str(&format!(" [{}: {errors} errors]", check.name)),
+        }
+    }
+    if let Some(path) = &file.extracted_to {
+        line.push_str(&format!(" -> {}", path.display()));
+    }
+    println!("{line}");
+
+    for child in &file.children {
+        print_carved_file(child, depth + 1);
+    }
+}
+
+/// Finds cryptographic constants in the input and prints them.
+fn crypto_constants(input: Input) -> io::Result<()> {
+    for found in find_constants(input)? {
+        println!(
+            "{} {} {}{}",
+            found.window.start().hex(),
+            found.window.size().as_u64(),
+            found.constant.name,
+            if found.is_complete() {
+                ""
+            } else {
+                " (partial)"
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Sends a command to a running instance and prints its response.
+fn send(socket: &str, command: &str) -> io::Result<()> {
+    let response = hexbait::remote::send_command(socket, command)?;
+    match response.strip_prefix("error: ") {
+        Some(err) => Err(io::Error::other(err.to_string())),
+        None => {
+            println!("{response}");
+            Ok(())
+        }
+    }
+}
@@ -7,3 +7,4 @@ pub mod image_processing;
 pub mod marking;
 pub mod modules;
 pub mod primitives;
+pub mod value_renderers;
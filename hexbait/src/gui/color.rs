@@ -1,6 +1,8 @@
 //! Defines colors used in the GUI and code for handling colors.
 
+use clap::ValueEnum;
 use egui::Color32;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     state::Settings,
@@ -190,7 +192,8 @@ pub fn metrics_color(
 }
 
 /// Represents the different possible color maps.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
 pub enum ColorMap {
     /// The magma color map.
     Magma,
@@ -29,12 +29,15 @@ pub(crate) fn highlight(
         0.85,
     );
 
+    let bytes_per_row = settings.bytes_per_row();
+    let half_row = bytes_per_row / 2;
+
     // TODO: fix this properly at some point by using the correct types in this function
     let mut range = range.start().as_u64()..=range.end().as_u64();
 
-    let screen_start_offset = screen_start_offset_in_rows * 16;
+    let screen_start_offset = screen_start_offset_in_rows * bytes_per_row;
     let screen_end_offset = std::cmp::min(
-        screen_start_offset + (rows_onscreen + 1) * 16,
+        screen_start_offset + (rows_onscreen + 1) * bytes_per_row,
         file_size.as_u64(),
     );
 
@@ -43,10 +46,10 @@ pub(crate) fn highlight(
         return;
     }
 
-    if *range.start() < screen_start_offset.saturating_sub(16) {
-        range = screen_start_offset - 16..=*range.end();
+    if *range.start() < screen_start_offset.saturating_sub(bytes_per_row) {
+        range = screen_start_offset - bytes_per_row..=*range.end();
     }
-    if *range.end() > screen_end_offset.saturating_add(16) {
+    if *range.end() > screen_end_offset.saturating_add(bytes_per_row) {
         range = *range.start()..=screen_end_offset;
     }
 
@@ -57,25 +60,27 @@ pub(crate) fn highlight(
     let small_space = settings.small_space();
 
     let row_start = |offset: u64| {
-        let row = offset / 16;
+        let row = offset / bytes_per_row;
         let start_row = screen_start_offset_in_rows;
         let row_offset = (row as i64 - start_row as i64).clamp(-1, rows_onscreen as i64 + 1);
 
         screen_rect.min.y + row_offset as f32 * char_height
     };
     let col_start_hex = |offset: u64| {
-        let col = offset % 16;
+        let col = offset % bytes_per_row;
         let start_offset = (16.0 * char_width) + large_space;
         let col_width = (2.0 * char_width) + small_space;
-        let middle_offset = (col >= 8) as u8 as f32 * small_space;
+        let middle_offset = (col >= half_row) as u8 as f32 * small_space;
 
         screen_rect.min.x + start_offset + middle_offset + col as f32 * col_width
     };
     let col_start_glyph = |offset: u64| {
-        let col = offset % 16;
-        let start_offset = (48.0 * char_width) + (2.0 * large_space) + (16.0 * small_space);
+        let col = offset % bytes_per_row;
+        let start_offset = ((16 + bytes_per_row * 2) as f32 * char_width)
+            + (2.0 * large_space)
+            + (bytes_per_row as f32 * small_space);
         let col_width = char_width;
-        let middle_offset = (col >= 8) as u8 as f32 * small_space;
+        let middle_offset = (col >= half_row) as u8 as f32 * small_space;
 
         screen_rect.min.x + start_offset + middle_offset + col as f32 * col_width
     };
@@ -101,8 +106,8 @@ pub(crate) fn highlight(
     // x positions of first and last column for both hex display and glyph display
     let first_x = (col_start_hex(0), col_start_glyph(0));
     let last_x = (
-        col_start_hex(15) + (2.0 * char_width),
-        col_start_glyph(15) + char_width,
+        col_start_hex(bytes_per_row - 1) + (2.0 * char_width),
+        col_start_glyph(bytes_per_row - 1) + char_width,
     );
 
     let mut add_point = |x: (f32, f32), y: f32| {
@@ -124,7 +129,7 @@ pub(crate) fn highlight(
         ));
     };
 
-    if *range.start() / 16 == range.end() / 16 {
+    if *range.start() / bytes_per_row == range.end() / bytes_per_row {
         // single row case
         add_point(start_x, start_y);
         add_point(end_x, start_y);
@@ -132,7 +137,9 @@ pub(crate) fn highlight(
         add_point(start_x, end_y);
 
         add_rect(start_x, end_x, start_y, end_y);
-    } else if (*range.start() / 16) + 1 == *range.end() / 16 && range.clone().count() <= 16 {
+    } else if (*range.start() / bytes_per_row) + 1 == *range.end() / bytes_per_row
+        && range.clone().count() as u64 <= bytes_per_row
+    {
         // split two-row case
         add_point(start_x, start_y);
         add_point(last_x, start_y);
@@ -150,19 +157,19 @@ pub(crate) fn highlight(
         // joined multi-row case
         add_point(start_x, start_y);
         add_point(last_x, start_y);
-        if *range.end() % 16 != 15 {
+        if *range.end() % bytes_per_row != bytes_per_row - 1 {
             add_point(last_x, end_y - char_height);
             add_point(end_x, end_y - char_height);
         }
         add_point(end_x, end_y);
         add_point(first_x, end_y);
-        if !range.start().is_multiple_of(16) {
+        if !range.start().is_multiple_of(bytes_per_row) {
             add_point(first_x, start_y + char_height);
             add_point(start_x, start_y + char_height);
         }
 
         add_rect(start_x, last_x, start_y, start_y + char_height);
-        if *range.start() / 16 + 1 != *range.end() / 16 {
+        if *range.start() / bytes_per_row + 1 != *range.end() / bytes_per_row {
             add_rect(first_x, last_x, start_y + char_height, end_y - char_height);
         }
         add_rect(first_x, end_x, end_y - char_height, end_y);
@@ -8,6 +8,7 @@ use hexbait_common::Len;
 use crate::{
     gui::{highlighting::trace_path, modules::scrollbars::offset_on_bar},
     marking::{MarkRef, MarkStore, MarkType},
+    search::TextEncoding,
     window::Window,
 };
 
@@ -16,14 +17,31 @@ use super::color;
 /// Shows the hover overlay for a marked location.
 pub fn hover_marking(ui: &mut Ui, mark: MarkRef) {
     let description = match &mark.ty {
-        MarkType::SearchResult => "Search result",
+        MarkType::SearchResult { .. } => "Search result",
         MarkType::UserMark { .. } => "User mark",
         MarkType::Selection => "Selection",
         MarkType::HoveredParsed => "Hovered parsed value",
         MarkType::HoveredParseErr => "Hovered parsing error",
+        MarkType::ParsedValueSearchResult => "Parsed value search result",
+        MarkType::CryptoConstant { .. } => "Cryptographic constant",
+        MarkType::ColoredParsed { .. } => "Parsed value",
+        MarkType::Unparsed => "Unparsed bytes",
     };
 
     ui.label(description);
+    if let MarkType::SearchResult { encoding } = &mark.ty
+        && *encoding != TextEncoding::Bytes
+    {
+        ui.label(format!("matched as {encoding}"));
+    }
+    if let MarkType::CryptoConstant { name } = &mark.ty {
+        ui.label(*name);
+    }
+    if let MarkType::ColoredParsed { name, .. } = &mark.ty
+        && !name.is_empty()
+    {
+        ui.label(name);
+    }
     if let MarkType::UserMark { name } = &mark.ty {
         if name.is_empty() {
             ui.label(RichText::new("unnamed").italics());
@@ -33,15 +51,16 @@ pub fn hover_marking(ui: &mut Ui, mark: MarkRef) {
     }
 
     ui.label(format!(
-        "Offset: {} ({}B)",
+        "Offset: {} ({}, {})",
+        mark.window.start().hex(),
         mark.window.start().as_u64(),
-        size_format::SizeFormatterBinary::new(mark.window.start().as_u64())
+        mark.window.start().len_from_start().human_size()
     ));
     if mark.window.size() > Len::from(1) {
         ui.label(format!(
-            "Length: {} ({}B)",
+            "Length: {} ({})",
             mark.window.size().as_u64(),
-            size_format::SizeFormatterBinary::new(mark.window.size().as_u64())
+            mark.window.size().human_size()
         ));
     }
 }
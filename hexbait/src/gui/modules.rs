@@ -6,6 +6,7 @@ use hexbait_common::Input;
 use crate::state::State;
 
 mod bars;
+pub mod carving;
 pub mod classification_info;
 pub mod content;
 pub mod data_inspector;
@@ -13,15 +14,18 @@ pub mod format_discovery;
 pub mod gilbert_map;
 pub mod hex;
 pub mod inspector;
+pub mod log;
 pub mod marking;
 pub mod parsed_value;
+pub mod pipe;
+pub mod script_console;
 pub mod scrollbars;
 pub mod search;
 pub mod settings;
 pub mod statistics_display;
 
 /// The different tab types in the hexbait application.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum TabType {
     /// Shows the content of the input.
     ///
@@ -40,6 +44,14 @@ pub enum TabType {
     ClassificationInfo,
     /// Shows the marking menu.
     Marking,
+    /// Shows the script console.
+    ScriptConsole,
+    /// Shows the collected log messages.
+    Log,
+    /// Shows the carving controls and the carved files.
+    Carving,
+    /// Shows controls to pipe the selection through external commands.
+    Pipe,
 }
 
 /// The context for the hexbait application.
@@ -68,6 +80,10 @@ impl egui_dock::TabViewer for Context {
             TabType::Search => search::show,
             TabType::ClassificationInfo => classification_info::show,
             TabType::Marking => marking::show,
+            TabType::ScriptConsole => script_console::show,
+            TabType::Log => log::show,
+            TabType::Carving => carving::show,
+            TabType::Pipe => pipe::show,
         };
 
         show_fn(ui, &mut self.state, &self.input);
@@ -76,7 +92,13 @@ impl egui_dock::TabViewer for Context {
     fn is_closeable(&self, tab: &Self::Tab) -> bool {
         matches!(
             tab,
-            TabType::Settings | TabType::Search | TabType::ClassificationInfo
+            TabType::Settings
+                | TabType::Search
+                | TabType::ClassificationInfo
+                | TabType::ScriptConsole
+                | TabType::Log
+                | TabType::Carving
+                | TabType::Pipe
         )
     }
 
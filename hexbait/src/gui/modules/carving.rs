@@ -0,0 +1,178 @@
+//! Renders the carving controls and the carved files in the GUI.
+
+use std::{path::PathBuf, sync::Arc};
+
+use egui::{Button, CollapsingHeader, DragValue, ProgressBar, RichText, ScrollArea, TextEdit, Ui};
+use hexbait_builtin_parsers::built_in_format_descriptions;
+use hexbait_common::Input;
+use hexbait_core::carve::{CarveOptions, CarvedFile, CarvingHandle};
+
+use crate::{marking::MarkType, state::State};
+
+/// Shows the carving controls and the carved files in the GUI.
+pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
+    let carving_state = &mut state.carving_state;
+
+    ui.horizontal(|ui| {
+        ui.label("Decompression depth:");
+        ui.add(DragValue::new(&mut carving_state.max_depth).range(0..=16));
+    });
+    if cfg!(not(target_arch = "wasm32")) {
+        ui.horizontal(|ui| {
+            ui.label("Output directory:");
+            ui.add(
+                TextEdit::singleline(&mut carving_state.output_dir)
+                    .hint_text("directory to extract into"),
+            );
+        });
+    }
+
+    ui.horizontal(|ui| {
+        let idle = carving_state.handle.is_none();
+        let mut output_dir = None;
+        let mut start = ui.add_enabled(idle, Button::new("scan")).clicked();
+        if cfg!(not(target_arch = "wasm32"))
+            && ui
+                .add_enabled(
+                    idle && !carving_state.output_dir.trim().is_empty(),
+                    Button::new("scan and extract"),
+                )
+                .clicked()
+        {
+            output_dir = Some(PathBuf::from(carving_state.output_dir.trim()));
+            start = true;
+        }
+
+        if start {
+            let options = CarveOptions {
+                max_depth: carving_state.max_depth,
+                output_dir,
+                // invalid built-in format descriptions are already logged by the parse state
+                parsers: built_in_format_descriptions()
+                    .into_iter()
+                    .filter_map(|(name, ir)| Some((name, ir.ok()?)))
+                    .collect(),
+            };
+            carving_state.handle = Some(CarvingHandle::start(input.clone(), Arc::new(options)));
+            carving_state.message = None;
+        }
+
+        if !idle && ui.button("cancel").clicked() {
+            carving_state.handle = None;
+            carving_state.message = Some(String::from("carving was cancelled"));
+        }
+    });
+
+    if let Some(handle) = &carving_state.handle {
+        match handle.take_result() {
+            Some(Ok(files)) => {
+                let count: usize = files.iter().map(CarvedFile::count).sum();
+                carving_state.message = Some(format!("found {count} files"));
+                carving_state.results = files;
+                carving_state.handle = None;
+            }
+            Some(Err(err)) => {
+                carving_state.message = Some(format!("carving failed: {err}"));
+                carving_state.handle = None;
+            }
+            None => {
+                ui.add(ProgressBar::new(handle.progress()).show_percentage());
+            }
+        }
+    }
+
+    if let Some(message) = &carving_state.message {
+        ui.label(message);
+    }
+
+    if !carving_state.results.is_empty() && ui.button("mark all").clicked() {
+        for file in &carving_state.results {
+            state.marked_locations.add(
+                file.window,
+                MarkType::UserMark {
+                    name: file.kind.mime_type.to_string(),
+                },
+            );
+        }
+    }
+
+    ui.separator();
+
+    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+        for (i, file) in carving_state.results.iter().enumerate() {
+            if let Some(file) = show_file(ui, file, i.to_string(), true) {
+                state
+                    .scroll_state
+                    .rearrange_bars_for_point(0, file.window.start());
+            }
+        }
+    });
+}
+
+/// Shows a carved file and the files nested in it.
+///
+/// Only files carved directly from the input can be navigated to.
+/// Returns the file if the user wants to navigate to it.
+fn show_file<'file>(
+    ui: &mut Ui,
+    file: &'file CarvedFile,
+    id: String,
+    in_input: bool,
+) -> Option<&'file CarvedFile> {
+    let mut clicked = None;
+
+    let show_summary = |ui: &mut Ui| {
+        let mut clicked = false;
+        ui.horizontal(|ui| {
+            let offset = RichText::new(file.window.start().hex().to_string()).monospace();
+            if in_input {
+                clicked = ui.link(offset).on_hover_text("go to the file").clicked();
+            } else {
+                ui.label(offset);
+            }
+            ui.label(file.kind.mime_type);
+            ui.label(RichText::new(file.window.size().human_size().to_string()).weak());
+            if let Some(len) = file.decompressed_len {
+                ui.label(RichText::new(format!("→ {}", len.human_size())).weak());
+            }
+            if let Some(check) = &file.parser_check {
+                if check.errors == 0 {
+                    ui.label(format!("{}: ok", check.name));
+                } else {
+                    ui.label(
+                        RichText::new(format!("{}: {} errors", check.name, check.errors))
+                            .color(ui.visuals().warn_fg_color),
+                    );
+                }
+            }
+        });
+        if let Some(path) = &file.extracted_to {
+            ui.label(RichText::new(format!("extracted to {}", path.display())).weak());
+        }
+        clicked
+    };
+
+    if file.children.is_empty() {
+        if show_summary(ui) {
+            clicked = Some(file);
+        }
+    } else {
+        CollapsingHeader::new(format!(
+            "{} {}",
+            file.window.start().hex(),
+            file.kind.mime_type
+        ))
+        .id_salt(&id)
+        .show(ui, |ui| {
+            if show_summary(ui) {
+                clicked = Some(file);
+            }
+            for (i, child) in file.children.iter().enumerate() {
+                // nested files are located in the decompressed content and not in the input
+                show_file(ui, child, format!("{id}/{i}"), false);
+            }
+        });
+    }
+
+    clicked
+}
@@ -3,10 +3,33 @@
 use egui::{Color32, Label, Rect, RichText, Sense, Ui, pos2, vec2};
 use hexbait_common::Input;
 
-use crate::state::State;
+use crate::state::{ParseType, State};
 
 /// Shows classification information in the GUI.
 pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
+    if let Some(kind) = state.classification_state.file_kind {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "File type: {} (.{})",
+                kind.mime_type, kind.extension
+            ));
+
+            if let Some((name, _)) = kind.builtin_parser().and_then(|name| {
+                state
+                    .parse_state
+                    .built_in_format_descriptions
+                    .get_key_value(name)
+            }) && state.parse_state.parse_type != ParseType::Builtin(name)
+                && ui.button(format!("parse as {name}")).clicked()
+            {
+                state.parse_state.parse_type = ParseType::Builtin(name);
+                state.parse_state.parse_offset = String::from("0");
+                state.parse_state.sync_parse_offset_to_selection_start = false;
+            }
+        });
+        ui.separator();
+    }
+
     if let Some(result) = &state.classification_state.classification_results {
         for class in result {
             ui.horizontal(|ui| {
@@ -2,6 +2,7 @@
 
 use egui::{Color32, Rect, RichText, Sense, Ui, scroll_area::DragScroll, vec2};
 use hexbait_common::{Endianness, Input};
+use hexbait_core::magic::FileKind;
 
 use crate::state::State;
 
@@ -17,9 +18,10 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
 
     let row_height = state.settings.font_size() * 1.1;
 
-    ui.horizontal(|ui| {
-        ui.selectable_value(&mut state.endianness, Endianness::Little, "Little Endian");
-        ui.selectable_value(&mut state.endianness, Endianness::Big, "Big Endian");
+    ui.horizontal_wrapped(|ui| {
+        for endianness in Endianness::ALL {
+            ui.selectable_value(&mut state.endianness, endianness, endianness.name());
+        }
     });
 
     let buf = selected.as_deref().unwrap_or(&[]);
@@ -33,12 +35,10 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
             let offset: usize = $offset;
             buf.get(offset..offset + ::std::mem::size_of::<$type>())
                 .map(|buf| {
-                    let from_bytes = match endianness {
-                        Endianness::Little => $type::from_le_bytes,
-                        Endianness::Big => $type::from_be_bytes,
-                    };
+                    let mut bytes: [u8; ::std::mem::size_of::<$type>()] = buf.try_into().unwrap();
+                    endianness.reorder_to_little_endian(&mut bytes);
 
-                    from_bytes(buf.try_into().unwrap())
+                    $type::from_le_bytes(bytes)
                 })
         }};
     }
@@ -295,13 +295,13 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
                 });
             }
 
-            if let Some(ty) = infer::get(buf) {
+            if let Some(kind) = FileKind::identify(buf) {
                 body.row(row_height, |mut row| {
                     row.col(|ui| {
                         ui.label("mime type");
                     });
                     row.col(|ui| {
-                        ui.label(ty.mime_type());
+                        ui.label(kind.mime_type);
                     });
                 });
             }
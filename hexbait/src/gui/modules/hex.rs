@@ -18,16 +18,17 @@ use crate::{
 
 /// Shows a hexdump in the GUI.
 pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
+    let bytes_per_row = state.settings.bytes_per_row();
     let start = state.scroll_state.hex_start();
-    let start_row = start.as_u64() / 16;
+    let start_row = start.as_u64() / bytes_per_row;
 
     let rect = ui.max_rect().intersect(ui.cursor());
     let height = ui.available_height();
-    let window_size = height.trunc() as u64 * 16;
+    let window_size = height.trunc() as u64 * bytes_per_row;
     let rows_onscreen = (height / state.settings.char_height()).trunc() as u64;
 
-    // add 16 more to show one row "beyond the screen"
-    let read_len = Len::from(window_size + 16);
+    // add one more row to show it "beyond the screen"
+    let read_len = Len::from(window_size + bytes_per_row);
 
     let file_size = input.len();
 
@@ -41,19 +42,21 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
         }
     };
 
-    let bar_width = (16 * state.settings.bar_width_multiplier()) as f32;
+    let bar_width = (bytes_per_row as usize * state.settings.bar_width_multiplier()) as f32;
     let offset_chars = 16;
-    let hex_chars = 16;
+    let hex_chars = bytes_per_row;
     let hex_rect_width = bar_width
         + ui.spacing().item_spacing.x
         + ((offset_chars + hex_chars * 3) as f32 * state.settings.char_width())
         + (2.0 * state.settings.large_space())
-        + (17.0 * state.settings.small_space());
+        + ((bytes_per_row + 1) as f32 * state.settings.small_space());
 
     let scroll_rect = rect.with_max_x(rect.min.x + hex_rect_width);
 
     // determine how many rows we can at most scroll down
-    let max_height = (window.len() as u64).min(window_size).div_ceil(16);
+    let max_height = (window.len() as u64)
+        .min(window_size)
+        .div_ceil(bytes_per_row);
     let max_scroll = max_height.saturating_sub(rows_onscreen);
 
     handle_scrolling(
@@ -62,6 +65,7 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
         scroll_rect,
         max_scroll,
         rows_onscreen,
+        bytes_per_row,
     );
 
     if ui.ctx().input(|input| !input.pointer.primary_down()) {
@@ -100,7 +104,7 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
         );
 
         for (i, row) in window
-            .chunks(16)
+            .chunks(bytes_per_row as usize)
             .enumerate()
             .skip(state.scroll_state.hex_scroll_offset as usize)
             .take(rows_onscreen as usize + 1)
@@ -109,7 +113,7 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
                 ui,
                 state,
                 input,
-                start + Len::from(i as u64 * 16),
+                start + Len::from(i as u64 * bytes_per_row),
                 row,
                 file_size,
             );
@@ -132,6 +136,7 @@ fn handle_scrolling(
     scroll_rect: Rect,
     max_scroll: u64,
     rows_onscreen: u64,
+    bytes_per_row: u64,
 ) {
     if ui.rect_contains_pointer(scroll_rect) {
         let raw_scroll_delta = ui.ctx().input(|input| input.smooth_scroll_delta).y;
@@ -141,7 +146,7 @@ fn handle_scrolling(
 
             if scroll_delta > scroll_state.hex_scroll_offset {
                 let diff = scroll_delta - scroll_state.hex_scroll_offset;
-                scroll_state.scroll_up(scroll_state.scrollbars.len() - 1, diff * 16);
+                scroll_state.scroll_up(scroll_state.scrollbars.len() - 1, diff * bytes_per_row);
 
                 scroll_state.hex_scroll_offset = 0;
             } else {
@@ -154,7 +159,7 @@ fn handle_scrolling(
                 let diff = (scroll_state.hex_scroll_offset + scroll_delta) - max_scroll;
                 scroll_state.scroll_down(
                     scroll_state.scrollbars.len() - 1,
-                    diff * 16,
+                    diff * bytes_per_row,
                     Len::from(rows_onscreen),
                 );
 
@@ -327,23 +332,26 @@ fn render_row(
         }
     };
 
+    let bytes_per_row = state.settings.bytes_per_row() as usize;
+    let half_row = bytes_per_row / 2;
+
     ui.horizontal(|ui| {
         ui.spacing_mut().item_spacing = Vec2::ZERO;
 
         let render_offset_info =
             |ui: &mut Ui, byte_offset: AbsoluteOffset, selection: Option<Window>| {
                 ui.label(format!(
-                    "offset from file start: 0x{:x} ({byte_offset:?}, {}B)",
-                    byte_offset.as_u64(),
-                    size_format::SizeFormatterBinary::new(byte_offset.as_u64())
+                    "offset from file start: {} ({byte_offset:?}, {})",
+                    byte_offset.hex(),
+                    byte_offset.len_from_start().human_size()
                 ));
                 if let Some(selection) = selection {
                     let selection_offset =
                         byte_offset.as_u64() as i64 - selection.start().as_u64() as i64;
                     ui.label(format!(
-                        "offset from selection start: {sign}0x{:x} ({selection_offset}, {sign}{}B)",
-                        selection_offset.unsigned_abs(),
-                        size_format::SizeFormatterBinary::new(selection_offset.unsigned_abs()),
+                        "offset from selection start: {sign}{} ({selection_offset}, {sign}{})",
+                        Len::from(selection_offset.unsigned_abs()).hex(),
+                        Len::from(selection_offset.unsigned_abs()).human_size(),
                         sign = if selection_offset < 0 { "-" } else { "" },
                     ));
                 }
@@ -353,16 +361,16 @@ fn render_row(
         render_offset(ui, &state.settings, Sense::hover(), offset).on_hover_ui(|ui| {
             let percentage = offset.as_u64() as f64 / file_size.as_u64() as f64 * 100.0;
             ui.label(format!(
-                "{} ({}B) {percentage:.02}% of file",
+                "{} ({}) {percentage:.02}% of file",
                 offset.as_u64(),
-                size_format::SizeFormatterBinary::new(offset.as_u64())
+                offset.len_from_start().human_size()
             ));
         });
         ui.add_space(state.settings.large_space());
 
         // hex values
         for (i, &byte) in row.iter().enumerate() {
-            if i == 8 {
+            if i == half_row {
                 ui.add_space(state.settings.small_space());
             }
 
@@ -384,24 +392,24 @@ fn render_row(
                 }
             });
 
-            if i < 15 {
+            if i < bytes_per_row - 1 {
                 ui.add_space(state.settings.small_space());
             }
         }
 
         // ensure non-full rows are still aligned
-        if row.len() < 16 {
+        if row.len() < bytes_per_row {
             let mut space = 0.0;
             // add the separator in the middle
-            if row.len() < 9 {
+            if row.len() <= half_row {
                 space += state.settings.small_space();
             }
 
             // add space for the characters
-            space += (16 - row.len()) as f32 * state.settings.char_width() * 2.0;
+            space += (bytes_per_row - row.len()) as f32 * state.settings.char_width() * 2.0;
 
             // add space between the characters
-            space += (15 - row.len()) as f32 * state.settings.small_space();
+            space += (bytes_per_row - 1 - row.len()) as f32 * state.settings.small_space();
 
             ui.add_space(space);
         }
@@ -409,7 +417,7 @@ fn render_row(
         ui.add_space(state.settings.large_space());
 
         for (i, &byte) in row.iter().enumerate() {
-            if i == 8 {
+            if i == half_row {
                 ui.add_space(state.settings.small_space());
             }
 
@@ -444,11 +452,12 @@ fn render_sidebar(
     start: u64,
 ) {
     let bar_width_multiplier = state.settings.bar_width_multiplier();
+    let bytes_per_row = state.settings.bytes_per_row() as usize;
 
     let mut rect = ui.max_rect().intersect(ui.cursor());
-    rect.set_width(16.0 * bar_width_multiplier as f32 + 1.0 + SIDE_BAR_WIDTH as f32);
+    rect.set_width((bytes_per_row * bar_width_multiplier) as f32 + 1.0 + SIDE_BAR_WIDTH as f32);
 
-    let num_rows = window.len().div_ceil(16);
+    let num_rows = window.len().div_ceil(bytes_per_row);
     rect.set_height(rect.height().min(num_rows as f32));
 
     let response = ui.allocate_rect(rect, Sense::click_and_drag());
@@ -472,22 +481,24 @@ fn render_sidebar(
             start,
             state.scroll_state.hex_scroll_offset,
             state.settings.linear_byte_colors(),
+            bytes_per_row,
         ),
         || (),
         |_, x, y| {
             let x = x / bar_width_multiplier;
 
-            if x == 16 {
+            if x == bytes_per_row {
                 Color32::BLACK
-            } else if x > 16 {
-                let start_offset = (start + y as u64) * 16;
-                let alignment = highest_aligned_value(start_offset, start_offset + 16);
+            } else if x > bytes_per_row {
+                let start_offset = (start + y as u64) * bytes_per_row as u64;
+                let alignment =
+                    highest_aligned_value(start_offset, start_offset + bytes_per_row as u64);
 
                 state
                     .settings
                     .alignment_marker_color(AbsoluteOffset::from(alignment))
                     .unwrap_or(Color32::BLACK)
-            } else if let Some(&byte) = window.get(y * 16 + x) {
+            } else if let Some(&byte) = window.get(y * bytes_per_row + x) {
                 if highlight_row_range.contains(&(y as u64)) {
                     state.settings.byte_color(byte)
                 } else {
@@ -503,7 +514,7 @@ fn render_sidebar(
         ui,
         rect,
         Window::from_start_len(
-            AbsoluteOffset::from(start * 16),
+            AbsoluteOffset::from(start * bytes_per_row as u64),
             Len::from(window.len() as u64),
         ),
         &mut state.marked_locations,
@@ -0,0 +1,83 @@
+//! Renders the collected log messages in the GUI.
+
+use egui::{Color32, ComboBox, RichText, ScrollArea, TextEdit, TextStyle, Ui};
+use hexbait_common::Input;
+use tracing::Level;
+
+use crate::{logging, state::State};
+
+/// Shows the log viewer in the GUI.
+pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
+    let log_state = &mut state.log_state;
+
+    ui.horizontal(|ui| {
+        ui.label("Level:");
+        ComboBox::new("log_level", "")
+            .selected_text(log_state.max_level.as_str())
+            .show_ui(ui, |ui| {
+                for level in [
+                    Level::ERROR,
+                    Level::WARN,
+                    Level::INFO,
+                    Level::DEBUG,
+                    Level::TRACE,
+                ] {
+                    ui.selectable_value(&mut log_state.max_level, level, level.as_str());
+                }
+            });
+        ui.add(
+            TextEdit::singleline(&mut log_state.filter)
+                .hint_text("filter")
+                .desired_width(150.0),
+        );
+        if ui.button("clear").clicked() {
+            logging::entries().clear();
+        }
+    });
+
+    ui.separator();
+
+    let entries = logging::entries();
+    let shown: Vec<_> = entries
+        .iter()
+        .filter(|entry| {
+            entry.level <= log_state.max_level
+                && (log_state.filter.is_empty()
+                    || entry.message.contains(&log_state.filter)
+                    || entry.target.contains(&log_state.filter))
+        })
+        .collect();
+
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    ScrollArea::both()
+        .auto_shrink(false)
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, shown.len(), |ui, rows| {
+            for entry in &shown[rows] {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("{:>10.3}", entry.time.as_secs_f64()))
+                            .monospace()
+                            .weak(),
+                    );
+                    ui.label(
+                        RichText::new(format!("{:>5}", entry.level.as_str()))
+                            .monospace()
+                            .color(level_color(ui, entry.level)),
+                    );
+                    ui.label(RichText::new(&entry.target).monospace().weak());
+                    ui.label(RichText::new(&entry.message).monospace());
+                });
+            }
+        });
+}
+
+/// The color in which the given level is displayed.
+fn level_color(ui: &Ui, level: Level) -> Color32 {
+    match level {
+        Level::ERROR => ui.visuals().error_fg_color,
+        Level::WARN => ui.visuals().warn_fg_color,
+        Level::INFO => ui.visuals().text_color(),
+        _ => ui.visuals().weak_text_color(),
+    }
+}
@@ -1,9 +1,15 @@
 //! Renders the marking menu in the GUI.
 
-use egui::Ui;
+use std::{borrow::Cow, path::Path};
+
+use egui::{ComboBox, RichText, TextEdit, Ui};
 use hexbait_common::Input;
 
-use crate::state::State;
+use crate::{
+    bookmarks::{self, BookmarkFormat},
+    project::ProjectMark,
+    state::State,
+};
 
 /// Shows the marking menu in the GUI.
 pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
@@ -22,4 +28,76 @@ pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
     {
         state.format_discovery.exit();
     }
+
+    if cfg!(not(target_arch = "wasm32")) {
+        ui.separator();
+        show_bookmarks(ui, state);
+    }
+}
+
+/// Shows the controls for importing and exporting bookmarks of other hex editors.
+fn show_bookmarks(ui: &mut Ui, state: &mut State) {
+    let bookmark_state = &mut state.bookmark_state;
+
+    ui.label("Bookmark file:");
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            TextEdit::singleline(&mut bookmark_state.path).hint_text("path of the bookmark file"),
+        );
+        if response.changed()
+            && let Some(format) = BookmarkFormat::from_path(Path::new(&bookmark_state.path))
+        {
+            bookmark_state.format = format;
+        }
+
+        ComboBox::new("bookmark_format", "")
+            .selected_text(bookmark_state.format.display_str())
+            .show_ui(ui, |ui| {
+                for format in BookmarkFormat::ALL {
+                    ui.selectable_value(&mut bookmark_state.format, format, format.display_str());
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        let has_path = !bookmark_state.path.trim().is_empty();
+        if ui
+            .add_enabled(has_path, egui::Button::new("import"))
+            .clicked()
+        {
+            let result = std::fs::read_to_string(bookmark_state.path.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|content| {
+                    bookmarks::import(bookmark_state.format, &content)
+                        .map_err(|err| err.to_string())
+                });
+            bookmark_state.message = Some(match result {
+                Ok(marks) => {
+                    for mark in &marks {
+                        mark.add_to(&mut state.marked_locations);
+                    }
+                    Cow::Owned(format!("imported {} bookmarks", marks.len()))
+                }
+                Err(err) => Cow::Owned(format!("failed to import bookmarks: {err}")),
+            });
+        }
+
+        if ui
+            .add_enabled(has_path, egui::Button::new("export"))
+            .on_hover_text("exports all marks")
+            .clicked()
+        {
+            let marks = ProjectMark::all_in(&state.marked_locations);
+            let content = bookmarks::export(bookmark_state.format, &marks);
+            bookmark_state.message =
+                Some(match std::fs::write(bookmark_state.path.trim(), content) {
+                    Ok(()) => Cow::Owned(format!("exported {} bookmarks", marks.len())),
+                    Err(err) => Cow::Owned(format!("failed to export bookmarks: {err}")),
+                });
+        }
+    });
+
+    if let Some(message) = &bookmark_state.message {
+        ui.label(RichText::new(message.as_ref()).weak());
+    }
 }
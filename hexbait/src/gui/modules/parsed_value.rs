@@ -1,20 +1,31 @@
 //! Implements showing of a parsed value.
 
-use egui::{FontId, Key, Layout, Response, RichText, ScrollArea, TextStyle, Ui, UiBuilder};
-use hexbait_common::{AbsoluteOffset, Input, RelativeOffset};
+use std::{collections::BTreeMap, sync::Arc};
+
+use egui::{
+    CollapsingHeader, FontId, Id, Key, Layout, Rect, Response, RichText, ScrollArea, TextStyle, Ui,
+    UiBuilder, vec2,
+};
+use hexbait_common::{AbsoluteOffset, Input};
 use hexbait_lang::{
-    ParseErr, ParseErrId, Value, ValueKind, View,
+    Coverage, ExportFormat, LazyElements, ParseErr, ParseErrId, ParseResult, Pointer, Provenance,
+    Value, ValueKind, export_value,
     ir::{
-        Symbol,
+        Classification, Symbol,
         path::{Path, PathComponent},
     },
+    value_locations,
 };
 
 use crate::{
     marking::MarkType,
+    search::parsed::{ParsedValueQuery, search_parsed_value},
     state::{ParseType, State},
 };
 
+/// The number of lazy elements of an array that are evaluated together once they become visible.
+const LAZY_CHUNK_LEN: usize = 100;
+
 /// Shows the parsed value module.
 pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
     ui.horizontal(|ui| {
@@ -86,6 +97,19 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
         &mut state.parse_state.sync_parse_offset_to_selection_start,
         "Sync parse offset to selection start",
     );
+    let renderer_names: Vec<_> = state
+        .parse_state
+        .value_renderers
+        .iter()
+        .map(|renderer| renderer.name)
+        .collect();
+    ui.checkbox(
+        &mut state.parse_state.show_value_previews,
+        "Show rich previews of values",
+    )
+    .on_hover_text(format!("available previews: {}", renderer_names.join(", ")));
+    ui.checkbox(&mut state.parse_state.shade_unparsed, "Shade unparsed gaps")
+        .on_hover_text("shades the bytes between parsed values that no value was parsed from");
 
     state
         .marked_locations
@@ -93,42 +117,78 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
     state
         .marked_locations
         .clear_marks_of_type(MarkType::HoveredParseErr);
-
-    let ir;
-    let parse_type = 'parse_type: {
-        match &state.parse_state.parse_type {
-            ParseType::None => None,
-            ParseType::Builtin(builtin) => {
-                state.parse_state.built_in_format_descriptions.get(builtin)
+    state
+        .marked_locations
+        .clear_marks_of_type(MarkType::ParsedValueSearchResult);
+
+    let result = state.parse_state.parse(input);
+    update_color_marks(state, result.as_ref());
+    update_unparsed_marks(state, result.as_ref());
+    if state.parse_state.is_parsing() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("parsing…");
+            if ui
+                .button("cancel")
+                .on_hover_text("show the values parsed so far")
+                .clicked()
+            {
+                state.parse_state.cancel_parsing();
             }
-            ParseType::Custom(path) => {
-                let Ok(content) = std::fs::read_to_string(path) else {
-                    break 'parse_type None;
-                };
-                let parse = hexbait_lang::parse(&content);
-                if !parse.errors.is_empty() {
-                    break 'parse_type None;
-                }
-                ir = hexbait_lang::ir::lower_file(parse.ast);
+        });
+    }
+    let Some(result) = result else {
+        return;
+    };
 
-                Some(&ir)
+    let mut jump_to_search_result = false;
+    ui.horizontal(|ui| {
+        ui.label("Find in value:");
+        let mut trigger_search = ui
+            .text_edit_singleline(&mut state.parse_state.value_search_text)
+            .lost_focus()
+            && ui.input(|i| i.key_pressed(Key::Enter));
+        trigger_search |= ui.button("find").clicked();
+
+        if trigger_search {
+            let query = ParsedValueQuery::new(&state.parse_state.value_search_text);
+            state.parse_state.value_search_results = search_parsed_value(&result.value, &query);
+            state.parse_state.value_search_current = 0;
+            jump_to_search_result = true;
+        }
+
+        let count = state.parse_state.value_search_results.len();
+        if count != 0 {
+            let current = &mut state.parse_state.value_search_current;
+            if ui.button("previous").clicked() {
+                *current = (*current + count - 1) % count;
+                jump_to_search_result = true;
             }
+            if ui.button("next").clicked() {
+                *current = (*current + 1) % count;
+                jump_to_search_result = true;
+            }
+            ui.label(format!("{}/{count}", *current + 1));
+        } else if trigger_search {
+            ui.label("no matches");
         }
-    };
+    });
 
-    let Some(parse_type) = parse_type else { return };
-    let Ok(parse_offset) = state
+    if let Some(path) = state
         .parse_state
-        .parse_offset
-        .parse()
-        .map(AbsoluteOffset::from)
-    else {
-        return;
-    };
+        .value_search_results
+        .get(state.parse_state.value_search_current)
+        && let Some(value) = result.value.subvalue_at_path(path)
+    {
+        if jump_to_search_result && let Some(range) = value.provenance.byte_ranges().next() {
+            state
+                .scroll_state
+                .rearrange_bars_for_point(0, range.start());
+        }
+        mark_provenance(state, &value.provenance, MarkType::ParsedValueSearchResult);
+    }
 
-    let view = View::from_input(input.clone());
-    let view = view.subview(parse_offset.to_relative()..RelativeOffset::from(view.len().as_u64()));
-    let result = hexbait_lang::eval_ir(parse_type, view, RelativeOffset::ZERO);
+    show_warnings(ui, state, &result);
 
     let hovered = ScrollArea::vertical()
         .auto_shrink([false, true])
@@ -144,31 +204,142 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
         })
         .inner;
 
+    mark_hovered(state, hovered, &result);
+}
+
+/// Shows the warnings of the parse result, such as failed checksum verifications.
+///
+/// The bytes that a hovered warning refers to are marked like those of a hovered error.
+fn show_warnings(ui: &mut Ui, state: &mut State, result: &ParseResult) {
+    if result.warnings.is_empty() {
+        return;
+    }
+
+    let mut hovered = None;
+    CollapsingHeader::new(
+        RichText::new(format!("{} warnings", result.warnings.len()))
+            .color(ui.visuals().warn_fg_color),
+    )
+    .id_salt("parse warnings")
+    .default_open(true)
+    .show(ui, |ui| {
+        ScrollArea::vertical()
+            .id_salt("parse warnings")
+            .max_height(ui.text_style_height(&TextStyle::Body) * 8.0)
+            .show(ui, |ui| {
+                for warning in &result.warnings {
+                    if ui
+                        .label(RichText::new(&warning.message).color(ui.visuals().warn_fg_color))
+                        .hovered()
+                    {
+                        hovered = Some(&warning.provenance);
+                    }
+                }
+            });
+    });
+
+    if let Some(provenance) = hovered {
+        mark_provenance(state, provenance, MarkType::HoveredParseErr);
+    }
+}
+
+/// Marks the bytes of the hovered value or error in the given parse result.
+fn mark_hovered(state: &mut State, hovered: HoverInfo, result: &ParseResult) {
     match hovered {
         HoverInfo::Nothing => (),
         HoverInfo::Value { path } => {
             if let Some(value) = result.value.subvalue_at_path(&path) {
-                for range in value.provenance.byte_ranges() {
-                    state.marked_locations.add(
-                        (AbsoluteOffset::from(*range.start())..=AbsoluteOffset::from(*range.end()))
-                            .into(),
-                        MarkType::HoveredParsed,
-                    );
-                }
+                mark_provenance(state, &value.provenance, MarkType::HoveredParsed);
             }
         }
         HoverInfo::Error { id } => {
-            for range in result.errors[id.raw_idx()].provenance.byte_ranges() {
-                state.marked_locations.add(
-                    (AbsoluteOffset::from(*range.start())..=AbsoluteOffset::from(*range.end()))
-                        .into(),
-                    MarkType::HoveredParseErr,
-                );
-            }
+            mark_provenance(
+                state,
+                &result.errors[id.raw_idx()].provenance,
+                MarkType::HoveredParseErr,
+            );
         }
     }
 }
 
+/// Marks all bytes of the given provenance with the given mark type.
+fn mark_provenance(state: &mut State, provenance: &Provenance, ty: MarkType) {
+    state
+        .marked_locations
+        .batch_add(provenance.byte_ranges(), ty);
+}
+
+/// Marks the bytes of the values that the format description gives a color.
+///
+/// The marks are only recomputed when the parse result changes.
+fn update_color_marks(state: &mut State, result: Option<&Arc<ParseResult>>) {
+    if state.parse_state.colored_result.as_ref().map(Arc::as_ptr) == result.map(Arc::as_ptr) {
+        return;
+    }
+
+    state
+        .marked_locations
+        .clear_marks_where(|ty| matches!(ty, MarkType::ColoredParsed { .. }));
+    if let Some(result) = result {
+        mark_colored_values(state, &result.value);
+    }
+    state.parse_state.colored_result = result.cloned();
+}
+
+/// Marks the bytes between the parsed values that were not parsed, if they should be shaded.
+///
+/// The marks are only recomputed when the parse result changes.
+fn update_unparsed_marks(state: &mut State, result: Option<&Arc<ParseResult>>) {
+    let result = result.filter(|_| state.parse_state.shade_unparsed);
+    if state.parse_state.shaded_result.as_ref().map(Arc::as_ptr) == result.map(Arc::as_ptr) {
+        return;
+    }
+
+    state
+        .marked_locations
+        .clear_marks_of_type(MarkType::Unparsed);
+    if let Some(result) = result {
+        let coverage = Coverage::of_value(&result.value);
+        state
+            .marked_locations
+            .batch_add(coverage.gaps(), MarkType::Unparsed);
+    }
+    state.parse_state.shaded_result = result.cloned();
+}
+
+/// Marks the bytes of the given value and the values within it if they have a color.
+///
+/// The marks are named after the innermost field containing the value.
+fn mark_colored_values(state: &mut State, value: &Value) {
+    let mut marks = BTreeMap::<_, Vec<_>>::new();
+    for location in value_locations(value) {
+        let Some(color) = &location.value.color else {
+            continue;
+        };
+        let name = location
+            .path
+            .iter()
+            .filter_map(|component| match component {
+                PathComponent::FieldAccess(name) => Some(name.as_str()),
+                PathComponent::Indexing(_) => None,
+            })
+            .last()
+            .unwrap_or_default();
+
+        marks
+            .entry(MarkType::ColoredParsed {
+                rgb: color.rgb(),
+                name: name.to_string(),
+            })
+            .or_default()
+            .push(location.range);
+    }
+
+    for (ty, ranges) in marks {
+        state.marked_locations.batch_add(ranges.into_iter(), ty);
+    }
+}
+
 /// Information about what is hovered.
 #[derive(Debug, PartialEq, Eq)]
 pub enum HoverInfo {
@@ -205,23 +376,53 @@ fn show_value(
 
     let mut this_hovered = false;
     let mut this_clicked = false;
+    let old_export_with_offsets = state.parse_state.export_with_offsets;
+    let mut export_with_offsets = old_export_with_offsets;
+
+    let mut handle_response = |mut response: Response| {
+        if let Some(doc) = &value.doc {
+            response = response.on_hover_text(&**doc);
+        }
 
-    let mut handle_response = |response: Response| {
         if response.clicked() {
             this_clicked = true;
         } else if response.hovered() {
             this_hovered = true;
         }
+
+        response.context_menu(|ui| {
+            ui.checkbox(&mut export_with_offsets, "include offsets");
+            for format in ExportFormat::ALL {
+                if ui
+                    .button(format!("copy as {}", format.display_str()))
+                    .clicked()
+                {
+                    ui.ctx()
+                        .copy_text(export_value(value, format, export_with_offsets));
+                    ui.close();
+                }
+            }
+        });
     };
 
     let mut child_hovered = HoverInfo::Nothing;
     let mut hovered_err = None;
+    let classified = value
+        .classification
+        .and_then(|classification| classification.format(&value.kind));
 
-    match &value.kind {
-        ValueKind::Boolean(_) | ValueKind::Integer(_) | ValueKind::Float(_) => {
-            handle_response(ui.label(format!("{name_prefix}{:?},", value.kind)));
+    match (&value.kind, classified) {
+        (_, Some(classified)) => {
+            handle_response(show_classified(ui, state, &name_prefix, value, classified));
+        }
+        (ValueKind::Boolean(_) | ValueKind::Integer(_) | ValueKind::Float(_), None) => {
+            let label = match &value.name {
+                Some(symbol) => format!("{name_prefix}{symbol} = {:?},", value.kind),
+                None => format!("{name_prefix}{:?},", value.kind),
+            };
+            handle_response(ui.label(label));
         }
-        ValueKind::Bytes(bytes) => {
+        (ValueKind::Bytes(bytes), None) => {
             ui.horizontal(|ui| {
                 let old_spacing = ui.spacing_mut().item_spacing;
                 ui.spacing_mut().item_spacing.x = 0.0;
@@ -281,7 +482,7 @@ fn show_value(
                 ui.spacing_mut().item_spacing = old_spacing;
             });
         }
-        ValueKind::Struct { fields, error } => {
+        (ValueKind::Struct { fields, error }, None) => {
             ui.vertical(|ui| {
                 handle_response(ui.label(format!("{name_prefix}{{")));
 
@@ -296,7 +497,7 @@ fn show_value(
                             let mut path = path.clone();
                             path.push(PathComponent::FieldAccess(name.clone()));
 
-                            let hovered = show_value(ui, state, path, Some(name), value, errors);
+                            let hovered = show_child(ui, state, path, Some(name), value, errors);
                             if hovered != HoverInfo::Nothing {
                                 child_hovered = hovered;
                             }
@@ -309,7 +510,7 @@ fn show_value(
                 handle_response(ui.label("},"));
             });
         }
-        ValueKind::Array { items, error } => {
+        (ValueKind::Array { items, error, lazy }, None) => {
             ui.vertical(|ui| {
                 handle_response(ui.label(format!("{name_prefix}[")));
 
@@ -324,11 +525,14 @@ fn show_value(
                             let mut path = path.clone();
                             path.push(PathComponent::Indexing(i));
 
-                            let hovered = show_value(ui, state, path, None, value, errors);
+                            let hovered = show_child(ui, state, path, None, value, errors);
                             if hovered != HoverInfo::Nothing {
                                 child_hovered = hovered;
                             }
                         }
+                        if let Some(lazy) = lazy {
+                            show_lazy_elements(ui, state, &path, lazy, items.last());
+                        }
                         hovered_err =
                             hovered_err.or(render_error_and_return_hovered(ui, error, errors));
                     },
@@ -339,10 +543,32 @@ fn show_value(
         }
     }
 
+    if let Some(pointer) = &value.pointer {
+        show_pointer(ui, state, &path, pointer);
+    }
+
+    if export_with_offsets != old_export_with_offsets {
+        state.parse_state.export_with_offsets = export_with_offsets;
+    }
+
+    if state.parse_state.show_value_previews
+        && let Some(renderer) = state
+            .parse_state
+            .value_renderers
+            .find(name.map(Symbol::as_str), value)
+    {
+        let id = Id::new((
+            "value preview",
+            &path,
+            value.provenance.byte_ranges().next(),
+        ));
+        ui.indent(id, |ui| (renderer.show)(ui, id, value));
+    }
+
     if this_clicked && let Some(byte_range) = value.provenance.byte_ranges().next() {
         state
             .scroll_state
-            .rearrange_bars_for_point(0, AbsoluteOffset::from(*byte_range.start()));
+            .rearrange_bars_for_point(0, byte_range.start());
     }
 
     if child_hovered != HoverInfo::Nothing {
@@ -356,6 +582,188 @@ fn show_value(
     }
 }
 
+/// Shows a value in the form described by its classification.
+///
+/// Offsets are shown as links that move the hex view to the offset.
+fn show_classified(
+    ui: &mut Ui,
+    state: &mut State,
+    name_prefix: &str,
+    value: &Value,
+    classified: String,
+) -> Response {
+    let offset = match (&value.classification, &value.kind) {
+        (Some(Classification::Offset), ValueKind::Integer(int)) => u64::try_from(int)
+            .ok()
+            .filter(|&offset| offset < state.scroll_state.file_size().as_u64()),
+        _ => None,
+    };
+
+    let Some(offset) = offset else {
+        return ui
+            .label(format!("{name_prefix}{classified},"))
+            .on_hover_text(format!("{:?}", value.kind));
+    };
+
+    ui.horizontal(|ui| {
+        let old_spacing = ui.spacing_mut().item_spacing;
+        ui.spacing_mut().item_spacing.x = 0.0;
+
+        ui.label(name_prefix);
+        if ui.link(classified).on_hover_text("go to offset").clicked() {
+            state
+                .scroll_state
+                .rearrange_bars_for_point(0, AbsoluteOffset::from(offset));
+        }
+        ui.label(",");
+
+        ui.spacing_mut().item_spacing = old_spacing;
+    })
+    .response
+}
+
+/// Displays a child value of a `struct` or array, if it is visible.
+///
+/// The height of every child is remembered from the last time it was shown. Children outside of
+/// the visible area only take up that much space without being shown, so that only the visible
+/// part of large values costs time. Children that were never shown use an estimated height.
+fn show_child(
+    ui: &mut Ui,
+    state: &mut State,
+    path: Path,
+    name: Option<&Symbol>,
+    value: &Value,
+    errors: &[ParseErr],
+) -> HoverInfo {
+    let id = Id::new(("value height", &path, value.provenance.byte_ranges().next()));
+    let height = ui.data(|data| data.get_temp(id)).unwrap_or_else(|| {
+        let row_height = ui.text_style_height(&TextStyle::Body);
+        let rows = estimated_rows(value) as f32;
+        rows * row_height + (rows - 1.0) * ui.spacing().item_spacing.y
+    });
+
+    let rect = Rect::from_min_size(ui.cursor().min, vec2(ui.available_width(), height));
+    if !ui.is_rect_visible(rect) {
+        ui.allocate_space(vec2(0.0, height));
+        ui.data_mut(|data| data.insert_temp(id, height));
+        return HoverInfo::Nothing;
+    }
+
+    let shown = ui.scope(|ui| show_value(ui, state, path, name, value, errors));
+    ui.data_mut(|data| data.insert_temp(id, shown.response.rect.height()));
+
+    shown.inner
+}
+
+/// Estimates the number of rows needed to show the value without previews.
+fn estimated_rows(value: &Value) -> usize {
+    usize::from(value.pointer.is_some())
+        + match &value.kind {
+            ValueKind::Boolean(_)
+            | ValueKind::Integer(_)
+            | ValueKind::Float(_)
+            | ValueKind::Bytes(_) => 1,
+            ValueKind::Struct { fields, error } => {
+                2 + usize::from(error.is_some())
+                    + fields
+                        .iter()
+                        .map(|(_, value)| estimated_rows(value))
+                        .sum::<usize>()
+            }
+            ValueKind::Array { items, error, lazy } => {
+                2 + usize::from(error.is_some())
+                    + usize::from(lazy.is_some())
+                    + items.iter().map(estimated_rows).sum::<usize>()
+            }
+        }
+}
+
+/// Displays the lazy elements of the array at the given path.
+///
+/// The elements are evaluated in chunks once they become visible. Chunks that were never visible
+/// only take up the height they are estimated to have, based on the given example element. Since
+/// the elements are not part of the parse result, the hovered values and errors among them are
+/// marked directly.
+fn show_lazy_elements(
+    ui: &mut Ui,
+    state: &mut State,
+    path: &Path,
+    lazy: &LazyElements,
+    example: Option<&Value>,
+) {
+    let id = Id::new(("lazy elements", path, lazy.len()));
+    let spacing = ui.spacing().item_spacing.y;
+    let rows = example.map_or(1, estimated_rows) as f32;
+    let element_height = rows * ui.text_style_height(&TextStyle::Body) + rows * spacing;
+
+    for chunk_start in (0..lazy.len()).step_by(LAZY_CHUNK_LEN) {
+        let chunk_id = id.with(chunk_start);
+        let chunk: Option<Arc<ParseResult>> = ui.data(|data| data.get_temp(chunk_id));
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => {
+                let len = LAZY_CHUNK_LEN.min(lazy.len() - chunk_start);
+                let height = len as f32 * element_height - spacing;
+                let rect = Rect::from_min_size(ui.cursor().min, vec2(ui.available_width(), height));
+                if !ui.is_rect_visible(rect) {
+                    ui.allocate_space(vec2(0.0, height));
+                    continue;
+                }
+
+                let chunk = Arc::new(lazy.evaluate(chunk_start..chunk_start + len));
+                ui.data_mut(|data| data.insert_temp(chunk_id, Arc::clone(&chunk)));
+                chunk
+            }
+        };
+
+        for (i, value) in chunk.value.kind.expect_array().iter().enumerate() {
+            let mut path = Path::new();
+            path.push(PathComponent::Indexing(i));
+
+            let hovered = show_child(ui, state, path, None, value, &chunk.errors);
+            mark_hovered(state, hovered, &chunk);
+        }
+    }
+}
+
+/// Displays a link to the target of the pointer at the given path and the target once it is loaded.
+///
+/// Hovering the link marks the first byte of the target. Like lazy elements, the target is not
+/// part of the parse result, so the hovered values and errors in it are marked directly.
+fn show_pointer(ui: &mut Ui, state: &mut State, path: &Path, pointer: &Pointer) {
+    let id = Id::new(("pointer target", path, pointer.target_offset()));
+    let target: Option<Arc<ParseResult>> = ui.data(|data| data.get_temp(id));
+
+    ui.indent(id, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("→");
+            if let Some(offset) = pointer.target_offset() {
+                let response = ui
+                    .link(format!("{:#x}", offset.as_u64()))
+                    .on_hover_text("follow pointer");
+                if response.clicked() {
+                    state.scroll_state.rearrange_bars_for_point(0, offset);
+                }
+                if response.hovered() {
+                    mark_provenance(
+                        state,
+                        &Provenance::from(offset..AbsoluteOffset::from(offset.as_u64() + 1)),
+                        MarkType::HoveredParsed,
+                    );
+                }
+            }
+            if target.is_none() && ui.button("load target").clicked() {
+                ui.data_mut(|data| data.insert_temp(id, Arc::new(pointer.evaluate())));
+            }
+        });
+
+        if let Some(target) = target {
+            let hovered = show_child(ui, state, Path::new(), None, &target.value, &target.errors);
+            mark_hovered(state, hovered, &target);
+        }
+    });
+}
+
 /// Renders the given error to the UI if it is present.
 ///
 /// Returns the hovered error if it is hovered.
@@ -0,0 +1,343 @@
+//! Renders the controls for piping selections through external commands.
+
+use std::fmt::Write as _;
+
+use egui::{Button, CollapsingHeader, RichText, ScrollArea, Spinner, TextEdit, TextStyle, Ui};
+use hexbait_common::{Input, Len};
+use hexbait_lang::{BytesValue, UpdateSequence};
+
+use crate::{
+    pipe::{ExternalTool, PipeJob},
+    state::{PipeResult, State},
+    window::Window,
+};
+
+/// The maximum number of bytes that are piped through a command.
+const MAX_PIPE_INPUT_LEN: Len = Len::from(256 * 1024 * 1024);
+
+/// The name under which the result of applying NTFS fixups is shown.
+const NTFS_FIXUPS_NAME: &str = "NTFS fixups";
+
+/// Shows the controls for piping selections through external commands.
+pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
+    show_transforms(ui, state, input);
+
+    if cfg!(target_arch = "wasm32") {
+        ui.label("Running external commands is not supported in the browser.");
+        if let Some(result) = &state.pipe_state.result {
+            ui.separator();
+            show_result(ui, result, &mut state.pipe_state.open_as_document);
+        }
+        return;
+    }
+
+    poll_job(state);
+
+    show_tools(ui, state);
+
+    ui.horizontal(|ui| {
+        ui.label("Command:");
+        ui.add(
+            TextEdit::singleline(&mut state.pipe_state.command)
+                .code_editor()
+                .hint_text("e.g. openssl asn1parse -inform DER"),
+        );
+    });
+
+    let selection = state.selection_state.selected_window();
+    ui.horizontal(|ui| {
+        let can_run = state.pipe_state.job.is_none()
+            && selection.is_some()
+            && !state.pipe_state.command.trim().is_empty();
+        if ui
+            .add_enabled(can_run, Button::new("run on selection"))
+            .clicked()
+            && let Some(window) = selection
+        {
+            start_job(ui, state, input, window);
+        }
+
+        match selection {
+            Some(window) => ui.label(format!("{} selected", window.size().human_size())),
+            None => ui.label(RichText::new("select bytes to pipe them through the command").weak()),
+        };
+    });
+
+    if let Some((_, job)) = &state.pipe_state.job {
+        let mut discard = false;
+        ui.horizontal(|ui| {
+            ui.add(Spinner::new());
+            ui.label(format!("running `{}`", job.command()));
+            discard = ui.button("discard").clicked();
+        });
+        if discard {
+            state.pipe_state.job = None;
+        }
+    }
+
+    ui.separator();
+
+    if let Some(result) = &state.pipe_state.result {
+        show_result(ui, result, &mut state.pipe_state.open_as_document);
+    }
+}
+
+/// Shows the transforms that are built into hexbait.
+fn show_transforms(ui: &mut Ui, state: &mut State, input: &Input) {
+    let selection = state.selection_state.selected_window();
+    ui.horizontal(|ui| {
+        ui.label("Transforms:");
+        if ui
+            .add_enabled(selection.is_some(), Button::new(NTFS_FIXUPS_NAME))
+            .on_hover_text(
+                "applies the update sequence array of the selected NTFS MFT entry or index record",
+            )
+            .clicked()
+            && let Some(window) = selection
+        {
+            state.pipe_state.result = Some(apply_ntfs_fixups(input, window));
+        }
+    });
+}
+
+/// Applies the update sequence array of the NTFS record in the window.
+///
+/// The offset and the number of entries of the array are read from the header of the record.
+fn apply_ntfs_fixups(input: &Input, window: Window) -> PipeResult {
+    if window.size() > MAX_PIPE_INPUT_LEN {
+        return error_result(
+            NTFS_FIXUPS_NAME,
+            window,
+            format!(
+                "the selection is larger than {}",
+                MAX_PIPE_INPUT_LEN.human_size()
+            ),
+        );
+    }
+
+    let record = match input.read_at(window.start(), window.size(), None) {
+        Ok(record) => BytesValue::Lit(record.to_vec().into()),
+        Err(err) => return error_result(NTFS_FIXUPS_NAME, window, err.to_string()),
+    };
+    let mut header = [0; 4];
+    if record.fill_buf_at(4, &mut header).is_err() {
+        return error_result(
+            NTFS_FIXUPS_NAME,
+            window,
+            String::from("the selection is too short to contain a record header"),
+        );
+    }
+    let offset = u16::from_le_bytes([header[0], header[1]]);
+    let count = u16::from_le_bytes([header[2], header[3]]);
+
+    let fixed = UpdateSequence::read(&record, usize::from(offset), usize::from(count))
+        .and_then(|sequence| sequence.apply(&record))
+        .and_then(|(fixed, torn)| Ok((fixed.value()?.to_vec(), torn)));
+    let (stdout, torn) = match fixed {
+        Ok(fixed) => fixed,
+        Err(err) => return error_result(NTFS_FIXUPS_NAME, window, err.to_string()),
+    };
+
+    PipeResult {
+        command: NTFS_FIXUPS_NAME.to_string(),
+        window,
+        lines: stdout
+            .chunks(16)
+            .map(|row| {
+                row.iter().fold(String::new(), |mut line, byte| {
+                    let _ = write!(line, "{byte:02x} ");
+                    line
+                })
+            })
+            .collect(),
+        stdout,
+        stderr: torn
+            .iter()
+            .map(|sector| format!("sector {sector} does not end in the update sequence number\n"))
+            .collect(),
+        status: format!("{count} update sequence entries at {offset:#x}"),
+        success: torn.is_empty(),
+    }
+}
+
+/// Shows the configured tools and the controls to add new ones.
+fn show_tools(ui: &mut Ui, state: &mut State) {
+    let mut removed = None;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Tools:");
+        for (i, tool) in state.settings.external_tools().iter().enumerate() {
+            let response = ui.button(&tool.name).on_hover_text(&tool.command);
+            if response.clicked() {
+                state.pipe_state.command = tool.command.clone();
+            }
+            response.context_menu(|ui| {
+                if ui.button("remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+    });
+    if let Some(i) = removed {
+        state.settings.external_tools_mut().remove(i);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(
+            TextEdit::singleline(&mut state.pipe_state.tool_name)
+                .hint_text("tool name")
+                .desired_width(150.0),
+        );
+        let can_save = !state.pipe_state.tool_name.trim().is_empty()
+            && !state.pipe_state.command.trim().is_empty();
+        if ui
+            .add_enabled(can_save, Button::new("save command as tool"))
+            .on_hover_text("use \"Save settings\" in the settings to keep it")
+            .clicked()
+        {
+            let tool = ExternalTool {
+                name: std::mem::take(&mut state.pipe_state.tool_name)
+                    .trim()
+                    .to_string(),
+                command: state.pipe_state.command.trim().to_string(),
+            };
+            state.settings.external_tools_mut().push(tool);
+        }
+    });
+}
+
+/// Starts piping the given window through the current command.
+fn start_job(ui: &Ui, state: &mut State, input: &Input, window: Window) {
+    let pipe_state = &mut state.pipe_state;
+    if window.size() > MAX_PIPE_INPUT_LEN {
+        pipe_state.result = Some(error_result(
+            &pipe_state.command,
+            window,
+            format!(
+                "the selection is larger than {}",
+                MAX_PIPE_INPUT_LEN.human_size()
+            ),
+        ));
+        return;
+    }
+
+    let command = pipe_state.command.trim().to_string();
+    let job = input
+        .read_at(window.start(), window.size(), None)
+        .and_then(|bytes| PipeJob::start(&command, bytes.to_vec(), ui.ctx().clone()));
+    match job {
+        Ok(job) => pipe_state.job = Some((window, job)),
+        Err(err) => pipe_state.result = Some(error_result(&command, window, err.to_string())),
+    }
+}
+
+/// Collects the result of the running command once it finished.
+fn poll_job(state: &mut State) {
+    let pipe_state = &mut state.pipe_state;
+    let Some((window, job)) = &pipe_state.job else {
+        return;
+    };
+    let Some(output) = job.try_result() else {
+        return;
+    };
+
+    let window = *window;
+    let command = job.command().to_string();
+    pipe_state.job = None;
+
+    pipe_state.result = Some(match output {
+        Ok(output) => {
+            tracing::debug!(command, status = %output.status, "external command finished");
+            PipeResult {
+                lines: String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                command,
+                window,
+                stdout: output.stdout,
+                stderr: output.stderr,
+                status: output.status.to_string(),
+                success: output.status.success(),
+            }
+        }
+        Err(err) => error_result(&command, window, format!("cannot run the command: {err}")),
+    });
+}
+
+/// Creates a result describing that the command could not be run.
+fn error_result(command: &str, window: Window, message: String) -> PipeResult {
+    PipeResult {
+        command: command.to_string(),
+        window,
+        stdout: Vec::new(),
+        lines: Vec::new(),
+        stderr: message,
+        status: String::new(),
+        success: false,
+    }
+}
+
+/// Shows the result of a command.
+fn show_result(ui: &mut Ui, result: &PipeResult, open_as_document: &mut Option<(String, Vec<u8>)>) {
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(&result.command).monospace());
+        ui.label(
+            RichText::new(format!(
+                "on {} at {}",
+                result.window.size().human_size(),
+                result.window.start().hex()
+            ))
+            .weak(),
+        );
+        if !result.status.is_empty() {
+            let color = if result.success {
+                ui.visuals().text_color()
+            } else {
+                ui.visuals().warn_fg_color
+            };
+            ui.label(RichText::new(&result.status).color(color));
+        }
+    });
+
+    if !result.stderr.is_empty() {
+        CollapsingHeader::new(RichText::new("error output").color(ui.visuals().warn_fg_color))
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.label(RichText::new(&result.stderr).monospace());
+            });
+    }
+
+    if result.stdout.is_empty() {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} of output",
+            Len::from(result.stdout.len() as u64).human_size()
+        ));
+        if ui.button("copy").clicked() {
+            ui.ctx()
+                .copy_text(String::from_utf8_lossy(&result.stdout).into_owned());
+        }
+        if ui
+            .button("open as document")
+            .on_hover_text("replaces the current file with the output")
+            .clicked()
+        {
+            *open_as_document = Some((result.command.clone(), result.stdout.clone()));
+        }
+    });
+
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    ScrollArea::both().auto_shrink(false).show_rows(
+        ui,
+        row_height,
+        result.lines.len(),
+        |ui, rows| {
+            for line in &result.lines[rows] {
+                ui.label(RichText::new(line).monospace());
+            }
+        },
+    );
+}
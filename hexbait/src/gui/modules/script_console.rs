@@ -0,0 +1,72 @@
+//! Renders the script console in the GUI.
+
+use egui::{Key, Modifiers, RichText, ScrollArea, TextEdit, Ui};
+use hexbait_common::Input;
+
+use crate::{
+    scripting::{ConsoleLineKind, SCRIPT_FUNCTIONS, run_script},
+    state::State,
+};
+
+/// Shows the script console in the GUI.
+pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
+    let response = ui.add(
+        TextEdit::multiline(&mut state.script_state.script)
+            .code_editor()
+            .desired_rows(4)
+            .desired_width(f32::INFINITY)
+            .hint_text("print(len());"),
+    );
+    let mut run =
+        response.has_focus() && ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Enter));
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                !state.script_state.script.trim().is_empty(),
+                egui::Button::new("run"),
+            )
+            .on_hover_text("Ctrl+Enter")
+            .clicked()
+        {
+            run = true;
+        }
+        if ui.button("clear output").clicked() {
+            state.script_state.output.clear();
+        }
+        if ui.button("reset variables").clicked() {
+            state.script_state.scope.clear();
+        }
+        ui.label("ℹ").on_hover_ui(|ui| {
+            ui.label("Scripts are written in Rhai. Available functions:");
+            for (signature, description) in SCRIPT_FUNCTIONS {
+                ui.label(format!("{signature}: {description}"));
+            }
+        });
+    });
+
+    if run && !state.script_state.script.trim().is_empty() {
+        let script = state.script_state.script.clone();
+        let mut scope = std::mem::take(&mut state.script_state.scope);
+        let output = run_script(&script, &mut scope, state, input);
+        state.script_state.scope = scope;
+        state.script_state.output.extend(output);
+    }
+
+    ui.separator();
+
+    ScrollArea::vertical()
+        .auto_shrink(false)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for line in &state.script_state.output {
+                let text = RichText::new(&line.text).monospace();
+                let text = match line.kind {
+                    ConsoleLineKind::Script => text.weak(),
+                    ConsoleLineKind::Output => text,
+                    ConsoleLineKind::Error => text.color(ui.visuals().error_fg_color),
+                };
+                ui.label(text);
+            }
+        });
+}
@@ -5,7 +5,6 @@ use egui::{
     Stroke, Tooltip, Ui, pos2, vec2,
 };
 use hexbait_common::{AbsoluteOffset, Input, Len, RelativeOffset};
-use size_format::SizeFormatterBinary;
 
 use crate::{
     IDLE_TIME,
@@ -65,7 +64,7 @@ pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
         ui.painter().text(
             rect.min,
             egui::Align2::LEFT_BOTTOM,
-            format!("{}B", SizeFormatterBinary::new(window.size().as_u64())),
+            window.size().human_size().to_string(),
             FontId::proportional(size_text_height),
             ui.style().noninteractive().text_color(),
         );
@@ -263,15 +262,15 @@ fn handle_interactions(
                     if start <= end {
                         let len = std::cmp::max(end - start, total_bytes);
 
-                        if start + len > RelativeOffset::from(window.size().as_u64()) {
-                            (RelativeOffset::from((window.size() - len).as_u64()), len)
+                        if start + len > window.size().to_relative() {
+                            ((window.size() - len).to_relative(), len)
                         } else {
                             (start, len)
                         }
                     } else {
                         let len = std::cmp::max(start - end, total_bytes);
 
-                        if RelativeOffset::from(len.as_u64()) > start {
+                        if len.to_relative() > start {
                             (RelativeOffset::ZERO, len)
                         } else {
                             (start - len, len)
@@ -1,9 +1,10 @@
 //! Renders a search screen in the GUI.
 
-use egui::{Button, Checkbox, Key, RichText, Ui};
+use egui::{Button, Checkbox, Key, ProgressBar, RichText, Ui};
 use hexbait_common::{AbsoluteOffset, Input};
+use hexbait_core::crypto_constants::ConstantScanHandle;
 
-use crate::{marking::MarkType, state::State, window::Window};
+use crate::{marking::MarkType, search::SearchQuery, state::State, window::Window};
 
 /// Shows the search screen in the GUI.
 pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
@@ -17,29 +18,46 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
             trigger_search = true;
         }
 
-        let search_bytes = match state.search.search_bytes() {
-            Ok(bytes) => Some(bytes),
+        let search_query = match state.search.search_query() {
+            Ok(query) => Some(query),
             Err(msg) => {
                 ui.label(RichText::new("⚠").color(ui.visuals().warn_fg_color))
                     .on_hover_ui(|ui| {
-                        ui.label(format!("invalid string literal: {msg}"));
+                        ui.label(format!("invalid search: {msg}"));
                     });
                 None
             }
         };
 
-        let valid_utf8 = search_bytes
+        let valid_utf8 = search_query
             .as_ref()
-            .map(|search_bytes| std::str::from_utf8(search_bytes).is_ok())
-            .unwrap_or(false);
+            .is_some_and(|query| query.as_text().is_some());
 
+        if let Some(SearchQuery::Pattern(pattern)) = &search_query {
+            ui.label(format!(
+                "searching for byte pattern: {}",
+                pattern
+                    .iter()
+                    .map(|byte| match byte {
+                        Some(byte) => format!("{byte:02x}"),
+                        None => String::from("??"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+
+        ui.checkbox(&mut state.search.search_regex, "regular expression");
         ui.checkbox(
             &mut state.search.search_ascii_case_insensitive,
             "ASCII case insensitive",
         );
         ui.add_enabled(
             valid_utf8,
-            Checkbox::new(&mut state.search.search_utf16, "include UTF-16"),
+            Checkbox::new(
+                &mut state.search.search_all_text_encodings,
+                "search as UTF-8, Latin-1 and UTF-16",
+            ),
         );
         ui.checkbox(
             &mut state.search.search_current_window,
@@ -48,9 +66,9 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
 
         if ui
             .add_enabled(
-                search_bytes
+                search_query
                     .as_ref()
-                    .is_some_and(|search_bytes| !search_bytes.is_empty()),
+                    .is_some_and(|query| query.is_searchable()),
                 Button::new("start search"),
             )
             .clicked()
@@ -58,39 +76,103 @@ pub fn show(ui: &mut Ui, state: &mut State, input: &Input) {
             trigger_search = true;
         }
 
-        if trigger_search && let Some(search_bytes) = &search_bytes {
+        if trigger_search {
             let window = if state.search.search_current_window {
                 state.scroll_state.selected_window()
             } else {
                 Window::from_start_len(AbsoluteOffset::ZERO, input.len())
             };
-            state.search.searcher.start_new_search(
-                search_bytes,
-                state.search.search_ascii_case_insensitive,
-                state.search.search_utf16 && valid_utf8,
-                window,
-            );
-            state
-                .marked_locations
-                .clear_marks_of_type(MarkType::SearchResult);
+            if state.search.start_search(window) {
+                state
+                    .marked_locations
+                    .clear_marks_where(|ty| matches!(ty, MarkType::SearchResult { .. }));
+            }
         }
 
-        if state.search.searcher.progress() < 1.0 && ui.button("end search").clicked() {
-            state.search.searcher.stop_search();
+        if state.search.searcher.is_running() {
+            ui.horizontal(|ui| {
+                if state.search.searcher.is_paused() {
+                    if ui.button("resume search").clicked() {
+                        state.search.searcher.resume_search();
+                    }
+                } else if ui.button("pause search").clicked() {
+                    state.search.searcher.pause_search();
+                }
+
+                if ui.button("end search").clicked() {
+                    state.search.searcher.stop_search();
+                }
+            });
         }
 
-        if state.marked_locations.count_of_type(MarkType::SearchResult) != 0
-            && ui.button("clear results").clicked()
-        {
+        let result_count = state
+            .marked_locations
+            .count_where(|ty| matches!(ty, MarkType::SearchResult { .. }));
+
+        if result_count != 0 && ui.button("clear results").clicked() {
             state
                 .marked_locations
-                .clear_marks_of_type(MarkType::SearchResult);
+                .clear_marks_where(|ty| matches!(ty, MarkType::SearchResult { .. }));
         }
 
         ui.label(format!(
-            "search {:.02}% complete ({} results)",
+            "search {:.02}% complete{} ({} results)",
             state.search.searcher.progress() * 100.0,
-            state.marked_locations.count_of_type(MarkType::SearchResult)
+            if state.search.searcher.is_paused() {
+                ", paused"
+            } else {
+                ""
+            },
+            result_count
         ));
+        if let Some(error) = state.search.searcher.error() {
+            ui.label(RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+
+        ui.separator();
+        show_crypto_constants(ui, state, input);
     });
 }
+
+/// Shows the controls of the cryptographic constant detection.
+fn show_crypto_constants(ui: &mut Ui, state: &mut State, input: &Input) {
+    ui.label("cryptographic constants (AES, SHA-2, CRC, Base64)");
+
+    ui.horizontal(|ui| {
+        let running = state.crypto_constants.handle.is_some();
+        if ui
+            .add_enabled(!running, Button::new("find constants"))
+            .clicked()
+        {
+            state
+                .marked_locations
+                .clear_marks_where(|ty| matches!(ty, MarkType::CryptoConstant { .. }));
+            state.crypto_constants.handle = Some(ConstantScanHandle::start(input.clone()));
+            state.crypto_constants.message = None;
+        }
+        if running && ui.button("cancel").clicked() {
+            state.crypto_constants.handle = None;
+        }
+    });
+
+    if let Some(handle) = &state.crypto_constants.handle {
+        ui.add(ProgressBar::new(handle.progress()).show_percentage());
+    }
+    if let Some(message) = &state.crypto_constants.message {
+        ui.label(RichText::new(message).color(ui.visuals().error_fg_color));
+    }
+
+    let found = state
+        .marked_locations
+        .count_where(|ty| matches!(ty, MarkType::CryptoConstant { .. }));
+    if found != 0 {
+        ui.horizontal(|ui| {
+            ui.label(format!("{found} constants found"));
+            if ui.button("clear").clicked() {
+                state
+                    .marked_locations
+                    .clear_marks_where(|ty| matches!(ty, MarkType::CryptoConstant { .. }));
+            }
+        });
+    }
+}
@@ -1,11 +1,13 @@
 //! Renders a settings screen in the GUI.
 
-use egui::{ComboBox, RichText, Slider, Ui};
+use std::path::PathBuf;
+
+use egui::{ComboBox, DragValue, Id, RichText, Slider, Ui};
 use hexbait_common::Input;
 
 use crate::{
     gui::color::ColorMap,
-    state::{State, ViewKind},
+    state::{BYTES_PER_ROW_CHOICES, State, Theme, ViewKind},
 };
 
 /// Shows the settings screen in the GUI.
@@ -67,5 +69,102 @@ pub fn show(ui: &mut Ui, state: &mut State, _: &Input) {
             ui.label("Statistics gamma smoothing:");
             ui.add(Slider::new(state.settings.statistics_gamma_factor_mut(), 0.0..=1.0));
         });
+
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            ComboBox::new("theme", "")
+                .selected_text(state.settings.theme().display_str())
+                .show_ui(ui, |ui| {
+                    for theme in [Theme::System, Theme::Dark, Theme::Light] {
+                        ui.selectable_value(state.settings.theme_mut(), theme, theme.display_str());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Bytes per row:");
+            let mut bytes_per_row = state.settings.bytes_per_row();
+            ComboBox::new("bytes_per_row", "")
+                .selected_text(bytes_per_row.to_string())
+                .show_ui(ui, |ui| {
+                    for choice in BYTES_PER_ROW_CHOICES {
+                        ui.selectable_value(&mut bytes_per_row, choice, choice.to_string());
+                    }
+                });
+            if bytes_per_row != state.settings.bytes_per_row() {
+                state.settings.set_bytes_per_row(bytes_per_row);
+                state.scroll_state.hex_scroll_offset = 0;
+            }
+        });
+
+        if cfg!(not(target_arch = "wasm32")) {
+            ui.horizontal(|ui| {
+                ui.label("Autosave interval:");
+                ui.add(
+                    DragValue::new(state.settings.autosave_interval_secs_mut())
+                        .range(0..=3600)
+                        .suffix(" s"),
+                )
+                .on_hover_text("0 disables autosaving");
+            });
+        }
+
+        ui.separator();
+        parser_directories(ui, state);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let message_id = Id::new("settings_save_message");
+            if ui.button("Save settings").clicked() {
+                let message = match state.settings.save() {
+                    Ok(()) => String::from("settings saved"),
+                    Err(err) => format!("failed to save settings: {err}"),
+                };
+                ui.data_mut(|data| data.insert_temp(message_id, message));
+            }
+            if let Some(path) = state.settings.config_path() {
+                ui.label(RichText::new(path.display().to_string()).weak());
+            }
+            if let Some(message) = ui.data(|data| data.get_temp::<String>(message_id)) {
+                ui.label(message);
+            }
+        });
+    });
+}
+
+/// Shows the directories from which format descriptions are loaded.
+fn parser_directories(ui: &mut Ui, state: &mut State) {
+    ui.label("Parser directories:");
+
+    let mut removed = None;
+    for (i, dir) in state.settings.parser_directories().iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("🗑").on_hover_text("remove").clicked() {
+                removed = Some(i);
+            }
+            ui.label(dir.display().to_string());
+        });
+    }
+    if let Some(i) = removed {
+        state.settings.parser_directories_mut().remove(i);
+    }
+
+    ui.horizontal(|ui| {
+        let new_dir_id = Id::new("settings_new_parser_directory");
+        let mut new_dir =
+            ui.data_mut(|data| data.get_temp::<String>(new_dir_id).unwrap_or_default());
+        ui.text_edit_singleline(&mut new_dir);
+        if ui.button("Add").clicked() && !new_dir.is_empty() {
+            state
+                .settings
+                .parser_directories_mut()
+                .push(PathBuf::from(std::mem::take(&mut new_dir)));
+            for file in state.settings.parser_directory_files() {
+                if !state.parse_state.custom_parsers.contains(&file) {
+                    state.parse_state.custom_parsers.push(file);
+                }
+            }
+        }
+        ui.data_mut(|data| data.insert_temp(new_dir_id, new_dir));
     });
 }
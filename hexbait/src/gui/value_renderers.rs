@@ -0,0 +1,293 @@
+//! Implements rich previews of parsed values.
+//!
+//! A [`ValueRenderers`] registry holds renderers that each decide by the name and the shape of a
+//! value whether they apply to it. The first applicable renderer shows a preview of the value
+//! below it in the parse tree.
+
+use egui::{ColorImage, Grid, Id, RichText, TextureHandle, TextureOptions, Ui};
+use hexbait_lang::{Value, ValueKind};
+use image::ImageFormat;
+
+/// The size in bytes up to which embedded images are decoded.
+const MAX_IMAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// The maximum width and height of image thumbnails.
+const THUMBNAIL_SIZE: u32 = 128;
+
+/// The maximum number of cells of arrays that are shown as tables.
+const MAX_TABLE_CELLS: usize = 1024;
+
+/// The earliest timestamp that is shown as a date, in seconds since the UNIX epoch.
+const MIN_TIMESTAMP: i64 = 0;
+
+/// The latest timestamp that is shown as a date, in seconds since the UNIX epoch.
+///
+/// This is the start of the year 2100.
+const MAX_TIMESTAMP: i64 = 4_102_444_800;
+
+/// The seconds between the start of Windows FILETIMEs (1601-01-01) and the UNIX epoch.
+const FILETIME_UNIX_DIFF_SECS: i64 = 11_644_473_600;
+
+/// A renderer that shows a rich preview of specific parsed values.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueRenderer {
+    /// The name of the renderer.
+    pub name: &'static str,
+    /// Determines if the renderer applies to a value with the given field name.
+    pub applies_to: fn(name: Option<&str>, value: &Value) -> bool,
+    /// Shows the preview of the value.
+    ///
+    /// The ID is unique to the value and can be used to cache data between frames.
+    pub show: fn(ui: &mut Ui, id: Id, value: &Value),
+}
+
+/// The registry of all renderers for parsed values.
+#[derive(Debug, Clone)]
+pub struct ValueRenderers {
+    /// The registered renderers, in the order in which they are tried.
+    renderers: Vec<ValueRenderer>,
+}
+
+impl ValueRenderers {
+    /// Creates a registry containing the built-in renderers.
+    pub fn new() -> ValueRenderers {
+        ValueRenderers {
+            renderers: vec![
+                ValueRenderer {
+                    name: "image",
+                    applies_to: is_image,
+                    show: show_image,
+                },
+                ValueRenderer {
+                    name: "timestamp",
+                    applies_to: is_timestamp,
+                    show: show_timestamp,
+                },
+                ValueRenderer {
+                    name: "table",
+                    applies_to: is_matrix,
+                    show: show_matrix,
+                },
+            ],
+        }
+    }
+
+    /// Registers a renderer, which takes precedence over the already registered ones.
+    pub fn register(&mut self, renderer: ValueRenderer) {
+        self.renderers.insert(0, renderer);
+    }
+
+    /// Returns the renderer for a value with the given field name, if there is one.
+    pub fn find(&self, name: Option<&str>, value: &Value) -> Option<ValueRenderer> {
+        self.renderers
+            .iter()
+            .find(|renderer| (renderer.applies_to)(name, value))
+            .copied()
+    }
+
+    /// Returns all registered renderers.
+    pub fn iter(&self) -> impl Iterator<Item = &ValueRenderer> {
+        self.renderers.iter()
+    }
+}
+
+impl Default for ValueRenderers {
+    fn default() -> Self {
+        ValueRenderers::new()
+    }
+}
+
+/// Determines the format of the image stored in the value, if it can be decoded.
+fn image_format(value: &Value) -> Option<ImageFormat> {
+    let ValueKind::Bytes(bytes) = &value.kind else {
+        return None;
+    };
+    if bytes.len() > MAX_IMAGE_LEN {
+        return None;
+    }
+
+    let mut header = [0; 16];
+    let len = header.len().min(bytes.len());
+    bytes.fill_buf_at(0, &mut header[..len]).ok()?;
+
+    image::guess_format(&header[..len]).ok().filter(|format| {
+        matches!(
+            format,
+            ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::Bmp
+        )
+    })
+}
+
+/// Determines if the value contains an image.
+fn is_image(_: Option<&str>, value: &Value) -> bool {
+    image_format(value).is_some()
+}
+
+/// The decoded thumbnail of an image, cached between frames.
+type CachedThumbnail = Result<(TextureHandle, [u32; 2]), String>;
+
+/// Shows a thumbnail of the image contained in the value.
+fn show_image(ui: &mut Ui, id: Id, value: &Value) {
+    let cached = ui.data(|data| data.get_temp::<CachedThumbnail>(id));
+    let thumbnail = cached.unwrap_or_else(|| {
+        let thumbnail = decode_thumbnail(ui, value);
+        ui.data_mut(|data| data.insert_temp(id, thumbnail.clone()));
+        thumbnail
+    });
+
+    match thumbnail {
+        Ok((texture, [width, height])) => {
+            ui.image((texture.id(), texture.size_vec2()));
+            ui.label(RichText::new(format!("{width}×{height} pixels")).weak());
+        }
+        Err(err) => {
+            ui.label(
+                RichText::new(format!("cannot decode image: {err}"))
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+    }
+}
+
+/// Decodes the image contained in the value and creates a thumbnail of it.
+fn decode_thumbnail(ui: &Ui, value: &Value) -> CachedThumbnail {
+    let format = image_format(value).ok_or("unknown image format")?;
+    let bytes = value
+        .kind
+        .expect_bytes()
+        .value()
+        .map_err(|err| err.to_string())?;
+    let image =
+        image::load_from_memory_with_format(&bytes, format).map_err(|err| err.to_string())?;
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    let color_image = ColorImage::from_rgba_unmultiplied(
+        [thumbnail.width() as usize, thumbnail.height() as usize],
+        thumbnail.as_raw(),
+    );
+    let texture = ui
+        .ctx()
+        .load_texture("value preview", color_image, TextureOptions::LINEAR);
+
+    Ok((texture, [image.width(), image.height()]))
+}
+
+/// Interprets the value as a timestamp, returning the date and the name of the encoding.
+///
+/// Only dates between 1970 and 2100 are considered plausible.
+fn timestamp(value: &Value) -> Option<(chrono::DateTime<chrono::Utc>, &'static str)> {
+    let ValueKind::Integer(int) = &value.kind else {
+        return None;
+    };
+    let int = i64::try_from(int).ok()?;
+
+    let (secs, nsecs, encoding) = if (MIN_TIMESTAMP..MAX_TIMESTAMP).contains(&int) {
+        (int, 0, "UNIX timestamp")
+    } else if (MIN_TIMESTAMP * 1000..MAX_TIMESTAMP * 1000).contains(&int) {
+        (
+            int / 1000,
+            (int % 1000) as u32 * 1_000_000,
+            "UNIX timestamp in milliseconds",
+        )
+    } else {
+        let secs = int / 10_000_000 - FILETIME_UNIX_DIFF_SECS;
+        if !(MIN_TIMESTAMP..MAX_TIMESTAMP).contains(&secs) {
+            return None;
+        }
+        (secs, (int % 10_000_000) as u32 * 100, "Windows FILETIME")
+    };
+
+    Some((chrono::DateTime::from_timestamp(secs, nsecs)?, encoding))
+}
+
+/// Determines if the value is a timestamp.
+///
+/// Timestamps are recognized by the name of their field. Values that are already classified by
+/// the format description are shown according to their classification instead.
+fn is_timestamp(name: Option<&str>, value: &Value) -> bool {
+    if value.classification.is_some() {
+        return false;
+    }
+    let Some(name) = name.map(str::to_ascii_lowercase) else {
+        return false;
+    };
+
+    (name.contains("time") || name.contains("date") || name.ends_with("_at"))
+        && timestamp(value).is_some()
+}
+
+/// Shows the value as a calendar date.
+fn show_timestamp(ui: &mut Ui, _: Id, value: &Value) {
+    let Some((datetime, encoding)) = timestamp(value) else {
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(datetime.to_string()).strong());
+        ui.label(RichText::new(format!("({encoding})")).weak());
+    });
+}
+
+/// Returns the rows of the value if it is a matrix of numbers.
+///
+/// A matrix is an array of at least two arrays of the same length containing only numbers.
+fn matrix_rows(value: &Value) -> Option<Vec<&[Value]>> {
+    let ValueKind::Array { items, .. } = &value.kind else {
+        return None;
+    };
+    if items.len() < 2 {
+        return None;
+    }
+
+    let rows = items
+        .iter()
+        .map(|item| match &item.kind {
+            ValueKind::Array { items, .. } => Some(&items[..]),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let columns = rows[0].len();
+    let is_number =
+        |value: &Value| matches!(value.kind, ValueKind::Integer(_) | ValueKind::Float(_));
+    let is_matrix = columns >= 2
+        && rows.len() * columns <= MAX_TABLE_CELLS
+        && rows
+            .iter()
+            .all(|row| row.len() == columns && row.iter().all(is_number));
+
+    is_matrix.then_some(rows)
+}
+
+/// Determines if the value is a matrix of numbers.
+fn is_matrix(_: Option<&str>, value: &Value) -> bool {
+    matrix_rows(value).is_some()
+}
+
+/// Shows the value as a table.
+fn show_matrix(ui: &mut Ui, id: Id, value: &Value) {
+    let Some(rows) = matrix_rows(value) else {
+        return;
+    };
+
+    Grid::new(id).striped(true).show(ui, |ui| {
+        ui.label("");
+        for column in 0..rows[0].len() {
+            ui.label(RichText::new(column.to_string()).weak());
+        }
+        ui.end_row();
+
+        for (i, row) in rows.iter().enumerate() {
+            ui.label(RichText::new(i.to_string()).weak());
+            for cell in *row {
+                let text = match &cell.kind {
+                    ValueKind::Integer(int) => int.to_string(),
+                    ValueKind::Float(float) => float.to_string(),
+                    _ => unreachable!("matrices only contain numbers"),
+                };
+                ui.monospace(text);
+            }
+            ui.end_row();
+        }
+    });
+}
@@ -7,10 +7,17 @@ use std::time::Duration;
 /// The idling time in case no user input is present.
 pub(crate) const IDLE_TIME: Duration = Duration::from_millis(100);
 
-pub mod cache;
+pub use hexbait_core::{cache, search, statistics, window};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod autosave;
+pub mod bookmarks;
 pub mod gui;
+pub mod logging;
 pub mod marking;
-pub mod search;
+pub mod pipe;
+pub mod project;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+pub mod scripting;
 pub mod state;
-pub mod statistics;
-pub mod window;
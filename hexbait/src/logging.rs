@@ -0,0 +1,193 @@
+//! Implements collecting log messages, so they can be shown in the GUI.
+//!
+//! Log messages are emitted with [`tracing`] throughout the hexbait crates.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    fs::File,
+    io,
+    path::Path,
+    sync::{Mutex, MutexGuard, OnceLock},
+    time::Duration,
+};
+
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+    span,
+};
+use tracing_subscriber::{
+    Layer,
+    filter::{LevelFilter, Targets},
+    fmt::format::FmtSpan,
+    layer::{Context, SubscriberExt as _},
+    registry::LookupSpan,
+    util::SubscriberInitExt as _,
+};
+use web_time::Instant;
+
+/// The maximum number of log entries that are kept.
+const MAX_LOG_ENTRIES: usize = 10_000;
+
+/// The collected log entries.
+static LOG_ENTRIES: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+/// The time at which logging started.
+static LOG_START: OnceLock<Instant> = OnceLock::new();
+
+/// A single collected log message.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// The time since logging started.
+    pub time: Duration,
+    /// The level of the message.
+    pub level: Level,
+    /// The module that emitted the message.
+    pub target: String,
+    /// The message including its fields.
+    pub message: String,
+}
+
+/// Returns the collected log entries, from oldest to newest.
+pub fn entries() -> MutexGuard<'static, VecDeque<LogEntry>> {
+    LOG_ENTRIES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Stores the given log entry, dropping the oldest entry if there are too many.
+fn push_entry(entry: LogEntry) {
+    let mut entries = entries();
+    if entries.len() >= MAX_LOG_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+/// The time since logging started.
+fn elapsed() -> Duration {
+    LOG_START.get_or_init(Instant::now).elapsed()
+}
+
+/// A [`Layer`] that collects log messages so they can be shown in the GUI.
+///
+/// When a span closes, an entry with the time spent in it is collected as well.
+pub struct LogLayer;
+
+/// The data stored for each open span.
+struct SpanTiming {
+    /// The time at which the span was created.
+    start: Instant,
+    /// The fields of the span.
+    fields: String,
+}
+
+impl<S> Layer<S> for LogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanTiming {
+            start: Instant::now(),
+            fields: visitor.fields,
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let mut message = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                let _ = write!(message, "{}: ", span.name());
+            }
+        }
+        message.push_str(&visitor.message);
+        message.push_str(&visitor.fields);
+
+        push_entry(LogEntry {
+            time: elapsed(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message,
+        });
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+
+        push_entry(LogEntry {
+            time: elapsed(),
+            level: *span.metadata().level(),
+            target: span.metadata().target().to_string(),
+            message: format!(
+                "{} took {:?}{}",
+                span.name(),
+                timing.start.elapsed(),
+                timing.fields
+            ),
+        });
+    }
+}
+
+/// Formats the fields of events and spans.
+#[derive(Default)]
+struct FieldVisitor {
+    /// The message of the event.
+    message: String,
+    /// All other fields formatted as ` name=value`.
+    fields: String,
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Initializes logging with the given maximum level.
+///
+/// Log messages are always collected for the GUI and additionally written to `log_file` if given.
+pub fn init(level: LevelFilter, log_file: Option<&Path>) -> io::Result<()> {
+    let file_layer = log_file
+        .map(|path| {
+            File::create(path).map(|file| {
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_span_events(FmtSpan::CLOSE)
+            })
+        })
+        .transpose()?;
+
+    tracing_subscriber::registry()
+        .with(LogLayer)
+        .with(file_layer)
+        .with(Targets::new().with_target("hexbait", level))
+        .init();
+
+    Ok(())
+}
@@ -5,23 +5,36 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::path::PathBuf;
+use std::{borrow::Cow, path::PathBuf, time::Duration};
 
-use clap::Parser;
 use egui::{CentralPanel, Frame, MenuBar, Panel, TextStyle, Ui};
 use egui_dock::{DockArea, DockState, SurfaceIndex};
+#[cfg(not(target_arch = "wasm32"))]
+use hexbait::{
+    autosave::Autosaver,
+    remote::{RemoteCommand, RemoteServer},
+};
 use hexbait::{
     gui::modules::{Context, TabType, hex_dock_state},
-    state::State,
+    project::{FileIdentity, Project, ProjectError},
+    state::{ProjectState, Settings, State},
 };
 use hexbait_common::Input;
+use web_time::Instant;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod commands;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// The time per frame that is spent on background work on platforms without threads.
+const COOPERATIVE_WORK_BUDGET: Duration = Duration::from_millis(8);
 
 // TODO: change font to render more characters
 // TODO: implement to-disk caching for some statistic sizes to decrease re-load times
 // TODO: re-use non-flat statistics for flat statistics
 // TODO: join polygons of adjoining marked locations
 // TODO: improve hover text for marked locations
-// TODO: implement more convenient escaping of byte arrays for search
 // TODO: rearrange UI in a more useful way
 // TODO: fix dragging across end during initial scrollbar selection
 // TODO: add relative search (from here backwards/forwards)
@@ -29,32 +42,171 @@ use hexbait_common::Input;
 // TODO: add some user documentation
 // TODO: fix statistics bug where 00 01 has high probability on zero-only content
 // TODO: make handling of usize <-> u64 conversions more consistent
-// TODO: add more useful conversions between Len, AbsoluteOffset and RelativeOffset and use them
-// where it makes sense
 
 /// hexbait - Hierarchical EXploration Binary Analysis & Inspection Tool
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Config {
+    /// A subcommand to run without the GUI
+    #[command(subcommand)]
+    command: Option<commands::Command>,
     /// The file to analyze
     file: Option<PathBuf>,
     /// A parser definition file to supply additional parsers
     #[arg(short, long)]
     parser_definitions: Vec<PathBuf>,
+    /// A project file to open
+    ///
+    /// If no file to analyze is given, the file that the project was saved for is opened.
+    #[arg(long)]
+    project: Option<PathBuf>,
+    /// The configuration file to load the settings from
+    ///
+    /// Defaults to `hexbait/config.toml` in the configuration directory of the platform.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Override the scale of the GUI for this run
+    #[arg(long)]
+    scale: Option<f32>,
+    /// Override the color map for this run
+    #[arg(long)]
+    color_map: Option<hexbait::gui::color::ColorMap>,
+    /// Override the number of bytes per row in the hex view for this run
+    #[arg(long, value_parser = parse_bytes_per_row)]
+    bytes_per_row: Option<u64>,
+    /// Override the color theme for this run
+    #[arg(long)]
+    theme: Option<hexbait::state::Theme>,
+    /// Load all format descriptions in this directory as additional parsers for this run
+    #[arg(long)]
+    parser_dir: Vec<PathBuf>,
+    /// The most verbose level of log messages to collect (off, error, warn, info, debug, trace)
+    #[arg(long, default_value = "info")]
+    log_level: tracing_subscriber::filter::LevelFilter,
+    /// A file to write log messages to
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+    /// Listen for commands from external tools on the local socket with the given name
+    ///
+    /// See `hexbait send --help` for the supported commands.
+    #[arg(long, value_name = "SOCKET", num_args = 0..=1, default_missing_value = hexbait::remote::DEFAULT_SOCKET_NAME)]
+    remote: Option<String>,
+    /// Scroll to this offset and parse at it
+    #[arg(long, value_parser = hexbait::remote::parse_number)]
+    offset: Option<u64>,
+    /// Parse with this built-in parser or format description
+    #[arg(long, value_name = "PARSER")]
+    parse_as: Option<String>,
+    /// Select the bytes from `start` up to, but excluding, `end`
+    #[arg(long, value_name = "START..END", value_parser = hexbait::remote::parse_range)]
+    select: Option<hexbait::window::Window>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Config {
+    /// Loads the settings from the configuration file and applies the overrides given for this run.
+    fn settings(&self) -> Result<Settings, hexbait::state::SettingsError> {
+        let mut settings = match self
+            .config
+            .clone()
+            .or_else(hexbait::state::default_config_path)
+        {
+            Some(path) => Settings::load(path)?,
+            None => Settings::new(),
+        };
+
+        if let Some(scale) = self.scale {
+            settings.set_scale(scale);
+        }
+        if let Some(color_map) = self.color_map {
+            *settings.color_map_mut() = color_map;
+        }
+        if let Some(bytes_per_row) = self.bytes_per_row {
+            settings.set_bytes_per_row(bytes_per_row);
+        }
+        if let Some(theme) = self.theme {
+            *settings.theme_mut() = theme;
+        }
+        settings
+            .parser_directories_mut()
+            .extend(self.parser_dir.iter().cloned());
+
+        Ok(settings)
+    }
+
+    /// Returns the commands that set up the view as requested on the command line.
+    ///
+    /// They are applied in order, so that scrolling to the offset takes precedence over scrolling
+    /// to the selection.
+    fn deep_link_commands(&self) -> Vec<RemoteCommand> {
+        let parser = self.parse_as.clone().map(RemoteCommand::Parser);
+        let select = self.select.map(RemoteCommand::Select);
+        let goto = self
+            .offset
+            .map(|offset| RemoteCommand::Goto(hexbait_common::AbsoluteOffset::from(offset)));
+
+        parser.into_iter().chain(select).chain(goto).collect()
+    }
+}
+
+/// Parses a number of bytes per row that is supported by the hex view.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_bytes_per_row(arg: &str) -> Result<u64, String> {
+    use hexbait::state::BYTES_PER_ROW_CHOICES;
+
+    arg.parse()
+        .ok()
+        .filter(|bytes_per_row| BYTES_PER_ROW_CHOICES.contains(bytes_per_row))
+        .ok_or_else(|| format!("must be one of {BYTES_PER_ROW_CHOICES:?}"))
 }
 
 /// The main entry point for the application.
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
+    use clap::Parser as _;
+
     let config = Config::parse();
 
-    let input = if let Some(file_name) = &config.file {
+    if let Some(command) = config.command {
+        if let Err(err) = command.run() {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(err) = hexbait::logging::init(config.log_level, config.log_file.as_deref()) {
+        eprintln!("error: failed to open log file: {err}");
+        std::process::exit(1);
+    }
+
+    let settings = match config.settings() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("error: failed to load settings: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let project = match config.project.as_ref().map(Project::load).transpose() {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("error: failed to load project: {err}");
+            std::process::exit(1);
+        }
+    };
+    let deep_link_commands = config.deep_link_commands();
+    let file = config.file.or_else(|| project.as_ref()?.file.path.clone());
+
+    let input = if let Some(file_name) = &file {
         Input::from_path(file_name)
     } else {
         Input::from_stdin()
     }
     .expect("TODO: implement proper error handling in main");
 
-    let file_name = if let Some(file) = &config.file {
+    let file_name = if let Some(file) = &file {
         file.display().to_string()
     } else {
         String::from("stdin")
@@ -67,36 +219,361 @@ fn main() -> eframe::Result {
     eframe::run_native(
         &format!("hexbait: {file_name}"),
         options,
-        Box::new(|_| {
-            Ok(Box::new(HexbaitApp {
-                frame_time: std::time::Duration::ZERO,
-                context: Context {
-                    state: State::new(&input, config.parser_definitions),
-                    input,
-                },
-                dock_state: hex_dock_state(),
-            }))
+        Box::new(|cc| {
+            let mut app = HexbaitApp::new(input, file, config.parser_definitions, settings);
+            if let Some(project) = &project {
+                app.apply_project(project);
+            }
+            for command in &deep_link_commands {
+                command
+                    .apply(&mut app.context.state, &app.context.input)
+                    .map_err(|err| format!("cannot apply the command line arguments: {err}"))?;
+            }
+            if let Some(offset) = config.offset {
+                let parse_state = &mut app.context.state.parse_state;
+                parse_state.parse_offset = offset.to_string();
+                parse_state.sync_parse_offset_to_selection_start = false;
+            }
+            if let Some(name) = &config.remote {
+                app.remote = Some(RemoteServer::start(name, cc.egui_ctx.clone())?);
+            }
+
+            Ok(Box::new(app))
         }),
     )
 }
 
+/// The main entry point for the application in the browser.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::JsCast as _;
+
+    hexbait::logging::init(tracing_subscriber::filter::LevelFilter::INFO, None)
+        .expect("no log file is opened");
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("hexbait_canvas"))
+            .and_then(|canvas| canvas.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("the page must contain a canvas with the id `hexbait_canvas`");
+
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| {
+                    let mut app = HexbaitApp::new(
+                        Input::from_bytes(Vec::new()),
+                        None,
+                        Vec::new(),
+                        Settings::new(),
+                    );
+                    app.file_loader = Some(web::FileLoader::new(cc.egui_ctx.clone()));
+                    app.file_loader.as_ref().unwrap().restore_cached();
+
+                    Ok(Box::new(app))
+                }),
+            )
+            .await;
+
+        if let Err(err) = result {
+            web_sys::console::error_2(&"failed to start hexbait:".into(), &err);
+        }
+    });
+}
+
 /// The hexbait application state.
 struct HexbaitApp {
     /// The time it took to render the last frame.
-    frame_time: std::time::Duration,
+    frame_time: Duration,
     /// The context required to render the hexbait application.
     context: Context,
     /// The dock state of the view.
     dock_state: DockState<TabType>,
+    /// Loads files opened in the browser.
+    #[cfg(target_arch = "wasm32")]
+    file_loader: Option<web::FileLoader>,
+    /// The server receiving commands from external tools.
+    #[cfg(not(target_arch = "wasm32"))]
+    remote: Option<RemoteServer>,
+    /// Autosaves the session, if the analyzed file was opened from a path.
+    #[cfg(not(target_arch = "wasm32"))]
+    autosaver: Option<Autosaver>,
+}
+
+impl HexbaitApp {
+    /// Creates the application state for the given input.
+    fn new(
+        input: Input,
+        file: Option<PathBuf>,
+        mut parser_definitions: Vec<PathBuf>,
+        settings: Settings,
+    ) -> HexbaitApp {
+        parser_definitions.extend(settings.parser_directory_files());
+
+        let mut state = State::new(&input, parser_definitions);
+        state.settings = settings;
+        #[cfg(not(target_arch = "wasm32"))]
+        let autosaver = file
+            .as_ref()
+            .and_then(|file| Autosaver::new(&input, file.clone()));
+        state.project_state = ProjectState::new(file);
+
+        HexbaitApp {
+            frame_time: Duration::ZERO,
+            context: Context { state, input },
+            dock_state: hex_dock_state(),
+            #[cfg(target_arch = "wasm32")]
+            file_loader: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            remote: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            autosaver,
+        }
+    }
+
+    /// Saves the current analysis to the project file.
+    fn save_project(&mut self) {
+        let state = &self.context.state;
+        let result = Project::capture(
+            state,
+            &self.context.input,
+            state.project_state.file_path.clone(),
+            &self.dock_state,
+        )
+        .map_err(ProjectError::from)
+        .and_then(|project| project.save(&state.project_state.project_path));
+
+        self.context.state.project_state.message = Some(match result {
+            Ok(()) => Cow::Borrowed("project saved"),
+            Err(err) => Cow::Owned(format!("failed to save project: {err}")),
+        });
+    }
+
+    /// Loads the project file and applies it to the current analysis.
+    fn load_project(&mut self) {
+        match Project::load(&self.context.state.project_state.project_path) {
+            Ok(project) => self.apply_project(&project),
+            Err(err) => {
+                self.context.state.project_state.message =
+                    Some(Cow::Owned(format!("failed to load project: {err}")));
+            }
+        }
+    }
+
+    /// Applies the given project to the current analysis.
+    fn apply_project(&mut self, project: &Project) {
+        project.apply(&mut self.context.state);
+        self.dock_state = project.layout.clone();
+
+        let message = match FileIdentity::of_input(&self.context.input, None) {
+            Ok(identity) if identity.same_content(&project.file) => Cow::Borrowed("project loaded"),
+            Ok(_) => Cow::Borrowed("project loaded, but it was saved for a different file"),
+            Err(err) => Cow::Owned(format!(
+                "project loaded, but the file could not be verified: {err}"
+            )),
+        };
+        self.context.state.project_state.message = Some(message);
+    }
+
+    /// Replaces the analyzed input with a file that was opened in the browser.
+    #[cfg(target_arch = "wasm32")]
+    fn open_web_file(&mut self, ui: &Ui) {
+        let dropped = ui.ctx().input(|input| {
+            input.raw.dropped_files.iter().find_map(|file| {
+                Some(web::LoadedFile {
+                    name: file.name.clone(),
+                    content: file.bytes.as_deref()?.to_vec(),
+                })
+            })
+        });
+        let Some(file) = dropped.or_else(|| self.file_loader.as_ref()?.take()) else {
+            return;
+        };
+
+        web::cache_file(&file.name, &file.content);
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            document.set_title(&format!("hexbait: {}", file.name));
+        }
+
+        self.replace_input(Input::from_bytes(file.content));
+        self.context.state.project_state.project_path = format!("{}.hbproj", file.name);
+    }
+
+    /// Replaces the analyzed input, keeping the settings and the loaded parsers.
+    ///
+    /// The session of the previous input ends normally.
+    fn replace_input(&mut self, input: Input) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mut autosaver) = self.autosaver.take() {
+            autosaver.finish();
+        }
+
+        let settings = std::mem::take(&mut self.context.state.settings);
+        let custom_parsers = std::mem::take(&mut self.context.state.parse_state.custom_parsers);
+        self.context = Context {
+            state: State::new(&input, custom_parsers),
+            input,
+        };
+        self.context.state.settings = settings;
+    }
+
+    /// Opens the output of an external command as a new document, if requested.
+    fn open_piped_output(&mut self, ui: &Ui) {
+        let Some((command, output)) = self.context.state.pipe_state.open_as_document.take() else {
+            return;
+        };
+
+        self.replace_input(Input::from_bytes(output));
+        self.context.state.project_state = ProjectState::new(None);
+        ui.ctx()
+            .send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                "hexbait: output of `{command}`"
+            )));
+    }
+
+    /// Autosaves the session and offers to restore a session that ended abnormally.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn autosave(&mut self, ui: &Ui) {
+        use hexbait::project::ProjectParser;
+
+        let Some(autosaver) = &mut self.autosaver else {
+            return;
+        };
+
+        if ui.ctx().input(|input| input.viewport().close_requested()) {
+            autosaver.finish();
+            return;
+        }
+        if let Some(interval) = self.context.state.settings.autosave_interval() {
+            autosaver.tick(&self.context.state, &self.dock_state, interval);
+            ui.ctx().request_repaint_after(interval);
+        }
+
+        let Some(recovery) = autosaver.recovery() else {
+            return;
+        };
+        let mut restore = None;
+        egui::Modal::new(egui::Id::new("restore_session")).show(ui.ctx(), |ui| {
+            ui.heading("Restore session");
+            ui.label("The previous session analyzing this file did not end normally.");
+            if let Some(saved_at) = recovery
+                .saved_at
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .and_then(|time| chrono::DateTime::from_timestamp(time.as_secs() as i64, 0))
+            {
+                ui.label(format!("It was last saved at {saved_at}."));
+            }
+            ui.label(format!(
+                "{} marks, parsed as {}",
+                recovery.project.marks.len(),
+                match &recovery.project.parser {
+                    ProjectParser::None => String::from("nothing"),
+                    ProjectParser::Builtin(name) => name.clone(),
+                    ProjectParser::Custom(path) => path.display().to_string(),
+                }
+            ));
+            if autosaver.recovery_content_differs() == Some(true) {
+                ui.label(
+                    egui::RichText::new("The file changed since the session was saved.")
+                        .color(ui.visuals().warn_fg_color),
+                );
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    restore = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    restore = Some(false);
+                }
+            });
+        });
+
+        match restore {
+            Some(true) => {
+                let recovery = autosaver.take_recovery().expect("a recovery is present");
+                recovery.project.apply(&mut self.context.state);
+                self.dock_state = recovery.project.layout;
+                self.context.state.project_state.message = Some(Cow::Borrowed("session restored"));
+            }
+            Some(false) => {
+                autosaver.take_recovery();
+            }
+            None => (),
+        }
+    }
+
+    /// Applies the commands that were sent by remote clients.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn handle_remote_requests(&mut self, ui: &Ui) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+
+        let requests: Vec<_> = remote.requests().collect();
+        for request in requests {
+            let result = match &request.command {
+                RemoteCommand::Open(path) => match Input::from_path(path) {
+                    Ok(input) => {
+                        self.replace_input(input);
+                        self.autosaver = Autosaver::new(&self.context.input, path.clone());
+                        self.context.state.project_state = ProjectState::new(Some(path.clone()));
+                        ui.ctx()
+                            .send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                                "hexbait: {}",
+                                path.display()
+                            )));
+                        Ok(())
+                    }
+                    Err(err) => Err(Cow::Owned(format!("cannot open {}: {err}", path.display()))),
+                },
+                command => command.apply(&mut self.context.state, &self.context.input),
+            };
+            request.reply(result);
+        }
+    }
 }
 
 impl eframe::App for HexbaitApp {
     fn ui(&mut self, ui: &mut Ui, _frame: &mut eframe::Frame) {
-        let start = std::time::Instant::now();
+        let start = Instant::now();
+
+        #[cfg(target_arch = "wasm32")]
+        self.open_web_file(ui);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.handle_remote_requests(ui);
+        self.open_piped_output(ui);
 
         Panel::top("menubar").show(ui, |ui| {
             self.context.state.settings.apply_settings_to_ui(ui);
             MenuBar::new().ui(ui, |ui| {
+                #[cfg(target_arch = "wasm32")]
+                if let Some(file_loader) = &self.file_loader {
+                    ui.menu_button("File", |ui| {
+                        if ui.button("Open…").clicked() {
+                            file_loader.pick_file();
+                            ui.close();
+                        }
+                    });
+                }
+
+                ui.menu_button("Project", |ui| {
+                    ui.label("Project file:");
+                    ui.text_edit_singleline(&mut self.context.state.project_state.project_path);
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.save_project();
+                        }
+                        if ui.button("Open").clicked() {
+                            self.load_project();
+                        }
+                    });
+                    if let Some(message) = &self.context.state.project_state.message {
+                        ui.label(message.as_ref());
+                    }
+                });
+
                 ui.menu_button("Tabs", |ui| {
                     // allow certain tabs to be toggled
                     for tab in &[
@@ -104,6 +581,10 @@ impl eframe::App for HexbaitApp {
                         TabType::Search,
                         TabType::ClassificationInfo,
                         TabType::Marking,
+                        TabType::ScriptConsole,
+                        TabType::Log,
+                        TabType::Carving,
+                        TabType::Pipe,
                     ] {
                         let open = self.dock_state.find_tab(tab).is_some();
 
@@ -142,6 +623,14 @@ impl eframe::App for HexbaitApp {
             });
 
         self.context.state.end_of_frame();
+        if let Some(delay) = self.context.state.repaint_after() {
+            ui.ctx().request_repaint_after(delay);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.autosave(ui);
+        if hexbait_core::worker::run_cooperative_workers(COOPERATIVE_WORK_BUDGET) {
+            ui.ctx().request_repaint();
+        }
         self.frame_time = start.elapsed();
     }
 }
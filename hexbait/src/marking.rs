@@ -5,7 +5,7 @@ use std::{collections::BTreeMap, ops::ControlFlow};
 use egui::Color32;
 use hexbait_common::{AbsoluteOffset, Len};
 
-use crate::{marking::store::SingleTypeStore, window::Window};
+use crate::{marking::store::SingleTypeStore, search::TextEncoding, window::Window};
 
 mod store;
 
@@ -63,7 +63,10 @@ impl PartialEq<MarkRef<'_>> for Mark {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MarkType {
     /// The result of a search.
-    SearchResult,
+    SearchResult {
+        /// The encoding under which the search result was found.
+        encoding: TextEncoding,
+    },
     /// A location marked by a user.
     UserMark {
         /// The name of the marked location.
@@ -75,28 +78,52 @@ pub enum MarkType {
     HoveredParsed,
     /// Provenance of a hovered parsing error.
     HoveredParseErr,
+    /// Provenance of the selected result of a search in the parsed value.
+    ParsedValueSearchResult,
+    /// A well-known cryptographic constant.
+    CryptoConstant {
+        /// The name of the constant.
+        name: &'static str,
+    },
+    /// A parsed value that the format description gives a color.
+    ColoredParsed {
+        /// The color of the value.
+        rgb: [u8; 3],
+        /// The name of the field containing the value.
+        name: String,
+    },
+    /// Bytes between parsed values that no value was parsed from.
+    Unparsed,
 }
 
 impl MarkType {
     /// The inner color of this marked location.
     pub fn inner_color(&self) -> Color32 {
         match self {
-            MarkType::SearchResult => Color32::BLUE,
+            MarkType::SearchResult { .. } => Color32::BLUE,
             MarkType::UserMark { .. } => Color32::WHITE,
             MarkType::Selection => Color32::WHITE,
             MarkType::HoveredParsed => Color32::DARK_RED,
             MarkType::HoveredParseErr => Color32::WHITE,
+            MarkType::ParsedValueSearchResult => Color32::BLUE,
+            MarkType::CryptoConstant { .. } => Color32::DARK_GREEN,
+            MarkType::ColoredParsed { rgb: [r, g, b], .. } => Color32::from_rgb(*r, *g, *b),
+            MarkType::Unparsed => Color32::DARK_GRAY,
         }
     }
 
     /// The border color of this marked location.
     pub fn border_color(&self) -> Color32 {
         match self {
-            MarkType::SearchResult => Color32::from_rgb(252, 15, 192),
+            MarkType::SearchResult { .. } => Color32::from_rgb(252, 15, 192),
             MarkType::UserMark { .. } => Color32::DARK_RED,
             MarkType::Selection => Color32::WHITE,
             MarkType::HoveredParsed => Color32::GOLD,
             MarkType::HoveredParseErr => Color32::LIGHT_RED,
+            MarkType::ParsedValueSearchResult => Color32::GOLD,
+            MarkType::CryptoConstant { .. } => Color32::GREEN,
+            MarkType::ColoredParsed { rgb: [r, g, b], .. } => Color32::from_rgb(*r, *g, *b),
+            MarkType::Unparsed => Color32::GRAY,
         }
     }
 }
@@ -143,6 +170,11 @@ impl MarkStore {
         self.per_type.remove(&ty);
     }
 
+    /// Clears all marks whose type matches the filter.
+    pub fn clear_marks_where(&mut self, mut filter: impl FnMut(&MarkType) -> bool) {
+        self.per_type.retain(|ty, _| !filter(ty));
+    }
+
     /// Removes all marks that match the filter and (if it is `Some(_)`) `ty`.
     pub fn remove_where(&mut self, ty: Option<MarkType>, mut filter: impl FnMut(MarkRef) -> bool) {
         match ty {
@@ -231,11 +263,28 @@ impl MarkStore {
         out
     }
 
+    /// Iterates over all marks made by the user.
+    pub fn user_marks(&self) -> impl Iterator<Item = MarkRef<'_>> {
+        self.per_type
+            .iter()
+            .filter(|(ty, _)| matches!(ty, MarkType::UserMark { .. }))
+            .flat_map(|(ty, store)| store.iter().map(move |window| MarkRef { window, ty }))
+    }
+
     /// Returns the number of marks with the given type.
     pub fn count_of_type(&self, ty: MarkType) -> usize {
         self.per_type.get(&ty).map(|store| store.len()).unwrap_or(0)
     }
 
+    /// Returns the number of marks whose type matches the filter.
+    pub fn count_where(&self, mut filter: impl FnMut(&MarkType) -> bool) -> usize {
+        self.per_type
+            .iter()
+            .filter(|(ty, _)| filter(ty))
+            .map(|(_, store)| store.len())
+            .sum()
+    }
+
     /// Returns the hovered mark, if any.
     pub fn hovered(&self) -> Option<&Mark> {
         self.hovered_location.as_ref()
@@ -92,7 +92,7 @@ impl SingleTypeStore {
                 return ControlFlow::Continue(());
             }
 
-            if current.end() > window.start() {
+            if current.overlaps(window) {
                 out(current)?;
             }
 
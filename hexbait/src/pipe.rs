@@ -0,0 +1,142 @@
+//! Implements piping bytes through external commands.
+//!
+//! Commands are run by the shell of the platform, so they may contain arguments and pipes.
+//! The bytes are written to the standard input of the command and its output is collected.
+
+use std::{
+    io::{self, Write as _},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// An external command configured by the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalTool {
+    /// The name under which the tool is shown.
+    pub name: String,
+    /// The shell command that is run.
+    pub command: String,
+}
+
+impl ExternalTool {
+    /// Returns the tools that are configured by default.
+    pub fn defaults() -> Vec<ExternalTool> {
+        [
+            ("strings", "strings"),
+            ("ASN.1 structure", "openssl asn1parse -inform DER -i"),
+            ("zstd decompress", "zstd -d -c"),
+        ]
+        .into_iter()
+        .map(|(name, command)| ExternalTool {
+            name: name.to_string(),
+            command: command.to_string(),
+        })
+        .collect()
+    }
+}
+
+/// The output of a finished command.
+#[derive(Debug)]
+pub struct PipeOutput {
+    /// The bytes written to the standard output.
+    pub stdout: Vec<u8>,
+    /// The text written to the standard error output.
+    pub stderr: String,
+    /// The exit status of the command.
+    pub status: ExitStatus,
+}
+
+/// A command that runs in the background.
+pub struct PipeJob {
+    /// The command that runs.
+    command: String,
+    /// The channel over which the output arrives once the command finished.
+    result: mpsc::Receiver<io::Result<PipeOutput>>,
+}
+
+impl PipeJob {
+    /// Starts running the command with `input` as its standard input.
+    ///
+    /// `ctx` is used to wake up the GUI when the command finished.
+    pub fn start(command: &str, input: Vec<u8>, ctx: egui::Context) -> io::Result<PipeJob> {
+        if cfg!(target_arch = "wasm32") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "running external commands is not supported in the browser",
+            ));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let owned_command = command.to_string();
+        std::thread::spawn(move || {
+            let result = run(&owned_command, input);
+            // the job may have been discarded in the meantime, which is fine
+            let _ = sender.send(result);
+            ctx.request_repaint();
+        });
+        tracing::debug!(command, "started external command");
+
+        Ok(PipeJob {
+            command: command.to_string(),
+            result: receiver,
+        })
+    }
+
+    /// The command that runs.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the output once the command finished.
+    pub fn try_result(&self) -> Option<io::Result<PipeOutput>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(io::Error::other("the command stopped unexpectedly")))
+            }
+        }
+    }
+}
+
+/// Runs the command with `input` as its standard input and collects its output.
+fn run(command: &str, input: Vec<u8>) -> io::Result<PipeOutput> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // the input is written from another thread, so that a command that writes a lot of output
+    // before reading all of its input does not block
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || match stdin.write_all(&input) {
+        // commands do not need to read all of their input
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    });
+
+    let output = child.wait_with_output()?;
+    writer.join().expect("writing the input does not panic")?;
+
+    Ok(PipeOutput {
+        stdout: output.stdout,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        status: output.status,
+    })
+}
+
+/// Creates a command that runs `command` in the shell of the platform.
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
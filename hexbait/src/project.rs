@@ -0,0 +1,266 @@
+//! Implements project files that store an analysis, so it can be continued later or shared.
+//!
+//! Project files are stored in the [RON](https://github.com/ron-rs/ron) format.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use egui_dock::DockState;
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    gui::modules::TabType,
+    marking::{MarkStore, MarkType},
+    state::{ParseType, State},
+    window::Window,
+};
+
+/// The size of the chunks in which the input is read when hashing it.
+const HASH_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The current version of the project file format.
+const PROJECT_VERSION: u32 = 1;
+
+/// A saved analysis of a file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Project {
+    /// The version of the project file format.
+    pub version: u32,
+    /// The identity of the analyzed file.
+    pub file: FileIdentity,
+    /// The locations marked by the user.
+    pub marks: Vec<ProjectMark>,
+    /// The parser that is used for the file.
+    pub parser: ProjectParser,
+    /// The offset at which the file is parsed.
+    pub parse_offset: String,
+    /// The layout of the tabs.
+    pub layout: DockState<TabType>,
+}
+
+/// Identifies the analyzed file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIdentity {
+    /// The path of the file, if it was opened from a path.
+    pub path: Option<PathBuf>,
+    /// The length of the file in bytes.
+    pub len: u64,
+    /// The SHA-256 hash of the file content as a lowercase hex string.
+    pub sha256: String,
+}
+
+impl FileIdentity {
+    /// Computes the identity of the given input.
+    ///
+    /// This reads the whole input to hash it.
+    pub fn of_input(input: &Input, path: Option<PathBuf>) -> io::Result<FileIdentity> {
+        let mut hasher = Sha256::new();
+        let mut buf = Vec::new();
+        let mut offset = AbsoluteOffset::ZERO;
+        while offset < AbsoluteOffset::ZERO + input.len() {
+            let chunk = input.read_at(offset, Len::from(HASH_CHUNK_SIZE), Some(&mut buf))?;
+            if chunk.is_empty() {
+                break;
+            }
+            hasher.update(&*chunk);
+            offset += Len::from(chunk.len() as u64);
+        }
+
+        Ok(FileIdentity {
+            path,
+            len: input.len().as_u64(),
+            sha256: hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        })
+    }
+
+    /// Determines if both identities describe the same content, regardless of the path.
+    pub fn same_content(&self, other: &FileIdentity) -> bool {
+        self.len == other.len && self.sha256 == other.sha256
+    }
+}
+
+/// A location marked by the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectMark {
+    /// The marked bytes.
+    pub window: Window,
+    /// The name of the mark.
+    pub name: String,
+}
+
+impl ProjectMark {
+    /// Returns all locations marked by the user in the store.
+    pub fn all_in(marks: &MarkStore) -> Vec<ProjectMark> {
+        marks
+            .user_marks()
+            .filter_map(|mark| match mark.ty {
+                MarkType::UserMark { name } => Some(ProjectMark {
+                    window: mark.window,
+                    name: name.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Adds this mark to the store.
+    pub fn add_to(&self, marks: &mut MarkStore) {
+        marks.add(
+            self.window,
+            MarkType::UserMark {
+                name: self.name.clone(),
+            },
+        );
+    }
+}
+
+/// The parser that is used for a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectParser {
+    /// No parser is used.
+    None,
+    /// A built-in parser with the given name is used.
+    Builtin(String),
+    /// The format description at the given path is used.
+    Custom(PathBuf),
+}
+
+/// An error that occurred while saving or loading a project.
+#[derive(Debug)]
+pub enum ProjectError {
+    /// The project file or the analyzed file could not be accessed.
+    Io(io::Error),
+    /// The project file could not be written.
+    Serialize(ron::Error),
+    /// The project file is not valid.
+    Format(ron::error::SpannedError),
+    /// The project file was written by a newer version of hexbait.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(err) => write!(f, "{err}"),
+            ProjectError::Serialize(err) => write!(f, "{err}"),
+            ProjectError::Format(err) => write!(f, "invalid project file: {err}"),
+            ProjectError::UnsupportedVersion(version) => {
+                write!(f, "unsupported project file version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<io::Error> for ProjectError {
+    fn from(err: io::Error) -> Self {
+        ProjectError::Io(err)
+    }
+}
+
+impl From<ron::Error> for ProjectError {
+    fn from(err: ron::Error) -> Self {
+        ProjectError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ProjectError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ProjectError::Format(err)
+    }
+}
+
+impl Project {
+    /// Captures the current analysis as a project.
+    pub fn capture(
+        state: &State,
+        input: &Input,
+        path: Option<PathBuf>,
+        layout: &DockState<TabType>,
+    ) -> io::Result<Project> {
+        Ok(Project::capture_for_file(
+            state,
+            FileIdentity::of_input(input, path)?,
+            layout,
+        ))
+    }
+
+    /// Captures the current analysis as a project for a file whose identity is already known.
+    pub fn capture_for_file(
+        state: &State,
+        file: FileIdentity,
+        layout: &DockState<TabType>,
+    ) -> Project {
+        let marks = ProjectMark::all_in(&state.marked_locations);
+
+        let parser = match &state.parse_state.parse_type {
+            ParseType::None => ProjectParser::None,
+            ParseType::Builtin(name) => ProjectParser::Builtin(name.to_string()),
+            ParseType::Custom(path) => ProjectParser::Custom(path.clone()),
+        };
+
+        Project {
+            version: PROJECT_VERSION,
+            file,
+            marks,
+            parser,
+            parse_offset: state.parse_state.parse_offset.clone(),
+            layout: layout.clone(),
+        }
+    }
+
+    /// Applies the project to the given state.
+    ///
+    /// Marks from the project are added to the existing marks.
+    pub fn apply(&self, state: &mut State) {
+        for mark in &self.marks {
+            mark.add_to(&mut state.marked_locations);
+        }
+
+        state.parse_state.parse_type = match &self.parser {
+            ProjectParser::None => ParseType::None,
+            ProjectParser::Builtin(name) => state
+                .parse_state
+                .built_in_format_descriptions
+                .get_key_value(name.as_str())
+                .map(|(name, _)| ParseType::Builtin(name))
+                .unwrap_or(ParseType::None),
+            ProjectParser::Custom(path) => {
+                if !state.parse_state.custom_parsers.contains(path) {
+                    state.parse_state.custom_parsers.push(path.clone());
+                }
+                ParseType::Custom(path.clone())
+            }
+        };
+        state.parse_state.parse_offset = self.parse_offset.clone();
+        state.parse_state.sync_parse_offset_to_selection_start = false;
+    }
+
+    /// Saves the project to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProjectError> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    /// Loads a project from the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Project, ProjectError> {
+        let content = std::fs::read_to_string(path)?;
+        let project: Project = ron::from_str(&content)?;
+        if project.version > PROJECT_VERSION {
+            return Err(ProjectError::UnsupportedVersion(project.version));
+        }
+
+        Ok(project)
+    }
+}
@@ -0,0 +1,358 @@
+//! Implements a local IPC server so that external tools can control a running hexbait instance.
+//!
+//! The server listens on a local socket (a unix domain socket or a named pipe on Windows).
+//! On unix, sockets are created in a directory that only the current user can access, so that
+//! other users cannot control hexbait.
+//! Clients send one command per line and receive one line in response, which is either `ok` or
+//! `error: <message>`.
+//!
+//! The following commands are supported:
+//!
+//! - `open <path>`: opens the file at the given path
+//! - `goto <offset>`: scrolls the view to the given offset
+//! - `select <start> <end>`: selects the bytes from `start` up to, but excluding, `end`
+//! - `parser <name>`: uses the built-in parser with the given name, the format description at
+//!   the given path or no parser if the name is `none`
+//!
+//! Offsets are either decimal or hexadecimal with a `0x` prefix.
+
+use std::{
+    borrow::Cow,
+    io::{self, BufRead as _, BufReader, Write as _},
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+};
+
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use hexbait_core::parse::load_parser;
+use interprocess::local_socket::{
+    GenericFilePath, GenericNamespaced, ListenerOptions, Name, Stream, prelude::*,
+};
+
+use crate::{
+    state::{ParseType, State},
+    window::Window,
+};
+
+/// The name of the socket that is used if no other name is given.
+pub const DEFAULT_SOCKET_NAME: &str = "hexbait.sock";
+
+/// A command sent by a remote client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Opens the file at the given path.
+    Open(PathBuf),
+    /// Scrolls the view to the given offset.
+    Goto(AbsoluteOffset),
+    /// Selects the given window.
+    Select(Window),
+    /// Sets the parser to the given built-in parser, format description path or `none`.
+    Parser(String),
+}
+
+impl RemoteCommand {
+    /// Parses a command from a line sent by a client.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexbait::remote::RemoteCommand;
+    /// # use hexbait_common::AbsoluteOffset;
+    /// assert_eq!(
+    ///     RemoteCommand::parse("goto 0x10"),
+    ///     Ok(RemoteCommand::Goto(AbsoluteOffset::from(16))),
+    /// );
+    /// assert!(RemoteCommand::parse("select 8 4").is_err());
+    /// ```
+    pub fn parse(line: &str) -> Result<RemoteCommand, Cow<'static, str>> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+
+        match command {
+            "open" if !args.is_empty() => Ok(RemoteCommand::Open(PathBuf::from(args))),
+            "goto" => Ok(RemoteCommand::Goto(AbsoluteOffset::from(parse_number(
+                args,
+            )?))),
+            "select" => {
+                let Some((start, end)) = args.split_once(' ') else {
+                    return Err(Cow::Borrowed("usage: select <start> <end>"));
+                };
+                Ok(RemoteCommand::Select(range_window(
+                    parse_number(start)?,
+                    parse_number(end.trim())?,
+                )?))
+            }
+            "parser" if !args.is_empty() => Ok(RemoteCommand::Parser(args.to_string())),
+            "open" => Err(Cow::Borrowed("usage: open <path>")),
+            "parser" => Err(Cow::Borrowed("usage: parser <name>")),
+            _ => Err(Cow::Owned(format!("unknown command: {command}"))),
+        }
+    }
+
+    /// Applies the command to the given state.
+    ///
+    /// Opening a file replaces the whole state and must be handled by the caller instead.
+    pub fn apply(&self, state: &mut State, input: &Input) -> Result<(), Cow<'static, str>> {
+        let end = AbsoluteOffset::ZERO + input.len();
+        match self {
+            RemoteCommand::Open(_) => {
+                return Err(Cow::Borrowed("opening files is not supported here"));
+            }
+            RemoteCommand::Goto(offset) => {
+                if *offset >= end {
+                    return Err(Cow::Borrowed("the offset is beyond the end of the file"));
+                }
+                state.scroll_state.rearrange_bars_for_point(0, *offset);
+            }
+            RemoteCommand::Select(window) => {
+                if window.end() > end {
+                    return Err(Cow::Borrowed("the selection is beyond the end of the file"));
+                }
+                state.selection_state.select(*window);
+                state
+                    .scroll_state
+                    .rearrange_bars_for_point(0, window.start());
+            }
+            RemoteCommand::Parser(name) => {
+                state.parse_state.parse_type = if name == "none" {
+                    ParseType::None
+                } else if let Some((name, _)) = state
+                    .parse_state
+                    .built_in_format_descriptions
+                    .get_key_value(name.as_str())
+                {
+                    ParseType::Builtin(name)
+                } else {
+                    let path = PathBuf::from(name);
+                    load_parser(&path, &state.parse_state.import_paths).map_err(|err| {
+                        Cow::Owned(format!("cannot load {}: {err}", path.display()))
+                    })?;
+                    if !state.parse_state.custom_parsers.contains(&path) {
+                        state.parse_state.custom_parsers.push(path.clone());
+                    }
+                    ParseType::Custom(path)
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal number.
+pub fn parse_number(s: &str) -> Result<u64, Cow<'static, str>> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    result.map_err(|_| Cow::Owned(format!("invalid number: {s:?}")))
+}
+
+/// Parses a range of bytes written as `<start>..<end>`, where `end` is excluded.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexbait::remote::parse_range;
+/// let window = parse_range("0x400..0x800").unwrap();
+/// assert_eq!(window.start().as_u64(), 0x400);
+/// assert_eq!(window.size().as_u64(), 0x400);
+/// assert!(parse_range("0x800..0x400").is_err());
+/// ```
+pub fn parse_range(s: &str) -> Result<Window, Cow<'static, str>> {
+    let Some((start, end)) = s.split_once("..") else {
+        return Err(Cow::Borrowed("expected a range in the form <start>..<end>"));
+    };
+
+    range_window(parse_number(start.trim())?, parse_number(end.trim())?)
+}
+
+/// Returns the window from `start` up to, but excluding, `end`.
+fn range_window(start: u64, end: u64) -> Result<Window, Cow<'static, str>> {
+    if end <= start {
+        return Err(Cow::Borrowed("the end must be after the start"));
+    }
+
+    Ok(Window::from_start_len(
+        AbsoluteOffset::from(start),
+        Len::from(end - start),
+    ))
+}
+
+/// Converts the given socket name to a name usable on the current platform.
+///
+/// Names containing a path separator are used as file paths. On unix, all other names are files
+/// in the directory returned by [`private_socket_dir`], because namespaced sockets can be
+/// connected to by any user. On other platforms they are namespaced names.
+pub fn socket_name(name: &str) -> io::Result<Name<'static>> {
+    let name = if name.contains(['/', '\\']) {
+        PathBuf::from(name).to_fs_name::<GenericFilePath>()?
+    } else if cfg!(unix) || !GenericNamespaced::is_supported() {
+        private_socket_dir()?
+            .join(name)
+            .to_fs_name::<GenericFilePath>()?
+    } else {
+        name.to_ns_name::<GenericNamespaced>()?
+    };
+
+    Ok(name.into_owned())
+}
+
+/// Returns a directory for sockets that only the current user can access.
+///
+/// This is `$XDG_RUNTIME_DIR` if it is set and otherwise a `hexbait-<user>` directory in the
+/// temporary directory, which is created with mode `0700` if it does not exist yet. An existing
+/// directory that is a symbolic link or that other users can access is rejected.
+#[cfg(unix)]
+fn private_socket_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt as _, PermissionsExt as _};
+
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from)
+        && dir.is_absolute()
+    {
+        return Ok(dir);
+    }
+
+    let user = std::env::var("USER").unwrap_or_else(|_| String::from("user"));
+    let dir = std::env::temp_dir().join(format!("hexbait-{user}"));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => return Ok(dir),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+        Err(err) => return Err(err),
+    }
+
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} must be a directory that only the current user can access",
+                dir.display()
+            ),
+        ));
+    }
+
+    Ok(dir)
+}
+
+/// Returns a directory for sockets, which is the temporary directory on non-unix platforms.
+#[cfg(not(unix))]
+fn private_socket_dir() -> io::Result<PathBuf> {
+    Ok(std::env::temp_dir())
+}
+
+/// A command received from a client, waiting to be applied.
+pub struct RemoteRequest {
+    /// The command to apply.
+    pub command: RemoteCommand,
+    /// The channel over which the result is sent back to the client.
+    reply: mpsc::Sender<Result<(), Cow<'static, str>>>,
+}
+
+impl RemoteRequest {
+    /// Sends the result of applying the command back to the client.
+    pub fn reply(self, result: Result<(), Cow<'static, str>>) {
+        // the client may have disconnected in the meantime, which is fine
+        let _ = self.reply.send(result);
+    }
+}
+
+/// A running IPC server.
+pub struct RemoteServer {
+    /// The channel over which the commands of all clients arrive.
+    requests: mpsc::Receiver<RemoteRequest>,
+}
+
+impl RemoteServer {
+    /// Starts listening on the socket with the given name.
+    ///
+    /// `ctx` is used to wake up the GUI when a command arrives.
+    pub fn start(name: &str, ctx: egui::Context) -> io::Result<RemoteServer> {
+        let listener = ListenerOptions::new()
+            .name(socket_name(name)?)
+            .try_overwrite(true)
+            .create_sync()?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for conn in listener.incoming() {
+                match conn {
+                    Ok(conn) => {
+                        let sender = sender.clone();
+                        let ctx = ctx.clone();
+                        thread::spawn(move || {
+                            if let Err(err) = handle_client(conn, &sender, &ctx) {
+                                tracing::debug!(%err, "remote client disconnected");
+                            }
+                        });
+                    }
+                    Err(err) => tracing::warn!(%err, "failed to accept remote client"),
+                }
+            }
+        });
+        tracing::info!(name, "listening for remote commands");
+
+        Ok(RemoteServer { requests: receiver })
+    }
+
+    /// Returns all commands that arrived since the last call.
+    pub fn requests(&self) -> impl Iterator<Item = RemoteRequest> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+/// Handles the commands of a single client until it disconnects.
+fn handle_client(
+    conn: Stream,
+    requests: &mpsc::Sender<RemoteRequest>,
+    ctx: &egui::Context,
+) -> io::Result<()> {
+    let mut conn = BufReader::new(conn);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if conn.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        tracing::debug!(command = line.trim(), "received remote command");
+
+        let result = match RemoteCommand::parse(&line) {
+            Ok(command) => {
+                let (reply, result) = mpsc::channel();
+                if requests.send(RemoteRequest { command, reply }).is_err() {
+                    // the GUI has shut down
+                    return Ok(());
+                }
+                ctx.request_repaint();
+
+                result
+                    .recv()
+                    .unwrap_or(Err(Cow::Borrowed("the command was dropped")))
+            }
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(()) => writeln!(conn.get_mut(), "ok")?,
+            Err(err) => writeln!(conn.get_mut(), "error: {err}")?,
+        }
+    }
+}
+
+/// Sends a single command to the server with the given socket name and returns its response.
+pub fn send_command(name: &str, command: &str) -> io::Result<String> {
+    let mut conn = BufReader::new(Stream::connect(socket_name(name)?)?);
+    writeln!(conn.get_mut(), "{}", command.trim())?;
+
+    let mut response = String::new();
+    conn.read_line(&mut response)?;
+
+    Ok(response.trim_end().to_string())
+}
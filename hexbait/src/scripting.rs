@@ -0,0 +1,366 @@
+//! Implements a sandboxed scripting console for quick one-off analyses.
+//!
+//! Scripts are written in [Rhai](https://rhai.rs) and can read the input, search through it,
+//! access the parsed value and add marks.
+
+use std::{cell::RefCell, rc::Rc};
+
+use aho_corasick::AhoCorasick;
+use hexbait_common::{AbsoluteOffset, Input, Len};
+use hexbait_lang::{Value, ValueKind};
+use rhai::{Array, Blob, Dynamic, Engine, EvalAltResult, INT, Map, Scope};
+
+use crate::{marking::MarkType, state::State, window::Window};
+
+/// The maximum number of bytes that a script can read at once.
+const MAX_READ_LEN: u64 = 16 * 1024 * 1024;
+
+/// The maximum length of bytes values in the parsed value that are made available to scripts.
+const MAX_PARSED_BYTES_LEN: usize = 64 * 1024;
+
+/// The maximum number of operations a single script run may perform.
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// The size of the chunks in which the input is read when searching.
+const FIND_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The functions available to scripts in addition to the Rhai standard library.
+pub const SCRIPT_FUNCTIONS: &[(&str, &str)] = &[
+    ("len()", "the length of the input in bytes"),
+    (
+        "read(offset, len)",
+        "reads up to `len` bytes at `offset` as a blob",
+    ),
+    (
+        "find(needle, from)",
+        "the offset of the first occurrence of a string or blob at or after `from`, or -1",
+    ),
+    ("search(text)", "starts a search in the search panel"),
+    (
+        "parsed()",
+        "the current parsed value as maps and arrays, or ()",
+    ),
+    (
+        "selection()",
+        "the current selection as `#{ start, end }`, or ()",
+    ),
+    ("mark(offset, len, name)", "marks the given bytes"),
+    ("goto(offset)", "scrolls the view to the given offset"),
+];
+
+/// The kind of a line in the script console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleLineKind {
+    /// A script that was run.
+    Script,
+    /// Output of a script.
+    Output,
+    /// An error that occurred while running a script.
+    Error,
+}
+
+/// A line in the script console.
+#[derive(Debug, Clone)]
+pub struct ConsoleLine {
+    /// The kind of the line.
+    pub kind: ConsoleLineKind,
+    /// The text of the line.
+    pub text: String,
+}
+
+/// An action requested by a script that is applied to the state after it finished.
+enum ScriptAction {
+    /// Adds a user mark.
+    Mark {
+        /// The window to mark.
+        window: Window,
+        /// The name of the mark.
+        name: String,
+    },
+    /// Starts a search for the given text.
+    Search(String),
+    /// Scrolls the view to the given offset.
+    Goto(AbsoluteOffset),
+}
+
+/// The data that is shared between the functions available to a script.
+struct ScriptContext {
+    /// The output of the script.
+    output: Vec<ConsoleLine>,
+    /// The actions that the script requested.
+    actions: Vec<ScriptAction>,
+}
+
+/// Runs the given script and returns its output.
+///
+/// `scope` keeps variables between different script runs.
+/// Marks, searches and other changes requested by the script are applied to `state` afterwards.
+pub fn run_script(
+    script: &str,
+    scope: &mut Scope<'static>,
+    state: &mut State,
+    input: &Input,
+) -> Vec<ConsoleLine> {
+    let ctx = Rc::new(RefCell::new(ScriptContext {
+        output: vec![ConsoleLine {
+            kind: ConsoleLineKind::Script,
+            text: script.to_string(),
+        }],
+        actions: Vec::new(),
+    }));
+
+    let parsed = state
+        .parse_state
+        .parse(input)
+        .map(|result| value_to_dynamic(&result.value))
+        .unwrap_or(Dynamic::UNIT);
+    let selection = state
+        .selection_state
+        .selected_window()
+        .map(|window| {
+            let mut map = Map::new();
+            map.insert("start".into(), offset_to_dynamic(window.start()));
+            map.insert("end".into(), offset_to_dynamic(window.end()));
+            Dynamic::from_map(map)
+        })
+        .unwrap_or(Dynamic::UNIT);
+
+    let engine = build_engine(&ctx, input, parsed, selection);
+    let result = engine.eval_with_scope::<Dynamic>(scope, script);
+    drop(engine);
+
+    let ScriptContext {
+        mut output,
+        actions,
+    } = Rc::into_inner(ctx)
+        .expect("the engine was dropped")
+        .into_inner();
+
+    match result {
+        Ok(value) if value.is_unit() => (),
+        Ok(value) => output.push(ConsoleLine {
+            kind: ConsoleLineKind::Output,
+            text: value.to_string(),
+        }),
+        Err(err) => output.push(ConsoleLine {
+            kind: ConsoleLineKind::Error,
+            text: err.to_string(),
+        }),
+    }
+
+    for action in actions {
+        match action {
+            ScriptAction::Mark { window, name } => {
+                state
+                    .marked_locations
+                    .add(window, MarkType::UserMark { name });
+            }
+            ScriptAction::Search(text) => {
+                state.search.search_text = text;
+                if state
+                    .search
+                    .start_search(Window::from_start_len(AbsoluteOffset::ZERO, input.len()))
+                {
+                    state
+                        .marked_locations
+                        .clear_marks_where(|ty| matches!(ty, MarkType::SearchResult { .. }));
+                }
+            }
+            ScriptAction::Goto(offset) => state.scroll_state.rearrange_bars_for_point(0, offset),
+        }
+    }
+
+    output
+}
+
+/// Builds a sandboxed engine with the hexbait specific functions.
+fn build_engine(
+    ctx: &Rc<RefCell<ScriptContext>>,
+    input: &Input,
+    parsed: Dynamic,
+    selection: Dynamic,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_call_levels(64);
+    engine.set_max_string_size(MAX_READ_LEN as usize);
+    engine.set_max_array_size(1024 * 1024);
+    engine.set_max_map_size(1024 * 1024);
+
+    {
+        let ctx = Rc::clone(ctx);
+        engine.on_print(move |text| {
+            ctx.borrow_mut().output.push(ConsoleLine {
+                kind: ConsoleLineKind::Output,
+                text: text.to_string(),
+            })
+        });
+    }
+    {
+        let ctx = Rc::clone(ctx);
+        engine.on_debug(move |text, _, pos| {
+            ctx.borrow_mut().output.push(ConsoleLine {
+                kind: ConsoleLineKind::Output,
+                text: format!("{pos:?}: {text}"),
+            })
+        });
+    }
+
+    let len = input.len();
+    engine.register_fn("len", move || len.as_u64() as INT);
+
+    {
+        let input = input.clone();
+        engine.register_fn(
+            "read",
+            move |offset: INT, len: INT| -> Result<Blob, Box<EvalAltResult>> {
+                read(&input, offset, len)
+            },
+        );
+    }
+    {
+        let input = input.clone();
+        engine.register_fn(
+            "find",
+            move |needle: &str, from: INT| -> Result<INT, Box<EvalAltResult>> {
+                find(&input, needle.as_bytes(), from)
+            },
+        );
+    }
+    {
+        let input = input.clone();
+        engine.register_fn(
+            "find",
+            move |needle: Blob, from: INT| -> Result<INT, Box<EvalAltResult>> {
+                find(&input, &needle, from)
+            },
+        );
+    }
+    {
+        let ctx = Rc::clone(ctx);
+        engine.register_fn("search", move |text: &str| {
+            ctx.borrow_mut()
+                .actions
+                .push(ScriptAction::Search(text.to_string()))
+        });
+    }
+    engine.register_fn("parsed", move || parsed.clone());
+    engine.register_fn("selection", move || selection.clone());
+    {
+        let ctx = Rc::clone(ctx);
+        engine.register_fn(
+            "mark",
+            move |offset: INT, len: INT, name: &str| -> Result<(), Box<EvalAltResult>> {
+                let window = Window::from_start_len(to_offset(offset)?, to_len(len)?);
+                ctx.borrow_mut().actions.push(ScriptAction::Mark {
+                    window,
+                    name: name.to_string(),
+                });
+                Ok(())
+            },
+        );
+    }
+    {
+        let ctx = Rc::clone(ctx);
+        engine.register_fn(
+            "goto",
+            move |offset: INT| -> Result<(), Box<EvalAltResult>> {
+                let offset = to_offset(offset)?;
+                ctx.borrow_mut().actions.push(ScriptAction::Goto(offset));
+                Ok(())
+            },
+        );
+    }
+
+    engine
+}
+
+/// Converts the given script integer to an offset.
+fn to_offset(offset: INT) -> Result<AbsoluteOffset, Box<EvalAltResult>> {
+    u64::try_from(offset)
+        .map(AbsoluteOffset::from)
+        .map_err(|_| format!("invalid offset: {offset}").into())
+}
+
+/// Converts the given script integer to a length.
+fn to_len(len: INT) -> Result<Len, Box<EvalAltResult>> {
+    u64::try_from(len)
+        .map(Len::from)
+        .map_err(|_| format!("invalid length: {len}").into())
+}
+
+/// Converts the given offset to a script integer.
+fn offset_to_dynamic(offset: AbsoluteOffset) -> Dynamic {
+    Dynamic::from_int(offset.as_u64() as INT)
+}
+
+/// Reads up to `len` bytes at `offset` from the input.
+fn read(input: &Input, offset: INT, len: INT) -> Result<Blob, Box<EvalAltResult>> {
+    let offset = to_offset(offset)?;
+    let len = to_len(len)?;
+    if len.as_u64() > MAX_READ_LEN {
+        return Err(format!("cannot read more than {MAX_READ_LEN} bytes at once").into());
+    }
+
+    input
+        .read_at(offset, len, None)
+        .map(|bytes| bytes.to_vec())
+        .map_err(|err| err.to_string().into())
+}
+
+/// Finds the first occurrence of `needle` at or after `from` in the input.
+///
+/// Returns `-1` if there is no such occurrence.
+fn find(input: &Input, needle: &[u8], from: INT) -> Result<INT, Box<EvalAltResult>> {
+    let mut offset = to_offset(from)?;
+    if needle.is_empty() {
+        return Ok(from);
+    }
+    let searcher = AhoCorasick::new([needle]).map_err(|err| err.to_string())?;
+    let overlap = Len::from(needle.len() as u64 - 1);
+
+    let mut buf = Vec::new();
+    while offset < AbsoluteOffset::ZERO + input.len() {
+        let chunk = input
+            .read_at(offset, Len::from(FIND_CHUNK_SIZE) + overlap, Some(&mut buf))
+            .map_err(|err| err.to_string())?;
+        if let Some(found) = searcher.find(&*chunk) {
+            return Ok((offset.as_u64() + found.start() as u64) as INT);
+        }
+
+        offset += Len::from(FIND_CHUNK_SIZE);
+    }
+
+    Ok(-1)
+}
+
+/// Converts the given parsed value to a value that can be used in scripts.
+fn value_to_dynamic(value: &Value) -> Dynamic {
+    match &value.kind {
+        ValueKind::Boolean(boolean) => Dynamic::from_bool(*boolean),
+        ValueKind::Integer(int) => match INT::try_from(int) {
+            Ok(int) => Dynamic::from_int(int),
+            Err(_) => Dynamic::from(int.to_string()),
+        },
+        ValueKind::Float(float) => Dynamic::from_float(*float),
+        ValueKind::Bytes(bytes) => {
+            if bytes.len() > MAX_PARSED_BYTES_LEN {
+                return Dynamic::UNIT;
+            }
+            match bytes.value() {
+                Ok(bytes) => Dynamic::from_blob(bytes.to_vec()),
+                Err(_) => Dynamic::UNIT,
+            }
+        }
+        ValueKind::Struct { fields, .. } => Dynamic::from_map(
+            fields
+                .iter()
+                .map(|(name, value)| (name.as_str().into(), value_to_dynamic(value)))
+                .collect::<Map>(),
+        ),
+        ValueKind::Array { items, .. } => {
+            Dynamic::from_array(items.iter().map(value_to_dynamic).collect::<Array>())
+        }
+    }
+}
@@ -1,227 +0,0 @@
-//! Does the actual searching on a background thread.
-
-use std::{
-    collections::BTreeSet,
-    sync::{
-        Arc, Mutex, RwLock,
-        mpsc::{self, RecvError, TryRecvError},
-    },
-};
-
-use aho_corasick::AhoCorasick;
-use hexbait_common::{AbsoluteOffset, Input, Len};
-
-use crate::window::Window;
-
-/// Contains shared state between background and foreground searcher.
-pub(crate) struct BackgroundSearcherStartResult {
-    /// The progress of the current search.
-    pub(crate) progress: Arc<RwLock<f32>>,
-    /// The requests for new searches to run.
-    pub(crate) requests: mpsc::Sender<Option<SearchRequest>>,
-}
-
-/// The search request that the background thread receives.
-pub(crate) struct SearchRequest {
-    /// The content to search for.
-    pub(crate) content: Vec<Vec<u8>>,
-    /// Whether to search case insensitively.
-    pub(crate) ascii_case_insensitive: bool,
-    /// The window to search.
-    pub(crate) window: Window,
-    /// The results buffer to use.
-    pub(crate) results: Arc<Mutex<BTreeSet<Window>>>,
-}
-
-/// The search state of the background searcher.
-pub(crate) struct BackgroundSearcher {
-    /// The progress of the current search.
-    progress: Arc<RwLock<f32>>,
-    /// The search results.
-    results: Arc<Mutex<BTreeSet<Window>>>,
-    /// The current offset at which the search happens.
-    current_offset: AbsoluteOffset,
-    /// The window that is searched.
-    search_window: Window,
-    /// The searcher performing the search itself.
-    searcher: Option<AhoCorasick>,
-    /// The size of the portion of the buffer that needs to overlap between searches.
-    overlap_size: Len,
-    /// The size of the search window.
-    search_window_size: Len,
-    /// The buffer where file contents are loaded.
-    buf: Vec<u8>,
-    /// The requests for new searches to run.
-    requests: mpsc::Receiver<Option<SearchRequest>>,
-    /// The input to read from.
-    input: Input,
-}
-
-/// The minimum size of the search window for a single iteration.
-const MIN_SEARCH_WINDOW_SIZE: Len = Len::mib(1);
-
-impl BackgroundSearcher {
-    /// Starts a background searcher thread.
-    pub(crate) fn start(input: &Input) -> BackgroundSearcherStartResult {
-        let progress = Arc::new(RwLock::new(1.0));
-        let results = Arc::new(Mutex::new(BTreeSet::new()));
-        let (sender, receiver) = mpsc::channel();
-
-        let source = input.clone();
-
-        let searcher = BackgroundSearcher {
-            progress: Arc::clone(&progress),
-            results,
-            current_offset: AbsoluteOffset::ZERO,
-            search_window: Window::from_start_len(AbsoluteOffset::ZERO, input.len()),
-            searcher: None,
-            overlap_size: Len::ZERO,
-            search_window_size: Len::ZERO,
-            buf: Vec::new(),
-            requests: receiver,
-            input: source,
-        };
-
-        std::thread::spawn(move || {
-            searcher.run();
-        });
-
-        BackgroundSearcherStartResult {
-            progress,
-            requests: sender,
-        }
-    }
-
-    /// Processes new search requests.
-    ///
-    /// A new request will always cancel previous requests.
-    fn process_new_requests(&mut self) -> bool {
-        let request = if self.search_is_running() {
-            match self.requests.try_recv() {
-                Ok(request) => request,
-                Err(TryRecvError::Empty) => return true,
-                Err(TryRecvError::Disconnected) => return false,
-            }
-        } else {
-            match self.requests.recv() {
-                Ok(request) => request,
-                Err(RecvError) => return false,
-            }
-        };
-
-        let Some(request) = request else {
-            self.stop_search();
-            return true;
-        };
-
-        let largest_content_size = Len::from(
-            request
-                .content
-                .iter()
-                .map(|content| content.len())
-                .max()
-                .unwrap_or(0) as u64,
-        );
-
-        if largest_content_size.is_zero() {
-            return true;
-        }
-
-        *self.progress.write().unwrap() = 0.0;
-        self.results = request.results;
-
-        self.current_offset = request.window.start();
-        self.search_window = request.window;
-        self.searcher = Some(
-            AhoCorasick::builder()
-                .ascii_case_insensitive(request.ascii_case_insensitive)
-                .build(&request.content)
-                .unwrap(),
-        );
-
-        self.overlap_size = largest_content_size - Len::from(1);
-        self.search_window_size = std::cmp::max(largest_content_size * 2, MIN_SEARCH_WINDOW_SIZE);
-
-        true
-    }
-
-    /// Stops a currently running search.
-    fn stop_search(&mut self) {
-        self.searcher = None;
-        *self.progress.write().unwrap() = 1.0;
-    }
-
-    /// Returns whether a search is currently running.
-    fn search_is_running(&self) -> bool {
-        self.searcher.is_some()
-    }
-
-    /// Runs one iteration of the search.
-    fn run_search(&mut self) {
-        let current_overlap = if self.current_offset == self.search_window.start() {
-            Len::ZERO
-        } else {
-            self.overlap_size
-        };
-        let start = self.current_offset - current_overlap;
-        let end = self.search_window.end();
-
-        // This is a bit wasteful because it reads overlapping bytes multiple times.
-        //
-        // In practice I expect many searches to be for small patterns, so this is less of an
-        // issue. Unfortunately while the new API for reading from `Input` is much nicer for
-        // everything else, here it falls short.
-        // But even then, when using memory mapped reads, this makes it actually more efficient.
-        let buf = self
-            .input
-            .read_at(
-                start,
-                self.search_window_size.min(end - start),
-                Some(&mut self.buf),
-            )
-            .expect("TODO: improve error handling here");
-        if buf.is_empty() {
-            // we finished the search
-            self.searcher = None;
-            *self.progress.write().unwrap() = 1.0;
-            return;
-        }
-        let buf_len = Len::from(u64::try_from(buf.len()).expect("buffer length must fit u64"));
-
-        for result in self.searcher.as_ref().unwrap().find_overlapping_iter(&*buf) {
-            let offset =
-                start + Len::from(u64::try_from(result.start()).expect("read buffer must fit u64"));
-            let len = Len::from(u64::try_from(result.len()).expect("search string must fit u64"));
-            let window = Window::from_start_len(offset, len);
-            self.results.lock().unwrap().insert(window);
-        }
-
-        if start + buf_len == self.search_window.end() {
-            // we finished the search
-            self.searcher = None;
-            *self.progress.write().unwrap() = 1.0;
-            return;
-        }
-
-        self.current_offset += buf_len - current_overlap;
-
-        let fraction_completed = ((self.current_offset - self.search_window.start()).as_u64()
-            as f32)
-            / (self.search_window.size().as_u64() as f32);
-
-        *self.progress.write().unwrap() = fraction_completed;
-    }
-
-    /// Runs the background thread.
-    fn run(mut self) {
-        loop {
-            if !self.process_new_requests() {
-                break;
-            }
-
-            if self.search_is_running() {
-                self.run_search();
-            }
-        }
-    }
-}
@@ -1,25 +1,43 @@
 //! Implements the structures storing the state of the hexbait application.
 
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
 
+pub use bookmark_state::BookmarkState;
+pub use carving_state::CarvingState;
 pub use classification_state::ClassificationState;
+pub use crypto_constants_state::CryptoConstantsState;
 pub use format_discovery_state::{ColumnInfo, ColumnType, FormatDiscoveryState};
 use hexbait_common::{Endianness, Input};
-pub use parse_state::{ParseState, ParseType};
+pub use log_state::LogState;
+pub use parse_state::{LAZY_ARRAY_THRESHOLD, ParseState, ParseType};
+pub use pipe_state::{PipeResult, PipeState};
+pub use project_state::ProjectState;
+pub use script_state::ScriptState;
 pub use scroll_state::{InteractionState, ScrollState, Scrollbar};
 pub use search_state::SearchState;
 pub use selection_state::SelectionState;
-pub use settings::{Settings, ViewKind};
+pub use settings::{
+    BYTES_PER_ROW_CHOICES, Settings, SettingsError, Theme, ViewKind, default_config_path,
+    default_format_dir,
+};
 pub use statistics_display_state::StatisticsDisplayState;
 
 use crate::{
+    IDLE_TIME,
     marking::{MarkStore, MarkType},
-    statistics::{StatisticsHandler, classification::classify_selected_window},
+    statistics::{StatisticsHandler, classification::classify},
 };
 
+mod bookmark_state;
+mod carving_state;
 mod classification_state;
+mod crypto_constants_state;
 mod format_discovery_state;
+mod log_state;
 mod parse_state;
+mod pipe_state;
+mod project_state;
+mod script_state;
 mod scroll_state;
 mod search_state;
 mod selection_state;
@@ -50,6 +68,20 @@ pub struct State {
     pub format_discovery: FormatDiscoveryState,
     /// The currently selected endianness.
     pub endianness: Endianness,
+    /// The state of the script console.
+    pub script_state: ScriptState,
+    /// The state for saving and loading projects.
+    pub project_state: ProjectState,
+    /// The state of the log viewer.
+    pub log_state: LogState,
+    /// The state of the carving module.
+    pub carving_state: CarvingState,
+    /// The state of the cryptographic constant detection.
+    pub crypto_constants: CryptoConstantsState,
+    /// The state of piping selections through external commands.
+    pub pipe_state: PipeState,
+    /// The state of importing and exporting bookmarks.
+    pub bookmark_state: BookmarkState,
 }
 
 impl State {
@@ -62,32 +94,97 @@ impl State {
             selection_state: SelectionState::new(),
             statistics_display_state: StatisticsDisplayState::new(),
             parse_state: ParseState::new(custom_parsers),
-            classification_state: ClassificationState::new(),
+            classification_state: ClassificationState::new(input),
             statistics_handler: StatisticsHandler::new(input.clone()),
             marked_locations: MarkStore::new(),
             format_discovery: FormatDiscoveryState::new(),
             endianness: Endianness::native(),
+            script_state: ScriptState::new(),
+            project_state: ProjectState::default(),
+            log_state: LogState::new(),
+            carving_state: CarvingState::new(),
+            crypto_constants: CryptoConstantsState::new(),
+            pipe_state: PipeState::new(),
+            bookmark_state: BookmarkState::new(),
         }
     }
 
     /// This method is called once at the end of a frame to do necessary bookkeeping.
     pub fn end_of_frame(&mut self) {
-        self.statistics_handler
-            .end_of_frame(&self.settings, &self.scroll_state);
+        if self.scroll_state.changed().is_changed() {
+            let bins_per_window = self.scroll_state.effective_height() as u64
+                * if self.settings.fine_grained_scrollbars() {
+                    16
+                } else {
+                    1
+                };
+
+            self.statistics_handler.request_windows(
+                self.scroll_state.windows().collect(),
+                bins_per_window,
+                self.scroll_state.gilbert_pixel_budget,
+            );
+        }
 
-        self.marked_locations.batch_add(
-            std::mem::take(&mut *self.search.searcher.results()).into_iter(),
-            MarkType::SearchResult,
-        );
+        let mut search_results_by_encoding = BTreeMap::<_, Vec<_>>::new();
+        for (window, encoding) in std::mem::take(&mut *self.search.searcher.results()) {
+            search_results_by_encoding
+                .entry(encoding)
+                .or_default()
+                .push(window);
+        }
+        for (encoding, windows) in search_results_by_encoding {
+            self.marked_locations
+                .batch_add(windows.into_iter(), MarkType::SearchResult { encoding });
+        }
+        if let Some(handle) = &self.crypto_constants.handle {
+            for found in handle.take_matches() {
+                self.marked_locations.add(
+                    found.window,
+                    MarkType::CryptoConstant {
+                        name: &found.constant.name,
+                    },
+                );
+            }
+            if handle.is_finished() {
+                self.crypto_constants.message = handle.take_error().map(|err| err.to_string());
+                self.crypto_constants.handle = None;
+            }
+        }
         self.marked_locations.end_of_frame();
 
         if self.parse_state.sync_parse_offset_to_selection_start
-            && let Some(selection) = self.selection_state.selection()
+            && let Some(selection) = self.selection_state.selected_window()
         {
             self.parse_state.parse_offset = selection.start().as_u64().to_string();
         }
 
-        classify_selected_window(self);
+        self.classify_selected_window();
+    }
+
+    /// Returns after how long the next frame is needed to show the progress of background work.
+    ///
+    /// Returns `None` if nothing runs in the background, so frames are only needed on input.
+    pub fn repaint_after(&self) -> Option<Duration> {
+        let searching = self.search.searcher.is_running() && !self.search.searcher.is_paused();
+        let working = searching
+            || self.crypto_constants.handle.is_some()
+            || self.carving_state.handle.is_some()
+            || self.parse_state.is_parsing();
+
+        working.then_some(IDLE_TIME)
+    }
+
+    /// Classifies the currently selected window.
+    fn classify_selected_window(&mut self) {
+        let (statistics, quality) = self
+            .statistics_handler
+            .get_bigram_statistics(self.scroll_state.selected_window());
+        if quality == 0.0 {
+            return;
+        }
+
+        self.classification_state.classification_results = classify(&statistics);
     }
 }
 
@@ -0,0 +1,32 @@
+//! Implements the state for importing and exporting bookmarks.
+
+use std::borrow::Cow;
+
+use crate::bookmarks::BookmarkFormat;
+
+/// The state for importing and exporting bookmarks of other hex editors.
+pub struct BookmarkState {
+    /// The path of the bookmark file.
+    pub path: String,
+    /// The format of the bookmark file.
+    pub format: BookmarkFormat,
+    /// The message about the last import or export.
+    pub message: Option<Cow<'static, str>>,
+}
+
+impl BookmarkState {
+    /// Creates a new bookmark state.
+    pub fn new() -> BookmarkState {
+        BookmarkState {
+            path: String::new(),
+            format: BookmarkFormat::ImHex,
+            message: None,
+        }
+    }
+}
+
+impl Default for BookmarkState {
+    fn default() -> Self {
+        BookmarkState::new()
+    }
+}
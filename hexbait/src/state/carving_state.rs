@@ -0,0 +1,39 @@
+//! Implements the state of the carving module.
+
+use hexbait_core::carve::{CarvedFile, CarvingHandle};
+
+/// The default number of levels of compressed streams that are decompressed.
+const DEFAULT_MAX_DEPTH: usize = 4;
+
+/// The state of the carving module.
+pub struct CarvingState {
+    /// The handle to the carving that currently runs in the background, if any.
+    pub handle: Option<CarvingHandle>,
+    /// The files carved by the last finished run.
+    pub results: Vec<CarvedFile>,
+    /// The directory that carved files are extracted into.
+    pub output_dir: String,
+    /// How many levels of compressed streams are decompressed.
+    pub max_depth: usize,
+    /// A message about the outcome of the last run.
+    pub message: Option<String>,
+}
+
+impl CarvingState {
+    /// Creates a new carving state.
+    pub fn new() -> CarvingState {
+        CarvingState {
+            handle: None,
+            results: Vec::new(),
+            output_dir: String::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            message: None,
+        }
+    }
+}
+
+impl Default for CarvingState {
+    fn default() -> Self {
+        CarvingState::new()
+    }
+}
@@ -1,24 +1,26 @@
 //! Implements the state for classifier.
 
+use hexbait_common::{AbsoluteOffset, Input};
+use hexbait_core::magic::FileKind;
+
 use crate::statistics::classification::Class;
 
 /// The state for the input classifier.
 pub struct ClassificationState {
     /// The classification results for the currently selected window.
     pub classification_results: Option<Vec<Class>>,
+    /// The type of the input as identified by its magic number.
+    pub file_kind: Option<FileKind>,
 }
 
 impl ClassificationState {
-    /// Creates a new classification state.
-    pub fn new() -> ClassificationState {
+    /// Creates a new classification state for the given input.
+    pub fn new(input: &Input) -> ClassificationState {
         ClassificationState {
             classification_results: None,
+            file_kind: FileKind::identify_at(input, AbsoluteOffset::ZERO)
+                .ok()
+                .flatten(),
         }
     }
 }
-
-impl Default for ClassificationState {
-    fn default() -> Self {
-        Self::new()
-    }
-}
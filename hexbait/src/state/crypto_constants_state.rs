@@ -0,0 +1,27 @@
+//! Implements the state of the cryptographic constant detection.
+
+use hexbait_core::crypto_constants::ConstantScanHandle;
+
+/// The state of the cryptographic constant detection.
+pub struct CryptoConstantsState {
+    /// The handle to the scan that currently runs in the background, if any.
+    pub handle: Option<ConstantScanHandle>,
+    /// A message about the outcome of the last scan.
+    pub message: Option<String>,
+}
+
+impl CryptoConstantsState {
+    /// Creates a new cryptographic constant detection state.
+    pub fn new() -> CryptoConstantsState {
+        CryptoConstantsState {
+            handle: None,
+            message: None,
+        }
+    }
+}
+
+impl Default for CryptoConstantsState {
+    fn default() -> Self {
+        CryptoConstantsState::new()
+    }
+}
@@ -0,0 +1,27 @@
+//! Implements the state of the log viewer.
+
+use tracing::Level;
+
+/// The state of the log viewer.
+pub struct LogState {
+    /// The most verbose level that is shown.
+    pub max_level: Level,
+    /// Only entries containing this text are shown.
+    pub filter: String,
+}
+
+impl LogState {
+    /// Creates a new log viewer state.
+    pub fn new() -> LogState {
+        LogState {
+            max_level: Level::DEBUG,
+            filter: String::new(),
+        }
+    }
+}
+
+impl Default for LogState {
+    fn default() -> Self {
+        LogState::new()
+    }
+}
@@ -1,45 +1,77 @@
 //! Implements the state for the hexbait parser.
 
-use std::{borrow::Cow, collections::BTreeMap, path::PathBuf};
-
-use hexbait_builtin_parsers::built_in_format_descriptions;
-
-/// The type of parser to use.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum ParseType {
-    /// Use no parser.
-    None,
-    /// Use a built-in parser.
-    Builtin(&'static str),
-    /// Use a custom parser.
-    Custom(PathBuf),
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path as FsPath, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use hexbait_builtin_parsers::{built_in_format_descriptions, import_paths};
+use hexbait_common::{AbsoluteOffset, Input};
+pub use hexbait_core::parse::ParseType;
+use hexbait_core::parse::{ParseHandle, load_parser};
+use hexbait_lang::{
+    DEFAULT_MAX_DEPTH, EvalLimits, EvalOptions, ParseErrKind, ParseResult,
+    ir::{File, ImportPaths, path::Path},
+};
+use web_time::Instant;
+
+use crate::{gui::value_renderers::ValueRenderers, state::default_format_dir};
+
+/// How often custom format descriptions are checked for modifications.
+const CUSTOM_PARSER_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the input is checked for modifications.
+const INPUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of parse results that are kept for reuse.
+const PARSE_RESULT_CACHE_SIZE: usize = 16;
+
+/// The number of elements of parsed arrays that are evaluated eagerly before the remaining ones are
+/// only evaluated once they are shown.
+pub const LAZY_ARRAY_THRESHOLD: usize = 1000;
+
+/// The limits on parsing, so that adversarial inputs cannot make it run for too long or overflow
+/// the stack.
+const PARSE_LIMITS: EvalLimits = EvalLimits {
+    max_repetitions: Some(1 << 28),
+    max_depth: Some(DEFAULT_MAX_DEPTH),
+    max_bytes: None,
+};
+
+/// A custom format description that was loaded from disk.
+struct CachedParser {
+    /// The modification time of the file when it was loaded, if known.
+    modified: Option<SystemTime>,
+    /// When the modification time was last checked.
+    last_check: Instant,
+    /// The lowered format description, or `None` if it could not be loaded.
+    ir: Option<Arc<File>>,
 }
 
-impl ParseType {
-    /// Returns a string representation of the parse type.
-    pub fn as_str(&self) -> Cow<'_, str> {
-        match self {
-            ParseType::None => Cow::Borrowed("none"),
-            ParseType::Builtin(name) => Cow::Borrowed(name),
-            ParseType::Custom(path_buf) => {
-                if let Some(file_name) = path_buf.file_name() {
-                    match file_name.to_string_lossy() {
-                        Cow::Borrowed(name) => {
-                            Cow::Borrowed(name.strip_suffix(".hbl").unwrap_or(name))
-                        }
-                        Cow::Owned(name) => {
-                            if let Some(name) = name.strip_suffix(".hbl") {
-                                Cow::Owned(String::from(name))
-                            } else {
-                                Cow::Owned(name)
-                            }
-                        }
-                    }
-                } else {
-                    path_buf.to_string_lossy()
-                }
-            }
-        }
+/// Describes what a parse result was parsed from.
+struct ParseKey {
+    /// The input that was parsed.
+    input: Input,
+    /// The selected parser.
+    parse_type: ParseType,
+    /// The offset at which the input was parsed.
+    offset: AbsoluteOffset,
+    /// The format description that was used.
+    parser: Arc<File>,
+    /// The modification time of the input when it was parsed, if known.
+    input_modified: Option<SystemTime>,
+}
+
+impl ParseKey {
+    /// Determines if both keys describe the same parse.
+    fn same_as(&self, other: &ParseKey) -> bool {
+        Input::ptr_eq(&self.input, &other.input)
+            && self.parse_type == other.parse_type
+            && self.offset == other.offset
+            && Arc::ptr_eq(&self.parser, &other.parser)
+            && self.input_modified == other.input_modified
     }
 }
 
@@ -52,9 +84,39 @@ pub struct ParseState {
     /// Whether the parse offset should be synced to the start of the selection.
     pub sync_parse_offset_to_selection_start: bool,
     /// The built-in format description.
-    pub built_in_format_descriptions: BTreeMap<&'static str, hexbait_lang::ir::File>,
+    pub built_in_format_descriptions: BTreeMap<&'static str, Arc<File>>,
     /// The path to the custom parser definitions.
     pub custom_parsers: Vec<PathBuf>,
+    /// Where the files imported by custom format descriptions are looked up.
+    pub import_paths: ImportPaths,
+    /// The text to search for in the parsed value.
+    pub value_search_text: String,
+    /// The paths of the values matching the last search in the parsed value.
+    pub value_search_results: Vec<Path>,
+    /// The index of the currently selected search result.
+    pub value_search_current: usize,
+    /// The renderers for rich previews of parsed values.
+    pub value_renderers: ValueRenderers,
+    /// Whether rich previews of parsed values are shown.
+    pub show_value_previews: bool,
+    /// Whether the offsets of parsed values are included when copying them.
+    pub export_with_offsets: bool,
+    /// The result whose colored values are currently marked.
+    pub colored_result: Option<Arc<ParseResult>>,
+    /// Whether the bytes between parsed values that were not parsed are shaded.
+    pub shade_unparsed: bool,
+    /// The result whose unparsed bytes are currently shaded.
+    pub shaded_result: Option<Arc<ParseResult>>,
+    /// The custom format descriptions that were already loaded, by their path.
+    custom_parser_cache: BTreeMap<PathBuf, CachedParser>,
+    /// The parsing that is running in the background.
+    parse_job: Option<(ParseKey, ParseHandle)>,
+    /// The results of the last parsings that finished, starting with the most recently used one.
+    parse_results: VecDeque<(ParseKey, Arc<ParseResult>)>,
+    /// The modification time of the input when it was last checked, if known.
+    input_modified: Option<SystemTime>,
+    /// When the input was last checked for modifications.
+    last_input_check: Option<Instant>,
 }
 
 impl ParseState {
@@ -64,8 +126,227 @@ impl ParseState {
             parse_type: ParseType::None,
             parse_offset: String::from("0"),
             sync_parse_offset_to_selection_start: true,
-            built_in_format_descriptions: built_in_format_descriptions(),
+            built_in_format_descriptions: built_in_format_descriptions()
+                .into_iter()
+                .filter_map(|(name, ir)| match ir {
+                    Ok(ir) => Some((name, Arc::new(ir))),
+                    Err(err) => {
+                        tracing::warn!(name, %err, "invalid built-in format description");
+                        None
+                    }
+                })
+                .collect(),
             custom_parsers,
+            import_paths: import_paths(default_format_dir().into_iter().collect()),
+            value_search_text: String::new(),
+            value_search_results: Vec::new(),
+            value_search_current: 0,
+            value_renderers: ValueRenderers::new(),
+            show_value_previews: true,
+            export_with_offsets: false,
+            colored_result: None,
+            shade_unparsed: false,
+            shaded_result: None,
+            custom_parser_cache: BTreeMap::new(),
+            parse_job: None,
+            parse_results: VecDeque::new(),
+            input_modified: None,
+            last_input_check: None,
+        }
+    }
+
+    /// Returns the result of parsing the input with the selected parser at the selected offset.
+    ///
+    /// Parsing happens in the background and is restarted whenever the parser or the parse offset
+    /// change. While it is running, the previous result is returned, if there is one. The last
+    /// results are kept, so that going back to a previous parser or offset does not parse again.
+    /// If the input is modified, it is parsed again and the previous results are discarded.
+    ///
+    /// Returns `None` if no parser is selected, the parser cannot be loaded, the parse offset is
+    /// invalid or nothing was parsed yet.
+    pub fn parse(&mut self, input: &Input) -> Option<Arc<ParseResult>> {
+        self.check_input_modified(input);
+
+        let Some(key) = self.parse_key(input) else {
+            self.parse_job = None;
+            return None;
+        };
+
+        if let Some((job_key, handle)) = self.parse_job.take() {
+            match handle.take_result() {
+                Some(result) => self.cache_result(job_key, Arc::new(result)),
+                None => self.parse_job = Some((job_key, handle)),
+            }
+        }
+
+        match self
+            .parse_results
+            .iter()
+            .position(|(cached_key, _)| cached_key.same_as(&key))
+        {
+            Some(0) => (),
+            // a cancelled result is incomplete, so it is only kept while it is selected
+            Some(i) if was_cancelled(&self.parse_results[i].1) => {
+                self.parse_results.remove(i);
+            }
+            Some(i) => {
+                let entry = self
+                    .parse_results
+                    .remove(i)
+                    .expect("the index was just found");
+                self.parse_results.push_front(entry);
+            }
+            None => (),
+        }
+
+        let is_cached = self
+            .parse_results
+            .front()
+            .is_some_and(|(cached_key, _)| cached_key.same_as(&key));
+        let is_running = self
+            .parse_job
+            .as_ref()
+            .is_some_and(|(job_key, _)| job_key.same_as(&key));
+        if !is_cached && !is_running {
+            let handle = ParseHandle::start(
+                Arc::clone(&key.parser),
+                input,
+                key.offset,
+                EvalOptions {
+                    limits: PARSE_LIMITS,
+                    lazy_array_threshold: Some(LAZY_ARRAY_THRESHOLD),
+                    ..EvalOptions::default()
+                },
+            );
+            self.parse_job = Some((key, handle));
+        }
+
+        self.parse_results
+            .front()
+            .map(|(_, result)| Arc::clone(result))
+    }
+
+    /// Stores a finished parse result as the most recently used one.
+    fn cache_result(&mut self, key: ParseKey, result: Arc<ParseResult>) {
+        self.parse_results
+            .retain(|(cached_key, _)| !cached_key.same_as(&key));
+        self.parse_results.push_front((key, result));
+        self.parse_results.truncate(PARSE_RESULT_CACHE_SIZE);
+    }
+
+    /// Checks if the input was modified since it was last checked.
+    ///
+    /// The results of parsing the old data of the input are discarded in that case.
+    fn check_input_modified(&mut self, input: &Input) {
+        if self
+            .last_input_check
+            .is_some_and(|last_check| last_check.elapsed() < INPUT_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_input_check = Some(Instant::now());
+
+        let modified = input.modified();
+        if modified == self.input_modified {
+            return;
+        }
+        self.input_modified = modified;
+
+        self.parse_results
+            .retain(|(key, _)| key.input_modified == modified);
+        if self
+            .parse_job
+            .as_ref()
+            .is_some_and(|(job_key, _)| job_key.input_modified != modified)
+        {
+            self.parse_job = None;
+        }
+    }
+
+    /// Determines if parsing is running in the background.
+    pub fn is_parsing(&self) -> bool {
+        self.parse_job.is_some()
+    }
+
+    /// Cancels the parsing that is running in the background.
+    ///
+    /// The values that were parsed until then become the result.
+    pub fn cancel_parsing(&self) {
+        if let Some((_, handle)) = &self.parse_job {
+            handle.cancel();
         }
     }
+
+    /// Returns what should be parsed with the current settings.
+    fn parse_key(&mut self, input: &Input) -> Option<ParseKey> {
+        let offset = self.parse_offset.parse().map(AbsoluteOffset::from).ok()?;
+        let parser = match &self.parse_type {
+            ParseType::None => return None,
+            ParseType::Builtin(builtin) => self.built_in_format_descriptions.get(builtin)?,
+            ParseType::Custom(path) => {
+                Self::cached_custom_parser(&mut self.custom_parser_cache, path, &self.import_paths)?
+            }
+        };
+
+        Some(ParseKey {
+            input: input.clone(),
+            parse_type: self.parse_type.clone(),
+            offset,
+            parser: Arc::clone(parser),
+            input_modified: self.input_modified,
+        })
+    }
+
+    /// Returns the custom format description at the given path.
+    ///
+    /// The format description is only loaded again if the file was modified since it was last
+    /// loaded.
+    fn cached_custom_parser<'cache>(
+        cache: &'cache mut BTreeMap<PathBuf, CachedParser>,
+        path: &FsPath,
+        import_paths: &ImportPaths,
+    ) -> Option<&'cache Arc<File>> {
+        let modified = |path: &FsPath| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+
+        let needs_reload = match cache.get_mut(path) {
+            Some(cached) if cached.last_check.elapsed() < CUSTOM_PARSER_CHECK_INTERVAL => false,
+            Some(cached) => {
+                cached.last_check = Instant::now();
+                modified(path) != cached.modified
+            }
+            None => true,
+        };
+
+        if needs_reload {
+            let modified = modified(path);
+            let ir = load_parser(path, import_paths)
+                .inspect_err(|err| {
+                    tracing::debug!(path = %path.display(), "cannot load format description: {err}")
+                })
+                .ok()
+                .map(Arc::new);
+            cache.insert(
+                path.to_path_buf(),
+                CachedParser {
+                    modified,
+                    last_check: Instant::now(),
+                    ir,
+                },
+            );
+        }
+
+        cache.get(path)?.ir.as_ref()
+    }
+}
+
+/// Determines if the parsing that produced the result was cancelled.
+fn was_cancelled(result: &ParseResult) -> bool {
+    result
+        .errors
+        .iter()
+        .any(|err| matches!(err.kind, ParseErrKind::Cancelled))
 }
@@ -0,0 +1,56 @@
+//! Implements the state of piping selections through external commands.
+
+use crate::{pipe::PipeJob, window::Window};
+
+/// The state of piping selections through external commands.
+pub struct PipeState {
+    /// The command to run.
+    pub command: String,
+    /// The name under which the command is saved as a tool.
+    pub tool_name: String,
+    /// The command that currently runs together with the window piped through it, if any.
+    pub job: Option<(Window, PipeJob)>,
+    /// The result of the last command.
+    pub result: Option<PipeResult>,
+    /// The output that should be opened as a new document.
+    ///
+    /// This is handled by the application at the end of the frame.
+    pub open_as_document: Option<(String, Vec<u8>)>,
+}
+
+/// The result of running a command on a selection.
+pub struct PipeResult {
+    /// The command that was run.
+    pub command: String,
+    /// The window that was piped through the command.
+    pub window: Window,
+    /// The bytes written to the standard output.
+    pub stdout: Vec<u8>,
+    /// The standard output split into lines for display.
+    pub lines: Vec<String>,
+    /// The text written to the standard error output.
+    pub stderr: String,
+    /// A description of how the command exited.
+    pub status: String,
+    /// Whether the command exited successfully.
+    pub success: bool,
+}
+
+impl PipeState {
+    /// Creates a new pipe state.
+    pub fn new() -> PipeState {
+        PipeState {
+            command: String::new(),
+            tool_name: String::new(),
+            job: None,
+            result: None,
+            open_as_document: None,
+        }
+    }
+}
+
+impl Default for PipeState {
+    fn default() -> Self {
+        PipeState::new()
+    }
+}
@@ -0,0 +1,35 @@
+//! Implements the state for saving and loading projects.
+
+use std::{borrow::Cow, path::PathBuf};
+
+/// The state for saving and loading projects.
+pub struct ProjectState {
+    /// The path of the analyzed file, if it was opened from a path.
+    pub file_path: Option<PathBuf>,
+    /// The path of the project file.
+    pub project_path: String,
+    /// The message about the last save or load.
+    pub message: Option<Cow<'static, str>>,
+}
+
+impl ProjectState {
+    /// Creates a new project state.
+    pub fn new(file_path: Option<PathBuf>) -> ProjectState {
+        let project_path = file_path
+            .as_ref()
+            .map(|path| format!("{}.hbproj", path.display()))
+            .unwrap_or_default();
+
+        ProjectState {
+            file_path,
+            project_path,
+            message: None,
+        }
+    }
+}
+
+impl Default for ProjectState {
+    fn default() -> Self {
+        ProjectState::new(None)
+    }
+}
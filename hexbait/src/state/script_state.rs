@@ -0,0 +1,32 @@
+//! Implements the state of the script console.
+
+use rhai::Scope;
+
+use crate::scripting::ConsoleLine;
+
+/// The state of the script console.
+pub struct ScriptState {
+    /// The script that is currently being edited.
+    pub script: String,
+    /// The output of previously run scripts.
+    pub output: Vec<ConsoleLine>,
+    /// The variables that are kept between script runs.
+    pub scope: Scope<'static>,
+}
+
+impl ScriptState {
+    /// Creates a new script state.
+    pub fn new() -> ScriptState {
+        ScriptState {
+            script: String::new(),
+            output: Vec::new(),
+            scope: Scope::new(),
+        }
+    }
+}
+
+impl Default for ScriptState {
+    fn default() -> Self {
+        ScriptState::new()
+    }
+}
@@ -29,7 +29,7 @@ pub struct ScrollState {
     /// The number of rows that have been scrolled down from the start in hex view.
     pub hex_scroll_offset: u64,
     /// The cached image for the sidebar in the hex view.
-    pub hex_sidebar_cached_image: CachedImage<(u64, u64, FineGrainedSrcollbars)>,
+    pub hex_sidebar_cached_image: CachedImage<(u64, u64, FineGrainedSrcollbars, usize)>,
     /// The cached image for the gilbert map.
     pub gilbert_map_cached_image: CachedImage<(Window, FineGrainedSrcollbars)>,
     /// The blurred version of the gilbert map.
@@ -55,6 +55,8 @@ pub struct ScrollState {
     fine_grained_scrollbars: bool,
     /// The height of a character in the hex view.
     hex_char_height: f32,
+    /// The number of bytes in a row of the hex view.
+    bytes_per_row: u64,
     /// The selection state in the previous frame.
     prev_selection_state: u64,
 }
@@ -84,6 +86,8 @@ impl ScrollState {
             fine_grained_scrollbars: true,
             // start with a random non-zero height
             hex_char_height: 20.0,
+            // start with the default, this is updated in the first step
+            bytes_per_row: 16,
             // the previous selection state is irrelevant for the first frame since we draw anyway
             prev_selection_state: 0,
         }
@@ -96,6 +100,7 @@ impl ScrollState {
 
         self.fine_grained_scrollbars = settings.fine_grained_scrollbars();
         self.hex_char_height = settings.char_height();
+        self.bytes_per_row = settings.bytes_per_row();
         self.height = height;
     }
 
@@ -145,25 +150,25 @@ impl ScrollState {
             // ensure that the correction below does not make the start invisible
 
             AbsoluteOffset::ZERO
-        } else if end > AbsoluteOffset::ZERO + self.file_size() - Len::from(16) {
+        } else if end > AbsoluteOffset::ZERO + self.file_size() - Len::from(self.bytes_per_row) {
             // over-correct towards the end to ensure it's guaranteed to be visible
 
-            AbsoluteOffset::from(self.file_size().round_up(16).as_u64())
+            AbsoluteOffset::from(self.file_size().round_up(self.bytes_per_row).as_u64())
                 - self.total_hexdump_bytes()
         } else {
-            start.align_down(16)
+            start.align_down(self.bytes_per_row)
         }
     }
 
     /// The number of bytes that a hexdump can show at once.
     pub fn total_hexdump_bytes(&self) -> Len {
         let total_rows = (self.height.trunc() as u64).max(1);
-        Len::from(total_rows * 16)
+        Len::from(total_rows * self.bytes_per_row)
     }
 
     /// The number of bytes visible at once in hex view.
     fn hex_visible_window_size(&self) -> Len {
-        Len::from((self.height / self.hex_char_height).trunc() as u64 * 16)
+        Len::from((self.height / self.hex_char_height).trunc() as u64 * self.bytes_per_row)
     }
 
     /// Creates a hash of the scrollbar selection state.
@@ -173,6 +178,7 @@ impl ScrollState {
         self.fine_grained_scrollbars.hash(&mut hasher);
         self.height.to_ne_bytes().hash(&mut hasher);
         self.hex_char_height.to_ne_bytes().hash(&mut hasher);
+        self.bytes_per_row.hash(&mut hasher);
         self.scrollbars.len().hash(&mut hasher);
         self.gilbert_pixel_budget.hash(&mut hasher);
         for bar in &self.scrollbars {
@@ -266,7 +272,7 @@ impl ScrollState {
                 tentative_hex_offset
             };
 
-        self.hex_scroll_offset = unrounded_hex_offset.as_u64() / 16;
+        self.hex_scroll_offset = unrounded_hex_offset.as_u64() / self.bytes_per_row;
     }
 
     /// Enforces the invariant that no fully selected bar can be in the middle.
@@ -379,11 +385,10 @@ impl Scrollbar {
     pub fn center_around(&mut self, center: RelativeOffset, window: Window) {
         let half_len = self.selection_len / 2;
 
-        if center < RelativeOffset::from(half_len.as_u64()) {
+        if center < half_len.to_relative() {
             self.selection_start = RelativeOffset::ZERO;
-        } else if center + half_len > RelativeOffset::from(window.size().as_u64()) {
-            self.selection_start =
-                RelativeOffset::from(window.size().as_u64()) - self.selection_len;
+        } else if center + half_len > window.size().to_relative() {
+            self.selection_start = window.size().to_relative() - self.selection_len;
         } else {
             self.selection_start = center - half_len;
         }
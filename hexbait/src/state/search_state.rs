@@ -3,9 +3,12 @@
 use std::borrow::Cow;
 
 use hexbait_common::Input;
-use hexbait_lang::ir::str_lit_content_to_bytes;
+use hexbait_lang::ir::{byte_pattern_to_bytes, str_lit_content_to_bytes};
 
-use crate::search::Searcher;
+use crate::{
+    search::{SearchQuery, Searcher, build_regex},
+    window::Window,
+};
 
 /// The search state.
 pub struct SearchState {
@@ -15,10 +18,12 @@ pub struct SearchState {
     pub search_text: String,
     /// Whether to search case insensitive (ASCII only).
     pub search_ascii_case_insensitive: bool,
-    /// Whether to search for a UTF-16 version of the input.
-    pub search_utf16: bool,
+    /// Whether to search for the input in all supported text encodings.
+    pub search_all_text_encodings: bool,
     /// Whether to search only the current window instead of the whole input.
     pub search_current_window: bool,
+    /// Whether to interpret the search text as a regular expression.
+    pub search_regex: bool,
 }
 
 impl SearchState {
@@ -28,18 +33,59 @@ impl SearchState {
             searcher: Searcher::new(input),
             search_text: String::new(),
             search_ascii_case_insensitive: true,
-            search_utf16: true,
+            search_all_text_encodings: true,
             search_current_window: false,
+            search_regex: false,
         }
     }
 
-    /// Returns the bytes to search for or an error message.
-    pub fn search_bytes(&self) -> Result<Vec<u8>, Cow<'static, str>> {
-        let mut search_bytes = Vec::new();
+    /// Returns the query to search for or an error message.
+    ///
+    /// The search text is interpreted as a byte pattern (e.g. `"MZ" 90 00 ?? "PE"`) if it is
+    /// one and contains a string literal, a wildcard or multiple parts.
+    /// Otherwise it is interpreted as text which may contain escape sequences.
+    /// If regular expression search is enabled, the search text is always a regular expression.
+    pub fn search_query(&self) -> Result<SearchQuery, Cow<'static, str>> {
+        if self.search_regex {
+            return match build_regex(&self.search_text, self.search_ascii_case_insensitive) {
+                Ok(_) => Ok(SearchQuery::Regex(self.search_text.clone())),
+                Err(msg) => Err(Cow::Owned(msg)),
+            };
+        }
 
+        let text = self.search_text.trim();
+        let mut pattern = Vec::new();
+        if (text.contains(['"', '?']) || text.contains(char::is_whitespace))
+            && byte_pattern_to_bytes(text, &mut pattern).is_ok()
+        {
+            return Ok(SearchQuery::Pattern(pattern));
+        }
+
+        let mut search_bytes = Vec::new();
         match str_lit_content_to_bytes(&self.search_text, &mut search_bytes) {
-            Ok(()) => Ok(search_bytes),
+            Ok(()) => Ok(SearchQuery::Text(search_bytes)),
             Err((msg, _)) => Err(msg),
         }
     }
+
+    /// Starts a search for the current search text in the given window.
+    ///
+    /// Returns whether a search was started.
+    pub fn start_search(&mut self, window: Window) -> bool {
+        let Ok(query) = self.search_query() else {
+            return false;
+        };
+        if !query.is_searchable() {
+            return false;
+        }
+
+        self.searcher.start_new_search(
+            &query,
+            self.search_ascii_case_insensitive,
+            self.search_all_text_encodings && query.as_text().is_some(),
+            window,
+        );
+
+        true
+    }
 }
@@ -1,17 +1,15 @@
 //! Implements the state container for the selection in the hex view.
 
-use std::ops::RangeInclusive;
-
 use hexbait_common::AbsoluteOffset;
 
 use crate::window::Window;
 
 /// The state of the selection.
 pub struct SelectionState {
-    /// The current selection.
-    selection: Option<RangeInclusive<AbsoluteOffset>>,
-    /// The previous selection.
-    prev_selection: Option<RangeInclusive<AbsoluteOffset>>,
+    /// The current selection as the offsets of the anchor and the cursor byte.
+    selection: Option<(AbsoluteOffset, AbsoluteOffset)>,
+    /// The previously selected window.
+    prev_selection: Option<Window>,
     /// Whether or not a selection is in progress.
     selecting: bool,
 }
@@ -29,17 +27,17 @@ impl SelectionState {
     /// Starts a selection at the given offset.
     fn start_selection(&mut self, offset: AbsoluteOffset) {
         self.selecting = true;
-        self.prev_selection = self.selection();
-        self.selection = Some(offset..=offset);
+        self.prev_selection = self.selected_window();
+        self.selection = Some((offset, offset));
     }
 
     /// Ends the current selection.
     pub fn handle_mouse_release(&mut self) {
         if self.selecting {
             self.selecting = false;
-            if let Some(selection) = &self.selection
-                && selection.start() == selection.end()
-                && self.selection == self.prev_selection
+            if let Some((anchor, cursor)) = self.selection
+                && anchor == cursor
+                && self.selected_window() == self.prev_selection
             {
                 self.selection = None;
             }
@@ -54,30 +52,37 @@ impl SelectionState {
         shift_pressed: bool,
     ) {
         if self.selecting {
-            self.selection = Some(*self.selection.as_ref().unwrap().start()..=offset);
+            self.extend_selection(offset);
         } else if clicked {
-            if shift_pressed {
-                self.selection = Some(*self.selection.as_ref().unwrap().start()..=offset);
+            if shift_pressed && self.selection.is_some() {
+                self.extend_selection(offset);
             } else {
                 self.start_selection(offset);
             }
         }
     }
 
-    /// Returns the current selection.
-    pub fn selection(&self) -> Option<RangeInclusive<AbsoluteOffset>> {
-        self.selection.as_ref().map(|selection| {
-            if selection.start() <= selection.end() {
-                selection.clone()
-            } else {
-                *selection.end()..=*selection.start()
-            }
-        })
+    /// Moves the cursor of the current selection to the given offset.
+    fn extend_selection(&mut self, offset: AbsoluteOffset) {
+        if let Some((_, cursor)) = &mut self.selection {
+            *cursor = offset;
+        }
+    }
+
+    /// Selects the given window.
+    ///
+    /// Empty windows clear the selection.
+    pub fn select(&mut self, window: Window) {
+        self.selecting = false;
+        self.selection = window
+            .range_inclusive()
+            .map(|range| (*range.start(), *range.end()));
     }
 
     /// Returns the selected window.
     pub fn selected_window(&self) -> Option<Window> {
-        self.selection().map(Window::from)
+        self.selection
+            .map(|(anchor, cursor)| Window::from(anchor.min(cursor)..=anchor.max(cursor)))
     }
 }
 
@@ -1,12 +1,31 @@
 //! Handles the user settings.
-
-use egui::{Color32, FontId, TextStyle, Ui};
+//!
+//! Settings are loaded from a [TOML](https://toml.io) configuration file and can be saved back to
+//! it from the settings screen.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use egui::{Color32, FontId, TextStyle, ThemePreference, Ui};
 use hexbait_common::AbsoluteOffset;
+use hexbait_core::parse::is_hexpat;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gui::color::{ALIGNMENT_MARKER_COLORS, BYTE_COLORS, ColorMap, LerpStrength},
+    pipe::ExternalTool,
+};
 
-use crate::gui::color::{ALIGNMENT_MARKER_COLORS, BYTE_COLORS, ColorMap, LerpStrength};
+/// The numbers of bytes per row that the hex view supports.
+pub const BYTES_PER_ROW_CHOICES: [u64; 3] = [8, 16, 32];
 
 /// Determine what to show in the main screen.
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ViewKind {
     /// Determine based on the size of the selected window.
     #[default]
@@ -28,7 +47,113 @@ impl ViewKind {
     }
 }
 
+/// The color theme of the GUI.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Follow the theme of the system.
+    #[default]
+    System,
+    /// Use a dark theme.
+    Dark,
+    /// Use a light theme.
+    Light,
+}
+
+impl Theme {
+    /// Returns this theme as a displayable string.
+    pub fn display_str(&self) -> &str {
+        match self {
+            Theme::System => "system",
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+}
+
+impl From<Theme> for ThemePreference {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::System => ThemePreference::System,
+            Theme::Dark => ThemePreference::Dark,
+            Theme::Light => ThemePreference::Light,
+        }
+    }
+}
+
+/// An error that occurred while loading or saving the settings.
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The configuration file could not be accessed.
+    Io(io::Error),
+    /// The configuration file is not valid.
+    Parse(toml::de::Error),
+    /// The settings could not be written.
+    Serialize(toml::ser::Error),
+    /// There is no configuration file to save the settings to.
+    NoConfigPath,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "{err}"),
+            SettingsError::Parse(err) => write!(f, "invalid configuration file: {err}"),
+            SettingsError::Serialize(err) => write!(f, "{err}"),
+            SettingsError::NoConfigPath => write!(f, "no configuration file location is known"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(err: io::Error) -> Self {
+        SettingsError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(err: toml::de::Error) -> Self {
+        SettingsError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for SettingsError {
+    fn from(err: toml::ser::Error) -> Self {
+        SettingsError::Serialize(err)
+    }
+}
+
+/// Returns the default location of the configuration file.
+///
+/// This is `hexbait/config.toml` in the configuration directory of the platform.
+pub fn default_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("hexbait").join("config.toml"))
+}
+
+/// Returns the directory where the user's format descriptions are imported from.
+///
+/// This is `hexbait/formats` in the configuration directory of the platform.
+pub fn default_format_dir() -> Option<PathBuf> {
+    Some(config_dir()?.join("hexbait").join("formats"))
+}
+
+/// Returns the configuration directory of the platform.
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))
+    }
+}
+
 /// The settings of the GUI.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     /// The scale of the GUI.
     ///
@@ -44,6 +169,19 @@ pub struct Settings {
     view_kind: ViewKind,
     /// How much to use gamma smoothing in the statistics display.
     statistics_gamma_factor: f64,
+    /// The number of bytes shown in a row of the hex view.
+    bytes_per_row: u64,
+    /// The color theme.
+    theme: Theme,
+    /// Directories from which all format descriptions are loaded as custom parsers.
+    parser_directories: Vec<PathBuf>,
+    /// The external commands that selections can be piped through.
+    external_tools: Vec<ExternalTool>,
+    /// The number of seconds between autosaves of the session, or zero to disable autosaving.
+    autosave_interval_secs: u64,
+    /// The configuration file that the settings are saved to.
+    #[serde(skip)]
+    config_path: Option<PathBuf>,
 }
 
 impl Settings {
@@ -56,11 +194,70 @@ impl Settings {
             fine_grained_scrollbars: true,
             view_kind: ViewKind::Auto,
             statistics_gamma_factor: 1.0,
+            bytes_per_row: 16,
+            theme: Theme::System,
+            parser_directories: Vec::new(),
+            external_tools: ExternalTool::defaults(),
+            autosave_interval_secs: 60,
+            config_path: None,
+        }
+    }
+
+    /// Loads the settings from the configuration file at the given path.
+    ///
+    /// If the file does not exist, the default settings are used.
+    /// Settings missing from the file keep their default values.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Settings, SettingsError> {
+        let path = path.into();
+        let mut settings = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Settings::new(),
+            Err(err) => return Err(err.into()),
+        };
+        settings.sanitize();
+        settings.config_path = Some(path);
+
+        Ok(settings)
+    }
+
+    /// Saves the settings to the configuration file they were loaded from.
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let path = self
+            .config_path
+            .as_deref()
+            .ok_or(SettingsError::NoConfigPath)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// The configuration file that the settings are saved to.
+    pub fn config_path(&self) -> Option<&Path> {
+        self.config_path.as_deref()
+    }
+
+    /// Replaces values that are out of range with the closest valid value.
+    fn sanitize(&mut self) {
+        if !self.scale.is_finite() {
+            self.scale = Settings::new().scale;
+        }
+        self.scale = self.scale.clamp(10.0, 50.0);
+        if !self.statistics_gamma_factor.is_finite() {
+            self.statistics_gamma_factor = Settings::new().statistics_gamma_factor;
         }
+        self.statistics_gamma_factor = self.statistics_gamma_factor.clamp(0.0, 1.0);
+        self.set_bytes_per_row(self.bytes_per_row);
     }
 
     /// Applies the current settings to the [`Ui`].
     pub fn apply_settings_to_ui(&self, ui: &mut Ui) {
+        if ui.ctx().options(|options| options.theme_preference) != self.theme.into() {
+            ui.ctx().set_theme(self.theme);
+        }
+
         let text_styles = &mut ui.style_mut().text_styles;
 
         text_styles.insert(TextStyle::Small, FontId::proportional(self.scale * 0.65));
@@ -96,6 +293,91 @@ impl Settings {
         &mut self.scale
     }
 
+    /// Sets the scale, clamping it to the supported range.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+        self.sanitize();
+    }
+
+    /// The number of bytes shown in a row of the hex view.
+    pub fn bytes_per_row(&self) -> u64 {
+        self.bytes_per_row
+    }
+
+    /// Sets the number of bytes shown in a row of the hex view.
+    ///
+    /// Values that are not supported are rounded to the closest supported value.
+    pub fn set_bytes_per_row(&mut self, bytes_per_row: u64) {
+        self.bytes_per_row = BYTES_PER_ROW_CHOICES
+            .into_iter()
+            .min_by_key(|choice| choice.abs_diff(bytes_per_row))
+            .expect("there are choices");
+    }
+
+    /// The color theme.
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Mutable access to the color theme.
+    pub fn theme_mut(&mut self) -> &mut Theme {
+        &mut self.theme
+    }
+
+    /// The directories from which all format descriptions are loaded as custom parsers.
+    pub fn parser_directories(&self) -> &[PathBuf] {
+        &self.parser_directories
+    }
+
+    /// Mutable access to the directories from which format descriptions are loaded.
+    pub fn parser_directories_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.parser_directories
+    }
+
+    /// The external commands that selections can be piped through.
+    pub fn external_tools(&self) -> &[ExternalTool] {
+        &self.external_tools
+    }
+
+    /// Mutable access to the external commands that selections can be piped through.
+    pub fn external_tools_mut(&mut self) -> &mut Vec<ExternalTool> {
+        &mut self.external_tools
+    }
+
+    /// The time between autosaves of the session, if autosaving is enabled.
+    pub fn autosave_interval(&self) -> Option<Duration> {
+        (self.autosave_interval_secs != 0).then(|| Duration::from_secs(self.autosave_interval_secs))
+    }
+
+    /// Mutable access to the number of seconds between autosaves, where zero disables them.
+    pub fn autosave_interval_secs_mut(&mut self) -> &mut u64 {
+        &mut self.autosave_interval_secs
+    }
+
+    /// Returns the paths of all format descriptions and ImHex patterns in the parser directories.
+    ///
+    /// Directories that cannot be read are skipped.
+    pub fn parser_directory_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for dir in &self.parser_directories {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            let start = files.len();
+            files.extend(
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| {
+                        path.is_file()
+                            && (path.extension() == Some("hbl".as_ref()) || is_hexpat(path))
+                    }),
+            );
+            files[start..].sort();
+        }
+
+        files
+    }
+
     /// The currently selected color map.
     pub fn color_map(&self) -> ColorMap {
         self.color_map
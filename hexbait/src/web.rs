@@ -0,0 +1,182 @@
+//! Implements opening files in the browser.
+//!
+//! Files are chosen with a file picker or dropped onto the page.
+//! The last opened file is kept in IndexedDB, so that it is restored when the page is reloaded.
+
+use std::{cell::RefCell, rc::Rc};
+
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::{JsCast as _, JsValue, closure::Closure};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{HtmlInputElement, IdbDatabase, IdbRequest, IdbTransactionMode};
+
+/// The name of the IndexedDB database used by hexbait.
+const DATABASE_NAME: &str = "hexbait";
+
+/// The name of the object store in which the last opened file is cached.
+const STORE_NAME: &str = "last_file";
+
+/// The key under which the name of the cached file is stored.
+const NAME_KEY: &str = "name";
+
+/// The key under which the content of the cached file is stored.
+const CONTENT_KEY: &str = "content";
+
+/// A file that was opened in the browser.
+pub struct LoadedFile {
+    /// The name of the file.
+    pub name: String,
+    /// The content of the file.
+    pub content: Vec<u8>,
+}
+
+/// Loads files in the background and hands them to the application once they are available.
+pub struct FileLoader {
+    /// The file that was most recently loaded, but not yet taken by the application.
+    loaded: Rc<RefCell<Option<LoadedFile>>>,
+    /// The context used to request a repaint once a file is loaded.
+    ctx: egui::Context,
+}
+
+impl FileLoader {
+    /// Creates a new file loader.
+    pub fn new(ctx: egui::Context) -> FileLoader {
+        FileLoader {
+            loaded: Rc::new(RefCell::new(None)),
+            ctx,
+        }
+    }
+
+    /// Takes the most recently loaded file, if any.
+    pub fn take(&self) -> Option<LoadedFile> {
+        self.loaded.borrow_mut().take()
+    }
+
+    /// Opens a file picker and loads the chosen file.
+    pub fn pick_file(&self) {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        let Ok(picker) = document.create_element("input").and_then(|element| {
+            element
+                .dyn_into::<HtmlInputElement>()
+                .map_err(JsValue::from)
+        }) else {
+            return;
+        };
+        picker.set_type("file");
+
+        let loaded = Rc::clone(&self.loaded);
+        let ctx = self.ctx.clone();
+        let picked = picker.clone();
+        let on_change = Closure::once_into_js(move || {
+            let Some(file) = picked.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+
+            spawn_local(async move {
+                if let Ok(buf) = JsFuture::from(file.array_buffer()).await {
+                    *loaded.borrow_mut() = Some(LoadedFile {
+                        name: file.name(),
+                        content: Uint8Array::new(&buf).to_vec(),
+                    });
+                    ctx.request_repaint();
+                }
+            });
+        });
+        picker.set_onchange(Some(on_change.unchecked_ref()));
+        picker.click();
+    }
+
+    /// Restores the last opened file from the cache, if there is one.
+    pub fn restore_cached(&self) {
+        let loaded = Rc::clone(&self.loaded);
+        let ctx = self.ctx.clone();
+
+        spawn_local(async move {
+            if let Ok(Some(file)) = load_cached_file().await {
+                // a file that was opened in the meantime takes precedence
+                loaded.borrow_mut().get_or_insert(file);
+                ctx.request_repaint();
+            }
+        });
+    }
+}
+
+/// Stores the given file in the cache, replacing the previously cached file.
+pub fn cache_file(name: &str, content: &[u8]) {
+    let name = JsValue::from_str(name);
+    let content = Uint8Array::from(content);
+
+    spawn_local(async move {
+        let result: Result<(), JsValue> = async {
+            let db = open_database().await?;
+            let store = db
+                .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+                .object_store(STORE_NAME)?;
+            store.put_with_key(&name, &JsValue::from_str(NAME_KEY))?;
+            wait_for(&store.put_with_key(&content, &JsValue::from_str(CONTENT_KEY))?).await?;
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            web_sys::console::warn_2(&"failed to cache the opened file:".into(), &err);
+        }
+    });
+}
+
+/// Loads the cached file, if there is one.
+async fn load_cached_file() -> Result<Option<LoadedFile>, JsValue> {
+    let db = open_database().await?;
+    let store = db
+        .transaction_with_str(STORE_NAME)?
+        .object_store(STORE_NAME)?;
+    let name = wait_for(&store.get(&JsValue::from_str(NAME_KEY))?).await?;
+    let content = wait_for(&store.get(&JsValue::from_str(CONTENT_KEY))?).await?;
+
+    let (Some(name), Some(content)) = (name.as_string(), content.dyn_ref::<Uint8Array>()) else {
+        return Ok(None);
+    };
+
+    Ok(Some(LoadedFile {
+        name,
+        content: content.to_vec(),
+    }))
+}
+
+/// Opens the database of hexbait, creating it if it does not exist yet.
+async fn open_database() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or("there is no window")?
+        .indexed_db()?
+        .ok_or("IndexedDB is not available")?;
+    let request = factory.open_with_u32(DATABASE_NAME, 1)?;
+
+    let upgraded = request.clone();
+    let on_upgrade_needed = Closure::once(move || {
+        if let Ok(db) = upgraded.result() {
+            let _ = db
+                .unchecked_into::<IdbDatabase>()
+                .create_object_store(STORE_NAME);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+
+    let db = wait_for(&request).await?;
+    drop(on_upgrade_needed);
+
+    Ok(db.unchecked_into())
+}
+
+/// Waits until the given request has finished and returns its result.
+async fn wait_for(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(promise).await?;
+
+    request.result()
+}